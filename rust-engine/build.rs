@@ -0,0 +1,37 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // A vendored protoc keeps the build free of system packages
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/engine.proto"], &["proto"])?;
+    
+    // Commit and time of the build for /health; GIT_COMMIT covers builds without a checkout, like docker's
+    let commit = std::env::var("GIT_COMMIT").ok().filter(|commit| !commit.is_empty()).or_else(|| git(&["rev-parse", "--short=12", "HEAD"]));
+    println!("cargo:rustc-env=BUILD_GIT_COMMIT={}", commit.unwrap_or_default());
+    let built_at = match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch,
+        Err(_) => SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs().to_string(),
+    };
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", built_at);
+    
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    // A new commit moves the branch ref, a checkout moves HEAD
+    let refs = ["HEAD".to_string()].into_iter().chain(git(&["symbolic-ref", "-q", "HEAD"]));
+    for name in refs {
+        if let Some(path) = git(&["rev-parse", "--git-path", &name]).filter(|path| std::path::Path::new(path).exists()) {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    Ok(())
+}
+
+/// Trimmed stdout of a successful git command
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    (output.status.success() && !stdout.trim().is_empty()).then(|| stdout.trim().to_string())
+}
@@ -0,0 +1,249 @@
+use crate::config::AuditConfig;
+use crate::types::{AuditEvent, AuditEventKind, ConfigChangeEvent, ExecutionJob, ResultSummary};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// How long the HTTP sink may take to accept one event
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Events waiting for the sink task; unset when no sink is configured
+static SINK: OnceLock<mpsc::UnboundedSender<Record>> = OnceLock::new();
+
+/// One line of the audit log
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Record {
+    Job(AuditEvent),
+    Config(ConfigChangeEvent),
+}
+
+impl Record {
+    /// What the record is about, for sink error messages
+    fn subject(&self) -> &str {
+        match self {
+            Record::Job(event) => &event.job_id,
+            Record::Config(_) => "a settings change",
+        }
+    }
+}
+
+/// Start the sink task for the configured file and/or URL; without either, events only stay on their jobs
+pub async fn install(config: &AuditConfig) -> Result<()> {
+    let url = config.http_url.clone();
+    if config.log_path.is_none() && url.is_none() {
+        return Ok(());
+    }
+    
+    let file = match &config.log_path {
+        Some(path) => {
+            let file = AuditFile::open(path.clone(), config.max_bytes, config.max_files).await
+                .with_context(|| format!("opening audit log {}", path.display()))?;
+            info!("📜 Writing audit events to {}", path.display());
+            Some(file)
+        }
+        None => None,
+    };
+    if let Some(url) = &url {
+        info!("📜 Posting audit events to {}", url);
+    }
+    
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(deliver(receiver, file, url));
+    let _ = SINK.set(sender);
+    Ok(())
+}
+
+/// Event describing the job as it is now
+pub fn event(job: &ExecutionJob, event: AuditEventKind) -> AuditEvent {
+    AuditEvent {
+        event,
+        job_id: job.id.clone(),
+        at: Utc::now(),
+        client: job.client.clone(),
+        language: job.request.language.clone(),
+        request_id: job.request_id.clone(),
+        attempt: job.attempts,
+        runner: job.runner.clone(),
+        result: job.result.as_deref().map(|result| ResultSummary {
+            status: result.status.clone(),
+            exit_code: result.exit_code,
+            time: result.time,
+            memory: result.memory,
+        }),
+    }
+}
+
+/// Hand an event to the configured sinks
+pub fn emit(event: &AuditEvent) {
+    if let Some(sink) = SINK.get() {
+        let _ = sink.send(Record::Job(event.clone()));
+    }
+}
+
+/// Hand a runtime settings change to the configured sinks
+pub fn emit_config_change(event: &ConfigChangeEvent) {
+    if let Some(sink) = SINK.get() {
+        let _ = sink.send(Record::Config(event.clone()));
+    }
+}
+
+/// Append a transition to the job's trail and emit it
+pub fn record(job: &mut ExecutionJob, kind: AuditEventKind) {
+    let event = event(job, kind);
+    emit(&event);
+    job.events.push(event);
+}
+
+/// Write events in the order they were emitted; sink failures are logged and the event dropped
+async fn deliver(mut events: mpsc::UnboundedReceiver<Record>, mut file: Option<AuditFile>, url: Option<String>) {
+    let client = reqwest::Client::new();
+    
+    while let Some(event) = events.recv().await {
+        if let Some(file) = &mut file {
+            let mut line = serde_json::to_vec(&event).unwrap_or_default();
+            line.push(b'\n');
+            if let Err(err) = file.append(&line).await {
+                warn!("Failed to write audit event for {}: {}", event.subject(), err);
+            }
+        }
+        
+        if let Some(url) = &url {
+            let posted = client.post(url)
+                .json(&event)
+                .timeout(HTTP_TIMEOUT)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(err) = posted {
+                warn!("Failed to post audit event for {}: {}", event.subject(), err);
+            }
+        }
+    }
+}
+
+/// Append-only JSONL file, renamed to `.1`, `.2`, ... once it would grow past `max_bytes`
+struct AuditFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl AuditFile {
+    async fn open(path: PathBuf, max_bytes: u64, max_files: usize) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path).await?;
+        let size = file.metadata().await?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_bytes,
+            max_files: max_files.max(1),
+        })
+    }
+    
+    async fn append(&mut self, line: &[u8]) -> Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate().await?;
+        }
+        self.file.write_all(line).await?;
+        self.file.flush().await?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+    
+    /// Shift rotated files up by one, dropping the oldest, and start a fresh live file
+    async fn rotate(&mut self) -> Result<()> {
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated(index);
+            if fs::try_exists(&from).await? {
+                fs::rename(&from, self.rotated(index + 1)).await?;
+            }
+        }
+        fs::rename(&self.path, self.rotated(1)).await?;
+        
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path).await?;
+        self.size = 0;
+        info!("📜 Rotated audit log {}", self.path.display());
+        Ok(())
+    }
+    
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn job(id: &str) -> ExecutionJob {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "request": { "id": id, "language": "python", "source_code": "print(1)" },
+            "status": "queued",
+            "created_at": "2030-01-01T00:00:00Z",
+            "started_at": null,
+            "finished_at": null,
+            "result": null,
+            "client": "key:0123456789ab",
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn events_are_written_as_json_lines_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let file = AuditFile::open(path.clone(), 1 << 20, 3).await.unwrap();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        
+        let mut job = job("job-1");
+        for kind in [AuditEventKind::Submitted, AuditEventKind::Started, AuditEventKind::Completed] {
+            record(&mut job, kind);
+            sender.send(Record::Job(job.events.last().unwrap().clone())).unwrap();
+        }
+        drop(sender);
+        deliver(receiver, Some(file), None).await;
+        
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let kinds: Vec<&str> = lines.iter().map(|line| line["event"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["submitted", "started", "completed"]);
+        assert_eq!((&lines[0]["job_id"], &lines[0]["client"], &lines[0]["language"]), (&"job-1".into(), &"key:0123456789ab".into(), &"python".into()));
+    }
+    
+    #[tokio::test]
+    async fn full_logs_rotate_and_drop_the_oldest_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let mut file = AuditFile::open(path.clone(), 25, 2).await.unwrap();
+        for n in 0..5 {
+            file.append(format!("{{\"line\": {}}}\n", n).as_bytes()).await.unwrap();
+        }
+        
+        // Two 12-byte lines fit in 25 bytes, so the live file holds the last line and each rotation two
+        let read = |suffix: &str| std::fs::read_to_string(format!("{}{}", path.display(), suffix)).unwrap();
+        assert_eq!(read(""), "{\"line\": 4}\n");
+        assert_eq!(read(".1"), "{\"line\": 2}\n{\"line\": 3}\n");
+        assert_eq!(read(".2"), "{\"line\": 0}\n{\"line\": 1}\n");
+        
+        file.append(b"{\"line\": 5}\n").await.unwrap();
+        file.append(b"{\"line\": 6}\n").await.unwrap();
+        assert_eq!(read(".2"), "{\"line\": 2}\n{\"line\": 3}\n", "the oldest file is dropped");
+        assert!(!std::path::Path::new(&format!("{}.3", path.display())).exists());
+    }
+}
+
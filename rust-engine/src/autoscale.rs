@@ -0,0 +1,164 @@
+use crate::config::AutoscaleConfig;
+use std::time::{Duration, Instant};
+
+/// How often the autoscaler looks at the queue
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the autoscaler sees on each tick
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub workers: usize,
+    pub active: usize,
+    pub queued: usize,
+    pub estimated_wait_secs: f64,
+}
+
+/// Decides when the worker pool grows or shrinks
+///
+/// A backlog has to last `scale_up_after_secs` and an idle pool `scale_down_after_secs` before
+/// anything changes, and each change restarts both windows, so a queue hovering around the
+/// threshold does not flap the pool back and forth.
+pub struct ScalingPolicy {
+    config: AutoscaleConfig,
+    backlog_since: Option<Instant>,
+    idle_since: Option<Instant>,
+}
+
+impl ScalingPolicy {
+    pub fn new(config: AutoscaleConfig) -> Self {
+        Self {
+            config,
+            backlog_since: None,
+            idle_since: None,
+        }
+    }
+    
+    /// Worker count to switch to, if it should change; `bounds` are the current (min, max)
+    pub fn decide(&mut self, sample: Sample, (min, max): (usize, usize), now: Instant) -> Option<usize> {
+        // Bounds changed through the admin API apply straight away
+        let clamped = sample.workers.clamp(min, max);
+        if clamped != sample.workers {
+            self.reset();
+            return Some(clamped);
+        }
+        
+        let config = &self.config;
+        let backlog = sample.queued >= config.scale_up_queue_depth
+            || (sample.queued > 0 && config.scale_up_wait_secs > 0.0 && sample.estimated_wait_secs >= config.scale_up_wait_secs);
+        let idle = sample.queued == 0 && sample.active < sample.workers;
+        
+        if backlog && sample.workers < max {
+            self.idle_since = None;
+            let since = *self.backlog_since.get_or_insert(now);
+            if now.duration_since(since) >= Duration::from_secs(config.scale_up_after_secs) {
+                let target = (sample.workers + config.step).min(max);
+                self.reset();
+                return Some(target);
+            }
+        } else if idle && sample.workers > min {
+            self.backlog_since = None;
+            let since = *self.idle_since.get_or_insert(now);
+            if now.duration_since(since) >= Duration::from_secs(config.scale_down_after_secs) {
+                // Only idle workers are retired; busy ones keep their job
+                let target = sample.workers.saturating_sub(config.step).max(min).max(sample.active);
+                self.reset();
+                return Some(target);
+            }
+        } else {
+            self.reset();
+        }
+        None
+    }
+    
+    /// Start both windows over, e.g. after a change or while the pool cannot drain the queue
+    pub fn reset(&mut self) {
+        self.backlog_since = None;
+        self.idle_since = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn policy() -> ScalingPolicy {
+        ScalingPolicy::new(AutoscaleConfig {
+            enabled: true,
+            min_workers: 1,
+            max_workers: 4,
+            scale_up_queue_depth: 5,
+            scale_up_wait_secs: 0.0,
+            scale_up_after_secs: 10,
+            scale_down_after_secs: 60,
+            step: 2,
+        })
+    }
+    
+    fn sample(workers: usize, active: usize, queued: usize) -> Sample {
+        Sample { workers, active, queued, estimated_wait_secs: 0.0 }
+    }
+    
+    fn secs(start: Instant, secs: u64) -> Instant {
+        start + Duration::from_secs(secs)
+    }
+    
+    #[test]
+    fn a_lasting_backlog_adds_workers_up_to_the_maximum() {
+        let mut policy = policy();
+        let start = Instant::now();
+        assert_eq!(policy.decide(sample(1, 1, 8), (1, 4), start), None);
+        assert_eq!(policy.decide(sample(1, 1, 8), (1, 4), secs(start, 9)), None);
+        assert_eq!(policy.decide(sample(1, 1, 8), (1, 4), secs(start, 10)), Some(3));
+        
+        // The window starts over after a change, and the step stops at the maximum
+        assert_eq!(policy.decide(sample(3, 3, 8), (1, 4), secs(start, 11)), None);
+        assert_eq!(policy.decide(sample(3, 3, 8), (1, 4), secs(start, 21)), Some(4));
+        assert_eq!(policy.decide(sample(4, 4, 8), (1, 4), secs(start, 60)), None);
+    }
+    
+    #[test]
+    fn a_backlog_that_clears_in_between_does_not_scale() {
+        let mut policy = policy();
+        let start = Instant::now();
+        assert_eq!(policy.decide(sample(1, 1, 8), (1, 4), start), None);
+        assert_eq!(policy.decide(sample(1, 1, 2), (1, 4), secs(start, 5)), None);
+        assert_eq!(policy.decide(sample(1, 1, 8), (1, 4), secs(start, 10)), None);
+        assert_eq!(policy.decide(sample(1, 1, 8), (1, 4), secs(start, 19)), None);
+        assert_eq!(policy.decide(sample(1, 1, 8), (1, 4), secs(start, 20)), Some(3));
+    }
+    
+    #[test]
+    fn long_waits_count_as_a_backlog() {
+        let mut policy = ScalingPolicy::new(AutoscaleConfig { scale_up_wait_secs: 30.0, ..policy().config });
+        let start = Instant::now();
+        let waiting = Sample { estimated_wait_secs: 45.0, ..sample(1, 1, 2) };
+        assert_eq!(policy.decide(waiting, (1, 4), start), None);
+        assert_eq!(policy.decide(waiting, (1, 4), secs(start, 10)), Some(3));
+    }
+    
+    #[test]
+    fn idle_workers_are_retired_but_busy_ones_are_kept() {
+        let mut policy = policy();
+        let start = Instant::now();
+        assert_eq!(policy.decide(sample(4, 1, 0), (1, 4), start), None);
+        assert_eq!(policy.decide(sample(4, 1, 0), (1, 4), secs(start, 59)), None);
+        assert_eq!(policy.decide(sample(4, 1, 0), (1, 4), secs(start, 60)), Some(2));
+        
+        // Three still running: only one of the four goes
+        let mut policy = self::policy();
+        assert_eq!(policy.decide(sample(4, 3, 0), (1, 4), start), None);
+        assert_eq!(policy.decide(sample(4, 3, 0), (1, 4), secs(start, 60)), Some(3));
+        
+        // Never below the minimum
+        assert_eq!(policy.decide(sample(2, 0, 0), (2, 4), secs(start, 61)), None);
+        assert_eq!(policy.decide(sample(2, 0, 0), (2, 4), secs(start, 200)), None);
+    }
+    
+    #[test]
+    fn changed_bounds_apply_at_once() {
+        let mut policy = policy();
+        let start = Instant::now();
+        assert_eq!(policy.decide(sample(4, 4, 0), (1, 2), start), Some(2));
+        assert_eq!(policy.decide(sample(1, 0, 0), (3, 4), start), Some(3));
+    }
+}
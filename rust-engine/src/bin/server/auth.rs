@@ -0,0 +1,30 @@
+use axum::http::HeaderMap;
+
+/// Header carrying the caller's API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Extract the API key sent with a request, if any
+pub fn api_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|key| !key.is_empty())
+}
+
+/// Check whether the request carries the configured admin key
+/// Admin features are disabled entirely when no admin key is configured
+pub fn is_admin(headers: &HeaderMap, admin_key: Option<&str>) -> bool {
+    match (admin_key, api_key(headers)) {
+        (Some(expected), Some(given)) => constant_time_eq(expected.as_bytes(), given.as_bytes()),
+        _ => false,
+    }
+}
+
+/// Compare two secrets without short-circuiting on the first mismatch
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
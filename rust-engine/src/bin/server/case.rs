@@ -0,0 +1,120 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::{Map, Value};
+
+/// Fields whose keys are chosen by clients or operators (languages, profiles, env vars) and are
+/// kept as they are; the objects under them are still renamed
+const MAP_FIELDS: &[&str] = &[
+    "env", "metadata", "runtimes", "warmup", "by_language", "priority_caps", "profiles", "language_aliases", "pool_sizes",
+    "languages", "secrets",
+];
+
+/// Serve JSON responses with camelCase field names when the query asks for `case=camel`.
+///
+/// Handlers keep producing the engine types as they serialize; this renames the fields of the
+/// finished body, so every endpoint of the versioned API answers in the same case. Requests
+/// need nothing of the sort, since the request types accept both spellings.
+pub async fn camel_case_responses(request: Request, next: Next) -> Response {
+    let camel = request.uri().query().is_some_and(|query| {
+        query.split('&').any(|pair| pair.eq_ignore_ascii_case("case=camel"))
+    });
+    // GET /profiles answers with a map of profile names
+    let top_level_map = request.uri().path().ends_with("/profiles");
+    let response = next.run(request).await;
+    let is_json = response.headers().get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !camel || !is_json {
+        return response;
+    }
+    
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Ok(value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    
+    let renamed = if top_level_map { rename_values(value) } else { rename(value) };
+    let body = serde_json::to_vec(&renamed).expect("a JSON value serializes");
+    parts.headers.insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Rename the fields of every object in `value`
+fn rename(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields.into_iter()
+                .map(|(key, value)| {
+                    let value = if MAP_FIELDS.contains(&key.as_str()) { rename_values(value) } else { rename(value) };
+                    (camel_case(&key), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(rename).collect()),
+        other => other,
+    }
+}
+
+/// Keep the keys of a map, renaming the objects it holds
+fn rename_values(value: Value) -> Value {
+    match value {
+        Value::Object(entries) => Value::Object(
+            entries.into_iter().map(|(key, value)| (key, rename(value))).collect::<Map<_, _>>(),
+        ),
+        other => rename(other),
+    }
+}
+
+/// `source_code` as `sourceCode`; anything but a lowercase snake_case name is left alone
+fn camel_case(key: &str) -> String {
+    if !key.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_') {
+        return key.to_string();
+    }
+    let mut words = key.split('_').filter(|word| !word.is_empty());
+    let mut camel = words.next().unwrap_or_default().to_string();
+    for word in words {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel.push(first.to_ascii_uppercase());
+            camel.push_str(chars.as_str());
+        }
+    }
+    camel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn only_snake_case_names_are_renamed() {
+        assert_eq!(camel_case("source_code"), "sourceCode");
+        assert_eq!(camel_case("max_processes_and_or_threads"), "maxProcessesAndOrThreads");
+        assert_eq!(camel_case("stdout"), "stdout");
+        assert_eq!(camel_case("LOG_LEVEL"), "LOG_LEVEL");
+    }
+    
+    #[test]
+    fn map_keys_are_kept_while_their_objects_are_renamed() {
+        let renamed = rename(json!({
+            "exit_code": 0,
+            "env": {"LOG_LEVEL": "debug", "my_var": "x"},
+            "by_language": {"c_sharp": {"total_executions": 2}},
+            "test_cases": [{"expected_output": "1"}],
+        }));
+        assert_eq!(renamed, json!({
+            "exitCode": 0,
+            "env": {"LOG_LEVEL": "debug", "my_var": "x"},
+            "byLanguage": {"c_sharp": {"totalExecutions": 2}},
+            "testCases": [{"expectedOutput": "1"}],
+        }));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::config::EngineConfig;
+use crate::executor::CodeExecutor;
+use crate::types::{ExecutionMetrics, ExecutionResult, ExecutionState, Timeline};
+use crate::validation;
+use crate::warmup;
+use crate::workdir::WorkdirStore;
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// LabForCode execution engine
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the HTTP (and optional gRPC) server; the default
+    Serve,
+    /// Execute one file in the sandbox without the server or queue, then exit with its exit code
+    Run(RunArgs),
+    /// Build and run a hello-world per language on the configured backend, then exit nonzero if any failed
+    Check(CheckArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RunArgs {
+    /// Language key, as listed by /languages
+    #[arg(short, long)]
+    pub language: String,
+    /// Source file to execute
+    #[arg(short, long)]
+    pub file: PathBuf,
+    /// File fed to the program's stdin
+    #[arg(short, long)]
+    pub stdin: Option<PathBuf>,
+    /// CPU time limit in seconds
+    #[arg(short, long)]
+    pub time_limit: Option<f64>,
+    /// Memory limit in megabytes
+    #[arg(short, long)]
+    pub memory: Option<u64>,
+    /// Limits profile from the configuration; the flags above still win
+    #[arg(short, long)]
+    pub profile: Option<String>,
+    /// How to print the result
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct CheckArgs {
+    /// Language keys to check, comma separated
+    #[arg(short, long, value_delimiter = ',', required_unless_present = "all")]
+    pub languages: Vec<String>,
+    /// Check every language
+    #[arg(long, conflicts_with = "languages")]
+    pub all: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Execute the file with the same validation and executor the server uses; returns the process exit code
+pub async fn run(args: RunArgs) -> Result<i32> {
+    let source_code = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("reading {}", args.file.display()))?;
+    let stdin = match &args.stdin {
+        Some(path) => Some(std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?),
+        None => None,
+    };
+    
+    let mut request = serde_json::from_value(serde_json::json!({
+        "id": Uuid::new_v4().to_string(),
+        "language": args.language,
+        "source_code": source_code,
+        "stdin": stdin,
+        "cpu_time_limit": args.time_limit,
+        "memory_limit": args.memory.map(|mb| mb * 1024 * 1024),
+        "profile": args.profile,
+    }))?;
+    
+    // Preserved directories would outlive the run, so the store lives in a scratch directory
+    let scratch = tempfile::tempdir()?;
+    let workdirs = WorkdirStore::new(scratch.path().join("preserved"), chrono::Duration::zero())?;
+    let config = EngineConfig::load()?;
+    let executor = CodeExecutor::new(workdirs, &config)?;
+    
+    let validator = &config.limits.ceilings;
+    let mut violations = validation::resolve_limit_units(&mut request);
+    violations.extend(validation::apply_profile(&mut request, &config.profiles));
+    let support = executor.support(&request);
+    validator.apply_ceilings(&mut request, support);
+    violations.extend(validator.validate(&request, support));
+    if !violations.is_empty() {
+        let fields: Vec<String> = violations.iter()
+            .map(|violation| format!("{}: {}", violation.field, violation.constraint))
+            .collect();
+        return Err(anyhow!("invalid request: {}", fields.join("; ")));
+    }
+    
+    // Nobody watches progress here; the executor ignores a closed channel
+    let (progress, _) = mpsc::unbounded_channel();
+    let mut timeline = Timeline::default();
+    let mut result = executor.execute(&request, progress, &mut timeline).await?;
+    result.timeline = timeline.entries().to_vec();
+    
+    match args.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&result)?),
+        OutputFormat::Text => print_text(&result),
+    }
+    
+    Ok(match result.status {
+        ExecutionState::Completed => result.exit_code.unwrap_or(0),
+        _ => result.exit_code.filter(|code| *code != 0).unwrap_or(1),
+    })
+}
+
+/// Run the smoke check on the executor the server would build; returns the process exit code
+pub async fn check(args: CheckArgs) -> Result<i32> {
+    let scratch = tempfile::tempdir()?;
+    let workdirs = WorkdirStore::new(scratch.path().join("preserved"), chrono::Duration::zero())?;
+    let config = EngineConfig::load()?;
+    let executor = CodeExecutor::new(workdirs, &config)?;
+    let languages = match args.all {
+        true => executor.language_keys(),
+        false => args.languages,
+    };
+    
+    println!("backend: {}", config.sandbox.backend);
+    println!("{:<12} {:<6} {:>9}", "language", "result", "time");
+    let outcomes = warmup::check(&executor, &languages).await;
+    for (language, outcome) in &outcomes {
+        let result = if outcome.ready { "pass" } else { "FAIL" };
+        let time = format!("{:.2}s", outcome.duration_ms as f64 / 1000.0);
+        match &outcome.error {
+            // Compiler output can run to many lines; the first says what went wrong
+            Some(error) => println!("{:<12} {:<6} {:>9}  {}", language, result, time, error.lines().next().unwrap_or_default()),
+            None => println!("{:<12} {:<6} {:>9}", language, result, time),
+        }
+    }
+    
+    let failed = outcomes.iter().filter(|(_, outcome)| !outcome.ready).count();
+    println!("{} of {} passed", outcomes.len() - failed, outcomes.len());
+    Ok(i32::from(failed > 0))
+}
+
+/// Human-readable summary followed by each non-empty output stream
+fn print_text(result: &ExecutionResult) {
+    println!("status:    {}", result.status.as_str());
+    if let Some(exit_code) = result.exit_code {
+        println!("exit code: {}", exit_code);
+    }
+    if let Some(signal) = &result.signal {
+        println!("signal:    {}", signal);
+    }
+    if let Some(description) = &result.exit_description {
+        println!("exit:      {}", description);
+    }
+    if let Some(time) = result.time {
+        println!("time:      {:.3}s", time);
+    }
+    if let Some(memory) = result.memory {
+        println!("memory:    {} KB", memory / 1024);
+    }
+    if let Some(ExecutionMetrics { user_time: Some(user), system_time: Some(system), .. }) = &result.metrics {
+        println!("cpu:       {:.3}s user, {:.3}s system", user, system);
+    }
+    if let Some(reason) = &result.failure_reason {
+        println!("reason:    {}", reason);
+    }
+    
+    let streams = [
+        ("compile output", &result.compile_output),
+        ("stdout", &result.stdout),
+        ("stderr", &result.stderr),
+    ];
+    for (name, text) in streams {
+        if let Some(text) = text.as_deref().filter(|text| !text.trim().is_empty()) {
+            println!("--- {} ---", name);
+            print!("{}", text);
+            if !text.ends_with('\n') {
+                println!();
+            }
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::decompression::RequestDecompressionLayer;
+use tracing::info;
+
+/// Compress responses of at least `min_bytes` with gzip, br or deflate as the client's Accept-Encoding allows.
+///
+/// Server-sent events are left alone so every event reaches the client as soon as it is written,
+/// as are images and gRPC-web, which gain nothing from it.
+pub fn response_layer(min_bytes: u16) -> CompressionLayer<impl Predicate> {
+    info!("🗜️ Compressing responses above {} bytes", min_bytes);
+    
+    let predicate = SizeAbove::new(min_bytes)
+        .and(NotForContentType::SSE)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES);
+    CompressionLayer::new().compress_when(predicate)
+}
+
+/// Inflate `Content-Encoding: gzip` request bodies before they are parsed; other encodings get 415.
+/// Only the gzip decoder is compiled in, and body size limits apply to the decompressed body
+pub fn request_layer() -> RequestDecompressionLayer {
+    RequestDecompressionLayer::new()
+}
@@ -0,0 +1,168 @@
+use crate::config::CorsConfig;
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
+use tracing::{info, warn};
+
+/// One entry of the allowed origins
+#[derive(Debug, Clone)]
+enum OriginPattern {
+    Any,                                        // *
+    Exact(String),                              // https://app.example.com
+    Subdomain { scheme: String, suffix: String }, // https://*.example.com
+}
+
+impl OriginPattern {
+    fn parse(spec: &str) -> Self {
+        let spec = spec.trim().trim_end_matches('/');
+        if spec == "*" {
+            return Self::Any;
+        }
+        match spec.split_once("://*.") {
+            Some((scheme, domain)) => Self::Subdomain {
+                scheme: format!("{}://", scheme.to_ascii_lowercase()),
+                suffix: format!(".{}", domain.to_ascii_lowercase()),
+            },
+            None => Self::Exact(spec.to_ascii_lowercase()),
+        }
+    }
+    
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Exact(allowed) => origin == allowed,
+            Self::Subdomain { scheme, suffix } => origin
+                .strip_prefix(scheme.as_str())
+                .and_then(|host| host.strip_suffix(suffix.as_str()))
+                .is_some_and(|subdomain| !subdomain.is_empty() && !subdomain.contains(['/', ':', '@'])),
+        }
+    }
+}
+
+/// Build the CORS policy from the server's CORS settings
+/// Without allowed origins every origin is allowed, which keeps local development working
+pub fn layer(config: &CorsConfig) -> CorsLayer {
+    let origins: Vec<OriginPattern> = config.allowed_origins
+        .iter()
+        .filter(|spec| !spec.trim().is_empty())
+        .map(|spec| OriginPattern::parse(spec))
+        .collect();
+    if origins.is_empty() {
+        warn!("🌐 CORS_ALLOWED_ORIGINS not set, allowing every origin");
+        return CorsLayer::permissive();
+    }
+    
+    // Methods were checked when the configuration was validated
+    let methods: Vec<Method> = config.allowed_methods
+        .iter()
+        .filter_map(|method| Method::from_bytes(method.trim().to_ascii_uppercase().as_bytes()).ok())
+        .collect();
+    let allow_credentials = config.allow_credentials;
+    let max_age = Duration::from_secs(config.max_age_secs);
+    
+    info!(
+        "🌐 CORS allows {} origin pattern(s), credentials {}",
+        origins.len(),
+        if allow_credentials { "on" } else { "off" },
+    );
+    
+    // Origins and headers are mirrored rather than wildcarded so credentials stay valid
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+            origin.to_str()
+                .map(|origin| origin.to_ascii_lowercase())
+                .is_ok_and(|origin| origins.iter().any(|pattern| pattern.matches(&origin)))
+        }))
+        .allow_methods(methods)
+        .allow_headers(AllowHeaders::mirror_request())
+        .allow_credentials(allow_credentials)
+        .expose_headers([HeaderName::from_static("x-request-id"), header::RETRY_AFTER])
+        .max_age(max_age)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{app_with, unlimited};
+    use axum::body::Body;
+    use axum::extract::ConnectInfo;
+    use axum::http::{Request, Response, StatusCode};
+    use labforcode_engine::EngineConfig;
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+    
+    #[test]
+    fn origins_match_exactly_or_by_subdomain() {
+        let exact = OriginPattern::parse("https://App.example.com/");
+        assert!(exact.matches("https://app.example.com"));
+        assert!(!exact.matches("http://app.example.com"));
+        assert!(!exact.matches("https://app.example.com.evil.test"));
+        
+        let subdomain = OriginPattern::parse("https://*.example.com");
+        assert!(subdomain.matches("https://app.example.com"));
+        assert!(subdomain.matches("https://a.b.example.com"));
+        assert!(!subdomain.matches("https://example.com"));
+        assert!(!subdomain.matches("https://evil.test/.example.com"));
+        assert!(!subdomain.matches("https://user@x.example.com"));
+        assert!(!subdomain.matches("http://app.example.com"));
+        
+        assert!(OriginPattern::parse("*").matches("http://localhost:3000"));
+    }
+    
+    /// Preflight for a POST from `origin`, asking to send a Content-Type header
+    async fn preflight(config: EngineConfig, origin: &str) -> Response<Body> {
+        let (app, _) = app_with(config, unlimited()).await;
+        let mut request = Request::options("/v1/execute")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        app.oneshot(request).await.unwrap()
+    }
+    
+    fn restricted() -> EngineConfig {
+        let mut config = EngineConfig::default();
+        config.server.cors = CorsConfig {
+            allowed_origins: vec!["https://app.example.com".to_string(), "https://*.example.org".to_string()],
+            allowed_methods: vec!["get".to_string(), "POST".to_string()],
+            allow_credentials: true,
+            max_age_secs: 600,
+        };
+        config
+    }
+    
+    fn header(response: &Response<Body>, name: header::HeaderName) -> Option<&str> {
+        response.headers().get(name).map(|value| value.to_str().unwrap())
+    }
+    
+    #[tokio::test]
+    async fn allowed_origins_pass_the_preflight() {
+        for origin in ["https://app.example.com", "https://docs.example.org"] {
+            let response = preflight(restricted(), origin).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(header(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN), Some(origin));
+            assert_eq!(header(&response, header::ACCESS_CONTROL_ALLOW_METHODS), Some("GET,POST"));
+            assert_eq!(header(&response, header::ACCESS_CONTROL_ALLOW_HEADERS), Some("content-type"));
+            assert_eq!(header(&response, header::ACCESS_CONTROL_ALLOW_CREDENTIALS), Some("true"));
+            assert_eq!(header(&response, header::ACCESS_CONTROL_MAX_AGE), Some("600"));
+        }
+    }
+    
+    #[tokio::test]
+    async fn other_origins_are_not_allowed() {
+        for origin in ["https://evil.test", "https://example.org", "http://app.example.com"] {
+            let response = preflight(restricted(), origin).await;
+            // Browsers refuse the response without an allowed origin, whatever else it carries
+            assert_eq!(header(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN), None, "{}", origin);
+        }
+    }
+    
+    #[tokio::test]
+    async fn no_configured_origins_allow_every_origin() {
+        let response = preflight(EngineConfig::default(), "http://localhost:5173").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header(&response, header::ACCESS_CONTROL_ALLOW_ORIGIN), Some("*"));
+    }
+}
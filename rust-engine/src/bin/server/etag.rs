@@ -0,0 +1,99 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+/// Tag successful GET responses with a weak ETag and answer 304 when If-None-Match still matches.
+///
+/// The tag hashes the whole response as the handler produced it, so anything that shows up in
+/// the body or headers (queue position, progress, a finished result) changes it. It is weak
+/// because the compression layer may re-encode the same representation. Other methods on the
+/// same route pass straight through.
+pub async fn conditional_get(request: Request, next: Next) -> Response {
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    
+    let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+    let response = next.run(request).await;
+    if !response.status().is_success() {
+        return response;
+    }
+    
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    
+    let etag = tag(parts.status, &parts.headers, &bytes);
+    if if_none_match.is_some_and(|value| matches(&value, &etag)) {
+        let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+        not_modified.headers_mut().insert(header::ETAG, etag);
+        if let Some(vary) = parts.headers.get(header::VARY) {
+            not_modified.headers_mut().insert(header::VARY, vary.clone());
+        }
+        return not_modified;
+    }
+    
+    parts.headers.insert(header::ETAG, etag);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn tag(status: StatusCode, headers: &HeaderMap, body: &[u8]) -> HeaderValue {
+    let mut hasher = Sha256::new();
+    hasher.update(status.as_str());
+    for (name, value) in headers {
+        hasher.update(name.as_str());
+        hasher.update(value.as_bytes());
+    }
+    hasher.update(body);
+    
+    let digest: String = hasher.finalize()[..16]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    HeaderValue::from_str(&format!("W/\"{}\"", digest)).expect("hex digest is a valid header value")
+}
+
+/// Weak comparison against `*` or any tag in the list
+fn matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(candidates) = if_none_match.to_str() else {
+        return false;
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let current = opaque(etag.to_str().unwrap_or_default());
+    candidates.split(',').any(|candidate| candidate.trim() == "*" || opaque(candidate) == current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn tags_change_with_the_representation() {
+        let headers = HeaderMap::new();
+        let queued = tag(StatusCode::OK, &headers, br#"{"status":"queued"}"#);
+        assert_eq!(queued, tag(StatusCode::OK, &headers, br#"{"status":"queued"}"#));
+        assert_ne!(queued, tag(StatusCode::OK, &headers, br#"{"status":"completed"}"#));
+        assert!(queued.to_str().unwrap().starts_with("W/\""));
+        
+        let mut plain = HeaderMap::new();
+        plain.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        assert_ne!(queued, tag(StatusCode::OK, &plain, br#"{"status":"queued"}"#));
+    }
+    
+    #[test]
+    fn if_none_match_compares_weakly() {
+        let etag = HeaderValue::from_static("W/\"abc\"");
+        for header in ["W/\"abc\"", "\"abc\"", "\"other\", W/\"abc\"", "*"] {
+            assert!(matches(&HeaderValue::from_static(header), &etag), "{}", header);
+        }
+        for header in ["W/\"abd\"", "\"other\"", ""] {
+            assert!(!matches(&HeaderValue::from_static(header), &etag), "{}", header);
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use crate::auth;
+use crate::engine::{DuplicateExecution, Paused, QueueFull, ShuttingDown};
+use crate::idempotency::{self, IdempotencyConflict};
+use crate::quota::QuotaExceeded;
+use crate::types::*;
+use crate::validation;
+use crate::AppState;
+use anyhow::Result;
+use futures::Stream;
+use labforcode_engine::proto;
+use labforcode_engine::spill;
+use proto::execution_service_server::{ExecutionService, ExecutionServiceServer};
+use proto::output_chunk::Stream as OutputStream;
+use proto::output_event::Event;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Bytes of output per StreamOutput chunk
+const OUTPUT_CHUNK_BYTES: usize = 64 * 1024;
+
+/// gRPC front end over the same engine, validator and rate limiter as the HTTP API
+struct GrpcService {
+    state: AppState,
+}
+
+/// Serve the gRPC API until `shutdown` resolves
+pub async fn serve(state: AppState, addr: SocketAddr, shutdown: impl Future<Output = ()>) -> Result<()> {
+    info!("🚀 gRPC server listening on {}", addr);
+    tonic::transport::Server::builder()
+        .add_service(ExecutionServiceServer::new(GrpcService { state }))
+        .serve_with_shutdown(addr, shutdown)
+        .await?;
+    Ok(())
+}
+
+#[tonic::async_trait]
+impl ExecutionService for GrpcService {
+    type StreamOutputStream = Pin<Box<dyn Stream<Item = Result<proto::OutputEvent, Status>> + Send>>;
+    
+    /// Same admission pipeline as POST /execute: limit units, ceilings, validation, rate limits, then the queue
+    async fn submit_execution(
+        &self,
+        request: Request<proto::ExecutionRequest>,
+    ) -> Result<Response<proto::SubmitExecutionResponse>, Status> {
+        let state = &self.state;
+        if state.engine.is_draining() {
+            return Err(Status::unavailable(ShuttingDown.to_string()));
+        }
+        
+        let headers = request.metadata().clone().into_headers();
+        let ip = request.remote_addr().map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |addr| addr.ip());
+        let request_id = headers.get(crate::REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let mut request = ExecutionRequest::try_from(request.into_inner())
+            .map_err(|err| invalid_argument(vec![err]))?;
+        let fingerprint = idempotency::fingerprint(&serde_json::to_value(&request).unwrap_or_default(), &HashMap::new());
+        
+        let admin = auth::is_admin(&headers, state.admin_api_key.as_deref());
+        if request.preserve_workdir.unwrap_or(false) && !admin {
+            return Err(Status::permission_denied("preserve_workdir requires the admin API key"));
+        }
+        
+        let mut violations = validation::resolve_limit_units(&mut request);
+        violations.extend(validation::apply_profile(&mut request, &state.engine.config().profiles));
+        let support = state.engine.support(&request);
+        let validator = state.engine.validator();
+        validator.apply_ceilings(&mut request, support);
+        validator.cap_priority(&mut request, auth::api_key(&headers), admin);
+        violations.extend(validation::decode_base64_fields(&mut request));
+        violations.extend(validator.validate(&request, support));
+        if !violations.is_empty() {
+            return Err(invalid_argument(violations));
+        }
+        
+        // A replay is answered before the rate limits, so it costs no tokens or slots
+        let idempotency_key = request.idempotency_key.clone()
+            .filter(|key| !key.is_empty())
+            .map(|key| format!("{}:{}", auth::api_key(&headers).unwrap_or_default(), key));
+        if let Some(key) = &idempotency_key {
+            if let Some(response) = state.engine.replay_idempotent(key, &fingerprint).map_err(submission_status)? {
+                return Ok(Response::new(response.into()));
+            }
+        }
+        
+        let client = state.limiter.client_key(auth::api_key(&headers), ip);
+        let finished = state.engine.finished_jobs(&state.limiter.tracked_jobs(&client)).await;
+        let reservation = state.limiter.admit(&client, &finished)
+            .map_err(|rejection| Status::resource_exhausted(rejection.to_string()))?;
+        
+        let context = SubmissionContext {
+            request_id: Some(request_id),
+            client: Some(client),
+            parent_id: None,
+        };
+        let submitted = match idempotency_key {
+            Some(key) => state.engine.submit_idempotent(request, context, &key, &fingerprint).await,
+            None => state.engine.submit_execution(request, context).await,
+        };
+        
+        let response = submitted.map_err(submission_status)?;
+        reservation.commit(&response.id);
+        Ok(Response::new(response.into()))
+    }
+    
+    async fn get_status(&self, request: Request<proto::ExecutionId>) -> Result<Response<proto::ExecutionStatus>, Status> {
+        let id = request.into_inner().id;
+        match self.state.engine.get_status(&id).await {
+            Ok(Some(status)) => Ok(Response::new(status.into())),
+            Ok(None) => Err(not_found(&id)),
+            Err(err) => Err(Status::internal(err.to_string())),
+        }
+    }
+    
+    async fn get_result(&self, request: Request<proto::ExecutionId>) -> Result<Response<proto::ExecutionResult>, Status> {
+        let id = request.into_inner().id;
+        match self.state.engine.get_result(&id).await {
+            Ok(Some(result)) => Ok(Response::new(ExecutionResult::clone(&result).into())),
+            Ok(None) => Err(not_found(&id)),
+            Err(err) => Err(Status::internal(err.to_string())),
+        }
+    }
+    
+    async fn cancel_execution(
+        &self,
+        request: Request<proto::ExecutionId>,
+    ) -> Result<Response<proto::CancelExecutionResponse>, Status> {
+        let id = request.into_inner().id;
+        let cancelled = self.state.engine.cancel_execution(&id).await
+            .map_err(|err| Status::internal(err.to_string()))?;
+        Ok(Response::new(CancelResponse {
+            cancelled,
+            message: if cancelled { "Execution cancelled" } else { "Could not cancel" }.to_string(),
+        }.into()))
+    }
+    
+    /// Status on every change until the job finishes, then stdout, stderr and compile output in chunks, then the result
+    async fn stream_output(&self, request: Request<proto::ExecutionId>) -> Result<Response<Self::StreamOutputStream>, Status> {
+        let id = request.into_inner().id;
+        let engine = Arc::clone(&self.state.engine);
+        
+        // Subscribe first so no change between the initial read and the stream is missed
+        let mut changes = engine.subscribe_changes();
+        let mut status = match engine.get_status(&id).await {
+            Ok(Some(status)) => status,
+            Ok(None) => return Err(not_found(&id)),
+            Err(err) => return Err(Status::internal(err.to_string())),
+        };
+        
+        let (sender, receiver) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let finished = status.status.is_terminal();
+                if sender.send(Ok(event(Event::Status(status.into())))).await.is_err() {
+                    return;
+                }
+                if finished {
+                    break;
+                }
+                
+                loop {
+                    match changes.recv().await {
+                        Ok(changed) if changed == id => break,
+                        Ok(_) => continue,
+                        // Missed some notifications; the refetch below catches up
+                        Err(broadcast::error::RecvError::Lagged(_)) => break,
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    }
+                }
+                status = match engine.get_status(&id).await {
+                    Ok(Some(status)) => status,
+                    Ok(None) => return,
+                    Err(err) => {
+                        let _ = sender.send(Err(Status::internal(err.to_string()))).await;
+                        return;
+                    }
+                };
+            }
+            
+            let result = match engine.get_result(&id).await {
+                Ok(Some(result)) => result,
+                Ok(None) => return,
+                Err(err) => {
+                    warn!("Could not stream the result of {}: {}", id, err);
+                    return;
+                }
+            };
+            let outputs = [
+                (OutputStream::Stdout, &result.stdout, Some(spill::OutputStream::Stdout)),
+                (OutputStream::Stderr, &result.stderr, Some(spill::OutputStream::Stderr)),
+                (OutputStream::CompileOutput, &result.compile_output, None),
+            ];
+            for (stream, text, spilled_stream) in outputs {
+                let spilled;
+                let bytes = match (text, spilled_stream) {
+                    (Some(text), _) => text.as_bytes(),
+                    // Outputs too large for the result are read back from their spill file
+                    (None, Some(spilled_stream)) => match engine.output_file(&id, spilled_stream).await {
+                        Ok(Some((path, _))) => {
+                            spilled = tokio::fs::read(path).await.unwrap_or_default();
+                            spilled.as_slice()
+                        }
+                        _ => continue,
+                    },
+                    (None, None) => continue,
+                };
+                for data in bytes.chunks(OUTPUT_CHUNK_BYTES) {
+                    let chunk = proto::OutputChunk { stream: stream.into(), data: data.to_vec() };
+                    if sender.send(Ok(event(Event::Output(chunk)))).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            let _ = sender.send(Ok(event(Event::Result(ExecutionResult::clone(&result).into())))).await;
+        });
+        
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+}
+
+fn event(event: Event) -> proto::OutputEvent {
+    proto::OutputEvent { event: Some(event) }
+}
+
+fn not_found(id: &str) -> Status {
+    Status::not_found(format!("Execution {} not found", id))
+}
+
+/// INVALID_ARGUMENT naming every field that failed validation
+fn invalid_argument(violations: Vec<FieldError>) -> Status {
+    let fields: Vec<String> = violations.iter()
+        .map(|violation| format!("{}: {}", violation.field, violation.constraint))
+        .collect();
+    Status::invalid_argument(format!("Request validation failed: {}", fields.join("; ")))
+}
+
+/// gRPC counterpart of the HTTP status `submission_error` picks
+fn submission_status(err: anyhow::Error) -> Status {
+    if err.is::<DuplicateExecution>() {
+        return Status::already_exists(err.to_string());
+    }
+    if err.is::<IdempotencyConflict>() {
+        return Status::failed_precondition(err.to_string());
+    }
+    if err.is::<QuotaExceeded>() {
+        return Status::resource_exhausted(err.to_string());
+    }
+    if err.is::<ShuttingDown>() || err.is::<Paused>() || err.is::<QueueFull>() {
+        return Status::unavailable(err.to_string());
+    }
+    warn!("gRPC execution submission failed: {}", err);
+    Status::internal(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EngineConfig;
+    use crate::tests::{get, state, unlimited};
+    use futures::StreamExt;
+    use tonic::Code;
+    
+    async fn service() -> GrpcService {
+        GrpcService { state: state(EngineConfig::load().unwrap(), unlimited()).await }
+    }
+    
+    fn python(source: &str) -> proto::ExecutionRequest {
+        let request: ExecutionRequest = serde_json::from_value(serde_json::json!({"language": "python", "source_code": source})).unwrap();
+        request.into()
+    }
+    
+    fn id(id: &str) -> Request<proto::ExecutionId> {
+        Request::new(proto::ExecutionId { id: id.to_string() })
+    }
+    
+    #[tokio::test]
+    async fn jobs_submitted_over_grpc_are_seen_over_http() {
+        let service = service().await;
+        let engine = Arc::clone(&service.state.engine);
+        engine.pause();
+        let app = crate::router(service.state.clone(), &engine.config().server);
+        
+        let submitted = service.submit_execution(Request::new(python("print(1)"))).await.unwrap().into_inner();
+        let status = service.get_status(id(&submitted.id)).await.unwrap().into_inner();
+        assert_eq!(ExecutionState::try_from(status.status).unwrap(), ExecutionState::Queued);
+        let (_, over_http) = get(&app, &format!("/v1/status/{}", submitted.id)).await;
+        assert_eq!(over_http["status"], "queued");
+        
+        let cancelled = service.cancel_execution(id(&submitted.id)).await.unwrap().into_inner();
+        assert!(cancelled.cancelled);
+        let result = service.get_result(id(&submitted.id)).await.unwrap().into_inner();
+        let result = ExecutionResult::try_from(result).unwrap();
+        assert_eq!(result.status, ExecutionState::Cancelled);
+        let (_, over_http) = get(&app, &format!("/v1/result/{}", submitted.id)).await;
+        assert_eq!(over_http, serde_json::to_value(&result).unwrap());
+    }
+    
+    #[tokio::test]
+    async fn bad_requests_and_unknown_ids_get_grpc_codes() {
+        let service = service().await;
+        let mut request = python("print(1)");
+        request.language = "cobol".to_string();
+        let refused = service.submit_execution(Request::new(request)).await.unwrap_err();
+        assert_eq!(refused.code(), Code::InvalidArgument);
+        assert!(refused.message().contains("language"), "{}", refused.message());
+        
+        assert_eq!(service.get_status(id("missing")).await.unwrap_err().code(), Code::NotFound);
+        assert_eq!(service.get_result(id("missing")).await.unwrap_err().code(), Code::NotFound);
+        assert_eq!(service.stream_output(id("missing")).await.err().unwrap().code(), Code::NotFound);
+    }
+    
+    #[tokio::test]
+    async fn output_streams_until_the_result() {
+        if std::process::Command::new("python3").arg("--version").output().is_err() {
+            eprintln!("skipped: needs python3");
+            return;
+        }
+        let service = service().await;
+        let submitted = service.submit_execution(Request::new(python("print('hi')"))).await.unwrap().into_inner();
+        let events: Vec<_> = service.stream_output(id(&submitted.id)).await.unwrap().into_inner()
+            .map(|event| event.unwrap().event.unwrap())
+            .collect()
+            .await;
+        
+        assert!(matches!(events.first(), Some(Event::Status(_))));
+        let stdout: Vec<u8> = events.iter()
+            .filter_map(|event| match event {
+                Event::Output(chunk) if chunk.stream == OutputStream::Stdout as i32 => Some(chunk.data.clone()),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert_eq!(stdout, b"hi\n");
+        let Some(Event::Result(result)) = events.last() else { panic!("stream ended without a result") };
+        assert_eq!(ExecutionResult::try_from(result.clone()).unwrap().status, ExecutionState::Completed);
+    }
+}
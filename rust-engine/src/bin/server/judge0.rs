@@ -0,0 +1,653 @@
+use crate::auth;
+use crate::engine::ExecutionEngine;
+use crate::rate_limit::Rejection;
+use crate::types::*;
+use crate::validation;
+use crate::{api_error, submission_error, too_many_requests, ApiError, AppState, RequestId};
+use axum::{
+    extract::{rejection::JsonRejection, ConnectInfo, Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Longest a `?wait=true` submission blocks before answering with the current state
+const WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Fields returned when `?fields` is not given, as in Judge0
+const DEFAULT_FIELDS: &[&str] = &["token", "stdout", "time", "memory", "stderr", "compile_output", "message", "status"];
+
+/// Judge0 fields the engine understands under the same name and unit
+const PASSTHROUGH_FIELDS: &[&str] = &[
+    "cpu_time_limit",
+    "cpu_extra_time",
+    "wall_time_limit",
+    "max_processes_and_or_threads",
+    "enable_per_process_and_thread_time_limit",
+    "enable_per_process_and_thread_memory_limit",
+    "redirect_stderr_to_stdout",
+    "enable_network",
+    "number_of_runs",
+    "compiler_options",
+    "command_line_arguments",
+    "callback_url",
+    "additional_files",
+];
+
+/// Judge0 sizes are in kilobytes; the engine counts bytes
+const KILOBYTE_FIELDS: &[&str] = &["memory_limit", "stack_limit", "max_file_size"];
+
+/// Text fields that are base64 encoded when `?base64_encoded=true`
+const TEXT_FIELDS: &[&str] = &["source_code", "stdin", "expected_output"];
+
+/// Judge0-style validation errors: field name to messages
+type FieldErrors = BTreeMap<String, Vec<String>>;
+
+#[derive(Debug, Default, Deserialize)]
+struct Params {
+    #[serde(default)]
+    base64_encoded: bool,
+    #[serde(default)]
+    wait: bool,
+    fields: Option<String>,
+    tokens: Option<String>,
+}
+
+/// Judge0 compatibility routes; tokens are execution ids
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/submissions", post(create_submission))
+        .route("/submissions/batch", post(create_batch).get(get_batch))
+        .route("/submissions/:token", get(get_submission))
+}
+
+/// POST /submissions
+#[utoipa::path(
+    post,
+    path = "/submissions",
+    tag = "judge0",
+    request_body(content = Object, description = "Judge0 submission; sizes in kilobytes"),
+    params(
+        ("base64_encoded" = Option<bool>, Query, description = "Text fields are base64 encoded"),
+        ("wait" = Option<bool>, Query, description = "Block until the submission finishes"),
+        ("fields" = Option<String>, Query, description = "Comma separated fields to return with wait=true, or *"),
+    ),
+    responses(
+        (status = 201, description = "Token, or the finished submission with wait=true", body = Object),
+        (status = 422, description = "Judge0-style field errors", body = Object),
+        (status = 429, description = "Rate, concurrency or usage quota exceeded", body = ErrorResponse),
+        (status = 503, description = "Queue full, paused or shutting down", body = ErrorResponse),
+    )
+)]
+async fn create_submission(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    Query(params): Query<Params>,
+    headers: HeaderMap,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    let Json(body) = payload.map_err(|rejection| api_error(rejection.status(), rejection.body_text()))?;
+    let client = state.limiter.client_key(auth::api_key(&headers), addr.ip());
+    let token = submit(&state, &client, &request_id, &body, params.base64_encoded).await?;
+    
+    if !params.wait {
+        return Ok((StatusCode::CREATED, Json(json!({ "token": token }))).into_response());
+    }
+    
+    let job = wait_for(&state.engine, &token).await?;
+    Ok((StatusCode::CREATED, Json(render(&state.engine, &job, &params))).into_response())
+}
+
+/// POST /submissions/batch
+#[utoipa::path(
+    post,
+    path = "/submissions/batch",
+    tag = "judge0",
+    request_body(content = Object, description = "{\"submissions\": [...]}"),
+    params(("base64_encoded" = Option<bool>, Query, description = "Text fields are base64 encoded")),
+    responses(
+        (status = 201, description = "A token or errors for each submission, in order", body = Vec<Object>),
+        (status = 422, description = "submissions is not an array", body = ErrorResponse),
+    )
+)]
+async fn create_batch(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    Query(params): Query<Params>,
+    headers: HeaderMap,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    let Json(body) = payload.map_err(|rejection| api_error(rejection.status(), rejection.body_text()))?;
+    let submissions = body.get("submissions")
+        .and_then(Value::as_array)
+        .ok_or_else(|| api_error(StatusCode::UNPROCESSABLE_ENTITY, "submissions must be an array"))?;
+    let client = state.limiter.client_key(auth::api_key(&headers), addr.ip());
+    
+    // Each entry gets a token or its own errors, like Judge0
+    let mut created = Vec::with_capacity(submissions.len());
+    for submission in submissions {
+        match translate(&state, submission, params.base64_encoded) {
+            Ok(request) => match enqueue(&state, &client, &request_id, request).await {
+                Ok(token) => created.push(json!({ "token": token })),
+                Err(refused) => created.push(json!({ "error": refused.message() })),
+            },
+            Err(errors) => created.push(json!(errors)),
+        }
+    }
+    
+    Ok((StatusCode::CREATED, Json(created)).into_response())
+}
+
+/// GET /submissions/:token
+#[utoipa::path(
+    get,
+    path = "/submissions/{token}",
+    tag = "judge0",
+    params(
+        ("token" = String, Path, description = "Execution id"),
+        ("base64_encoded" = Option<bool>, Query, description = "Encode text fields"),
+        ("fields" = Option<String>, Query, description = "Comma separated fields to return, or *"),
+    ),
+    responses(
+        (status = 200, description = "Submission in Judge0 form", body = Object),
+        (status = 404, description = "Unknown token", body = ErrorResponse),
+    )
+)]
+async fn get_submission(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(params): Query<Params>,
+) -> Result<Json<Value>, ApiError> {
+    match state.engine.lookup(&token).await {
+        Ok(Some(job)) => Ok(Json(render(&state.engine, &job, &params))),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, "Not Found")),
+        Err(err) => Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())),
+    }
+}
+
+/// GET /submissions/batch?tokens=a,b,c
+#[utoipa::path(
+    get,
+    path = "/submissions/batch",
+    tag = "judge0",
+    params(
+        ("tokens" = String, Query, description = "Comma separated tokens"),
+        ("base64_encoded" = Option<bool>, Query, description = "Encode text fields"),
+        ("fields" = Option<String>, Query, description = "Comma separated fields to return, or *"),
+    ),
+    responses(
+        (status = 200, description = "{\"submissions\": [...]}, null for unknown tokens", body = Object),
+    )
+)]
+async fn get_batch(
+    State(state): State<AppState>,
+    Query(params): Query<Params>,
+) -> Result<Json<Value>, ApiError> {
+    let tokens = params.tokens.as_deref().unwrap_or_default();
+    
+    let mut submissions = Vec::new();
+    for token in tokens.split(',').map(str::trim).filter(|token| !token.is_empty()) {
+        let job = state.engine.lookup(token).await
+            .map_err(|err| api_error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+        submissions.push(job.map_or(Value::Null, |job| render(&state.engine, &job, &params)));
+    }
+    
+    Ok(Json(json!({ "submissions": submissions })))
+}
+
+/// Why a translated submission was not enqueued
+enum Refused {
+    Limited(Rejection),
+    Failed(anyhow::Error),
+}
+
+impl Refused {
+    fn into_response(self) -> ApiError {
+        match self {
+            Refused::Limited(rejection) => too_many_requests(rejection),
+            Refused::Failed(err) => submission_error(err),
+        }
+    }
+    
+    fn message(&self) -> String {
+        match self {
+            Refused::Limited(rejection) => rejection.to_string(),
+            Refused::Failed(err) => err.to_string(),
+        }
+    }
+}
+
+/// Translate, validate and enqueue one submission, returning its token
+async fn submit(
+    state: &AppState,
+    client: &str,
+    request_id: &str,
+    body: &Value,
+    base64_encoded: bool,
+) -> Result<String, ApiError> {
+    let request = translate(state, body, base64_encoded)
+        .map_err(|errors| (StatusCode::UNPROCESSABLE_ENTITY, Json(errors)).into_response())?;
+    enqueue(state, client, request_id, request).await.map_err(Refused::into_response)
+}
+
+/// Admit and submit a translated request through the same caps as the native API
+async fn enqueue(state: &AppState, client: &str, request_id: &str, request: ExecutionRequest) -> Result<String, Refused> {
+    let finished = state.engine.finished_jobs(&state.limiter.tracked_jobs(client)).await;
+    let reservation = state.limiter.admit(client, &finished).map_err(Refused::Limited)?;
+    
+    let context = SubmissionContext {
+        request_id: Some(request_id.to_string()),
+        client: Some(client.to_string()),
+        parent_id: None,
+    };
+    let response = state.engine.submit_execution(request, context).await
+        .map_err(Refused::Failed)?;
+    reservation.commit(&response.id);
+    Ok(response.id)
+}
+
+/// Build a native request from a Judge0 submission, then apply the engine's ceilings and validation
+fn translate(state: &AppState, body: &Value, base64_encoded: bool) -> Result<ExecutionRequest, FieldErrors> {
+    let mut errors = FieldErrors::new();
+    let fields = match body.as_object() {
+        Some(fields) => fields,
+        None => {
+            add_error(&mut errors, "submission", "must be a JSON object");
+            return Err(errors);
+        }
+    };
+    
+    let mut native = Map::new();
+    match fields.get("language_id").and_then(Value::as_u64) {
+        Some(id) => match state.engine.language_by_id(id as u32) {
+            Some((language, _)) => {
+                native.insert("language".to_string(), language.into());
+                native.insert("language_id".to_string(), id.into());
+            }
+            None => add_error(&mut errors, "language_id", format!("language with id {} doesn't exist", id)),
+        },
+        None => add_error(&mut errors, "language_id", "can't be blank"),
+    }
+    
+    for field in TEXT_FIELDS {
+        let text = match fields.get(*field) {
+            Some(Value::String(text)) => text,
+            Some(Value::Null) | None => continue,
+            Some(_) => {
+                add_error(&mut errors, field, "must be a string");
+                continue;
+            }
+        };
+        native.insert(field.to_string(), text.clone().into());
+    }
+    if base64_encoded {
+        native.insert("base64_encoded".to_string(), true.into());
+    }
+    if !native.contains_key("source_code") && !errors.contains_key("source_code") {
+        add_error(&mut errors, "source_code", "can't be blank");
+    }
+    
+    for field in KILOBYTE_FIELDS {
+        if let Some(kilobytes) = fields.get(*field).and_then(Value::as_f64) {
+            native.insert(field.to_string(), ((kilobytes * 1024.0) as u64).into());
+        }
+    }
+    for field in PASSTHROUGH_FIELDS {
+        if let Some(value) = fields.get(*field).filter(|value| !value.is_null()) {
+            native.insert(field.to_string(), value.clone());
+        }
+    }
+    
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let mut request: ExecutionRequest = match serde_json::from_value(Value::Object(native)) {
+        Ok(request) => request,
+        Err(err) => {
+            add_error(&mut errors, "submission", err.to_string());
+            return Err(errors);
+        }
+    };
+    
+    let mut violations = validation::resolve_limit_units(&mut request);
+    let support = state.engine.support(&request);
+    let validator = state.engine.validator();
+    validator.apply_ceilings(&mut request, support);
+    violations.extend(validation::decode_base64_fields(&mut request));
+    violations.extend(validator.validate(&request, support));
+    for violation in violations {
+        add_error(&mut errors, &violation.field, violation.constraint);
+    }
+    if errors.is_empty() {
+        Ok(request)
+    } else {
+        Err(errors)
+    }
+}
+
+fn add_error(errors: &mut FieldErrors, field: &str, message: impl Into<String>) {
+    errors.entry(field.to_string()).or_default().push(message.into());
+}
+
+/// Wait until the job finishes or the wait times out
+async fn wait_for(engine: &ExecutionEngine, token: &str) -> Result<ExecutionJob, ApiError> {
+    engine.wait_for_completion(token, WAIT_TIMEOUT).await
+        .map_err(|err| api_error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    engine.lookup(token).await
+        .map_err(|err| api_error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "Not Found"))
+}
+
+/// A job as a Judge0 submission, restricted to the requested fields
+fn render(engine: &ExecutionEngine, job: &ExecutionJob, params: &Params) -> Value {
+    let request = &job.request;
+    let result = job.result.as_ref();
+    let limits = engine.limits(request);
+    let (status_id, description) = status(job);
+    let language_name = request.language_id
+        .and_then(|id| engine.language_by_id(id))
+        .map(|(_, name)| name);
+    
+    // stdin and outputs stay encoded when the job itself was submitted with base64_encoded
+    let stored_encoded = request.base64_encoded.unwrap_or(false);
+    let bytes = |value: Option<&str>, encoded: bool| match value {
+        Some(value) => Value::from(encode_output(&decode_output(value, encoded), params.base64_encoded)),
+        None => Value::Null,
+    };
+    let text = |value: Option<&str>| bytes(value, false);
+    let output = |value: Option<&str>| bytes(value, stored_encoded);
+    // Judge0 reports a stream the program never wrote to as null
+    let stream = |value: Option<&str>| output(value.filter(|value| !value.is_empty()));
+    let time = result.and_then(|result| result.time).map(|time| format!("{:.3}", time));
+    
+    let submission = json!({
+        "token": job.id,
+        "source_code": text(Some(&request.source_code)),
+        "language_id": request.language_id,
+        "language": { "id": request.language_id, "name": language_name },
+        "stdin": output(request.stdin.as_deref()),
+        "expected_output": text(request.expected_output.as_deref()),
+        "stdout": stream(result.and_then(|result| result.stdout.as_deref())),
+        "stderr": stream(result.and_then(|result| result.stderr.as_deref())),
+        "compile_output": stream(result.and_then(|result| result.compile_output.as_deref())),
+        "message": text(message(job).as_deref()),
+        "exit_code": result.and_then(|result| result.exit_code),
+        "exit_signal": result.and_then(|result| result.signal.as_deref()).and_then(signal_number),
+        "status": { "id": status_id, "description": description },
+        "status_id": status_id,
+        "created_at": job.created_at,
+        "finished_at": job.finished_at,
+        "time": time,
+        "wall_time": time,
+        "memory": result.and_then(|result| result.memory).map(|bytes| bytes / 1024),
+        "number_of_runs": request.number_of_runs.unwrap_or(1),
+        "cpu_time_limit": limits.cpu_time,
+        "cpu_extra_time": limits.cpu_extra_time,
+        "wall_time_limit": limits.wall_time,
+        "memory_limit": limits.memory / 1024,
+        "stack_limit": limits.stack_limit / 1024,
+        "max_file_size": limits.file_size / 1024,
+        "max_processes_and_or_threads": limits.processes,
+        "enable_per_process_and_thread_time_limit": limits.enable_per_process_time_limit,
+        "enable_per_process_and_thread_memory_limit": limits.enable_per_process_memory_limit,
+        "redirect_stderr_to_stdout": request.redirect_stderr_to_stdout.unwrap_or(false),
+        "enable_network": request.enable_network.unwrap_or(false),
+        "compiler_options": request.compiler_options,
+        "command_line_arguments": request.command_line_arguments,
+        "callback_url": request.callback_url,
+    });
+    
+    let Value::Object(submission) = submission else {
+        unreachable!("json! object literal");
+    };
+    select_fields(submission, params.fields.as_deref())
+}
+
+/// Keep only the fields asked for; `*` keeps everything
+fn select_fields(submission: Map<String, Value>, fields: Option<&str>) -> Value {
+    let wanted: Vec<&str> = match fields {
+        Some("*") => return Value::Object(submission),
+        Some(fields) => fields.split(',').map(str::trim).collect(),
+        None => DEFAULT_FIELDS.to_vec(),
+    };
+    Value::Object(submission.into_iter().filter(|(field, _)| wanted.contains(&field.as_str())).collect())
+}
+
+/// Judge0 status id and description
+fn status(job: &ExecutionJob) -> (u32, &'static str) {
+    let result = job.result.as_ref();
+    match job.status {
+        ExecutionState::Scheduled | ExecutionState::Queued => (1, "In Queue"),
+        ExecutionState::Processing | ExecutionState::Running => (2, "Processing"),
+        ExecutionState::Completed => {
+            let stdout = result.and_then(|result| result.stdout.as_deref()).unwrap_or_default();
+            let stdout = decode_output(stdout, job.request.base64_encoded.unwrap_or(false));
+            match job.request.expected_output.as_deref() {
+                Some(expected) if expected.trim_end() != String::from_utf8_lossy(&stdout).trim_end() => (4, "Wrong Answer"),
+                _ => (3, "Accepted"),
+            }
+        }
+        ExecutionState::WrongAnswer => (4, "Wrong Answer"),
+        ExecutionState::TimeLimitExceeded => (5, "Time Limit Exceeded"),
+        ExecutionState::CompilationError => (6, "Compilation Error"),
+        ExecutionState::RuntimeError => match result.and_then(|result| result.signal.as_deref()) {
+            Some("SIGSEGV") => (7, "Runtime Error (SIGSEGV)"),
+            Some("SIGXFSZ") => (8, "Runtime Error (SIGXFSZ)"),
+            Some("SIGFPE") => (9, "Runtime Error (SIGFPE)"),
+            Some("SIGABRT") => (10, "Runtime Error (SIGABRT)"),
+            None if result.and_then(|result| result.exit_code).is_some_and(|code| code != 0) => (11, "Runtime Error (NZEC)"),
+            _ => (12, "Runtime Error (Other)"),
+        },
+        ExecutionState::OutputLimitExceeded => (8, "Runtime Error (SIGXFSZ)"),
+        ExecutionState::MemoryLimitExceeded => (12, "Runtime Error (Other)"),
+        ExecutionState::Cancelled | ExecutionState::InternalError => (13, "Internal Error"),
+    }
+}
+
+/// Judge0's human-readable explanation of the status
+fn message(job: &ExecutionJob) -> Option<String> {
+    let result = job.result.as_ref()?;
+    match job.status {
+        ExecutionState::TimeLimitExceeded => Some("Time limit exceeded".to_string()),
+        ExecutionState::RuntimeError => match (&result.signal, result.exit_code) {
+            (Some(_), _) => result.exit_description.clone(),
+            (None, Some(code)) if code != 0 => result.exit_description.clone(),
+            _ => result.failure_reason.clone(),
+        },
+        _ => result.failure_reason.clone(),
+    }
+}
+
+/// Number of a signal reported by name
+fn signal_number(name: &str) -> Option<i32> {
+    match name {
+        "SIGHUP" => Some(1),
+        "SIGINT" => Some(2),
+        "SIGQUIT" => Some(3),
+        "SIGILL" => Some(4),
+        "SIGTRAP" => Some(5),
+        "SIGABRT" => Some(6),
+        "SIGBUS" => Some(7),
+        "SIGFPE" => Some(8),
+        "SIGKILL" => Some(9),
+        "SIGSEGV" => Some(11),
+        "SIGPIPE" => Some(13),
+        "SIGALRM" => Some(14),
+        "SIGTERM" => Some(15),
+        "SIGXCPU" => Some(24),
+        "SIGXFSZ" => Some(25),
+        "SIGSYS" => Some(31),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{app, get, post, unlimited};
+    
+    /// Whether the host can run Python submissions, noting the skip when it cannot
+    fn has_python() -> bool {
+        let found = std::process::Command::new("python3").arg("--version").output().is_ok();
+        if !found {
+            eprintln!("skipped: needs python3");
+        }
+        found
+    }
+    
+    #[tokio::test]
+    async fn submissions_are_refused_with_judge0_field_errors() {
+        let (app, _) = app(unlimited()).await;
+        let (status, body) = post(&app, "/submissions", &[], json!({"source_code": "print(1)"})).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body, json!({"language_id": ["can't be blank"]}));
+        
+        let (status, body) = post(&app, "/submissions", &[], json!({"language_id": 9999})).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body, json!({
+            "language_id": ["language with id 9999 doesn't exist"],
+            "source_code": ["can't be blank"],
+        }));
+    }
+    
+    #[tokio::test]
+    async fn tokens_are_execution_ids_and_sizes_are_kilobytes() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        let (status, body) = post(&app, "/submissions", &[], json!({
+            "source_code": "print(1)",
+            "language_id": 71,
+            "memory_limit": 128000,
+        })).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let token = body["token"].as_str().unwrap();
+        let job = engine.lookup(token).await.unwrap().unwrap();
+        assert_eq!((job.request.language.as_str(), job.request.memory_limit), ("python", Some(128000 * 1024)));
+        
+        let (status, body) = get(&app, &format!("/submissions/{}?fields=token,status,memory_limit,language", token)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({
+            "token": token,
+            "status": {"id": 1, "description": "In Queue"},
+            "memory_limit": 128000,
+            "language": {"id": 71, "name": "Python 3"},
+        }));
+        
+        let (status, _) = get(&app, "/submissions/no-such-token").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+    
+    #[tokio::test]
+    async fn batches_answer_each_entry_in_order() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        let (status, created) = post(&app, "/submissions/batch", &[], json!({"submissions": [
+            {"source_code": "print(1)", "language_id": 71},
+            {"source_code": "print(2)"},
+        ]})).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(created[0]["token"].is_string(), "{}", created);
+        assert_eq!(created[1], json!({"language_id": ["can't be blank"]}));
+        
+        let tokens = format!("{},unknown", created[0]["token"].as_str().unwrap());
+        let (_, body) = get(&app, &format!("/submissions/batch?tokens={}&fields=status_id", tokens)).await;
+        assert_eq!(body, json!({"submissions": [{"status_id": 1}, null]}));
+        
+        let (status, _) = post(&app, "/submissions/batch", &[], json!({"submissions": {}})).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    
+    #[tokio::test]
+    async fn waited_submissions_answer_like_judge0() {
+        if !has_python() {
+            return;
+        }
+        let (app, _) = app(unlimited()).await;
+        let (status, body) = post(&app, "/submissions?wait=true", &[], json!({
+            "source_code": "print(\"hello, world\")",
+            "language_id": 71,
+            "stdin": "",
+        })).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let fields: Vec<&str> = body.as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(fields, ["compile_output", "memory", "message", "status", "stderr", "stdout", "time", "token"]);
+        assert_eq!(body["stdout"], "hello, world\n");
+        assert_eq!(body["status"], json!({"id": 3, "description": "Accepted"}));
+        assert_eq!((&body["stderr"], &body["compile_output"], &body["message"]), (&Value::Null, &Value::Null, &Value::Null));
+        assert!(body["time"].as_str().unwrap().parse::<f64>().is_ok(), "{}", body["time"]);
+        
+        let (_, body) = post(&app, "/submissions?wait=true&fields=status,exit_code", &[], json!({
+            "source_code": "import sys\nsys.exit(3)",
+            "language_id": 71,
+        })).await;
+        assert_eq!(body, json!({"status": {"id": 11, "description": "Runtime Error (NZEC)"}, "exit_code": 3}));
+        
+        let (_, body) = post(&app, "/submissions?wait=true&fields=status", &[], json!({
+            "source_code": "print(41)",
+            "language_id": 71,
+            "expected_output": "42",
+        })).await;
+        assert_eq!(body["status"], json!({"id": 4, "description": "Wrong Answer"}));
+    }
+    
+    #[tokio::test]
+    async fn base64_submissions_are_answered_in_base64() {
+        if !has_python() {
+            return;
+        }
+        let (app, _) = app(unlimited()).await;
+        // print(input()) with "hi" on stdin
+        let (status, body) = post(&app, "/submissions?base64_encoded=true&wait=true&fields=stdout,status_id", &[], json!({
+            "source_code": "cHJpbnQoaW5wdXQoKSk=",
+            "language_id": 71,
+            "stdin": "aGk=",
+        })).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body, json!({"stdout": "aGkK", "status_id": 3}));
+        
+        let (status, body) = post(&app, "/submissions?base64_encoded=true", &[], json!({"source_code": "not base64!", "language_id": 71})).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body.get("source_code").is_some(), "{}", body);
+    }
+    
+    #[test]
+    fn signal_numbers_match_the_sandboxes_names() {
+        for signal in 1..=31 {
+            let name = labforcode_engine::sandbox::signal_name(signal);
+            if !name.starts_with("signal ") {
+                assert_eq!(signal_number(&name), Some(signal), "{}", name);
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn messages_explain_how_the_program_exited() {
+        if !has_python() {
+            return;
+        }
+        let (app, _) = app(unlimited()).await;
+        let (_, body) = post(&app, "/submissions?wait=true&fields=status,message,exit_signal", &[], json!({
+            "source_code": "import os, signal\nos.kill(os.getpid(), signal.SIGFPE)",
+            "language_id": 71,
+        })).await;
+        assert_eq!(body, json!({
+            "status": {"id": 9, "description": "Runtime Error (SIGFPE)"},
+            "message": "killed by SIGFPE (floating point exception, likely division by zero)",
+            "exit_signal": 8,
+        }));
+        
+        let (_, body) = post(&app, "/submissions?wait=true&fields=message", &[], json!({
+            "source_code": "import sys\nsys.exit(3)",
+            "language_id": 71,
+        })).await;
+        assert_eq!(body, json!({"message": "exited normally with code 3"}));
+    }
+}
@@ -0,0 +1,2828 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{ConnectInfo, DefaultBodyLimit, Extension, Path, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
+    routing::{get, post, put, delete},
+    Router,
+};
+use clap::Parser;
+use futures::Stream;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+use tracing::{info, info_span, warn, Instrument};
+use uuid::Uuid;
+
+mod auth;
+mod case;
+mod cli;
+mod compression;
+mod cors;
+mod etag;
+mod grpc;
+mod judge0;
+mod multipart;
+mod openapi;
+mod versioning;
+
+use labforcode_engine::{audit, config, engine, executor, idempotency, quota, rate_limit, recent, spill, telemetry, types, validation, warmup, workdir};
+
+use config::{EngineConfig, Profile, RuntimeSettings};
+use engine::{DuplicateExecution, ExecutionEngine, InvalidCapacity, InvalidSettings, NoDeadLetter, Paused, QueueFull, ShuttingDown};
+use idempotency::IdempotencyConflict;
+use multipart::{Submission, UploadLimits};
+use quota::QuotaExceeded;
+use metrics_exporter_prometheus::PrometheusHandle;
+use rate_limit::{RateLimiter, Rejection};
+use recent::RECENT_CAPACITY;
+use spill::OutputStream;
+use types::*;
+use versioning::ApiVersion;
+
+/// Application state shared across handlers
+#[derive(Clone)]
+pub struct AppState {
+    engine: Arc<ExecutionEngine>,
+    admin_api_key: Option<String>,
+    limiter: RateLimiter,
+    metrics: PrometheusHandle,
+    uploads: UploadLimits,
+}
+
+/// Header carrying the id that ties a request to its log lines
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Correlation id of the HTTP request being handled
+#[derive(Clone)]
+struct RequestId(String);
+
+/// Longest GET /result/:id?wait=N holds the connection
+const MAX_RESULT_WAIT: Duration = Duration::from_secs(30);
+
+/// Header that makes /execute safe to retry
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Page size of admin listings when `limit` is left out
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Largest page of an admin listing
+const MAX_PAGE_SIZE: usize = 500;
+
+/// Entries GET /stats/recent returns when `limit` is left out
+const DEFAULT_RECENT: usize = 100;
+
+/// Error status with a JSON body describing what went wrong
+type ApiError = Response;
+
+/// Main entry point for the Rust execution engine
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // Load environment variables
+    dotenv::dotenv().ok();
+    let cli = cli::Cli::parse();
+    
+    // Initialize logging; LOG_FORMAT=json emits one object per line, span fields included.
+    // `run` and `check` print their results on stdout, so their logs go to stderr
+    let run = matches!(cli.command, Some(cli::Command::Run(_) | cli::Command::Check(_)));
+    match (std::env::var("LOG_FORMAT").as_deref() == Ok("json"), run) {
+        (true, true) => tracing_subscriber::fmt().json().with_writer(std::io::stderr).init(),
+        (true, false) => tracing_subscriber::fmt().json().init(),
+        (false, true) => tracing_subscriber::fmt().with_writer(std::io::stderr).init(),
+        (false, false) => tracing_subscriber::fmt::init(),
+    }
+    
+    match cli.command {
+        Some(cli::Command::Run(args)) => std::process::exit(cli::run(args).await?),
+        Some(cli::Command::Check(args)) => std::process::exit(cli::check(args).await?),
+        Some(cli::Command::Serve) | None => serve().await,
+    }
+}
+
+/// Run the HTTP server, and the gRPC server when a gRPC port is configured, until shutdown
+async fn serve() -> anyhow::Result<()> {
+    info!("🦀 Starting LabForCode Rust Engine");
+    
+    // ENGINE_CONFIG and the environment, checked before anything starts
+    let config = EngineConfig::load()?;
+    let metrics = telemetry::install()?;
+    audit::install(&config.audit).await?;
+    
+    // Initialize the execution engine
+    let engine = Arc::new(ExecutionEngine::new(config).await?);
+    let config = engine.config();
+    let admin_api_key = config.server.admin_api_key.clone();
+    let limiter = RateLimiter::from_config(&config.rate_limit).with_api_keys(config.server.admin_api_key.clone());
+    limiter.start_pruning();
+    let drain_timeout = Duration::from_secs(config.server.shutdown_drain_timeout_secs);
+    let shutdown_engine = Arc::clone(&engine);
+    let uploads = UploadLimits::from_config(&config.server);
+    let state = AppState { engine, admin_api_key, limiter, metrics, uploads };
+    let grpc_state = state.clone();
+    
+    let app = router(state, &config.server);
+    
+    // Start the server
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
+    
+    info!("🚀 Rust Engine listening on {}", addr);
+    
+    // Both servers keep answering (with 503 / UNAVAILABLE for new work) while running jobs drain, then stop together
+    let (stop, stopped) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        shutdown_engine.shutdown(drain_timeout).await;
+        let _ = stop.send(true);
+    });
+    let until_stopped = |mut stopped: tokio::sync::watch::Receiver<bool>| async move {
+        let _ = stopped.wait_for(|stopped| *stopped).await;
+    };
+    
+    let listener = TcpListener::bind(&addr).await?;
+    let http = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(until_stopped(stopped.clone()));
+    
+    // A gRPC port enables the gRPC API next to the HTTP one
+    match config.server.grpc_port {
+        Some(port) => {
+            let grpc_addr = SocketAddr::from(([0, 0, 0, 0], port));
+            let grpc = grpc::serve(grpc_state, grpc_addr, until_stopped(stopped));
+            tokio::try_join!(async { http.await.map_err(anyhow::Error::from) }, grpc)?;
+        }
+        None => http.await?,
+    }
+    
+    info!("👋 Rust Engine stopped");
+    Ok(())
+}
+
+/// Build the router; the engine API is versioned, probes, metrics and the Judge0 surface are not
+fn router(state: AppState, server: &config::ServerConfig) -> Router {
+    let api = Router::new()
+        .route("/execute", post(execute_code).layer(DefaultBodyLimit::max(state.uploads.max_total_bytes)))
+        .route("/status/:id", get(get_execution_status).layer(middleware::from_fn(etag::conditional_get)))
+        .route("/status/:id/events", get(stream_execution_status))
+        .route(
+            "/result/:id",
+            get(get_execution_result).delete(delete_execution).layer(middleware::from_fn(etag::conditional_get)),
+        )
+        .route("/result/:id/stdout", get(get_stdout_file))
+        .route("/result/:id/stderr", get(get_stderr_file))
+        .route("/result/:id/files", get(list_workdir_files))
+        .route("/result/:id/files/*path", get(get_workdir_file))
+        .route("/cancel/:id", delete(cancel_execution))
+        .route("/executions", get(list_executions))
+        .route("/executions/:id/rerun", post(rerun_execution))
+        .route("/executions/:id/events", get(get_execution_events))
+        .route("/stats", get(get_engine_stats))
+        .route("/stats/recent", get(get_recent_executions))
+        .route("/languages", get(get_supported_languages))
+        .route("/profiles", get(get_profiles))
+        .route("/usage", get(get_usage))
+        .route("/admin/clients", get(get_client_stats))
+        .route("/admin/queue", get(get_queue))
+        .route("/admin/queue/:id", delete(drop_queued))
+        .route("/admin/queue/:id/promote", post(promote_queued))
+        .route("/admin/cancel", post(cancel_matching))
+        .route("/admin/stats/reset", post(reset_engine_stats))
+        .route("/admin/pause", post(pause_processing))
+        .route("/admin/resume", post(resume_processing))
+        .route("/admin/capacity", get(get_capacity).put(update_capacity))
+        .route("/admin/config", get(get_config).put(update_config))
+        .route("/admin/callbacks/failed", get(get_failed_callbacks))
+        .route("/admin/callbacks/:id/retry", post(retry_callback))
+        .route("/admin/usage", get(get_all_usage))
+        .route("/admin/quotas/:client", put(update_quota))
+        .layer(middleware::from_fn(case::camel_case_responses));
+    Router::new()
+        .route("/", get(health_check))
+        .route("/health", get(health_check))
+        .route("/metrics", get(get_metrics))
+        .merge(versioning::mount(api, server.legacy_routes_sunset))
+        .merge(judge0::router())
+        .merge(openapi::router())
+        .layer(compression::request_layer())
+        .layer(compression::response_layer(server.compression_min_bytes))
+        .layer(middleware::from_fn(assign_request_id))
+        .layer(cors::layer(&server.cors))
+        .with_state(state)
+}
+
+/// Resolve on Ctrl-C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!("Could not listen for Ctrl-C: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+    
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                warn!("Could not listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("🛑 Shutdown signal received");
+}
+
+/// Accept the caller's X-Request-Id or assign one, log the request under it and echo it back
+async fn assign_request_id(mut request: Request, next: Next) -> Response {
+    let id = request.headers().get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    
+    let span = info_span!("request", request_id = %id, method = %request.method(), path = %request.uri().path());
+    request.extensions_mut().insert(RequestId(id.clone()));
+    
+    let mut response = next.run(request).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Health check endpoint; answers 503 while draining so load balancers stop routing here
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "info",
+    params(
+        ("minimal" = Option<bool>, Query, description = "Leave out build, backend and capacity details"),
+    ),
+    responses(
+        (status = 200, description = "Engine is accepting work", body = HealthResponse),
+        (status = 503, description = "Engine is draining for shutdown, warming up, the docker runtime is missing, or memory or disk space is short", body = HealthResponse),
+    )
+)]
+async fn health_check(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> (StatusCode, Json<HealthResponse>) {
+    let temp_disk = state.engine.temp_disk();
+    let (code, status) = if state.engine.is_draining() {
+        (StatusCode::SERVICE_UNAVAILABLE, "draining")
+    } else if state.engine.warming_up() {
+        (StatusCode::SERVICE_UNAVAILABLE, "warming up")
+    } else if !state.engine.runtime_ready() {
+        (StatusCode::SERVICE_UNAVAILABLE, "runtime unavailable")
+    } else if state.engine.memory_pressure() {
+        (StatusCode::SERVICE_UNAVAILABLE, "memory pressure")
+    } else if state.engine.low_disk_space() {
+        (StatusCode::SERVICE_UNAVAILABLE, "low disk space")
+    } else {
+        (StatusCode::OK, "healthy")
+    };
+    
+    let mut response = HealthResponse {
+        status: status.to_string(),
+        engine: "rust".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        paused: state.engine.is_paused(),
+        runtimes: state.engine.docker_runtimes(),
+        api_versions: versioning::SUPPORTED.iter().map(|version| version.as_str().to_string()).collect(),
+        commit: Some(env!("BUILD_GIT_COMMIT")).filter(|commit| !commit.is_empty()).map(String::from),
+        built_at: env!("BUILD_TIMESTAMP").parse().ok().and_then(|secs| chrono::DateTime::from_timestamp(secs, 0)),
+        workers: Some(state.engine.capacity().max_concurrent_executions),
+        queue: Some(state.engine.config().queue.backend),
+        sandbox: Some(state.engine.sandbox_features()),
+        warmup: state.engine.warmup_report(),
+        temp_disk,
+        timestamp: chrono::Utc::now(),
+    };
+    // Load balancer probes only need the status
+    if params.get("minimal").is_some_and(|value| value == "true") {
+        response.runtimes.clear();
+        response.api_versions.clear();
+        response.commit = None;
+        response.built_at = None;
+        response.warmup.clear();
+        response.workers = None;
+        response.queue = None;
+        response.sandbox = None;
+        response.temp_disk = None;
+    }
+    (code, Json(response))
+}
+
+/// Execute code submission
+#[utoipa::path(
+    post,
+    context_path = "/v1",
+    path = "/execute",
+    tag = "executions",
+    request_body = ExecutionRequest,
+    params(
+        ("base64_encoded" = Option<bool>, Query, description = "Text fields are base64 encoded"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replay the original response to repeats of this request"),
+    ),
+    responses(
+        (status = 200, description = "Execution queued, scheduled or deduplicated", body = ExecutionResponse),
+        (status = 403, description = "preserve_workdir without the admin key", body = ErrorResponse),
+        (status = 409, description = "Id belongs to an unfinished execution", body = ErrorResponse),
+        (status = 422, description = "Request failed validation", body = ErrorResponse),
+        (status = 429, description = "Rate, concurrency or usage quota exceeded", body = ErrorResponse),
+        (status = 503, description = "Queue full, paused or shutting down", body = ErrorResponse),
+    )
+)]
+async fn execute_code(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Submission(body): Submission,
+) -> Result<Json<ExecutionResponse>, ApiError> {
+    if state.engine.is_draining() {
+        return Err(api_error(StatusCode::SERVICE_UNAVAILABLE, ShuttingDown.to_string()));
+    }
+    
+    // Ceilings can change at runtime; one submission is checked against one set
+    let validator = state.engine.validator();
+    let mut violations = Vec::new();
+    if validator.reject_unknown_fields {
+        violations.extend(validation::unknown_fields(&body));
+    }
+    let mut request: ExecutionRequest = serde_json::from_value(body.clone())
+        .map_err(|err| api_error(StatusCode::UNPROCESSABLE_ENTITY, format!("Invalid request body: {}", err)))?;
+    info!("Received execution request for language: {}", request.language);
+    
+    // ?base64_encoded=true applies to the whole submission, like the body field
+    if params.get("base64_encoded").is_some_and(|v| v == "true") {
+        request.base64_encoded = Some(true);
+    }
+    
+    if request.preserve_workdir.unwrap_or(false) {
+        require_admin(&state, &headers)
+            .map_err(|status| api_error(status, "preserve_workdir requires the admin API key"))?;
+    }
+    
+    violations.extend(validation::resolve_limit_units(&mut request));
+    violations.extend(validation::apply_profile(&mut request, &state.engine.config().profiles));
+    let support = state.engine.support(&request);
+    validator.apply_ceilings(&mut request, support);
+    validator.cap_priority(
+        &mut request,
+        auth::api_key(&headers),
+        auth::is_admin(&headers, state.admin_api_key.as_deref()),
+    );
+    violations.extend(validation::decode_base64_fields(&mut request));
+    violations.extend(validator.validate(&request, support));
+    if !violations.is_empty() {
+        // An unknown language comes with the closest keys and the supported ones
+        let details = (!support.language).then(|| state.engine.language_suggestions(&request.language));
+        return Err(validation_failed_with(violations, details));
+    }
+    
+    // Idempotency keys are scoped to the caller's API key; a replay costs no tokens or slots
+    let idempotency_key = headers.get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| request.idempotency_key.clone())
+        .filter(|key| !key.is_empty())
+        .map(|key| format!("{}:{}", auth::api_key(&headers).unwrap_or_default(), key));
+    let fingerprint = idempotency::fingerprint(&body, &params);
+    if let Some(key) = &idempotency_key {
+        if let Some(response) = state.engine.replay_idempotent(key, &fingerprint).map_err(submission_error)? {
+            return Ok(Json(response));
+        }
+    }
+    
+    // Per-client rate and concurrency caps
+    let client = state.limiter.client_key(auth::api_key(&headers), addr.ip());
+    let finished = state.engine.finished_jobs(&state.limiter.tracked_jobs(&client)).await;
+    let reservation = state.limiter.admit(&client, &finished).map_err(too_many_requests)?;
+    
+    let context = SubmissionContext {
+        request_id: Some(request_id),
+        client: Some(client),
+        parent_id: None,
+    };
+    let submitted = match idempotency_key {
+        Some(key) => state.engine.submit_idempotent(request, context, &key, &fingerprint).await,
+        None => state.engine.submit_execution(request, context).await,
+    };
+    
+    match submitted {
+        Ok(response) => {
+            reservation.commit(&response.id);
+            Ok(Json(response))
+        }
+        Err(err) => Err(submission_error(err)),
+    }
+}
+
+/// Map a failed submission to its HTTP response
+fn submission_error(err: anyhow::Error) -> ApiError {
+    if err.is::<DuplicateExecution>() {
+        return api_error(StatusCode::CONFLICT, err.to_string());
+    }
+    if err.is::<IdempotencyConflict>() {
+        return api_error(StatusCode::UNPROCESSABLE_ENTITY, err.to_string());
+    }
+    if err.is::<ShuttingDown>() || err.is::<Paused>() {
+        return api_error(StatusCode::SERVICE_UNAVAILABLE, err.to_string());
+    }
+    if let Some(exceeded) = err.downcast_ref::<QuotaExceeded>() {
+        return quota_exceeded(exceeded);
+    }
+    match err.downcast_ref::<QueueFull>() {
+        Some(full) => queue_full(full),
+        None => {
+            warn!("Execution submission failed: {}", err);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        }
+    }
+}
+
+/// 422 listing every field that failed validation
+fn validation_failed(violations: Vec<FieldError>) -> ApiError {
+    validation_failed_with(violations, None)
+}
+
+/// 422 listing every field that failed validation, with extra context in `details`
+fn validation_failed_with(violations: Vec<FieldError>, details: Option<serde_json::Value>) -> ApiError {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(ErrorResponse {
+            error: "Request validation failed".to_string(),
+            fields: violations,
+            details,
+        }),
+    ).into_response()
+}
+
+/// Build an error response without field details
+fn api_error(status: StatusCode, error: impl Into<String>) -> ApiError {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.into(),
+            fields: Vec::new(),
+            details: None,
+        }),
+    ).into_response()
+}
+
+/// 429 response telling the client when to retry
+fn too_many_requests(rejection: Rejection) -> ApiError {
+    let retry_after = rejection.retry_after().as_secs_f64().ceil().max(1.0) as u64;
+    
+    let mut response = api_error(StatusCode::TOO_MANY_REQUESTS, rejection.to_string());
+    response.headers_mut().insert(header::RETRY_AFTER, retry_after.into());
+    response
+}
+
+/// 429 response naming the exhausted quota and when it resets
+fn quota_exceeded(exceeded: &QuotaExceeded) -> ApiError {
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(ErrorResponse {
+            error: exceeded.to_string(),
+            fields: Vec::new(),
+            details: Some(serde_json::json!({
+                "code": "quota_exceeded",
+                "period": exceeded.period,
+                "counter": exceeded.counter,
+                "used": exceeded.used,
+                "limit": exceeded.limit,
+                "resets_at": exceeded.resets_at,
+            })),
+        }),
+    ).into_response();
+    
+    let retry_after = (exceeded.resets_at - chrono::Utc::now()).num_seconds().max(1) as u64;
+    response.headers_mut().insert(header::RETRY_AFTER, retry_after.into());
+    response
+}
+
+/// Lifecycle events of an execution, oldest first
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/executions/{id}/events",
+    tag = "executions",
+    params(("id" = String, Path, description = "Execution id")),
+    responses(
+        (status = 200, description = "Audit trail, oldest first", body = Vec<AuditEvent>),
+        (status = 404, description = "Unknown execution"),
+    )
+)]
+async fn get_execution_events(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<AuditEvent>>, StatusCode> {
+    match state.engine.events(&id).await {
+        Ok(Some(events)) => Ok(Json(events)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Submit a copy of a finished execution under a new id, optionally with different stdin or limits
+#[utoipa::path(
+    post,
+    context_path = "/v1",
+    path = "/executions/{id}/rerun",
+    tag = "executions",
+    params(("id" = String, Path, description = "Execution id")),
+    request_body(content = Option<RerunOverrides>, description = "Optional stdin, limit and number_of_runs overrides"),
+    responses(
+        (status = 200, description = "Re-run queued", body = ExecutionResponse),
+        (status = 404, description = "Unknown execution", body = ErrorResponse),
+        (status = 409, description = "Execution has not finished", body = ErrorResponse),
+        (status = 422, description = "Overrides failed validation", body = ErrorResponse),
+        (status = 429, description = "Rate, concurrency or usage quota exceeded", body = ErrorResponse),
+        (status = 503, description = "Queue full, paused or shutting down", body = ErrorResponse),
+    )
+)]
+async fn rerun_execution(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ExecutionResponse>, ApiError> {
+    if state.engine.is_draining() {
+        return Err(api_error(StatusCode::SERVICE_UNAVAILABLE, ShuttingDown.to_string()));
+    }
+    
+    // The body is optional; without one the execution is repeated as it was
+    let overrides: RerunOverrides = if body.is_empty() {
+        RerunOverrides::default()
+    } else {
+        serde_json::from_slice(&body)
+            .map_err(|err| api_error(StatusCode::UNPROCESSABLE_ENTITY, format!("Invalid request body: {}", err)))?
+    };
+    
+    let parent = state.engine.lookup(&id).await
+        .map_err(|err| api_error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("Execution {} not found", id)))?;
+    if !parent.status.is_terminal() {
+        return Err(api_error(StatusCode::CONFLICT, format!("Execution {} has not finished", id)));
+    }
+    info!("🔁 Re-running execution {}", id);
+    
+    let mut request = parent.request;
+    request.id = String::new();
+    request.idempotency_key = None;
+    request.run_at = None;
+    request.dedupe = None; // a re-run must not collapse back onto its own parent
+    overrides.apply(&mut request);
+    
+    let mut violations = validation::resolve_limit_units(&mut request);
+    let support = state.engine.support(&request);
+    let validator = state.engine.validator();
+    validator.apply_ceilings(&mut request, support);
+    violations.extend(validation::check_encoded_stdin(&request));
+    violations.extend(validator.validate(&request, support));
+    if !violations.is_empty() {
+        return Err(validation_failed(violations));
+    }
+    
+    let client = state.limiter.client_key(auth::api_key(&headers), addr.ip());
+    let finished = state.engine.finished_jobs(&state.limiter.tracked_jobs(&client)).await;
+    let reservation = state.limiter.admit(&client, &finished).map_err(too_many_requests)?;
+    
+    let context = SubmissionContext {
+        request_id: Some(request_id),
+        client: Some(client),
+        parent_id: Some(id),
+    };
+    let response = state.engine.submit_execution(request, context).await.map_err(submission_error)?;
+    reservation.commit(&response.id);
+    Ok(Json(response))
+}
+
+/// Get execution status
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/status/{id}",
+    tag = "executions",
+    params(
+        ("id" = String, Path, description = "Execution id"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a previous response"),
+    ),
+    responses(
+        (status = 200, description = "Current status", body = ExecutionStatus),
+        (status = 304, description = "Unchanged since the ETag in If-None-Match"),
+        (status = 404, description = "Unknown execution"),
+    )
+)]
+async fn get_execution_status(
+    State(state): State<AppState>,
+    Extension(version): Extension<ApiVersion>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    match state.engine.get_status(&id).await {
+        Ok(Some(status)) => Ok(version.mapper().status(status)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// List the status of recent executions, newest first, filtered by state, language or metadata
+/// Callers see the jobs they submitted; the admin key sees every job and may filter with `client`
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/executions",
+    tag = "executions",
+    params(
+        ("status" = Option<String>, Query, description = "Only jobs in this state, by name or code"),
+        ("language" = Option<String>, Query, description = "Only jobs in this language"),
+        ("metadata.{key}" = Option<String>, Query, description = "Only jobs whose metadata has this value for the key, e.g. metadata.user_id=42; repeat for several keys"),
+        ("client" = Option<String>, Query, description = "Only jobs of this client (admin only)"),
+        ("limit" = Option<usize>, Query, description = "Most jobs listed, 1-1000, default 100"),
+    ),
+    responses(
+        (status = 200, description = "Matching jobs held in memory, newest first; queue positions are left out", body = Vec<ExecutionStatus>),
+        (status = 422, description = "Unusable filter", body = ErrorResponse),
+    )
+)]
+async fn list_executions(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ExecutionStatus>>, ApiError> {
+    let mut query = ExecutionListQuery::parse(&params).map_err(validation_failed)?;
+    query.client = if auth::is_admin(&headers, state.admin_api_key.as_deref()) {
+        params.get("client").cloned()
+    } else {
+        Some(state.limiter.client_key(auth::api_key(&headers), addr.ip()))
+    };
+    Ok(Json(state.engine.list_executions(&query).await))
+}
+
+/// Stream status events (progress included) until the job finishes, as server-sent events
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/status/{id}/events",
+    tag = "executions",
+    params(("id" = String, Path, description = "Execution id")),
+    responses(
+        (status = 200, description = "Server-sent `status` events until the execution finishes", body = ExecutionStatus, content_type = "text/event-stream"),
+        (status = 404, description = "Unknown execution"),
+    )
+)]
+async fn stream_execution_status(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    // Subscribe first so no change between the initial read and the stream is missed
+    let changes = state.engine.subscribe_changes();
+    let first = match state.engine.get_status(&id).await {
+        Ok(Some(status)) => status,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    
+    let engine = Arc::clone(&state.engine);
+    let events = futures::stream::unfold(
+        (Some(first), changes, false),
+        move |(pending, mut changes, finished)| {
+            let engine = Arc::clone(&engine);
+            let id = id.clone();
+            async move {
+                if finished {
+                    return None;
+                }
+                let status = match pending {
+                    Some(status) => status,
+                    None => {
+                        loop {
+                            match changes.recv().await {
+                                Ok(changed) if changed == id => break,
+                                Ok(_) => continue,
+                                // Missed some notifications; the refetch below catches up
+                                Err(broadcast::error::RecvError::Lagged(_)) => break,
+                                Err(broadcast::error::RecvError::Closed) => return None,
+                            }
+                        }
+                        engine.get_status(&id).await.ok().flatten()?
+                    }
+                };
+                
+                let finished = status.status.is_terminal();
+                let event = Event::default().event("status").json_data(&status).ok()?;
+                Some((Ok(event), (None, changes, finished)))
+            }
+        },
+    );
+    
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Get execution result
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/result/{id}",
+    tag = "executions",
+    params(
+        ("id" = String, Path, description = "Execution id"),
+        ("wait" = Option<f64>, Query, description = "Seconds to wait for the execution to finish, at most 30"),
+        ("include_output" = Option<bool>, Query, description = "false (or 0, no) leaves out stdout, stderr and compile output"),
+        ("fields" = Option<String>, Query, description = "Comma-separated top-level fields to return, e.g. status,time,memory"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a previous response"),
+    ),
+    responses(
+        (status = 200, description = "Result so far; with Accept: text/plain, the stdout of a completed execution", content(
+            ("application/json" = ExecutionResult),
+            ("text/plain" = String),
+        )),
+        (status = 202, description = "Accept: text/plain only: still running", content_type = "text/plain"),
+        (status = 302, description = "Archived and evicted, with ARCHIVE_SERVE=redirect: a presigned URL of the result"),
+        (status = 304, description = "Unchanged since the ETag in If-None-Match"),
+        (status = 424, description = "Accept: text/plain only: stdout of a failed execution; X-Status says why", content_type = "text/plain"),
+        (status = 404, description = "Unknown execution"),
+        (status = 422, description = "include_output or fields not understood", body = ErrorResponse),
+    )
+)]
+async fn get_execution_result(
+    State(state): State<AppState>,
+    Extension(version): Extension<ApiVersion>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let query = match ResultQuery::parse(&params) {
+        Ok(query) => query,
+        Err(violations) => return Ok(validation_failed(violations)),
+    };
+    
+    // ?wait=N long-polls for up to N seconds (capped) until the job finishes
+    let wait = params.get("wait")
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+        .map(|secs| Duration::from_secs_f64(secs).min(MAX_RESULT_WAIT));
+    if let Some(wait) = wait {
+        state.engine.wait_for_completion(&id, wait).await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    
+    let mut response = if prefers_plain_text(&headers) {
+        match state.engine.get_output(&id).await {
+            Ok(Some((result, base64_encoded))) => {
+                // A stdout too large for the result is read back from its file
+                let spilled = match result.stdout_bytes {
+                    Some(_) => match state.engine.output_file(&id, OutputStream::Stdout).await {
+                        Ok(Some((path, _))) => tokio::fs::read(path).await.ok(),
+                        _ => None,
+                    },
+                    None => None,
+                };
+                plain_text_result(result, base64_encoded, spilled)
+            }
+            Ok(None) => return Err(StatusCode::NOT_FOUND),
+            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    } else {
+        match state.engine.get_result(&id).await {
+            Ok(Some(result)) => version.mapper().result(ResultView {
+                result,
+                include_output: query.include_output,
+                fields: query.fields,
+            }),
+            Ok(None) => match state.engine.archived_result_url(&id).await {
+                Ok(Some(url)) => return Ok((StatusCode::FOUND, [(header::LOCATION, url)]).into_response()),
+                Ok(None) => return Err(StatusCode::NOT_FOUND),
+                Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+            },
+            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    };
+    response.headers_mut().insert(header::VARY, HeaderValue::from_static("accept"));
+    Ok(response)
+}
+
+/// Whether Accept names text/plain ahead of application/json; JSON stays the default
+fn prefers_plain_text(headers: &HeaderMap) -> bool {
+    let Some(accept) = headers.get(header::ACCEPT).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    accept.split(',')
+        .map(|range| range.split(';').next().unwrap_or_default().trim())
+        .find(|media_type| media_type.eq_ignore_ascii_case("text/plain") || media_type.eq_ignore_ascii_case("application/json"))
+        .is_some_and(|media_type| media_type.eq_ignore_ascii_case("text/plain"))
+}
+
+/// Bare stdout for scripts: the HTTP status tells success (200) from failure (424) and
+/// still running (202), and the X- headers carry what the JSON result would
+fn plain_text_result(result: Arc<ExecutionResult>, base64_encoded: bool, spilled: Option<Vec<u8>>) -> Response {
+    use base64::{engine::general_purpose, Engine as _};
+    
+    let status = match &result.status {
+        state if !state.is_terminal() => StatusCode::ACCEPTED,
+        ExecutionState::Completed => StatusCode::OK,
+        ExecutionState::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::FAILED_DEPENDENCY,
+    };
+    let state = result.status.as_str();
+    let exit_code = result.exit_code;
+    let time = result.time;
+    let memory = result.memory;
+    
+    // Base64 submissions get base64 output in JSON; here the caller wants the actual bytes
+    let body = if let Some(bytes) = spilled {
+        Bytes::from(bytes)
+    } else if base64_encoded {
+        let stdout = result.stdout.as_deref().unwrap_or_default();
+        Bytes::from(general_purpose::STANDARD.decode(stdout).unwrap_or_else(|_| stdout.as_bytes().to_vec()))
+    } else {
+        // Plain stdout is sent straight from the shared result rather than copied out of it
+        Bytes::from_owner(SharedStdout(result))
+    };
+    
+    let mut response = (status, [(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body).into_response();
+    let headers = response.headers_mut();
+    headers.insert("x-status", HeaderValue::from_static(state));
+    if let Some(exit_code) = exit_code {
+        headers.insert("x-exit-code", exit_code.into());
+    }
+    if let Some(time) = time {
+        headers.insert("x-time-ms", ((time * 1000.0).round() as u64).into());
+    }
+    if let Some(memory) = memory {
+        headers.insert("x-memory-kb", (memory / 1024).into());
+    }
+    response
+}
+
+/// A result's stdout as a response body, keeping the result alive instead of copying the text
+struct SharedStdout(Arc<ExecutionResult>);
+
+impl AsRef<[u8]> for SharedStdout {
+    fn as_ref(&self) -> &[u8] {
+        self.0.stdout.as_deref().unwrap_or_default().as_bytes()
+    }
+}
+
+/// Delete a finished execution and anything retained for it
+#[utoipa::path(
+    delete,
+    context_path = "/v1",
+    path = "/result/{id}",
+    tag = "admin",
+    security(("api_key" = [])),
+    params(("id" = String, Path, description = "Execution id")),
+    responses(
+        (status = 204, description = "Execution deleted"),
+        (status = 403, description = "Admin key missing"),
+        (status = 404, description = "Unknown or unfinished execution"),
+    )
+)]
+async fn delete_execution(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> StatusCode {
+    if let Err(status) = require_admin(&state, &headers) {
+        return status;
+    }
+    match state.engine.delete_execution(&id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// List files in a preserved working directory
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/result/{id}/files",
+    tag = "admin",
+    security(("api_key" = [])),
+    params(("id" = String, Path, description = "Execution id")),
+    responses(
+        (status = 200, description = "Files of the preserved working directory", body = Vec<WorkdirEntry>),
+        (status = 403, description = "Admin key missing"),
+        (status = 404, description = "No preserved working directory"),
+    )
+)]
+async fn list_workdir_files(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<WorkdirEntry>>, StatusCode> {
+    require_admin(&state, &headers)?;
+    
+    match state.engine.list_workdir(&id).await {
+        Ok(Some(entries)) => Ok(Json(entries)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Download a single file from a preserved working directory
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/result/{id}/files/{path}",
+    tag = "admin",
+    security(("api_key" = [])),
+    params(("id" = String, Path, description = "Execution id"), ("path" = String, Path, description = "File path inside the working directory")),
+    responses(
+        (status = 200, description = "File contents", body = [u8], content_type = "application/octet-stream"),
+        (status = 400, description = "Path escapes the working directory"),
+        (status = 403, description = "Admin key missing"),
+        (status = 404, description = "No such file"),
+    )
+)]
+async fn get_workdir_file(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((id, path)): Path<(String, String)>,
+) -> Result<impl IntoResponse, StatusCode> {
+    require_admin(&state, &headers)?;
+    
+    let file = match state.engine.workdir_file(&id, &path).await {
+        Ok(Some(file)) => file,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(err) => {
+            warn!("Rejected workdir file request for {}: {}", id, err);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    };
+    
+    let bytes = tokio::fs::read(&file).await.map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], bytes))
+}
+
+/// Download a stdout too large to embed in the result
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/result/{id}/stdout",
+    tag = "executions",
+    params(
+        ("id" = String, Path, description = "Execution id"),
+        ("Range" = Option<String>, Header, description = "Byte range, e.g. bytes=0-1048575"),
+    ),
+    responses(
+        (status = 200, description = "The whole output", body = [u8], content_type = "text/plain"),
+        (status = 206, description = "The requested range", body = [u8], content_type = "text/plain"),
+        (status = 404, description = "Unknown execution, or its stdout is in the result"),
+        (status = 416, description = "Range outside the output"),
+    )
+)]
+async fn get_stdout_file(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    request: Request,
+) -> Result<Response, StatusCode> {
+    serve_spilled(&state, &id, OutputStream::Stdout, request).await
+}
+
+/// Download a stderr too large to embed in the result
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/result/{id}/stderr",
+    tag = "executions",
+    params(
+        ("id" = String, Path, description = "Execution id"),
+        ("Range" = Option<String>, Header, description = "Byte range, e.g. bytes=0-1048575"),
+    ),
+    responses(
+        (status = 200, description = "The whole output", body = [u8], content_type = "text/plain"),
+        (status = 206, description = "The requested range", body = [u8], content_type = "text/plain"),
+        (status = 404, description = "Unknown execution, or its stderr is in the result"),
+        (status = 416, description = "Range outside the output"),
+    )
+)]
+async fn get_stderr_file(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    request: Request,
+) -> Result<Response, StatusCode> {
+    serve_spilled(&state, &id, OutputStream::Stderr, request).await
+}
+
+/// Stream a spill file as an attachment, with Content-Length and Range support; binary output as octet-stream
+async fn serve_spilled(state: &AppState, id: &str, stream: OutputStream, request: Request) -> Result<Response, StatusCode> {
+    let (path, binary) = match state.engine.output_file(id, stream).await {
+        Ok(Some(file)) => file,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    
+    let mut response = ServeFile::new(path).oneshot(request).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Body::new);
+    let content_type = if binary { "application/octet-stream" } else { "text/plain; charset=utf-8" };
+    let disposition = format!("attachment; filename=\"{}.{}\"", id, stream.as_str());
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    if let Ok(disposition) = HeaderValue::from_str(&disposition) {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+    Ok(response)
+}
+
+/// Reject the request unless it carries the admin API key
+fn require_admin(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    if auth::is_admin(headers, state.admin_api_key.as_deref()) {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// 503 response carrying the backlog size and how long it should take to drain
+fn queue_full(full: &QueueFull) -> ApiError {
+    let mut response = (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ErrorResponse {
+            error: full.to_string(),
+            fields: Vec::new(),
+            details: Some(serde_json::json!({
+                "queue_length": full.queued,
+                "estimated_wait_seconds": full.estimated_wait_secs,
+            })),
+        }),
+    ).into_response();
+    
+    let retry_after = full.estimated_wait_secs.ceil().max(1.0) as u64;
+    response.headers_mut().insert(header::RETRY_AFTER, retry_after.into());
+    response
+}
+
+/// Current queue depth and concurrency limits
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/admin/capacity",
+    tag = "admin",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Queue depth and concurrency limits", body = CapacitySettings),
+        (status = 403, description = "Admin key missing"),
+    )
+)]
+async fn get_capacity(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<CapacitySettings>, StatusCode> {
+    require_admin(&state, &headers)?;
+    Ok(Json(state.engine.capacity()))
+}
+
+/// Effective configuration, including runtime settings changes, without secrets
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/admin/config",
+    tag = "admin",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Configuration in force; see /admin/capacity for the current worker count", body = EngineConfig),
+        (status = 403, description = "Admin key missing"),
+    )
+)]
+async fn get_config(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<EngineConfig>, StatusCode> {
+    require_admin(&state, &headers)?;
+    Ok(Json(state.engine.config().redacted()))
+}
+
+/// Change ceilings, disabled languages, queue depth, autoscaling bounds or output caps without restarting
+#[utoipa::path(
+    put,
+    context_path = "/v1",
+    path = "/admin/config",
+    tag = "admin",
+    security(("api_key" = [])),
+    request_body = RuntimeSettings,
+    responses(
+        (status = 200, description = "Every runtime setting now in force", body = RuntimeSettings),
+        (status = 403, description = "Admin key missing"),
+        (status = 422, description = "A setting the startup checks would refuse", body = ErrorResponse),
+        (status = 500, description = "The settings could not be saved; nothing was changed", body = ErrorResponse),
+    )
+)]
+async fn update_config(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    headers: HeaderMap,
+    Json(changes): Json<RuntimeSettings>,
+) -> Result<Json<RuntimeSettings>, ApiError> {
+    require_admin(&state, &headers).map_err(IntoResponse::into_response)?;
+    let admin = state.limiter.client_key(auth::api_key(&headers), addr.ip());
+    match state.engine.update_settings(changes, &admin, Some(request_id)).await {
+        Ok(settings) => Ok(Json(settings)),
+        Err(err) if err.is::<InvalidSettings>() => Err(api_error(StatusCode::UNPROCESSABLE_ENTITY, err.to_string())),
+        Err(err) => Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", err))),
+    }
+}
+
+/// `?offset=&limit=` of admin listings
+#[derive(Debug, serde::Deserialize)]
+struct PageParams {
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+/// Callbacks that failed every attempt, newest first; kept for PRESERVE_TTL_SECS
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/admin/callbacks/failed",
+    tag = "admin",
+    security(("api_key" = [])),
+    params(
+        ("offset" = Option<usize>, Query, description = "Entries to skip"),
+        ("limit" = Option<usize>, Query, description = "Entries to return, default 50, at most 500"),
+    ),
+    responses(
+        (status = 200, description = "One page of dead-lettered callbacks", body = DeadLetterPage),
+        (status = 403, description = "Admin key missing"),
+    )
+)]
+async fn get_failed_callbacks(
+    State(state): State<AppState>,
+    Query(page): Query<PageParams>,
+    headers: HeaderMap,
+) -> Result<Json<DeadLetterPage>, StatusCode> {
+    require_admin(&state, &headers)?;
+    let limit = page.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    Ok(Json(state.engine.failed_callbacks(page.offset.unwrap_or(0), limit)))
+}
+
+/// Deliver a dead-lettered callback once more with the job's current result
+#[utoipa::path(
+    post,
+    context_path = "/v1",
+    path = "/admin/callbacks/{id}/retry",
+    tag = "admin",
+    security(("api_key" = [])),
+    params(("id" = String, Path, description = "Execution id")),
+    responses(
+        (status = 204, description = "Delivered; the entry is removed"),
+        (status = 403, description = "Admin key missing"),
+        (status = 404, description = "The execution's callback is not dead-lettered"),
+        (status = 502, description = "Failed again; the updated entry", body = DeadLetter),
+    )
+)]
+async fn retry_callback(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    require_admin(&state, &headers)?;
+    match state.engine.retry_callback(&id).await {
+        Ok(None) => Ok(StatusCode::NO_CONTENT.into_response()),
+        Ok(Some(entry)) => Ok((StatusCode::BAD_GATEWAY, Json(entry)).into_response()),
+        Err(err) if err.is::<NoDeadLetter>() => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Change the queue depth and concurrency limits at runtime
+#[utoipa::path(
+    put,
+    context_path = "/v1",
+    path = "/admin/capacity",
+    tag = "admin",
+    security(("api_key" = [])),
+    request_body = CapacityUpdate,
+    responses(
+        (status = 200, description = "Limits now in force", body = CapacitySettings),
+        (status = 403, description = "Admin key missing"),
+        (status = 422, description = "Autoscaling bounds out of order", body = ErrorResponse),
+    )
+)]
+async fn update_capacity(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(update): Json<CapacityUpdate>,
+) -> Result<Json<CapacitySettings>, ApiError> {
+    require_admin(&state, &headers).map_err(IntoResponse::into_response)?;
+    match state.engine.update_capacity(update) {
+        Ok(settings) => Ok(Json(settings)),
+        Err(err) if err.is::<InvalidCapacity>() => Err(api_error(StatusCode::UNPROCESSABLE_ENTITY, err.to_string())),
+        Err(err) => Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())),
+    }
+}
+
+/// Per-client rate limiting counters
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/admin/clients",
+    tag = "admin",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Rate limiting counters per client", body = Vec<ClientStats>),
+        (status = 403, description = "Admin key missing"),
+    )
+)]
+async fn get_client_stats(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ClientStats>>, StatusCode> {
+    require_admin(&state, &headers)?;
+    Ok(Json(state.limiter.stats()))
+}
+
+/// Usage and remaining quota of the calling client
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/usage",
+    tag = "info",
+    responses(
+        (status = 200, description = "The caller's usage and remaining quota", body = UsageReport),
+    )
+)]
+async fn get_usage(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Json<UsageReport> {
+    let client = state.limiter.client_key(auth::api_key(&headers), addr.ip());
+    Json(state.engine.usage(&client))
+}
+
+/// Usage and remaining quota of every client
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/admin/usage",
+    tag = "admin",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Usage and remaining quota of every client", body = Vec<UsageReport>),
+        (status = 403, description = "Admin key missing"),
+    )
+)]
+async fn get_all_usage(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<UsageReport>>, StatusCode> {
+    require_admin(&state, &headers)?;
+    Ok(Json(state.engine.all_usage()))
+}
+
+/// Replace a client's quota; `client` is a raw API key or a key:/ip: client key as listed by /admin/usage
+#[utoipa::path(
+    put,
+    context_path = "/v1",
+    path = "/admin/quotas/{client}",
+    tag = "admin",
+    security(("api_key" = [])),
+    params(("client" = String, Path, description = "Raw API key or key:/ip: client id")),
+    request_body = Quota,
+    responses(
+        (status = 200, description = "Usage under the new quota", body = UsageReport),
+        (status = 403, description = "Admin key missing"),
+    )
+)]
+async fn update_quota(
+    State(state): State<AppState>,
+    Path(client): Path<String>,
+    headers: HeaderMap,
+    Json(quota): Json<Quota>,
+) -> Result<Json<UsageReport>, StatusCode> {
+    require_admin(&state, &headers)?;
+    Ok(Json(state.engine.set_quota(&client, quota).await))
+}
+
+/// List queued jobs in the order they will run
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/admin/queue",
+    tag = "admin",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Queued jobs in run order", body = Vec<QueuedJobInfo>),
+        (status = 403, description = "Admin key missing"),
+    )
+)]
+async fn get_queue(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<QueuedJobInfo>>, StatusCode> {
+    require_admin(&state, &headers)?;
+    state.engine.queued_jobs().await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Move a queued job ahead of everything else
+#[utoipa::path(
+    post,
+    context_path = "/v1",
+    path = "/admin/queue/{id}/promote",
+    tag = "admin",
+    security(("api_key" = [])),
+    params(("id" = String, Path, description = "Execution id")),
+    responses(
+        (status = 204, description = "Job moved to the front"),
+        (status = 403, description = "Admin key missing"),
+        (status = 404, description = "Job is not queued"),
+    )
+)]
+async fn promote_queued(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    require_admin(&state, &headers)?;
+    match state.engine.promote_queued(&id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Remove a queued job without running it; the job is marked cancelled
+#[utoipa::path(
+    delete,
+    context_path = "/v1",
+    path = "/admin/queue/{id}",
+    tag = "admin",
+    security(("api_key" = [])),
+    params(("id" = String, Path, description = "Execution id")),
+    responses(
+        (status = 204, description = "Job dropped and marked cancelled"),
+        (status = 403, description = "Admin key missing"),
+        (status = 404, description = "Job is not queued"),
+    )
+)]
+async fn drop_queued(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    require_admin(&state, &headers)?;
+    match state.engine.drop_queued(&id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Cancel every unfinished job matching a filter, killing the running ones
+#[utoipa::path(
+    post,
+    context_path = "/v1",
+    path = "/admin/cancel",
+    tag = "admin",
+    security(("api_key" = [])),
+    request_body = CancelFilter,
+    responses(
+        (status = 200, description = "Jobs cancelled, counted by the status they had", body = BulkCancelResponse),
+        (status = 403, description = "Admin key missing"),
+    )
+)]
+async fn cancel_matching(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(filter): Json<CancelFilter>,
+) -> Result<Json<BulkCancelResponse>, StatusCode> {
+    require_admin(&state, &headers)?;
+    match state.engine.cancel_matching(&filter).await {
+        Ok(response) => Ok(Json(response)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Cancel execution
+#[utoipa::path(
+    delete,
+    context_path = "/v1",
+    path = "/cancel/{id}",
+    tag = "executions",
+    params(("id" = String, Path, description = "Execution id")),
+    responses(
+        (status = 200, description = "Whether the execution was cancelled", body = CancelResponse),
+    )
+)]
+async fn cancel_execution(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<CancelResponse>, StatusCode> {
+    match state.engine.cancel_execution(&id).await {
+        Ok(success) => Ok(Json(CancelResponse { 
+            cancelled: success,
+            message: if success { "Execution cancelled" } else { "Could not cancel" }.to_string(),
+        })),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Get engine statistics; `?detail=false` leaves out the per-language breakdown
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/stats",
+    tag = "info",
+    params(("detail" = Option<bool>, Query, description = "false leaves out the per-language breakdown")),
+    responses(
+        (status = 200, description = "Engine statistics", body = EngineStats),
+    )
+)]
+async fn get_engine_stats(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<EngineStats>, StatusCode> {
+    let detail = params.get("detail")
+        .map(|v| v != "false")
+        .unwrap_or(true);
+    
+    match state.engine.get_stats().await {
+        Ok(mut stats) => {
+            if !detail {
+                stats.by_language.clear();
+            }
+            Ok(Json(stats))
+        }
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// The latest finished jobs, newest first
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/stats/recent",
+    tag = "info",
+    params(("limit" = Option<usize>, Query, description = "Entries to return, default 100, at most 1000")),
+    responses(
+        (status = 200, description = "Latest finished jobs", body = Vec<RecentExecution>),
+    )
+)]
+async fn get_recent_executions(
+    State(state): State<AppState>,
+    Query(page): Query<PageParams>,
+) -> Json<Vec<RecentExecution>> {
+    let limit = page.limit.unwrap_or(DEFAULT_RECENT).min(RECENT_CAPACITY);
+    Json(state.engine.recent_executions(limit).await)
+}
+
+/// Prometheus metrics
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "info",
+    responses(
+        (status = 200, description = "Prometheus text exposition", body = String, content_type = "text/plain"),
+    )
+)]
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        telemetry::render(&state.metrics),
+    )
+}
+
+/// Reset execution time averages and percentiles
+#[utoipa::path(
+    post,
+    context_path = "/v1",
+    path = "/admin/stats/reset",
+    tag = "admin",
+    security(("api_key" = [])),
+    responses(
+        (status = 204, description = "Timing statistics reset"),
+        (status = 403, description = "Admin key missing"),
+    )
+)]
+async fn reset_engine_stats(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    require_admin(&state, &headers)?;
+    state.engine.reset_stats().await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Stop starting queued jobs until resumed
+#[utoipa::path(
+    post,
+    context_path = "/v1",
+    path = "/admin/pause",
+    tag = "admin",
+    security(("api_key" = [])),
+    responses(
+        (status = 204, description = "Workers stop starting queued jobs"),
+        (status = 403, description = "Admin key missing"),
+    )
+)]
+async fn pause_processing(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    require_admin(&state, &headers)?;
+    state.engine.pause();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Start running queued jobs again
+#[utoipa::path(
+    post,
+    context_path = "/v1",
+    path = "/admin/resume",
+    tag = "admin",
+    security(("api_key" = [])),
+    responses(
+        (status = 204, description = "Workers start queued jobs again"),
+        (status = 403, description = "Admin key missing"),
+    )
+)]
+async fn resume_processing(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    require_admin(&state, &headers)?;
+    state.engine.resume();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Get supported languages
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/languages",
+    tag = "info",
+    responses(
+        (status = 200, description = "Supported languages", body = Vec<LanguageInfo>),
+    )
+)]
+async fn get_supported_languages(State(state): State<AppState>) -> Json<Vec<LanguageInfo>> {
+    let language = |id: u32, name: &str, version: &str, compile_cmd: Option<&str>, run_cmd: &str| {
+        let limits = state.engine.language_limits(id);
+        let (key, aliases) = state.engine.language_names(id).unwrap_or_default();
+        LanguageInfo {
+            id,
+            key,
+            aliases,
+            name: name.to_string(),
+            version: version.to_string(),
+            compile_cmd: compile_cmd.map(String::from),
+            run_cmd: run_cmd.to_string(),
+            default_limits: limits.as_ref().map(|(defaults, _)| defaults.clone()),
+            max_limits: limits.map(|(_, ceilings)| ceilings),
+            max_source_bytes: state.engine.language_max_source_bytes(id),
+        }
+    };
+    
+    Json(vec![
+        language(71, "Python", "3.11", None, "python3 main.py"),
+        language(63, "JavaScript", "18.x", None, "node main.js"),
+        language(54, "C++", "GCC 11", Some("g++ -o main main.cpp"), "./main"),
+        language(50, "C", "GCC 11", Some("gcc -o main main.c"), "./main"),
+        language(62, "Java", "OpenJDK 17", Some("javac Main.java"), "java Main"),
+        language(60, "Go", "1.21", None, "go run main.go"),
+        language(73, "Rust", "1.70", Some("rustc main.rs -o main"), "./main"),
+    ])
+}
+
+/// Get the limit profiles a request may select with `profile`
+#[utoipa::path(
+    get,
+    context_path = "/v1",
+    path = "/profiles",
+    tag = "info",
+    responses(
+        (status = 200, description = "Profiles by name", body = BTreeMap<String, Profile>),
+    )
+)]
+async fn get_profiles(State(state): State<AppState>) -> Json<BTreeMap<String, Profile>> {
+    Json(state.engine.config().profiles.clone().into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Instant;
+    
+    /// Router over a fresh single-worker engine, which is returned too
+    pub(crate) async fn app(limiter: RateLimiter) -> (Router, Arc<ExecutionEngine>) {
+        app_with(EngineConfig::load().unwrap(), limiter).await
+    }
+    
+    /// Router over a single-worker engine with this configuration
+    pub(crate) async fn app_with(config: EngineConfig, limiter: RateLimiter) -> (Router, Arc<ExecutionEngine>) {
+        let state = state(config, limiter).await;
+        let engine = Arc::clone(&state.engine);
+        (router(state, &engine.config().server), engine)
+    }
+    
+    /// State over a single-worker engine with this configuration, as both servers share it
+    pub(crate) async fn state(config: EngineConfig, limiter: RateLimiter) -> AppState {
+        let engine = Arc::new(ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap());
+        let config = engine.config();
+        AppState {
+            engine: Arc::clone(&engine),
+            admin_api_key: config.server.admin_api_key.clone(),
+            limiter,
+            metrics: PrometheusBuilder::new().build_recorder().handle(),
+            uploads: UploadLimits::from_config(&config.server),
+        }
+    }
+    
+    /// Limiter that lets everything through
+    pub(crate) fn unlimited() -> RateLimiter {
+        RateLimiter::new(0, 1, 0)
+    }
+    
+    /// POST a JSON body from a local client, returning the status and parsed response
+    pub(crate) async fn post(app: &Router, uri: &str, headers: &[(&str, &str)], body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::post(uri).header(header::CONTENT_TYPE, "application/json");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let mut request = request.body(Body::from(body.to_string())).unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+    
+    /// GET from a local client, returning the status and parsed response
+    pub(crate) async fn get(app: &Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::get(uri).body(Body::empty()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+    
+    /// GET with extra headers, returning the raw response
+    pub(crate) async fn fetch(app: &Router, uri: &str, headers: &[(&str, &str)]) -> Response {
+        let mut request = Request::get(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let mut request = request.body(Body::empty()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        app.clone().oneshot(request).await.unwrap()
+    }
+    
+    /// Body-less request with an API key, returning only the status
+    async fn send(app: &Router, method: &str, uri: &str, key: &str) -> StatusCode {
+        let mut request = Request::builder().method(method).uri(uri).header(auth::API_KEY_HEADER, key).body(Body::empty()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+    
+    /// Id of a job that finished without running, so it has a result on any host
+    pub(crate) async fn cancelled_job(app: &Router, engine: &ExecutionEngine) -> String {
+        engine.pause();
+        let submission = serde_json::json!({"language": "python", "source_code": "print(1)"});
+        let (_, body) = post(app, "/v1/execute", &[], submission).await;
+        let id = body["id"].as_str().unwrap().to_string();
+        assert!(engine.cancel_execution(&id).await.unwrap());
+        id
+    }
+    
+    #[tokio::test]
+    async fn request_ids_are_echoed_and_kept_on_the_job() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        let submit = |request_id: Option<&'static str>| {
+            let mut request = Request::post("/v1/execute").header(header::CONTENT_TYPE, "application/json");
+            if let Some(id) = request_id {
+                request = request.header(REQUEST_ID_HEADER, id);
+            }
+            let body = serde_json::json!({"language": "python", "source_code": "print(1)"}).to_string();
+            let mut request = request.body(Body::from(body)).unwrap();
+            request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+            app.clone().oneshot(request)
+        };
+        
+        let response = submit(Some("trace-123")).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "trace-123");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let id = serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()["id"].as_str().unwrap().to_string();
+        assert_eq!(engine.lookup(&id).await.unwrap().unwrap().request_id.as_deref(), Some("trace-123"));
+        
+        // Requests without one are given a fresh id
+        let response = submit(None).await.unwrap();
+        let assigned = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(Uuid::parse_str(assigned).is_ok(), "{}", assigned);
+        let response = fetch(&app, "/health", &[]).await;
+        assert_ne!(response.headers()[REQUEST_ID_HEADER], assigned);
+    }
+    
+    #[tokio::test]
+    async fn a_draining_engine_turns_away_traffic() {
+        let (app, engine) = app(unlimited()).await;
+        engine.shutdown(Duration::ZERO).await;
+        
+        let (status, body) = get(&app, "/health").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "draining");
+        let (status, _) = post(&app, "/v1/execute", &[], serde_json::json!({"language": "python", "source_code": "print(1)"})).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+    
+    #[tokio::test]
+    async fn stats_break_submissions_down_by_language_unless_asked_not_to() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        for language in ["python", "c", "python"] {
+            let (status, body) = post(&app, "/v1/execute", &[], serde_json::json!({"language": language, "source_code": "main"})).await;
+            assert_eq!(status, StatusCode::OK, "{}", body);
+        }
+        
+        let (_, stats) = get(&app, "/v1/stats").await;
+        assert_eq!(stats["by_language"]["python"]["submissions"], 2);
+        assert_eq!(stats["by_language"]["c"]["submissions"], 1);
+        // Languages nobody submitted are left out
+        assert!(stats["by_language"].get("java").is_none());
+        
+        let (_, stats) = get(&app, "/v1/stats?detail=false").await;
+        assert!(stats.get("by_language").is_none(), "{}", stats);
+    }
+    
+    #[tokio::test]
+    async fn runtime_settings_are_changed_by_admins_only() {
+        let mut config = EngineConfig::load().unwrap();
+        config.server.admin_api_key = Some("admin-secret".to_string());
+        let (app, engine) = app_with(config, unlimited()).await;
+        let put = |key: &'static str, body: serde_json::Value| {
+            let mut request = Request::put("/v1/admin/config")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(auth::API_KEY_HEADER, key)
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+            app.clone().oneshot(request)
+        };
+        
+        let response = put("other", serde_json::json!({"max_queue_depth": 5})).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = put("admin-secret", serde_json::json!({"disabled_languages": ["cobol"]})).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        
+        let response = put("admin-secret", serde_json::json!({"max_queue_depth": 5})).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let settings: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(settings["max_queue_depth"], 5);
+        assert_eq!(engine.capacity().max_queue_depth, 5);
+    }
+    
+    #[tokio::test]
+    async fn language_aliases_are_stored_under_their_key() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        let (status, body) = post(&app, "/v1/execute", &[], serde_json::json!({"language": "Python3.11", "source_code": "print(1)"})).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let job = engine.lookup(body["id"].as_str().unwrap()).await.unwrap().unwrap();
+        assert_eq!(job.request.language, "python");
+        
+        let (status, body) = post(&app, "/v1/execute", &[], serde_json::json!({"language": "pyhton", "source_code": "print(1)"})).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["details"]["suggestions"], serde_json::json!(["python"]));
+        assert!(body["details"]["supported_languages"].as_array().unwrap().contains(&"rust".into()));
+    }
+    
+    #[tokio::test]
+    async fn executions_are_listed_by_metadata() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        for user in ["42", "7"] {
+            let submission = serde_json::json!({"language": "python", "source_code": "print(1)", "metadata": {"user_id": user}});
+            let (status, body) = post(&app, "/v1/execute", &[], submission).await;
+            assert_eq!(status, StatusCode::OK, "{}", body);
+        }
+        
+        let (status, body) = get(&app, "/v1/executions?metadata.user_id=42").await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let listed = body.as_array().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0]["metadata"], serde_json::json!({"user_id": "42"}));
+        
+        let (status, body) = get(&app, "/v1/executions?limit=0").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+    }
+    
+    #[tokio::test]
+    async fn duplicate_id_of_an_unfinished_job_is_a_conflict() {
+        let (app, engine) = app(unlimited()).await;
+        // Paused, so the first job is still queued when the second arrives
+        engine.pause();
+        
+        let first = serde_json::json!({"id": "test-1", "language": "python", "source_code": "print('first')"});
+        let (status, body) = post(&app, "/v1/execute", &[], first).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["id"], "test-1");
+        
+        let second = serde_json::json!({"id": "test-1", "language": "python", "source_code": "print('second')"});
+        let (status, body) = post(&app, "/v1/execute", &[], second).await;
+        assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+        
+        // The first job is untouched
+        let status = engine.get_status("test-1").await.unwrap().unwrap();
+        assert_eq!(status.status, ExecutionState::Queued);
+        if std::process::Command::new("python3").arg("--version").output().is_err() {
+            eprintln!("skipped running the job: needs python3");
+            return;
+        }
+        engine.resume();
+        engine.wait_for_completion("test-1", Duration::from_secs(30)).await.unwrap();
+        let result = engine.get_result("test-1").await.unwrap().unwrap();
+        assert_eq!(result.stdout.as_deref(), Some("first\n"));
+    }
+    
+    #[tokio::test]
+    async fn omitted_ids_are_generated() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        
+        let submission = serde_json::json!({"language": "python", "source_code": "print(1)"});
+        let (_, first) = post(&app, "/v1/execute", &[], submission.clone()).await;
+        let (_, second) = post(&app, "/v1/execute", &[], submission).await;
+        let (first, second) = (first["id"].as_str().unwrap(), second["id"].as_str().unwrap());
+        assert!(Uuid::parse_str(first).is_ok(), "{}", first);
+        assert_ne!(first, second);
+    }
+    
+    #[tokio::test]
+    async fn idempotent_replays_are_not_rate_limited() {
+        let (app, engine) = app(RateLimiter::new(60, 1, 0)).await;
+        engine.pause();
+        
+        let submission = serde_json::json!({"language": "python", "source_code": "print(1)"});
+        let key = [("idempotency-key", "retry-me")];
+        let (status, first) = post(&app, "/v1/execute", &key, submission.clone()).await;
+        assert_eq!(status, StatusCode::OK, "{}", first);
+        
+        // The bucket is empty now, yet the retry is answered
+        let (status, replay) = post(&app, "/v1/execute", &key, submission.clone()).await;
+        assert_eq!(status, StatusCode::OK, "{}", replay);
+        assert_eq!(replay["id"], first["id"]);
+        let (status, _) = post(&app, "/v1/execute", &[], submission).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    }
+    
+    #[tokio::test]
+    async fn idempotency_keys_reused_for_another_request_are_refused() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        
+        // cHJpbnQoMSk= is print(1) in base64
+        let submission = serde_json::json!({"language": "python", "source_code": "cHJpbnQoMSk="});
+        let (status, first) = post(&app, "/v1/execute?base64_encoded=true", &[], serde_json::json!({
+            "language": "python", "source_code": "cHJpbnQoMSk=", "idempotencyKey": "once",
+        })).await;
+        assert_eq!(status, StatusCode::OK, "{}", first);
+        
+        // Either spelling of the key, and the body flag in place of the query, is the same request
+        let (status, replay) = post(&app, "/v1/execute", &[("idempotency-key", "once")], serde_json::json!({
+            "language": "python", "source_code": "cHJpbnQoMSk=", "base64_encoded": true,
+        })).await;
+        assert_eq!((status, &replay["id"]), (StatusCode::OK, &first["id"]));
+        
+        // The same body read as plain text is not
+        let (status, body) = post(&app, "/v1/execute", &[("idempotency-key", "once")], submission).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body["error"].as_str().unwrap().contains("different request"), "{}", body);
+        let (status, _) = post(&app, "/v1/execute?base64_encoded=true", &[("idempotency-key", "once")], serde_json::json!({
+            "language": "python", "source_code": "cHJpbnQoMik=",
+        })).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    
+    #[tokio::test]
+    async fn unknown_api_keys_share_their_address_bucket() {
+        let (app, engine) = app(RateLimiter::new(60, 1, 0).with_api_keys(["known".to_string()])).await;
+        engine.pause();
+        
+        let submission = serde_json::json!({"language": "python", "source_code": "print(1)"});
+        let (status, _) = post(&app, "/v1/execute", &[("x-api-key", "made-up-1")], submission.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = post(&app, "/v1/execute", &[("x-api-key", "made-up-2")], submission.clone()).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        let (status, _) = post(&app, "/v1/execute", &[("x-api-key", "known")], submission).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    
+    #[tokio::test]
+    async fn result_fields_select_what_is_returned() {
+        let (app, engine) = app(unlimited()).await;
+        let id = cancelled_job(&app, &engine).await;
+        
+        let (status, body) = get(&app, &format!("/v1/result/{}?fields=status,failureReason", id)).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let keys: Vec<&String> = body.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["failure_reason", "status"]);
+        assert_eq!(body["status"], "cancelled");
+        
+        // Booleans are read leniently
+        for flag in ["No", "1", "TRUE"] {
+            let (status, body) = get(&app, &format!("/v1/result/{}?include_output={}", id, flag)).await;
+            assert_eq!(status, StatusCode::OK, "{}: {}", flag, body);
+        }
+    }
+    
+    #[tokio::test]
+    async fn unusable_result_parameters_are_refused() {
+        let (app, engine) = app(unlimited()).await;
+        let id = cancelled_job(&app, &engine).await;
+        
+        let (status, body) = get(&app, &format!("/v1/result/{}?include_output=ture&fields=status,stdot", id)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let fields = &body["fields"];
+        assert_eq!((fields[0]["field"].as_str(), fields[1]["field"].as_str()), (Some("include_output"), Some("fields")));
+        assert!(fields[1]["constraint"].as_str().unwrap().contains("valid fields: id, status,"), "{}", body);
+    }
+    
+    #[tokio::test]
+    async fn large_outputs_are_downloaded_in_ranges() {
+        if std::process::Command::new("python3").arg("--version").output().is_err() {
+            eprintln!("skipped: needs python3");
+            return;
+        }
+        let spill = tempfile::tempdir().unwrap();
+        let mut config = EngineConfig::load().unwrap();
+        config.retention.spill_dir = spill.path().to_path_buf();
+        config.retention.output_spill_bytes = 1024;
+        let (app, engine) = app_with(config, unlimited()).await;
+        
+        let submission = serde_json::json!({"language": "python", "source_code": "print('x' * 4095)"});
+        let (_, body) = post(&app, "/v1/execute", &[], submission).await;
+        let id = body["id"].as_str().unwrap().to_string();
+        engine.wait_for_completion(&id, Duration::from_secs(30)).await.unwrap();
+        
+        let (_, result) = get(&app, &format!("/v1/result/{}", id)).await;
+        assert!(result["stdout"].is_null(), "{}", result);
+        assert_eq!(result["stdout_bytes"], 4096);
+        let url = result["stdout_url"].as_str().unwrap().to_string();
+        
+        let whole = fetch(&app, &url, &[]).await;
+        assert_eq!(whole.status(), StatusCode::OK);
+        assert_eq!(whole.headers()[header::CONTENT_LENGTH], "4096");
+        let part = fetch(&app, &url, &[("range", "bytes=4090-")]).await;
+        assert_eq!(part.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(part.headers()[header::CONTENT_RANGE], "bytes 4090-4095/4096");
+        let bytes = axum::body::to_bytes(part.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"xxxxx\n");
+        assert_eq!(fetch(&app, &url, &[("range", "bytes=5000-")]).await.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        
+        // The file goes with its job
+        engine.delete_execution(&id).await.unwrap();
+        assert_eq!(fetch(&app, &url, &[]).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(std::fs::read_dir(spill.path()).unwrap().count(), 0);
+    }
+    
+    /// POST a multipart body of (name, filename, content) parts
+    async fn post_multipart(app: &Router, parts: &[(&str, Option<&str>, &[u8])]) -> (StatusCode, serde_json::Value) {
+        let mut body = Vec::new();
+        for (name, file_name, content) in parts {
+            let file_name = file_name.map(|file_name| format!("; filename=\"{}\"", file_name)).unwrap_or_default();
+            body.extend_from_slice(format!("--boundary\r\nContent-Disposition: form-data; name=\"{}\"{}\r\n\r\n", name, file_name).as_bytes());
+            body.extend_from_slice(content);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(b"--boundary--\r\n");
+        let mut request = Request::post("/v1/execute")
+            .header(header::CONTENT_TYPE, "multipart/form-data; boundary=boundary")
+            .body(Body::from(body))
+            .unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+    
+    #[tokio::test]
+    async fn a_multipart_stdin_part_becomes_binary_stdin() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        
+        let request = br#"{"language": "python"}"#;
+        let binary = [0u8, 0xff, b'\n', 0x80];
+        let (status, body) = post_multipart(&app, &[("request", None, request), ("source", None, b"print(1)"), ("stdin", None, &binary)]).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let job = engine.lookup(body["id"].as_str().unwrap()).await.unwrap().unwrap();
+        assert_eq!(job.request.stdin_bytes(), Some(binary.to_vec()));
+        
+        // Input from both the request and a part is ambiguous
+        let request = br#"{"language": "python", "source_code": "print(1)", "stdin": "text"}"#;
+        let (status, body) = post_multipart(&app, &[("request", None, request), ("stdin", Some("input.bin"), &binary)]).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+    }
+    
+    #[tokio::test]
+    async fn stdin_and_stdin_base64_together_are_refused() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        
+        let submission = serde_json::json!({"language": "python", "source_code": "print(1)", "stdin": "1", "stdin_base64": "MQ=="});
+        let (status, body) = post(&app, "/v1/execute", &[], submission).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "stdin_base64");
+    }
+    
+    #[tokio::test]
+    async fn invalid_base64_submissions_are_refused() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        
+        let submission = serde_json::json!({"language": "python", "source_code": "print(1)", "base64_encoded": true});
+        let (status, body) = post(&app, "/v1/execute", &[], submission).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "source_code");
+        
+        // The query parameter applies to the whole body
+        let submission = serde_json::json!({"language": "python", "source_code": "cHJpbnQoMSk=", "stdin": "not base64!"});
+        let (status, body) = post(&app, "/v1/execute?base64_encoded=true", &[], submission).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "stdin");
+    }
+    
+    #[tokio::test]
+    async fn admins_reorder_and_drop_queued_jobs() {
+        let mut config = EngineConfig::load().unwrap();
+        config.server.admin_api_key = Some("admin-secret".to_string());
+        let (app, engine) = app_with(config, unlimited()).await;
+        engine.pause();
+        let mut ids = Vec::new();
+        for n in 1..=3 {
+            let (_, body) = post(&app, "/v1/execute", &[], serde_json::json!({"language": "python", "source_code": format!("print({})", n)})).await;
+            ids.push(body["id"].as_str().unwrap().to_string());
+        }
+        let admin = [(auth::API_KEY_HEADER, "admin-secret")];
+        let queued = || async {
+            let response = fetch(&app, "/v1/admin/queue", &admin).await;
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let queue: Vec<serde_json::Value> = serde_json::from_slice(&bytes).unwrap();
+            queue.iter().map(|job| job["id"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(queued().await, ids);
+        assert_eq!(fetch(&app, "/v1/admin/queue", &[]).await.status(), StatusCode::FORBIDDEN);
+        
+        let promote = format!("/v1/admin/queue/{}/promote", ids[2]);
+        assert_eq!(send(&app, "POST", &promote, "other").await, StatusCode::FORBIDDEN);
+        assert_eq!(send(&app, "POST", &promote, "admin-secret").await, StatusCode::NO_CONTENT);
+        assert_eq!(queued().await, [ids[2].as_str(), &ids[0], &ids[1]]);
+        
+        let drop = format!("/v1/admin/queue/{}", ids[0]);
+        assert_eq!(send(&app, "DELETE", &drop, "admin-secret").await, StatusCode::NO_CONTENT);
+        assert_eq!(send(&app, "DELETE", &drop, "admin-secret").await, StatusCode::NOT_FOUND);
+        assert_eq!(queued().await, [ids[2].as_str(), &ids[1]]);
+        let dropped = engine.lookup(&ids[0]).await.unwrap().unwrap();
+        assert_eq!(dropped.status, ExecutionState::Cancelled);
+    }
+    
+    #[tokio::test]
+    async fn admins_pause_and_resume_processing() {
+        let mut config = EngineConfig::load().unwrap();
+        config.server.admin_api_key = Some("admin-secret".to_string());
+        config.paused_submissions = labforcode_engine::config::PausedSubmissions::Reject;
+        let (app, engine) = app_with(config, unlimited()).await;
+        
+        assert_eq!(send(&app, "POST", "/v1/admin/pause", "other").await, StatusCode::FORBIDDEN);
+        assert!(!engine.is_paused());
+        assert_eq!(send(&app, "POST", "/v1/admin/pause", "admin-secret").await, StatusCode::NO_CONTENT);
+        
+        let (_, health) = get(&app, "/health").await;
+        assert_eq!(health["paused"], true);
+        let (_, stats) = get(&app, "/v1/stats").await;
+        assert_eq!(stats["paused"], true);
+        let (status, _) = post(&app, "/v1/execute", &[], serde_json::json!({"language": "python", "source_code": "print(1)"})).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        
+        assert_eq!(send(&app, "POST", "/v1/admin/resume", "admin-secret").await, StatusCode::NO_CONTENT);
+        let (_, health) = get(&app, "/health").await;
+        assert_eq!(health["paused"], false);
+    }
+    
+    #[tokio::test]
+    async fn scheduled_jobs_report_their_run_at() {
+        let (app, _) = app(unlimited()).await;
+        let run_at = chrono::Utc::now() + chrono::Duration::hours(1);
+        let submission = serde_json::json!({"language": "python", "source_code": "print(1)", "run_at": run_at.to_rfc3339()});
+        let (status, body) = post(&app, "/v1/execute", &[], submission).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        
+        let (_, status) = get(&app, &format!("/v1/status/{}", body["id"].as_str().unwrap())).await;
+        assert_eq!((&status["status"], &status["status_code"]), (&serde_json::json!("scheduled"), &serde_json::json!(1)), "{}", status);
+        let reported: chrono::DateTime<chrono::Utc> = status["run_at"].as_str().unwrap().parse().unwrap();
+        assert_eq!(reported, run_at);
+    }
+    
+    #[tokio::test]
+    async fn reruns_need_a_finished_execution() {
+        let (app, engine) = app(unlimited()).await;
+        let (status, _) = post(&app, "/v1/executions/missing/rerun", &[], serde_json::json!({})).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        
+        engine.pause();
+        let (_, queued) = post(&app, "/v1/execute", &[], serde_json::json!({"language": "python", "source_code": "print(1)"})).await;
+        let (status, _) = post(&app, &format!("/v1/executions/{}/rerun", queued["id"].as_str().unwrap()), &[], serde_json::json!({})).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        
+        let parent = cancelled_job(&app, &engine).await;
+        let rerun = format!("/v1/executions/{}/rerun", parent);
+        let (status, _) = post(&app, &rerun, &[], serde_json::json!({"wall_time_limit": -1.0})).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, body) = post(&app, &rerun, &[], serde_json::json!({"stdin": "2"})).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        
+        let child = engine.lookup(body["id"].as_str().unwrap()).await.unwrap().unwrap();
+        assert_ne!(child.id, parent);
+        assert_eq!(child.parent_id.as_deref(), Some(parent.as_str()));
+        assert_eq!((child.request.source_code.as_str(), child.request.stdin.as_deref()), ("print(1)", Some("2")));
+    }
+    
+    #[tokio::test]
+    async fn reruns_with_new_stdin_give_new_output() {
+        if std::process::Command::new("python3").arg("--version").output().is_err() {
+            eprintln!("skipped: needs python3");
+            return;
+        }
+        let (app, engine) = app(unlimited()).await;
+        let output = |id: String| {
+            let engine = Arc::clone(&engine);
+            async move {
+                engine.wait_for_completion(&id, Duration::from_secs(30)).await.unwrap();
+                engine.get_result(&id).await.unwrap().unwrap().stdout.clone().unwrap()
+            }
+        };
+        
+        let submission = serde_json::json!({"language": "python", "source_code": "print(int(input()) * 2)", "stdin": "21"});
+        let (_, body) = post(&app, "/v1/execute", &[], submission).await;
+        let parent = body["id"].as_str().unwrap().to_string();
+        assert_eq!(output(parent.clone()).await, "42\n");
+        
+        let (_, body) = post(&app, &format!("/v1/executions/{}/rerun", parent), &[], serde_json::json!({"stdin": "50"})).await;
+        assert_eq!(output(body["id"].as_str().unwrap().to_string()).await, "100\n");
+        // The parent keeps its own input and result
+        assert_eq!(output(parent).await, "42\n");
+    }
+    
+    #[tokio::test]
+    async fn results_echo_the_limits_left_after_clamping() {
+        if std::process::Command::new("python3").arg("--version").output().is_err() {
+            eprintln!("skipped: needs python3");
+            return;
+        }
+        let mut config = EngineConfig::load().unwrap();
+        config.limits.ceilings.mode = validation::LimitsMode::Clamp;
+        config.limits.ceilings.max_wall_time = 12.0;
+        let (app, engine) = app_with(config, unlimited()).await;
+        
+        let submission = serde_json::json!({"language": "python", "source_code": "print(1)", "wall_time_limit": 60.0, "enable_network": false, "number_of_runs": 2});
+        let (status, body) = post(&app, "/v1/execute", &[], submission).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let id = body["id"].as_str().unwrap();
+        engine.wait_for_completion(id, Duration::from_secs(30)).await.unwrap();
+        
+        let (_, result) = get(&app, &format!("/v1/result/{}", id)).await;
+        assert_eq!(result["limits"]["wall_time"], 12.0, "{}", result["limits"]);
+        assert_eq!(result["options"]["number_of_runs"], 2);
+        assert_eq!(result["options"]["enable_network"], false);
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn stats_gauges_stay_within_the_totals_under_load() {
+        if std::process::Command::new("python3").arg("--version").output().is_err() {
+            eprintln!("skipped: needs python3");
+            return;
+        }
+        let (app, engine) = app(unlimited()).await;
+        let workers = engine.capacity().max_concurrent_executions as u64;
+        let done = Arc::new(AtomicBool::new(false));
+        let poller = {
+            let (app, done) = (app.clone(), Arc::clone(&done));
+            tokio::spawn(async move {
+                let mut polls = 0;
+                while !done.load(Ordering::SeqCst) {
+                    let (_, stats) = get(&app, "/v1/stats").await;
+                    let gauge = |name: &str| stats[name].as_u64().unwrap_or_else(|| panic!("{} is not a count: {}", name, stats));
+                    let (total, queued, active) = (gauge("total_executions"), gauge("queued_executions"), gauge("active_executions"));
+                    assert!(active <= workers, "{} active on {} workers", active, workers);
+                    assert!(queued + active <= total, "{} queued and {} active of {}", queued, active, total);
+                    polls += 1;
+                    tokio::task::yield_now().await;
+                }
+                polls
+            })
+        };
+        
+        // Eight clients submit five jobs each, cancelling some while queued or running
+        let clients: Vec<_> = (0..8).map(|_| {
+            let app = app.clone();
+            tokio::spawn(async move {
+                let mut ids = Vec::new();
+                for n in 0..5 {
+                    let submission = serde_json::json!({"language": "python", "source_code": format!("print({})", n)});
+                    let (status, body) = post(&app, "/v1/execute", &[], submission).await;
+                    assert_eq!(status, StatusCode::OK, "{}", body);
+                    let id = body["id"].as_str().unwrap().to_string();
+                    if n % 3 == 0 {
+                        send(&app, "DELETE", &format!("/v1/cancel/{}", id), "").await;
+                    }
+                    ids.push(id);
+                }
+                ids
+            })
+        }).collect();
+        for client in clients {
+            for id in client.await.unwrap() {
+                engine.wait_for_completion(&id, Duration::from_secs(60)).await.unwrap();
+            }
+        }
+        
+        // A worker frees its slot just after publishing the result
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while engine.get_stats().await.unwrap().active_executions > 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        done.store(true, Ordering::SeqCst);
+        assert!(poller.await.unwrap() > 0);
+        
+        let (_, stats) = get(&app, "/v1/stats").await;
+        assert_eq!(stats["total_executions"], 40);
+        assert_eq!((&stats["queued_executions"], &stats["active_executions"]), (&0.into(), &0.into()), "{}", stats);
+    }
+    
+    #[tokio::test]
+    async fn exhausted_quotas_answer_429_for_that_key_only() {
+        let mut config = EngineConfig::load().unwrap();
+        config.server.admin_api_key = Some("admin-secret".to_string());
+        let keys = ["tenant-a", "tenant-b"].map(String::from);
+        let (app, engine) = app_with(config, unlimited().with_api_keys(keys)).await;
+        engine.pause();
+        
+        let quota = serde_json::json!({"daily": {"submissions": 1}});
+        let mut request = Request::put("/v1/admin/quotas/tenant-a")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(auth::API_KEY_HEADER, "admin-secret")
+            .body(Body::from(quota.to_string()))
+            .unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+        
+        let submission = serde_json::json!({"language": "python", "source_code": "print(1)"});
+        let tenant_a = [(auth::API_KEY_HEADER, "tenant-a")];
+        let (status, _) = post(&app, "/v1/execute", &tenant_a, submission.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = post(&app, "/v1/execute", &tenant_a, submission.clone()).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body["details"]["code"], "quota_exceeded");
+        assert_eq!((&body["details"]["period"], &body["details"]["counter"]), (&"daily".into(), &"submissions".into()));
+        
+        let (status, _) = post(&app, "/v1/execute", &[(auth::API_KEY_HEADER, "tenant-b")], submission).await;
+        assert_eq!(status, StatusCode::OK, "other keys are unaffected");
+        
+        let response = fetch(&app, "/v1/usage", &tenant_a).await;
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let usage: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(usage["daily"]["used"]["submissions"], 1);
+        assert_eq!(usage["daily"]["remaining"]["submissions"], 0);
+    }
+    
+    #[tokio::test]
+    async fn execution_events_are_listed_oldest_first() {
+        let (app, engine) = app(unlimited()).await;
+        let id = cancelled_job(&app, &engine).await;
+        
+        let (status, events) = get(&app, &format!("/v1/executions/{}/events", id)).await;
+        assert_eq!(status, StatusCode::OK);
+        let kinds: Vec<&str> = events.as_array().unwrap().iter().map(|event| event["event"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["submitted", "cancelled"]);
+        
+        assert_eq!(send(&app, "GET", "/v1/executions/unknown/events", "").await, StatusCode::NOT_FOUND);
+    }
+    
+    /// Serve the router on a local port, as `serve` does, and return its base URL
+    async fn listen(app: Router) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+    
+    #[tokio::test]
+    async fn curl_style_form_uploads_run_end_to_end() {
+        if std::process::Command::new("python3").arg("--version").output().is_err() {
+            eprintln!("skipped: needs python3");
+            return;
+        }
+        let (app, _) = app(unlimited()).await;
+        let base = listen(app).await;
+        let client = reqwest::Client::new();
+        
+        // curl -F 'request={"language":"python"}' -F source=@main.py -F data/input.txt=@input.txt $BASE/v1/execute
+        let form = reqwest::multipart::Form::new()
+            .text("request", r#"{"language": "python"}"#)
+            .part("source", reqwest::multipart::Part::bytes(b"print(open('data/input.txt').read().upper())".to_vec()).file_name("main.py"))
+            .part("data/input.txt", reqwest::multipart::Part::bytes(b"hello".to_vec()).file_name("data/input.txt"));
+        let response = client.post(format!("{}/v1/execute", base)).multipart(form).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), StatusCode::OK.as_u16());
+        let submitted: serde_json::Value = response.json().await.unwrap();
+        
+        let result: serde_json::Value = client.get(format!("{}/v1/result/{}?wait=20", base, submitted["id"].as_str().unwrap()))
+            .send().await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(result["status"], "completed", "{}", result);
+        assert_eq!(result["stdout"], "HELLO\n");
+    }
+    
+    #[tokio::test]
+    async fn form_uploads_outside_the_workdir_or_over_the_limits_are_refused() {
+        let config = EngineConfig {
+            server: config::ServerConfig { multipart_max_part_bytes: 1024, multipart_max_total_bytes: 4096, ..Default::default() },
+            ..EngineConfig::load().unwrap()
+        };
+        let (app, engine) = app_with(config, unlimited()).await;
+        engine.pause();
+        let base = listen(app).await;
+        let client = reqwest::Client::new();
+        let submit = |files: Vec<(&'static str, usize)>| {
+            let mut form = reqwest::multipart::Form::new()
+                .text("request", r#"{"language": "python", "source_code": "print(1)"}"#);
+            for (name, size) in files {
+                form = form.part(name, reqwest::multipart::Part::bytes(vec![b'x'; size]).file_name(name));
+            }
+            client.post(format!("{}/v1/execute", base)).multipart(form).send()
+        };
+        
+        let escaping = submit(vec![("../outside.txt", 1)]).await.unwrap();
+        assert_eq!(escaping.status().as_u16(), StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+        assert!(escaping.text().await.unwrap().contains("../outside.txt"));
+        
+        let large_part = submit(vec![("big.txt", 2000)]).await.unwrap();
+        assert_eq!(large_part.status().as_u16(), StatusCode::PAYLOAD_TOO_LARGE.as_u16());
+        
+        let large_total = submit(vec![("a.txt", 1000), ("b.txt", 1000), ("c.txt", 1000), ("d.txt", 1000), ("e.txt", 1000)]).await.unwrap();
+        assert_eq!(large_total.status().as_u16(), StatusCode::PAYLOAD_TOO_LARGE.as_u16());
+        
+        let fits = submit(vec![("a.txt", 1000), ("b.txt", 1000)]).await.unwrap();
+        assert_eq!(fits.status().as_u16(), StatusCode::OK.as_u16());
+    }
+    
+    #[test]
+    fn plain_text_is_served_only_when_preferred_over_json() {
+        let accepts = |accept: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, HeaderValue::from_str(accept).unwrap());
+            prefers_plain_text(&headers)
+        };
+        assert!(accepts("text/plain"));
+        assert!(accepts("Text/Plain; charset=utf-8"));
+        assert!(accepts("text/plain, application/json"));
+        assert!(!accepts("application/json, text/plain"));
+        assert!(!accepts("*/*"));
+        assert!(!prefers_plain_text(&HeaderMap::new()));
+    }
+    
+    #[tokio::test]
+    async fn plain_text_statuses_follow_the_outcome() {
+        let (app, engine) = app(unlimited()).await;
+        let id = cancelled_job(&app, &engine).await;
+        let mut result = ExecutionResult::clone(&engine.get_result(&id).await.unwrap().unwrap());
+        result.stdout = Some("out\n".to_string());
+        result.exit_code = Some(3);
+        result.time = Some(0.25);
+        result.memory = Some(2048 * 1024);
+        
+        let expected = [
+            (ExecutionState::Queued, StatusCode::ACCEPTED),
+            (ExecutionState::Running, StatusCode::ACCEPTED),
+            (ExecutionState::Completed, StatusCode::OK),
+            (ExecutionState::RuntimeError, StatusCode::FAILED_DEPENDENCY),
+            (ExecutionState::CompilationError, StatusCode::FAILED_DEPENDENCY),
+            (ExecutionState::WrongAnswer, StatusCode::FAILED_DEPENDENCY),
+            (ExecutionState::TimeLimitExceeded, StatusCode::FAILED_DEPENDENCY),
+            (ExecutionState::Cancelled, StatusCode::FAILED_DEPENDENCY),
+            (ExecutionState::InternalError, StatusCode::INTERNAL_SERVER_ERROR),
+        ];
+        for (state, status) in expected {
+            result.status = state.clone();
+            
+            let response = plain_text_result(Arc::new(result.clone()), false, None);
+            assert_eq!(response.status(), status, "{:?}", state);
+            let headers = response.headers();
+            assert_eq!(headers["x-status"], state.as_str());
+            assert_eq!(headers["x-exit-code"], "3");
+            assert_eq!(headers["x-time-ms"], "250");
+            assert_eq!(headers["x-memory-kb"], "2048");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(&body[..], b"out\n");
+        }
+        
+        // Output spilled to disk is served from there
+        let response = plain_text_result(Arc::new(result.clone()), false, Some(b"spilled".to_vec()));
+        assert_eq!(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()[..], b"spilled");
+        
+        // Base64 submissions get their stdout decoded
+        result.stdout = Some("b3V0Cg==".to_string());
+        let response = plain_text_result(Arc::new(result.clone()), true, None);
+        assert_eq!(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()[..], b"out\n");
+        
+        // Plain stdout is borrowed from the shared result, which the body keeps alive
+        let shared = Arc::new(result);
+        let response = plain_text_result(Arc::clone(&shared), false, None);
+        assert_eq!(Arc::strong_count(&shared), 2);
+        assert_eq!(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()[..], b"b3V0Cg==");
+        assert_eq!(Arc::strong_count(&shared), 1);
+    }
+    
+    #[tokio::test]
+    async fn plain_text_results_of_queued_and_failed_jobs() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        let (_, queued) = post(&app, "/v1/execute", &[], serde_json::json!({"language": "python", "source_code": "print(1)"})).await;
+        let url = format!("/v1/result/{}", queued["id"].as_str().unwrap());
+        
+        let response = fetch(&app, &url, &[("accept", "text/plain")]).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(response.headers()["x-status"], "queued");
+        assert_eq!(response.headers()[header::VARY], "accept");
+        let response = fetch(&app, &url, &[]).await;
+        assert!(response.headers()[header::CONTENT_TYPE].to_str().unwrap().starts_with("application/json"));
+        
+        if std::process::Command::new("python3").arg("--version").output().is_err() {
+            eprintln!("skipped: needs python3");
+            return;
+        }
+        engine.resume();
+        let source = "import sys\nprint('partial')\nsys.exit(3)";
+        let (_, failing) = post(&app, "/v1/execute", &[], serde_json::json!({"language": "python", "source_code": source})).await;
+        let response = fetch(&app, &format!("/v1/result/{}?wait=20", failing["id"].as_str().unwrap()), &[("accept", "text/plain")]).await;
+        assert_eq!(response.status(), StatusCode::FAILED_DEPENDENCY);
+        assert_eq!(response.headers()["x-status"], "runtime_error");
+        assert_eq!(response.headers()["x-exit-code"], "3");
+        assert!(response.headers().contains_key("x-time-ms"));
+        assert!(response.headers().contains_key("x-memory-kb"));
+        assert_eq!(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()[..], b"partial\n");
+    }
+    
+    /// POST raw bytes with these headers, returning the raw response
+    async fn post_bytes(app: &Router, uri: &str, headers: &[(&str, &str)], body: Vec<u8>) -> Response {
+        let mut request = Request::post(uri).header(header::CONTENT_TYPE, "application/json");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let mut request = request.body(Body::from(body)).unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        app.clone().oneshot(request).await.unwrap()
+    }
+    
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+    
+    #[tokio::test]
+    async fn responses_are_compressed_as_accept_encoding_allows() {
+        let (app, engine) = app(unlimited()).await;
+        let id = cancelled_job(&app, &engine).await;
+        let encoding = |response: &Response| response.headers().get(header::CONTENT_ENCODING).map(|value| value.to_str().unwrap().to_string());
+        
+        // The language list is well over the 1KB threshold
+        let languages = fetch(&app, "/v1/languages", &[]).await;
+        let plain = axum::body::to_bytes(languages.into_body(), usize::MAX).await.unwrap();
+        assert!(plain.len() > 1024);
+        
+        let gzipped = fetch(&app, "/v1/languages", &[("accept-encoding", "gzip")]).await;
+        assert_eq!(encoding(&gzipped).as_deref(), Some("gzip"));
+        let compressed = axum::body::to_bytes(gzipped.into_body(), usize::MAX).await.unwrap();
+        let mut inflated = Vec::new();
+        std::io::Read::read_to_end(&mut flate2::read::GzDecoder::new(&compressed[..]), &mut inflated).unwrap();
+        assert_eq!(inflated, plain);
+        
+        let brotli = fetch(&app, "/v1/languages", &[("accept-encoding", "br")]).await;
+        assert_eq!(encoding(&brotli).as_deref(), Some("br"));
+        let preferred = fetch(&app, "/v1/languages", &[("accept-encoding", "gzip;q=0.5, br;q=1.0")]).await;
+        assert_eq!(encoding(&preferred).as_deref(), Some("br"));
+        assert_eq!(encoding(&fetch(&app, "/v1/languages", &[("accept-encoding", "identity")]).await), None);
+        
+        // Small bodies and event streams go out as they are
+        assert_eq!(encoding(&fetch(&app, &format!("/v1/status/{}", id), &[("accept-encoding", "gzip")]).await), None);
+        let events = fetch(&app, &format!("/v1/status/{}/events", id), &[("accept-encoding", "gzip")]).await;
+        assert!(events.headers()[header::CONTENT_TYPE].to_str().unwrap().starts_with("text/event-stream"));
+        assert_eq!(encoding(&events), None);
+    }
+    
+    #[tokio::test]
+    async fn gzipped_request_bodies_are_inflated() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        
+        let submission = serde_json::json!({"language": "python", "source_code": "print(1)"});
+        let response = post_bytes(&app, "/v1/execute", &[("content-encoding", "gzip")], gzip(submission.to_string().as_bytes())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let batch = serde_json::json!({
+            "submissions": (0..20).map(|index| serde_json::json!({"language_id": 71, "source_code": format!("print({})", index)})).collect::<Vec<_>>(),
+        });
+        let response = post_bytes(&app, "/submissions/batch", &[("content-encoding", "gzip")], gzip(batch.to_string().as_bytes())).await;
+        assert!(response.status().is_success(), "{}", response.status());
+        let body: serde_json::Value = serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        let tokens = body.as_array().unwrap();
+        assert_eq!(tokens.len(), 20);
+        assert!(tokens.iter().all(|token| token["token"].is_string()), "{}", body);
+        
+        let response = post_bytes(&app, "/v1/execute", &[("content-encoding", "zstd")], submission.to_string().into_bytes()).await;
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+    
+    #[tokio::test]
+    async fn unchanged_polls_get_304_until_the_result_lands() {
+        let (app, engine) = app(unlimited()).await;
+        engine.pause();
+        let (_, submitted) = post(&app, "/v1/execute", &[], serde_json::json!({"language": "python", "source_code": "print(1)"})).await;
+        let id = submitted["id"].as_str().unwrap();
+        
+        for uri in [format!("/v1/result/{}", id), format!("/v1/status/{}", id)] {
+            let first = fetch(&app, &uri, &[]).await;
+            assert_eq!(first.status(), StatusCode::OK);
+            let etag = first.headers()[header::ETAG].to_str().unwrap().to_string();
+            
+            let again = fetch(&app, &uri, &[("if-none-match", &etag)]).await;
+            assert_eq!(again.status(), StatusCode::NOT_MODIFIED, "{}", uri);
+            assert_eq!(again.headers()[header::ETAG], etag.as_str());
+            assert!(axum::body::to_bytes(again.into_body(), usize::MAX).await.unwrap().is_empty());
+        }
+        
+        let queued = fetch(&app, &format!("/v1/result/{}", id), &[]).await;
+        let etag = queued.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert!(engine.cancel_execution(id).await.unwrap());
+        
+        let finished = fetch(&app, &format!("/v1/result/{}", id), &[("if-none-match", &etag)]).await;
+        assert_eq!(finished.status(), StatusCode::OK);
+        assert_ne!(finished.headers()[header::ETAG], etag.as_str());
+        let body: serde_json::Value = serde_json::from_slice(&axum::body::to_bytes(finished.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["status"], "cancelled");
+    }
+    
+    #[tokio::test]
+    async fn unprefixed_routes_mirror_v1_and_announce_their_sunset() {
+        let config = EngineConfig {
+            server: config::ServerConfig { legacy_routes_sunset: Some("2027-03-01T00:00:00Z".parse().unwrap()), ..Default::default() },
+            ..EngineConfig::load().unwrap()
+        };
+        let (app, engine) = app_with(config, unlimited()).await;
+        let id = cancelled_job(&app, &engine).await;
+        
+        for path in ["/languages".to_string(), "/profiles".to_string(), format!("/result/{}", id), format!("/status/{}", id)] {
+            let current = fetch(&app, &format!("/v1{}", path), &[]).await;
+            let legacy = fetch(&app, &path, &[]).await;
+            assert_eq!(legacy.status(), current.status(), "{}", path);
+            assert!(!current.headers().contains_key("deprecation"));
+            assert_eq!(legacy.headers()["deprecation"], "true");
+            assert_eq!(legacy.headers()["sunset"], "Mon, 01 Mar 2027 00:00:00 GMT");
+            assert_eq!(legacy.headers()[header::LINK], format!("</v1{}>; rel=\"successor-version\"", path).as_str());
+            
+            let current = axum::body::to_bytes(current.into_body(), usize::MAX).await.unwrap();
+            let legacy = axum::body::to_bytes(legacy.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(legacy, current, "{}", path);
+        }
+        
+        // Submissions work through the alias too
+        let (status, submitted) = post(&app, "/execute", &[], serde_json::json!({"language": "python", "source_code": "print(1)"})).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(engine.lookup(submitted["id"].as_str().unwrap()).await.unwrap().is_some());
+    }
+    
+    #[tokio::test]
+    async fn health_lists_the_supported_api_versions() {
+        let (app, _) = app(unlimited()).await;
+        let (_, health) = get(&app, "/health").await;
+        assert_eq!(health["api_versions"], serde_json::json!(["v1"]));
+        let (_, minimal) = get(&app, "/health?minimal=true").await;
+        assert!(minimal.get("api_versions").is_none_or(|versions| versions.as_array().is_some_and(Vec::is_empty)));
+    }
+    
+    #[tokio::test]
+    async fn dead_letters_are_admin_only() {
+        let mut config = EngineConfig::load().unwrap();
+        config.server.admin_api_key = Some("admin-secret".to_string());
+        let (app, engine) = app_with(config, unlimited()).await;
+        let id = cancelled_job(&app, &engine).await;
+        
+        assert_eq!(send(&app, "GET", "/v1/admin/callbacks/failed", "other").await, StatusCode::FORBIDDEN);
+        let response = fetch(&app, "/v1/admin/callbacks/failed?offset=0&limit=5", &[(auth::API_KEY_HEADER, "admin-secret")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let page: serde_json::Value = serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(page["total"], 0);
+        assert_eq!(page["items"], serde_json::json!([]));
+        
+        let retry = format!("/v1/admin/callbacks/{}/retry", id);
+        assert_eq!(send(&app, "POST", &retry, "other").await, StatusCode::FORBIDDEN);
+        assert_eq!(send(&app, "POST", &retry, "admin-secret").await, StatusCode::NOT_FOUND);
+    }
+    
+    #[tokio::test]
+    async fn strong_isolation_is_refused_where_no_runtime_offers_it() {
+        let (app, _) = app(unlimited()).await;
+        let request = serde_json::json!({"language": "python", "source_code": "print(1)", "isolation": "strong"});
+        let (status, body) = post(&app, "/v1/execute", &[], request.clone()).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body.to_string().contains("\"isolation\""), "{}", body);
+        
+        // Runtimes docker does not offer, whether or not a daemon is running here
+        let mut config = EngineConfig::load().unwrap();
+        config.sandbox.backend = labforcode_engine::executor::ExecutionBackend::Docker;
+        config.sandbox.docker.runtime = "labforcode-missing".to_string();
+        config.sandbox.docker.strong_runtime = "labforcode-missing-strong".to_string();
+        let (app, _) = app_with(config, unlimited()).await;
+        let (status, health) = get(&app, "/health").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(health["status"], "runtime unavailable");
+        assert_eq!(health["runtimes"], serde_json::json!({"labforcode-missing": false, "labforcode-missing-strong": false}));
+        let (status, _) = post(&app, "/v1/execute", &[], request).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    
+    #[tokio::test]
+    async fn memory_pressure_fails_readiness() {
+        // No host has this much available, so the first sample finds pressure
+        let mut config = EngineConfig::load().unwrap();
+        config.memory_floor_bytes = u64::MAX;
+        let (app, engine) = app_with(config, unlimited()).await;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !engine.memory_pressure() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        }).await.unwrap();
+        
+        let (status, health) = get(&app, "/health").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(health["status"], "memory pressure");
+        let (_, stats) = get(&app, "/v1/stats").await;
+        assert_eq!(stats["memory_pressure"], true);
+    }
+    
+    #[tokio::test]
+    async fn health_reports_the_build_and_features_but_no_secrets() {
+        let mut config = EngineConfig::load().unwrap();
+        config.server.admin_api_key = Some("admin-hunter2".to_string());
+        config.queue.redis_url = "redis://:queue-hunter2@redis.internal:6379".to_string();
+        let (app, _) = app_with(config, unlimited()).await;
+        
+        let response = fetch(&app, "/health", &[]).await;
+        let body = String::from_utf8(axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap();
+        assert!(!body.contains("hunter2"), "{}", body);
+        let health: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(health["engine"], "rust");
+        assert_eq!(health["version"], env!("CARGO_PKG_VERSION"));
+        assert!(health["built_at"].as_str().is_some_and(|time| chrono::DateTime::parse_from_rfc3339(time).is_ok()), "{}", health);
+        if !env!("BUILD_GIT_COMMIT").is_empty() {
+            assert_eq!(health["commit"], env!("BUILD_GIT_COMMIT"));
+        }
+        assert_eq!(health["workers"], 1);
+        assert_eq!(health["queue"], "memory");
+        let sandbox = &health["sandbox"];
+        assert_eq!(sandbox["backend"], "process");
+        assert_eq!(sandbox["seccomp"], false);
+        for feature in ["docker", "isolate", "filesystem_isolation", "cgroups_v2_writable"] {
+            assert!(sandbox[feature].is_boolean(), "{} in {}", feature, sandbox);
+        }
+        
+        // Probes get the status and nothing that takes work to gather
+        let (_, minimal) = get(&app, "/health?minimal=true").await;
+        let mut keys: Vec<&str> = minimal.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["engine", "paused", "status", "timestamp", "version"]);
+    }
+    
+    #[tokio::test]
+    async fn languages_list_their_own_limits_and_requests_over_them_are_refused() {
+        let mut config = EngineConfig::load().unwrap();
+        config.limits.languages.insert("c".to_string(), labforcode_engine::config::LanguageLimits {
+            defaults: labforcode_engine::types::LimitOverrides { memory: Some(32 << 20), ..Default::default() },
+            max: labforcode_engine::types::LimitOverrides { cpu_time: Some(1.0), ..Default::default() },
+            ..Default::default()
+        });
+        let (app, _) = app_with(config, unlimited()).await;
+        
+        let (_, languages) = get(&app, "/v1/languages").await;
+        let language = |key: &str| languages.as_array().unwrap().iter().find(|language| language["key"] == key).unwrap().clone();
+        let (c, java, python) = (language("c"), language("java"), language("python"));
+        assert_eq!(c["default_limits"]["memory"], 32 << 20);
+        assert_eq!(c["max_limits"]["cpu_time"], 1.0);
+        assert_eq!(java["default_limits"]["memory"], 512 << 20);
+        assert_eq!(python["max_limits"]["cpu_time"], labforcode_engine::validation::RequestValidator::default().max_cpu_time);
+        
+        // Within the server ceiling, but over C's own
+        let request = serde_json::json!({"language": "c", "source_code": "int main() {}", "cpu_time_limit": 2.0});
+        let (status, body) = post(&app, "/v1/execute", &[], request).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body.to_string().contains("cpu_time_limit"), "{}", body);
+        let request = serde_json::json!({"language": "python", "source_code": "print(1)", "cpu_time_limit": 2.0});
+        assert_eq!(post(&app, "/v1/execute", &[], request).await.0, StatusCode::OK);
+    }
+    
+    #[tokio::test]
+    async fn profiles_are_listed_and_fill_in_requests() {
+        let mut config = EngineConfig::load().unwrap();
+        config.profiles.insert("contest".to_string(), serde_json::from_value(serde_json::json!({
+            "limits": {"cpu_time": 1.0, "memory": 134217728},
+            "options": {"number_of_runs": 3},
+        })).unwrap());
+        let (app, engine) = app_with(config, unlimited()).await;
+        engine.pause();
+        
+        let (status, profiles) = get(&app, "/v1/profiles").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(profiles["contest"]["limits"], serde_json::json!({"cpu_time": 1.0, "memory": 134217728}));
+        assert_eq!(profiles["contest"]["options"], serde_json::json!({"number_of_runs": 3}));
+        
+        // The request's own cpu_time_limit wins over the profile's
+        let request = serde_json::json!({"language": "python", "source_code": "print(1)", "profile": "contest", "cpu_time_limit": 2.0});
+        let (status, body) = post(&app, "/v1/execute", &[], request).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let job = engine.lookup(body["id"].as_str().unwrap()).await.unwrap().unwrap();
+        assert_eq!(job.request.cpu_time_limit, Some(2.0));
+        assert_eq!(job.request.memory_limit, Some(134217728));
+        assert_eq!(job.request.number_of_runs, Some(3));
+        
+        let request = serde_json::json!({"language": "python", "source_code": "print(1)", "profile": "benchmark"});
+        let (status, body) = post(&app, "/v1/execute", &[], request).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body.to_string().contains("unknown profile 'benchmark'"), "{}", body);
+        
+        // An override is still held to the ceilings
+        let request = serde_json::json!({"language": "python", "source_code": "print(1)", "profile": "contest", "cpu_time_limit": 600.0});
+        assert_eq!(post(&app, "/v1/execute", &[], request).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    
+    #[tokio::test]
+    async fn oversized_sources_are_refused_before_they_are_queued() {
+        let mut config = EngineConfig::load().unwrap();
+        config.limits.ceilings.max_source_bytes = 100;
+        config.limits.languages.insert("python".to_string(), labforcode_engine::config::LanguageLimits {
+            max_source_bytes: Some(1000),
+            ..Default::default()
+        });
+        let (app, engine) = app_with(config, unlimited()).await;
+        engine.pause();
+        
+        let (_, languages) = get(&app, "/v1/languages").await;
+        let language = |key: &str| languages.as_array().unwrap().iter().find(|language| language["key"] == key).unwrap().clone();
+        assert_eq!((&language("python")["max_source_bytes"], &language("c")["max_source_bytes"]), (&1000.into(), &100.into()));
+        
+        let submit = |language: &str, bytes: usize| serde_json::json!({"language": language, "source_code": "/".repeat(bytes)});
+        let (status, body) = post(&app, "/v1/execute", &[], submit("c", 101)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body.to_string().contains("must be at most 100 bytes"), "{}", body);
+        assert_eq!(engine.get_stats().await.unwrap().total_executions, 0);
+        
+        // Python's own ceiling is higher than the server's
+        let (status, body) = post(&app, "/v1/execute", &[], submit("python", 500)).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let job = engine.lookup(body["id"].as_str().unwrap()).await.unwrap().unwrap();
+        assert_eq!(job.source_size_bytes, 500);
+        assert_eq!(post(&app, "/v1/execute", &[], submit("python", 1001)).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(post(&app, "/v1/execute", &[], submit("python", 300)).await.0, StatusCode::OK);
+        
+        let (_, stats) = get(&app, "/v1/stats").await;
+        assert_eq!(stats["by_language"]["python"]["average_source_bytes"], 400.0);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::config::ServerConfig;
+use crate::{api_error, ApiError, AppState};
+use axum::{
+    async_trait,
+    extract::{multipart::{Field, MultipartError}, FromRequest, Multipart, Request},
+    http::{header, StatusCode},
+    Json,
+};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::{Cursor, Write};
+use std::path::{Component, Path};
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+/// Size caps for /execute bodies; exceeding either is a 413. The total also bounds JSON bodies
+#[derive(Clone, Copy)]
+pub struct UploadLimits {
+    pub max_part_bytes: usize,
+    pub max_total_bytes: usize,
+}
+
+impl UploadLimits {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self {
+            max_part_bytes: config.multipart_max_part_bytes,
+            max_total_bytes: config.multipart_max_total_bytes,
+        }
+    }
+}
+
+/// JSON body of an execution request, either posted as JSON or assembled from multipart/form-data.
+///
+/// A multipart submission has a `request` part with the JSON metadata, an optional `source` part
+/// with the program, an optional `stdin` part whose bytes are the program's input, and any number
+/// of file parts that are written into the working directory under their filenames. The files
+/// travel to the executor as the `additional_files` ZIP.
+pub struct Submission(pub Value);
+
+#[async_trait]
+impl FromRequest<AppState> for Submission {
+    type Rejection = ApiError;
+    
+    async fn from_request(request: Request, state: &AppState) -> Result<Self, Self::Rejection> {
+        let is_multipart = request.headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("multipart/form-data"));
+        
+        if !is_multipart {
+            let Json(body) = Json::<Value>::from_request(request, state).await
+                .map_err(|rejection| api_error(rejection.status(), rejection.body_text()))?;
+            return Ok(Self(body));
+        }
+        
+        let multipart = Multipart::from_request(request, state).await
+            .map_err(|rejection| api_error(rejection.status(), rejection.body_text()))?;
+        read_submission(multipart, state.uploads).await.map(Self)
+    }
+}
+
+async fn read_submission(mut multipart: Multipart, limits: UploadLimits) -> Result<Value, ApiError> {
+    let mut body = None;
+    let mut source = None;
+    let mut stdin = None;
+    let mut files = Vec::new();
+    let mut names = HashSet::new();
+    
+    while let Some(field) = multipart.next_field().await.map_err(|err| stream_error(err, limits))? {
+        let name = field.name().unwrap_or_default().to_string();
+        let file_name = field.file_name().map(str::to_string);
+        let data = read_part(field, &name, limits).await?;
+        
+        match name.as_str() {
+            "request" => {
+                let request: Value = serde_json::from_slice(&data).map_err(|err| {
+                    api_error(StatusCode::UNPROCESSABLE_ENTITY, format!("Invalid request part: {}", err))
+                })?;
+                if !request.is_object() {
+                    return Err(api_error(StatusCode::UNPROCESSABLE_ENTITY, "request part must be a JSON object"));
+                }
+                body = Some(request);
+            }
+            "source" => {
+                let text = String::from_utf8(data)
+                    .map_err(|_| api_error(StatusCode::UNPROCESSABLE_ENTITY, "source part must be UTF-8 text"))?;
+                source = Some(text);
+            }
+            "stdin" => stdin = Some(data),
+            _ => {
+                let Some(path) = file_name.filter(|path| !path.is_empty()) else {
+                    return Err(api_error(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!("Part '{}' is not a file; only request, source and stdin may be plain fields", name),
+                    ));
+                };
+                if !is_relative_path(&path) {
+                    return Err(api_error(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!("Invalid file name '{}': must be a relative path inside the working directory", path),
+                    ));
+                }
+                if !names.insert(path.clone()) {
+                    return Err(api_error(StatusCode::UNPROCESSABLE_ENTITY, format!("File '{}' uploaded twice", path)));
+                }
+                files.push((path, data));
+            }
+        }
+    }
+    
+    let Some(mut body) = body else {
+        return Err(api_error(StatusCode::UNPROCESSABLE_ENTITY, "Multipart submissions need a request part"));
+    };
+    if let Some(source) = source {
+        if body.get("source_code").is_some() {
+            return Err(api_error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "source_code must come from either the request or the source part, not both",
+            ));
+        }
+        body["source_code"] = Value::String(source);
+    }
+    if let Some(stdin) = stdin {
+        if body.get("stdin").is_some() || body.get("stdin_base64").is_some() {
+            return Err(api_error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "stdin must come from either the request or the stdin part, not both",
+            ));
+        }
+        body["stdin_base64"] = Value::String(general_purpose::STANDARD.encode(stdin));
+    }
+    if !files.is_empty() {
+        let existing = body.get("additional_files").and_then(Value::as_str);
+        let archive = bundle_files(existing, files)
+            .map_err(|err| api_error(StatusCode::UNPROCESSABLE_ENTITY, err.to_string()))?;
+        body["additional_files"] = Value::String(archive);
+    }
+    
+    Ok(body)
+}
+
+/// Read one part, refusing it once it passes the per-part limit
+async fn read_part(mut field: Field<'_>, name: &str, limits: UploadLimits) -> Result<Vec<u8>, ApiError> {
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(|err| stream_error(err, limits))? {
+        data.extend_from_slice(&chunk);
+        if data.len() > limits.max_part_bytes {
+            return Err(api_error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Part '{}' exceeds the {} byte limit", name, limits.max_part_bytes),
+            ));
+        }
+    }
+    Ok(data)
+}
+
+/// The route's body limit enforces the total; name it instead of multer's generic read error
+fn stream_error(err: MultipartError, limits: UploadLimits) -> ApiError {
+    match err.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => api_error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Upload exceeds the {} byte limit", limits.max_total_bytes),
+        ),
+        status => api_error(status, err.body_text()),
+    }
+}
+
+/// Same rule the workdir file routes apply: only plain components, so nothing lands outside the directory
+fn is_relative_path(path: &str) -> bool {
+    !path.contains('\\') && Path::new(path).components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Base64 ZIP of the uploaded files, appended to the entries of any `additional_files` already in the request
+fn bundle_files(existing: Option<&str>, files: Vec<(String, Vec<u8>)>) -> anyhow::Result<String> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    
+    if let Some(existing) = existing {
+        let data = general_purpose::STANDARD.decode(existing)
+            .map_err(|e| anyhow::anyhow!("additional_files must be a base64 ZIP: {}", e))?;
+        let mut archive = ZipArchive::new(Cursor::new(data))
+            .map_err(|e| anyhow::anyhow!("additional_files must be a base64 ZIP: {}", e))?;
+        for index in 0..archive.len() {
+            writer.raw_copy_file(archive.by_index_raw(index)?)?;
+        }
+    }
+    
+    for (path, data) in files {
+        writer.start_file(path, FileOptions::default())?;
+        writer.write_all(&data)?;
+    }
+    
+    let archive = writer.finish()?.into_inner();
+    Ok(general_purpose::STANDARD.encode(archive))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    
+    /// (name, content) of every entry of a base64 ZIP
+    fn entries(archive: &str) -> Vec<(String, String)> {
+        let mut archive = ZipArchive::new(Cursor::new(general_purpose::STANDARD.decode(archive).unwrap())).unwrap();
+        (0..archive.len())
+            .map(|index| {
+                let mut file = archive.by_index(index).unwrap();
+                let mut content = String::new();
+                file.read_to_string(&mut content).unwrap();
+                (file.name().to_string(), content)
+            })
+            .collect()
+    }
+    
+    #[test]
+    fn file_names_must_stay_inside_the_working_directory() {
+        for path in ["input.txt", "data/input.txt", "a.b/c"] {
+            assert!(is_relative_path(path), "{}", path);
+        }
+        for path in ["../input.txt", "data/../../input.txt", "/etc/passwd", "data\\input.txt", "./input.txt"] {
+            assert!(!is_relative_path(path), "{}", path);
+        }
+    }
+    
+    #[test]
+    fn uploaded_files_join_the_additional_files_already_sent() {
+        let first = bundle_files(None, vec![("a.txt".to_string(), b"a".to_vec())]).unwrap();
+        let both = bundle_files(Some(&first), vec![("data/b.txt".to_string(), b"b".to_vec())]).unwrap();
+        assert_eq!(
+            entries(&both),
+            [("a.txt".to_string(), "a".to_string()), ("data/b.txt".to_string(), "b".to_string())],
+        );
+        assert!(bundle_files(Some("not a zip"), Vec::new()).is_err());
+    }
+}
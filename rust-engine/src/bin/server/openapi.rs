@@ -0,0 +1,381 @@
+use crate::config::*;
+use crate::executor::ExecutionBackend;
+use crate::types::*;
+use crate::validation::{LimitsMode, RequestValidator};
+use crate::AppState;
+use axum::Router;
+use utoipa::openapi::path::PathItemType;
+use utoipa::openapi::schema::{KnownFormat, ObjectBuilder, SchemaFormat, SchemaType};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::openapi::Content;
+use utoipa::{Modify, OpenApi};
+
+/// OpenAPI 3 description of every route, generated from the handler annotations and serde types
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "LabForCode Rust Engine", description = "Sandboxed code execution with a Judge0-compatible API"),
+    paths(
+        crate::health_check,
+        crate::execute_code,
+        crate::get_execution_status,
+        crate::list_executions,
+        crate::stream_execution_status,
+        crate::get_execution_result,
+        crate::get_stdout_file,
+        crate::get_stderr_file,
+        crate::delete_execution,
+        crate::list_workdir_files,
+        crate::get_workdir_file,
+        crate::cancel_execution,
+        crate::rerun_execution,
+        crate::get_execution_events,
+        crate::get_engine_stats,
+        crate::get_recent_executions,
+        crate::get_metrics,
+        crate::get_supported_languages,
+        crate::get_profiles,
+        crate::get_usage,
+        crate::get_client_stats,
+        crate::get_queue,
+        crate::drop_queued,
+        crate::promote_queued,
+        crate::cancel_matching,
+        crate::reset_engine_stats,
+        crate::pause_processing,
+        crate::resume_processing,
+        crate::get_capacity,
+        crate::get_config,
+        crate::update_config,
+        crate::get_failed_callbacks,
+        crate::retry_callback,
+        crate::update_capacity,
+        crate::get_all_usage,
+        crate::update_quota,
+        crate::judge0::create_submission,
+        crate::judge0::create_batch,
+        crate::judge0::get_submission,
+        crate::judge0::get_batch,
+    ),
+    components(schemas(
+        ExecutionRequest,
+        RerunOverrides,
+        ExecutionResponse,
+        ExecutionStatus,
+        ExecutionResult,
+        ExecutionMetrics,
+        ExecutionState,
+        StatusWithCode,
+        ExecutionPhase,
+        ExecutionProgress,
+        TimelineEntry,
+        TimelineStage,
+        PhaseTimings,
+        ExecutionOptions,
+        ResourceLimits,
+        LimitOverrides,
+        LimitCeilings,
+        CpuAffinity,
+        AutoAffinity,
+        IsolationLevel,
+        SandboxFeatures,
+        DiskSpace,
+        Runner,
+        LanguageWarmup,
+        OutputFilter,
+        OutputEncoding,
+        Artifact,
+        ExecutedCommand,
+        CommandPhase,
+        Diagnostic,
+        DiagnosticSeverity,
+        Interactor,
+        InteractorResult,
+        TestCase,
+        TestCaseResult,
+        TestGroupSummary,
+        ErrorResponse,
+        FieldError,
+        AuditEvent,
+        AuditEventKind,
+        ConfigChangeEvent,
+        ResultSummary,
+        EngineStats,
+        LanguageStats,
+        StatsWindows,
+        StatsWindow,
+        RecentExecution,
+        HealthResponse,
+        LanguageInfo,
+        CancelResponse,
+        WorkdirEntry,
+        QueuedJobInfo,
+        CancelFilter,
+        CancelStatus,
+        BulkCancelResponse,
+        DeadLetter,
+        DeadLetterPage,
+        CapacitySettings,
+        CapacityUpdate,
+        ClientStats,
+        UsageReport,
+        PeriodUsage,
+        UsageCounters,
+        QuotaLimits,
+        Quota,
+        EngineConfig,
+        RuntimeSettings,
+        ServerConfig,
+        CorsConfig,
+        AutoscaleConfig,
+        PausedSubmissions,
+        QueueConfig,
+        QueueBackend,
+        SandboxConfig,
+        ExecutionBackend,
+        DockerPoolConfig,
+        IsolateConfig,
+        FetchConfig,
+        LimitsConfig,
+        LanguageLimits,
+        Profile,
+        ProfileOptions,
+        RequestValidator,
+        LimitsMode,
+        RetentionConfig,
+        RateLimitConfig,
+        AuditConfig,
+        CallbackConfig,
+        ArchiveConfig,
+        ArchiveServe,
+        WarmupConfig,
+    )),
+    modifiers(&ApiKeyAuth, &RootAlias, &MultipartExecute),
+    tags(
+        (name = "executions", description = "Submit executions and follow them to a result"),
+        (name = "judge0", description = "Judge0-compatible submissions"),
+        (name = "admin", description = "Operator endpoints; require X-API-Key: $ADMIN_API_KEY"),
+        (name = "info", description = "Health, statistics and metadata"),
+    ),
+)]
+pub struct ApiDoc;
+
+/// Admin routes authenticate with the X-API-Key header
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))));
+    }
+}
+
+/// `/` answers like `/health`; a handler can only be annotated with one path
+struct RootAlias;
+
+impl Modify for RootAlias {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(health) = openapi.paths.paths.get("/health").cloned() {
+            openapi.paths.paths.insert("/".to_string(), health);
+        }
+    }
+}
+
+/// POST /execute also takes multipart/form-data; the path macro only describes one content type
+struct MultipartExecute;
+
+impl Modify for MultipartExecute {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let Some(request_body) = openapi.paths.paths.get_mut("/v1/execute")
+            .and_then(|path| path.operations.get_mut(&PathItemType::Post))
+            .and_then(|operation| operation.request_body.as_mut())
+        else {
+            return;
+        };
+        
+        let file = || ObjectBuilder::new()
+            .schema_type(SchemaType::String)
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Binary)));
+        let form = ObjectBuilder::new()
+            .property(
+                "request",
+                ObjectBuilder::new().schema_type(SchemaType::String).description(Some("ExecutionRequest as JSON")),
+            )
+            .required("request")
+            .property("source", file().description(Some("Program source; replaces source_code")))
+            .additional_properties(Some(file().description(Some("Written into the working directory under its filename"))));
+        request_body.content.insert("multipart/form-data".to_string(), Content::new(form));
+    }
+}
+
+/// GET /openapi.json
+#[cfg(not(feature = "swagger-ui"))]
+pub fn router() -> Router<AppState> {
+    use axum::{http::header, routing::get};
+    
+    // The document never changes at runtime, so it is rendered once
+    let document = ApiDoc::openapi().to_json().expect("OpenAPI document serializes");
+    Router::new().route(
+        "/openapi.json",
+        get(move || async move { ([(header::CONTENT_TYPE, "application/json")], document) }),
+    )
+}
+
+/// GET /openapi.json, plus Swagger UI at /docs
+#[cfg(feature = "swagger-ui")]
+pub fn router() -> Router<AppState> {
+    utoipa_swagger_ui::SwaggerUi::new("/docs")
+        .url("/openapi.json", ApiDoc::openapi())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{app, cancelled_job, get, post, unlimited};
+    use serde_json::Value;
+    
+    /// The document as GET /openapi.json serves it
+    async fn document() -> Value {
+        let (app, _) = app(unlimited()).await;
+        let (status, document) = get(&app, "/openapi.json").await;
+        assert!(status.is_success());
+        document
+    }
+    
+    /// `(path, method)` of every route the server mounts, read from the router definitions;
+    /// `:id` becomes `{id}` and the versioned API is listed under /v1
+    fn mounted_routes() -> Vec<(String, String)> {
+        let main = include_str!("main.rs");
+        let start = main.find("fn router(").unwrap();
+        let end = start + main[start..].find("\n}\n").unwrap();
+        let judge0 = include_str!("judge0.rs");
+        let judge0_start = judge0.find("pub fn router(").unwrap();
+        let judge0_end = judge0_start + judge0[judge0_start..].find("\n}\n").unwrap();
+        
+        let mut routes = Vec::new();
+        for source in [&main[start..end], &judge0[judge0_start..judge0_end]] {
+            for route in source.split(".route(").skip(1) {
+                let path = route.split('"').nth(1).unwrap();
+                let versioned = !["/", "/health", "/metrics", "/openapi.json"].contains(&path) && !path.starts_with("/submissions");
+                let path = path.split('/')
+                    .map(|segment| match segment.strip_prefix(':').or_else(|| segment.strip_prefix('*')) {
+                        Some(name) => format!("{{{}}}", name),
+                        None => segment.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let path = if versioned { format!("/v1{}", path) } else { path };
+                
+                // Methods are the routing functions called on this route, e.g. `get(a).delete(b)`
+                let handlers = route.split(".layer(").next().unwrap();
+                for method in ["get", "post", "put", "delete"] {
+                    let called = handlers.match_indices(&format!("{}(", method)).any(|(at, _)| {
+                        at == 0 || !handlers.as_bytes()[at - 1].is_ascii_alphanumeric() && handlers.as_bytes()[at - 1] != b'_'
+                    });
+                    if called {
+                        routes.push((path.clone(), method.to_string()));
+                    }
+                }
+            }
+        }
+        routes
+    }
+    
+    /// Property names of a schema, following the `allOf` that `#[serde(flatten)]` produces
+    fn properties(document: &Value, schema: &Value) -> Vec<String> {
+        if let Some(name) = schema["$ref"].as_str().and_then(|reference| reference.rsplit('/').next()) {
+            return properties(document, &document["components"]["schemas"][name]);
+        }
+        let mut names: Vec<String> = schema["properties"].as_object().into_iter().flat_map(|map| map.keys().cloned()).collect();
+        for part in schema["allOf"].as_array().into_iter().flatten() {
+            names.extend(properties(document, part));
+        }
+        names
+    }
+    
+    /// Every top-level field of a payload is described by its schema
+    fn assert_described(document: &Value, schema: &str, payload: &Value) {
+        let properties = properties(document, &document["components"]["schemas"][schema]);
+        assert!(!properties.is_empty(), "{} has no properties", schema);
+        for key in payload.as_object().unwrap().keys() {
+            assert!(properties.contains(key), "{}.{} is not in the document", schema, key);
+        }
+    }
+    
+    #[tokio::test]
+    async fn the_served_document_parses_as_openapi() {
+        let document = document().await;
+        let parsed: utoipa::openapi::OpenApi = serde_json::from_value(document.clone()).unwrap();
+        assert_eq!(parsed.paths.paths.len(), ApiDoc::openapi().paths.paths.len());
+        assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(document["components"]["securitySchemes"]["api_key"].is_object());
+        assert_eq!(document["paths"]["/"], document["paths"]["/health"]);
+        
+        let execute = &document["paths"]["/v1/execute"]["post"];
+        for content_type in ["application/json", "multipart/form-data"] {
+            assert!(execute["requestBody"]["content"][content_type].is_object(), "POST /v1/execute takes {}", content_type);
+        }
+    }
+    
+    #[test]
+    fn every_mounted_route_is_annotated() {
+        let document = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let routes = mounted_routes();
+        assert!(routes.len() > 40, "only found {} routes", routes.len());
+        
+        let missing: Vec<_> = routes.iter()
+            .filter(|(path, _)| path != "/openapi.json")
+            .filter(|(path, method)| document["paths"][path][method].is_null())
+            .collect();
+        assert!(missing.is_empty(), "routes without #[utoipa::path]: {:?}", missing);
+        
+        // ...and nothing is documented that is not served
+        let paths = document["paths"].as_object().unwrap();
+        for (path, item) in paths {
+            for method in item.as_object().unwrap().keys() {
+                assert!(routes.contains(&(path.clone(), method.clone())), "{} {} is documented but not routed", method, path);
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn example_payloads_round_trip_through_the_documented_types() {
+        let document = document().await;
+        let (app, engine) = app(unlimited()).await;
+        
+        let request = serde_json::json!({
+            "language": "python",
+            "source_code": "print(input())",
+            "cpu_time_limit": 2.0,
+            "memory_limit": 134217728,
+            "env": {"MODE": "test"},
+            "test_cases": [{"stdin": "a", "expected_output": "a"}],
+        });
+        assert_described(&document, "ExecutionRequest", &request);
+        let parsed: ExecutionRequest = serde_json::from_value(request.clone()).unwrap();
+        assert_eq!(parsed.env.as_ref().and_then(|env| env.get("MODE")).map(String::as_str), Some("test"));
+        
+        let (status, accepted) = post(&app, "/v1/execute", &[], request).await;
+        assert!(status.is_success(), "{} {}", status, accepted);
+        assert_described(&document, "ExecutionResponse", &accepted);
+        
+        let (status, refused) = post(&app, "/v1/execute", &[], serde_json::json!({"language": "cobol", "source_code": ""})).await;
+        assert!(status.is_client_error());
+        assert_described(&document, "ErrorResponse", &refused);
+        
+        let id = cancelled_job(&app, &engine).await;
+        let (_, result) = get(&app, &format!("/v1/result/{}", id)).await;
+        assert_described(&document, "ExecutionResult", &result);
+        let parsed: ExecutionResult = serde_json::from_value(result.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), result);
+        
+        let (_, stats) = get(&app, "/v1/stats").await;
+        assert_described(&document, "EngineStats", &stats);
+        let (_, health) = get(&app, "/health").await;
+        assert_described(&document, "HealthResponse", &health);
+        let (_, languages) = get(&app, "/v1/languages").await;
+        for language in languages.as_array().unwrap() {
+            assert_described(&document, "LanguageInfo", language);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::types::{ExecutionStatus, ResultView};
+use crate::AppState;
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use tracing::info;
+
+/// Versions the API is served under, oldest first
+pub const SUPPORTED: &[ApiVersion] = &[ApiVersion::V1];
+
+/// Version the unprefixed, deprecated routes answer as
+const LEGACY: ApiVersion = ApiVersion::V1;
+
+/// API version a request came in through, available to handlers as an extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    V1,
+}
+
+impl ApiVersion {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "v1",
+        }
+    }
+    
+    /// How this version renders engine types
+    pub fn mapper(self) -> &'static dyn ResponseMapper {
+        match self {
+            ApiVersion::V1 => &V1,
+        }
+    }
+}
+
+/// Per-version view of the engine types. A new version that reshapes results implements this
+/// over the same ExecutionStatus and ExecutionResult instead of forking the handlers.
+pub trait ResponseMapper: Send + Sync {
+    fn status(&self, status: ExecutionStatus) -> Response;
+    fn result(&self, view: ResultView) -> Response;
+}
+
+/// v1 serves the engine types as they serialize
+struct V1;
+
+impl ResponseMapper for V1 {
+    fn status(&self, status: ExecutionStatus) -> Response {
+        Json(status).into_response()
+    }
+    
+    fn result(&self, view: ResultView) -> Response {
+        Json(view).into_response()
+    }
+}
+
+/// Serve `api` under every supported version's prefix, and unprefixed as deprecated aliases of the oldest
+/// that announce `sunset` when one is configured
+pub fn mount(api: Router<AppState>, sunset: Option<DateTime<Utc>>) -> Router<AppState> {
+    let sunset = sunset_header(sunset);
+    let mut router = Router::new();
+    for version in SUPPORTED {
+        router = router.nest(&format!("/{}", version.as_str()), api.clone().layer(Extension(*version)));
+    }
+    
+    let legacy = api
+        .layer(Extension(LEGACY))
+        .layer(middleware::from_fn(move |request: Request, next: Next| {
+            let sunset = sunset.clone();
+            async move { deprecated(request, next, sunset).await }
+        }));
+    router.merge(legacy)
+}
+
+/// Mark an unprefixed response as deprecated and point at its versioned successor
+async fn deprecated(request: Request, next: Next, sunset: Option<HeaderValue>) -> Response {
+    let successor = format!("</{}{}>; rel=\"successor-version\"", LEGACY.as_str(), request.uri().path());
+    let mut response = next.run(request).await;
+    
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.append(header::LINK, link);
+    }
+    if let Some(sunset) = sunset {
+        headers.insert("sunset", sunset);
+    }
+    response
+}
+
+/// Sunset date as the HTTP date the Sunset header carries
+fn sunset_header(sunset: Option<DateTime<Utc>>) -> Option<HeaderValue> {
+    let date = sunset?.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    info!("🌅 Unversioned routes sunset on {}", date);
+    HeaderValue::from_str(&date).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn sunset_dates_render_as_http_dates() {
+        let sunset = "2027-03-01T12:30:00Z".parse().unwrap();
+        assert_eq!(sunset_header(Some(sunset)).unwrap(), "Mon, 01 Mar 2027 12:30:00 GMT");
+        assert!(sunset_header(None).is_none());
+    }
+}
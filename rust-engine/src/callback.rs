@@ -0,0 +1,339 @@
+//! Result callbacks posted to `callback_url`, and the signatures receivers check them with.
+//!
+//! When a secret is configured for the submitter's API key (or globally), every callback
+//! carries `X-Engine-Timestamp` (Unix seconds) and `X-Engine-Signature: sha256=<hex>`, the
+//! HMAC-SHA256 of `<timestamp>.<raw body>`. Signing the timestamp lets receivers refuse
+//! replays of old callbacks; [`verify`] does both checks:
+//!
+//! ```
+//! use labforcode_engine::callback::{sign, verify, SignatureError, DEFAULT_TOLERANCE};
+//!
+//! let body = br#"{"id":"42","status":"completed"}"#;
+//! let timestamp = chrono::Utc::now().timestamp().to_string();
+//! let signature = sign(b"secret", &timestamp, body);
+//!
+//! assert!(verify(b"secret", &timestamp, &signature, body, DEFAULT_TOLERANCE).is_ok());
+//! let tampered = br#"{"id":"42","status":"failed"}"#;
+//! assert_eq!(verify(b"secret", &timestamp, &signature, tampered, DEFAULT_TOLERANCE), Err(SignatureError::Mismatch));
+//! ```
+use crate::config::CallbackConfig;
+use crate::rate_limit::RateLimiter;
+use crate::telemetry;
+use crate::types::{DeadLetter, DeadLetterPage, ExecutionJob, ExecutionResult};
+use anyhow::Result;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Header carrying `sha256=<hex>` of the signed payload
+pub const SIGNATURE_HEADER: &str = "X-Engine-Signature";
+
+/// Header carrying the Unix time the callback was signed at
+pub const TIMESTAMP_HEADER: &str = "X-Engine-Timestamp";
+
+/// Header carrying the X-Request-Id of the submission, so receivers can correlate the callback
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// How far a callback's timestamp may be from the receiver's clock before it counts as a replay
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// Delay before the second attempt; doubles with each attempt after that
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest delay between attempts
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Why a callback failed verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum SignatureError {
+    /// A header is missing its `sha256=` prefix, is not hex, or the timestamp is not a number
+    #[error("malformed signature headers")]
+    Malformed,
+    /// The timestamp is outside the tolerance
+    #[error("timestamp outside the allowed tolerance")]
+    Expired,
+    /// The body or timestamp was not signed with this secret
+    #[error("signature does not match")]
+    Mismatch,
+}
+
+/// Signature header value for a body sent at `timestamp`
+pub fn sign(secret: &[u8], timestamp: &str, body: &[u8]) -> String {
+    let digest: String = payload_mac(secret, timestamp, body)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256={}", digest)
+}
+
+/// Check the headers of a received callback against its raw body, in constant time
+pub fn verify(
+    secret: &[u8],
+    timestamp: &str,
+    signature: &str,
+    body: &[u8],
+    tolerance: Duration,
+) -> Result<(), SignatureError> {
+    let sent_at: i64 = timestamp.trim().parse().map_err(|_| SignatureError::Malformed)?;
+    let expected = signature.trim().strip_prefix("sha256=").and_then(decode_hex).ok_or(SignatureError::Malformed)?;
+    
+    if Utc::now().timestamp().abs_diff(sent_at) > tolerance.as_secs() {
+        return Err(SignatureError::Expired);
+    }
+    payload_mac(secret, timestamp.trim(), body)
+        .verify_slice(&expected)
+        .map_err(|_| SignatureError::Mismatch)
+}
+
+/// HMAC over `<timestamp>.<body>`
+fn payload_mac(secret: &[u8], timestamp: &str, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// Bytes of an even-length hex string
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Posts finished results to their `callback_url` and keeps the ones that could not be delivered
+#[derive(Clone)]
+pub(crate) struct CallbackSender {
+    client: reqwest::Client,
+    secret: Option<String>,
+    secrets: Arc<HashMap<String, String>>, // keyed by client id, the form jobs record their submitter in
+    max_attempts: u32,
+    dead_letters: Arc<Mutex<VecDeque<DeadLetter>>>, // oldest first
+    dead_letter_capacity: usize,
+    dead_letter_ttl: chrono::Duration,
+    delivered: Arc<AtomicU64>,
+    dead_lettered: Arc<AtomicU64>,
+}
+
+impl CallbackSender {
+    pub fn new(config: &CallbackConfig, dead_letter_ttl: chrono::Duration) -> Result<Self> {
+        // A redirect would turn the POST into a GET elsewhere, so 3xx counts as a failed attempt
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+        let secrets = config.secrets
+            .iter()
+            .map(|(key, secret)| (RateLimiter::api_key_id(key), secret.clone()))
+            .collect();
+        
+        Ok(Self {
+            client,
+            secret: config.secret.clone(),
+            secrets: Arc::new(secrets),
+            max_attempts: config.max_attempts,
+            dead_letters: Arc::new(Mutex::new(VecDeque::new())),
+            dead_letter_capacity: config.dead_letter_capacity,
+            dead_letter_ttl,
+            delivered: Arc::new(AtomicU64::new(0)),
+            dead_lettered: Arc::new(AtomicU64::new(0)),
+        })
+    }
+    
+    /// POST the result until the receiver answers 2xx, backing off between attempts
+    /// Once the attempts run out the callback is dead-lettered and the entry returned
+    pub async fn deliver(&self, job: &ExecutionJob, result: &ExecutionResult) -> Result<(), DeadLetter> {
+        self.attempt(job, result, self.max_attempts).await
+    }
+    
+    /// Try a dead-lettered callback once more; a success removes its entry, a failure updates it
+    pub async fn retry(&self, job: &ExecutionJob, result: &ExecutionResult) -> Result<(), DeadLetter> {
+        self.attempt(job, result, 1).await
+    }
+    
+    /// Given-up callbacks, newest first, skipping `offset` and returning at most `limit`
+    pub fn dead_letters(&self, offset: usize, limit: usize) -> DeadLetterPage {
+        let entries = self.dead_letters.lock().unwrap();
+        DeadLetterPage {
+            total: entries.len(),
+            offset,
+            items: entries.iter().rev().skip(offset).take(limit).cloned().collect(),
+        }
+    }
+    
+    /// Whether the job's callback is dead-lettered
+    pub fn is_dead_lettered(&self, id: &str) -> bool {
+        self.dead_letters.lock().unwrap().iter().any(|entry| entry.job_id == id)
+    }
+    
+    /// Drop the dead-letter entry of a job that no longer exists
+    pub fn forget(&self, id: &str) {
+        self.dead_letters.lock().unwrap().retain(|entry| entry.job_id != id);
+    }
+    
+    /// Drop entries older than the retention TTL
+    pub fn sweep(&self) {
+        let cutoff = Utc::now() - self.dead_letter_ttl;
+        self.dead_letters.lock().unwrap().retain(|entry| entry.failed_at > cutoff);
+    }
+    
+    /// Delivered and dead-lettered callbacks since startup
+    pub fn counters(&self) -> (u64, u64) {
+        (self.delivered.load(Ordering::Relaxed), self.dead_lettered.load(Ordering::Relaxed))
+    }
+    
+    async fn attempt(&self, job: &ExecutionJob, result: &ExecutionResult, max_attempts: u32) -> Result<(), DeadLetter> {
+        let Some(url) = &job.request.callback_url else {
+            return Ok(());
+        };
+        let body = serde_json::to_vec(result).unwrap_or_default();
+        let secret = job.client.as_ref()
+            .and_then(|client| self.secrets.get(client))
+            .or(self.secret.as_ref());
+        
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            
+            // Each attempt is signed afresh so retries aren't mistaken for replays
+            let mut request = self.client.post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            if let Some(request_id) = &job.request_id {
+                request = request.header(REQUEST_ID_HEADER, request_id);
+            }
+            if let Some(secret) = secret {
+                let timestamp = Utc::now().timestamp().to_string();
+                request = request
+                    .header(SIGNATURE_HEADER, sign(secret.as_bytes(), &timestamp, &body))
+                    .header(TIMESTAMP_HEADER, timestamp);
+            }
+            
+            let (status, error) = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    info!("📬 Delivered callback for {} on attempt {}", job.id, attempt);
+                    self.delivered.fetch_add(1, Ordering::Relaxed);
+                    self.forget(&job.id);
+                    return Ok(());
+                }
+                Ok(response) => (Some(response.status().as_u16()), format!("receiver answered {}", response.status())),
+                Err(err) => (None, err.without_url().to_string()),
+            };
+            if attempt >= max_attempts {
+                warn!("Giving up on the callback for {} after {} attempts: {}", job.id, attempt, error);
+                let digest: String = Sha256::digest(&body).iter().map(|b| format!("{:02x}", b)).collect();
+                return Err(self.dead_letter(DeadLetter {
+                    job_id: job.id.clone(),
+                    url: url.clone(),
+                    attempts: attempt,
+                    last_status: status,
+                    last_error: error,
+                    payload_sha256: digest,
+                    failed_at: Utc::now(),
+                }));
+            }
+            
+            let delay = RETRY_BASE_DELAY
+                .saturating_mul(2u32.saturating_pow(attempt - 1))
+                .min(RETRY_MAX_DELAY);
+            warn!("Callback for {} failed on attempt {}, retrying in {}s: {}", job.id, attempt, delay.as_secs(), error);
+            tokio::time::sleep(delay).await;
+        }
+    }
+    
+    /// Store a failed callback, carrying over the attempts of an earlier entry for the same job
+    fn dead_letter(&self, mut entry: DeadLetter) -> DeadLetter {
+        let mut entries = self.dead_letters.lock().unwrap();
+        match entries.iter().position(|existing| existing.job_id == entry.job_id) {
+            Some(index) => {
+                if let Some(previous) = entries.remove(index) {
+                    entry.attempts += previous.attempts;
+                }
+            }
+            None => {
+                self.dead_lettered.fetch_add(1, Ordering::Relaxed);
+                telemetry::callback_failed();
+            }
+        }
+        
+        entries.push_back(entry.clone());
+        while entries.len() > self.dead_letter_capacity {
+            entries.pop_front();
+        }
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn sender(capacity: usize, ttl: chrono::Duration) -> CallbackSender {
+        let config = CallbackConfig { dead_letter_capacity: capacity, ..Default::default() };
+        CallbackSender::new(&config, ttl).unwrap()
+    }
+    
+    fn failure(id: &str, failed_at: chrono::DateTime<Utc>) -> DeadLetter {
+        DeadLetter {
+            job_id: id.to_string(),
+            url: "http://receiver.test/hook".to_string(),
+            attempts: 5,
+            last_status: Some(500),
+            last_error: "receiver answered 500 Internal Server Error".to_string(),
+            payload_sha256: String::new(),
+            failed_at,
+        }
+    }
+    
+    #[test]
+    fn dead_letters_are_paged_newest_first_and_bounded() {
+        let sender = sender(3, chrono::Duration::hours(1));
+        for id in ["a", "b", "c", "d"] {
+            sender.dead_letter(failure(id, Utc::now()));
+        }
+        
+        let page = sender.dead_letters(0, 2);
+        assert_eq!(page.total, 3);
+        let ids: Vec<_> = page.items.iter().map(|entry| entry.job_id.as_str()).collect();
+        assert_eq!(ids, ["d", "c"]);
+        let ids: Vec<_> = sender.dead_letters(2, 2).items.iter().map(|entry| entry.job_id.clone()).collect();
+        assert_eq!(ids, ["b"]);
+        assert!(!sender.is_dead_lettered("a"));
+        assert_eq!(sender.counters(), (0, 4));
+    }
+    
+    #[test]
+    fn failed_retries_add_to_the_entry_instead_of_a_new_one() {
+        let sender = sender(10, chrono::Duration::hours(1));
+        sender.dead_letter(failure("a", Utc::now()));
+        let mut retried = failure("a", Utc::now());
+        retried.attempts = 1;
+        assert_eq!(sender.dead_letter(retried).attempts, 6);
+        assert_eq!(sender.dead_letters(0, 10).total, 1);
+        assert_eq!(sender.counters(), (0, 1));
+        
+        sender.forget("a");
+        assert!(!sender.is_dead_lettered("a"));
+    }
+    
+    #[test]
+    fn entries_expire_with_job_retention() {
+        let sender = sender(10, chrono::Duration::hours(1));
+        sender.dead_letter(failure("old", Utc::now() - chrono::Duration::hours(2)));
+        sender.dead_letter(failure("new", Utc::now()));
+        sender.sweep();
+        assert!(!sender.is_dead_lettered("old"));
+        assert!(sender.is_dead_lettered("new"));
+    }
+}
@@ -0,0 +1,353 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Files in the working directory that never influence compilation
+const IGNORED_INPUTS: &[&str] = &["input.txt"];
+
+/// Build directories whose contents are not worth caching
+const IGNORED_OUTPUT_DIRS: &[&str] = &["target"];
+
+/// How long a looked-up toolchain version is trusted before it is checked again
+const TOOLCHAIN_RECHECK: Duration = Duration::from_secs(60);
+
+/// Where a compile runs, which decides what identifies its toolchain
+#[derive(Debug, Clone, Copy)]
+pub enum Toolchain<'a> {
+    /// On the host, natively or in an isolate box: the compiler's `--version`
+    Host,
+    /// In a container of this docker image: the image's content id
+    Image(&'a str),
+}
+
+/// When a toolchain was identified, and its version if it could be
+type ToolchainVersion = (Instant, Option<String>);
+
+/// Size-bounded LRU cache of compiled artifacts keyed by sources, flags and toolchain
+#[derive(Clone)]
+pub struct CompileCache {
+    root: PathBuf,
+    max_bytes: u64,
+    toolchain_versions: Arc<Mutex<HashMap<String, ToolchainVersion>>>,
+    version_ttl: Duration,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+/// Compiled output restored from the cache
+pub struct CachedCompile {
+    pub compile_output: String,
+}
+
+impl CompileCache {
+    /// Create a cache rooted at the given directory
+    pub fn new(root: PathBuf, max_bytes: u64) -> Result<Self> {
+        fs::create_dir_all(&root)?;
+        info!("📦 Compile cache enabled at {} ({} bytes max)", root.display(), max_bytes);
+        
+        Ok(Self {
+            root,
+            max_bytes,
+            toolchain_versions: Arc::new(Mutex::new(HashMap::new())),
+            version_ttl: TOOLCHAIN_RECHECK,
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        })
+    }
+    
+    /// Hash the compile command, toolchain version and every input file;
+    /// None when the toolchain cannot be identified, so the build is not cached
+    pub async fn key(&self, compile_cmd: &[String], working_dir: &Path, toolchain: Toolchain<'_>) -> Result<Option<String>> {
+        let Some(toolchain) = self.toolchain_version(&compile_cmd[0], toolchain).await else {
+            debug!("Not caching the build: the {} toolchain could not be identified", compile_cmd[0]);
+            return Ok(None);
+        };
+        
+        let mut hasher = Sha256::new();
+        hasher.update(toolchain.as_bytes());
+        for arg in compile_cmd {
+            hasher.update(arg.as_bytes());
+            hasher.update([0]);
+        }
+        
+        let mut files = list_files(working_dir, working_dir)?;
+        files.sort();
+        for relative in files {
+            if IGNORED_INPUTS.iter().any(|ignored| relative == Path::new(ignored)) {
+                continue;
+            }
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hasher.update(fs::read(working_dir.join(&relative))?);
+        }
+        
+        Ok(Some(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()))
+    }
+    
+    /// Copy a cached build into the working directory, if present
+    pub fn restore(&self, key: &str, working_dir: &Path) -> Result<Option<CachedCompile>> {
+        let entry = self.root.join(key);
+        let output_file = entry.join("compile_output");
+        
+        if !output_file.is_file() {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return Ok(None);
+        }
+        
+        let files_dir = entry.join("files");
+        for relative in list_files(&files_dir, &files_dir)? {
+            let target = working_dir.join(&relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(files_dir.join(&relative), &target)?;
+        }
+        
+        // Bump the entry's last-used time for LRU eviction
+        fs::File::options().append(true).open(&output_file)?.set_modified(SystemTime::now())?;
+        
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        debug!("Compile cache hit for {}", key);
+        Ok(Some(CachedCompile {
+            compile_output: fs::read_to_string(&output_file)?,
+        }))
+    }
+    
+    /// Store the files a successful compile produced
+    pub fn store(&self, key: &str, working_dir: &Path, before: &HashSet<PathBuf>, compile_output: &str) -> Result<()> {
+        let entry = self.root.join(key);
+        let staging = self.root.join(format!(".{}.tmp", key));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        
+        let files_dir = staging.join("files");
+        fs::create_dir_all(&files_dir)?;
+        
+        for relative in list_files(working_dir, working_dir)? {
+            let ignored = relative.components().next()
+                .is_some_and(|first| IGNORED_OUTPUT_DIRS.iter().any(|dir| first.as_os_str() == *dir));
+            if before.contains(&relative) || ignored {
+                continue;
+            }
+            
+            let target = files_dir.join(&relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(working_dir.join(&relative), &target)?;
+        }
+        fs::write(staging.join("compile_output"), compile_output)?;
+        
+        // Publish atomically so concurrent readers never see a half-written entry
+        if entry.exists() {
+            fs::remove_dir_all(&staging)?;
+        } else if let Err(err) = fs::rename(&staging, &entry) {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(err.into());
+        }
+        
+        self.evict();
+        Ok(())
+    }
+    
+    /// Snapshot of the files present before compiling
+    pub fn snapshot(&self, working_dir: &Path) -> Result<HashSet<PathBuf>> {
+        Ok(list_files(working_dir, working_dir)?.into_iter().collect())
+    }
+    
+    /// Cache hits and misses since startup
+    pub fn counters(&self) -> (u64, u64) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+    
+    /// Remove least recently used entries until the cache fits its size cap
+    fn evict(&self) {
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = match fs::read_dir(&self.root) {
+            Ok(dir) => dir.flatten()
+                .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+                .map(|entry| {
+                    let path = entry.path();
+                    let last_used = fs::metadata(path.join("compile_output"))
+                        .and_then(|meta| meta.modified())
+                        .unwrap_or(SystemTime::UNIX_EPOCH);
+                    (last_used, dir_size(&path), path)
+                })
+                .collect(),
+            Err(err) => {
+                warn!("Could not scan compile cache: {}", err);
+                return;
+            }
+        };
+        
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        entries.sort_by_key(|(last_used, _, _)| *last_used);
+        
+        for (_, size, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            match fs::remove_dir_all(&path) {
+                Ok(()) => {
+                    debug!("Evicted compile cache entry {}", path.display());
+                    total = total.saturating_sub(size);
+                }
+                Err(err) => warn!("Failed to evict {}: {}", path.display(), err),
+            }
+        }
+    }
+    
+    /// Identity of the toolchain that compiles, re-checked once it is older than `version_ttl`
+    async fn toolchain_version(&self, program: &str, toolchain: Toolchain<'_>) -> Option<String> {
+        let memo_key = match toolchain {
+            Toolchain::Host => format!("host:{}", program),
+            Toolchain::Image(image) => format!("image:{}", image),
+        };
+        let mut versions = self.toolchain_versions.lock().await;
+        if let Some((looked_up, version)) = versions.get(&memo_key) {
+            if looked_up.elapsed() < self.version_ttl {
+                return version.clone();
+            }
+        }
+        
+        let version = match toolchain {
+            Toolchain::Host => match tokio::process::Command::new(program).arg("--version").output().await {
+                Ok(output) if output.status.success() => Some(format!(
+                    "{}{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                )),
+                _ => None,
+            },
+            Toolchain::Image(image) => crate::docker::image_id(image).await.ok().map(|id| format!("{}@{}", image, id)),
+        };
+        
+        versions.insert(memo_key, (Instant::now(), version.clone()));
+        version
+    }
+}
+
+/// Recursively list regular files under `dir` relative to `root`
+fn list_files(root: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        
+        if file_type.is_dir() {
+            files.extend(list_files(root, &path)?);
+        } else if file_type.is_file() {
+            if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Total size of the files under a directory
+fn dir_size(dir: &Path) -> u64 {
+    list_files(dir, dir)
+        .map(|files| files.iter().filter_map(|f| fs::metadata(dir.join(f)).ok()).map(|m| m.len()).sum())
+        .unwrap_or(0)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    
+    /// A compiler whose `--version` prints the contents of `version` beside it
+    fn fake_compiler(dir: &Path, version: &str) -> String {
+        let compiler = dir.join("cc");
+        fs::write(dir.join("version"), version).unwrap();
+        fs::write(&compiler, format!("#!/bin/sh\ncat {}\n", dir.join("version").display())).unwrap();
+        fs::set_permissions(&compiler, fs::Permissions::from_mode(0o755)).unwrap();
+        compiler.to_string_lossy().into_owned()
+    }
+    
+    /// Store a build of `bytes` bytes under `key`, as if the compiler had produced it
+    fn store_build(cache: &CompileCache, key: &str, bytes: usize) {
+        let workdir = tempfile::tempdir().unwrap();
+        let before = cache.snapshot(workdir.path()).unwrap();
+        fs::write(workdir.path().join("main"), vec![0u8; bytes]).unwrap();
+        cache.store(key, workdir.path(), &before, "").unwrap();
+    }
+    
+    #[tokio::test]
+    async fn a_new_compiler_version_misses_the_cache() {
+        let root = tempfile::tempdir().unwrap();
+        let tools = tempfile::tempdir().unwrap();
+        let workdir = tempfile::tempdir().unwrap();
+        fs::write(workdir.path().join("main.c"), "int main() {}").unwrap();
+        let compiler = fake_compiler(tools.path(), "cc 1.0");
+        let compile_cmd = [compiler, "main.c".to_string()];
+        
+        let cache = CompileCache { version_ttl: Duration::ZERO, ..CompileCache::new(root.path().to_path_buf(), 1 << 20).unwrap() };
+        let key = cache.key(&compile_cmd, workdir.path(), Toolchain::Host).await.unwrap().unwrap();
+        store_build(&cache, &key, 16);
+        assert_eq!(cache.key(&compile_cmd, workdir.path(), Toolchain::Host).await.unwrap().unwrap(), key);
+        assert!(cache.restore(&key, workdir.path()).unwrap().is_some());
+        
+        // The upgrade is seen once the remembered version is due for a re-check
+        fake_compiler(tools.path(), "cc 2.0");
+        let upgraded = cache.key(&compile_cmd, workdir.path(), Toolchain::Host).await.unwrap().unwrap();
+        assert_ne!(upgraded, key);
+        assert!(cache.restore(&upgraded, workdir.path()).unwrap().is_none());
+        
+        let remembering = CompileCache::new(root.path().to_path_buf(), 1 << 20).unwrap();
+        let first = remembering.key(&compile_cmd, workdir.path(), Toolchain::Host).await.unwrap();
+        fake_compiler(tools.path(), "cc 3.0");
+        assert_eq!(remembering.key(&compile_cmd, workdir.path(), Toolchain::Host).await.unwrap(), first);
+    }
+    
+    #[tokio::test]
+    async fn unidentified_toolchains_are_not_cached() {
+        let root = tempfile::tempdir().unwrap();
+        let workdir = tempfile::tempdir().unwrap();
+        let cache = CompileCache::new(root.path().to_path_buf(), 1 << 20).unwrap();
+        let compile_cmd = ["/nonexistent/cc".to_string(), "main.c".to_string()];
+        assert_eq!(cache.key(&compile_cmd, workdir.path(), Toolchain::Host).await.unwrap(), None);
+        
+        // Without a docker daemon the image has no id to key on
+        if crate::docker::image_id("labforcode-no-such-image:latest").await.is_err() {
+            let compile_cmd = ["gcc".to_string(), "main.c".to_string()];
+            assert_eq!(cache.key(&compile_cmd, workdir.path(), Toolchain::Image("labforcode-no-such-image:latest")).await.unwrap(), None);
+        }
+    }
+    
+    #[test]
+    fn eviction_keeps_the_cache_under_its_cap() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = CompileCache::new(root.path().to_path_buf(), 2500).unwrap();
+        let entry_used = |key: &str, at: u64| {
+            let used = SystemTime::UNIX_EPOCH + Duration::from_secs(at);
+            fs::File::options().append(true).open(root.path().join(key).join("compile_output")).unwrap().set_modified(used).unwrap();
+        };
+        
+        store_build(&cache, "old", 1000);
+        entry_used("old", 1_000);
+        store_build(&cache, "recent", 1000);
+        entry_used("recent", 3_000);
+        store_build(&cache, "restored", 1000);
+        entry_used("restored", 2_000);
+        assert!(!root.path().join("old").exists(), "the least recently used entry is evicted first");
+        assert!(root.path().join("restored").exists() && root.path().join("recent").exists());
+        
+        // Restoring bumps an entry past the others
+        let workdir = tempfile::tempdir().unwrap();
+        assert!(cache.restore("restored", workdir.path()).unwrap().is_some());
+        store_build(&cache, "newest", 1000);
+        assert!(!root.path().join("recent").exists());
+        assert!(root.path().join("restored").exists() && root.path().join("newest").exists());
+        assert!(dir_size(root.path()) <= 2500);
+    }
+}
@@ -0,0 +1,1422 @@
+//! Engine configuration, loaded once at startup and handed to every module.
+//!
+//! [`EngineConfig::load`] starts from the defaults, applies the TOML file named by ENGINE_CONFIG
+//! and then the individual environment variables, so a variable always wins over the file.
+//! Embedders can build an [`EngineConfig`] in code instead and pass it to
+//! [`ExecutionEngine::new`](crate::ExecutionEngine::new).
+use crate::executor::ExecutionBackend;
+use crate::rate_limit::RateLimiter;
+use crate::sandbox::available_cores;
+use crate::types::{CpuAffinity, ExecutionRequest, IsolationLevel, LimitOverrides, OutputFilter, ResourceLimits, MAX_PRIORITY};
+use crate::validation::{RequestValidator, NICE_RANGE};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::de::{DeserializeOwned, IntoDeserializer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Environment variable naming the TOML configuration file
+pub const CONFIG_PATH_VAR: &str = "ENGINE_CONFIG";
+
+/// Everything the engine and its frontends can be tuned with
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    /// HTTP and gRPC frontend settings
+    pub server: ServerConfig,
+    /// Name results and stats report for this instance, defaulting to the host name (INSTANCE_NAME)
+    pub instance_name: Option<String>,
+    /// Jobs run at once (MAX_CONCURRENT_EXECUTIONS)
+    pub workers: usize,
+    /// Jobs allowed to wait before submissions are refused, 0 for unbounded (MAX_QUEUE_DEPTH)
+    pub max_queue_depth: usize,
+    /// Workers take no new jobs while the host has less memory available than this, 0 to disable (MEMORY_FLOOR_BYTES)
+    pub memory_floor_bytes: u64,
+    /// Growing and shrinking `workers` with the queue
+    pub autoscale: AutoscaleConfig,
+    /// What happens to submissions while processing is paused (PAUSED_SUBMISSIONS)
+    pub paused_submissions: PausedSubmissions,
+    /// Where waiting jobs are kept
+    pub queue: QueueConfig,
+    /// How programs are run
+    pub sandbox: SandboxConfig,
+    /// Downloads of `source_url` and `additional_files_url`
+    pub fetch: FetchConfig,
+    /// Limits applied when a request leaves them out, and the most a request may ask for
+    pub limits: LimitsConfig,
+    /// Named limits and options a request selects with `profile`
+    pub profiles: HashMap<String, Profile>,
+    /// More names a language is accepted under, by language key, on top of the built-in ones
+    pub language_aliases: HashMap<String, Vec<String>>,
+    /// Language keys whose submissions are refused (DISABLED_LANGUAGES)
+    pub disabled_languages: Vec<String>,
+    /// JSON file keeping the changes made through PUT /admin/config across restarts (RUNTIME_SETTINGS_PATH)
+    #[schema(value_type = Option<String>)]
+    pub runtime_settings_path: Option<PathBuf>,
+    /// How long finished jobs and their leftovers are kept
+    pub retention: RetentionConfig,
+    /// Per-client admission limits
+    pub rate_limit: RateLimitConfig,
+    /// JSON file with per-client quotas (QUOTA_CONFIG)
+    #[schema(value_type = Option<String>)]
+    pub quota_config: Option<PathBuf>,
+    /// Where lifecycle events are sent
+    pub audit: AuditConfig,
+    /// How results are posted to `callback_url`
+    pub callbacks: CallbackConfig,
+    /// S3-compatible bucket finished results are copied to
+    pub archive: ArchiveConfig,
+    /// Pulling images and building a hello-world per language before reporting ready
+    pub warmup: WarmupConfig,
+}
+
+/// HTTP and gRPC frontend settings
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// HTTP port (RUST_ENGINE_PORT)
+    pub port: u16,
+    /// gRPC port; the gRPC API is off without it (GRPC_PORT)
+    pub grpc_port: Option<u16>,
+    /// Key that unlocks the admin endpoints (ADMIN_API_KEY); never shown by /admin/config
+    #[serde(skip_serializing)]
+    pub admin_api_key: Option<String>,
+    /// How long shutdown waits for running jobs (SHUTDOWN_DRAIN_TIMEOUT_SECS)
+    pub shutdown_drain_timeout_secs: u64,
+    /// Responses smaller than this are sent uncompressed (COMPRESSION_MIN_BYTES)
+    pub compression_min_bytes: u16,
+    /// Largest single multipart part (MULTIPART_MAX_PART_BYTES)
+    pub multipart_max_part_bytes: usize,
+    /// Largest /execute body (MULTIPART_MAX_TOTAL_BYTES)
+    pub multipart_max_total_bytes: usize,
+    /// Announced in the Sunset header of unversioned routes (LEGACY_ROUTES_SUNSET, RFC 3339)
+    pub legacy_routes_sunset: Option<DateTime<Utc>>,
+    /// Cross-origin policy
+    pub cors: CorsConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            grpc_port: None,
+            admin_api_key: None,
+            shutdown_drain_timeout_secs: 30,
+            compression_min_bytes: 1024,
+            multipart_max_part_bytes: 16 * 1024 * 1024, // 16MB
+            multipart_max_total_bytes: 64 * 1024 * 1024, // 64MB
+            legacy_routes_sunset: None,
+            cors: CorsConfig::default(),
+        }
+    }
+}
+
+/// Cross-origin policy; every origin is allowed while `allowed_origins` is empty
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Exact origins, `https://*.example.com` patterns or `*` (CORS_ALLOWED_ORIGINS)
+    pub allowed_origins: Vec<String>,
+    /// Methods allowed in cross-origin requests (CORS_ALLOWED_METHODS)
+    pub allowed_methods: Vec<String>,
+    /// Allow cookies and authorization headers (CORS_ALLOW_CREDENTIALS)
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight answer (CORS_MAX_AGE_SECS)
+    pub max_age_secs: u64,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: ["GET", "POST", "PUT", "DELETE", "OPTIONS"].map(String::from).to_vec(),
+            allow_credentials: false,
+            max_age_secs: 600,
+        }
+    }
+}
+
+/// What happens to submissions while processing is paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PausedSubmissions {
+    /// Queue them for when processing resumes
+    #[default]
+    Accept,
+    /// Refuse them with 503
+    Reject,
+}
+
+/// Which queue waiting jobs are kept in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueBackend {
+    /// In this process; lost on restart
+    #[default]
+    Memory,
+    /// In Redis, shared between instances
+    Redis,
+}
+
+/// Growing and shrinking the worker pool with the queue; `workers` is the starting size
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutoscaleConfig {
+    /// Whether the pool is resized at all (AUTOSCALE_ENABLED)
+    pub enabled: bool,
+    /// Fewest workers kept, even when idle (AUTOSCALE_MIN_WORKERS)
+    pub min_workers: usize,
+    /// Most workers started under load (AUTOSCALE_MAX_WORKERS)
+    pub max_workers: usize,
+    /// Queued jobs that count as a backlog (AUTOSCALE_UP_QUEUE_DEPTH)
+    pub scale_up_queue_depth: usize,
+    /// Estimated wait that counts as a backlog regardless of depth, 0 disables (AUTOSCALE_UP_WAIT_SECS)
+    pub scale_up_wait_secs: f64,
+    /// How long a backlog must last before workers are added (AUTOSCALE_UP_AFTER_SECS)
+    pub scale_up_after_secs: u64,
+    /// How long the queue must stay empty with workers idle before some are retired (AUTOSCALE_DOWN_AFTER_SECS)
+    pub scale_down_after_secs: u64,
+    /// Workers added or retired at a time (AUTOSCALE_STEP)
+    pub step: usize,
+}
+
+impl Default for AutoscaleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_workers: 1,
+            max_workers: 2 * std::thread::available_parallelism().map_or(1, |n| n.get()),
+            scale_up_queue_depth: 10,
+            scale_up_wait_secs: 30.0,
+            scale_up_after_secs: 10,
+            scale_down_after_secs: 120,
+            step: 1,
+        }
+    }
+}
+
+/// Where waiting jobs are kept
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueueConfig {
+    /// Queue implementation (QUEUE_BACKEND)
+    pub backend: QueueBackend,
+    /// Redis server for the redis backend (REDIS_URL)
+    pub redis_url: String,
+    /// How long a taken job may go unacknowledged before Redis hands it out again (QUEUE_VISIBILITY_TIMEOUT_SECS)
+    pub visibility_timeout_secs: u64,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            backend: QueueBackend::Memory,
+            redis_url: "redis://127.0.0.1:6379".to_string(),
+            visibility_timeout_secs: 600,
+        }
+    }
+}
+
+/// How programs are run
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Host processes or pooled containers (EXECUTION_BACKEND)
+    pub backend: ExecutionBackend,
+    /// Parent of the per-job working directories (TEMP_BASE)
+    #[schema(value_type = String)]
+    pub temp_base: PathBuf,
+    /// Working directories older than this are swept as leaked (TEMP_MAX_AGE_SECS)
+    pub temp_max_age_secs: u64,
+    /// Jobs fail fast while the volume under `temp_base` has less free space than this, 0 to disable (TEMP_MIN_FREE_BYTES)
+    pub temp_min_free_bytes: u64,
+    /// Total artifact content returned inline per result (ARTIFACT_MAX_BYTES)
+    pub artifact_max_bytes: u64,
+    /// stdout and stderr past this many bytes are dropped, leaving the result flagged as truncated (OUTPUT_MAX_BYTES)
+    pub output_max_bytes: usize,
+    /// Directory compiled programs are cached in; no caching without it (COMPILE_CACHE_DIR)
+    #[schema(value_type = Option<String>)]
+    pub compile_cache_dir: Option<PathBuf>,
+    /// Size the compile cache is trimmed to (COMPILE_CACHE_MAX_BYTES)
+    pub compile_cache_max_bytes: u64,
+    /// Give native jobs a mount namespace with a read-only root, a private /tmp, hidden processes and no view
+    /// of other jobs' directories; skipped with a warning where namespaces are unavailable (ISOLATE_FILESYSTEM)
+    pub isolate_filesystem: bool,
+    /// Size of the private /tmp of isolated jobs (PRIVATE_TMP_BYTES)
+    pub private_tmp_bytes: u64,
+    /// Cores jobs pinned with `cpu_affinity: "auto"` are spread over, keeping the rest for the engine;
+    /// every available core when empty (CPU_POOL)
+    pub cpu_pool: Vec<usize>,
+    /// Container pool used by the docker backend
+    pub docker: DockerPoolConfig,
+    /// Boxes used by the isolate backend
+    pub isolate: IsolateConfig,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            backend: ExecutionBackend::Process,
+            temp_base: std::env::temp_dir().join("labforcode-rust"),
+            temp_max_age_secs: 3600,
+            temp_min_free_bytes: 100 * 1024 * 1024, // 100MB
+            artifact_max_bytes: 5 * 1024 * 1024, // 5MB
+            output_max_bytes: 64 * 1024 * 1024, // 64MB
+            compile_cache_dir: None,
+            compile_cache_max_bytes: 512 * 1024 * 1024, // 512MB
+            isolate_filesystem: true,
+            private_tmp_bytes: 64 * 1024 * 1024, // 64MB
+            cpu_pool: Vec::new(),
+            docker: DockerPoolConfig::default(),
+            isolate: IsolateConfig::default(),
+        }
+    }
+}
+
+/// Container pool used by the docker backend
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct DockerPoolConfig {
+    /// Warm containers kept per image (DOCKER_POOL_SIZE)
+    pub pool_size: usize,
+    /// Pool size overrides by language, `python=4,...` in the environment (DOCKER_POOL_SIZES)
+    pub pool_sizes: HashMap<String, usize>,
+    /// Idle pools are emptied after this long (DOCKER_POOL_IDLE_SECS)
+    pub idle_secs: u64,
+    /// OCI runtime containers are started with (DOCKER_RUNTIME)
+    pub runtime: String,
+    /// Runtime of jobs that ask for `isolation: "strong"`, normally gVisor (DOCKER_STRONG_RUNTIME)
+    pub strong_runtime: String,
+}
+
+impl Default for DockerPoolConfig {
+    fn default() -> Self {
+        Self {
+            pool_size: 2,
+            pool_sizes: HashMap::new(),
+            idle_secs: 600,
+            runtime: "runc".to_string(),
+            strong_runtime: "runsc".to_string(),
+        }
+    }
+}
+
+/// Boxes of the isolate backend, the sandbox of the IOI
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct IsolateConfig {
+    /// The isolate binary, usually installed setuid root (ISOLATE_BIN)
+    pub binary: String,
+    /// Box ids available to jobs; a job waits when all are in use (ISOLATE_BOXES)
+    pub boxes: usize,
+    /// Lowest box id used, to share a host with other isolate users (ISOLATE_FIRST_BOX_ID)
+    pub first_box_id: u32,
+    /// Meter and limit memory per box with cgroups (ISOLATE_CGROUPS)
+    pub cgroups: bool,
+}
+
+impl Default for IsolateConfig {
+    fn default() -> Self {
+        Self {
+            binary: "isolate".to_string(),
+            boxes: 100,
+            first_box_id: 0,
+            cgroups: true,
+        }
+    }
+}
+
+/// Downloads of `source_url` and `additional_files_url`; refused while `allowed_hosts` is empty
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct FetchConfig {
+    /// Hosts that may be fetched from: `files.example.com`, `*.example.com` or `*` (FETCH_ALLOWED_HOSTS)
+    pub allowed_hosts: Vec<String>,
+    /// Largest download (FETCH_MAX_BYTES)
+    pub max_bytes: u64,
+    /// How long one download may take; not counted against the job's limits (FETCH_TIMEOUT_SECS)
+    pub timeout_secs: u64,
+    /// Redirects followed before a download fails (FETCH_MAX_REDIRECTS)
+    pub max_redirects: usize,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        Self {
+            allowed_hosts: Vec::new(),
+            max_bytes: 16 * 1024 * 1024, // 16MB
+            timeout_secs: 30,
+            max_redirects: 3,
+        }
+    }
+}
+
+/// Limits applied when a request leaves them out, and the most a request may ask for
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Limits of requests that do not set their own (DEFAULT_* variables)
+    pub defaults: ResourceLimits,
+    /// Server ceilings (MAX_* variables and LIMITS_MODE)
+    pub ceilings: RequestValidator,
+    /// Defaults and maxima per language key, over the built-in ones
+    pub languages: HashMap<String, LanguageLimits>,
+}
+
+/// A language's own defaults and maxima, applied between the global defaults and ceilings
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LanguageLimits {
+    /// Limits of requests in this language that do not set their own, over `limits.defaults`
+    pub defaults: LimitOverrides,
+    /// Most a request in this language may ask for, under `limits.ceilings`
+    pub max: LimitOverrides,
+    /// Largest source_code in this language, replacing `limits.ceilings.max_source_bytes` even when higher
+    pub max_source_bytes: Option<usize>,
+}
+
+/// Named limits and options a request selects with `profile`; the request's own fields still win
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Limits of requests that select this profile and do not set their own
+    pub limits: LimitOverrides,
+    /// Options of requests that select this profile and do not set their own
+    pub options: ProfileOptions,
+}
+
+/// Execution options a profile fills in
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Merge stderr into stdout
+    pub redirect_stderr_to_stdout: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Allow network access
+    pub enable_network: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Run the program this many times
+    pub number_of_runs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Give each run its own copy of the built working directory
+    pub fresh_workdir_per_run: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Sandbox boundary
+    pub isolation: Option<IsolationLevel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Escape sequences or control characters removed from text outputs
+    pub output_filter: Option<OutputFilter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Turn CRLF into LF in text outputs
+    pub normalize_newlines: Option<bool>,
+}
+
+impl Profile {
+    /// Fill in the limits and options the request leaves out
+    pub fn apply(&self, request: &mut ExecutionRequest) {
+        fn fill<T>(target: &mut Option<T>, value: Option<T>) {
+            if target.is_none() {
+                *target = value;
+            }
+        }
+        
+        let limits = &self.limits;
+        fill(&mut request.cpu_time_limit, limits.cpu_time);
+        fill(&mut request.wall_time_limit, limits.wall_time);
+        fill(&mut request.memory_limit, limits.memory);
+        fill(&mut request.stack_limit, limits.stack_limit);
+        fill(&mut request.max_file_size, limits.file_size);
+        fill(&mut request.workdir_quota, limits.workdir_quota);
+        fill(&mut request.max_processes_and_or_threads, limits.processes);
+        
+        let options = &self.options;
+        fill(&mut request.redirect_stderr_to_stdout, options.redirect_stderr_to_stdout);
+        fill(&mut request.enable_network, options.enable_network);
+        fill(&mut request.number_of_runs, options.number_of_runs);
+        fill(&mut request.fresh_workdir_per_run, options.fresh_workdir_per_run);
+        fill(&mut request.isolation, options.isolation);
+        fill(&mut request.output_filter, options.output_filter);
+        fill(&mut request.normalize_newlines, options.normalize_newlines);
+    }
+}
+
+/// How long finished jobs and their leftovers are kept
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// Where working directories kept for inspection go (PRESERVE_DIR), in a `labforcode-workdirs` subdirectory
+    #[schema(value_type = String)]
+    pub preserve_dir: PathBuf,
+    /// How long preserved working directories and dead-lettered callbacks are kept (PRESERVE_TTL_SECS)
+    pub preserve_ttl_secs: i64,
+    /// How long an idempotency key answers repeated submissions (IDEMPOTENCY_TTL_SECS)
+    pub idempotency_ttl_secs: i64,
+    /// How long a finished job answers identical `dedupe` submissions (DEDUPE_WINDOW_SECS)
+    pub dedupe_window_secs: i64,
+    /// SQLite file jobs and usage are persisted to; nothing survives a restart without it (JOB_STORE_PATH)
+    #[schema(value_type = Option<String>)]
+    pub job_store_path: Option<PathBuf>,
+    /// Where outputs too large to embed in a result are kept (OUTPUT_SPILL_DIR)
+    #[schema(value_type = String)]
+    pub spill_dir: PathBuf,
+    /// stdout or stderr longer than this is served as a download instead, 0 disables (OUTPUT_SPILL_BYTES)
+    pub output_spill_bytes: usize,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            preserve_dir: std::env::temp_dir().join("labforcode-preserved"),
+            preserve_ttl_secs: 3600,
+            idempotency_ttl_secs: 24 * 3600,
+            dedupe_window_secs: 60,
+            job_store_path: None,
+            spill_dir: std::env::temp_dir().join("labforcode-spill"),
+            output_spill_bytes: 8 * 1024 * 1024,
+        }
+    }
+}
+
+/// Per-client admission limits; clients are identified by API key, else by IP
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Sustained submissions per minute, 0 disables (RATE_LIMIT_PER_MINUTE)
+    pub per_minute: u32,
+    /// Submissions allowed in a burst; `per_minute` when unset (RATE_LIMIT_BURST)
+    pub burst: Option<u32>,
+    /// Queued or running jobs per client, 0 disables (MAX_CONCURRENT_PER_CLIENT)
+    pub max_running_per_client: usize,
+    /// API keys that are limited on their own; other keys count against the caller's IP (API_KEYS)
+    pub api_keys: Vec<String>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_minute: 60,
+            burst: None,
+            max_running_per_client: 4,
+            api_keys: Vec::new(),
+        }
+    }
+}
+
+/// Where lifecycle events are sent; without a path or URL they only stay on their jobs
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// JSON lines file (AUDIT_LOG_PATH)
+    #[schema(value_type = Option<String>)]
+    pub log_path: Option<PathBuf>,
+    /// Endpoint each event is POSTed to (AUDIT_HTTP_URL)
+    pub http_url: Option<String>,
+    /// Size at which the file is rotated (AUDIT_LOG_MAX_BYTES)
+    pub max_bytes: u64,
+    /// Rotated files kept next to the live one (AUDIT_LOG_MAX_FILES)
+    pub max_files: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            log_path: None,
+            http_url: None,
+            max_bytes: 100 * 1024 * 1024, // 100MB
+            max_files: 5,
+        }
+    }
+}
+
+/// How results are posted to `callback_url`; secrets are never shown by /admin/config
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct CallbackConfig {
+    /// Signs callbacks of submitters without a secret of their own (CALLBACK_SECRET)
+    #[serde(skip_serializing)]
+    pub secret: Option<String>,
+    /// Signing secret per API key (CALLBACK_SECRETS, `key=secret,...`)
+    #[serde(skip_serializing)]
+    pub secrets: HashMap<String, String>,
+    /// How long a receiver may take to answer one attempt (CALLBACK_TIMEOUT_SECS)
+    pub timeout_secs: u64,
+    /// Attempts before a callback is given up (CALLBACK_MAX_ATTEMPTS)
+    pub max_attempts: u32,
+    /// Given-up callbacks kept for /admin/callbacks/failed; the oldest are dropped past this (CALLBACK_DEAD_LETTER_CAPACITY)
+    pub dead_letter_capacity: usize,
+}
+
+impl Default for CallbackConfig {
+    fn default() -> Self {
+        Self {
+            secret: None,
+            secrets: HashMap::new(),
+            timeout_secs: 10,
+            max_attempts: 5,
+            dead_letter_capacity: 1000,
+        }
+    }
+}
+
+/// S3-compatible bucket finished results are copied to; archival is off without a bucket
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchiveConfig {
+    /// Bucket name (ARCHIVE_BUCKET)
+    pub bucket: Option<String>,
+    /// Service URL; buckets are addressed path-style under it (ARCHIVE_ENDPOINT)
+    pub endpoint: String,
+    /// Region requests are signed for (ARCHIVE_REGION)
+    pub region: String,
+    /// Access key id (ARCHIVE_ACCESS_KEY_ID)
+    pub access_key_id: Option<String>,
+    /// Secret access key (ARCHIVE_SECRET_ACCESS_KEY); never shown by /admin/config
+    #[serde(skip_serializing)]
+    pub secret_access_key: Option<String>,
+    /// Results are stored as `<prefix>/<date>/<id>.json` (ARCHIVE_PREFIX)
+    pub prefix: String,
+    /// How GET /result answers for a job only the archive still has (ARCHIVE_SERVE)
+    pub serve: ArchiveServe,
+    /// How long a redirect's presigned URL stays valid (ARCHIVE_PRESIGN_TTL_SECS)
+    pub presign_ttl_secs: u64,
+    /// Archived jobs finished this long ago are dropped from memory and the job store; kept when unset (ARCHIVE_EVICT_AFTER_SECS)
+    pub evict_after_secs: Option<u64>,
+    /// Days of date prefixes searched for a job only the archive has (ARCHIVE_LOOKUP_DAYS)
+    pub lookup_days: u32,
+    /// How long one request to the bucket may take (ARCHIVE_TIMEOUT_SECS)
+    pub timeout_secs: u64,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            bucket: None,
+            endpoint: "https://s3.amazonaws.com".to_string(),
+            region: "us-east-1".to_string(),
+            access_key_id: None,
+            secret_access_key: None,
+            prefix: "results".to_string(),
+            serve: ArchiveServe::Proxy,
+            presign_ttl_secs: 300,
+            evict_after_secs: None,
+            lookup_days: 7,
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// How GET /result answers for a job only the archive still has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveServe {
+    /// Fetch the result and return it as if it were local
+    #[default]
+    Proxy,
+    /// Answer 302 with a presigned URL of the archived object
+    Redirect,
+}
+
+/// Startup warm-up, so the first real submission of a language doesn't pay for a cold image or toolchain
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct WarmupConfig {
+    /// Pull each language's image and build and run a hello-world per compiled language (WARMUP)
+    pub enabled: bool,
+    /// /health reports ready after this long even if warm-up has not finished (WARMUP_TIMEOUT_SECS)
+    pub timeout_secs: u64,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_secs: 300,
+        }
+    }
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            server: ServerConfig::default(),
+            instance_name: None,
+            workers: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            max_queue_depth: 1000,
+            memory_floor_bytes: 128 * 1024 * 1024,
+            autoscale: AutoscaleConfig::default(),
+            paused_submissions: PausedSubmissions::Accept,
+            queue: QueueConfig::default(),
+            sandbox: SandboxConfig::default(),
+            fetch: FetchConfig::default(),
+            limits: LimitsConfig::default(),
+            profiles: HashMap::new(),
+            language_aliases: HashMap::new(),
+            disabled_languages: Vec::new(),
+            runtime_settings_path: None,
+            retention: RetentionConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            quota_config: None,
+            audit: AuditConfig::default(),
+            callbacks: CallbackConfig::default(),
+            archive: ArchiveConfig::default(),
+            warmup: WarmupConfig::default(),
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Defaults, then the file named by ENGINE_CONFIG, then the environment, then the settings
+    /// saved by PUT /admin/config; validated
+    pub fn load() -> Result<Self> {
+        let mut config = match env_value(CONFIG_PATH_VAR) {
+            Some(path) => Self::from_file(Path::new(&path))?,
+            None => Self::default(),
+        };
+        config.apply_env()?;
+        if let Some(path) = config.runtime_settings_path.clone() {
+            if let Some(saved) = RuntimeSettings::read(&path)? {
+                saved.apply(&mut config)
+                    .with_context(|| format!("applying runtime settings from {}", path.display()))?;
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+    
+    /// Defaults overridden by the environment; validated
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+    
+    /// Defaults overridden by a TOML file; not validated, so the environment can still fix it up
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading configuration file {}", path.display()))?;
+        toml::from_str(&text)
+            .with_context(|| format!("parsing configuration file {}", path.display()))
+    }
+    
+    /// Override settings whose environment variable is set and not empty
+    pub fn apply_env(&mut self) -> Result<()> {
+        let server = &mut self.server;
+        parse("RUST_ENGINE_PORT", &mut server.port)?;
+        parse_option("GRPC_PORT", &mut server.grpc_port)?;
+        if let Some(key) = env_value("ADMIN_API_KEY") {
+            server.admin_api_key = Some(key);
+        }
+        parse("SHUTDOWN_DRAIN_TIMEOUT_SECS", &mut server.shutdown_drain_timeout_secs)?;
+        parse("COMPRESSION_MIN_BYTES", &mut server.compression_min_bytes)?;
+        parse("MULTIPART_MAX_PART_BYTES", &mut server.multipart_max_part_bytes)?;
+        parse("MULTIPART_MAX_TOTAL_BYTES", &mut server.multipart_max_total_bytes)?;
+        parse_option("LEGACY_ROUTES_SUNSET", &mut server.legacy_routes_sunset)?;
+        parse_list("CORS_ALLOWED_ORIGINS", &mut server.cors.allowed_origins)?;
+        parse_list("CORS_ALLOWED_METHODS", &mut server.cors.allowed_methods)?;
+        parse("CORS_ALLOW_CREDENTIALS", &mut server.cors.allow_credentials)?;
+        parse("CORS_MAX_AGE_SECS", &mut server.cors.max_age_secs)?;
+        
+        parse_option("INSTANCE_NAME", &mut self.instance_name)?;
+        parse("MAX_CONCURRENT_EXECUTIONS", &mut self.workers)?;
+        parse("MAX_QUEUE_DEPTH", &mut self.max_queue_depth)?;
+        parse("MEMORY_FLOOR_BYTES", &mut self.memory_floor_bytes)?;
+        parse_enum("PAUSED_SUBMISSIONS", &mut self.paused_submissions)?;
+        
+        let autoscale = &mut self.autoscale;
+        parse("AUTOSCALE_ENABLED", &mut autoscale.enabled)?;
+        parse("AUTOSCALE_MIN_WORKERS", &mut autoscale.min_workers)?;
+        parse("AUTOSCALE_MAX_WORKERS", &mut autoscale.max_workers)?;
+        parse("AUTOSCALE_UP_QUEUE_DEPTH", &mut autoscale.scale_up_queue_depth)?;
+        parse("AUTOSCALE_UP_WAIT_SECS", &mut autoscale.scale_up_wait_secs)?;
+        parse("AUTOSCALE_UP_AFTER_SECS", &mut autoscale.scale_up_after_secs)?;
+        parse("AUTOSCALE_DOWN_AFTER_SECS", &mut autoscale.scale_down_after_secs)?;
+        parse("AUTOSCALE_STEP", &mut autoscale.step)?;
+        
+        parse_enum("QUEUE_BACKEND", &mut self.queue.backend)?;
+        parse("REDIS_URL", &mut self.queue.redis_url)?;
+        parse("QUEUE_VISIBILITY_TIMEOUT_SECS", &mut self.queue.visibility_timeout_secs)?;
+        
+        let sandbox = &mut self.sandbox;
+        parse_enum("EXECUTION_BACKEND", &mut sandbox.backend)?;
+        parse("TEMP_BASE", &mut sandbox.temp_base)?;
+        parse("TEMP_MAX_AGE_SECS", &mut sandbox.temp_max_age_secs)?;
+        parse("TEMP_MIN_FREE_BYTES", &mut sandbox.temp_min_free_bytes)?;
+        parse("ARTIFACT_MAX_BYTES", &mut sandbox.artifact_max_bytes)?;
+        parse("OUTPUT_MAX_BYTES", &mut sandbox.output_max_bytes)?;
+        parse_option("COMPILE_CACHE_DIR", &mut sandbox.compile_cache_dir)?;
+        parse("COMPILE_CACHE_MAX_BYTES", &mut sandbox.compile_cache_max_bytes)?;
+        parse("ISOLATE_FILESYSTEM", &mut sandbox.isolate_filesystem)?;
+        parse("PRIVATE_TMP_BYTES", &mut sandbox.private_tmp_bytes)?;
+        parse_list("CPU_POOL", &mut sandbox.cpu_pool)?;
+        parse("DOCKER_POOL_SIZE", &mut sandbox.docker.pool_size)?;
+        parse_map("DOCKER_POOL_SIZES", &mut sandbox.docker.pool_sizes)?;
+        parse("DOCKER_POOL_IDLE_SECS", &mut sandbox.docker.idle_secs)?;
+        parse("DOCKER_RUNTIME", &mut sandbox.docker.runtime)?;
+        parse("DOCKER_STRONG_RUNTIME", &mut sandbox.docker.strong_runtime)?;
+        parse("ISOLATE_BIN", &mut sandbox.isolate.binary)?;
+        parse("ISOLATE_BOXES", &mut sandbox.isolate.boxes)?;
+        parse("ISOLATE_FIRST_BOX_ID", &mut sandbox.isolate.first_box_id)?;
+        parse("ISOLATE_CGROUPS", &mut sandbox.isolate.cgroups)?;
+        
+        parse_list("FETCH_ALLOWED_HOSTS", &mut self.fetch.allowed_hosts)?;
+        parse("FETCH_MAX_BYTES", &mut self.fetch.max_bytes)?;
+        parse("FETCH_TIMEOUT_SECS", &mut self.fetch.timeout_secs)?;
+        parse("FETCH_MAX_REDIRECTS", &mut self.fetch.max_redirects)?;
+        
+        let defaults = &mut self.limits.defaults;
+        parse("DEFAULT_CPU_TIME_LIMIT", &mut defaults.cpu_time)?;
+        parse("DEFAULT_WALL_TIME_LIMIT", &mut defaults.wall_time)?;
+        parse("DEFAULT_MEMORY_LIMIT", &mut defaults.memory)?;
+        parse("DEFAULT_STACK_LIMIT", &mut defaults.stack_limit)?;
+        parse("DEFAULT_MAX_FILE_SIZE", &mut defaults.file_size)?;
+        parse("DEFAULT_WORKDIR_QUOTA", &mut defaults.workdir_quota)?;
+        parse("DEFAULT_MAX_PROCESSES", &mut defaults.processes)?;
+        parse_option("DEFAULT_CPU_AFFINITY", &mut defaults.cpu_affinity)?;
+        parse_option("DEFAULT_NICE", &mut defaults.nice)?;
+        
+        let ceilings = &mut self.limits.ceilings;
+        parse_enum("LIMITS_MODE", &mut ceilings.mode)?;
+        parse("REJECT_UNKNOWN_FIELDS", &mut ceilings.reject_unknown_fields)?;
+        parse("MAX_SOURCE_BYTES", &mut ceilings.max_source_bytes)?;
+        parse("MAX_ADDITIONAL_FILES_BYTES", &mut ceilings.max_additional_files_bytes)?;
+        parse("MAX_CPU_TIME_LIMIT", &mut ceilings.max_cpu_time)?;
+        parse("MAX_WALL_TIME_LIMIT", &mut ceilings.max_wall_time)?;
+        parse("MAX_MEMORY_LIMIT", &mut ceilings.max_memory)?;
+        parse("MAX_STACK_LIMIT", &mut ceilings.max_stack)?;
+        parse("MAX_PROCESSES", &mut ceilings.max_processes)?;
+        parse("MAX_FILE_SIZE", &mut ceilings.max_file_size)?;
+        parse("MAX_WORKDIR_QUOTA", &mut ceilings.max_workdir_quota)?;
+        parse("MAX_NUMBER_OF_RUNS", &mut ceilings.max_runs)?;
+        parse("MAX_TEST_CASES", &mut ceilings.max_test_cases)?;
+        parse("MAX_RETRIES", &mut ceilings.max_retries)?;
+        parse("MAX_CLIENT_PRIORITY", &mut ceilings.max_client_priority)?;
+        parse_map("PRIORITY_CAPS", &mut ceilings.priority_caps)?;
+        
+        let retention = &mut self.retention;
+        parse("PRESERVE_DIR", &mut retention.preserve_dir)?;
+        parse("PRESERVE_TTL_SECS", &mut retention.preserve_ttl_secs)?;
+        parse("IDEMPOTENCY_TTL_SECS", &mut retention.idempotency_ttl_secs)?;
+        parse("DEDUPE_WINDOW_SECS", &mut retention.dedupe_window_secs)?;
+        parse_option("JOB_STORE_PATH", &mut retention.job_store_path)?;
+        parse("OUTPUT_SPILL_DIR", &mut retention.spill_dir)?;
+        parse("OUTPUT_SPILL_BYTES", &mut retention.output_spill_bytes)?;
+        
+        parse("RATE_LIMIT_PER_MINUTE", &mut self.rate_limit.per_minute)?;
+        parse_option("RATE_LIMIT_BURST", &mut self.rate_limit.burst)?;
+        parse("MAX_CONCURRENT_PER_CLIENT", &mut self.rate_limit.max_running_per_client)?;
+        parse_list("API_KEYS", &mut self.rate_limit.api_keys)?;
+        
+        parse_option("QUOTA_CONFIG", &mut self.quota_config)?;
+        parse_list("DISABLED_LANGUAGES", &mut self.disabled_languages)?;
+        parse_option("RUNTIME_SETTINGS_PATH", &mut self.runtime_settings_path)?;
+        
+        parse_option("AUDIT_LOG_PATH", &mut self.audit.log_path)?;
+        parse_option("AUDIT_HTTP_URL", &mut self.audit.http_url)?;
+        parse("AUDIT_LOG_MAX_BYTES", &mut self.audit.max_bytes)?;
+        parse("AUDIT_LOG_MAX_FILES", &mut self.audit.max_files)?;
+        
+        if let Some(secret) = env_value("CALLBACK_SECRET") {
+            self.callbacks.secret = Some(secret);
+        }
+        parse_map("CALLBACK_SECRETS", &mut self.callbacks.secrets)?;
+        parse("CALLBACK_TIMEOUT_SECS", &mut self.callbacks.timeout_secs)?;
+        parse("CALLBACK_MAX_ATTEMPTS", &mut self.callbacks.max_attempts)?;
+        parse("CALLBACK_DEAD_LETTER_CAPACITY", &mut self.callbacks.dead_letter_capacity)?;
+        
+        let archive = &mut self.archive;
+        parse_option("ARCHIVE_BUCKET", &mut archive.bucket)?;
+        parse("ARCHIVE_ENDPOINT", &mut archive.endpoint)?;
+        parse("ARCHIVE_REGION", &mut archive.region)?;
+        parse_option("ARCHIVE_ACCESS_KEY_ID", &mut archive.access_key_id)?;
+        parse_option("ARCHIVE_SECRET_ACCESS_KEY", &mut archive.secret_access_key)?;
+        parse("ARCHIVE_PREFIX", &mut archive.prefix)?;
+        parse_enum("ARCHIVE_SERVE", &mut archive.serve)?;
+        parse("ARCHIVE_PRESIGN_TTL_SECS", &mut archive.presign_ttl_secs)?;
+        parse_option("ARCHIVE_EVICT_AFTER_SECS", &mut archive.evict_after_secs)?;
+        parse("ARCHIVE_LOOKUP_DAYS", &mut archive.lookup_days)?;
+        parse("ARCHIVE_TIMEOUT_SECS", &mut archive.timeout_secs)?;
+        
+        parse("WARMUP", &mut self.warmup.enabled)?;
+        parse("WARMUP_TIMEOUT_SECS", &mut self.warmup.timeout_secs)?;
+        Ok(())
+    }
+    
+    /// Check settings that are valid on their own but make no sense, reporting every problem at once
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        
+        if self.server.port == 0 {
+            problems.push("server.port must not be 0".to_string());
+        }
+        if self.server.grpc_port == Some(self.server.port) {
+            problems.push(format!("server.grpc_port must differ from server.port ({})", self.server.port));
+        }
+        if self.server.multipart_max_part_bytes > self.server.multipart_max_total_bytes {
+            problems.push(format!(
+                "server.multipart_max_part_bytes ({}) exceeds server.multipart_max_total_bytes ({})",
+                self.server.multipart_max_part_bytes, self.server.multipart_max_total_bytes,
+            ));
+        }
+        for method in &self.server.cors.allowed_methods {
+            if axum::http::Method::from_bytes(method.as_bytes()).is_err() {
+                problems.push(format!("server.cors.allowed_methods: '{}' is not an HTTP method", method));
+            }
+        }
+        
+        if self.instance_name.as_deref().is_some_and(|name| name.trim().is_empty()) {
+            problems.push("instance_name must not be blank".to_string());
+        }
+        if self.workers == 0 {
+            problems.push("workers must be at least 1".to_string());
+        }
+        let autoscale = &self.autoscale;
+        if autoscale.min_workers == 0 {
+            problems.push("autoscale.min_workers must be at least 1".to_string());
+        }
+        if autoscale.min_workers > autoscale.max_workers {
+            problems.push(format!(
+                "autoscale.min_workers ({}) exceeds autoscale.max_workers ({})",
+                autoscale.min_workers, autoscale.max_workers,
+            ));
+        } else if autoscale.enabled && !(autoscale.min_workers..=autoscale.max_workers).contains(&self.workers) {
+            problems.push(format!(
+                "workers ({}) must be between autoscale.min_workers ({}) and autoscale.max_workers ({})",
+                self.workers, autoscale.min_workers, autoscale.max_workers,
+            ));
+        }
+        if autoscale.scale_up_queue_depth == 0 {
+            problems.push("autoscale.scale_up_queue_depth must be at least 1".to_string());
+        }
+        if autoscale.scale_up_wait_secs.is_nan() || autoscale.scale_up_wait_secs < 0.0 {
+            problems.push("autoscale.scale_up_wait_secs must not be negative".to_string());
+        }
+        if autoscale.step == 0 {
+            problems.push("autoscale.step must be at least 1".to_string());
+        }
+        if self.queue.backend == QueueBackend::Redis
+            && !["redis://", "rediss://", "redis+unix://"].iter().any(|scheme| self.queue.redis_url.starts_with(scheme))
+        {
+            problems.push(format!("queue.redis_url '{}' is not a redis:// URL", redact_url(&self.queue.redis_url)));
+        }
+        if self.queue.visibility_timeout_secs == 0 {
+            problems.push("queue.visibility_timeout_secs must be at least 1".to_string());
+        }
+        
+        if self.sandbox.private_tmp_bytes == 0 {
+            problems.push("sandbox.private_tmp_bytes must be at least 1".to_string());
+        }
+        if self.sandbox.output_max_bytes == 0 {
+            problems.push("sandbox.output_max_bytes must be at least 1".to_string());
+        }
+        if self.sandbox.isolate.boxes == 0 {
+            problems.push("sandbox.isolate.boxes must be at least 1".to_string());
+        }
+        for (name, runtime) in [("runtime", &self.sandbox.docker.runtime), ("strong_runtime", &self.sandbox.docker.strong_runtime)] {
+            if runtime.trim().is_empty() {
+                problems.push(format!("sandbox.docker.{} must not be empty", name));
+            }
+        }
+        
+        if self.fetch.max_bytes == 0 {
+            problems.push("fetch.max_bytes must be at least 1".to_string());
+        }
+        if self.fetch.timeout_secs == 0 {
+            problems.push("fetch.timeout_secs must be at least 1".to_string());
+        }
+        for host in &self.fetch.allowed_hosts {
+            let invalid = host.contains(['/', ':', '@']) || host.trim_start_matches("*.").contains('*');
+            if host != "*" && invalid {
+                problems.push(format!("fetch.allowed_hosts: '{}' is not a host name or *.domain pattern", host));
+            }
+        }
+        
+        let defaults = &self.limits.defaults;
+        let ceilings = &self.limits.ceilings;
+        let mut exceeds = |name: &str, default: f64, ceiling_name: &str, ceiling: f64| {
+            if default <= 0.0 {
+                problems.push(format!("limits.defaults.{} must be positive", name));
+            } else if default > ceiling {
+                problems.push(format!(
+                    "limits.defaults.{} ({}) exceeds limits.ceilings.{} ({})",
+                    name, default, ceiling_name, ceiling,
+                ));
+            }
+        };
+        exceeds("cpu_time", defaults.cpu_time, "max_cpu_time", ceilings.max_cpu_time);
+        exceeds("wall_time", defaults.wall_time, "max_wall_time", ceilings.max_wall_time);
+        exceeds("memory", defaults.memory as f64, "max_memory", ceilings.max_memory as f64);
+        exceeds("stack_limit", defaults.stack_limit as f64, "max_stack", ceilings.max_stack as f64);
+        exceeds("file_size", defaults.file_size as f64, "max_file_size", ceilings.max_file_size as f64);
+        exceeds("workdir_quota", defaults.workdir_quota as f64, "max_workdir_quota", ceilings.max_workdir_quota as f64);
+        exceeds("processes", defaults.processes as f64, "max_processes", ceilings.max_processes as f64);
+        if defaults.nice.is_some_and(|nice| !NICE_RANGE.contains(&nice)) {
+            problems.push(format!("limits.defaults.nice must be between {} and {}", NICE_RANGE.start(), NICE_RANGE.end()));
+        }
+        let cores = available_cores();
+        let unavailable: Vec<String> = self.sandbox.cpu_pool
+            .iter()
+            .chain(match &defaults.cpu_affinity {
+                Some(CpuAffinity::Cores(pinned)) => pinned.as_slice(),
+                _ => &[],
+            })
+            .filter(|core| !cores.contains(core))
+            .map(usize::to_string)
+            .collect();
+        if !unavailable.is_empty() {
+            problems.push(format!("cores {} are not available to the engine", unavailable.join(",")));
+        }
+        let mut languages: Vec<_> = self.limits.languages.iter().collect();
+        languages.sort_by_key(|(key, _)| key.as_str());
+        for (key, language) in languages {
+            let pairs = [
+                ("cpu_time", language.defaults.cpu_time, language.max.cpu_time),
+                ("wall_time", language.defaults.wall_time, language.max.wall_time),
+                ("memory", language.defaults.memory.map(|v| v as f64), language.max.memory.map(|v| v as f64)),
+                ("stack_limit", language.defaults.stack_limit.map(|v| v as f64), language.max.stack_limit.map(|v| v as f64)),
+                ("file_size", language.defaults.file_size.map(|v| v as f64), language.max.file_size.map(|v| v as f64)),
+                ("workdir_quota", language.defaults.workdir_quota.map(|v| v as f64), language.max.workdir_quota.map(|v| v as f64)),
+                ("processes", language.defaults.processes.map(f64::from), language.max.processes.map(f64::from)),
+            ];
+            for (name, default, max) in pairs {
+                for (kind, value) in [("defaults", default), ("max", max)] {
+                    if value.is_some_and(|value| value.is_nan() || value <= 0.0) {
+                        problems.push(format!("limits.languages.{}.{}.{} must be positive", key, kind, name));
+                    }
+                }
+                if let (Some(default), Some(max)) = (default, max) {
+                    if default > max {
+                        problems.push(format!(
+                            "limits.languages.{}.defaults.{} ({}) exceeds limits.languages.{}.max.{} ({})",
+                            key, name, default, key, name, max,
+                        ));
+                    }
+                }
+            }
+        }
+        let mut profiles: Vec<_> = self.profiles.iter().collect();
+        profiles.sort_by_key(|(name, _)| name.as_str());
+        for (name, profile) in profiles {
+            let limits = &profile.limits;
+            let bounds = [
+                ("cpu_time", limits.cpu_time, ceilings.max_cpu_time),
+                ("wall_time", limits.wall_time, ceilings.max_wall_time),
+                ("memory", limits.memory.map(|v| v as f64), ceilings.max_memory as f64),
+                ("stack_limit", limits.stack_limit.map(|v| v as f64), ceilings.max_stack as f64),
+                ("file_size", limits.file_size.map(|v| v as f64), ceilings.max_file_size as f64),
+                ("workdir_quota", limits.workdir_quota.map(|v| v as f64), ceilings.max_workdir_quota as f64),
+                ("processes", limits.processes.map(f64::from), f64::from(ceilings.max_processes)),
+                ("number_of_runs", profile.options.number_of_runs.map(f64::from), f64::from(ceilings.max_runs)),
+            ];
+            for (field, value, ceiling) in bounds {
+                let Some(value) = value else {
+                    continue;
+                };
+                let section = if field == "number_of_runs" { "options" } else { "limits" };
+                if value.is_nan() || value <= 0.0 {
+                    problems.push(format!("profiles.{}.{}.{} must be positive", name, section, field));
+                } else if value > ceiling {
+                    problems.push(format!("profiles.{}.{}.{} ({}) exceeds the server ceiling ({})", name, section, field, value, ceiling));
+                }
+            }
+        }
+        if ceilings.max_client_priority > MAX_PRIORITY {
+            problems.push(format!("limits.ceilings.max_client_priority must be at most {}", MAX_PRIORITY));
+        }
+        if ceilings.priority_caps.values().any(|cap| *cap > MAX_PRIORITY) {
+            problems.push(format!("limits.ceilings.priority_caps values must be at most {}", MAX_PRIORITY));
+        }
+        
+        let retention = &self.retention;
+        for (name, secs) in [
+            ("preserve_ttl_secs", retention.preserve_ttl_secs),
+            ("idempotency_ttl_secs", retention.idempotency_ttl_secs),
+            ("dedupe_window_secs", retention.dedupe_window_secs),
+        ] {
+            if secs < 0 {
+                problems.push(format!("retention.{} must not be negative", name));
+            }
+        }
+        
+        if self.audit.max_files == 0 {
+            problems.push("audit.max_files must be at least 1".to_string());
+        }
+        
+        if self.callbacks.timeout_secs == 0 {
+            problems.push("callbacks.timeout_secs must be at least 1".to_string());
+        }
+        if self.callbacks.max_attempts == 0 {
+            problems.push("callbacks.max_attempts must be at least 1".to_string());
+        }
+        if self.callbacks.secret.as_deref() == Some("") || self.callbacks.secrets.values().any(String::is_empty) {
+            problems.push("callbacks secrets must not be empty".to_string());
+        }
+        
+        let archive = &self.archive;
+        if archive.bucket.is_some() {
+            if archive.access_key_id.is_none() || archive.secret_access_key.is_none() {
+                problems.push("archive.bucket needs archive.access_key_id and archive.secret_access_key".to_string());
+            }
+            if reqwest::Url::parse(&archive.endpoint).map_or(true, |url| !matches!(url.scheme(), "http" | "https")) {
+                problems.push(format!("archive.endpoint '{}' is not an http or https URL", archive.endpoint));
+            }
+            // SigV4 presigned URLs are valid for at most a week
+            if archive.presign_ttl_secs == 0 || archive.presign_ttl_secs > 7 * 24 * 3600 {
+                problems.push("archive.presign_ttl_secs must be between 1 and 604800".to_string());
+            }
+            if archive.timeout_secs == 0 {
+                problems.push("archive.timeout_secs must be at least 1".to_string());
+            }
+        }
+        if self.warmup.enabled && self.warmup.timeout_secs == 0 {
+            problems.push("warmup.timeout_secs must be at least 1".to_string());
+        }
+        
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("invalid configuration:\n  - {}", problems.join("\n  - ")))
+        }
+    }
+    
+    /// Copy that is safe to show operators: the admin key, callback secrets and archive secret key are dropped (they are never serialized either),
+    /// API keys are hashed, also where they key priority caps, and URL passwords are masked
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        config.server.admin_api_key = None;
+        config.callbacks.secret = None;
+        config.callbacks.secrets.clear();
+        config.archive.secret_access_key = None;
+        config.queue.redis_url = redact_url(&config.queue.redis_url);
+        config.audit.http_url = config.audit.http_url.as_deref().map(redact_url);
+        config.rate_limit.api_keys = config.rate_limit.api_keys.iter().map(|key| RateLimiter::api_key_id(key)).collect();
+        config.limits.ceilings.priority_caps = config.limits.ceilings.priority_caps
+            .into_iter()
+            .map(|(key, cap)| (RateLimiter::api_key_id(&key), cap))
+            .collect();
+        config
+    }
+}
+
+/// Settings PUT /admin/config changes while the engine runs; fields left out keep their value.
+///
+/// Changes are applied to a copy of the configuration, which must then pass
+/// [`EngineConfig::validate`] like the startup configuration:
+///
+/// ```
+/// use labforcode_engine::config::RuntimeSettings;
+/// use labforcode_engine::EngineConfig;
+///
+/// let mut config = EngineConfig::default();
+/// let changes: RuntimeSettings = serde_json::from_value(serde_json::json!({ "max_queue_depth": 200 })).unwrap();
+/// changes.apply(&mut config).unwrap();
+/// assert_eq!(config.max_queue_depth, 200);
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    /// Keys of `limits.ceilings` to change, such as `max_wall_time`
+    pub ceilings: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Language keys whose submissions are refused, replacing the current list
+    pub disabled_languages: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Jobs allowed to wait before submissions are refused, 0 for unbounded
+    pub max_queue_depth: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Fewest workers the autoscaler keeps
+    pub min_workers: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Most workers the autoscaler starts
+    pub max_workers: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Bytes of stdout or stderr kept per run
+    pub output_max_bytes: Option<usize>,
+}
+
+impl RuntimeSettings {
+    /// Every setting as it stands in `config`
+    pub fn current(config: &EngineConfig) -> Self {
+        let ceilings = match serde_json::to_value(&config.limits.ceilings) {
+            Ok(serde_json::Value::Object(ceilings)) => Some(ceilings),
+            _ => None,
+        };
+        Self {
+            ceilings,
+            disabled_languages: Some(config.disabled_languages.clone()),
+            max_queue_depth: Some(config.max_queue_depth),
+            min_workers: Some(config.autoscale.min_workers),
+            max_workers: Some(config.autoscale.max_workers),
+            output_max_bytes: Some(config.sandbox.output_max_bytes),
+        }
+    }
+    
+    /// Write the changes into `config`; the result still needs [`EngineConfig::validate`]
+    pub fn apply(&self, config: &mut EngineConfig) -> Result<()> {
+        if let Some(changes) = &self.ceilings {
+            let mut ceilings = match serde_json::to_value(&config.limits.ceilings)? {
+                serde_json::Value::Object(ceilings) => ceilings,
+                _ => serde_json::Map::new(),
+            };
+            ceilings.extend(changes.clone());
+            config.limits.ceilings = serde_json::from_value(serde_json::Value::Object(ceilings))
+                .map_err(|err| anyhow!("ceilings: {}", err))?;
+        }
+        if let Some(languages) = &self.disabled_languages {
+            config.disabled_languages = languages.clone();
+        }
+        if let Some(depth) = self.max_queue_depth {
+            config.max_queue_depth = depth;
+        }
+        let autoscale = &mut config.autoscale;
+        autoscale.min_workers = self.min_workers.unwrap_or(autoscale.min_workers);
+        autoscale.max_workers = self.max_workers.unwrap_or(autoscale.max_workers);
+        if autoscale.enabled && autoscale.min_workers <= autoscale.max_workers {
+            // The pool is moved into the new bounds rather than refused
+            config.workers = config.workers.clamp(autoscale.min_workers, autoscale.max_workers);
+        }
+        if let Some(bytes) = self.output_max_bytes {
+            config.sandbox.output_max_bytes = bytes;
+        }
+        Ok(())
+    }
+    
+    /// The changes with API keys in `priority_caps` replaced by their client keys, for logs
+    pub fn redacted(&self) -> Self {
+        let mut settings = self.clone();
+        if let Some(caps) = settings.ceilings.as_mut().and_then(|ceilings| ceilings.get_mut("priority_caps")) {
+            if let Some(keys) = caps.as_object_mut() {
+                *keys = std::mem::take(keys).into_iter()
+                    .map(|(key, cap)| (RateLimiter::api_key_id(&key), cap))
+                    .collect();
+            }
+        }
+        settings
+    }
+    
+    /// Add later changes on top of these ones
+    pub fn merge(&mut self, later: RuntimeSettings) {
+        match (&mut self.ceilings, later.ceilings) {
+            (Some(ceilings), Some(later)) => ceilings.extend(later),
+            (ceilings, later) => *ceilings = later.or(ceilings.take()),
+        }
+        self.disabled_languages = later.disabled_languages.or(self.disabled_languages.take());
+        self.max_queue_depth = later.max_queue_depth.or(self.max_queue_depth);
+        self.min_workers = later.min_workers.or(self.min_workers);
+        self.max_workers = later.max_workers.or(self.max_workers);
+        self.output_max_bytes = later.output_max_bytes.or(self.output_max_bytes);
+    }
+    
+    /// Settings saved at `path`, or None before the first change
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map(Some)
+                .with_context(|| format!("parsing runtime settings {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("reading runtime settings {}", path.display())),
+        }
+    }
+    
+    /// Save to `path`, replacing the file in one step so a crash never leaves half of it
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("writing runtime settings {}", partial.display()))?;
+        std::fs::rename(&partial, path)
+            .with_context(|| format!("replacing runtime settings {}", path.display()))
+    }
+}
+
+/// A variable's value, treating an empty one as unset
+fn env_value(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Overwrite `target` with the parsed variable
+fn parse<T: FromStr>(name: &str, target: &mut T) -> Result<()>
+where
+    T::Err: Display,
+{
+    if let Some(value) = env_value(name) {
+        *target = value.parse()
+            .map_err(|err| anyhow!("{}='{}' is invalid: {}", name, value, err))?;
+    }
+    Ok(())
+}
+
+/// Overwrite an optional setting with the parsed variable
+fn parse_option<T: FromStr>(name: &str, target: &mut Option<T>) -> Result<()>
+where
+    T::Err: Display,
+{
+    if let Some(value) = env_value(name) {
+        *target = Some(value.parse()
+            .map_err(|err| anyhow!("{}='{}' is invalid: {}", name, value, err))?);
+    }
+    Ok(())
+}
+
+/// Overwrite an enum setting with the variable, spelled as in the TOML file
+fn parse_enum<T: DeserializeOwned>(name: &str, target: &mut T) -> Result<()> {
+    if let Some(value) = env_value(name) {
+        let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> = value.as_str().into_deserializer();
+        *target = T::deserialize(deserializer)
+            .map_err(|err| anyhow!("{}='{}' is invalid: {}", name, value, err))?;
+    }
+    Ok(())
+}
+
+/// Overwrite a list setting with the comma separated variable
+fn parse_list<T: FromStr>(name: &str, target: &mut Vec<T>) -> Result<()>
+where
+    T::Err: Display,
+{
+    if let Some(value) = env_value(name) {
+        *target = value.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse().map_err(|err| anyhow!("{}: '{}' is invalid: {}", name, item, err)))
+            .collect::<Result<_>>()?;
+    }
+    Ok(())
+}
+
+/// Overwrite a map setting with the variable's `key=value,...` pairs
+fn parse_map<T: FromStr>(name: &str, target: &mut HashMap<String, T>) -> Result<()>
+where
+    T::Err: Display,
+{
+    let Some(value) = env_value(name) else {
+        return Ok(());
+    };
+    
+    // Keys may be API keys, so errors point at the entry instead of quoting it
+    let mut map = HashMap::new();
+    for (index, pair) in value.split(',').map(str::trim).filter(|pair| !pair.is_empty()).enumerate() {
+        let Some((key, item)) = pair.split_once('=') else {
+            bail!("{}: entry {} is not a key=value pair", name, index + 1);
+        };
+        let item = item.trim().parse()
+            .map_err(|err| anyhow!("{}: entry {} has an invalid value: {}", name, index + 1, err))?;
+        map.insert(key.trim().to_string(), item);
+    }
+    *target = map;
+    Ok(())
+}
+
+/// URL with any password replaced by `***`
+fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) if parsed.password().is_some() => {
+            let _ = parsed.set_password(Some("***"));
+            parsed.to_string()
+        }
+        _ => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn settings(changes: serde_json::Value) -> RuntimeSettings {
+        serde_json::from_value(changes).unwrap()
+    }
+    
+    #[test]
+    fn runtime_settings_change_only_what_they_name() {
+        let mut config = EngineConfig::default();
+        let changes = settings(serde_json::json!({
+            "ceilings": { "max_wall_time": 30.0 },
+            "disabled_languages": ["cpp"],
+            "max_queue_depth": 200,
+        }));
+        changes.apply(&mut config).unwrap();
+        assert_eq!(config.limits.ceilings.max_wall_time, 30.0);
+        assert_eq!(config.limits.ceilings.max_cpu_time, EngineConfig::default().limits.ceilings.max_cpu_time);
+        assert_eq!((config.disabled_languages.as_slice(), config.max_queue_depth), (&["cpp".to_string()][..], 200));
+        config.validate().unwrap();
+        
+        let current = RuntimeSettings::current(&config);
+        assert_eq!(current.max_queue_depth, Some(200));
+        assert_eq!(current.ceilings.unwrap()["max_wall_time"], 30.0);
+    }
+    
+    #[test]
+    fn later_changes_win_and_ceilings_merge_key_by_key() {
+        let mut saved = settings(serde_json::json!({ "ceilings": { "max_wall_time": 30.0 }, "max_queue_depth": 200 }));
+        saved.merge(settings(serde_json::json!({ "ceilings": { "max_memory": 1024 }, "max_queue_depth": 50 })));
+        assert_eq!(saved.ceilings.as_ref().unwrap().len(), 2);
+        assert_eq!(saved.max_queue_depth, Some(50));
+        
+        saved.merge(RuntimeSettings::default());
+        assert_eq!(saved.max_queue_depth, Some(50));
+    }
+    
+    #[test]
+    fn runtime_settings_are_checked_like_the_configuration_file() {
+        assert!(settings(serde_json::json!({ "ceilings": { "max_wal_time": 1 } })).apply(&mut EngineConfig::default()).is_err());
+        assert!(serde_json::from_value::<RuntimeSettings>(serde_json::json!({ "workers": 3 })).is_err());
+        
+        // A ceiling below the default it caps passes apply but not validation
+        let mut config = EngineConfig::default();
+        settings(serde_json::json!({ "ceilings": { "max_cpu_time": 0.5 } })).apply(&mut config).unwrap();
+        assert!(config.validate().unwrap_err().to_string().contains("limits.defaults.cpu_time"));
+    }
+    
+    #[test]
+    fn runtime_settings_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        assert!(RuntimeSettings::read(&path).unwrap().is_none());
+        
+        settings(serde_json::json!({ "disabled_languages": ["go"], "output_max_bytes": 4096 })).write(&path).unwrap();
+        let saved = RuntimeSettings::read(&path).unwrap().unwrap();
+        assert_eq!(saved.disabled_languages.unwrap(), ["go"]);
+        assert_eq!(saved.output_max_bytes, Some(4096));
+        assert!(!path.with_extension("partial").exists());
+    }
+    
+    #[test]
+    fn language_limits_are_read_from_the_file_and_checked() {
+        let config: EngineConfig = toml::from_str(
+            "[limits.languages.c.defaults]\nmemory = 33554432\n\n[limits.languages.c.max]\nmemory = 67108864\ncpu_time = 1.0\n",
+        ).unwrap();
+        let c = &config.limits.languages["c"];
+        assert_eq!((c.defaults.memory, c.max.memory, c.max.cpu_time), (Some(32 << 20), Some(64 << 20), Some(1.0)));
+        config.validate().unwrap();
+        
+        assert!(toml::from_str::<EngineConfig>("[limits.languages.c.max]\nmemroy = 1\n").is_err());
+        
+        let mut config = EngineConfig::default();
+        config.limits.languages.insert("c".to_string(), LanguageLimits {
+            defaults: LimitOverrides { cpu_time: Some(3.0), processes: Some(0), ..Default::default() },
+            max: LimitOverrides { cpu_time: Some(2.0), ..Default::default() },
+            ..Default::default()
+        });
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("limits.languages.c.defaults.cpu_time (3) exceeds limits.languages.c.max.cpu_time (2)"), "{}", err);
+        assert!(err.contains("limits.languages.c.defaults.processes must be positive"), "{}", err);
+    }
+    
+    #[test]
+    fn profiles_must_fit_under_the_ceilings() {
+        let config: EngineConfig = toml::from_str(
+            "[profiles.quick.limits]\ncpu_time = 1.0\n\n[profiles.benchmark.limits]\ncpu_time = 60.0\n\n[profiles.benchmark.options]\nnumber_of_runs = 0\n",
+        ).unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("profiles.benchmark.limits.cpu_time (60) exceeds the server ceiling (15)"), "{}", err);
+        assert!(err.contains("profiles.benchmark.options.number_of_runs must be positive"), "{}", err);
+        assert!(!err.contains("profiles.quick"), "{}", err);
+        
+        assert!(toml::from_str::<EngineConfig>("[profiles.quick.options]\nnetwork = true\n").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::jobs::JobTable;
+use crate::types::{ExecutionJob, ExecutionRequest, ExecutionState};
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+/// Request fields that don't change what a run produces, so they are left out of the hash
+const IGNORED_FIELDS: &[&str] = &[
+    "id",
+    "idempotency_key",
+    "callback_url",
+    "priority",
+    "max_retries",
+    "run_at",
+    "dedupe",
+    "preserve_workdir",
+];
+
+/// Maps the hash of each `dedupe: true` submission to the job that runs it
+#[derive(Clone)]
+pub struct DedupeIndex {
+    entries: Arc<Mutex<HashMap<String, String>>>,
+    window: Duration, // how long a finished job still answers identical submissions
+}
+
+impl DedupeIndex {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            window,
+        }
+    }
+    
+    /// The job already answering `key`, or None after recording `id` as the job that will
+    /// The lock is held across the check so identical concurrent submissions can't both miss
+    pub async fn claim(&self, key: &str, id: &str, jobs: &JobTable) -> Option<ExecutionJob> {
+        let mut entries = self.entries.lock().await;
+        if let Some(existing) = entries.get(key) {
+            match jobs.get(existing).await {
+                Some(job) if self.is_reusable(&job) => return Some(job),
+                _ => debug!("Dedupe entry for {} is stale", existing),
+            }
+        }
+        
+        entries.insert(key.to_string(), id.to_string());
+        None
+    }
+    
+    /// Drop the claim of a submission that failed to enqueue
+    pub async fn release(&self, key: &str, id: &str) {
+        let mut entries = self.entries.lock().await;
+        if entries.get(key).is_some_and(|claimed| claimed == id) {
+            entries.remove(key);
+        }
+    }
+    
+    /// Forget entries whose job has finished outside the window or no longer exists
+    pub async fn sweep(&self, jobs: &JobTable) {
+        let mut entries = self.entries.lock().await;
+        let mut stale = Vec::new();
+        for (key, id) in entries.iter() {
+            if !jobs.get(id).await.is_some_and(|job| self.is_reusable(&job)) {
+                stale.push(key.clone());
+            }
+        }
+        for key in stale {
+            entries.remove(&key);
+        }
+    }
+    
+    /// Still running, or finished recently enough that its result can stand in for a new run
+    /// Cancelled jobs and engine failures say nothing about the code, so they are never reused
+    fn is_reusable(&self, job: &ExecutionJob) -> bool {
+        match job.status {
+            ExecutionState::Cancelled | ExecutionState::InternalError => false,
+            ref status if !status.is_terminal() => true,
+            _ => job.finished_at.is_some_and(|finished_at| Utc::now() - finished_at < self.window),
+        }
+    }
+}
+
+/// Hash of a validated request, scoped to the submitting client so tenants never share jobs
+pub fn key(client: Option<&str>, request: &ExecutionRequest) -> String {
+    let mut fields = serde_json::to_value(request).unwrap_or_default();
+    if let Some(fields) = fields.as_object_mut() {
+        for field in IGNORED_FIELDS {
+            fields.remove(*field);
+        }
+    }
+    
+    // serde_json maps are sorted, so equal requests serialize identically
+    let digest: String = Sha256::digest(fields.to_string().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("{}:{}", client.unwrap_or_default(), digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn request(fields: serde_json::Value) -> ExecutionRequest {
+        let mut body = serde_json::json!({"language": "python", "source_code": "print(input())", "stdin": "1", "dedupe": true});
+        body.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(body).unwrap()
+    }
+    
+    fn job(id: &str, status: ExecutionState, finished_secs_ago: Option<i64>) -> ExecutionJob {
+        let mut job: ExecutionJob = serde_json::from_value(serde_json::json!({
+            "id": id,
+            "request": { "id": id, "language": "python", "source_code": "print(1)" },
+            "status": "queued",
+            "created_at": "2030-01-01T00:00:00Z",
+            "started_at": null,
+            "finished_at": null,
+            "result": null,
+        })).unwrap();
+        job.status = status;
+        job.finished_at = finished_secs_ago.map(|secs| Utc::now() - Duration::seconds(secs));
+        job
+    }
+    
+    #[test]
+    fn keys_ignore_delivery_fields_but_not_inputs_or_clients() {
+        let base = key(Some("key:a"), &request(serde_json::json!({})));
+        let delivery = request(serde_json::json!({"id": "other", "priority": 9, "callback_url": "https://example.com/hook", "max_retries": 2}));
+        assert_eq!(key(Some("key:a"), &delivery), base);
+        
+        assert_ne!(key(Some("key:a"), &request(serde_json::json!({"stdin": "2"}))), base);
+        assert_ne!(key(Some("key:a"), &request(serde_json::json!({"wall_time_limit": 3.0}))), base);
+        assert_ne!(key(Some("key:b"), &request(serde_json::json!({}))), base);
+        assert_ne!(key(None, &request(serde_json::json!({}))), base);
+    }
+    
+    #[tokio::test]
+    async fn claims_answer_with_live_or_recent_jobs_only() {
+        let jobs = JobTable::new();
+        let index = DedupeIndex::new(Duration::seconds(60));
+        for (id, status, finished) in [
+            ("running", ExecutionState::Running, None),
+            ("recent", ExecutionState::Completed, Some(5)),
+            ("old", ExecutionState::Completed, Some(600)),
+            ("cancelled", ExecutionState::Cancelled, Some(5)),
+            ("broken", ExecutionState::InternalError, Some(5)),
+        ] {
+            jobs.insert(job(id, status, finished)).await;
+            assert!(index.claim(id, id, &jobs).await.is_none(), "first claim of {}", id);
+        }
+        
+        for (key, reused) in [("running", true), ("recent", true), ("old", false), ("cancelled", false), ("broken", false)] {
+            let claimed = index.claim(key, "newcomer", &jobs).await.map(|job| job.id);
+            assert_eq!(claimed.is_some(), reused, "{}", key);
+        }
+        // The stale entries now name the newcomer, which the sweep drops along with the rest of the unknown ids
+        index.sweep(&jobs).await;
+        assert_eq!(index.entries.lock().await.len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn released_claims_no_longer_answer() {
+        let jobs = JobTable::new();
+        let index = DedupeIndex::new(Duration::seconds(60));
+        jobs.insert(job("first", ExecutionState::Queued, None)).await;
+        assert!(index.claim("key", "first", &jobs).await.is_none());
+        
+        // Only the claimant may release it
+        index.release("key", "someone-else").await;
+        assert_eq!(index.claim("key", "second", &jobs).await.unwrap().id, "first");
+        index.release("key", "first").await;
+        assert!(index.claim("key", "second", &jobs).await.is_none());
+    }
+}
+
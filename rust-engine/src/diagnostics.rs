@@ -0,0 +1,222 @@
+//! Best-effort extraction of compiler diagnostics, so editors can underline the offending line.
+//!
+//! Each compiler family has its own small parser, picked by the compiler program's name. Lines
+//! that don't look like a diagnostic are skipped, and a compiler without a parser yields none;
+//! the raw `compile_output` is always returned alongside.
+use crate::docker::CONTAINER_WORKDIR;
+use crate::isolate::BOX_WORKDIR;
+use crate::types::{Diagnostic, DiagnosticSeverity};
+use std::path::Path;
+
+/// Diagnostics in the output of `compiler`, with file names relative to the working directory.
+///
+/// `workdir` is where the compile ran; paths under it, or under the sandboxes' own working
+/// directories, are reported relative to it, so the submitter sees `main.cpp` rather than a temp path.
+///
+/// ```
+/// use labforcode_engine::diagnostics::parse;
+/// use std::path::Path;
+///
+/// let output = "/tmp/job-1/main.cpp:4:5: error: 'x' was not declared in this scope\n";
+/// let found = parse("g++", output, Path::new("/tmp/job-1"));
+/// assert_eq!((found[0].file.as_str(), found[0].line, found[0].column), ("main.cpp", 4, Some(5)));
+/// ```
+pub fn parse(compiler: &str, output: &str, workdir: &Path) -> Vec<Diagnostic> {
+    let program = Path::new(compiler).file_name().and_then(|name| name.to_str()).unwrap_or(compiler);
+    let mut diagnostics = match program {
+        "rustc" | "cargo" => parse_rustc(output),
+        "javac" => parse_javac(output),
+        _ if is_gcc_like(program) => output.lines().filter_map(gcc_line).collect(),
+        _ => Vec::new(),
+    };
+    for diagnostic in &mut diagnostics {
+        diagnostic.file = normalize_file(&diagnostic.file, workdir);
+    }
+    diagnostics
+}
+
+/// gcc, g++, cc, c++ and clang, including versioned names such as `g++-12`
+fn is_gcc_like(program: &str) -> bool {
+    let base = program.split('-').next().unwrap_or(program);
+    matches!(base, "gcc" | "g++" | "cc" | "c++" | "clang" | "clang++")
+}
+
+/// `file:line:column: severity: message`, or `file:line: severity: message` as javac prints it
+fn gcc_line(line: &str) -> Option<Diagnostic> {
+    let (location, rest) = line.split_once(": ")?;
+    let (severity, message) = rest.split_once(": ")?;
+    let severity = severity_of(severity)?;
+    
+    let (head, last) = location.rsplit_once(':')?;
+    let last: u32 = last.parse().ok()?;
+    let (file, line, column) = match head.rsplit_once(':') {
+        Some((file, line)) if line.parse::<u32>().is_ok() => (file, line.parse().ok()?, Some(last)),
+        _ => (head, last, None),
+    };
+    if file.is_empty() {
+        return None;
+    }
+    Some(Diagnostic {
+        file: file.to_string(),
+        line,
+        column,
+        severity,
+        message: message.trim().to_string(),
+    })
+}
+
+/// A header such as `error[E0425]: message`, located by the ` --> file:line:column` line after it
+fn parse_rustc(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut pending: Option<(DiagnosticSeverity, String)> = None;
+    for line in output.lines() {
+        if let Some(location) = line.trim_start().strip_prefix("--> ") {
+            let Some((severity, message)) = pending.take() else {
+                continue;
+            };
+            let mut parts = location.trim().rsplitn(3, ':');
+            let (Some(column), Some(line), Some(file)) = (parts.next(), parts.next(), parts.next()) else {
+                continue;
+            };
+            if let (Ok(line), Ok(column)) = (line.parse(), column.parse()) {
+                diagnostics.push(Diagnostic { file: file.to_string(), line, column: Some(column), severity, message });
+            }
+        } else if !line.starts_with(char::is_whitespace) {
+            // Every unindented line starts a new diagnostic or ends the previous one
+            pending = line.split_once(": ").and_then(|(header, message)| {
+                let severity = header.split('[').next().unwrap_or(header);
+                Some((severity_of(severity)?, message.trim().to_string()))
+            });
+        }
+    }
+    diagnostics
+}
+
+/// gcc-style lines without a column; the column comes from the caret under the quoted source line
+fn parse_javac(output: &str) -> Vec<Diagnostic> {
+    let lines: Vec<&str> = output.lines().collect();
+    let mut diagnostics = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let Some(mut diagnostic) = gcc_line(line) else {
+            continue;
+        };
+        if diagnostic.column.is_none() {
+            diagnostic.column = lines.get(index + 2)
+                .filter(|caret| caret.trim() == "^")
+                .and_then(|caret| caret.find('^'))
+                .map(|offset| offset as u32 + 1);
+        }
+        diagnostics.push(diagnostic);
+    }
+    diagnostics
+}
+
+fn severity_of(word: &str) -> Option<DiagnosticSeverity> {
+    match word {
+        "error" | "fatal error" => Some(DiagnosticSeverity::Error),
+        "warning" => Some(DiagnosticSeverity::Warning),
+        "note" => Some(DiagnosticSeverity::Note),
+        _ => None,
+    }
+}
+
+/// The path as the submitter knows it rather than where the sandbox put it
+fn normalize_file(file: &str, workdir: &Path) -> String {
+    let root = workdir.to_string_lossy();
+    for root in [root.as_ref(), BOX_WORKDIR, CONTAINER_WORKDIR] {
+        if let Some(relative) = file.strip_prefix(root).and_then(|rest| rest.strip_prefix('/')) {
+            return relative.to_string();
+        }
+    }
+    // The working directory reached through a symlink, such as /private/tmp on macOS
+    if let Some(name) = Path::new(file).file_name().filter(|_| file.starts_with('/')) {
+        if workdir.join(name).is_file() {
+            return name.to_string_lossy().into_owned();
+        }
+    }
+    file.trim_start_matches("./").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn gcc_errors_warnings_and_notes_are_found() {
+        let output = "\
+/tmp/job-1.x7Yz/main.cpp: In function 'int main()':
+/tmp/job-1.x7Yz/main.cpp:4:5: error: 'x' was not declared in this scope
+    4 |     x = 1;
+      |     ^
+/tmp/job-1.x7Yz/main.cpp:2:7: warning: unused variable 'y' [-Wunused-variable]
+util.h:1:1: note: declared here
+/usr/bin/ld: main.o: in function `main':
+main.cpp:(.text+0x5): undefined reference to `foo()'
+";
+        let found = parse("g++", output, Path::new("/tmp/job-1.x7Yz"));
+        assert_eq!(found.len(), 3, "{:?}", found);
+        assert_eq!((found[0].file.as_str(), found[0].line, found[0].column), ("main.cpp", 4, Some(5)));
+        assert_eq!(found[0].severity, DiagnosticSeverity::Error);
+        assert_eq!(found[0].message, "'x' was not declared in this scope");
+        assert_eq!(found[1].severity, DiagnosticSeverity::Warning);
+        assert_eq!((found[2].file.as_str(), found[2].severity), ("util.h", DiagnosticSeverity::Note));
+    }
+    
+    #[test]
+    fn rustc_diagnostics_take_their_location_from_the_arrow_line() {
+        let output = "\
+error[E0425]: cannot find value `x` in this scope
+ --> /box/main.rs:2:20
+  |
+2 |     println!(\"{}\", x);
+  |                    ^ not found in this scope
+
+warning: unused variable: `y`
+ --> main.rs:3:9
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+error: aborting due to 1 previous error; 1 warning emitted
+";
+        let found = parse("rustc", output, Path::new("/tmp/job-2"));
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert_eq!((found[0].file.as_str(), found[0].line, found[0].column), ("main.rs", 2, Some(20)));
+        assert_eq!(found[0].message, "cannot find value `x` in this scope");
+        assert_eq!((found[1].severity, found[1].line), (DiagnosticSeverity::Warning, 3));
+    }
+    
+    #[test]
+    fn javac_columns_come_from_the_caret() {
+        let output = "\
+Main.java:3: error: cannot find symbol
+        System.out.println(x);
+                           ^
+  symbol:   variable x
+  location: class Main
+1 error
+";
+        let found = parse("javac", output, Path::new("/tmp/job-3"));
+        assert_eq!(found.len(), 1, "{:?}", found);
+        assert_eq!((found[0].file.as_str(), found[0].line, found[0].column), ("Main.java", 3, Some(28)));
+        assert_eq!(found[0].message, "cannot find symbol");
+    }
+    
+    #[test]
+    fn compilers_are_recognised_by_program_name() {
+        let output = "main.c:1:1: error: expected ';'\n";
+        for compiler in ["gcc", "/usr/bin/gcc-12", "clang", "cc"] {
+            assert_eq!(parse(compiler, output, Path::new("/tmp")).len(), 1, "{}", compiler);
+        }
+        assert!(parse("python3", output, Path::new("/tmp")).is_empty());
+    }
+    
+    #[test]
+    fn sandbox_paths_become_the_submitted_name() {
+        let workdir = Path::new("/tmp/job-4");
+        assert_eq!(normalize_file("/tmp/job-4/src/lib.rs", workdir), "src/lib.rs");
+        assert_eq!(normalize_file(&format!("{}/main.c", BOX_WORKDIR), workdir), "main.c");
+        assert_eq!(normalize_file(&format!("{}/main.c", CONTAINER_WORKDIR), workdir), "main.c");
+        assert_eq!(normalize_file("./main.c", workdir), "main.c");
+        assert_eq!(normalize_file("/usr/include/stdio.h", workdir), "/usr/include/stdio.h");
+    }
+}
@@ -0,0 +1,388 @@
+use crate::config::DockerPoolConfig;
+use crate::sandbox;
+use crate::types::{CpuAffinity, IsolationLevel, ResourceLimits};
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Directory inside pooled containers where job files are copied
+pub const CONTAINER_WORKDIR: &str = "/workspace";
+
+/// Claimed containers are renamed to this, the job's tag, '.' and the short container id
+const JOB_CONTAINER_PREFIX: &str = "labforcode-job-";
+
+/// Image and OCI runtime a set of pooled containers was started with
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PoolKey {
+    image: String,
+    runtime: String,
+}
+
+/// Pool of pre-created, paused containers per language image and runtime
+#[derive(Clone)]
+pub struct ContainerPool {
+    idle: Arc<Mutex<HashMap<PoolKey, Vec<String>>>>,
+    last_used: Arc<Mutex<HashMap<PoolKey, Instant>>>,
+    sizes: HashMap<String, usize>,
+    default_size: usize,
+    idle_timeout: Duration,
+    runtime: String,
+    strong_runtime: String,
+    runtimes: Arc<BTreeMap<String, bool>>, // configured runtimes and whether docker offered them at startup
+}
+
+impl ContainerPool {
+    /// Create an empty pool; images are only warmed once a language is used
+    pub fn new(sizes: HashMap<String, usize>, default_size: usize, idle_timeout: Duration, runtime: String, strong_runtime: String) -> Self {
+        info!("🐳 Docker backend enabled (default pool size {}, runtime {})", default_size, runtime);
+        
+        let installed = installed_runtimes();
+        let runtimes: BTreeMap<String, bool> = [&runtime, &strong_runtime]
+            .into_iter()
+            .map(|name| (name.clone(), installed.contains(name)))
+            .collect();
+        for (name, available) in &runtimes {
+            if !available {
+                warn!("Docker runtime {} is not available", name);
+            }
+        }
+        
+        Self {
+            idle: Arc::new(Mutex::new(HashMap::new())),
+            last_used: Arc::new(Mutex::new(HashMap::new())),
+            sizes,
+            default_size,
+            idle_timeout,
+            runtime,
+            strong_runtime,
+            runtimes: Arc::new(runtimes),
+        }
+    }
+    
+    /// Build a pool from the docker settings, given each language's image
+    pub fn from_config(config: &DockerPoolConfig, language_images: &HashMap<String, String>) -> Self {
+        // Sizes are configured per language but the pool is kept per image
+        let sizes = config.pool_sizes
+            .iter()
+            .filter_map(|(language, size)| Some((language_images.get(language)?.clone(), *size)))
+            .collect();
+        
+        Self::new(
+            sizes,
+            config.pool_size,
+            Duration::from_secs(config.idle_secs),
+            config.runtime.clone(),
+            config.strong_runtime.clone(),
+        )
+    }
+    
+    /// Configured runtimes and whether docker offered them at startup
+    pub fn runtimes(&self) -> &BTreeMap<String, bool> {
+        &self.runtimes
+    }
+    
+    /// Whether jobs asking for this isolation level can be run
+    pub fn supports(&self, isolation: IsolationLevel) -> bool {
+        self.runtimes.get(self.runtime_for(isolation)).copied().unwrap_or(false)
+    }
+    
+    /// Take a warm container for the image (or start one), applying the job's limits and naming it after the job
+    pub async fn claim(&self, image: &str, isolation: IsolationLevel, limits: &ResourceLimits, enable_network: bool, job_id: &str) -> Result<String> {
+        let key = PoolKey {
+            image: image.to_string(),
+            runtime: self.runtime_for(isolation).to_string(),
+        };
+        
+        // Pooled containers have no network, so networked jobs get a fresh container
+        if enable_network {
+            let container = create_container(&key, "bridge").await?;
+            return self.prepare(container, limits, job_id).await;
+        }
+        
+        self.last_used.lock().await.insert(key.clone(), Instant::now());
+        
+        let pooled = self.idle.lock().await.get_mut(&key).and_then(|idle| idle.pop());
+        let container = match pooled {
+            Some(container) => container,
+            None => {
+                debug!("No warm {} container for {}, starting one", key.runtime, image);
+                create_container(&key, "none").await?
+            }
+        };
+        
+        // Replace the claimed container in the background
+        let pool = self.clone();
+        tokio::spawn(async move { pool.refill(&key).await });
+        
+        self.prepare(container, limits, job_id).await
+    }
+    
+    /// Runtime containers of this isolation level are started with
+    fn runtime_for(&self, isolation: IsolationLevel) -> &str {
+        match isolation {
+            IsolationLevel::Standard => &self.runtime,
+            IsolationLevel::Strong => &self.strong_runtime,
+        }
+    }
+    
+    /// Wake a container and apply the job's limits, discarding it on failure
+    async fn prepare(&self, container: String, limits: &ResourceLimits, job_id: &str) -> Result<String> {
+        let memory = limits.memory.to_string();
+        let pids = (limits.processes + 1).to_string(); // +1 for the idle sleep process
+        let mut update = vec!["update", "--memory", &memory, "--memory-swap", &memory, "--pids-limit", &pids];
+        let cpuset = match &limits.cpu_affinity {
+            Some(affinity @ CpuAffinity::Cores(_)) => affinity.to_string(),
+            _ => String::new(),
+        };
+        if !cpuset.is_empty() {
+            update.extend(["--cpuset-cpus", &cpuset]);
+        }
+        update.push(&container);
+        // Several runs of a job can hold containers at once, so the id keeps the names apart
+        let short_id: String = container.chars().take(12).collect();
+        let name = format!("{}{}.{}", JOB_CONTAINER_PREFIX, sandbox::job_tag(job_id), short_id);
+        
+        let prepared = async {
+            docker(&["unpause", &container]).await?;
+            docker(&update).await?;
+            docker(&["rename", &container, &name]).await
+        }.await;
+        
+        match prepared {
+            Ok(()) => Ok(container),
+            Err(err) => {
+                self.release(container);
+                Err(err)
+            }
+        }
+    }
+    
+    /// Destroy a used container; it is never returned to the pool
+    pub fn release(&self, container: String) {
+        tokio::spawn(async move {
+            if let Err(err) = docker(&["rm", "-f", &container]).await {
+                warn!("Failed to remove container {}: {}", container, err);
+            }
+        });
+    }
+    
+    /// Remove containers a job left behind, such as when the engine died while it ran
+    pub async fn remove_job_containers(&self, job_id: &str) -> Result<usize> {
+        let filter = format!("name=^/{}{}\\.", JOB_CONTAINER_PREFIX, sandbox::job_tag(job_id));
+        let output = Command::new("docker").args(["ps", "-aq", "--filter", &filter]).output().await?;
+        if !output.status.success() {
+            return Err(anyhow!("docker ps failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        
+        let containers: Vec<String> = String::from_utf8_lossy(&output.stdout).split_whitespace().map(String::from).collect();
+        for container in &containers {
+            docker(&["rm", "-f", container]).await?;
+        }
+        Ok(containers.len())
+    }
+    
+    /// Top up recently used pools and drain pools nobody has used for a while
+    pub async fn maintain(&self) {
+        let pools: Vec<(PoolKey, bool)> = {
+            let last_used = self.last_used.lock().await;
+            last_used.iter()
+                .map(|(key, used)| (key.clone(), used.elapsed() < self.idle_timeout))
+                .collect()
+        };
+        
+        for (key, active) in pools {
+            if active {
+                self.refill(&key).await;
+                continue;
+            }
+            
+            let drained = self.idle.lock().await.remove(&key).unwrap_or_default();
+            if !drained.is_empty() {
+                info!("🐳 Draining {} idle {} containers for {}", drained.len(), key.runtime, key.image);
+            }
+            for container in drained {
+                self.release(container);
+            }
+            self.last_used.lock().await.remove(&key);
+        }
+    }
+    
+    /// Create containers until the pool reaches the image's configured size
+    async fn refill(&self, key: &PoolKey) {
+        let target = self.sizes.get(&key.image).copied().unwrap_or(self.default_size);
+        
+        loop {
+            let current = self.idle.lock().await.get(key).map_or(0, Vec::len);
+            if current >= target {
+                break;
+            }
+            
+            match create_container(key, "none").await {
+                Ok(container) => self.idle.lock().await.entry(key.clone()).or_default().push(container),
+                Err(err) => {
+                    warn!("Failed to warm {} container for {}: {}", key.runtime, key.image, err);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Copy the host working directory into a container
+pub async fn copy_in(container: &str, working_dir: &Path) -> Result<()> {
+    let source = format!("{}/.", working_dir.display());
+    let target = format!("{}:{}", container, CONTAINER_WORKDIR);
+    docker(&["cp", &source, &target]).await
+}
+
+/// Copy a container's working directory back to the host
+pub async fn copy_out(container: &str, working_dir: &Path) -> Result<()> {
+    let source = format!("{}:{}/.", container, CONTAINER_WORKDIR);
+    let target = working_dir.display().to_string();
+    docker(&["cp", &source, &target]).await
+}
+
+/// Bytes a container has written to its filesystem, working directory included
+///
+/// Measured by the daemon, so a program that has used up its process limit can't hide from it.
+pub async fn disk_usage(container: &str) -> Result<u64> {
+    let output = Command::new("docker")
+        .args(["container", "inspect", "--size", "--format", "{{.SizeRw}}", container])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!("docker inspect failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.trim().parse().map_err(|_| anyhow!("unexpected container size: {}", stdout.trim()))
+}
+
+/// Kill everything running in a container; its files can still be copied out
+///
+/// Killing the `docker exec` client leaves the command it started running, so this stops the container itself.
+pub async fn kill(container: &str) -> Result<()> {
+    docker(&["kill", container]).await
+}
+
+/// Content id of a local image, which changes whenever the image is rebuilt or re-pulled
+pub async fn image_id(image: &str) -> Result<String> {
+    let output = Command::new("docker").args(["image", "inspect", "--format", "{{.Id}}", image]).output().await?;
+    if !output.status.success() {
+        return Err(anyhow!("docker image inspect failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Pull an image unless it is already present
+pub async fn ensure_image(image: &str) -> Result<()> {
+    if docker(&["image", "inspect", "--format", "{{.Id}}", image]).await.is_ok() {
+        return Ok(());
+    }
+    info!("🐳 Pulling {}", image);
+    docker(&["pull", image]).await
+}
+
+/// Runtimes the docker daemon offers, empty when it cannot be reached
+fn installed_runtimes() -> Vec<String> {
+    let output = std::process::Command::new("docker")
+        .args(["info", "--format", "{{json .Runtimes}}"])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            serde_json::from_slice::<HashMap<String, serde_json::Value>>(&output.stdout)
+                .map(|runtimes| runtimes.into_keys().collect())
+                .unwrap_or_default()
+        }
+        Ok(output) => {
+            warn!("docker info failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+            Vec::new()
+        }
+        Err(err) => {
+            warn!("docker info failed: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+/// Start a paused container that idles until claimed
+async fn create_container(key: &PoolKey, network: &str) -> Result<String> {
+    let runtime = format!("--runtime={}", key.runtime);
+    let oom_score_adj = format!("--oom-score-adj={}", sandbox::CHILD_OOM_SCORE_ADJ.to_string_lossy());
+    let output = Command::new("docker")
+        .args([
+            "run", "-d",
+            &runtime,
+            &oom_score_adj,
+            "--network", network,
+            "--label", "labforcode.pool=1",
+            "-w", CONTAINER_WORKDIR,
+            &key.image,
+            "sleep", "infinity",
+        ])
+        .output()
+        .await?;
+    
+    if !output.status.success() {
+        return Err(anyhow!("docker run failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    
+    let container = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    docker(&["exec", &container, "mkdir", "-p", CONTAINER_WORKDIR]).await?;
+    docker(&["pause", &container]).await?;
+    Ok(container)
+}
+
+/// Run a docker CLI command, failing with its stderr
+async fn docker(args: &[&str]) -> Result<()> {
+    let output = Command::new("docker").args(args).output().await?;
+    
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("docker {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Pool whose runtimes are known to be present or missing, without asking docker
+    fn pool(runtimes: &[(&str, bool)]) -> ContainerPool {
+        ContainerPool {
+            idle: Arc::new(Mutex::new(HashMap::new())),
+            last_used: Arc::new(Mutex::new(HashMap::new())),
+            sizes: HashMap::new(),
+            default_size: 2,
+            idle_timeout: Duration::from_secs(600),
+            runtime: "runc".to_string(),
+            strong_runtime: "runsc".to_string(),
+            runtimes: Arc::new(runtimes.iter().map(|(name, available)| (name.to_string(), *available)).collect()),
+        }
+    }
+    
+    #[test]
+    fn each_isolation_level_uses_its_own_runtime() {
+        let pool = pool(&[("runc", true), ("runsc", true)]);
+        assert_eq!(pool.runtime_for(IsolationLevel::Standard), "runc");
+        assert_eq!(pool.runtime_for(IsolationLevel::Strong), "runsc");
+        assert!(pool.supports(IsolationLevel::Standard));
+        assert!(pool.supports(IsolationLevel::Strong));
+    }
+    
+    #[test]
+    fn missing_runtimes_are_not_supported() {
+        let without_gvisor = pool(&[("runc", true), ("runsc", false)]);
+        assert!(without_gvisor.supports(IsolationLevel::Standard));
+        assert!(!without_gvisor.supports(IsolationLevel::Strong));
+        
+        // A runtime docker was never asked about counts as missing
+        assert!(!pool(&[]).supports(IsolationLevel::Standard));
+    }
+}
@@ -1,246 +1,1629 @@
-use crate::executor::CodeExecutor;
-use crate::queue::ExecutionQueue;
+use crate::audit;
+use crate::archive::ResultArchive;
+use crate::callback::CallbackSender;
+use crate::autoscale::{self, Sample, ScalingPolicy};
+use crate::config::{ArchiveServe, AutoscaleConfig, EngineConfig, PausedSubmissions, RuntimeSettings};
+use crate::dedupe::{self, DedupeIndex};
+use crate::executor::{CodeExecutor, ExecutionBackend};
+use crate::idempotency::IdempotencyStore;
+use crate::histogram::LatencyHistogram;
+use crate::jobs::JobTable;
+use crate::pressure::{self, DiskReader, MemoryGuard, MemoryReader, SystemMemory};
+use crate::queue::{self, JobQueue};
+use crate::quota::UsageTracker;
+use crate::recent::RecentExecutions;
+use crate::sandbox;
+use crate::spill::OutputStream;
+use crate::store::JobStore;
+use crate::telemetry;
 use crate::types::*;
+use crate::validation::{RequestValidator, Support};
+use crate::warmup::Warmup;
+use crate::workdir::WorkdirStore;
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{info, error};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch, Notify, RwLock, Semaphore};
+use tracing::{info, error, instrument, warn};
 use chrono::Utc;
+use uuid::Uuid;
 
-/// Main execution engine that coordinates everything
-pub struct ExecutionEngine {
-    queue: ExecutionQueue,
-    executor: CodeExecutor,
-    jobs: Arc<RwLock<HashMap<String, ExecutionJob>>>,
-    stats: Arc<RwLock<EngineStats>>,
-    start_time: chrono::DateTime<Utc>,
+/// Delay before the first retry of a job that hit an internal error; doubles with each attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest delay between retries
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// failure_reason of jobs that were running when the engine stopped and had no retries left
+const INTERRUPTED_MESSAGE: &str = "engine restarted during execution";
+
+/// failure_reason of jobs POST /admin/cancel stopped without giving one
+const ADMIN_CANCEL_REASON: &str = "cancelled by an administrator";
+
+/// How often shutdown checks whether running jobs have finished
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Submission refused because the engine is draining for shutdown
+#[derive(Debug, thiserror::Error)]
+#[error("Engine is shutting down")]
+pub struct ShuttingDown;
+
+/// Submission refused because processing is paused and PAUSED_SUBMISSIONS=reject
+#[derive(Debug, thiserror::Error)]
+#[error("Engine is paused")]
+pub struct Paused;
+
+/// Submission reused the id of a job that has not finished yet
+#[derive(Debug, thiserror::Error)]
+#[error("Execution {0} is already queued or running")]
+pub struct DuplicateExecution(pub String);
+
+/// Capacity update whose autoscaling bounds make no sense
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid capacity: {0}")]
+pub struct InvalidCapacity(pub String);
+
+/// Runtime settings change that the startup checks would refuse
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid settings: {0}")]
+pub struct InvalidSettings(pub String);
+
+/// Manual callback retry for a job whose callback is not dead-lettered
+#[derive(Debug, thiserror::Error)]
+#[error("No failed callback for execution {0}")]
+pub struct NoDeadLetter(pub String);
+
+/// Submission refused because the queue is at its configured depth
+#[derive(Debug, thiserror::Error)]
+#[error("Execution queue is full ({queued} jobs waiting)")]
+pub struct QueueFull {
+    /// Jobs waiting when the submission was refused
+    pub queued: usize,
+    /// Rough time until the queue drains, from the average execution time
+    pub estimated_wait_secs: f64,
 }
 
-impl ExecutionEngine {
-    /// Create a new execution engine
-    pub async fn new() -> Result<Self> {
+/// How many job change notifications a slow status stream may fall behind before it refetches
+const CHANGE_BUFFER: usize = 256;
+
+/// Per-job signals for readers that long-poll or stream a job's status
+struct JobWatchers {
+    senders: std::sync::Mutex<HashMap<String, watch::Sender<bool>>>,
+    changes: broadcast::Sender<String>, // ids of jobs whose status or progress changed
+}
+
+impl JobWatchers {
+    fn new() -> Self {
+        Self {
+            senders: std::sync::Mutex::new(HashMap::new()),
+            changes: broadcast::channel(CHANGE_BUFFER).0,
+        }
+    }
+    
+    /// Tell status streams that a job moved on without finishing
+    fn changed(&self, id: &str) {
+        let _ = self.changes.send(id.to_string());
+    }
+    
+    /// Receiver that flips to true once the job reaches a terminal state
+    fn subscribe(&self, id: &str) -> watch::Receiver<bool> {
+        let mut senders = self.senders.lock().unwrap();
+        senders.entry(id.to_string())
+            .or_insert_with(|| watch::channel(false).0)
+            .subscribe()
+    }
+    
+    /// Wake everyone waiting on a job; called after its final state is in the jobs map
+    fn notify(&self, id: &str) {
+        if let Some(sender) = self.senders.lock().unwrap().remove(id) {
+            sender.send_replace(true);
+        }
+        self.changed(id);
+    }
+    
+    /// Drop the signal once nobody is waiting on it any more
+    fn release(&self, id: &str) {
+        let mut senders = self.senders.lock().unwrap();
+        if senders.get(id).is_some_and(|sender| sender.receiver_count() == 0) {
+            senders.remove(id);
+        }
+    }
+}
+
+/// Operator switch that stops workers from starting new jobs; running jobs and the queue are untouched
+struct PauseSwitch {
+    paused: AtomicBool,
+    resumed: Notify,
+    reject_submissions: bool, // answer 503 instead of queueing while paused
+}
+
+/// Numbered worker slots of this instance; a running job holds one, so its runner can name the worker
+struct WorkerSlots {
+    instance: String,
+    busy: std::sync::Mutex<BTreeSet<usize>>,
+}
+
+impl WorkerSlots {
+    /// INSTANCE_NAME, else the host name
+    fn new(instance_name: Option<&str>) -> Self {
+        let instance = instance_name.map(str::to_string)
+            .or_else(sysinfo::System::host_name)
+            .unwrap_or_else(|| "engine".to_string());
+        Self { instance, busy: std::sync::Mutex::new(BTreeSet::new()) }
+    }
+    
+    /// Lowest free slot, numbered from 1
+    fn take(&self) -> usize {
+        let mut busy = self.busy.lock().unwrap();
+        let slot = (1..).find(|slot| !busy.contains(slot)).unwrap_or_default();
+        busy.insert(slot);
+        slot
+    }
+    
+    fn release(&self, slot: usize) {
+        self.busy.lock().unwrap().remove(&slot);
+    }
+}
+
+/// Jobs in the queue and on workers, kept up to date at each transition rather than counted from the job map
+#[derive(Default)]
+struct JobGauges {
+    queued: AtomicU64, // includes scheduled jobs waiting for their run_at
+    active: AtomicU64, // jobs holding a worker, even if cancelled while running
+}
+
+impl JobGauges {
+    fn enqueued(&self) {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+    }
+    
+    fn dequeued(&self) {
+        decrement(&self.queued);
+    }
+    
+    fn started(&self) {
+        self.active.fetch_add(1, Ordering::SeqCst);
+    }
+    
+    fn finished(&self) {
+        decrement(&self.active);
+    }
+}
+
+/// Lower a gauge without wrapping, e.g. when another instance takes a job this one queued on a shared queue
+fn decrement(gauge: &AtomicU64) {
+    let _ = gauge.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |value| value.checked_sub(1));
+}
+
+/// Admission and concurrency limits that can be changed while running
+struct Capacity {
+    max_queue_depth: AtomicUsize, // 0 means unbounded
+    max_concurrent: AtomicUsize,
+    permits: Arc<Semaphore>,
+    autoscale: bool,
+    min_workers: AtomicUsize, // autoscaling bounds, also applied to manual changes while autoscaling
+    max_workers: AtomicUsize,
+}
+
+impl Capacity {
+    fn new(max_queue_depth: usize, max_concurrent: usize, autoscale: &AutoscaleConfig) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            max_queue_depth: AtomicUsize::new(max_queue_depth),
+            max_concurrent: AtomicUsize::new(max_concurrent),
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+            autoscale: autoscale.enabled,
+            min_workers: AtomicUsize::new(autoscale.min_workers),
+            max_workers: AtomicUsize::new(autoscale.max_workers),
+        }
+    }
+    
+    /// Current autoscaling bounds as (min, max)
+    fn bounds(&self) -> (usize, usize) {
+        (self.min_workers.load(Ordering::SeqCst), self.max_workers.load(Ordering::SeqCst))
+    }
+    
+    /// Grow or shrink the worker pool; shrinking waits for running jobs to release their permits
+    fn set_max_concurrent(&self, max_concurrent: usize) {
+        let max_concurrent = max_concurrent.max(1);
+        let previous = self.max_concurrent.swap(max_concurrent, Ordering::SeqCst);
+        telemetry::set_workers(max_concurrent);
+        
+        if max_concurrent > previous {
+            self.permits.add_permits(max_concurrent - previous);
+        } else if max_concurrent < previous {
+            let excess = previous - max_concurrent;
+            let outstanding = excess - self.permits.forget_permits(excess);
+            if outstanding > 0 {
+                let permits = Arc::clone(&self.permits);
+                tokio::spawn(async move {
+                    if let Ok(permit) = permits.acquire_many_owned(outstanding as u32).await {
+                        permit.forget();
+                    }
+                });
+            }
+        }
+    }
+    
+    fn settings(&self) -> CapacitySettings {
+        CapacitySettings {
+            max_queue_depth: self.max_queue_depth.load(Ordering::SeqCst),
+            max_concurrent_executions: self.max_concurrent.load(Ordering::SeqCst),
+            autoscale: self.autoscale,
+            min_workers: self.min_workers.load(Ordering::SeqCst),
+            max_workers: self.max_workers.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Settings for a new [`ExecutionEngine`]; anything left unset comes from the [`EngineConfig`]
+#[derive(Default)]
+pub struct EngineBuilder {
+    config: Option<EngineConfig>,
+    max_concurrent: Option<usize>,
+    max_queue_depth: Option<usize>,
+    queue: Option<Arc<dyn JobQueue>>,
+    backend: Option<ExecutionBackend>,
+    limits: Option<RequestValidator>,
+    memory_reader: Option<Arc<dyn MemoryReader>>,
+    disk_reader: Option<Arc<dyn DiskReader>>,
+}
+
+impl EngineBuilder {
+    /// Start from this configuration instead of [`EngineConfig::load`]
+    pub fn config(mut self, config: EngineConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+    
+    /// Number of jobs run at once (`workers`)
+    pub fn max_concurrent(mut self, workers: usize) -> Self {
+        self.max_concurrent = Some(workers);
+        self
+    }
+    
+    /// Jobs allowed to wait before submissions are refused, 0 for unbounded (`max_queue_depth`)
+    pub fn max_queue_depth(mut self, depth: usize) -> Self {
+        self.max_queue_depth = Some(depth);
+        self
+    }
+    
+    /// Queue jobs wait in, instead of the one `queue` describes
+    pub fn queue(mut self, queue: Arc<dyn JobQueue>) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+    
+    /// Where programs run (`sandbox.backend`)
+    pub fn backend(mut self, backend: ExecutionBackend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+    
+    /// Ceilings submissions are checked against (`limits.ceilings`)
+    pub fn limits(mut self, limits: RequestValidator) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+    
+    /// Where the memory watchdog reads available memory, instead of the operating system (`memory_floor_bytes`)
+    pub fn memory_reader(mut self, reader: Arc<dyn MemoryReader>) -> Self {
+        self.memory_reader = Some(reader);
+        self
+    }
+    
+    /// Where the free space of TEMP_BASE's volume is read, instead of the operating system (`sandbox.temp_min_free_bytes`)
+    pub fn disk_reader(mut self, reader: Arc<dyn DiskReader>) -> Self {
+        self.disk_reader = Some(reader);
+        self
+    }
+    
+    /// Create the engine, recover jobs left by a previous run and start the workers
+    pub async fn build(self) -> Result<ExecutionEngine> {
         info!("🔧 Initializing Rust execution engine");
         
-        let queue = ExecutionQueue::new().await?;
-        let executor = CodeExecutor::new()?;
-        let jobs = Arc::new(RwLock::new(HashMap::new()));
+        let mut config = match self.config {
+            Some(config) => config,
+            None => EngineConfig::load()?,
+        };
+        if let Some(workers) = self.max_concurrent {
+            config.workers = workers;
+        }
+        if let Some(depth) = self.max_queue_depth {
+            config.max_queue_depth = depth;
+        }
+        if let Some(backend) = self.backend {
+            config.sandbox.backend = backend;
+        }
+        if let Some(limits) = self.limits {
+            config.limits.ceilings = limits;
+        }
+        config.validate()?;
+        sandbox::protect_engine();
+        
+        // Working directories kept for debugging live outside the regular temp area
+        let retention = &config.retention;
+        let workdirs = WorkdirStore::new(
+            retention.preserve_dir.clone(),
+            chrono::Duration::seconds(retention.preserve_ttl_secs),
+        )?;
+        
+        // Repeated submissions with the same idempotency key are answered from here
+        let idempotency = IdempotencyStore::new(chrono::Duration::seconds(retention.idempotency_ttl_secs));
+        
+        // Finished jobs answer identical `dedupe: true` submissions for this long
+        let dedupe = DedupeIndex::new(chrono::Duration::seconds(retention.dedupe_window_secs));
+        
+        // Bound the backlog and how many jobs run at once
+        let capacity = Arc::new(Capacity::new(config.max_queue_depth, config.workers, &config.autoscale));
+        telemetry::set_workers(config.workers);
+        
+        // While paused, submissions are queued (accept) or refused with 503 (reject)
+        let pause = Arc::new(PauseSwitch {
+            paused: AtomicBool::new(false),
+            resumed: Notify::new(),
+            reject_submissions: config.paused_submissions == PausedSubmissions::Reject,
+        });
+        
+        // Workers stop dequeuing while available memory is below the floor
+        let reader = self.memory_reader.unwrap_or_else(|| Arc::new(SystemMemory));
+        let memory = Arc::new(MemoryGuard::new(config.memory_floor_bytes, reader));
+        
+        let queue = match self.queue {
+            Some(queue) => queue,
+            None => queue::from_config(&config.queue).await?,
+        };
+        let store = match &retention.job_store_path {
+            Some(path) => Some(JobStore::open(path).await?),
+            None => None,
+        };
+        let usage = UsageTracker::load(config.quota_config.as_deref(), store.clone()).await?;
+        let mut executor = CodeExecutor::new(workdirs.clone(), &config)?;
+        if let Some(reader) = self.disk_reader {
+            executor.set_disk_reader(reader);
+        }
+        // Logs a warning when the volume starts out below the floor; each job checks again
+        executor.disk().sample();
+        let callbacks = CallbackSender::new(&config.callbacks, chrono::Duration::seconds(retention.preserve_ttl_secs))?;
+        let archive = ResultArchive::from_config(&config.archive)?;
+        let validator = std::sync::RwLock::new(Arc::new(config.limits.ceilings.clone()));
+        let jobs = Arc::new(JobTable::new());
         let stats = Arc::new(RwLock::new(EngineStats::default()));
+        let warmup = Arc::new(if config.warmup.enabled { Warmup::default() } else { Warmup::skipped() });
         let start_time = Utc::now();
+        let slots = Arc::new(WorkerSlots::new(config.instance_name.as_deref()));
+        info!("🏷️ Running as instance {}", slots.instance);
         
-        let engine = Self {
+        let engine = ExecutionEngine {
             queue,
+            store,
             executor,
+            callbacks,
+            archive,
+            validator,
+            config: std::sync::RwLock::new(Arc::new(config)),
+            settings_lock: tokio::sync::Mutex::new(()),
             jobs,
+            watchers: Arc::new(JobWatchers::new()),
             stats,
+            gauges: Arc::new(JobGauges::default()),
+            workdirs,
+            idempotency,
+            dedupe,
+            usage,
+            capacity,
+            slots,
+            draining: Arc::new(AtomicBool::new(false)),
+            pause,
+            memory,
+            warmup,
             start_time,
         };
         
-        // Start the worker loop
+        engine.recover().await?;
+        
+        // Start the worker loop, after the first memory sample so a host already short of memory takes no job
+        engine.start_memory_watchdog();
         engine.start_worker().await;
+        engine.start_janitor();
+        engine.start_autoscaler();
+        engine.start_warmup();
         
         info!("✅ Rust execution engine initialized");
         Ok(engine)
     }
+}
+
+/// Main execution engine that coordinates everything
+pub struct ExecutionEngine {
+    queue: Arc<dyn JobQueue>,
+    store: Option<JobStore>,
+    executor: CodeExecutor,
+    callbacks: CallbackSender,
+    archive: Option<ResultArchive>,
+    validator: std::sync::RwLock<Arc<RequestValidator>>,
+    config: std::sync::RwLock<Arc<EngineConfig>>, // swapped whole by PUT /admin/config
+    settings_lock: tokio::sync::Mutex<()>, // one settings change, and its save, at a time
+    jobs: Arc<JobTable>,
+    watchers: Arc<JobWatchers>,
+    stats: Arc<RwLock<EngineStats>>,
+    gauges: Arc<JobGauges>,
+    workdirs: WorkdirStore,
+    idempotency: IdempotencyStore,
+    dedupe: DedupeIndex,
+    usage: UsageTracker,
+    capacity: Arc<Capacity>,
+    slots: Arc<WorkerSlots>,
+    draining: Arc<AtomicBool>,
+    pause: Arc<PauseSwitch>,
+    memory: Arc<MemoryGuard>,
+    warmup: Arc<Warmup>,
+    start_time: chrono::DateTime<Utc>,
+}
+
+impl ExecutionEngine {
+    /// Settings for a new engine, starting from the environment defaults
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+    
+    /// Create an engine from a complete configuration
+    pub async fn new(config: EngineConfig) -> Result<Self> {
+        Self::builder().config(config).build().await
+    }
+    
+    /// Configuration in force, including runtime settings changes; capacity may have been changed since
+    pub fn config(&self) -> Arc<EngineConfig> {
+        Arc::clone(&self.config.read().unwrap())
+    }
+    
+    /// Ceilings submissions should be checked against before [`Self::submit_execution`]
+    pub fn validator(&self) -> Arc<RequestValidator> {
+        Arc::clone(&self.validator.read().unwrap())
+    }
+    
+    /// Change ceilings, disabled languages, queue depth, autoscaling bounds or output caps without restarting.
+    ///
+    /// The changed configuration is checked like the startup one, saved to RUNTIME_SETTINGS_PATH
+    /// when set, and recorded in the audit log under `admin`. Jobs already running keep their
+    /// settings; jobs dequeued from now on get the new ones. Returns every setting now in force.
+    pub async fn update_settings(&self, changes: RuntimeSettings, admin: &str, request_id: Option<String>) -> Result<RuntimeSettings> {
+        let _one_at_a_time = self.settings_lock.lock().await;
+        let mut config = (*self.config()).clone();
+        changes.apply(&mut config)
+            .and_then(|()| config.validate())
+            .map_err(|err| InvalidSettings(format!("{:#}", err)))?;
+        let disabled = self.executor.language_ids(&config.disabled_languages)
+            .map_err(|err| InvalidSettings(err.to_string()))?;
+        
+        // Saved before anything changes, so a setting in force is never lost on restart
+        if let Some(path) = &config.runtime_settings_path {
+            let mut saved = RuntimeSettings::read(path)?.unwrap_or_default();
+            saved.merge(changes.clone());
+            saved.write(path)?;
+        }
+        
+        *self.validator.write().unwrap() = Arc::new(config.limits.ceilings.clone());
+        self.executor.update_settings(&config, disabled);
+        self.update_capacity(CapacityUpdate {
+            max_queue_depth: Some(config.max_queue_depth),
+            max_concurrent_executions: None,
+            min_workers: Some(config.autoscale.min_workers),
+            max_workers: Some(config.autoscale.max_workers),
+        })?;
+        let settings = RuntimeSettings::current(&config.redacted());
+        *self.config.write().unwrap() = Arc::new(config);
+        
+        let changes = changes.redacted();
+        info!("🛠️ Runtime settings changed by {}: {}", admin, serde_json::to_string(&changes).unwrap_or_default());
+        audit::emit_config_change(&ConfigChangeEvent {
+            event: AuditEventKind::ConfigChanged,
+            at: Utc::now(),
+            admin: admin.to_string(),
+            request_id,
+            changes,
+        });
+        Ok(settings)
+    }
     
     /// Submit a new execution request
-    pub async fn submit_execution(&self, request: ExecutionRequest) -> Result<ExecutionResponse> {
+    /// With `dedupe`, an identical live or recently finished job from the same client is returned instead
+    pub async fn submit_execution(&self, mut request: ExecutionRequest, context: SubmissionContext) -> Result<ExecutionResponse> {
+        // The engine owns identity; client ids are only honoured when not in use
+        if request.id.is_empty() {
+            request.id = Uuid::new_v4().to_string();
+        }
+        
+        // Aliases are stored as the language key, so stats and filters see one name per language
+        if let Some(key) = self.executor.resolve_language(&request.language) {
+            request.language = key.to_string();
+        }
+        if let Some(interactor) = &mut request.interactor {
+            if let Some(key) = self.executor.resolve_language(&interactor.language) {
+                interactor.language = key.to_string();
+            }
+        }
+        
+        let dedupe_key = request.dedupe
+            .unwrap_or(false)
+            .then(|| dedupe::key(context.client.as_deref(), &request));
+        if let Some(key) = &dedupe_key {
+            if let Some(existing) = self.dedupe.claim(key, &request.id, &self.jobs).await {
+                info!("♊ Deduplicated submission onto execution {}", existing.id);
+                let message = if existing.status.is_terminal() {
+                    format!("Identical execution {} already finished", existing.id)
+                } else {
+                    format!("Identical execution {} is already {}", existing.id, existing.status.as_str())
+                };
+                return Ok(ExecutionResponse {
+                    id: existing.id,
+                    status: "deduplicated".to_string(),
+                    message,
+                    parent_id: existing.parent_id,
+                });
+            }
+        }
+        
+        // Quotas count the submission up front and give it back if it never reaches the queue
+        let id = request.id.clone();
+        let client = context.client.clone();
+        let submitted = match &client {
+            Some(client) => match self.usage.admit(client).await {
+                Ok(()) => {
+                    let submitted = self.enqueue_execution(request, context).await;
+                    if submitted.is_err() {
+                        self.usage.refund(client).await;
+                    }
+                    submitted
+                }
+                Err(exceeded) => Err(exceeded.into()),
+            },
+            None => self.enqueue_execution(request, context).await,
+        };
+        if let (Err(_), Some(key)) = (&submitted, &dedupe_key) {
+            self.dedupe.release(key, &id).await;
+        }
+        submitted
+    }
+    
+    /// Usage and remaining quota of one client
+    pub fn usage(&self, client: &str) -> UsageReport {
+        self.usage.report(client)
+    }
+    
+    /// Usage and remaining quota of every client
+    pub fn all_usage(&self) -> Vec<UsageReport> {
+        self.usage.reports()
+    }
+    
+    /// Replace a client's daily and monthly quota
+    pub async fn set_quota(&self, client: &str, quota: Quota) -> UsageReport {
+        self.usage.set_quota(client, quota).await
+    }
+    
+    /// Store and queue a job for a request that has an id
+    async fn enqueue_execution(&self, request: ExecutionRequest, context: SubmissionContext) -> Result<ExecutionResponse> {
+        if self.is_draining() {
+            return Err(ShuttingDown.into());
+        }
+        if self.pause.reject_submissions && self.is_paused() {
+            return Err(Paused.into());
+        }
+        
         info!("📝 Submitting execution: {}", request.id);
         
-        let job = ExecutionJob {
+        let now = Utc::now();
+        let status = if request.run_at.is_some_and(|run_at| run_at > now) {
+            ExecutionState::Scheduled
+        } else {
+            ExecutionState::Queued
+        };
+        let source_size_bytes = request.source_code.len() as u64;
+        let mut job = ExecutionJob {
             id: request.id.clone(),
             request,
-            status: ExecutionState::Queued,
-            created_at: Utc::now(),
+            status,
+            created_at: now,
             started_at: None,
             finished_at: None,
             result: None,
+            request_id: context.request_id,
+            client: context.client,
+            attempts: 0,
+            parent_id: context.parent_id,
+            progress: None,
+            events: Vec::new(),
+            timeline: Timeline::default(),
+            archived_at: None,
+            source_size_bytes,
+            runner: None,
         };
+        job.mark_enqueued(now);
         
-        // Store the job
-        {
-            let mut jobs = self.jobs.write().await;
-            jobs.insert(job.id.clone(), job.clone());
-        }
+        // Carried on the job from the start, but only emitted once the job is accepted
+        let submitted = audit::event(&job, AuditEventKind::Submitted);
+        job.events.push(submitted.clone());
         
-        // Store the job ID before moving the job
+        // Store and queue the job under its shard lock so a concurrent submission can't reuse the id
         let job_id = job.id.clone();
-        
-        // Queue for execution
-        self.queue.enqueue(job).await?;
-        
-        // Update stats
+        let language = job.request.language.clone();
         {
-            let mut stats = self.stats.write().await;
-            stats.total_executions += 1;
-            stats.queued_executions += 1;
+            let mut jobs = self.jobs.shard(&job_id).write().await;
+            if jobs.get(&job.id).is_some_and(|existing| !existing.status.is_terminal()) {
+                return Err(DuplicateExecution(job.id).into());
+            }
+            
+            // Read from the gauges, so the check costs no scan of the jobs or queue round trip under the lock
+            let max_queue_depth = self.capacity.max_queue_depth.load(Ordering::SeqCst);
+            let queued = self.gauges.queued.load(Ordering::SeqCst) as usize;
+            if max_queue_depth > 0 && queued >= max_queue_depth {
+                drop(jobs);
+                return Err(self.queue_full(queued).await.into());
+            }
+            
+            // Counted and gauged before a worker can take it, so stats never show more waiting than submitted
+            // and a worker's decrement never lands before this increment
+            {
+                let mut stats = self.stats.write().await;
+                stats.total_executions += 1;
+                let language = stats.by_language.entry(language.clone()).or_default();
+                language.submissions += 1;
+                language.average_source_bytes += (job.source_size_bytes as f64 - language.average_source_bytes) / language.submissions as f64;
+                self.gauges.enqueued();
+            }
+            
+            let previous = jobs.insert(job.id.clone(), job.clone());
+            if let Err(err) = self.queue.enqueue(job.clone()).await {
+                match previous {
+                    Some(previous) => jobs.insert(job_id, previous),
+                    None => jobs.remove(&job_id),
+                };
+                let mut stats = self.stats.write().await;
+                self.gauges.dequeued();
+                stats.total_executions -= 1;
+                if let Some(language) = stats.by_language.get_mut(&language) {
+                    language.submissions -= 1;
+                    language.average_source_bytes = match language.submissions {
+                        0 => 0.0,
+                        n => (language.average_source_bytes * (n + 1) as f64 - job.source_size_bytes as f64) / n as f64,
+                    };
+                }
+                return Err(err);
+            }
+        }
+        audit::emit(&submitted);
+        
+        if let Ok(depth) = self.queue.size().await {
+            telemetry::set_queue_depth(depth);
         }
+        persist(self.store.as_ref(), &job).await;
         
+        let message = match job.request.run_at.filter(|_| job.status == ExecutionState::Scheduled) {
+            Some(run_at) => format!("Execution scheduled for {}", run_at.to_rfc3339()),
+            None => "Execution queued successfully".to_string(),
+        };
         Ok(ExecutionResponse {
             id: job_id,
-            status: "queued".to_string(),
-            message: "Execution queued successfully".to_string(),
+            status: job.status.as_str().to_string(),
+            message,
+            parent_id: job.parent_id.clone(),
         })
     }
     
-    /// Get execution status
-    pub async fn get_status(&self, id: &str) -> Result<Option<ExecutionStatus>> {
-        let jobs = self.jobs.read().await;
-        if let Some(job) = jobs.get(id) {
-            Ok(Some(ExecutionStatus {
-                id: job.id.clone(),
-                status: job.status.clone(),
-                created_at: job.created_at,
-                started_at: job.started_at,
-                finished_at: job.finished_at,
-                progress: None, // Could add progress tracking later
-            }))
-        } else {
-            Ok(None)
+    /// Reload jobs that were unfinished when the engine last stopped and queue them again
+    async fn recover(&self) -> Result<()> {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return Ok(()),
+        };
+        
+        let unfinished = store.unfinished().await?;
+        if unfinished.is_empty() {
+            return Ok(());
         }
-    }
-    
-    /// Get execution result
-    pub async fn get_result(&self, id: &str, include_output: bool) -> Result<Option<ExecutionResult>> {
-        let jobs = self.jobs.read().await;
-        if let Some(job) = jobs.get(id) {
-            if let Some(mut result) = job.result.clone() {
-                if !include_output {
-                    result.stdout = None;
-                    result.stderr = None;
-                    result.compile_output = None;
+        info!("♻️ Recovering {} unfinished executions", unfinished.len());
+        
+        let now = Utc::now();
+        let mut interrupted = Vec::new();
+        for mut job in unfinished {
+            if matches!(job.status, ExecutionState::Processing | ExecutionState::Running) {
+                let removed = self.executor.clean_up_job(&job.id).await;
+                if removed > 0 {
+                    info!("🧹 Removed {} sandbox leftovers of {}", removed, job.id);
+                }
+                
+                // The crashed run counts as an attempt, exactly as if it had failed with an engine error
+                if job.attempts > job.request.max_retries.unwrap_or(0) {
+                    warn!("Execution {} was running when the engine stopped, failing it", job.id);
+                    // A shared queue would otherwise deliver it again once its visibility timeout ran out
+                    self.queue.ack(&job.id).await?;
+                    self.fail_interrupted(&mut job).await;
+                    store.save(&job).await?;
+                    interrupted.push(job.id.clone());
+                    self.jobs.insert(job).await;
+                    continue;
                 }
-                Ok(Some(result))
+                info!("🔁 Execution {} was running when the engine stopped, retrying it", job.id);
+                job.progress = None;
+            }
+            
+            job.status = if job.is_scheduled(now) {
+                ExecutionState::Scheduled
             } else {
-                // Job exists but no result yet
-                Ok(Some(ExecutionResult {
-                    id: job.id.clone(),
-                    status: job.status.clone(),
-                    stdout: None,
-                    stderr: None,
-                    compile_output: None,
-                    exit_code: None,
-                    signal: None,
-                    time: None,
-                    memory: None,
-                    created_at: job.created_at,
-                    finished_at: job.finished_at,
-                }))
+                ExecutionState::Queued
+            };
+            job.started_at = None;
+            job.mark_enqueued(now);
+            
+            // Shared queues still hold (or will re-deliver) the job themselves
+            if !self.queue.is_durable() {
+                self.queue.enqueue(job.clone()).await?;
             }
-        } else {
-            Ok(None)
+            self.gauges.enqueued();
+            store.save(&job).await?;
+            self.jobs.insert(job).await;
         }
+        
+        // Callbacks retry for minutes and uploads take a while, which startup should not wait for
+        for id in interrupted {
+            let callbacks = self.callbacks.clone();
+            let archive = self.archive.clone();
+            let jobs = Arc::clone(&self.jobs);
+            let store = self.store.clone();
+            let executor = self.executor.clone();
+            tokio::spawn(async move {
+                Self::send_callback(&id, &callbacks, &jobs, store.as_ref(), &executor).await;
+                Self::archive_result(&id, archive.as_ref(), &jobs, store.as_ref(), &executor).await;
+            });
+        }
+        
+        Ok(())
     }
     
-    /// Cancel execution
-    pub async fn cancel_execution(&self, id: &str) -> Result<bool> {
-        info!("🛑 Cancelling execution: {}", id);
+    /// Give a job that died with the engine its final result
+    async fn fail_interrupted(&self, job: &mut ExecutionJob) {
+        job.status = ExecutionState::InternalError;
+        job.finished_at = Some(Utc::now());
+        job.progress = None;
+        job.timeline.stamp(TimelineStage::Finalized);
+        job.result = Some(Arc::new(ExecutionResult {
+            failure_reason: Some(INTERRUPTED_MESSAGE.to_string()),
+            ..(*job_result(job, &self.executor)).clone()
+        }));
+        audit::record(job, AuditEventKind::Failed);
         
-        let mut jobs = self.jobs.write().await;
-        if let Some(job) = jobs.get_mut(id) {
-            if matches!(job.status, ExecutionState::Queued | ExecutionState::Processing | ExecutionState::Running) {
-                job.status = ExecutionState::Cancelled;
-                job.finished_at = Some(Utc::now());
-                
-                // TODO: Actually kill the process if running
-                // self.executor.kill_process(id).await?;
-                
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        } else {
-            Ok(false)
+        let mut stats = self.stats.write().await;
+        stats.failed_executions += 1;
+        stats.by_language.entry(job.request.language.clone()).or_default().failed += 1;
+    }
+    
+    /// Find a job in memory, falling back to the persistent store
+    pub async fn lookup(&self, id: &str) -> Result<Option<ExecutionJob>> {
+        if let Some(job) = self.jobs.get(id).await {
+            return Ok(Some(job));
         }
+        self.load(id).await
     }
-
-    /// Get engine statistics
-    pub async fn get_stats(&self) -> Result<EngineStats> {
-        let stats = self.stats.read().await;
-        let mut current_stats = stats.clone();
-        // Update uptime
-        current_stats.uptime_seconds = (Utc::now() - self.start_time).num_seconds() as u64;
-        
-        // Update system metrics
-        let sys = sysinfo::System::new_all();
-        current_stats.system_load = sysinfo::System::load_average().one as f64;
-        current_stats.memory_usage = sys.used_memory();
-        
-        // Count active executions
-        let jobs = self.jobs.read().await;
-        current_stats.active_executions = jobs.values()
-            .filter(|job| matches!(job.status, ExecutionState::Processing | ExecutionState::Running))
-            .count() as u64;
-        
-        current_stats.queued_executions = jobs.values()
-            .filter(|job| matches!(job.status, ExecutionState::Queued))
-            .count() as u64;
+    
+    /// A job no longer in memory, from the store
+    async fn load(&self, id: &str) -> Result<Option<ExecutionJob>> {
+        match &self.store {
+            Some(store) => store.load(id).await,
+            None => Ok(None),
+        }
+    }
+    
+    /// Record a rejection and estimate how long the current backlog will take
+    async fn queue_full(&self, queued: usize) -> QueueFull {
+        let mut stats = self.stats.write().await;
+        stats.rejected_executions += 1;
         
-        Ok(current_stats)
+        let workers = self.capacity.max_concurrent.load(Ordering::SeqCst) as f64;
+        QueueFull {
+            queued,
+            estimated_wait_secs: queued as f64 * stats.average_execution_time / workers,
+        }
+    }
+    
+    /// Current queue depth and concurrency limits
+    pub fn capacity(&self) -> CapacitySettings {
+        self.capacity.settings()
     }
+    
+    /// Change the queue depth, concurrency limits and autoscaling bounds without restarting
+    /// While autoscaling, the worker count is kept within the bounds
+    pub fn update_capacity(&self, update: CapacityUpdate) -> Result<CapacitySettings> {
+        let (min, max) = self.capacity.bounds();
+        let (min, max) = (update.min_workers.unwrap_or(min), update.max_workers.unwrap_or(max));
+        if min == 0 {
+            return Err(InvalidCapacity("min_workers must be at least 1".to_string()).into());
+        }
+        if min > max {
+            return Err(InvalidCapacity(format!("min_workers ({}) exceeds max_workers ({})", min, max)).into());
+        }
+        self.capacity.min_workers.store(min, Ordering::SeqCst);
+        self.capacity.max_workers.store(max, Ordering::SeqCst);
         
-    /// Start the worker loop to process queued jobs
-    async fn start_worker(&self) {
-        let queue = self.queue.clone();
-        let executor = self.executor.clone();
-        let jobs = Arc::clone(&self.jobs);
-        let stats = Arc::clone(&self.stats);
+        if let Some(max_queue_depth) = update.max_queue_depth {
+            self.capacity.max_queue_depth.store(max_queue_depth, Ordering::SeqCst);
+        }
+        let workers = update.max_concurrent_executions
+            .unwrap_or_else(|| self.capacity.max_concurrent.load(Ordering::SeqCst));
+        if self.capacity.autoscale {
+            self.capacity.set_max_concurrent(workers.clamp(min, max));
+        } else if update.max_concurrent_executions.is_some() {
+            self.capacity.set_max_concurrent(workers);
+        }
         
-        tokio::spawn(async move {
-            info!("🔄 Starting execution worker loop");
-            
-            loop {
-                match queue.dequeue().await {
-                    Ok(Some(mut job)) => {
-                        info!("🚀 Processing execution: {}", job.id);
-                        
-                        // Update job status
-                        job.status = ExecutionState::Processing;
-                        job.started_at = Some(Utc::now());
-                        
-                        {
-                            let mut jobs_map = jobs.write().await;
-                            jobs_map.insert(job.id.clone(), job.clone());
-                        }
-                        
-                        // Execute the code
-                        match executor.execute(&job.request).await {
-                            Ok(result) => {
-                                info!("✅ Execution completed: {}", job.id);
-                                job.status = result.status.clone();
-                                job.finished_at = Some(Utc::now());
-                                job.result = Some(result);
-                                
-                                // Update stats
-                                {
-                                    let mut stats_map = stats.write().await;
-                                    stats_map.completed_executions += 1;
+        let settings = self.capacity.settings();
+        info!("⚖️ Capacity updated: {:?}", settings);
+        Ok(settings)
+    }
+    
+    /// Submit at most once per idempotency key, replaying the original response for repeats
+    pub async fn submit_idempotent(
+        &self,
+        request: ExecutionRequest,
+        context: SubmissionContext,
+        key: &str,
+        fingerprint: &str,
+    ) -> Result<ExecutionResponse> {
+        self.idempotency.submit(key, fingerprint, || self.submit_execution(request, context)).await
+    }
+    
+    /// The response an idempotency key already got, so a retry can be answered before rate limits are charged
+    pub fn replay_idempotent(&self, key: &str, fingerprint: &str) -> Result<Option<ExecutionResponse>> {
+        self.idempotency.replay(key, fingerprint)
+    }
+    
+    /// The subset of `ids` that have finished or no longer exist
+    pub async fn finished_jobs(&self, ids: &[String]) -> Vec<String> {
+        let mut finished = Vec::new();
+        for id in ids {
+            if self.jobs.shard(id).read().await.get(id).is_none_or(|job| job.status.is_terminal()) {
+                finished.push(id.clone());
+            }
+        }
+        finished
+    }
+    
+    /// Key and display name of the language with a Judge0 language id
+    pub fn language_by_id(&self, id: u32) -> Option<(String, String)> {
+        self.executor.language_by_id(id)
+    }
+    
+    /// What of the request this engine can honour, for submission checks
+    pub fn support(&self, request: &ExecutionRequest) -> Support {
+        self.executor.support(request)
+    }
+    
+    /// Key of the language a name or alias such as "python3" or "c++" stands for
+    pub fn resolve_language(&self, name: &str) -> Option<&str> {
+        self.executor.resolve_language(name)
+    }
+    
+    /// Closest language keys to an unknown name, and every supported key, for the 422 of an unsupported language
+    pub fn language_suggestions(&self, name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "suggestions": self.executor.suggest_languages(name),
+            "supported_languages": self.executor.language_keys(),
+        })
+    }
+    
+    /// Key and aliases of the language with a Judge0 language id
+    pub fn language_names(&self, id: u32) -> Option<(String, Vec<String>)> {
+        self.executor.language_names(id)
+    }
+    
+    /// Limits the request runs with, taking what it leaves out from its language's defaults
+    pub fn limits(&self, request: &ExecutionRequest) -> ResourceLimits {
+        self.executor.limits(request)
+    }
+    
+    /// Defaults and ceilings of the language with a Judge0 language id
+    pub fn language_limits(&self, id: u32) -> Option<(ResourceLimits, LimitCeilings)> {
+        self.executor.language_limits(id)
+    }
+    
+    /// Largest source_code of the language with a Judge0 language id
+    pub fn language_max_source_bytes(&self, id: u32) -> Option<usize> {
+        self.executor.language_max_source_bytes(id)
+    }
+    
+    /// Configured docker runtimes and whether they were available at startup
+    pub fn docker_runtimes(&self) -> BTreeMap<String, bool> {
+        self.executor.docker_runtimes()
+    }
+    
+    /// Backend and isolation features of this host
+    pub fn sandbox_features(&self) -> SandboxFeatures {
+        self.executor.sandbox_features(self.config().sandbox.backend)
+    }
+    
+    /// Startup warm-up is still running
+    pub fn warming_up(&self) -> bool {
+        !self.warmup.finished()
+    }
+    
+    /// How each language's startup warm-up went; empty when warm-up is off
+    pub fn warmup_report(&self) -> BTreeMap<String, LanguageWarmup> {
+        self.warmup.languages()
+    }
+    
+    /// False when the docker backend's default runtime was missing at startup
+    pub fn runtime_ready(&self) -> bool {
+        self.executor.runtime_ready()
+    }
+    
+    /// Workers are deferring new jobs because available memory is below `memory_floor_bytes`
+    pub fn memory_pressure(&self) -> bool {
+        self.memory.under_pressure()
+    }
+    
+    /// Size and free space of the volume under TEMP_BASE, read now
+    pub fn temp_disk(&self) -> Option<DiskSpace> {
+        self.executor.disk().sample()
+    }
+    
+    /// New jobs fail because TEMP_BASE's volume had less free space than `temp_min_free_bytes` when last read
+    pub fn low_disk_space(&self) -> bool {
+        self.executor.disk().low()
+    }
+    
+    /// Get execution status
+    pub async fn get_status(&self, id: &str) -> Result<Option<ExecutionStatus>> {
+        let average_execution_time = self.stats.read().await.average_execution_time;
+        let workers = self.capacity.max_concurrent.load(Ordering::SeqCst) as f64;
+        
+        if let Some(job) = self.lookup(id).await? {
+            let status = status_of(&job);
+            let (band_position, queue_position) = match status.status {
+                ExecutionState::Queued => (self.queue.band_position(id).await?, self.queue.position(id).await?),
+                _ => (None, None),
+            };
+            
+            // The job starts once everything ahead of it, plus a free slot, has gone through the workers
+            let estimated_wait_seconds = queue_position
+                .map(|ahead| average_execution_time * (ahead + 1) as f64 / workers);
+            
+            Ok(Some(ExecutionStatus {
+                band_position,
+                queue_position,
+                estimated_wait_seconds,
+                ..status
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    /// Status of the jobs held in memory that the query selects, newest first
+    /// Queue positions are left out; GET /status of a single job has them
+    pub async fn list_executions(&self, query: &ExecutionListQuery) -> Vec<ExecutionStatus> {
+        let mut jobs = Vec::new();
+        for shard in self.jobs.shards() {
+            jobs.extend(shard.read().await.values().filter(|job| query.matches(job)).map(status_of));
+        }
+        jobs.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+        jobs.truncate(query.limit);
+        jobs
+    }
+    
+    /// Get execution result
+    /// The stored result is shared, not copied; callers project away outputs when serializing
+    /// Jobs evicted after archival are fetched back from the bucket when it is served by proxy
+    pub async fn get_result(&self, id: &str) -> Result<Option<Arc<ExecutionResult>>> {
+        if let Some(result) = self.jobs.read(id, |job| job_result(job, &self.executor)).await {
+            return Ok(Some(result));
+        }
+        if let Some(job) = self.load(id).await? {
+            return Ok(Some(job_result(&job, &self.executor)));
+        }
+        match &self.archive {
+            Some(archive) if archive.serve() == ArchiveServe::Proxy => Ok(archive.fetch(id).await?.map(Arc::new)),
+            _ => Ok(None),
+        }
+    }
+    
+    /// A job's result and whether it was submitted base64 encoded, for serving its raw output
+    pub async fn get_output(&self, id: &str) -> Result<Option<(Arc<ExecutionResult>, bool)>> {
+        let output = |job: &ExecutionJob| (job_result(job, &self.executor), job.request.base64_encoded.unwrap_or(false));
+        if let Some(output) = self.jobs.read(id, output).await {
+            return Ok(Some(output));
+        }
+        Ok(self.load(id).await?.as_ref().map(output))
+    }
+    
+    /// Presigned URL of an evicted job's archived result, when the archive is served by redirect
+    pub async fn archived_result_url(&self, id: &str) -> Result<Option<String>> {
+        match &self.archive {
+            Some(archive) if archive.serve() == ArchiveServe::Redirect => archive.presigned_url(id).await,
+            _ => Ok(None),
+        }
+    }
+    
+    /// Wait up to `timeout` for a job to reach a terminal state; returns at once if it already has
+    pub async fn wait_for_completion(&self, id: &str, timeout: Duration) -> Result<()> {
+        // Subscribe before checking, so a job finishing in between still wakes us
+        let mut finished = self.watchers.subscribe(id);
+        let pending = self.lookup(id).await?.is_some_and(|job| !job.status.is_terminal());
+        if pending {
+            let _ = tokio::time::timeout(timeout, finished.wait_for(|done| *done)).await;
+        }
+        
+        drop(finished);
+        self.watchers.release(id);
+        Ok(())
+    }
+    
+    /// Jobs waiting in the queue, in the order they will run
+    pub async fn queued_jobs(&self) -> Result<Vec<QueuedJobInfo>> {
+        Ok(self.queue.snapshot().await?
+            .into_iter()
+            .enumerate()
+            .map(|(position, entry)| QueuedJobInfo {
+                id: entry.job.id.clone(),
+                position,
+                language: entry.job.request.language.clone(),
+                client: entry.job.client.clone(),
+                priority: entry.job.priority(),
+                promoted: entry.promoted,
+                enqueued_at: entry.enqueued_at,
+            })
+            .collect())
+    }
+    
+    /// Audit trail of a job, oldest first
+    pub async fn events(&self, id: &str) -> Result<Option<Vec<AuditEvent>>> {
+        Ok(self.lookup(id).await?.map(|job| job.events))
+    }
+    
+    /// Move a queued job to the front of the queue; false if it is not waiting
+    pub async fn promote_queued(&self, id: &str) -> Result<bool> {
+        let promoted = self.queue.promote(id).await?;
+        if promoted {
+            info!("⏫ Promoted queued execution: {}", id);
+        }
+        Ok(promoted)
+    }
+    
+    /// Drop a queued job without running it and mark it cancelled; false if it is not waiting
+    pub async fn drop_queued(&self, id: &str) -> Result<bool> {
+        if !self.queue.remove(id).await? {
+            return Ok(false);
+        }
+        self.gauges.dequeued();
+        info!("🗑️ Dropped queued execution: {}", id);
+        
+        {
+            let mut jobs = self.jobs.shard(id).write().await;
+            if let Some(job) = jobs.get_mut(id) {
+                job.status = ExecutionState::Cancelled;
+                job.finished_at = Some(Utc::now());
+                audit::record(job, AuditEventKind::Cancelled);
+                persist(self.store.as_ref(), job).await;
+            }
+        }
+        self.watchers.notify(id);
+        
+        if let Ok(depth) = self.queue.size().await {
+            telemetry::set_queue_depth(depth);
+        }
+        Ok(true)
+    }
+    
+    /// Ids of jobs as their status or progress changes; subscribe before reading the current status
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<String> {
+        self.watchers.changes.subscribe()
+    }
+    
+    /// Cancel execution
+    pub async fn cancel_execution(&self, id: &str) -> Result<bool> {
+        info!("🛑 Cancelling execution: {}", id);
+        
+        let mut jobs = self.jobs.shard(id).write().await;
+        if let Some(job) = jobs.get_mut(id) {
+            if !job.status.is_terminal() {
+                // Waiting jobs leave the queue (or the delayed set) so no worker picks them up
+                if matches!(job.status, ExecutionState::Scheduled | ExecutionState::Queued) && self.queue.remove(id).await? {
+                    self.gauges.dequeued();
+                }
+                job.status = ExecutionState::Cancelled;
+                job.finished_at = Some(Utc::now());
+                job.progress = None;
+                audit::record(job, AuditEventKind::Cancelled);
+                persist(self.store.as_ref(), job).await;
+                self.watchers.notify(id);
+                
+                // A running job stops after its current run; the process itself is not killed
+                if self.executor.cancel(id) {
+                    info!("Execution {} will stop after its current run", id);
+                }
+                
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        } else {
+            Ok(false)
+        }
+    }
+    
+    /// Cancel every unfinished job the filter matches: waiting jobs leave the queue, running ones are killed.
+    /// Every shard stays locked for the sweep, so no matching job can start or finish halfway through it.
+    pub async fn cancel_matching(&self, filter: &CancelFilter) -> Result<BulkCancelResponse> {
+        let reason = filter.reason.clone().unwrap_or_else(|| ADMIN_CANCEL_REASON.to_string());
+        let mut shards = Vec::new();
+        for shard in self.jobs.shards() {
+            shards.push(shard.write().await);
+        }
+        
+        let mut by_status = BTreeMap::new();
+        let mut cancelled = Vec::new();
+        for jobs in shards.iter_mut() {
+            for job in jobs.values_mut().filter(|job| filter.matches(job)) {
+                let prior = job.status.clone();
+                if matches!(prior, ExecutionState::Scheduled | ExecutionState::Queued) && self.queue.remove(&job.id).await? {
+                    self.gauges.dequeued();
+                }
+                job.result = Some(Arc::new(ExecutionResult {
+                    status: ExecutionState::Cancelled,
+                    failure_reason: Some(reason.clone()),
+                    ..(*job_result(job, &self.executor)).clone()
+                }));
+                job.status = ExecutionState::Cancelled;
+                job.finished_at = Some(Utc::now());
+                job.progress = None;
+                audit::record(job, AuditEventKind::Cancelled);
+                persist(self.store.as_ref(), job).await;
+                
+                // No further run starts, and the current one is killed rather than left to finish
+                if self.executor.cancel(&job.id) {
+                    self.executor.kill_job(&job.id);
+                }
+                *by_status.entry(prior.as_str().to_string()).or_insert(0) += 1;
+                cancelled.push(job.id.clone());
+            }
+        }
+        drop(shards);
+        
+        for id in &cancelled {
+            self.watchers.notify(id);
+        }
+        if let Ok(depth) = self.queue.size().await {
+            telemetry::set_queue_depth(depth);
+        }
+        warn!("🛑 Cancelled {} executions matching {:?}: {}", cancelled.len(), filter, reason);
+        Ok(BulkCancelResponse { cancelled: cancelled.len() as u64, by_status })
+    }
+    
+    /// Stop starting new executions; running ones finish and queued ones wait
+    pub fn pause(&self) {
+        if !self.pause.paused.swap(true, Ordering::SeqCst) {
+            warn!("⏸️ Execution processing paused");
+        }
+    }
+    
+    /// Start taking jobs from the queue again, waking the worker loop at once
+    pub fn resume(&self) {
+        if self.pause.paused.swap(false, Ordering::SeqCst) {
+            info!("▶️ Execution processing resumed");
+            self.pause.resumed.notify_one();
+        }
+    }
+    
+    /// Whether workers are held back from starting new jobs
+    pub fn is_paused(&self) -> bool {
+        self.pause.paused.load(Ordering::SeqCst)
+    }
+    
+    /// Whether shutdown has started and new work is being refused
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+    
+    /// Stop taking work, give running jobs up to `drain_timeout` to finish, then kill and cancel the rest
+    /// Queued jobs are left alone; with a job store or shared queue they are picked up after restart
+    pub async fn shutdown(&self, drain_timeout: Duration) {
+        self.draining.store(true, Ordering::SeqCst);
+        info!("🛑 Draining running executions for up to {}s", drain_timeout.as_secs());
+        
+        let deadline = tokio::time::Instant::now() + drain_timeout;
+        while self.gauges.active.load(Ordering::SeqCst) > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+        
+        let killed = self.executor.kill_running();
+        if killed > 0 {
+            warn!("Killed {} processes still running at shutdown", killed);
+        }
+        
+        for shard in self.jobs.shards() {
+            let mut jobs = shard.write().await;
+            for job in jobs.values_mut().filter(|job| matches!(job.status, ExecutionState::Processing | ExecutionState::Running)) {
+                warn!("Cancelling execution {} at shutdown", job.id);
+                job.status = ExecutionState::Cancelled;
+                job.finished_at = Some(Utc::now());
+                job.progress = None;
+                audit::record(job, AuditEventKind::Cancelled);
+                persist(self.store.as_ref(), job).await;
+                self.watchers.notify(&job.id);
+                
+                // Shared queues would otherwise hand the job to another instance
+                if let Err(err) = self.queue.ack(&job.id).await {
+                    error!("❌ Failed to acknowledge {}: {}", job.id, err);
+                }
+            }
+        }
+    }
+    
+    /// Delete an execution along with any preserved working directory and archived result
+    pub async fn delete_execution(&self, id: &str) -> Result<bool> {
+        let removed = {
+            let mut jobs = self.jobs.shard(id).write().await;
+            match jobs.get(id) {
+                Some(job) if !job.status.is_terminal() => None,
+                Some(_) => jobs.remove(id),
+                None => None,
+            }
+        };
+        let Some(job) = removed else {
+            return Ok(false);
+        };
+        
+        self.callbacks.forget(id);
+        self.workdirs.remove(id).await?;
+        self.executor.spill().remove(id)?;
+        if let Some(store) = &self.store {
+            store.delete(id).await?;
+        }
+        if let (Some(archive), Some(_), Some(finished_at)) = (&self.archive, job.archived_at, job.finished_at) {
+            archive.delete(id, finished_at).await?;
+        }
+        Ok(true)
+    }
+    
+    /// Callbacks that failed every attempt, newest first
+    pub fn failed_callbacks(&self, offset: usize, limit: usize) -> DeadLetterPage {
+        self.callbacks.dead_letters(offset, limit)
+    }
+    
+    /// Try a dead-lettered callback once more with the job's current result
+    /// Returns the updated entry when it failed again, or [`NoDeadLetter`] when there is nothing to retry
+    pub async fn retry_callback(&self, id: &str) -> Result<Option<DeadLetter>> {
+        if !self.callbacks.is_dead_lettered(id) {
+            return Err(NoDeadLetter(id.to_string()).into());
+        }
+        let Some(job) = self.lookup(id).await? else {
+            self.callbacks.forget(id);
+            return Err(NoDeadLetter(id.to_string()).into());
+        };
+        
+        let outcome = self.callbacks.retry(&job, &job_result(&job, &self.executor)).await;
+        Self::record_callback(id, &outcome, &self.jobs, self.store.as_ref()).await;
+        Ok(outcome.err())
+    }
+    
+    /// List the files of a preserved working directory
+    pub async fn list_workdir(&self, id: &str) -> Result<Option<Vec<WorkdirEntry>>> {
+        self.workdirs.list(id).await
+    }
+    
+    /// Locate a file inside a preserved working directory
+    pub async fn workdir_file(&self, id: &str, path: &str) -> Result<Option<PathBuf>> {
+        self.workdirs.resolve(id, path).await
+    }
+    
+    /// Spill file of a job's stdout or stderr, and whether it holds binary (base64-submitted) output
+    pub async fn output_file(&self, id: &str, stream: OutputStream) -> Result<Option<(PathBuf, bool)>> {
+        let Some(job) = self.lookup(id).await? else {
+            return Ok(None);
+        };
+        let binary = job.request.base64_encoded.unwrap_or(false);
+        Ok(self.executor.spill().file(id, stream).map(|path| (path, binary)))
+    }
+    
+    /// Get engine statistics
+    ///
+    /// Figures are for this instance, named as in the `runner` of the jobs it ran.
+    pub async fn get_stats(&self) -> Result<EngineStats> {
+        let stats = self.stats.read().await;
+        let mut current_stats = stats.clone();
+        current_stats.instance = self.slots.instance.clone();
+        current_stats.paused = self.is_paused();
+        current_stats.memory_pressure = self.memory.under_pressure();
+        current_stats.deferred_dequeues = self.memory.deferred();
+        current_stats.temp_disk = self.temp_disk();
+        current_stats.low_disk_space = self.low_disk_space();
+        
+        // Update uptime
+        current_stats.uptime_seconds = (Utc::now() - self.start_time).num_seconds() as u64;
+        current_stats.p50_execution_time = stats.execution_times.percentile(0.50) as f64 / 1000.0;
+        current_stats.p95_execution_time = stats.execution_times.percentile(0.95) as f64 / 1000.0;
+        current_stats.p99_execution_time = stats.execution_times.percentile(0.99) as f64 / 1000.0;
+        current_stats.overhead_ms_p50 = stats.overhead_times.percentile(0.50) as f64;
+        current_stats.overhead_ms_p95 = stats.overhead_times.percentile(0.95) as f64;
+        current_stats.windows = stats.recent.windows(Utc::now());
+        
+        // Update system metrics
+        let sys = sysinfo::System::new_all();
+        current_stats.system_load = sysinfo::System::load_average().one;
+        current_stats.memory_usage = sys.used_memory();
+        
+        if let Some((hits, misses)) = self.executor.compile_cache_counters() {
+            current_stats.compile_cache_hits = hits;
+            current_stats.compile_cache_misses = misses;
+            if hits + misses > 0 {
+                current_stats.compile_cache_hit_rate = hits as f64 / (hits + misses) as f64;
+            }
+        }
+        
+        (current_stats.callbacks_delivered, current_stats.callbacks_dead_lettered) = self.callbacks.counters();
+        if let Some(archive) = &self.archive {
+            (current_stats.results_archived, current_stats.archive_failures, current_stats.archive_pending) = archive.counters();
+        }
+        current_stats.active_executions = self.gauges.active.load(Ordering::SeqCst);
+        current_stats.workers = self.capacity.max_concurrent.load(Ordering::SeqCst);
+        current_stats.queued_executions = self.gauges.queued.load(Ordering::SeqCst);
+        
+        Ok(current_stats)
+    }
+    
+    /// Up to `limit` of the latest finished jobs, newest first
+    pub async fn recent_executions(&self, limit: usize) -> Vec<RecentExecution> {
+        self.stats.read().await.recent.latest(limit)
+    }
+        
+    /// Forget the timing aggregates; counters are left alone
+    pub async fn reset_stats(&self) {
+        let mut stats = self.stats.write().await;
+        stats.execution_times.reset();
+        stats.average_execution_time = 0.0;
+        stats.average_queue_wait_time = 0.0;
+        stats.queue_wait_samples = 0;
+        stats.average_start_latency_ms = 0.0;
+        stats.start_latency_samples = 0;
+        stats.average_overhead_ms = 0.0;
+        stats.overhead_samples = 0;
+        stats.overhead_times.reset();
+        stats.recent.reset();
+        info!("📉 Timing statistics reset");
+    }
+    
+    /// Periodic housekeeping: expired preserved dirs, idempotency keys, dedupe entries and dead letters, orphaned spill files, leaked temp dirs, container pools,
+    /// failed archive uploads and archived jobs due for eviction
+    fn start_janitor(&self) {
+        let workdirs = self.workdirs.clone();
+        let idempotency = self.idempotency.clone();
+        let dedupe = self.dedupe.clone();
+        let callbacks = self.callbacks.clone();
+        let archive = self.archive.clone();
+        let jobs = Arc::clone(&self.jobs);
+        let store = self.store.clone();
+        let executor = self.executor.clone();
+        let stats = Arc::clone(&self.stats);
+        
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                workdirs.sweep().await;
+                idempotency.sweep();
+                dedupe.sweep(&jobs).await;
+                callbacks.sweep();
+                executor.maintain_container_pool().await;
+                
+                if let Some(archive) = &archive {
+                    for id in archive.take_pending() {
+                        Self::archive_result(&id, Some(archive), &jobs, store.as_ref(), &executor).await;
+                    }
+                    let evicted = Self::evict_archived(archive, &jobs, store.as_ref()).await;
+                    if evicted > 0 {
+                        info!("🗄️ Evicted {} archived executions", evicted);
+                        stats.write().await.evicted_executions += evicted;
+                    }
+                }
+                
+                // Spilled outputs of jobs that are gone: evicted, or lost in a restart without a job store
+                for id in executor.spill().ids() {
+                    let known = jobs.shard(&id).read().await.contains_key(&id) || match &store {
+                        Some(store) => !matches!(store.load(&id).await, Ok(None)),
+                        None => false,
+                    };
+                    if !known {
+                        if let Err(err) = executor.spill().remove(&id) {
+                            warn!("Failed to remove the spilled output of {}: {}", id, err);
+                        }
+                    }
+                }
+                
+                // Catch temp directories leaked by crashed or killed executions
+                let sweeper = executor.clone();
+                match tokio::task::spawn_blocking(move || sweeper.sweep_stale_dirs()).await {
+                    Ok(reclaimed) if reclaimed > 0 => {
+                        info!("🧹 Reclaimed {} stale temp directories", reclaimed);
+                        stats.write().await.reclaimed_temp_dirs += reclaimed as u64;
+                    }
+                    Ok(_) => {}
+                    Err(err) => error!("❌ Temp directory sweep failed: {}", err),
+                }
+            }
+        });
+    }
+    
+    /// Grow the worker pool while a backlog lasts and retire idle workers once the queue stays empty
+    fn start_autoscaler(&self) {
+        if !self.capacity.autoscale {
+            return;
+        }
+        let capacity = Arc::clone(&self.capacity);
+        let gauges = Arc::clone(&self.gauges);
+        let stats = Arc::clone(&self.stats);
+        let pause = Arc::clone(&self.pause);
+        let draining = Arc::clone(&self.draining);
+        let mut policy = ScalingPolicy::new(self.config().autoscale.clone());
+        
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(autoscale::SAMPLE_INTERVAL);
+            loop {
+                interval.tick().await;
+                // Nothing drains the queue while paused, so its backlog says nothing about the pool size
+                if pause.paused.load(Ordering::SeqCst) || draining.load(Ordering::SeqCst) {
+                    policy.reset();
+                    continue;
+                }
+                
+                let workers = capacity.max_concurrent.load(Ordering::SeqCst);
+                let queued = gauges.queued.load(Ordering::SeqCst) as usize;
+                let average_execution_time = stats.read().await.average_execution_time;
+                let sample = Sample {
+                    workers,
+                    active: gauges.active.load(Ordering::SeqCst) as usize,
+                    queued,
+                    estimated_wait_secs: queued as f64 * average_execution_time / workers as f64,
+                };
+                if let Some(target) = policy.decide(sample, capacity.bounds(), std::time::Instant::now()) {
+                    info!("⚖️ Autoscaling from {} to {} workers ({} queued, {} running)", workers, target, queued, sample.active);
+                    capacity.set_max_concurrent(target);
+                }
+            }
+        });
+    }
+    
+    /// Sample available memory so workers can stop taking jobs before the host runs out
+    fn start_memory_watchdog(&self) {
+        if !self.memory.enabled() {
+            return;
+        }
+        self.memory.sample();
+        let memory = Arc::clone(&self.memory);
+        
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(pressure::SAMPLE_INTERVAL);
+            loop {
+                interval.tick().await;
+                memory.sample();
+            }
+        });
+    }
+    
+    /// Warm every language in the background; /health reports not ready until it is done
+    fn start_warmup(&self) {
+        if self.warmup.finished() {
+            return;
+        }
+        let warmup = Arc::clone(&self.warmup);
+        let executor = self.executor.clone();
+        let timeout = Duration::from_secs(self.config().warmup.timeout_secs);
+        tokio::spawn(async move {
+            info!("🔥 Warming up languages");
+            warmup.run(&executor, timeout).await;
+        });
+    }
+    
+    /// Start the worker loop to process queued jobs
+    async fn start_worker(&self) {
+        let queue = Arc::clone(&self.queue);
+        let store = self.store.clone();
+        let executor = self.executor.clone();
+        let callbacks = self.callbacks.clone();
+        let archive = self.archive.clone();
+        let jobs = Arc::clone(&self.jobs);
+        let watchers = Arc::clone(&self.watchers);
+        let stats = Arc::clone(&self.stats);
+        let gauges = Arc::clone(&self.gauges);
+        let usage = self.usage.clone();
+        let permits = Arc::clone(&self.capacity.permits);
+        let slots = Arc::clone(&self.slots);
+        let draining = Arc::clone(&self.draining);
+        let pause = Arc::clone(&self.pause);
+        let memory = Arc::clone(&self.memory);
+        
+        tokio::spawn(async move {
+            info!("🔄 Starting execution worker loop");
+            
+            loop {
+                // Wait for a free slot before taking the next job
+                let permit = match Arc::clone(&permits).acquire_owned().await {
+                    Ok(permit) => permit,
+                    Err(_) => break,
+                };
+                if draining.load(Ordering::SeqCst) {
+                    info!("🛑 Worker loop stopped for shutdown");
+                    break;
+                }
+                if pause.paused.load(Ordering::SeqCst) {
+                    // resume() leaves a permit on the Notify, so a resume racing this check isn't lost
+                    drop(permit);
+                    pause.resumed.notified().await;
+                    continue;
+                }
+                if memory.under_pressure() {
+                    // Only a job left waiting counts as deferred, not an idle worker
+                    drop(permit);
+                    if gauges.queued.load(Ordering::SeqCst) > 0 {
+                        memory.defer();
+                    }
+                    tokio::time::sleep(pressure::SAMPLE_INTERVAL).await;
+                    continue;
+                }
+                
+                match queue.dequeue().await {
+                    Ok(Some(mut job)) => {
+                        gauges.dequeued();
+                        if let Ok(depth) = queue.size().await {
+                            telemetry::set_queue_depth(depth);
+                        }
+                        
+                        let queue = Arc::clone(&queue);
+                        let store = store.clone();
+                        let executor = executor.clone();
+                        let callbacks = callbacks.clone();
+                        let archive = archive.clone();
+                        let jobs = Arc::clone(&jobs);
+                        let watchers = Arc::clone(&watchers);
+                        let stats = Arc::clone(&stats);
+                        let gauges = Arc::clone(&gauges);
+                        let usage = usage.clone();
+                        let slots = Arc::clone(&slots);
+                        tokio::spawn(async move {
+                            let id = job.id.clone();
+                            let slot = slots.take();
+                            job.runner = Some(Runner::new(&slots.instance, slot, executor.backend_for(&job.request)));
+                            let retry = Self::process_job(job, &executor, &jobs, &watchers, &stats, &gauges, store.as_ref()).await;
+                            slots.release(slot);
+                            
+                            // Charge the client for what a finished run consumed
+                            if let Some(job) = jobs.get(&id).await {
+                                if let (Some(client), Some(result)) = (&job.client, &job.result) {
+                                    usage.record(client, result).await;
                                 }
                             }
-                            Err(err) => {
-                                error!("❌ Execution failed: {} - {}", job.id, err);
-                                job.status = ExecutionState::InternalError;
-                                job.finished_at = Some(Utc::now());
-                                
-                                // Update stats
-                                {
-                                    let mut stats_map = stats.write().await;
-                                    stats_map.failed_executions += 1;
-                                }
+                            
+                            // Unacknowledged jobs are re-delivered by shared queues
+                            if let Err(err) = queue.ack(&id).await {
+                                error!("❌ Failed to acknowledge {}: {}", id, err);
                             }
-                        }
-                        
-                        // Store the updated job
-                        {
-                            let mut jobs_map = jobs.write().await;
-                            jobs_map.insert(job.id.clone(), job);
-                        }
+                            drop(permit);
+                            
+                            if let Some((job, delay)) = retry {
+                                Self::requeue_after(job, delay, queue.as_ref(), &jobs, &watchers, &gauges, store.as_ref()).await;
+                            }
+                            
+                            Self::send_callback(&id, &callbacks, &jobs, store.as_ref(), &executor).await;
+                            Self::archive_result(&id, archive.as_ref(), &jobs, store.as_ref(), &executor).await;
+                        });
                     }
                     Ok(None) => {
                         // No jobs in queue, wait a bit
+                        drop(permit);
                         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                     }
                     Err(err) => {
+                        drop(permit);
                         error!("❌ Queue error: {}", err);
                         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
                     }
@@ -248,20 +1631,995 @@ impl ExecutionEngine {
             }
         });
     }
+    
+    /// POST a job that finished for good to its callback_url and note the outcome on the job
+    /// Cancelled jobs and jobs waiting for a retry are not called back
+    async fn send_callback(
+        id: &str,
+        callbacks: &CallbackSender,
+        jobs: &JobTable,
+        store: Option<&JobStore>,
+        executor: &CodeExecutor,
+    ) {
+        let Some(job) = jobs.get(id).await else {
+            return;
+        };
+        if job.request.callback_url.is_none() || !job.status.is_terminal() || job.status == ExecutionState::Cancelled {
+            return;
+        }
+        
+        let outcome = callbacks.deliver(&job, &job_result(&job, executor)).await;
+        Self::record_callback(id, &outcome, jobs, store).await;
+    }
+    
+    /// Note a delivery outcome on the job; retries can take minutes, so it goes on the current copy
+    async fn record_callback(id: &str, outcome: &Result<(), DeadLetter>, jobs: &JobTable, store: Option<&JobStore>) {
+        let kind = match outcome {
+            Ok(()) => AuditEventKind::CallbackDelivered,
+            Err(_) => AuditEventKind::CallbackFailed,
+        };
+        let job = {
+            let mut jobs_map = jobs.shard(id).write().await;
+            let Some(job) = jobs_map.get_mut(id) else {
+                return;
+            };
+            audit::record(job, kind);
+            job.clone()
+        };
+        persist(store, &job).await;
+    }
+    
+    /// Copy a job that finished for good to the archive bucket and note when on the job
+    /// A failed upload is logged and left to the janitor's retry; the job itself is unaffected
+    async fn archive_result(
+        id: &str,
+        archive: Option<&ResultArchive>,
+        jobs: &JobTable,
+        store: Option<&JobStore>,
+        executor: &CodeExecutor,
+    ) {
+        let Some(archive) = archive else {
+            return;
+        };
+        let job = match (jobs.get(id).await, store) {
+            (Some(job), _) => job,
+            (None, Some(store)) => match store.load(id).await {
+                Ok(Some(job)) => job,
+                _ => return,
+            },
+            (None, None) => return,
+        };
+        let Some(finished_at) = job.finished_at.filter(|_| job.status.is_terminal() && job.archived_at.is_none()) else {
+            return;
+        };
+        
+        if let Err(err) = archive.store(id, finished_at, &job_result(&job, executor)).await {
+            warn!("Failed to archive execution {}: {}", id, err);
+            return;
+        }
+        let archived_at = Some(Utc::now());
+        let job = match jobs.shard(id).write().await.get_mut(id) {
+            Some(current) => {
+                current.archived_at = archived_at;
+                current.clone()
+            }
+            None => ExecutionJob { archived_at, ..job },
+        };
+        persist(store, &job).await;
+    }
+    
+    /// Drop archived jobs that finished longer than `evict_after` ago from memory and the job store
+    async fn evict_archived(archive: &ResultArchive, jobs: &JobTable, store: Option<&JobStore>) -> u64 {
+        let Some(evict_after) = archive.evict_after() else {
+            return 0;
+        };
+        let cutoff = Utc::now() - evict_after;
+        let mut evicted = 0;
+        for shard in jobs.shards() {
+            let mut shard = shard.write().await;
+            let before = shard.len();
+            shard.retain(|_, job| job.archived_at.is_none() || job.finished_at.is_none_or(|finished_at| finished_at >= cutoff));
+            evicted += (before - shard.len()) as u64;
+        }
+        
+        
+        // The store also holds archived jobs that finished before a restart and never came back into memory
+        match store.map(|store| store.delete_archived(cutoff)) {
+            Some(deleted) => deleted.await.unwrap_or_else(|err| {
+                warn!("Failed to evict archived executions: {}", err);
+                evicted
+            }),
+            None => evicted,
+        }
+    }
+    
+    /// Record each progress update on the job until the executor closes the channel
+    /// The job enters the Running state once its program (rather than a compiler) starts
+    async fn track_progress(
+        id: &str,
+        mut updates: mpsc::UnboundedReceiver<ExecutionProgress>,
+        jobs: &JobTable,
+        watchers: &JobWatchers,
+    ) {
+        while let Some(progress) = updates.recv().await {
+            {
+                let mut jobs_map = jobs.shard(id).write().await;
+                let Some(job) = jobs_map.get_mut(id) else {
+                    continue;
+                };
+                if !matches!(job.status, ExecutionState::Processing | ExecutionState::Running) {
+                    continue;
+                }
+                if progress.phase == ExecutionPhase::Running {
+                    if job.progress.as_ref().is_some_and(|previous| previous.phase == ExecutionPhase::Compiling) {
+                        audit::record(job, AuditEventKind::Compiled);
+                    }
+                    job.status = ExecutionState::Running;
+                }
+                job.progress = Some(progress);
+            }
+            watchers.changed(id);
+        }
+    }
+    
+    /// Put a job that hit an internal error back on the queue once its backoff has passed
+    async fn requeue_after(
+        mut job: ExecutionJob,
+        delay: Duration,
+        queue: &dyn JobQueue,
+        jobs: &JobTable,
+        watchers: &JobWatchers,
+        gauges: &JobGauges,
+        store: Option<&JobStore>,
+    ) {
+        tokio::time::sleep(delay).await;
+        
+        let mut jobs_map = jobs.shard(&job.id).write().await;
+        if jobs_map.get(&job.id).is_some_and(|current| current.status != ExecutionState::Queued) {
+            info!("Not retrying {}, it was cancelled during backoff", job.id);
+            return;
+        }
+        job.mark_enqueued(Utc::now());
+        match queue.enqueue(job.clone()).await {
+            Ok(()) => gauges.enqueued(),
+            Err(err) => {
+                error!("❌ Failed to re-queue {} for retry: {}", job.id, err);
+                job.status = ExecutionState::InternalError;
+                job.finished_at = Some(Utc::now());
+                audit::record(&mut job, AuditEventKind::Failed);
+                jobs_map.insert(job.id.clone(), job.clone());
+                drop(jobs_map);
+                watchers.notify(&job.id);
+                persist(store, &job).await;
+            }
+        }
+    }
+    
+    /// Run a single job and record its outcome
+    /// Returns the job and a backoff delay when an internal error should be retried
+    #[instrument(skip_all, fields(id = %job.id, request_id = job.request_id.as_deref()))]
+    async fn process_job(
+        mut job: ExecutionJob,
+        executor: &CodeExecutor,
+        jobs: &JobTable,
+        watchers: &JobWatchers,
+        stats: &RwLock<EngineStats>,
+        gauges: &JobGauges,
+        store: Option<&JobStore>,
+    ) -> Option<(ExecutionJob, Duration)> {
+        info!("🚀 Processing execution: {}", job.id);
+        telemetry::execution_started();
+        gauges.started();
+        
+        // Update job status
+        job.status = ExecutionState::Processing;
+        job.started_at = Some(Utc::now());
+        job.attempts += 1;
+        job.timeline.stamp(TimelineStage::Dequeued);
+        {
+            // The queue's copy may predate events recorded since it was enqueued
+            let mut jobs_map = jobs.shard(&job.id).write().await;
+            if let Some(current) = jobs_map.get(&job.id) {
+                // Cancelled between leaving the queue and getting here
+                if current.status == ExecutionState::Cancelled {
+                    telemetry::execution_finished();
+                    gauges.finished();
+                    return None;
+                }
+                job.events = current.events.clone();
+            }
+            audit::record(&mut job, AuditEventKind::Started);
+            jobs_map.insert(job.id.clone(), job.clone());
+        }
+        watchers.changed(&job.id);
+        persist(store, &job).await;
+        
+        // Execute the code, publishing phase changes as the executor reports them
+        let (progress, updates) = mpsc::unbounded_channel();
+        let (outcome, ()) = tokio::join!(
+            executor.execute(&job.request, progress, &mut job.timeline),
+            Self::track_progress(&job.id, updates, jobs, watchers),
+        );
+        job.progress = None;
+        match outcome {
+            Ok(result) => {
+                info!("✅ Execution completed: {}", job.id);
+                job.status = result.status.clone();
+                job.finished_at = Some(Utc::now());
+                job.timeline.stamp(TimelineStage::Finalized);
+                let overhead_ms = job.timeline.overhead_ms();
+                
+                // Update stats
+                {
+                    let mut stats_map = stats.write().await;
+                    stats_map.completed_executions += 1;
+                    
+                    // How long the job occupied a worker, and how long it waited for one
+                    if let Some(started_at) = job.started_at {
+                        let duration = (Utc::now() - started_at).num_milliseconds().max(0) as u64;
+                        stats_map.execution_times.record(duration);
+                        stats_map.average_execution_time = stats_map.execution_times.mean() / 1000.0;
+                        
+                        let wait = (started_at - job.created_at).num_milliseconds().max(0) as f64 / 1000.0;
+                        stats_map.queue_wait_samples += 1;
+                        stats_map.average_queue_wait_time +=
+                            (wait - stats_map.average_queue_wait_time) / stats_map.queue_wait_samples as f64;
+                    }
+                    
+                    // Submission-to-start latency, including any container startup
+                    if let Some(started_at) = result.started_at {
+                        let latency = (started_at - job.created_at).num_milliseconds().max(0) as f64;
+                        stats_map.start_latency_samples += 1;
+                        stats_map.average_start_latency_ms +=
+                            (latency - stats_map.average_start_latency_ms) / stats_map.start_latency_samples as f64;
+                    }
+                    
+                    if let Some(overhead) = overhead_ms {
+                        stats_map.overhead_times.record(overhead.round() as u64);
+                        stats_map.overhead_samples += 1;
+                        stats_map.average_overhead_ms +=
+                            (overhead - stats_map.average_overhead_ms) / stats_map.overhead_samples as f64;
+                    }
+                    
+                    let language = stats_map.by_language.entry(job.request.language.clone()).or_default();
+                    language.completed += 1;
+                    let samples = language.completed as f64;
+                    language.average_execution_time += (result.time.unwrap_or(0.0) - language.average_execution_time) / samples;
+                    language.average_memory += (result.memory.unwrap_or(0) as f64 - language.average_memory) / samples;
+                }
+                
+                let mut result = ExecutionResult {
+                    attempts: job.attempts,
+                    metadata: job.request.metadata.clone(),
+                    runner: job.runner.clone(),
+                    timeline: job.timeline.entries().to_vec(),
+                    queue_wait_ms: job.timeline.queue_wait_ms(),
+                    overhead_ms,
+                    phases: job.timeline.phases(),
+                    ..result
+                };
+                // Huge outputs are stored and persisted as files rather than in the result
+                if let Err(err) = executor.spill().spill(&mut result, job.request.base64_encoded.unwrap_or(false)) {
+                    warn!("Could not spill the output of {}, keeping it in the result: {}", job.id, err);
+                }
+                job.result = Some(Arc::new(result));
+            }
+            Err(err) if job.attempts <= job.request.max_retries.unwrap_or(0) => {
+                // Only engine failures land here; user errors come back as Ok with their own status
+                let delay = retry_delay(job.attempts);
+                warn!(
+                    "🔁 Execution {} failed on attempt {}, retrying in {}s: {}",
+                    job.id, job.attempts, delay.as_secs(), err
+                );
+                telemetry::execution_finished();
+                gauges.finished();
+                
+                job.status = ExecutionState::Queued;
+                job.started_at = None;
+                job.runner = None;
+                {
+                    let mut jobs_map = jobs.shard(&job.id).write().await;
+                    match jobs_map.get(&job.id) {
+                        Some(current) if current.status == ExecutionState::Cancelled => return None,
+                        Some(current) => job.events = current.events.clone(),
+                        None => {}
+                    }
+                    jobs_map.insert(job.id.clone(), job.clone());
+                }
+                watchers.changed(&job.id);
+                persist(store, &job).await;
+                return Some((job, delay));
+            }
+            Err(err) => {
+                error!("❌ Execution failed: {} - {}", job.id, err);
+                job.status = ExecutionState::InternalError;
+                job.finished_at = Some(Utc::now());
+                job.timeline.stamp(TimelineStage::Finalized);
+                
+                // A full disk is worth naming; other engine errors stay in the logs
+                if let Some(reason) = executor.disk().out_of_space(&err) {
+                    job.result = Some(Arc::new(ExecutionResult {
+                        failure_reason: Some(reason),
+                        ..(*job_result(&job, executor)).clone()
+                    }));
+                }
+                
+                // Update stats
+                {
+                    let mut stats_map = stats.write().await;
+                    stats_map.failed_executions += 1;
+                    stats_map.by_language.entry(job.request.language.clone()).or_default().failed += 1;
+                }
+            }
+        }
+        
+        let seconds = job.started_at.zip(job.finished_at)
+            .map_or(0.0, |(start, end)| (end - start).num_milliseconds().max(0) as f64 / 1000.0);
+        telemetry::record_execution(&job.request.language, job.status.as_str(), seconds);
+        stats.write().await.recent.record(RecentExecution {
+            finished_at: job.finished_at.unwrap_or_else(Utc::now),
+            language: job.request.language.clone(),
+            status: job.status.clone(),
+            duration_ms: (seconds * 1000.0).round() as u64,
+        });
+        telemetry::execution_finished();
+        gauges.finished();
+        
+        // Store the updated job; one cancelled while running keeps only the runs that finished
+        {
+            let mut jobs_map = jobs.shard(&job.id).write().await;
+            match jobs_map.get_mut(&job.id) {
+                Some(current) if current.status == ExecutionState::Cancelled => {
+                    let mut result = job.result.filter(|result| result.status == ExecutionState::Cancelled)?;
+                    // Keep the reason a bulk cancel recorded
+                    if let Some(reason) = current.result.as_ref().and_then(|cancelled| cancelled.failure_reason.clone()) {
+                        Arc::make_mut(&mut result).failure_reason.get_or_insert(reason);
+                    }
+                    current.result = Some(result);
+                    let current = current.clone();
+                    drop(jobs_map);
+                    watchers.notify(&current.id);
+                    persist(store, &current).await;
+                    return None;
+                }
+                Some(current) => job.events = current.events.clone(),
+                None => {}
+            }
+            let finished = match job.status {
+                ExecutionState::InternalError => AuditEventKind::Failed,
+                _ => AuditEventKind::Completed,
+            };
+            audit::record(&mut job, finished);
+            jobs_map.insert(job.id.clone(), job.clone());
+        }
+        watchers.notify(&job.id);
+        persist(store, &job).await;
+        None
+    }
+}
+
+/// Where a job stands, without its place in the queue
+fn status_of(job: &ExecutionJob) -> ExecutionStatus {
+    // Scheduled jobs stay marked as such until a worker takes them, even once due
+    let status = match job.status {
+        ExecutionState::Scheduled if !job.is_scheduled(Utc::now()) => ExecutionState::Queued,
+        ref status => status.clone(),
+    };
+    ExecutionStatus {
+        id: job.id.clone(),
+        status,
+        created_at: job.created_at,
+        started_at: job.started_at,
+        finished_at: job.finished_at,
+        run_at: job.request.run_at,
+        parent_id: job.parent_id.clone(),
+        progress: job.progress.clone(),
+        priority: job.priority(),
+        attempt: job.attempts,
+        band_position: None,
+        queue_position: None,
+        estimated_wait_seconds: None,
+        metadata: job.request.metadata.clone(),
+        runner: job.runner.clone(),
+    }
+}
+
+/// The job's result, or one carrying just its status and limits while it has none
+fn job_result(job: &ExecutionJob, executor: &CodeExecutor) -> Arc<ExecutionResult> {
+    if let Some(result) = &job.result {
+        return Arc::clone(result);
+    }
+    
+    Arc::new(ExecutionResult {
+        id: job.id.clone(),
+        status: job.status.clone(),
+        stdout: None,
+        stderr: None,
+        compile_output: None,
+        diagnostics: Vec::new(),
+        stdout_bytes: None,
+        stdout_url: None,
+        stderr_bytes: None,
+        stderr_url: None,
+        stdout_encoding: None,
+        stderr_encoding: None,
+        stdout_truncated: false,
+        stderr_truncated: false,
+        exit_code: None,
+        signal: None,
+        exit_description: None,
+        time: None,
+        memory: None,
+        metrics: None,
+        artifacts: None,
+        workdir_listing: None,
+        executed_commands: Vec::new(),
+        failure_reason: None,
+        compile_cache_hit: false,
+        failed_run_indices: Vec::new(),
+        warnings: Vec::new(),
+        attempts: job.attempts,
+        limits: Some(executor.limits(&job.request)),
+        options: Some(ExecutionOptions::from_request(&job.request)),
+        metadata: job.request.metadata.clone(),
+        runner: job.runner.clone(),
+        started_at: job.started_at,
+        created_at: job.created_at,
+        finished_at: job.finished_at,
+        timeline: job.timeline.entries().to_vec(),
+        queue_wait_ms: job.timeline.queue_wait_ms(),
+        overhead_ms: job.timeline.overhead_ms(),
+        phases: job.timeline.phases(),
+        setup_bytes: None,
+        interactor: None,
+        test_cases: Vec::new(),
+        test_summary: None,
+    })
+}
+
+/// Exponential backoff before retry number `attempt` (1-based)
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(RETRY_MAX_DELAY)
+}
+
+/// Write a job to the persistent store, if one is configured
+/// Persistence failures are logged rather than failing the execution
+async fn persist(store: Option<&JobStore>, job: &ExecutionJob) {
+    if let Some(store) = store {
+        if let Err(err) = store.save(job).await {
+            warn!("Failed to persist execution {}: {}", job.id, err);
+        }
+    }
 }
 
 impl Default for EngineStats {
     fn default() -> Self {
         Self {
+            instance: String::new(),
             total_executions: 0,
             active_executions: 0,
+            workers: 0,
             queued_executions: 0,
             completed_executions: 0,
             failed_executions: 0,
             average_execution_time: 0.0,
+            p50_execution_time: 0.0,
+            p95_execution_time: 0.0,
+            p99_execution_time: 0.0,
+            execution_times: LatencyHistogram::new(),
+            average_queue_wait_time: 0.0,
+            queue_wait_samples: 0,
             system_load: 0.0,
             memory_usage: 0,
             uptime_seconds: 0,
+            reclaimed_temp_dirs: 0,
+            compile_cache_hits: 0,
+            compile_cache_misses: 0,
+            compile_cache_hit_rate: 0.0,
+            average_start_latency_ms: 0.0,
+            start_latency_samples: 0,
+            average_overhead_ms: 0.0,
+            overhead_samples: 0,
+            overhead_ms_p50: 0.0,
+            overhead_ms_p95: 0.0,
+            overhead_times: LatencyHistogram::new(),
+            rejected_executions: 0,
+            callbacks_delivered: 0,
+            callbacks_dead_lettered: 0,
+            results_archived: 0,
+            archive_failures: 0,
+            archive_pending: 0,
+            evicted_executions: 0,
+            paused: false,
+            memory_pressure: false,
+            deferred_dequeues: 0,
+            low_disk_space: false,
+            temp_disk: None,
+            windows: StatsWindows::default(),
+            recent: RecentExecutions::new(),
+            by_language: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn python(code: &str) -> ExecutionRequest {
+        ExecutionRequest {
+            language: "python".to_string(),
+            source_code: code.to_string(),
+            ..Default::default()
+        }
+    }
+    
+    #[tokio::test]
+    async fn submissions_beyond_the_queue_depth_are_refused() {
+        let engine = ExecutionEngine::builder().max_concurrent(1).max_queue_depth(2).build().await.unwrap();
+        engine.pause();
+        for _ in 0..2 {
+            engine.submit_execution(python("print(1)"), SubmissionContext::default()).await.unwrap();
+        }
+        
+        let err = engine.submit_execution(python("print(1)"), SubmissionContext::default()).await.unwrap_err();
+        let full = err.downcast_ref::<QueueFull>().expect("queue full");
+        assert_eq!(full.queued, 2);
+        assert_eq!(engine.get_stats().await.unwrap().rejected_executions, 1);
+        
+        // Room again once a job leaves the queue
+        let queued = engine.queued_jobs().await.unwrap();
+        engine.drop_queued(&queued[0].id).await.unwrap();
+        engine.submit_execution(python("print(1)"), SubmissionContext::default()).await.unwrap();
+    }
+    
+    /// Submit one queued job per (client, language) pair
+    async fn submit_from(engine: &ExecutionEngine, jobs: &[(&str, &str)]) -> Vec<String> {
+        let mut ids = Vec::new();
+        for (client, language) in jobs {
+            let request = ExecutionRequest { language: language.to_string(), ..python("print(1)") };
+            let context = SubmissionContext { client: Some(client.to_string()), ..Default::default() };
+            ids.push(engine.submit_execution(request, context).await.unwrap().id);
+        }
+        ids
+    }
+    
+    async fn statuses(engine: &ExecutionEngine, ids: &[String]) -> Vec<ExecutionState> {
+        let mut statuses = Vec::new();
+        for id in ids {
+            statuses.push(engine.get_status(id).await.unwrap().unwrap().status);
+        }
+        statuses
+    }
+    
+    #[tokio::test]
+    async fn bulk_cancel_takes_only_the_matching_jobs_off_the_queue() {
+        let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+        engine.pause();
+        let ids = submit_from(&engine, &[("flood", "python"), ("flood", "python"), ("flood", "cpp"), ("alice", "python")]).await;
+        
+        let filter = CancelFilter {
+            client: Some("flood".to_string()),
+            language: Some("python".to_string()),
+            reason: Some("queue flood".to_string()),
+            ..Default::default()
+        };
+        let response = engine.cancel_matching(&filter).await.unwrap();
+        assert_eq!(response.cancelled, 2);
+        assert_eq!(response.by_status, BTreeMap::from([("queued".to_string(), 2)]));
+        
+        use ExecutionState::{Cancelled, Queued};
+        assert_eq!(statuses(&engine, &ids).await, [Cancelled, Cancelled, Queued, Queued]);
+        let result = engine.get_result(&ids[0]).await.unwrap().unwrap();
+        assert_eq!(result.failure_reason.as_deref(), Some("queue flood"));
+        let queued: Vec<String> = engine.queued_jobs().await.unwrap().into_iter().map(|job| job.id).collect();
+        assert_eq!(queued, ids[2..]);
+    }
+    
+    #[tokio::test]
+    async fn bulk_cancel_honours_status_and_submission_time() {
+        let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+        engine.pause();
+        let older = submit_from(&engine, &[("flood", "python")]).await;
+        let cutoff = Utc::now();
+        let newer = submit_from(&engine, &[("flood", "python")]).await;
+        
+        // Nothing is running while the engine is paused
+        let running_only = CancelFilter { status: Some(vec![CancelStatus::Running]), ..Default::default() };
+        assert_eq!(engine.cancel_matching(&running_only).await.unwrap().cancelled, 0);
+        
+        let before_cutoff = CancelFilter { created_before: Some(cutoff), ..Default::default() };
+        assert_eq!(engine.cancel_matching(&before_cutoff).await.unwrap().cancelled, 1);
+        assert_eq!(statuses(&engine, &older).await, [ExecutionState::Cancelled]);
+        assert_eq!(statuses(&engine, &newer).await, [ExecutionState::Queued]);
+        
+        // Cancelled jobs are finished, so a second sweep passes over them
+        let response = engine.cancel_matching(&CancelFilter::default()).await.unwrap();
+        assert_eq!(response.cancelled, 1);
+    }
+    
+    #[tokio::test]
+    async fn jobs_fail_while_the_temp_volume_is_short_of_space() {
+        use crate::pressure::DiskReader;
+        use std::path::Path;
+        
+        const MB: u64 = 1024 * 1024;
+        struct FakeDisk(AtomicU64);
+        impl DiskReader for FakeDisk {
+            fn space(&self, _path: &Path) -> Option<(u64, u64)> {
+                Some((1024 * MB, self.0.load(Ordering::SeqCst)))
+            }
+        }
+        
+        let disk = Arc::new(FakeDisk(AtomicU64::new(10 * MB))); // below the default 100 MB floor
+        let engine = ExecutionEngine::builder().max_concurrent(1).disk_reader(disk.clone()).build().await.unwrap();
+        let run = || async {
+            let id = engine.submit_execution(python("print(1)"), SubmissionContext::default()).await.unwrap().id;
+            engine.wait_for_completion(&id, Duration::from_secs(30)).await.unwrap();
+            engine.get_result(&id).await.unwrap().unwrap()
+        };
+        
+        let result = run().await;
+        assert_eq!(result.status, ExecutionState::InternalError);
+        assert!(result.failure_reason.as_deref().unwrap().starts_with("insufficient disk space: 10 MB free"));
+        let stats = engine.get_stats().await.unwrap();
+        assert!(stats.low_disk_space);
+        assert_eq!(stats.temp_disk.unwrap().used_bytes, 1014 * MB);
+        
+        disk.0.store(500 * MB, Ordering::SeqCst);
+        if std::process::Command::new("python3").arg("--version").output().is_err() {
+            eprintln!("skipped running a job after recovery: needs python3");
+            return;
+        }
+        assert_eq!(run().await.status, ExecutionState::Completed);
+        assert!(!engine.low_disk_space());
+    }
+    
+    #[tokio::test]
+    async fn listed_jobs_are_filtered_by_exact_metadata() {
+        let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+        engine.pause(); // nothing needs to run to be listed
+        for (user, submission) in [("42", "s-1"), ("7", "s-2"), ("42", "s-3")] {
+            let metadata = HashMap::from([("user_id".to_string(), user.to_string()), ("submission_id".to_string(), submission.to_string())]);
+            let request = ExecutionRequest { metadata: Some(metadata), ..python("print(1)") };
+            engine.submit_execution(request, SubmissionContext::default()).await.unwrap();
+        }
+        
+        let mut query = ExecutionListQuery::default();
+        query.metadata.insert("user_id".to_string(), "42".to_string());
+        let listed = engine.list_executions(&query).await;
+        let submissions: Vec<&str> = listed.iter()
+            .map(|status| status.metadata.as_ref().unwrap()["submission_id"].as_str())
+            .collect();
+        assert_eq!(submissions, ["s-3", "s-1"]);
+        
+        query.metadata.insert("user_id".to_string(), "4".to_string()); // no prefix matching
+        assert!(engine.list_executions(&query).await.is_empty());
+        query.metadata.clear();
+        query.limit = 2;
+        assert_eq!(engine.list_executions(&query).await.len(), 2);
+    }
+    
+    #[test]
+    fn workers_take_the_lowest_free_slot() {
+        let slots = WorkerSlots::new(Some("engine-eu-1"));
+        assert_eq!(slots.instance, "engine-eu-1");
+        assert_eq!((slots.take(), slots.take(), slots.take()), (1, 2, 3));
+        slots.release(2);
+        assert_eq!(slots.take(), 2);
+        assert_eq!(slots.take(), 4);
+        
+        assert!(!WorkerSlots::new(None).instance.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn settings_changes_apply_to_new_submissions_and_are_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let config = EngineConfig { runtime_settings_path: Some(path.clone()), ..EngineConfig::default() };
+        let engine = ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap();
+        
+        let changes: RuntimeSettings = serde_json::from_value(serde_json::json!({ "disabled_languages": ["cpp"] })).unwrap();
+        let settings = engine.update_settings(changes, "key:admin", None).await.unwrap();
+        assert_eq!(settings.disabled_languages.unwrap(), ["cpp"]);
+        assert_eq!(engine.config().disabled_languages, ["cpp"]);
+        assert_eq!(RuntimeSettings::read(&path).unwrap().unwrap().disabled_languages.unwrap(), ["cpp"]);
+        
+        // New C++ submissions are refused, as the server does with 422
+        let request = ExecutionRequest { language: "cpp".to_string(), source_code: "int main() {}".to_string(), ..Default::default() };
+        let violations = engine.validator().validate(&request, engine.support(&request));
+        assert_eq!(violations[0].field, "language");
+        assert_eq!(violations[0].constraint, "language 'cpp' is disabled on this engine");
+    }
+    
+    #[tokio::test]
+    async fn settings_the_startup_checks_refuse_change_nothing() {
+        let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+        for changes in [
+            serde_json::json!({ "disabled_languages": ["cobol"] }),
+            serde_json::json!({ "ceilings": { "max_cpu_time": 0.5 } }),
+        ] {
+            let changes: RuntimeSettings = serde_json::from_value(changes).unwrap();
+            let error = engine.update_settings(changes, "key:admin", None).await.unwrap_err();
+            assert!(error.is::<InvalidSettings>(), "{:#}", error);
         }
+        assert!(engine.config().disabled_languages.is_empty());
+        assert_eq!(engine.validator().max_cpu_time, EngineConfig::default().limits.ceilings.max_cpu_time);
+    }
+    
+    #[tokio::test]
+    async fn stats_report_percentiles_until_reset() {
+        let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+        {
+            let mut stats = engine.stats.write().await;
+            for ms in (1..=100).map(|i| i * 10) {
+                stats.execution_times.record(ms);
+            }
+            stats.completed_executions = 100;
+        }
+        let stats = engine.get_stats().await.unwrap();
+        // Seconds, each within its bucket's error above the exact percentile
+        for (reported, exact) in [(stats.p50_execution_time, 0.5), (stats.p95_execution_time, 0.95), (stats.p99_execution_time, 0.99)] {
+            assert!(reported >= exact && reported <= exact * 1.07, "{} for {}", reported, exact);
+        }
+        
+        // Counters survive a reset; timings start over
+        engine.reset_stats().await;
+        let stats = engine.get_stats().await.unwrap();
+        assert_eq!(stats.completed_executions, 100);
+        assert_eq!((stats.average_execution_time, stats.p50_execution_time, stats.p99_execution_time), (0.0, 0.0, 0.0));
+    }
+    
+    #[tokio::test]
+    async fn draining_refuses_new_work_and_leaves_the_queue_alone() {
+        let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+        engine.pause();
+        let queued = engine.submit_execution(python("print(1)"), SubmissionContext::default()).await.unwrap().id;
+        
+        engine.shutdown(Duration::from_millis(10)).await;
+        assert!(engine.is_draining());
+        let refused = engine.submit_execution(python("print(2)"), SubmissionContext::default()).await.unwrap_err();
+        assert!(refused.is::<ShuttingDown>());
+        // Queued jobs wait for the next start instead of being cancelled
+        assert_eq!(engine.get_status(&queued).await.unwrap().unwrap().status, ExecutionState::Queued);
+    }
+    
+    #[tokio::test]
+    async fn paused_engines_queue_or_refuse_submissions_as_configured() {
+        let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+        engine.pause();
+        let id = engine.submit_execution(python("print(1)"), SubmissionContext::default()).await.unwrap().id;
+        assert!(engine.get_stats().await.unwrap().paused);
+        assert_eq!(engine.lookup(&id).await.unwrap().unwrap().status, ExecutionState::Queued);
+        
+        let config = EngineConfig { paused_submissions: PausedSubmissions::Reject, ..Default::default() };
+        let engine = ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap();
+        engine.pause();
+        let err = engine.submit_execution(python("print(1)"), SubmissionContext::default()).await.unwrap_err();
+        assert!(err.is::<Paused>(), "{}", err);
+        
+        engine.resume();
+        assert!(!engine.get_stats().await.unwrap().paused);
+        engine.submit_execution(python("print(1)"), SubmissionContext::default()).await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn cancelled_scheduled_jobs_leave_the_queue() {
+        let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+        let run_at = Utc::now() + chrono::Duration::hours(1);
+        let request = ExecutionRequest { run_at: Some(run_at), ..python("print(1)") };
+        let id = engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id;
+        
+        let status = engine.get_status(&id).await.unwrap().unwrap();
+        assert_eq!((status.status, status.run_at), (ExecutionState::Scheduled, Some(run_at)));
+        assert_eq!(engine.get_stats().await.unwrap().queued_executions, 1);
+        
+        assert!(engine.cancel_execution(&id).await.unwrap());
+        assert_eq!(engine.lookup(&id).await.unwrap().unwrap().status, ExecutionState::Cancelled);
+        assert_eq!(engine.get_stats().await.unwrap().queued_executions, 0);
+        assert!(!engine.queue.remove(&id).await.unwrap(), "still in the delayed set");
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn queued_gauge_follows_concurrent_submissions_and_cancellations() {
+        let engine = Arc::new(ExecutionEngine::builder().max_concurrent(1).build().await.unwrap());
+        engine.pause();
+        let done = Arc::new(AtomicBool::new(false));
+        let poller = {
+            let (engine, done) = (Arc::clone(&engine), Arc::clone(&done));
+            tokio::spawn(async move {
+                while !done.load(Ordering::SeqCst) {
+                    let stats = engine.get_stats().await.unwrap();
+                    assert!(stats.queued_executions <= stats.total_executions, "{} queued of {}", stats.queued_executions, stats.total_executions);
+                    assert_eq!(stats.active_executions, 0, "nothing runs while paused");
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        
+        // Each task cancels every third of its jobs and drops every fifth from the queue
+        let tasks: Vec<_> = (0..8).map(|_| {
+            let engine = Arc::clone(&engine);
+            tokio::spawn(async move {
+                let mut removed = 0;
+                for n in 0..25 {
+                    let id = engine.submit_execution(python("print(1)"), SubmissionContext::default()).await.unwrap().id;
+                    if n % 3 == 0 {
+                        assert!(engine.cancel_execution(&id).await.unwrap());
+                        removed += 1;
+                    } else if n % 5 == 0 {
+                        assert!(engine.drop_queued(&id).await.unwrap());
+                        removed += 1;
+                    }
+                }
+                removed
+            })
+        }).collect();
+        let mut removed = 0;
+        for task in tasks {
+            removed += task.await.unwrap();
+        }
+        done.store(true, Ordering::SeqCst);
+        poller.await.unwrap();
+        
+        let stats = engine.get_stats().await.unwrap();
+        assert_eq!(stats.total_executions, 200);
+        assert_eq!(stats.queued_executions, 200 - removed);
+        assert_eq!(stats.queued_executions as usize, engine.queued_jobs().await.unwrap().len());
+    }
+    
+    #[tokio::test]
+    async fn identical_dedupe_submissions_share_a_job_per_client() {
+        let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+        engine.pause();
+        let submit = |client: &str, dedupe: bool| {
+            let request = ExecutionRequest { dedupe: Some(dedupe), ..python("print(1)") };
+            let context = SubmissionContext { client: Some(client.to_string()), ..Default::default() };
+            engine.submit_execution(request, context)
+        };
+        
+        let first = submit("key:a", true).await.unwrap();
+        let again = submit("key:a", true).await.unwrap();
+        assert_eq!((again.id.as_str(), again.status.as_str()), (first.id.as_str(), "deduplicated"));
+        assert_ne!(submit("key:b", true).await.unwrap().id, first.id, "never across clients");
+        assert_ne!(submit("key:a", false).await.unwrap().id, first.id, "only when asked for");
+        
+        // A finished job answers within the window, a cancelled one never does
+        {
+            let mut jobs = engine.jobs.shard(&first.id).write().await;
+            let job = jobs.get_mut(&first.id).unwrap();
+            job.status = ExecutionState::Completed;
+            job.finished_at = Some(Utc::now());
+        }
+        assert_eq!(submit("key:a", true).await.unwrap().id, first.id);
+        engine.jobs.shard(&first.id).write().await.get_mut(&first.id).unwrap().status = ExecutionState::Cancelled;
+        let fresh = submit("key:a", true).await.unwrap();
+        assert_ne!(fresh.id, first.id);
+        assert_eq!(fresh.status, "queued");
+    }
+    
+    #[tokio::test]
+    async fn cancelled_jobs_record_submission_then_cancellation() {
+        let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+        engine.pause();
+        let context = SubmissionContext { request_id: Some("req-7".to_string()), ..Default::default() };
+        let id = engine.submit_execution(python("print(1)"), context).await.unwrap().id;
+        assert!(engine.cancel_execution(&id).await.unwrap());
+        
+        let events = engine.events(&id).await.unwrap().unwrap();
+        let kinds: Vec<AuditEventKind> = events.iter().map(|event| event.event).collect();
+        assert_eq!(kinds, [AuditEventKind::Submitted, AuditEventKind::Cancelled]);
+        assert!(events.iter().all(|event| event.request_id.as_deref() == Some("req-7") && event.language == "python"));
+        assert!(events[0].at <= events[1].at);
+        assert!(engine.events("unknown").await.unwrap().is_none());
+    }
+    
+    /// A job a worker had started when the engine died
+    fn interrupted(id: &str, max_retries: u32, callback_url: Option<&str>) -> ExecutionJob {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "request": {"id": id, "language": "python", "source_code": "print('again')", "max_retries": max_retries, "callback_url": callback_url},
+            "status": "running",
+            "created_at": Utc::now(),
+            "started_at": Utc::now(),
+            "finished_at": null,
+            "result": null,
+            "attempts": 1,
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn jobs_running_at_a_crash_are_retried_or_failed_on_startup() {
+        use axum::routing::post;
+        
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = EngineConfig::default();
+        config.retention.job_store_path = Some(dir.path().join("jobs.db"));
+        config.sandbox.temp_base = dir.path().join("work");
+        
+        // Callback receiver for the job that is given up on
+        let (sender, mut callbacks) = tokio::sync::mpsc::unbounded_channel();
+        let receiver = axum::Router::new().route("/hook", post(move |body: String| async move {
+            let _ = sender.send(body);
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, receiver).await });
+        
+        // What the crashed engine left behind: the jobs in the store and one job's working directory
+        let store = JobStore::open(&dir.path().join("jobs.db")).await.unwrap();
+        store.save(&interrupted("out-of-retries", 0, Some(&url))).await.unwrap();
+        store.save(&interrupted("has-a-retry", 1, None)).await.unwrap();
+        drop(store);
+        let leftover = config.sandbox.temp_base.join(format!("{}.x1y2z3", sandbox::job_tag("out-of-retries")));
+        std::fs::create_dir_all(&leftover).unwrap();
+        std::fs::write(leftover.join("main.py"), "print(1)").unwrap();
+        
+        let engine = ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap();
+        
+        let failed = engine.lookup("out-of-retries").await.unwrap().unwrap();
+        assert_eq!(failed.status, ExecutionState::InternalError);
+        assert_eq!(failed.result.unwrap().failure_reason.as_deref(), Some(INTERRUPTED_MESSAGE));
+        assert!(!leftover.exists());
+        let callback = tokio::time::timeout(Duration::from_secs(10), callbacks.recv()).await.unwrap().unwrap();
+        assert!(callback.contains("out-of-retries") && callback.contains("internal_error"), "{}", callback);
+        
+        let retried = engine.lookup("has-a-retry").await.unwrap().unwrap();
+        assert_ne!(retried.status, ExecutionState::InternalError);
+        if std::process::Command::new("python3").arg("--version").output().is_ok() {
+            engine.wait_for_completion("has-a-retry", Duration::from_secs(60)).await.unwrap();
+            let retried = engine.lookup("has-a-retry").await.unwrap().unwrap();
+            assert_eq!(retried.status, ExecutionState::Completed);
+            assert_eq!(retried.attempts, 2);
+        }
+    }
+    
+    #[tokio::test]
+    async fn limits_come_from_the_request_then_the_language_then_the_server() {
+        use crate::config::LanguageLimits;
+        
+        const MB: u64 = 1024 * 1024;
+        let mut config = EngineConfig::default();
+        config.limits.languages.insert("python".to_string(), LanguageLimits {
+            defaults: LimitOverrides { cpu_time: Some(1.5), memory: Some(64 * MB), ..Default::default() },
+            // A language maximum above the server's ceiling does not raise it
+            max: LimitOverrides { cpu_time: Some(2.0), memory: Some(1024 * 1024 * MB), ..Default::default() },
+            ..Default::default()
+        });
+        let engine = ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap();
+        let global = ResourceLimits::default();
+        
+        let limits = engine.limits(&python("print(1)"));
+        assert_eq!((limits.cpu_time, limits.memory, limits.wall_time), (1.5, 64 * MB, global.wall_time));
+        let limits = engine.limits(&ExecutionRequest { memory_limit: Some(32 * MB), ..python("print(1)") });
+        assert_eq!((limits.cpu_time, limits.memory), (1.5, 32 * MB));
+        
+        // The JVM's built-in default, and a language with none of its own
+        let java = ExecutionRequest { language: "java".to_string(), ..python("") };
+        assert_eq!(engine.limits(&java).memory, 512 * MB);
+        let c = ExecutionRequest { language: "c".to_string(), ..python("") };
+        assert_eq!(engine.limits(&c).memory, global.memory);
+        
+        let (_, ceilings) = engine.language_limits(71).unwrap(); // Python's Judge0 id
+        assert_eq!(ceilings.cpu_time, 2.0);
+        assert_eq!(ceilings.memory, RequestValidator::default().max_memory);
+    }
+    
+    #[tokio::test]
+    async fn language_defaults_never_exceed_the_ceilings() {
+        const MB: u64 = 1024 * 1024;
+        let mut config = EngineConfig::default();
+        config.limits.ceilings.max_memory = 256 * MB;
+        let engine = ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap();
+        let java = ExecutionRequest { language: "java".to_string(), ..python("") };
+        assert_eq!(engine.limits(&java).memory, 256 * MB);
+        
+        // Limits for a language the engine does not have are a configuration mistake
+        let mut config = EngineConfig::default();
+        config.limits.languages.insert("cobol".to_string(), Default::default());
+        let err = ExecutionEngine::builder().config(config).build().await.err().unwrap();
+        assert!(err.to_string().contains("limits.languages.cobol: unknown language"), "{}", err);
     }
 }
@@ -1,28 +1,159 @@
-use crate::sandbox::Sandbox;
+use crate::compile_cache::{CompileCache, Toolchain};
+use crate::config::{EngineConfig, LanguageLimits};
+use crate::diagnostics;
+use crate::docker::{self, ContainerPool, CONTAINER_WORKDIR};
+use crate::fetch::Fetcher;
+use crate::isolate::{self, BoxLease, BoxPool};
+#[cfg(unix)]
+use crate::isolation::MountIsolation;
+use crate::output;
+use crate::pressure::{DiskGuard, DiskReader, SystemDisk};
+use crate::sandbox::{self, kill_process, merge_stderr_into_stdout, signal_name, termination_signal, wait_with_metrics, CpuPool, Exited, Sandbox, SegvFault, Truncated};
+use crate::spill::OutputSpill;
 use crate::types::*;
+use crate::validation::{RequestValidator, Support};
+use crate::watchdog::{Watch, Watchdog};
+use crate::workdir::{self, WorkdirStore};
 use anyhow::{anyhow, Result};
 use chrono::Utc;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::time::{Duration, Instant};
-use tempfile::TempDir;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use tempfile::{NamedTempFile, TempDir};
+use tokio::sync::mpsc;
 use tokio::time::timeout;
 use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+
+
+/// How often the working directory size is sampled during a run
+const QUOTA_SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long to wait for output after killing a process
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// strerror(EFBIG), printed when a write goes past RLIMIT_FSIZE
+const FILE_TOO_LARGE_MESSAGE: &str = "File too large";
+
+/// How far from the stack limit a SIGSEGV may fault and still be the stack running out:
+/// the kernel's stack guard gap, which also leaves room for a large frame
+const STACK_FAULT_SLACK: u64 = 1024 * 1024;
+
+/// Maximum number of artifacts listed in a result
+const MAX_ARTIFACTS: usize = 100;
+
+/// Most files a result's workdir_listing names
+const MAX_WORKDIR_ENTRIES: usize = 100;
+
+/// Locale every program runs with
+const CHILD_LANG: &str = "C.UTF-8";
+
+/// PATH of native programs when the engine itself has none
+const DEFAULT_CHILD_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// Receives phase changes of a running execution
+pub type ProgressSender = mpsc::UnboundedSender<ExecutionProgress>;
+
+/// Where programs are run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionBackend {
+    /// Child processes on this host, limited by rlimits
+    #[default]
+    #[serde(alias = "native")]
+    Process,
+    /// Pooled containers for languages that have an image, processes for the rest
+    Docker,
+    /// Boxes of the isolate sandbox, which meters time and memory itself
+    Isolate,
+}
+
+impl fmt::Display for ExecutionBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Process => "process",
+            Self::Docker => "docker",
+            Self::Isolate => "isolate",
+        })
+    }
+}
+
+impl FromStr for ExecutionBackend {
+    type Err = String;
+    
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "process" | "native" => Ok(Self::Process),
+            "docker" => Ok(Self::Docker),
+            "isolate" => Ok(Self::Isolate),
+            other => Err(format!("'{}' is not process, docker or isolate", other)),
+        }
+    }
+}
+
+/// Ids of the languages with these keys, or an error naming the first unknown one
+fn language_ids(languages: &HashMap<String, LanguageConfig>, keys: &[String]) -> Result<HashSet<u32>> {
+    keys.iter()
+        .map(|key| {
+            languages.get(key)
+                .map(|config| config.id)
+                .ok_or_else(|| anyhow!("disabled_languages: unknown language '{}'", key))
+        })
+        .collect()
+}
 
 /// Code executor that handles different programming languages
+#[derive(Clone)]
 pub struct CodeExecutor {
     languages: HashMap<String, LanguageConfig>,
+    aliases: HashMap<String, String>, // other accepted names, normalized, and the key they stand for
+    out_of_service: Arc<Mutex<HashSet<u32>>>, // ids of languages whose warm-up failed
+    disabled: Arc<Mutex<HashSet<u32>>>, // ids of languages an operator turned off
     temp_base: PathBuf,
+    temp_max_age: Duration,
+    active_dirs: Arc<Mutex<HashSet<PathBuf>>>,
+    running: Arc<Mutex<HashMap<u32, String>>>, // pids of processes that haven't exited yet, and their job ids
+    executing: Arc<Mutex<HashMap<String, bool>>>, // ids of jobs being executed, true once cancelled
+    artifact_max_bytes: u64,
+    output_max_bytes: Arc<AtomicUsize>, // changed at runtime through PUT /admin/config
+    default_limits: ResourceLimits,
+    ceilings: Arc<RwLock<RequestValidator>>,
+    compile_cache: Option<CompileCache>,
+    container_pool: Option<ContainerPool>,
+    box_pool: Option<BoxPool>,
+    child_path: std::ffi::OsString, // the only part of the engine's environment native programs see
+    cpu_pool: CpuPool,
+    #[cfg(unix)]
+    isolation: Option<MountIsolation>, // None when disabled or the host allows no mount namespaces
+    fetcher: Fetcher,
+    workdirs: WorkdirStore,
+    spill: OutputSpill,
+    disk: Arc<DiskGuard>,
+    watchdog: Watchdog, // kills programs the wait below failed to stop
 }
 
 impl CodeExecutor {
-    /// Create a new code executor
-    pub fn new() -> Result<Self> {
-        let temp_base = std::env::temp_dir().join("labforcode-rust");
+    /// Create a new code executor from the sandbox settings and default limits
+    pub fn new(workdirs: WorkdirStore, config: &EngineConfig) -> Result<Self> {
+        let sandbox = &config.sandbox;
+        
+        // Operators can point this at a dedicated volume
+        let temp_base = sandbox.temp_base.clone();
         fs::create_dir_all(&temp_base)?;
+        let temp_max_age = Duration::from_secs(sandbox.temp_max_age_secs);
+        let disk = Arc::new(DiskGuard::new(temp_base.clone(), sandbox.temp_min_free_bytes, Arc::new(SystemDisk)));
+        
+        // Created before the isolation probe, which hides it from jobs
+        let retention = &config.retention;
+        let spill = OutputSpill::new(retention.spill_dir.clone(), retention.output_spill_bytes)?;
         
         let mut languages = HashMap::new();
         
@@ -33,7 +164,13 @@ impl CodeExecutor {
             source_file: "main.py".to_string(),
             compile_cmd: None,
             run_cmd: vec!["python3".to_string(), "main.py".to_string()],
+            source_extension: None,
+            supports_cargo: false,
             docker_image: Some("python:3.11-alpine".to_string()),
+            limits: LanguageLimits::default(),
+            aliases: vec!["py".to_string(), "py3".to_string(), "python3".to_string(), "cpython".to_string()],
+            stack_overflow_messages: &["RecursionError: maximum recursion depth exceeded", "Fatal Python error: Cannot recover from stack overflow"],
+            ignores_sigxfsz: true,
         });
         
         // JavaScript (Node.js)
@@ -43,7 +180,13 @@ impl CodeExecutor {
             source_file: "main.js".to_string(),
             compile_cmd: None,
             run_cmd: vec!["node".to_string(), "main.js".to_string()],
+            source_extension: None,
+            supports_cargo: false,
             docker_image: Some("node:18-alpine".to_string()),
+            limits: LanguageLimits::default(),
+            aliases: vec!["js".to_string(), "node".to_string(), "nodejs".to_string(), "node.js".to_string()],
+            stack_overflow_messages: &["RangeError: Maximum call stack size exceeded"],
+            ignores_sigxfsz: false,
         });
         
         // C++
@@ -53,7 +196,13 @@ impl CodeExecutor {
             source_file: "main.cpp".to_string(),
             compile_cmd: Some(vec!["g++".to_string(), "-o".to_string(), "main".to_string(), "main.cpp".to_string(), "-std=c++17".to_string()]),
             run_cmd: vec!["./main".to_string()],
+            source_extension: Some("cpp".to_string()),
+            supports_cargo: false,
             docker_image: Some("gcc:latest".to_string()),
+            limits: LanguageLimits::default(),
+            aliases: vec!["c++".to_string(), "cplusplus".to_string(), "cxx".to_string(), "g++".to_string()],
+            stack_overflow_messages: &[],
+            ignores_sigxfsz: false,
         });
         
         // C
@@ -63,7 +212,13 @@ impl CodeExecutor {
             source_file: "main.c".to_string(),
             compile_cmd: Some(vec!["gcc".to_string(), "-o".to_string(), "main".to_string(), "main.c".to_string(), "-std=c17".to_string(), "-lm".to_string()]),
             run_cmd: vec!["./main".to_string()],
+            source_extension: Some("c".to_string()),
+            supports_cargo: false,
             docker_image: Some("gcc:latest".to_string()),
+            limits: LanguageLimits::default(),
+            aliases: Vec::new(),
+            stack_overflow_messages: &[],
+            ignores_sigxfsz: false,
         });
         
         // Java
@@ -73,7 +228,17 @@ impl CodeExecutor {
             source_file: "Main.java".to_string(),
             compile_cmd: Some(vec!["javac".to_string(), "Main.java".to_string()]),
             run_cmd: vec!["java".to_string(), "Main".to_string()],
+            source_extension: Some("java".to_string()),
+            supports_cargo: false,
             docker_image: Some("openjdk:17-alpine".to_string()),
+            // The JVM reserves its heap up front and cannot start in the global default
+            limits: LanguageLimits {
+                defaults: LimitOverrides { memory: Some(512 * 1024 * 1024), ..Default::default() },
+                ..Default::default()
+            },
+            aliases: vec!["jdk".to_string(), "openjdk".to_string()],
+            stack_overflow_messages: &["Exception in thread \"main\" java.lang.StackOverflowError"],
+            ignores_sigxfsz: true,
         });
         
         // Go
@@ -83,7 +248,13 @@ impl CodeExecutor {
             source_file: "main.go".to_string(),
             compile_cmd: None,
             run_cmd: vec!["go".to_string(), "run".to_string(), "main.go".to_string()],
+            source_extension: None,
+            supports_cargo: false,
             docker_image: Some("golang:1.21-alpine".to_string()),
+            limits: LanguageLimits::default(),
+            aliases: vec!["golang".to_string()],
+            stack_overflow_messages: &["runtime: goroutine stack exceeds"],
+            ignores_sigxfsz: true,
         });
         
         // Rust
@@ -93,48 +264,267 @@ impl CodeExecutor {
             source_file: "main.rs".to_string(),
             compile_cmd: Some(vec!["rustc".to_string(), "main.rs".to_string(), "-o".to_string(), "main".to_string()]),
             run_cmd: vec!["./main".to_string()],
+            source_extension: None,
+            supports_cargo: true,
             docker_image: Some("rust:1.70-alpine".to_string()),
+            limits: LanguageLimits::default(),
+            aliases: vec!["rs".to_string(), "rustc".to_string()],
+            stack_overflow_messages: &["fatal runtime error: stack overflow"],
+            ignores_sigxfsz: false,
         });
         
-        Ok(Self {
+        // Operators' per-language limits go over the built-in ones
+        for (key, overrides) in &config.limits.languages {
+            let language = languages.get_mut(key)
+                .ok_or_else(|| anyhow!("limits.languages.{}: unknown language", key))?;
+            language.limits = LanguageLimits {
+                defaults: overrides.defaults.or(&language.limits.defaults),
+                max: overrides.max.or(&language.limits.max),
+                max_source_bytes: overrides.max_source_bytes.or(language.limits.max_source_bytes),
+            };
+        }
+        
+        // Operators' aliases are added to the built-in ones; no name may point at two languages
+        for (key, extra) in &config.language_aliases {
+            let language = languages.get_mut(key)
+                .ok_or_else(|| anyhow!("language_aliases.{}: unknown language", key))?;
+            language.aliases.extend(extra.iter().map(|alias| normalize_language(alias)));
+        }
+        let mut aliases = HashMap::new();
+        for (key, language) in &languages {
+            for alias in &language.aliases {
+                if languages.contains_key(alias) && alias != key {
+                    return Err(anyhow!("language alias '{}' of {} is another language's key", alias, key));
+                }
+                if let Some(other) = aliases.insert(alias.clone(), key.clone()).filter(|other| other != key) {
+                    return Err(anyhow!("language alias '{}' is given to both {} and {}", alias, other, key));
+                }
+            }
+        }
+        
+        let disabled = language_ids(&languages, &config.disabled_languages)?;
+        
+        // Compiled artifacts are cached only when a cache directory is configured
+        let compile_cache = match &sandbox.compile_cache_dir {
+            Some(dir) => Some(CompileCache::new(dir.clone(), sandbox.compile_cache_max_bytes)?),
+            None => None,
+        };
+        
+        // The docker backend runs languages with an image inside pooled containers
+        let container_pool = match sandbox.backend {
+            ExecutionBackend::Docker => {
+                let images = languages.iter()
+                    .filter_map(|(key, config)| Some((key.clone(), config.docker_image.clone()?)))
+                    .collect();
+                Some(ContainerPool::from_config(&sandbox.docker, &images))
+            }
+            ExecutionBackend::Process | ExecutionBackend::Isolate => None,
+        };
+        let box_pool = match sandbox.backend {
+            ExecutionBackend::Isolate => Some(BoxPool::from_config(&sandbox.isolate)?),
+            _ => None,
+        };
+        
+        let executor = Self {
             languages,
+            aliases,
+            out_of_service: Arc::new(Mutex::new(HashSet::new())),
+            disabled: Arc::new(Mutex::new(disabled)),
             temp_base,
-        })
+            temp_max_age,
+            active_dirs: Arc::new(Mutex::new(HashSet::new())),
+            running: Arc::new(Mutex::new(HashMap::new())),
+            executing: Arc::new(Mutex::new(HashMap::new())),
+            watchdog: Watchdog::start(KILL_GRACE_PERIOD)?,
+            artifact_max_bytes: sandbox.artifact_max_bytes,
+            output_max_bytes: Arc::new(AtomicUsize::new(sandbox.output_max_bytes)),
+            default_limits: config.limits.defaults.clone(),
+            ceilings: Arc::new(RwLock::new(config.limits.ceilings.clone())),
+            compile_cache,
+            container_pool,
+            box_pool,
+            child_path: std::env::var_os("PATH").unwrap_or_else(|| DEFAULT_CHILD_PATH.into()),
+            cpu_pool: CpuPool::new(&sandbox.cpu_pool),
+            #[cfg(unix)]
+            isolation: MountIsolation::probe(sandbox, &config.retention),
+            fetcher: Fetcher::new(&config.fetch, config.limits.ceilings.max_additional_files_bytes)?,
+            workdirs,
+            spill,
+            disk,
+        };
+        
+        // Reclaim directories leaked by a previous crash
+        let reclaimed = executor.sweep_stale_dirs();
+        if reclaimed > 0 {
+            info!("🧹 Reclaimed {} stale temp directories on startup", reclaimed);
+        }
+        
+        Ok(executor)
+    }
+    
+    /// Delete temp directories older than the max age that no active job is using
+    pub fn sweep_stale_dirs(&self) -> usize {
+        let entries = match fs::read_dir(&self.temp_base) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!("Could not scan {}: {}", self.temp_base.display(), err);
+                return 0;
+            }
+        };
+        
+        let now = SystemTime::now();
+        let mut reclaimed = 0;
+        
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if self.active_dirs.lock().unwrap().contains(&path) {
+                continue;
+            }
+            
+            let age = entry.metadata()
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok());
+            let stale = age.is_some_and(|age| age >= self.temp_max_age);
+            if !stale {
+                continue;
+            }
+            
+            let removed = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+            match removed {
+                Ok(()) => {
+                    debug!("Removed stale temp entry {}", path.display());
+                    reclaimed += 1;
+                }
+                Err(err) => warn!("Failed to remove stale temp entry {}: {}", path.display(), err),
+            }
+        }
+        
+        reclaimed
     }
     
     /// Execute code with advanced resource limits and options
-    pub async fn execute(&self, request: &ExecutionRequest) -> Result<ExecutionResult> {
+    /// Phase changes are sent on `progress` as they happen; the channel closes when execution ends.
+    /// Sandbox setup, compilation and each run are stamped on `timeline`.
+    pub async fn execute(&self, request: &ExecutionRequest, progress: ProgressSender, timeline: &mut Timeline) -> Result<ExecutionResult> {
         let start_time = Instant::now();
         let created_at = Utc::now();
         
         info!("🚀 Executing {} code for {}", request.language, request.id);
+        let _executing = ExecutingGuard::new(&self.executing, &request.id);
+        
+        // Inputs given by URL are downloaded first, on their own timeout rather than the job's limits
+        let fetched;
+        let request = if Fetcher::needed(request) {
+            match self.fetcher.resolve(request, self.max_source_bytes(self.language_config(request))).await {
+                Ok(resolved) => {
+                    fetched = resolved;
+                    &fetched
+                }
+                Err(err) => {
+                    warn!("Could not download the inputs of {}: {}", request.id, err);
+                    return Ok(failed_before_start(request, err.to_string(), created_at));
+                }
+            }
+        } else {
+            request
+        };
+        
+        // Better to fail now than to hit ENOSPC halfway through a compile
+        if let Err(message) = self.disk.check() {
+            warn!("Not starting {}: {}", request.id, message);
+            return Ok(failed_before_start(request, message, created_at));
+        }
+        // Jobs queued before their language was disabled don't start either
+        if self.language_config(request).is_some_and(|config| self.disabled.lock().unwrap().contains(&config.id)) {
+            let message = format!("language '{}' was disabled by an administrator", request.language);
+            warn!("Not starting {}: {}", request.id, message);
+            return Ok(failed_before_start(request, message, created_at));
+        }
         
         // Get language config
-        let lang_config = self.languages.get(&request.language.to_lowercase())
-            .or_else(|| self.languages.get(&request.language_id.unwrap_or(0).to_string()))
+        let lang_config = self.language_config(request)
             .ok_or_else(|| anyhow!("Unsupported language: {}", request.language))?;
         
+        // Submission checks refuse these too, but a stored job may outlive a backend change
+        if request.interactor.is_some() && !self.support(request).interactive {
+            return Err(anyhow!("interactive runs need both programs on the native backend"));
+        }
+        
+        // Pick the entry file (explicit override, detected Java class, or the default)
+        let entry_file = self.resolve_entry_file(request, lang_config)?;
+        let lang_config = &lang_config.with_entry_file(&entry_file);
+        
         // Create resource limits from request
-        let limits = ResourceLimits::from_request(request);
+        let mut limits = ResourceLimits::from_request(request, &self.language_defaults(Some(lang_config)));
+        
+        // Pinned jobs keep their cores for every run; the limits record the cores actually used, none in isolate boxes
+        let cores = limits.cpu_affinity.as_ref()
+            .filter(|_| self.box_pool.is_none())
+            .map(|affinity| self.cpu_pool.assign(affinity));
+        limits.cpu_affinity = cores.as_ref()
+            .filter(|lease| !lease.cores().is_empty())
+            .map(|lease| CpuAffinity::Cores(lease.cores().to_vec()));
         let options = ExecutionOptions::from_request(request);
         
-        // Determine if we should run multiple times
-        let num_runs = options.number_of_runs.max(1);
+        // Test groups build once and run each case instead of repeating the program
+        if request.test_cases.is_some() {
+            let mut result = self.execute_test_group(request, lang_config, &limits, &options, &progress, timeline).await?;
+            result.created_at = created_at;
+            if self.is_cancelled(&request.id) {
+                result.status = ExecutionState::Cancelled;
+            }
+            info!("✅ Test group completed in {}ms", start_time.elapsed().as_millis());
+            return Ok(result);
+        }
+        
+        // Determine if we should run multiple times; embedders skip validation, so the ceiling is applied here too
+        let num_runs = options.number_of_runs.min(self.ceilings.read().unwrap().max_runs).max(1);
+        let base64_encoded = request.base64_encoded.unwrap_or(false);
+        
+        // Runs share one build; unless the request opts out, each starts from its own copy of the built directory
+        let mut build = match self.build(request, lang_config, &limits, &options, &progress, &mut *timeline).await? {
+            Ok(build) => build,
+            Err(failed) => return Ok(Self::aggregate_results(&request.id, vec![*failed], created_at, base64_encoded)),
+        };
+        let fresh = options.fresh_workdir_per_run && num_runs > 1;
+        if fresh {
+            // Every copy claims its own sandbox, so the build's goes back to the pool
+            build.lease = None;
+            build.sandbox_box = None;
+        }
+        let mut last_copy = None;
         let mut results = Vec::new();
         
         for run_index in 0..num_runs {
             debug!("Executing run {} of {}", run_index + 1, num_runs);
             
+            let copy = match fresh {
+                true => Some(self.copy_build(request, lang_config, &limits, &options, &build).await?),
+                false => None,
+            };
             let run_result = self.execute_single_run(
                 request,
                 lang_config,
                 &limits,
                 &options,
-                run_index + 1,
+                copy.as_ref().unwrap_or(&build),
+                RunHooks {
+                    progress: &progress,
+                    running: ExecutionProgress::running(run_index + 1, num_runs),
+                    timeline: &mut *timeline,
+                },
             ).await?;
+            last_copy = copy; // the previous run's copy is deleted here
             
             results.push(run_result.clone());
             
+            // A cancel lets the current run finish and skips the rest
+            if self.is_cancelled(&request.id) {
+                info!("🛑 Execution {} cancelled after run {} of {}", request.id, run_index + 1, num_runs);
+                break;
+            }
+            
             // If any run fails, we can decide whether to continue or stop
             if run_result.exit_code.unwrap_or(0) != 0 && options.stop_on_first_failure {
                 warn!("Run {} failed, stopping remaining runs", run_index + 1);
@@ -142,8 +532,14 @@ impl CodeExecutor {
             }
         }
         
+        // A preserved directory shows the files of the last run
+        self.release_workdir(request, last_copy.unwrap_or(build).temp_dir).await;
+        
         // Aggregate results from multiple runs
-        let aggregated_result = self.aggregate_results(&request.id, results, created_at);
+        let mut aggregated_result = Self::aggregate_results(&request.id, results, created_at, base64_encoded);
+        if self.is_cancelled(&request.id) {
+            aggregated_result.status = ExecutionState::Cancelled;
+        }
         
         let execution_time = start_time.elapsed().as_millis() as f64;
         info!("✅ Execution completed in {}ms", execution_time);
@@ -151,215 +547,1465 @@ impl CodeExecutor {
         Ok(aggregated_result)
     }
     
-    /// Execute a single run of the code
+    /// Execute a single run of the built code in the build's working directory
     async fn execute_single_run(
         &self,
         request: &ExecutionRequest,
         lang_config: &LanguageConfig,
         limits: &ResourceLimits,
         options: &ExecutionOptions,
-        _run_number: u32,
+        build: &Build,
+        hooks: RunHooks<'_>,
     ) -> Result<ExecutionResult> {
-        // Create temporary directory for this execution
-        let temp_dir = TempDir::new_in(&self.temp_base)?;
-        let temp_path = temp_dir.path();
-        
-        // Write source code to file
-        let source_path = temp_path.join(&lang_config.source_file);
-        fs::write(&source_path, &request.source_code)?;
+        let temp_path = build.temp_dir.path();
+        let workspace = build.workspace(&request.id);
+        let base64_encoded = request.base64_encoded.unwrap_or(false);
+        let stdin_path = write_stdin(temp_path, request.stdin_bytes().as_deref())?;
         
-        // Handle additional files (ZIP extraction)
-        if let Some(additional_files) = &request.additional_files {
-            self.extract_additional_files(temp_path, additional_files)?;
-        }
+        let interactor = build.interactor.as_deref();
+        let run_cmd = run_command(request, lang_config);
         
-        // Create stdin file if provided
-        let stdin_path = if let Some(stdin) = &request.stdin {
-            if !stdin.is_empty() {
-                let stdin_path = temp_path.join("input.txt");
-                fs::write(&stdin_path, stdin)?;
-                Some(stdin_path)
-            } else {
-                None
-            }
-        } else {
-            None
+        // Execute the program
+        debug!("Running code...");
+        let _ = hooks.progress.send(hooks.running);
+        hooks.timeline.stamp(TimelineStage::RunStarted);
+        let run_result = match interactor {
+            Some(interactor) => self.run_interactive(
+                Party { job: &request.id, cmd: &run_cmd, dir: temp_path, env: request.env.as_ref() },
+                Party { job: &request.id, cmd: &interactor.run_cmd, dir: interactor.dir.path(), env: None },
+                limits,
+            ).await.map(|(program, judge)| (program, Some(judge))),
+            None => self.run_command_with_limits(
+                &run_cmd,
+                &workspace,
+                limits,
+                stdin_path.as_deref(),
+                request.env.as_ref(),
+                options,
+            ).await.map(|program| (program, None)),
         };
+        hooks.timeline.stamp(TimelineStage::RunFinished);
+        let (mut run_result, judge) = run_result?;
+        explain_runtime_failure(&mut run_result, lang_config, limits, options);
         
-        let mut compile_output = None;
+        // Compilers first, then the program and the interactor it talked to
+        let mut executed_commands: Vec<ExecutedCommand> = build.compile_command.iter()
+            .chain(interactor.and_then(|interactor| interactor.compile_command.as_ref()))
+            .cloned()
+            .collect();
+        executed_commands.push(run_result.executed(CommandPhase::Run, &run_cmd));
+        if let (Some(judge), Some(interactor)) = (&judge, interactor) {
+            executed_commands.push(judge.executed(CommandPhase::Interactor, &interactor.run_cmd));
+        }
         
-        // Compile if needed
-        if let Some(compile_cmd) = &lang_config.compile_cmd {
-            debug!("Compiling code...");
-            let compile_result = self.run_command_with_limits(
-                compile_cmd,
-                temp_path,
-                limits,
-                None, // No stdin for compilation
-                options,
-            ).await?;
-            
-            compile_output = Some(format!("{}\n{}", compile_result.stdout, compile_result.stderr));
-            
-            if compile_result.exit_code != 0 {
-                return Ok(ExecutionResult {
-                    id: request.id.clone(),
-                    status: ExecutionState::CompilationError,
-                    stdout: Some(compile_result.stdout),
-                    stderr: Some(compile_result.stderr),
-                    compile_output,
-                    exit_code: Some(compile_result.exit_code),
-                    signal: None,
-                    time: Some(compile_result.execution_time),
-                    memory: Some(compile_result.memory_usage),
-                    created_at: Utc::now(),
-                    finished_at: Some(Utc::now()),
-                });
+        let mut status = run_status(&run_result);
+        
+        // The interactor's exit code is the verdict unless the program failed on its own;
+        // a SIGPIPE only means the interactor hung up first
+        if let Some(judge) = &judge {
+            let hung_up = status == ExecutionState::RuntimeError && run_result.signal.as_deref() == Some("SIGPIPE");
+            if status == ExecutionState::Completed || hung_up {
+                status = match (judge.exit_code, &judge.signal) {
+                    (_, Some(signal)) => {
+                        run_result.failure_reason = Some(format!("interactor killed by {}", signal));
+                        ExecutionState::InternalError
+                    }
+                    (0, None) => ExecutionState::Completed,
+                    _ => ExecutionState::WrongAnswer,
+                };
             }
         }
+        let interactor_result = judge.map(|judge| InteractorResult {
+            exit_code: judge.exit_code,
+            exit_description: sandbox::exit_description(judge.exit_code, judge.signal.as_deref()),
+            signal: judge.signal,
+            stderr: encode_output(&output::clean(&judge.stderr, options), base64_encoded),
+            time: judge.execution_time,
+        });
         
-        // Execute the program
-        debug!("Running code...");
-        let run_result = self.run_command_with_limits(
-            &lang_config.run_cmd,
-            temp_path,
-            limits,
-            stdin_path.as_deref(),
-            options,
-        ).await?;
-        
-        // Determine final status based on exit code and execution
-        let status = if run_result.exit_code == 0 {
-            ExecutionState::Completed
-        } else if run_result.timed_out {
-            ExecutionState::TimeLimitExceeded
-        } else if run_result.memory_exceeded {
-            ExecutionState::MemoryLimitExceeded
-        } else {
-            ExecutionState::RuntimeError
+        // Collect requested output files before the temp dir goes away
+        let artifacts = match &request.artifact_patterns {
+            Some(patterns) if !patterns.is_empty() => {
+                let mut excluded: Vec<glob::Pattern> = [lang_config.source_file.as_str(), "input.txt"].iter()
+                    .map(|name| glob::Pattern::new(&glob::Pattern::escape(name)))
+                    .collect::<std::result::Result<_, _>>()?;
+                if let Some(binary) = lang_config.run_cmd[0].strip_prefix("./") {
+                    excluded.push(glob::Pattern::new(&glob::Pattern::escape(binary))?);
+                }
+                // javac leaves a class file per class rather than one binary
+                if lang_config.compile_cmd.as_ref().is_some_and(|cmd| cmd[0] == "javac") {
+                    excluded.push(glob::Pattern::new("*.class")?);
+                }
+                Some(self.collect_artifacts(temp_path, patterns, &excluded)?)
+            }
+            _ => None,
         };
+        let workdir_listing = list_workdir(request, lang_config, temp_path, &status);
+        
+        let stdout = output::clean(&run_result.stdout, options);
+        let stderr = (!options.redirect_stderr_to_stdout).then(|| output::clean(&run_result.stderr, options));
         
         Ok(ExecutionResult {
             id: request.id.clone(),
             status,
-            stdout: Some(run_result.stdout),
-            stderr: Some(run_result.stderr),
-            compile_output,
+            stdout: Some(encode_output(&stdout, base64_encoded)),
+            stderr: stderr.as_deref().map(|stderr| encode_output(stderr, base64_encoded)),
+            compile_output: build.compile_output.as_ref().map(|output| encode_output(&output::clean(output, options), base64_encoded)),
+            diagnostics: build.diagnostics.clone(),
+            stdout_bytes: None,
+            stdout_url: None,
+            stderr_bytes: None,
+            stderr_url: None,
+            stdout_encoding: Some(OutputEncoding::of(&stdout)),
+            stderr_encoding: stderr.as_deref().map(OutputEncoding::of),
+            stdout_truncated: run_result.truncated.stdout,
+            stderr_truncated: run_result.truncated.stderr,
             exit_code: Some(run_result.exit_code),
+            exit_description: Some(sandbox::exit_description(run_result.exit_code, run_result.signal.as_deref())),
             signal: run_result.signal,
             time: Some(run_result.execution_time),
             memory: Some(run_result.memory_usage),
+            metrics: Some(run_result.metrics),
+            artifacts,
+            workdir_listing,
+            executed_commands,
+            failure_reason: run_result.failure_reason,
+            compile_cache_hit: build.compile_cache_hit,
+            failed_run_indices: Vec::new(), // filled in by aggregate_results
+            warnings: limits.warnings(),
+            attempts: 1, // the engine overwrites this when it retries
+            limits: Some(limits.clone()),
+            options: Some(options.clone()),
+            metadata: None, // echoed from the request by the engine
+            runner: None, // stamped by the engine
+            started_at: Some(build.compile_started_at.unwrap_or(run_result.started_at)),
             created_at: Utc::now(),
             finished_at: Some(Utc::now()),
+            timeline: Vec::new(), // filled in by the engine
+            queue_wait_ms: None,
+            overhead_ms: None,
+            phases: None,
+            setup_bytes: Some(build.setup_bytes),
+            interactor: interactor_result,
+            test_cases: Vec::new(),
+            test_summary: None,
         })
     }
     
-    /// Run a command with resource limits and sandboxing
-    async fn run_command_with_limits(
+    /// Run every test case of a test group against a single build of the program
+    /// Each case gets a fresh copy of the built working directory; a cancel skips the cases not yet started.
+    async fn execute_test_group(
         &self,
-        cmd_args: &[String],
-        working_dir: &Path,
+        request: &ExecutionRequest,
+        lang_config: &LanguageConfig,
         limits: &ResourceLimits,
-        stdin_file: Option<&Path>,
         options: &ExecutionOptions,
-    ) -> Result<CommandResult> {
-        if cmd_args.is_empty() {
-            return Err(anyhow!("Empty command"));
-        }
-        
-        let mut command = Command::new(&cmd_args[0]);
-        command.args(&cmd_args[1..]);
-        command.current_dir(working_dir);
+        progress: &ProgressSender,
+        timeline: &mut Timeline,
+    ) -> Result<ExecutionResult> {
+        let cases = request.test_cases.as_deref().unwrap_or_default();
+        let base64_encoded = request.base64_encoded.unwrap_or(false);
+        let Build {
+            temp_dir, _active, lease, sandbox_box, compile_output, compile_command, compile_started_at, diagnostics, compile_cache_hit, setup_bytes,
+            interactor: _, // validation keeps interactors out of test groups
+        } =
+            match self.build(request, lang_config, limits, options, progress, &mut *timeline).await? {
+                Ok(build) => build,
+                Err(failed) => return Ok(*failed),
+            };
+        // Every case claims its own sandbox, so the build's goes back to the pool
+        drop((lease, sandbox_box));
         
-        // Set up stdio
-        command.stdout(Stdio::piped());
+        let run_cmd = run_command(request, lang_config);
+        let mut case_results = Vec::new();
+        let mut executed_commands: Vec<ExecutedCommand> = compile_command.into_iter().collect();
+        let mut case_streams = Vec::new(); // encodings and truncation of each case's output
+        let mut metrics: Option<ExecutionMetrics> = None;
+        let mut started_at = compile_started_at;
         
-        if options.redirect_stderr_to_stdout {
-            command.stderr(Stdio::piped()); // We'll merge manually
-        } else {
-            command.stderr(Stdio::piped());
+        timeline.stamp(TimelineStage::RunStarted);
+        for (index, case) in cases.iter().enumerate() {
+            if self.is_cancelled(&request.id) {
+                info!("🛑 Execution {} cancelled after {} of {} test cases", request.id, index, cases.len());
+                break;
+            }
+            debug!("Running test case {} of {}", index + 1, cases.len());
+            let _ = progress.send(ExecutionProgress::running(index as u32 + 1, cases.len() as u32));
+            
+            let case_limits = ResourceLimits {
+                cpu_time: case.cpu_time_limit.unwrap_or(limits.cpu_time),
+                wall_time: case.wall_time_limit.unwrap_or(limits.wall_time),
+                memory: case.memory_limit.unwrap_or(limits.memory),
+                ..limits.clone()
+            };
+            let case_dir = tempfile::Builder::new()
+                .prefix(&format!("{}.", sandbox::job_tag(&request.id)))
+                .tempdir_in(&self.temp_base)?;
+            let _case_active = ActiveDirGuard::new(&self.active_dirs, case_dir.path());
+            isolate::copy_tree(temp_dir.path(), case_dir.path())?;
+            let stdin = case.stdin.as_deref().map(|stdin| decode_output(stdin, base64_encoded));
+            let stdin_path = write_stdin(case_dir.path(), stdin.as_deref())?;
+            
+            let (case_lease, case_box) = self.claim_sandbox(request, lang_config, &case_limits, options).await?;
+            let workspace = Workspace {
+                job: &request.id,
+                dir: case_dir.path(),
+                container: case_lease.as_ref().map(|lease| lease.id.as_str()),
+                sandbox_box: case_box.as_ref(),
+                enforce_quota: true,
+            };
+            let mut run = self.run_command_with_limits(
+                &run_cmd,
+                &workspace,
+                &case_limits,
+                stdin_path.as_deref(),
+                request.env.as_ref(),
+                options,
+            ).await?;
+            explain_runtime_failure(&mut run, lang_config, &case_limits, options);
+            executed_commands.push(run.executed(CommandPhase::Run, &run_cmd));
+            
+            let mut status = run_status(&run);
+            if status == ExecutionState::Completed
+                && case.expected_output.as_deref().is_some_and(|expected| !output_matches(&run.stdout, expected))
+            {
+                status = ExecutionState::WrongAnswer;
+            }
+            started_at = started_at.or(Some(run.started_at));
+            metrics = Some(match metrics {
+                Some(total) => total.combine(&run.metrics),
+                None => run.metrics,
+            });
+            let stdout = output::clean(&run.stdout, options);
+            let stderr = (!options.redirect_stderr_to_stdout).then(|| output::clean(&run.stderr, options));
+            case_streams.push((OutputEncoding::of(&stdout), stderr.as_deref().map(OutputEncoding::of), run.truncated));
+            case_results.push(TestCaseResult {
+                status,
+                stdout: Some(encode_output(&stdout, base64_encoded)),
+                stderr: stderr.as_deref().map(|stderr| encode_output(stderr, base64_encoded)),
+                exit_code: Some(run.exit_code),
+                exit_description: Some(sandbox::exit_description(run.exit_code, run.signal.as_deref())),
+                signal: run.signal,
+                time: Some(run.execution_time),
+                memory: Some(run.memory_usage),
+                failure_reason: run.failure_reason,
+            });
         }
+        timeline.stamp(TimelineStage::RunFinished);
+        self.release_workdir(request, temp_dir).await;
         
-        if let Some(_stdin_file) = stdin_file {
-            command.stdin(Stdio::piped());
-        } else {
-            command.stdin(Stdio::null());
-        }
+        // The first failing case stands for the group, or the last one when every case passed
+        let failed_run_indices: Vec<u32> = case_results.iter()
+            .enumerate()
+            .filter(|(_, result)| result.status != ExecutionState::Completed)
+            .map(|(index, _)| index as u32)
+            .collect();
+        let decisive_index = failed_run_indices.first().map(|&index| index as usize)
+            .or(case_results.len().checked_sub(1));
+        let decisive = decisive_index.map(|index| &case_results[index]);
+        let streams = decisive_index.map(|index| case_streams[index]);
+        let summary = TestGroupSummary {
+            passed: (case_results.len() - failed_run_indices.len()) as u32,
+            failed: failed_run_indices.len() as u32,
+            skipped: (cases.len() - case_results.len()) as u32,
+            first_failure: failed_run_indices.first().copied(),
+        };
         
-        // Apply sandbox limits
-        let sandbox = Sandbox::new(limits.clone());
-        sandbox.apply_limits(&mut command)?;
+        Ok(ExecutionResult {
+            id: request.id.clone(),
+            status: decisive.map_or(ExecutionState::Cancelled, |result| result.status.clone()),
+            stdout: decisive.and_then(|result| result.stdout.clone()),
+            stderr: decisive.and_then(|result| result.stderr.clone()),
+            compile_output: compile_output.map(|output| encode_output(&output::clean(&output, options), base64_encoded)),
+            diagnostics,
+            stdout_bytes: None,
+            stdout_url: None,
+            stderr_bytes: None,
+            stderr_url: None,
+            stdout_encoding: streams.map(|(stdout, _, _)| stdout),
+            stderr_encoding: streams.and_then(|(_, stderr, _)| stderr),
+            stdout_truncated: streams.is_some_and(|(_, _, truncated)| truncated.stdout),
+            stderr_truncated: streams.is_some_and(|(_, _, truncated)| truncated.stderr),
+            exit_code: decisive.and_then(|result| result.exit_code),
+            signal: decisive.and_then(|result| result.signal.clone()),
+            exit_description: decisive.and_then(|result| result.exit_description.clone()),
+            time: Some(case_results.iter().filter_map(|result| result.time).sum()),
+            memory: Some(case_results.iter().filter_map(|result| result.memory).max().unwrap_or(0)),
+            metrics,
+            artifacts: None,
+            workdir_listing: None,
+            executed_commands,
+            failure_reason: decisive.and_then(|result| result.failure_reason.clone()),
+            compile_cache_hit,
+            failed_run_indices,
+            warnings: limits.warnings(),
+            attempts: 1, // the engine overwrites this when it retries
+            limits: Some(limits.clone()),
+            options: Some(options.clone()),
+            metadata: None, // echoed from the request by the engine
+            runner: None, // stamped by the engine
+            started_at,
+            created_at: Utc::now(),
+            finished_at: Some(Utc::now()),
+            timeline: Vec::new(), // filled in by the engine
+            queue_wait_ms: None,
+            overhead_ms: None,
+            phases: None,
+            setup_bytes: Some(setup_bytes),
+            interactor: None,
+            test_cases: case_results,
+            test_summary: Some(summary),
+        })
+    }
+    
+    /// Copy of a build in a new working directory with a sandbox of its own, so a run does not see earlier runs' files
+    async fn copy_build(
+        &self,
+        request: &ExecutionRequest,
+        lang_config: &LanguageConfig,
+        limits: &ResourceLimits,
+        options: &ExecutionOptions,
+        build: &Build,
+    ) -> Result<Build> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix(&format!("{}.", sandbox::job_tag(&request.id)))
+            .tempdir_in(&self.temp_base)?;
+        let active = ActiveDirGuard::new(&self.active_dirs, temp_dir.path());
+        // Copied rather than hard-linked, so a program rewriting a file in place leaves the build alone
+        isolate::copy_tree(build.temp_dir.path(), temp_dir.path())?;
+        let (lease, sandbox_box) = self.claim_sandbox(request, lang_config, limits, options).await?;
         
-        // Start the process
-        let start_time = Instant::now();
-        let mut child = command.spawn()?;
+        Ok(Build {
+            temp_dir,
+            _active: active,
+            lease,
+            sandbox_box,
+            compile_output: build.compile_output.clone(),
+            compile_command: build.compile_command.clone(),
+            compile_started_at: build.compile_started_at,
+            diagnostics: build.diagnostics.clone(),
+            compile_cache_hit: build.compile_cache_hit,
+            setup_bytes: build.setup_bytes,
+            interactor: build.interactor.clone(),
+        })
+    }
+    
+    /// Set up a working directory with the sources and a sandbox, then compile, returning the build or the failed result
+    async fn build(
+        &self,
+        request: &ExecutionRequest,
+        lang_config: &LanguageConfig,
+        limits: &ResourceLimits,
+        options: &ExecutionOptions,
+        progress: &ProgressSender,
+        timeline: &mut Timeline,
+    ) -> Result<std::result::Result<Build, Box<ExecutionResult>>> {
+        // Create temporary directory for this execution
+        let temp_dir = tempfile::Builder::new()
+            .prefix(&format!("{}.", sandbox::job_tag(&request.id)))
+            .tempdir_in(&self.temp_base)?;
+        let temp_path = temp_dir.path();
+        let active = ActiveDirGuard::new(&self.active_dirs, temp_path);
         
-        // Write stdin if provided
-        if let Some(stdin_file) = stdin_file {
-            if let Some(mut stdin) = child.stdin.take() {
-                let stdin_data = fs::read(stdin_file)?;
-                stdin.write_all(&stdin_data)?;
-                drop(stdin); // Close stdin
-            }
+        // Write source code to file
+        let source_path = temp_path.join(&lang_config.source_file);
+        fs::write(&source_path, &request.source_code)?;
+        let mut setup_bytes = request.source_code.len() as u64;
+        
+        // Handle additional files (ZIP extraction)
+        if let Some(additional_files) = &request.additional_files {
+            setup_bytes += self.extract_additional_files(temp_path, additional_files)?;
         }
         
-        // Wait for completion with timeout
-        let timeout_duration = Duration::from_secs_f64(limits.wall_time);
-        let wait_result = timeout(timeout_duration, async move {
-            tokio::task::spawn_blocking(move || {
-                child.wait_with_output()
-            }).await.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
-        }).await;
+        let (lease, sandbox_box) = self.claim_sandbox(request, lang_config, limits, options).await?;
+        let workspace = Workspace {
+            job: &request.id,
+            dir: temp_path,
+            container: lease.as_ref().map(|lease| lease.id.as_str()),
+            sandbox_box: sandbox_box.as_ref(),
+            enforce_quota: true,
+        };
+        timeline.stamp(TimelineStage::SandboxReady);
         
-        let execution_time = start_time.elapsed().as_secs_f64();
+        let mut compile_output = None;
+        let mut compile_command = None;
+        let mut compile_started_at = None;
+        let mut compile_cache_hit = false;
+        let mut diagnostics = Vec::new();
         
-        match wait_result {
-            Ok(Ok(output)) => {
-                let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                
-                // Handle stderr redirection
-                let (final_stdout, final_stderr) = if options.redirect_stderr_to_stdout {
-                    (format!("{}{}", stdout, stderr), String::new())
-                } else {
-                    (stdout, stderr)
-                };
-                
-                Ok(CommandResult {
-                    stdout: final_stdout,
-                    stderr: final_stderr,
-                    exit_code: output.status.code().unwrap_or(-1),
-                    signal: None, // TODO: Extract signal on Unix
-                    execution_time,
-                    memory_usage: 0, // TODO: Track memory usage
-                    timed_out: false,
-                    memory_exceeded: false,
-                })
+        // Compile if needed
+        if let Some(compile_cmd) = self.resolve_compile_cmd(request, lang_config, temp_path)? {
+            // Reuse a previous build of identical sources when the cache is enabled
+            // A container compiles with its image's toolchain, not the host's
+            let toolchain = match (&lease, &lang_config.docker_image) {
+                (Some(_), Some(image)) => Toolchain::Image(image),
+                _ => Toolchain::Host,
+            };
+            let cache_key = match &self.compile_cache {
+                Some(cache) => cache.key(&compile_cmd, temp_path, toolchain).await?,
+                None => None,
+            };
+            if let (Some(cache), Some(key)) = (&self.compile_cache, &cache_key) {
+                if let Some(cached) = cache.restore(key, temp_path)? {
+                    compile_output = Some(cached.compile_output.into_bytes());
+                    compile_cache_hit = true;
+                }
             }
-            Ok(Err(e)) => Err(anyhow!("Process execution failed: {}", e)),
-            Err(_) => {
-                // Timeout occurred - process was killed by timeout mechanism
-                Ok(CommandResult {
-                    stdout: String::new(),
-                    stderr: "Time limit exceeded".to_string(),
-                    exit_code: -1,
-                    signal: Some("SIGKILL".to_string()),
-                    execution_time,
-                    memory_usage: 0,
-                    timed_out: true,
-                    memory_exceeded: false,
-                })
+            
+            if !compile_cache_hit {
+                let _ = progress.send(ExecutionProgress::compiling());
+                timeline.stamp(TimelineStage::CompileStarted);
+                let compiled = self.compile(request, &compile_cmd, &workspace, limits, options, cache_key.as_deref()).await;
+                timeline.stamp(TimelineStage::CompileFinished);
+                match compiled? {
+                    Ok((output, command, started_at)) => {
+                        compile_output = Some(output);
+                        compile_command = Some(command);
+                        compile_started_at = Some(started_at);
+                    }
+                    Err(mut failed) => {
+                        failed.workdir_listing = list_workdir(request, lang_config, temp_path, &failed.status);
+                        self.release_workdir(request, temp_dir).await;
+                        failed.setup_bytes = Some(setup_bytes);
+                        return Ok(Err(failed));
+                    }
+                }
+            }
+            if let Some(output) = &compile_output {
+                diagnostics = compile_diagnostics(&compile_cmd, output, temp_path);
             }
         }
+        
+        // An interactive problem's judge is built once too, in a directory of its own, and shared by every run
+        let interactor = match &request.interactor {
+            Some(interactor) => match self.prepare_interactor(request, interactor, limits, options).await? {
+                Ok(built) => Some(Arc::new(built)),
+                Err(mut failed) => {
+                    failed.executed_commands.splice(0..0, compile_command);
+                    self.release_workdir(request, temp_dir).await;
+                    failed.setup_bytes = Some(setup_bytes);
+                    return Ok(Err(failed));
+                }
+            },
+            None => None,
+        };
+        
+        Ok(Ok(Build {
+            temp_dir,
+            _active: active,
+            lease,
+            sandbox_box,
+            compile_output,
+            compile_command,
+            compile_started_at,
+            diagnostics,
+            compile_cache_hit,
+            setup_bytes,
+            interactor,
+        }))
     }
     
-    /// Extract additional files from base64 ZIP
-    fn extract_additional_files(&self, temp_path: &Path, base64_zip: &str) -> Result<()> {
-        use base64::{engine::general_purpose, Engine as _};
-        use std::io::Cursor;
-        use zip::ZipArchive;
+    /// Claim a warm container on the docker backend, or a freshly initialised box on the isolate backend
+    async fn claim_sandbox(
+        &self,
+        request: &ExecutionRequest,
+        lang_config: &LanguageConfig,
+        limits: &ResourceLimits,
+        options: &ExecutionOptions,
+    ) -> Result<(Option<ContainerLease>, Option<BoxLease>)> {
+        let lease = match (&self.container_pool, &lang_config.docker_image) {
+            (Some(pool), Some(image)) => Some(ContainerLease {
+                id: pool.claim(image, options.isolation, limits, options.enable_network, &request.id).await?,
+                pool: pool.clone(),
+            }),
+            // Submission checks refuse these, but a stored job may outlive a config change
+            _ if options.isolation == IsolationLevel::Strong => {
+                return Err(anyhow!("strong isolation needs the docker backend and an image for {}", request.language));
+            }
+            _ => None,
+        };
+        let sandbox_box = match &self.box_pool {
+            Some(pool) => Some(pool.claim().await?),
+            None => None,
+        };
+        Ok((lease, sandbox_box))
+    }
+    
+    /// Run the compile step, returning the compiler output, invocation and start time or the failed result
+    async fn compile(
+        &self,
+        request: &ExecutionRequest,
+        compile_cmd: &[String],
+        workspace: &Workspace<'_>,
+        limits: &ResourceLimits,
+        options: &ExecutionOptions,
+        cache_key: Option<&str>,
+    ) -> Result<std::result::Result<(Vec<u8>, ExecutedCommand, chrono::DateTime<Utc>), Box<ExecutionResult>>> {
+        let working_dir = workspace.dir;
+        let cache = self.compile_cache.as_ref().zip(cache_key);
+        let snapshot = match cache {
+            Some((cache, _)) => Some(cache.snapshot(working_dir)?),
+            None => None,
+        };
         
-        debug!("Extracting additional files from ZIP");
+        // Build output, such as a cargo target directory, can outgrow the quota meant for the program
+        debug!("Compiling code...");
+        let compile_result = self.run_command_with_limits(
+            compile_cmd,
+            &Workspace { enforce_quota: false, ..*workspace },
+            limits,
+            None, // No stdin for compilation
+            None, // Toolchain gets the cleared environment without the user's variables
+            options,
+        ).await?;
+        
+        let compile_output = [compile_result.stdout.as_slice(), b"\n", compile_result.stderr.as_slice()].concat();
+        let compile_command = compile_result.executed(CommandPhase::Compile, compile_cmd);
+        let base64_encoded = request.base64_encoded.unwrap_or(false);
+        
+        if compile_result.exit_code != 0 {
+            let stdout = output::clean(&compile_result.stdout, options);
+            let stderr = (!options.redirect_stderr_to_stdout).then(|| output::clean(&compile_result.stderr, options));
+            return Ok(Err(Box::new(ExecutionResult {
+                id: request.id.clone(),
+                status: ExecutionState::CompilationError,
+                stdout: Some(encode_output(&stdout, base64_encoded)),
+                stderr: stderr.as_deref().map(|stderr| encode_output(stderr, base64_encoded)),
+                compile_output: Some(encode_output(&output::clean(&compile_output, options), base64_encoded)),
+                diagnostics: compile_diagnostics(compile_cmd, &compile_output, working_dir),
+                stdout_bytes: None,
+                stdout_url: None,
+                stderr_bytes: None,
+                stderr_url: None,
+                stdout_encoding: Some(OutputEncoding::of(&stdout)),
+                stderr_encoding: stderr.as_deref().map(OutputEncoding::of),
+                stdout_truncated: compile_result.truncated.stdout,
+                stderr_truncated: compile_result.truncated.stderr,
+                exit_code: Some(compile_result.exit_code),
+                exit_description: Some(sandbox::exit_description(compile_result.exit_code, compile_result.signal.as_deref())),
+                signal: compile_result.signal,
+                time: Some(compile_result.execution_time),
+                memory: Some(compile_result.memory_usage),
+                metrics: Some(compile_result.metrics),
+                artifacts: None,
+                workdir_listing: None,
+                executed_commands: vec![compile_command],
+                failure_reason: compile_result.failure_reason,
+                compile_cache_hit: false,
+                failed_run_indices: Vec::new(),
+                warnings: limits.warnings(),
+                attempts: 1,
+                limits: Some(limits.clone()),
+                options: Some(options.clone()),
+                metadata: None, // echoed from the request by the engine
+                runner: None, // stamped by the engine
+                started_at: Some(compile_result.started_at),
+                created_at: Utc::now(),
+                finished_at: Some(Utc::now()),
+                timeline: Vec::new(), // filled in by the engine
+                queue_wait_ms: None,
+                overhead_ms: None,
+                phases: None,
+                setup_bytes: None,
+                interactor: None,
+                test_cases: Vec::new(),
+                test_summary: None,
+            })));
+        }
+        
+        if compile_cmd[0] == "cargo" {
+            self.install_cargo_binary(working_dir)?;
+        }
+        
+        if let (Some((cache, key)), Some(snapshot)) = (cache, snapshot) {
+            if let Err(err) = cache.store(key, working_dir, &snapshot, &String::from_utf8_lossy(&compile_output)) {
+                warn!("Failed to store compile cache entry: {}", err);
+            }
+        }
+        
+        Ok(Ok((compile_output, compile_command, compile_result.started_at)))
+    }
+    
+    /// Look up a request's language by name, falling back to its numeric id
+    fn language_config(&self, request: &ExecutionRequest) -> Option<&LanguageConfig> {
+        self.resolve_language(&request.language)
+            .and_then(|key| self.languages.get(key))
+            .or_else(|| {
+                let id = request.language_id?;
+                self.languages.values().find(|config| config.id == id)
+            })
+    }
+    
+    /// Key of the language a client's spelling stands for.
+    ///
+    /// Names are matched without case or surrounding space, as keys or aliases, then again with a
+    /// version suffix removed, so "Python3.11" is python and "node18" is javascript.
+    pub fn resolve_language(&self, name: &str) -> Option<&str> {
+        let name = normalize_language(name);
+        let unversioned = name.trim_end_matches(|c: char| c.is_ascii_digit() || matches!(c, '.' | '-' | '_' | ' '));
+        for candidate in [name.as_str(), unversioned] {
+            if let Some((key, _)) = self.languages.get_key_value(candidate) {
+                return Some(key.as_str());
+            }
+            if let Some(key) = self.aliases.get(candidate) {
+                return Some(key.as_str());
+            }
+        }
+        None
+    }
+    
+    /// Keys of the languages whose key or an alias is a few edits from `name`, closest first
+    pub fn suggest_languages(&self, name: &str) -> Vec<String> {
+        let name = normalize_language(name);
+        // Allow about one typo per three characters
+        let tolerance = (name.chars().count() / 3).clamp(1, 3);
+        let mut scored: Vec<(usize, &String)> = self.languages.keys()
+            .map(|key| (key, key))
+            .chain(self.aliases.iter())
+            .map(|(candidate, key)| (edit_distance(&name, candidate), key))
+            .filter(|(distance, _)| *distance <= tolerance)
+            .collect();
+        scored.sort();
+        
+        let mut keys: Vec<String> = Vec::new();
+        for (_, key) in scored {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        keys.truncate(3);
+        keys
+    }
+    
+    /// Key and aliases of the language with a Judge0 language id
+    pub fn language_names(&self, id: u32) -> Option<(String, Vec<String>)> {
+        let (key, language) = self.languages.iter().find(|(_, config)| config.id == id)?;
+        let mut aliases = language.aliases.clone();
+        aliases.sort();
+        Some((key.clone(), aliases))
+    }
+    
+    /// Key and display name of the language with a Judge0 language id
+    pub fn language_by_id(&self, id: u32) -> Option<(String, String)> {
+        self.languages.iter()
+            .find(|(_, config)| config.id == id)
+            .map(|(key, config)| (key.clone(), config.name.clone()))
+    }
+    
+    /// What of the request this executor can honour, for submission checks
+    pub fn support(&self, request: &ExecutionRequest) -> Support {
+        let language = self.language_config(request);
+        let isolation = match request.isolation.unwrap_or_default() {
+            IsolationLevel::Standard => true,
+            strong => match (&self.container_pool, language) {
+                (Some(pool), Some(config)) => config.docker_image.is_some() && pool.supports(strong),
+                _ => false,
+            },
+        };
+        let interactor = request.interactor.as_ref()
+            .map(|interactor| self.resolve_language(&interactor.language).and_then(|key| self.languages.get(key)));
+        Support {
+            language: language.is_some(),
+            in_service: language.is_none_or(|config| !self.out_of_service.lock().unwrap().contains(&config.id)),
+            enabled: language.is_none_or(|config| !self.disabled.lock().unwrap().contains(&config.id)),
+            isolation,
+            max_limits: language.map(|config| config.limits.max).unwrap_or_default(),
+            max_source_bytes: language.and_then(|config| config.limits.max_source_bytes),
+            interactor_language: interactor.is_none_or(|config| config.is_some()),
+            interactive: interactor.is_none_or(|config| self.runs_natively(language) && self.runs_natively(config)),
+        }
+    }
+    
+    /// Keys of every language, sorted
+    pub fn language_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.languages.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+    
+    /// Make sure a language's image is present, then build and run `program` in it when given
+    pub async fn warm_up(&self, language: &str, program: Option<&str>) -> Result<()> {
+        let config = self.languages.get(language)
+            .ok_or_else(|| anyhow!("Unsupported language: {}", language))?;
+        if let (Some(_), Some(image)) = (&self.container_pool, &config.docker_image) {
+            docker::ensure_image(image).await?;
+        }
+        let Some(program) = program else {
+            return Ok(());
+        };
+        
+        let request = ExecutionRequest {
+            id: format!("warmup-{}", language),
+            language: language.to_string(),
+            source_code: program.to_string(),
+            ..Default::default()
+        };
+        let (progress, _) = mpsc::unbounded_channel();
+        let result = self.execute(&request, progress, &mut Timeline::default()).await?;
+        if result.status != ExecutionState::Completed {
+            let detail = [&result.failure_reason, &result.compile_output, &result.stderr]
+                .into_iter()
+                .flatten()
+                .find(|text| !text.trim().is_empty())
+                .map(|text| text.trim().to_string())
+                .unwrap_or_default();
+            return Err(anyhow!("hello-world finished as {}: {}", result.status.as_str(), detail));
+        }
+        Ok(())
+    }
+    
+    /// Refuse a language's submissions from now on
+    pub fn take_out_of_service(&self, language: &str) {
+        if let Some(config) = self.languages.get(language) {
+            self.out_of_service.lock().unwrap().insert(config.id);
+        }
+    }
+    
+    /// Ids of the languages with these keys, or an error naming the first unknown one
+    pub(crate) fn language_ids(&self, keys: &[String]) -> Result<HashSet<u32>> {
+        language_ids(&self.languages, keys)
+    }
+    
+    /// Take the runtime settings of `config` for jobs that start from now on
+    pub(crate) fn update_settings(&self, config: &EngineConfig, disabled: HashSet<u32>) {
+        *self.ceilings.write().unwrap() = config.limits.ceilings.clone();
+        self.output_max_bytes.store(config.sandbox.output_max_bytes, Ordering::SeqCst);
+        *self.disabled.lock().unwrap() = disabled;
+    }
+    
+    /// Whether a language's programs would be plain processes on this backend
+    fn runs_natively(&self, language: Option<&LanguageConfig>) -> bool {
+        self.box_pool.is_none() && (self.container_pool.is_none() || language.is_some_and(|config| config.docker_image.is_none()))
+    }
+    
+    /// Limits a request runs with: its own values, then its language's defaults, then the global defaults
+    pub fn limits(&self, request: &ExecutionRequest) -> ResourceLimits {
+        ResourceLimits::from_request(request, &self.language_defaults(self.language_config(request)))
+    }
+    
+    /// Defaults and ceilings of the language with a Judge0 language id
+    pub fn language_limits(&self, id: u32) -> Option<(ResourceLimits, LimitCeilings)> {
+        let language = self.languages.values().find(|config| config.id == id)?;
+        Some((self.language_defaults(Some(language)), self.ceilings.read().unwrap().ceilings(&language.limits.max)))
+    }
+    
+    /// Largest source of the language with a Judge0 language id
+    pub fn language_max_source_bytes(&self, id: u32) -> Option<usize> {
+        let language = self.languages.values().find(|config| config.id == id)?;
+        Some(self.max_source_bytes(Some(language)))
+    }
+    
+    /// The language's own source ceiling, else the server's
+    fn max_source_bytes(&self, language: Option<&LanguageConfig>) -> usize {
+        language.and_then(|config| config.limits.max_source_bytes).unwrap_or_else(|| self.ceilings.read().unwrap().max_source_bytes)
+    }
+    
+    /// The global defaults with the language's own applied, lowered to its ceilings
+    fn language_defaults(&self, language: Option<&LanguageConfig>) -> ResourceLimits {
+        let mut defaults = self.default_limits.clone();
+        if let Some(language) = language {
+            language.limits.defaults.apply(&mut defaults);
+            self.ceilings.read().unwrap().ceilings(&language.limits.max).clamp(&mut defaults);
+        }
+        defaults
+    }
+    
+    /// Configured docker runtimes and whether they were available at startup; empty without the docker backend
+    pub fn docker_runtimes(&self) -> BTreeMap<String, bool> {
+        self.container_pool.as_ref().map(|pool| pool.runtimes().clone()).unwrap_or_default()
+    }
+    
+    /// Backend and isolation features of this host
+    pub fn sandbox_features(&self, backend: ExecutionBackend) -> SandboxFeatures {
+        #[cfg(unix)]
+        let filesystem_isolation = self.isolation.is_some();
+        #[cfg(not(unix))]
+        let filesystem_isolation = false;
+        SandboxFeatures {
+            backend,
+            docker: self.container_pool.is_some() && self.runtime_ready(),
+            isolate: self.box_pool.is_some(),
+            filesystem_isolation,
+            cgroups_v2_writable: sandbox::cgroup_v2_writable(),
+            seccomp: false,
+        }
+    }
+    
+    /// Sandbox a request runs in: languages without an image run as processes on the docker backend
+    pub fn backend_for(&self, request: &ExecutionRequest) -> ExecutionBackend {
+        let image = self.language_config(request).and_then(|config| config.docker_image.as_ref());
+        match (&self.container_pool, &self.box_pool) {
+            (Some(_), _) if image.is_some() => ExecutionBackend::Docker,
+            (_, Some(_)) => ExecutionBackend::Isolate,
+            _ => ExecutionBackend::Process,
+        }
+    }
+    
+    /// False when the docker backend's default runtime was missing at startup
+    pub fn runtime_ready(&self) -> bool {
+        self.container_pool.as_ref().is_none_or(|pool| pool.supports(IsolationLevel::Standard))
+    }
+    
+    /// Files of outputs too large to embed in results
+    pub fn spill(&self) -> &OutputSpill {
+        &self.spill
+    }
+    
+    /// Free-space check of the volume under TEMP_BASE
+    pub(crate) fn disk(&self) -> &DiskGuard {
+        &self.disk
+    }
+    
+    /// Read the free space of TEMP_BASE's volume from `reader` instead of the operating system
+    pub(crate) fn set_disk_reader(&mut self, reader: Arc<dyn DiskReader>) {
+        self.disk = Arc::new(self.disk.with_reader(reader));
+    }
+    
+    /// Keep the docker backend's container pools at their configured sizes
+    pub async fn maintain_container_pool(&self) {
+        if let Some(pool) = &self.container_pool {
+            pool.maintain().await;
+        }
+    }
+    
+    /// Have a job being executed stop after its current run; false if it is not executing
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.executing.lock().unwrap().get_mut(id) {
+            Some(cancelled) => {
+                *cancelled = true;
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Whether the job was cancelled while executing
+    fn is_cancelled(&self, id: &str) -> bool {
+        self.executing.lock().unwrap().get(id).copied().unwrap_or(false)
+    }
+    
+    /// Kill every process still running, returning how many there were
+    pub fn kill_running(&self) -> usize {
+        let running = self.running.lock().unwrap();
+        for pid in running.keys() {
+            kill_process(*pid);
+        }
+        running.len()
+    }
+    
+    /// Kill the processes of one job, returning how many there were; with `cancel` first, no further run starts
+    pub fn kill_job(&self, id: &str) -> usize {
+        let running = self.running.lock().unwrap();
+        let pids: Vec<u32> = running.iter().filter(|(_, job)| *job == id).map(|(pid, _)| *pid).collect();
+        for pid in &pids {
+            kill_process(*pid);
+        }
+        pids.len()
+    }
+    
+    /// Remove the temp directories and containers of a job that was running when the engine died
+    ///
+    /// Isolate boxes need nothing here: every box is cleaned when the pool is created.
+    pub async fn clean_up_job(&self, id: &str) -> usize {
+        let prefix = format!("{}.", sandbox::job_tag(id));
+        let mut removed = 0;
+        
+        for entry in fs::read_dir(&self.temp_base).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if !entry.file_name().to_string_lossy().starts_with(&prefix) || self.active_dirs.lock().unwrap().contains(&path) {
+                continue;
+            }
+            match fs::remove_dir_all(&path) {
+                Ok(()) => removed += 1,
+                Err(err) => warn!("Failed to remove {} left by {}: {}", path.display(), id, err),
+            }
+        }
+        
+        if let Some(pool) = &self.container_pool {
+            match pool.remove_job_containers(id).await {
+                Ok(containers) => removed += containers,
+                Err(err) => warn!("Failed to remove containers left by {}: {}", id, err),
+            }
+        }
+        removed
+    }
+    
+    /// Compile cache hits and misses, if the cache is enabled
+    pub fn compile_cache_counters(&self) -> Option<(u64, u64)> {
+        self.compile_cache.as_ref().map(|cache| cache.counters())
+    }
+    
+    /// Drop the working directory, or hand it to the retained area when requested
+    async fn release_workdir(&self, request: &ExecutionRequest, temp_dir: TempDir) {
+        if !request.preserve_workdir.unwrap_or(false) {
+            return;
+        }
+        
+        let kept = temp_dir.keep();
+        if let Err(err) = self.workdirs.preserve(&request.id, &kept).await {
+            warn!("Could not preserve working directory for {}: {}", request.id, err);
+            let _ = fs::remove_dir_all(&kept);
+        }
+    }
+    
+    /// Run a command with resource limits and sandboxing
+    async fn run_command_with_limits(
+        &self,
+        cmd_args: &[String],
+        workspace: &Workspace<'_>,
+        limits: &ResourceLimits,
+        stdin_file: Option<&Path>,
+        env: Option<&HashMap<String, String>>,
+        options: &ExecutionOptions,
+    ) -> Result<CommandResult> {
+        if cmd_args.is_empty() {
+            return Err(anyhow!("Empty command"));
+        }
+        
+        // Containers and boxes work on a copy of the directory, synced before and after each command
+        if let Some(container) = workspace.container {
+            docker::copy_in(container, workspace.dir).await?;
+        }
+        if let Some(sandbox_box) = workspace.sandbox_box {
+            isolate::copy_in(sandbox_box, workspace.dir)?;
+        }
+        
+        // isolate reports how the program ended in a file kept outside the box
+        let meta = match workspace.sandbox_box {
+            Some(_) => Some(NamedTempFile::new_in(&self.temp_base)?),
+            None => None,
+        };
+        
+        let mut command = match (workspace.container, workspace.sandbox_box, &meta) {
+            (Some(container), _, _) => docker_exec_command(container, cmd_args, env, limits.nice, options.redirect_stderr_to_stdout),
+            (None, Some(sandbox_box), Some(meta)) => sandbox_box.command(cmd_args, limits, env, options, meta.path()),
+            _ => {
+                let mut command = Command::new(&cmd_args[0]);
+                command.args(&cmd_args[1..]);
+                command
+            }
+        };
+        command.current_dir(workspace.dir);
+        
+        // Own process group, so a kill also reaches anything the program forked
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        
+        // Set up stdio
+        command.stdout(Stdio::piped());
+        
+        // One pipe for both streams, so the kernel keeps their writes in order; isolate merges inside the box
+        if options.redirect_stderr_to_stdout && workspace.sandbox_box.is_none() {
+            merge_stderr_into_stdout(&mut command);
+        } else {
+            command.stderr(Stdio::piped());
+        }
+        
+        if let Some(_stdin_file) = stdin_file {
+            command.stdin(Stdio::piped());
+        } else {
+            command.stdin(Stdio::null());
+        }
+        
+        // Containers get their limits from docker, boxes from isolate; native processes from the sandbox
+        if workspace.container.is_none() && workspace.sandbox_box.is_none() {
+            self.confine(&mut command, workspace.dir, limits, env)?;
+        }
+        
+        // Start the process
+        let start_time = Instant::now();
+        let started_at = Utc::now();
+        let mut child = command.spawn()?;
+        
+        // Write stdin if provided
+        if let Some(stdin_file) = stdin_file {
+            if let Some(mut stdin) = child.stdin.take() {
+                let stdin_data = fs::read(stdin_file)?;
+                // Fed from a thread: a program that writes before reading all its input would otherwise block us both.
+                // One that exits without reading it all closes the pipe, which is not an error
+                std::thread::spawn(move || {
+                    if let Err(err) = stdin.write_all(&stdin_data) {
+                        if err.kind() != std::io::ErrorKind::BrokenPipe {
+                            warn!("Failed to write stdin: {}", err);
+                        }
+                    }
+                });
+            }
+        }
+        
+        // Wait for completion, enforcing the wall time and the working directory quota
+        let pid = child.id();
+        let _running = RunningGuard::new(&self.running, pid, workspace.job);
+        let mut timeout_duration = Duration::from_secs_f64(limits.wall_time);
+        let mut quota_dir = match workspace.container {
+            Some(container) => QuotaDir::Container(container.to_string()),
+            None => QuotaDir::Host(workspace.dir.to_path_buf()),
+        };
+        if let Some(sandbox_box) = workspace.sandbox_box {
+            // isolate enforces the wall time itself and says so in the meta file; this is only a backstop
+            timeout_duration += KILL_GRACE_PERIOD;
+            quota_dir = QuotaDir::Host(sandbox_box.dir().to_path_buf());
+        }
+        let quota_dir = workspace.enforce_quota.then_some(quota_dir);
+        // Should the timer below not end the wait, the watchdog kills the program a grace period later
+        let watch = self.watchdog.watch(&child, start_time + timeout_duration);
+        let max_output = self.output_max_bytes.load(Ordering::SeqCst);
+        // Only a native program is the engine's own child; docker and isolate stand between it and the rest
+        let trace = workspace.container.is_none() && workspace.sandbox_box.is_none();
+        let mut wait_handle = tokio::task::spawn_blocking(move || wait_with_metrics(child, max_output, trace));
+        
+        let outcome = tokio::select! {
+            result = &mut wait_handle => WaitOutcome::Exited(result.map_err(std::io::Error::other).and_then(|r| r).map(Box::new)),
+            _ = tokio::time::sleep(timeout_duration) => WaitOutcome::TimedOut,
+            usage = watch_workdir_quota(quota_dir, limits.workdir_quota) => WaitOutcome::QuotaExceeded(usage),
+        };
+        
+        let execution_time = start_time.elapsed().as_secs_f64();
+        
+        if let Some(container) = workspace.container {
+            // Killing the docker client below would leave the program running in the container
+            if matches!(outcome, WaitOutcome::TimedOut | WaitOutcome::QuotaExceeded(_)) {
+                if let Err(err) = docker::kill(container).await {
+                    warn!("Failed to kill processes in container {}: {}", container, err);
+                }
+            }
+            if let Err(err) = docker::copy_out(container, workspace.dir).await {
+                warn!("Failed to copy results out of container {}: {}", container, err);
+            }
+        }
+        if let Some(sandbox_box) = workspace.sandbox_box {
+            if let Err(err) = isolate::copy_out(sandbox_box, workspace.dir) {
+                warn!("Failed to copy results out of the isolate box: {}", err);
+            }
+        }
+        
+        match outcome {
+            WaitOutcome::Exited(Ok(exited)) if watch.fired() => {
+                warn!("Process {} was stopped by the watchdog, not the wall time limit", pid);
+                let metrics = if workspace.container.is_none() && meta.is_none() { exited.metrics } else { ExecutionMetrics::default() };
+                Ok(time_limit_exceeded(execution_time, metrics, started_at))
+            }
+            WaitOutcome::Exited(Ok(exited)) if meta.is_some() => {
+                // The rusage would be isolate's own; the meta file describes the program
+                let meta = meta.as_ref().map(|meta| isolate::Meta::read(meta.path())).transpose()?.unwrap_or_default();
+                isolate_result(meta, exited.output, exited.truncated, limits, started_at, execution_time)
+            }
+            WaitOutcome::Exited(Ok(exited)) => {
+                // The docker CLI's usage says nothing about the program in the container
+                let metrics = match workspace.container {
+                    Some(_) => ExecutionMetrics { wall_time: Some(execution_time), ..Default::default() },
+                    None => ExecutionMetrics { wall_time: Some(execution_time), ..exited.metrics },
+                };
+                Ok(exited_result(Exited { metrics, ..*exited }, limits, started_at, execution_time))
+            }
+            WaitOutcome::Exited(Err(e)) => Err(anyhow!("Process execution failed: {}", e)),
+            WaitOutcome::TimedOut => {
+                // Timeout occurred - kill the process, and anything holding its pipes, so nothing outlives the job
+                watch.kill();
+                let metrics = match wait_killed(watch, wait_handle).await {
+                    Some(exited) if workspace.container.is_none() && meta.is_none() => exited.metrics,
+                    _ => ExecutionMetrics::default(),
+                };
+                Ok(time_limit_exceeded(execution_time, metrics, started_at))
+            }
+            WaitOutcome::QuotaExceeded(usage) => {
+                warn!("Working directory grew by {} bytes, killing process {}", usage, pid);
+                watch.kill();
+                
+                // Keep whatever output was produced before the kill
+                let (stdout, metrics, truncated) = match wait_killed(watch, wait_handle).await {
+                    Some(exited) if workspace.container.is_none() && meta.is_none() => (exited.output.stdout, exited.metrics, exited.truncated),
+                    Some(exited) => (exited.output.stdout, ExecutionMetrics::default(), exited.truncated),
+                    _ => (Vec::new(), ExecutionMetrics::default(), Truncated::default()),
+                };
+                
+                Ok(CommandResult {
+                    stdout,
+                    stderr: b"Disk quota exceeded".to_vec(),
+                    exit_code: -1,
+                    signal: Some("SIGKILL".to_string()),
+                    execution_time,
+                    memory_usage: 0,
+                    timed_out: false,
+                    memory_exceeded: false,
+                    output_exceeded: true,
+                    failure_reason: Some(format!(
+                        "working directory quota of {} bytes exceeded ({} bytes written, detected by disk usage sampling)",
+                        limits.workdir_quota, usage
+                    )),
+                    truncated: Truncated { stderr: false, ..truncated },
+                    metrics: ExecutionMetrics { wall_time: Some(execution_time), ..metrics },
+                    started_at,
+                })
+            }
+        }
+    }
+    
+    /// Write and compile the interactor of an interactive run in a directory of its own, once for all of the job's runs
+    /// An interactor that does not compile fails the job as an internal error: the problem is broken, not the submission
+    async fn prepare_interactor(
+        &self,
+        request: &ExecutionRequest,
+        interactor: &Interactor,
+        limits: &ResourceLimits,
+        options: &ExecutionOptions,
+    ) -> Result<std::result::Result<BuiltInteractor, Box<ExecutionResult>>> {
+        let interactor_request = ExecutionRequest {
+            id: request.id.clone(),
+            language: interactor.language.clone(),
+            source_code: interactor.source_code.clone(),
+            base64_encoded: request.base64_encoded,
+            ..Default::default()
+        };
+        let lang_config = self.language_config(&interactor_request)
+            .ok_or_else(|| anyhow!("Unsupported interactor language: {}", interactor.language))?;
+        let entry_file = self.resolve_entry_file(&interactor_request, lang_config)?;
+        let lang_config = lang_config.with_entry_file(&entry_file);
+        
+        let dir = tempfile::Builder::new()
+            .prefix(&format!("{}.", sandbox::job_tag(&request.id)))
+            .tempdir_in(&self.temp_base)?;
+        let active = ActiveDirGuard::new(&self.active_dirs, dir.path());
+        fs::write(dir.path().join(&lang_config.source_file), &interactor.source_code)?;
+        // The test's input is for the interactor; the program only hears what the interactor tells it
+        if let Some(stdin) = request.stdin_bytes() {
+            fs::write(dir.path().join("input.txt"), stdin)?;
+        }
+        
+        let mut compile_command = None;
+        if let Some(compile_cmd) = self.resolve_compile_cmd(&interactor_request, &lang_config, dir.path())? {
+            let workspace = Workspace { job: &request.id, dir: dir.path(), container: None, sandbox_box: None, enforce_quota: true };
+            match self.compile(&interactor_request, &compile_cmd, &workspace, limits, options, None).await? {
+                Ok((_, command, _)) => compile_command = Some(ExecutedCommand { phase: CommandPhase::InteractorCompile, ..command }),
+                Err(mut failed) => {
+                    failed.status = ExecutionState::InternalError;
+                    failed.failure_reason = Some("interactor did not compile".to_string());
+                    failed.diagnostics.clear(); // they point into the interactor, not the submission
+                    for command in &mut failed.executed_commands {
+                        command.phase = CommandPhase::InteractorCompile;
+                    }
+                    return Ok(Err(failed));
+                }
+            }
+        }
+        
+        Ok(Ok(BuiltInteractor { dir, _active: active, run_cmd: lang_config.run_cmd, compile_command }))
+    }
+    
+    /// Native process setup: a cleared environment, mount isolation and the sandbox limits, then the user's variables
+    fn confine(&self, command: &mut Command, dir: &Path, limits: &ResourceLimits, env: Option<&HashMap<String, String>>) -> Result<()> {
+        // A fresh environment, so programs cannot read the engine's REDIS_URL, keys and the like
+        command.env_clear()
+            .env("PATH", &self.child_path)
+            .env("HOME", dir)
+            .env("LANG", CHILD_LANG);
+        #[cfg(unix)]
+        if let Some(isolation) = &self.isolation {
+            isolation.apply(command, dir)?;
+        }
+        let sandbox = Sandbox::new(limits.clone());
+        sandbox.apply_limits(command)?;
+        
+        // User environment is layered on top of whatever the sandbox left in place
+        if let Some(env) = env {
+            command.envs(env);
+        }
+        Ok(())
+    }
+    
+    /// Run the program and the interactor at once, each one's stdout piped into the other's stdin
+    /// Both get the same limits. The wall time covers the whole exchange, so a deadlock ends with both process groups killed.
+    async fn run_interactive(
+        &self,
+        program: Party<'_>,
+        interactor: Party<'_>,
+        limits: &ResourceLimits,
+    ) -> Result<(CommandResult, CommandResult)> {
+        let (interactor_stdin, program_stdout) = std::io::pipe()?;
+        let (program_stdin, interactor_stdout) = std::io::pipe()?;
+        let mut program_command = self.party_command(&program, limits)?;
+        program_command.stdin(program_stdin).stdout(program_stdout);
+        let mut interactor_command = self.party_command(&interactor, limits)?;
+        interactor_command.stdin(interactor_stdin).stdout(interactor_stdout);
+        
+        let start_time = Instant::now();
+        let started_at = Utc::now();
+        let program_child = program_command.spawn()?;
+        let interactor_child = match interactor_command.spawn() {
+            Ok(child) => child,
+            Err(err) => {
+                kill_process(program_child.id());
+                tokio::task::spawn_blocking(move || wait_with_metrics(program_child, 0, false));
+                return Err(anyhow!("Could not start the interactor: {}", err));
+            }
+        };
+        // The commands hold the engine's copies of the pipe ends; neither side sees EOF until they are closed
+        drop(program_command);
+        drop(interactor_command);
+        
+        let (program_pid, interactor_pid) = (program_child.id(), interactor_child.id());
+        let _running = [
+            RunningGuard::new(&self.running, program_pid, program.job),
+            RunningGuard::new(&self.running, interactor_pid, interactor.job),
+        ];
+        let deadline = start_time + Duration::from_secs_f64(limits.wall_time);
+        let watches = [self.watchdog.watch(&program_child, deadline), self.watchdog.watch(&interactor_child, deadline)];
+        let max_output = self.output_max_bytes.load(Ordering::SeqCst);
+        let waits = async {
+            tokio::join!(
+                tokio::task::spawn_blocking(move || wait_with_metrics(program_child, max_output, true)),
+                tokio::task::spawn_blocking(move || wait_with_metrics(interactor_child, max_output, false)),
+            )
+        };
+        tokio::pin!(waits);
+        
+        let mut exits = None;
+        let cut_short = tokio::select! {
+            both = &mut waits => {
+                exits = Some(both);
+                None
+            }
+            _ = tokio::time::sleep(Duration::from_secs_f64(limits.wall_time)) => Some(WaitOutcome::TimedOut),
+            usage = watch_workdir_quota(Some(QuotaDir::Host(program.dir.to_path_buf())), limits.workdir_quota) => Some(WaitOutcome::QuotaExceeded(usage)),
+        };
+        let (program_exit, interactor_exit) = match exits {
+            Some(both) => both,
+            None => {
+                watches.iter().for_each(Watch::kill);
+                timeout(KILL_GRACE_PERIOD, waits).await
+                    .map_err(|_| anyhow!("Interactive run did not stop after being killed"))?
+            }
+        };
+        let execution_time = start_time.elapsed().as_secs_f64();
+        let timed_out = matches!(cut_short, Some(WaitOutcome::TimedOut)) || watches.iter().any(Watch::fired);
+        
+        let finish = |exit: std::result::Result<std::io::Result<Exited>, tokio::task::JoinError>| {
+            let exited = exit.map_err(std::io::Error::other).and_then(|r| r)
+                .map_err(|e| anyhow!("Process execution failed: {}", e))?;
+            let metrics = ExecutionMetrics { wall_time: Some(execution_time), ..exited.metrics };
+            let mut result = exited_result(Exited { metrics, ..exited }, limits, started_at, execution_time);
+            // Only a side that was still running when time ran out was killed for it
+            if timed_out && result.signal.as_deref() == Some("SIGKILL") {
+                result.timed_out = true;
+            }
+            Ok::<_, anyhow::Error>(result)
+        };
+        let mut program_result = finish(program_exit)?;
+        let interactor_result = finish(interactor_exit)?;
+        if let Some(WaitOutcome::QuotaExceeded(usage)) = cut_short {
+            warn!("Working directory grew by {} bytes, killed interactive run", usage);
+            program_result.output_exceeded = true;
+            program_result.failure_reason = Some(format!(
+                "working directory quota of {} bytes exceeded ({} bytes written, detected by disk usage sampling)",
+                limits.workdir_quota, usage
+            ));
+        }
+        
+        Ok((program_result, interactor_result))
+    }
+    
+    /// Native command for one side of an interactive run, in its own process group with stderr captured
+    fn party_command(&self, party: &Party<'_>, limits: &ResourceLimits) -> Result<Command> {
+        let (program, args) = party.cmd.split_first().ok_or_else(|| anyhow!("Empty command"))?;
+        let mut command = Command::new(program);
+        command.args(args)
+            .current_dir(party.dir)
+            .stderr(Stdio::piped());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        self.confine(&mut command, party.dir, limits, party.env)?;
+        Ok(command)
+    }
+    
+    /// Determine the file name the submitted source is written to
+    fn resolve_entry_file(&self, request: &ExecutionRequest, lang_config: &LanguageConfig) -> Result<String> {
+        let default_extension = Path::new(&lang_config.source_file)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        
+        if let Some(name) = &request.source_file_name {
+            let path = Path::new(name);
+            let is_plain_name = !name.is_empty()
+                && !name.contains(['/', '\\'])
+                && path.file_name().and_then(|n| n.to_str()) == Some(name.as_str())
+                && name != "."
+                && name != "..";
+            
+            if !is_plain_name {
+                return Err(anyhow!("Invalid source_file_name '{}': must be a plain relative file name", name));
+            }
+            
+            if path.extension().and_then(|ext| ext.to_str()) != Some(default_extension) {
+                return Err(anyhow!(
+                    "Invalid source_file_name '{}': expected a .{} file for {}",
+                    name, default_extension, lang_config.name
+                ));
+            }
+            
+            return Ok(name.clone());
+        }
+        
+        // Java requires the file to be named after its public class
+        if default_extension == "java" {
+            if let Some(class_name) = detect_java_public_class(&request.source_code) {
+                debug!("Detected public Java class {}", class_name);
+                return Ok(format!("{}.java", class_name));
+            }
+        }
+        
+        Ok(lang_config.source_file.clone())
+    }
+    
+    /// Build the compile command for a submission, pulling in any extra
+    /// translation units that were extracted next to the entry file
+    fn resolve_compile_cmd(
+        &self,
+        request: &ExecutionRequest,
+        lang_config: &LanguageConfig,
+        working_dir: &Path,
+    ) -> Result<Option<Vec<String>>> {
+        // A provided Cargo.toml switches Rust submissions over to cargo
+        if lang_config.supports_cargo && working_dir.join("Cargo.toml").is_file() {
+            self.prepare_cargo_project(working_dir, &lang_config.source_file)?;
+            return Ok(Some(vec!["cargo".to_string(), "build".to_string(), "--release".to_string()]));
+        }
+        
+        let compile_cmd = match &lang_config.compile_cmd {
+            Some(cmd) => cmd,
+            None => return Ok(None),
+        };
+        
+        let mut resolved = Vec::with_capacity(compile_cmd.len());
+        for arg in compile_cmd {
+            match &lang_config.source_extension {
+                Some(extension) if *arg == lang_config.source_file => {
+                    resolved.extend(self.collect_sources(working_dir, &lang_config.source_file, extension)?);
+                }
+                _ => resolved.push(arg.clone()),
+            }
+        }
+        
+        // Compiler options go right after the compiler so trailing flags like -lm keep working
+        if let Some(options) = &request.compiler_options {
+            resolved.splice(1..1, options.split_whitespace().map(String::from));
+        }
+        
+        Ok(Some(resolved))
+    }
+    
+    /// List every source file with the given extension in the working directory,
+    /// entry file first and the rest in a stable order
+    fn collect_sources(&self, working_dir: &Path, entry_file: &str, extension: &str) -> Result<Vec<String>> {
+        let mut sources = Vec::new();
+        
+        for entry in fs::read_dir(working_dir)? {
+            let path = entry?.path();
+            if !path.is_file() || path.extension().and_then(|ext| ext.to_str()) != Some(extension) {
+                continue;
+            }
+            
+            if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                if name != entry_file {
+                    sources.push(name.to_string());
+                }
+            }
+        }
+        
+        sources.sort();
+        sources.insert(0, entry_file.to_string());
+        
+        debug!("Compiling sources: {:?}", sources);
+        Ok(sources)
+    }
+    
+    /// Move the entry file into src/ when the Cargo project doesn't provide one
+    fn prepare_cargo_project(&self, working_dir: &Path, entry_file: &str) -> Result<()> {
+        let src_dir = working_dir.join("src");
+        let cargo_main = src_dir.join("main.rs");
+        
+        if !cargo_main.exists() {
+            fs::create_dir_all(&src_dir)?;
+            fs::rename(working_dir.join(entry_file), &cargo_main)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Copy the binary produced by `cargo build --release` to the configured entry point
+    fn install_cargo_binary(&self, working_dir: &Path) -> Result<()> {
+        let release_dir = working_dir.join("target").join("release");
+        
+        for entry in fs::read_dir(&release_dir)? {
+            let path = entry?.path();
+            if path.is_file() && is_executable(&path) {
+                fs::copy(&path, working_dir.join("main"))?;
+                return Ok(());
+            }
+        }
+        
+        Err(anyhow!("cargo build produced no binary in {}", release_dir.display()))
+    }
+    
+    /// Collect files matching the artifact globs, inlining content until the size cap is hit
+    /// Files matching `excluded`, the sources and build products, are never collected.
+    fn collect_artifacts(&self, working_dir: &Path, patterns: &[String], excluded: &[glob::Pattern]) -> Result<Vec<Artifact>> {
+        use base64::{engine::general_purpose, Engine as _};
+        
+        let patterns = patterns.iter()
+            .map(|pattern| glob::Pattern::new(pattern)
+                .map_err(|e| anyhow!("Invalid artifact pattern '{}': {}", pattern, e)))
+            .collect::<Result<Vec<_>>>()?;
+        
+        let mut files = Vec::new();
+        collect_files(working_dir, working_dir, &mut files)?;
+        files.sort();
+        
+        let mut artifacts = Vec::new();
+        let mut remaining = self.artifact_max_bytes;
+        
+        for relative in files {
+            if artifacts.len() >= MAX_ARTIFACTS {
+                warn!("Artifact limit of {} reached, skipping the rest", MAX_ARTIFACTS);
+                break;
+            }
+            
+            let name = relative.to_string_lossy().to_string();
+            let wanted = patterns.iter().any(|pattern| pattern.matches_path(&relative));
+            if !wanted || excluded.iter().any(|pattern| pattern.matches_path(&relative)) {
+                continue;
+            }
+            
+            let path = working_dir.join(&relative);
+            let size = fs::metadata(&path)?.len();
+            
+            let (content_base64, truncated) = if size <= remaining {
+                remaining -= size;
+                (Some(general_purpose::STANDARD.encode(fs::read(&path)?)), false)
+            } else {
+                (None, true)
+            };
+            
+            debug!("Collected artifact {} ({} bytes)", name, size);
+            artifacts.push(Artifact { name, size, content_base64, truncated });
+        }
+        
+        Ok(artifacts)
+    }
+    
+    /// Extract additional files from base64 ZIP
+    fn extract_additional_files(&self, temp_path: &Path, base64_zip: &str) -> Result<u64> {
+        use base64::{engine::general_purpose, Engine as _};
+        use std::io::Cursor;
+        use zip::ZipArchive;
+        
+        debug!("Extracting additional files from ZIP");
         
         // Decode base64
         let zip_data = general_purpose::STANDARD
@@ -372,6 +2018,7 @@ impl CodeExecutor {
             .map_err(|e| anyhow!("Failed to open ZIP archive: {}", e))?;
         
         // Extract all files
+        let mut extracted_bytes = 0;
         for i in 0..archive.len() {
             let mut file = archive.by_index(i)
                 .map_err(|e| anyhow!("Failed to read ZIP entry {}: {}", i, e))?;
@@ -392,65 +2039,115 @@ impl CodeExecutor {
             let mut extracted_file = fs::File::create(&file_path)
                 .map_err(|e| anyhow!("Failed to create file {}: {}", file_path.display(), e))?;
             
-            std::io::copy(&mut file, &mut extracted_file)
+            extracted_bytes += std::io::copy(&mut file, &mut extracted_file)
                 .map_err(|e| anyhow!("Failed to extract file {}: {}", file_path.display(), e))?;
             
             debug!("Extracted file: {}", file_path.display());
         }
         
-        info!("Successfully extracted {} files from ZIP", archive.len());
-        Ok(())
+        info!("Successfully extracted {} files ({} bytes) from ZIP", archive.len(), extracted_bytes);
+        Ok(extracted_bytes)
     }
     
     /// Aggregate results from multiple runs
     fn aggregate_results(
-        &self,
         id: &str,
         results: Vec<ExecutionResult>,
         created_at: chrono::DateTime<Utc>,
+        base64_encoded: bool,
     ) -> ExecutionResult {
         if results.is_empty() {
             return ExecutionResult {
                 id: id.to_string(),
                 status: ExecutionState::InternalError,
-                stdout: Some("No results".to_string()),
-                stderr: Some("No execution results".to_string()),
+                stdout: Some(encode_output(b"No results", base64_encoded)),
+                stderr: Some(encode_output(b"No execution results", base64_encoded)),
                 compile_output: None,
+                diagnostics: Vec::new(),
+                stdout_bytes: None,
+                stdout_url: None,
+                stderr_bytes: None,
+                stderr_url: None,
+                stdout_encoding: Some(OutputEncoding::Utf8),
+                stderr_encoding: Some(OutputEncoding::Utf8),
+                stdout_truncated: false,
+                stderr_truncated: false,
                 exit_code: Some(-1),
                 signal: None,
+                exit_description: None,
                 time: Some(0.0),
                 memory: Some(0),
+                metrics: None,
+                artifacts: None,
+                workdir_listing: None,
+                executed_commands: Vec::new(),
+                failure_reason: None,
+                compile_cache_hit: false,
+                failed_run_indices: Vec::new(),
+                warnings: Vec::new(),
+                attempts: 1,
+                limits: None,
+                options: None,
+                metadata: None, // echoed from the request by the engine
+                runner: None, // stamped by the engine
+                started_at: None,
                 created_at,
                 finished_at: Some(Utc::now()),
+                timeline: Vec::new(), // filled in by the engine
+                queue_wait_ms: None,
+                overhead_ms: None,
+                phases: None,
+                setup_bytes: None,
+                interactor: None,
+                test_cases: Vec::new(),
+                test_summary: None,
             };
         }
         
+        let failed_run_indices: Vec<u32> = results
+            .iter()
+            .enumerate()
+            .filter(|(_, result)| result.status != ExecutionState::Completed)
+            .map(|(index, _)| index as u32)
+            .collect();
+        
         if results.len() == 1 {
-            return results.into_iter().next().unwrap();
+            let mut result = results.into_iter().next().unwrap();
+            result.failed_run_indices = failed_run_indices;
+            return result;
         }
         
-        // For multiple runs, aggregate the results
-        let mut combined_stdout = String::new();
-        let mut combined_stderr = String::new();
+        // The worst run decides status, exit code and signal; ties go to the earliest run
+        let worst = results
+            .iter()
+            .rev()
+            .max_by_key(|result| result.status.severity())
+            .unwrap();
+        
+        // For multiple runs, aggregate the raw bytes so encoded outputs stay valid
+        let mut combined_stdout = Vec::new();
+        let mut combined_stderr = Vec::new();
         let mut total_time = 0.0;
         let mut max_memory = 0;
-        let mut final_status = ExecutionState::Completed;
-        let mut final_exit_code = 0;
+        let metrics = results.iter()
+            .map(|result| result.metrics.clone())
+            .reduce(|total, metrics| total.zip(metrics).map(|(total, metrics)| total.combine(&metrics)))
+            .flatten();
         
         for (i, result) in results.iter().enumerate() {
             if i > 0 {
-                combined_stdout.push_str("\n--- Run ");
-                combined_stdout.push_str(&(i + 1).to_string());
-                combined_stdout.push_str(" ---\n");
+                combined_stdout.extend_from_slice(format!("\n--- Run {} ---\n", i + 1).as_bytes());
             }
             
             if let Some(stdout) = &result.stdout {
-                combined_stdout.push_str(stdout);
+                combined_stdout.extend(decode_output(stdout, base64_encoded));
             }
             
             if let Some(stderr) = &result.stderr {
                 if !stderr.is_empty() {
-                    combined_stderr.push_str(&format!("Run {}: {}\n", i + 1, stderr));
+                    combined_stderr.extend_from_slice(format!("Run {}: ", i + 1).as_bytes());
+                    combined_stderr.extend(decode_output(stderr, base64_encoded));
+                    combined_stderr.push(b'\n');
                 }
             }
             
@@ -461,41 +2158,603 @@ impl CodeExecutor {
             if let Some(memory) = result.memory {
                 max_memory = max_memory.max(memory);
             }
-            
-            // If any run failed, mark the overall status as failed
-            if result.status != ExecutionState::Completed {
-                final_status = result.status.clone();
-                final_exit_code = result.exit_code.unwrap_or(-1);
-            }
         }
-        
-        ExecutionResult {
+        
+        ExecutionResult {
+            id: id.to_string(),
+            status: worst.status.clone(),
+            stdout: Some(encode_output(&combined_stdout, base64_encoded)),
+            // Runs with stderr merged into stdout have none to combine
+            stderr: results[0].stderr.as_ref().map(|_| encode_output(&combined_stderr, base64_encoded)),
+            compile_output: results[0].compile_output.clone(),
+            diagnostics: results[0].diagnostics.clone(),
+            stdout_bytes: None,
+            stdout_url: None,
+            stderr_bytes: None,
+            stderr_url: None,
+            stdout_encoding: results.iter().filter_map(|result| result.stdout_encoding).max(),
+            stderr_encoding: results.iter().filter_map(|result| result.stderr_encoding).max(),
+            stdout_truncated: results.iter().any(|result| result.stdout_truncated),
+            stderr_truncated: results.iter().any(|result| result.stderr_truncated),
+            exit_code: worst.exit_code,
+            signal: worst.signal.clone(),
+            exit_description: worst.exit_description.clone(),
+            time: Some(total_time),
+            memory: Some(max_memory),
+            metrics,
+            artifacts: results.last().and_then(|result| result.artifacts.clone()),
+            workdir_listing: worst.workdir_listing.clone(),
+            // Runs share the one build, so its compilers are listed once
+            executed_commands: results.iter()
+                .enumerate()
+                .flat_map(|(index, result)| {
+                    result.executed_commands.iter().filter(move |command| {
+                        index == 0 || !matches!(command.phase, CommandPhase::Compile | CommandPhase::InteractorCompile)
+                    })
+                })
+                .cloned()
+                .collect(),
+            failure_reason: worst.failure_reason.clone(),
+            compile_cache_hit: results[0].compile_cache_hit,
+            failed_run_indices,
+            warnings: results[0].warnings.clone(),
+            attempts: 1,
+            limits: results[0].limits.clone(),
+            options: results[0].options.clone(),
+            metadata: None, // echoed from the request by the engine
+            runner: None, // stamped by the engine
+            started_at: results[0].started_at,
+            created_at,
+            finished_at: Some(Utc::now()),
+            timeline: Vec::new(), // filled in by the engine
+            queue_wait_ms: None,
+            overhead_ms: None,
+            phases: None,
+            setup_bytes: results[0].setup_bytes,
+            interactor: worst.interactor.clone(),
+            test_cases: Vec::new(),
+            test_summary: None,
+        }
+    }
+}
+
+/// Marks a working directory as in use so the stale-dir sweep leaves it alone
+struct ActiveDirGuard {
+    active_dirs: Arc<Mutex<HashSet<PathBuf>>>,
+    path: PathBuf,
+}
+
+impl ActiveDirGuard {
+    fn new(active_dirs: &Arc<Mutex<HashSet<PathBuf>>>, path: &Path) -> Self {
+        active_dirs.lock().unwrap().insert(path.to_path_buf());
+        Self {
+            active_dirs: Arc::clone(active_dirs),
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Drop for ActiveDirGuard {
+    fn drop(&mut self) {
+        self.active_dirs.lock().unwrap().remove(&self.path);
+    }
+}
+
+/// Registers a spawned process so shutdown and cancels can find it
+struct RunningGuard {
+    running: Arc<Mutex<HashMap<u32, String>>>,
+    pid: u32,
+}
+
+impl RunningGuard {
+    fn new(running: &Arc<Mutex<HashMap<u32, String>>>, pid: u32, job: &str) -> Self {
+        running.lock().unwrap().insert(pid, job.to_string());
+        Self {
+            running: Arc::clone(running),
+            pid,
+        }
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.running.lock().unwrap().remove(&self.pid);
+    }
+}
+
+/// Registers a job being executed so it can be cancelled between runs
+struct ExecutingGuard {
+    executing: Arc<Mutex<HashMap<String, bool>>>,
+    id: String,
+}
+
+impl ExecutingGuard {
+    fn new(executing: &Arc<Mutex<HashMap<String, bool>>>, id: &str) -> Self {
+        executing.lock().unwrap().insert(id.to_string(), false);
+        Self {
+            executing: Arc::clone(executing),
             id: id.to_string(),
-            status: final_status,
-            stdout: Some(combined_stdout),
-            stderr: Some(combined_stderr),
-            compile_output: results[0].compile_output.clone(),
-            exit_code: Some(final_exit_code),
-            signal: None,
-            time: Some(total_time),
-            memory: Some(max_memory),
-            created_at,
-            finished_at: Some(Utc::now()),
         }
     }
 }
 
+impl Drop for ExecutingGuard {
+    fn drop(&mut self) {
+        self.executing.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Resolve once the command has written more than the quota into the working directory, yielding the bytes written
+///
+/// What was there when it started, such as the compiled program, does not count.
+async fn watch_workdir_quota(dir: Option<QuotaDir>, quota: u64) -> u64 {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => return std::future::pending().await,
+    };
+    
+    let baseline = dir.usage().await;
+    loop {
+        tokio::time::sleep(QUOTA_SAMPLE_INTERVAL).await;
+        
+        let usage = dir.usage().await.saturating_sub(baseline);
+        if usage > quota {
+            return usage;
+        }
+    }
+}
+
+/// Working directory whose size the quota is checked against
+enum QuotaDir {
+    /// A directory on the host, including an isolate box
+    Host(PathBuf),
+    /// A container, by everything it has written
+    Container(String),
+}
+
+impl QuotaDir {
+    /// Bytes in use, 0 when they could not be measured
+    async fn usage(&self) -> u64 {
+        match self {
+            QuotaDir::Host(dir) => {
+                let dir = dir.clone();
+                tokio::task::spawn_blocking(move || disk_usage(&dir)).await.unwrap_or(0)
+            }
+            QuotaDir::Container(container) => docker::disk_usage(container).await.unwrap_or_else(|err| {
+                debug!("Could not measure the working directory of container {}: {}", container, err);
+                0
+            }),
+        }
+    }
+}
+
+/// Result of a job that failed before anything was run, such as when its inputs could not be downloaded
+fn failed_before_start(request: &ExecutionRequest, message: String, created_at: chrono::DateTime<Utc>) -> ExecutionResult {
+    ExecutionResult {
+        id: request.id.clone(),
+        status: ExecutionState::InternalError,
+        stdout: None,
+        stderr: Some(encode_output(message.as_bytes(), request.base64_encoded.unwrap_or(false))),
+        compile_output: None,
+        diagnostics: Vec::new(),
+        stdout_bytes: None,
+        stdout_url: None,
+        stderr_bytes: None,
+        stderr_url: None,
+        stdout_encoding: None,
+        stderr_encoding: Some(OutputEncoding::Utf8),
+        stdout_truncated: false,
+        stderr_truncated: false,
+        exit_code: None,
+        signal: None,
+        exit_description: None,
+        time: None,
+        memory: None,
+        metrics: None,
+        artifacts: None,
+        workdir_listing: None,
+        executed_commands: Vec::new(),
+        failure_reason: Some(message),
+        compile_cache_hit: false,
+        failed_run_indices: Vec::new(),
+        warnings: Vec::new(),
+        attempts: 1,
+        limits: None,
+        options: None,
+        metadata: None, // echoed from the request by the engine
+        runner: None, // stamped by the engine
+        started_at: None,
+        created_at,
+        finished_at: Some(Utc::now()),
+        timeline: Vec::new(), // filled in by the engine
+        queue_wait_ms: None,
+        overhead_ms: None,
+        phases: None,
+        setup_bytes: None,
+        interactor: None,
+        test_cases: Vec::new(),
+        test_summary: None,
+    }
+}
+
+/// Bytes allocated on disk for everything under `path`, like `du`
+fn disk_usage(path: &Path) -> u64 {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    
+    entries.flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => disk_usage(&entry.path()),
+            Ok(meta) => allocated_size(&meta),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(unix)]
+fn allocated_size(meta: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    
+    // Count allocated blocks so sparse files can't dodge the quota
+    (meta.blocks() * 512).max(meta.len())
+}
+
+#[cfg(not(unix))]
+fn allocated_size(meta: &fs::Metadata) -> u64 {
+    meta.len()
+}
+
+/// How waiting on a child process ended
+enum WaitOutcome {
+    Exited(std::io::Result<Box<Exited>>),
+    TimedOut,
+    QuotaExceeded(u64),
+}
+
 /// Result of running a command
 #[derive(Debug)]
 struct CommandResult {
-    stdout: String,
-    stderr: String,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
     exit_code: i32,
     signal: Option<String>,
     execution_time: f64,
     memory_usage: u64,
     timed_out: bool,
     memory_exceeded: bool,
+    output_exceeded: bool, // the file size limit or working directory quota was hit
+    failure_reason: Option<String>, // which enforcement mechanism stopped the process, or why it crashed
+    truncated: Truncated, // stdout or stderr went over OUTPUT_MAX_BYTES
+    metrics: ExecutionMetrics,
+    started_at: chrono::DateTime<Utc>,
+}
+
+impl CommandResult {
+    /// What ran, for the result's executed_commands; `argv` is the command as passed to run_command_with_limits
+    fn executed(&self, phase: CommandPhase, argv: &[String]) -> ExecutedCommand {
+        ExecutedCommand {
+            phase,
+            argv: argv.to_vec(),
+            duration_ms: (self.execution_time * 1000.0).round() as u64,
+            exit_code: self.exit_code,
+        }
+    }
+}
+
+/// Give a killed program the grace period to be reaped. One whose pipes are still held after it
+/// stays registered with the watchdog, which keeps killing until its wait ends
+async fn wait_killed(
+    watch: Watch,
+    mut wait_handle: tokio::task::JoinHandle<std::io::Result<Exited>>,
+) -> Option<Exited> {
+    match timeout(KILL_GRACE_PERIOD, &mut wait_handle).await {
+        Ok(exited) => exited.ok()?.ok(),
+        Err(_) => {
+            tokio::spawn(async move {
+                let _watch = watch;
+                let _ = wait_handle.await;
+            });
+            None
+        }
+    }
+}
+
+/// Result of a command killed for running out of wall time
+fn time_limit_exceeded(execution_time: f64, metrics: ExecutionMetrics, started_at: chrono::DateTime<Utc>) -> CommandResult {
+    CommandResult {
+        stdout: Vec::new(),
+        stderr: b"Time limit exceeded".to_vec(),
+        exit_code: -1,
+        signal: Some("SIGKILL".to_string()),
+        execution_time,
+        memory_usage: 0,
+        timed_out: true,
+        memory_exceeded: false,
+        output_exceeded: false,
+        failure_reason: None,
+        truncated: Truncated::default(),
+        metrics: ExecutionMetrics { wall_time: Some(execution_time), ..metrics },
+        started_at,
+    }
+}
+
+/// Result of a native or docker command that exited on its own
+fn exited_result(exited: Exited, limits: &ResourceLimits, started_at: chrono::DateTime<Utc>, execution_time: f64) -> CommandResult {
+    let Exited { output, metrics, truncated, fault } = exited;
+    let signal = termination_signal(&output.status);
+    let output_exceeded = signal.as_deref() == Some("SIGXFSZ");
+    let failure_reason = if output_exceeded {
+        Some(format!("file size limit of {} bytes exceeded (RLIMIT_FSIZE)", limits.file_size))
+    } else if signal.as_deref() == Some("SIGSEGV") {
+        fault.and_then(|fault| stack_fault_reason(&fault, limits.stack_limit))
+    } else {
+        None
+    };
+    
+    CommandResult {
+        stdout: output.stdout,
+        stderr: output.stderr,
+        exit_code: output.status.code().unwrap_or(-1),
+        signal,
+        execution_time,
+        memory_usage: metrics.memory_peak.unwrap_or(0),
+        timed_out: false,
+        memory_exceeded: false,
+        output_exceeded,
+        failure_reason,
+        truncated,
+        metrics,
+        started_at,
+    }
+}
+
+/// Result of a command run by isolate, from its meta file rather than isolate's own exit status
+fn isolate_result(
+    meta: isolate::Meta,
+    output: std::process::Output,
+    truncated: Truncated,
+    limits: &ResourceLimits,
+    started_at: chrono::DateTime<Utc>,
+    execution_time: f64,
+) -> Result<CommandResult> {
+    if meta.status.as_deref() == Some("XX") {
+        return Err(anyhow!("isolate failed: {}", meta.message.unwrap_or_default()));
+    }
+    
+    let failed = meta.status.is_some();
+    let signal = meta.exit_signal.map(signal_name);
+    let output_exceeded = signal.as_deref() == Some("SIGXFSZ");
+    let failure_reason = if output_exceeded {
+        Some(format!("file size limit of {} bytes exceeded (isolate --fsize)", limits.file_size))
+    } else if meta.oom_killed {
+        Some(format!("memory limit of {} bytes exceeded (isolate control group)", limits.memory))
+    } else if failed {
+        meta.message.clone()
+    } else {
+        None
+    };
+    
+    // isolate reports no page faults, I/O or user and system time separately
+    let metrics = ExecutionMetrics {
+        cpu_time: Some(meta.time),
+        memory_peak: Some(meta.memory),
+        wall_time: Some(meta.wall_time.unwrap_or(execution_time)),
+        voluntary_context_switches: meta.voluntary_context_switches,
+        involuntary_context_switches: meta.involuntary_context_switches,
+        ..Default::default()
+    };
+    
+    Ok(CommandResult {
+        stdout: output.stdout,
+        stderr: output.stderr,
+        exit_code: meta.exit_code.unwrap_or(-1),
+        signal,
+        execution_time: meta.time,
+        memory_usage: meta.memory,
+        timed_out: meta.timed_out(),
+        memory_exceeded: meta.oom_killed,
+        output_exceeded,
+        failure_reason,
+        truncated,
+        metrics,
+        started_at,
+    })
+}
+
+/// Verdict of a run from how its command ended
+fn run_status(run: &CommandResult) -> ExecutionState {
+    if run.output_exceeded {
+        ExecutionState::OutputLimitExceeded
+    } else if run.exit_code == 0 {
+        ExecutionState::Completed
+    } else if run.timed_out {
+        ExecutionState::TimeLimitExceeded
+    } else if run.memory_exceeded {
+        ExecutionState::MemoryLimitExceeded
+    } else {
+        ExecutionState::RuntimeError
+    }
+}
+
+/// Whether a program printed the expected output, ignoring trailing whitespace as the Judge0 API does
+fn output_matches(stdout: &[u8], expected: &str) -> bool {
+    String::from_utf8_lossy(stdout).trim_end() == expected.trim_end()
+}
+
+/// Diagnostics in a compiler's output, with file names relative to the working directory
+fn compile_diagnostics(compile_cmd: &[String], output: &[u8], working_dir: &Path) -> Vec<Diagnostic> {
+    diagnostics::parse(&compile_cmd[0], &String::from_utf8_lossy(output), working_dir)
+}
+
+/// The language's run command with the user-supplied program arguments appended
+fn run_command(request: &ExecutionRequest, lang_config: &LanguageConfig) -> Vec<String> {
+    let mut run_cmd = lang_config.run_cmd.clone();
+    if let Some(args) = &request.command_line_arguments {
+        run_cmd.extend(args.split_whitespace().map(String::from));
+    }
+    run_cmd
+}
+
+/// Write a run's stdin to `input.txt`, returning its path; empty input gets no file
+fn write_stdin(dir: &Path, stdin: Option<&[u8]>) -> Result<Option<PathBuf>> {
+    match stdin {
+        Some(stdin) if !stdin.is_empty() => {
+            let stdin_path = dir.join("input.txt");
+            fs::write(&stdin_path, stdin)?;
+            Ok(Some(stdin_path))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Explain a SIGSEGV that faulted where the stack, grown to its limit, would end
+/// The kernel refuses to grow the stack past RLIMIT_STACK, so the faulting access lies just below it
+fn stack_fault_reason(fault: &SegvFault, stack_limit: u64) -> Option<String> {
+    let (start, end) = fault.stack?;
+    let depth = end.checked_sub(fault.address)?;
+    let near_limit = depth + STACK_FAULT_SLACK >= stack_limit && depth <= stack_limit.saturating_add(STACK_FAULT_SLACK);
+    (fault.address < start && near_limit).then(|| format!("stack overflow (stack limit {} bytes)", stack_limit))
+}
+
+/// Read a failed run the way its language's runtime reports trouble
+/// Only the runtime's own stack overflow report counts, at the start of a line of its error output; and only a
+/// runtime that ignores SIGXFSZ, failing the write with EFBIG instead, gets "File too large" taken as the file size limit
+fn explain_runtime_failure(run: &mut CommandResult, lang_config: &LanguageConfig, limits: &ResourceLimits, options: &ExecutionOptions) {
+    if run.exit_code == 0 || run.timed_out || run.memory_exceeded || run.output_exceeded {
+        return;
+    }
+    
+    let errors = String::from_utf8_lossy(if options.redirect_stderr_to_stdout { &run.stdout } else { &run.stderr });
+    if lang_config.ignores_sigxfsz && errors.contains(FILE_TOO_LARGE_MESSAGE) {
+        run.output_exceeded = true;
+        run.failure_reason = Some(format!("file size limit of {} bytes exceeded (RLIMIT_FSIZE)", limits.file_size));
+    } else if errors.lines().any(|line| lang_config.stack_overflow_messages.iter().any(|message| line.starts_with(message))) {
+        run.failure_reason = Some(format!("stack overflow (stack limit {} bytes)", limits.stack_limit));
+    }
+}
+
+/// Where a job's commands run: the host directory, optionally mirrored into a container or isolate box
+#[derive(Clone, Copy)]
+struct Workspace<'a> {
+    job: &'a str,
+    dir: &'a Path,
+    container: Option<&'a str>,
+    sandbox_box: Option<&'a BoxLease>,
+    enforce_quota: bool, // off while compiling: the quota is on what the program writes
+}
+
+/// Working directory with the program built, and the sandbox it was built in
+struct Build {
+    temp_dir: TempDir,
+    _active: ActiveDirGuard,
+    lease: Option<ContainerLease>,
+    sandbox_box: Option<BoxLease>,
+    compile_output: Option<Vec<u8>>,
+    compile_command: Option<ExecutedCommand>, // none for interpreted languages and compile cache hits
+    compile_started_at: Option<chrono::DateTime<Utc>>,
+    diagnostics: Vec<Diagnostic>,
+    compile_cache_hit: bool,
+    setup_bytes: u64, // written into the working directory before compiling
+    interactor: Option<Arc<BuiltInteractor>>, // shared by copies of the build
+}
+
+/// The compiled judge of an interactive problem, with the test's input beside it
+struct BuiltInteractor {
+    dir: TempDir,
+    _active: ActiveDirGuard,
+    run_cmd: Vec<String>,
+    compile_command: Option<ExecutedCommand>,
+}
+
+impl Build {
+    fn workspace<'a>(&'a self, job: &'a str) -> Workspace<'a> {
+        Workspace {
+            job,
+            dir: self.temp_dir.path(),
+            container: self.lease.as_ref().map(|lease| lease.id.as_str()),
+            sandbox_box: self.sandbox_box.as_ref(),
+            enforce_quota: true,
+        }
+    }
+}
+
+/// One side of an interactive run
+struct Party<'a> {
+    job: &'a str,
+    cmd: &'a [String],
+    dir: &'a Path,
+    env: Option<&'a HashMap<String, String>>,
+}
+
+/// Where a run reports its phase changes and stamps the stages it reaches
+struct RunHooks<'a> {
+    progress: &'a ProgressSender,
+    running: ExecutionProgress, // sent once the program starts
+    timeline: &'a mut Timeline,
+}
+
+/// Claimed container that is destroyed once the run is over
+struct ContainerLease {
+    pool: ContainerPool,
+    id: String,
+}
+
+impl Drop for ContainerLease {
+    fn drop(&mut self) {
+        self.pool.release(std::mem::take(&mut self.id));
+    }
+}
+
+/// Build a `docker exec` invocation of the command inside the container, under `nice` when one is set
+/// docker keeps stdout and stderr apart on the way out, so merged streams are joined inside the container
+fn docker_exec_command(
+    container: &str,
+    cmd_args: &[String],
+    env: Option<&HashMap<String, String>>,
+    nice: Option<i32>,
+    merge_stderr: bool,
+) -> Command {
+    let mut command = Command::new("docker");
+    command.args(["exec", "-i", "-w", CONTAINER_WORKDIR]);
+    command.args(["-e", &format!("HOME={}", CONTAINER_WORKDIR), "-e", &format!("LANG={}", CHILD_LANG)]);
+    
+    if let Some(env) = env {
+        for (key, value) in env {
+            command.arg("-e").arg(format!("{}={}", key, value));
+        }
+    }
+    
+    command.arg(container);
+    if merge_stderr {
+        command.args(["sh", "-c", "exec \"$@\" 2>&1", "sh"]);
+    }
+    if let Some(nice) = nice {
+        command.args(["nice", "-n", &nice.to_string()]);
+    }
+    command.args(cmd_args);
+    command
+}
+
+/// A language name as clients may spell it: trimmed and lowercased
+fn normalize_language(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Levenshtein distance: characters inserted, removed or replaced to turn one string into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(ca != *cb);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
 }
 
 /// Language configuration
@@ -506,5 +2765,473 @@ struct LanguageConfig {
     source_file: String,
     compile_cmd: Option<Vec<String>>,
     run_cmd: Vec<String>,
+    source_extension: Option<String>, // extra sources with this extension are compiled too
+    supports_cargo: bool,             // a provided Cargo.toml switches to cargo build
     docker_image: Option<String>,
-}
\ No newline at end of file
+    limits: LanguageLimits,
+    aliases: Vec<String>, // other names the language is accepted under
+    stack_overflow_messages: &'static [&'static str], // lines the runtime starts its report with when the call stack runs out
+    ignores_sigxfsz: bool, // writes past the file size limit fail with EFBIG instead of killing the program
+}
+
+impl LanguageConfig {
+    /// Copy of this config with the entry file renamed throughout the commands
+    fn with_entry_file(&self, entry_file: &str) -> LanguageConfig {
+        if entry_file == self.source_file {
+            return self.clone();
+        }
+        
+        let old_stem = file_stem(&self.source_file);
+        let new_stem = file_stem(entry_file);
+        
+        let mut config = self.clone();
+        config.source_file = entry_file.to_string();
+        config.compile_cmd = self.compile_cmd.as_ref().map(|cmd| {
+            cmd.iter()
+                .map(|arg| if *arg == self.source_file { entry_file.to_string() } else { arg.clone() })
+                .collect()
+        });
+        // Run commands refer to either the file (python3 main.py) or the class (java Main)
+        config.run_cmd = self.run_cmd.iter()
+            .map(|arg| {
+                if *arg == self.source_file {
+                    entry_file.to_string()
+                } else if arg == old_stem {
+                    new_stem.to_string()
+                } else {
+                    arg.clone()
+                }
+            })
+            .collect();
+        config
+    }
+}
+
+/// Recursively list regular files under `dir` as paths relative to `root`, ignoring symlinks
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        
+        if file_type.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if file_type.is_file() {
+            if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Files in the working directory after a runtime or compilation error, sorted and without the
+/// binary, for requests that ask with `list_workdir`
+fn list_workdir(
+    request: &ExecutionRequest,
+    lang_config: &LanguageConfig,
+    working_dir: &Path,
+    status: &ExecutionState,
+) -> Option<Vec<WorkdirEntry>> {
+    let failed = matches!(status, ExecutionState::RuntimeError | ExecutionState::CompilationError);
+    if !failed || !request.list_workdir.unwrap_or(false) {
+        return None;
+    }
+    let mut entries = Vec::new();
+    if let Err(err) = workdir::list_tree(working_dir, working_dir, &mut entries) {
+        warn!("Could not list the working directory of {}: {}", request.id, err);
+        return None;
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    
+    let binary = lang_config.run_cmd[0].strip_prefix("./");
+    entries.retain(|entry| Some(entry.path.as_str()) != binary);
+    entries.truncate(MAX_WORKDIR_ENTRIES);
+    Some(entries)
+}
+
+/// File name without its extension
+fn file_stem(file_name: &str) -> &str {
+    Path::new(file_name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(file_name)
+}
+
+/// Find the name of the top-level public class (or interface/enum/record) in Java source
+/// Public types nested in another one don't name the file, so only declarations outside any braces count.
+fn detect_java_public_class(source: &str) -> Option<String> {
+    let tokens = java_tokens(source);
+    let mut iter = tokens.iter().peekable();
+    let mut depth = 0usize;
+    
+    while let Some(token) = iter.next() {
+        match token.as_str() {
+            "{" => depth += 1,
+            "}" => depth = depth.saturating_sub(1),
+            "public" if depth == 0 => {
+                // Skip modifiers between `public` and the type keyword
+                while matches!(iter.peek().map(|t| t.as_str()), Some("final" | "abstract" | "static" | "strictfp" | "sealed" | "non-sealed")) {
+                    iter.next();
+                }
+                
+                if matches!(iter.peek().map(|t| t.as_str()), Some("class" | "interface" | "enum" | "record")) {
+                    iter.next();
+                    if let Some(name) = iter.next() {
+                        if name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$') {
+                            return Some(name.clone());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    None
+}
+
+/// Split Java source into identifier-like tokens and braces, ignoring comments and literals
+fn java_tokens(source: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut chars = source.chars().peekable();
+    
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = '\0';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            '"' | '\'' => {
+                let mut escaped = false;
+                for next in chars.by_ref() {
+                    if escaped {
+                        escaped = false;
+                    } else if next == '\\' {
+                        escaped = true;
+                    } else if next == c {
+                        break;
+                    }
+                }
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '$' => {
+                current.push(c);
+                continue;
+            }
+            // The one keyword with a hyphen in it
+            '-' if current == "non" && chars.clone().take(6).eq("sealed".chars()) => {
+                current.push(c);
+                continue;
+            }
+            '{' | '}' => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                tokens.push(c.to_string());
+                continue;
+            }
+            _ => {}
+        }
+        
+        if !current.is_empty() {
+            tokens.push(std::mem::take(&mut current));
+        }
+    }
+    
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    
+    tokens
+}
+
+/// Check whether a file can be executed directly
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    
+    fs::metadata(path)
+        .map(|meta| meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("exe")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn detects_the_top_level_public_class() {
+        let source = "import java.util.*;\n// public class Commented {}\npublic final class Solution {\n    public static void main(String[] args) {}\n}\n";
+        assert_eq!(detect_java_public_class(source).as_deref(), Some("Solution"));
+        assert_eq!(detect_java_public_class("public non-sealed class Shape {}").as_deref(), Some("Shape"));
+        assert_eq!(detect_java_public_class("public record Point(int x, int y) {}").as_deref(), Some("Point"));
+    }
+    
+    #[test]
+    fn ignores_public_types_nested_in_a_class() {
+        let source = "class Main {\n    public static class Inner {}\n    public static void main(String[] args) {}\n}\n";
+        assert_eq!(detect_java_public_class(source), None);
+        
+        let source = "public class Outer {\n    public static class Inner {}\n}\n";
+        assert_eq!(detect_java_public_class(source).as_deref(), Some("Outer"));
+    }
+    
+    #[test]
+    fn skips_braces_and_keywords_in_literals() {
+        let source = "class Main { String s = \"}\"; char c = '{'; }\npublic class Late {}\n";
+        assert_eq!(detect_java_public_class(source).as_deref(), Some("Late"));
+        assert_eq!(java_tokens("int x = a-b;"), ["int", "x", "a", "b"]);
+    }
+    
+    #[test]
+    fn only_faults_at_the_stack_limit_are_stack_overflows() {
+        let (start, end) = (0x7ff0_0000_0000 - 8 * 1024 * 1024, 0x7ff0_0000_0000);
+        let fault = |address| SegvFault { address, stack: Some((start, end)) };
+        let limit = 8 * 1024 * 1024;
+        
+        assert!(stack_fault_reason(&fault(start - 64), limit).is_some_and(|reason| reason.starts_with("stack overflow")));
+        // A null pointer, a write inside the stack, and an address far below it
+        assert_eq!(stack_fault_reason(&fault(0), limit), None);
+        assert_eq!(stack_fault_reason(&fault(start + 4096), limit), None);
+        assert_eq!(stack_fault_reason(&fault(start - 64 * 1024 * 1024), limit), None);
+        // Just below a stack that is nowhere near its limit
+        assert_eq!(stack_fault_reason(&fault(start - 64), 64 * limit), None);
+        assert_eq!(stack_fault_reason(&SegvFault { address: start - 64, stack: None }, limit), None);
+    }
+    
+    #[test]
+    fn docker_merges_stderr_inside_the_container() {
+        let cmd = ["python3".to_string(), "main.py".to_string()];
+        let args = |command: &Command| command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect::<Vec<_>>();
+        
+        let merged = docker_exec_command("box", &cmd, None, Some(5), true);
+        let merged = args(&merged);
+        let after_container = &merged[merged.iter().position(|arg| arg == "box").unwrap() + 1..];
+        assert_eq!(after_container, ["sh", "-c", "exec \"$@\" 2>&1", "sh", "nice", "-n", "5", "python3", "main.py"]);
+        
+        let separate = args(&docker_exec_command("box", &cmd, None, None, false));
+        assert_eq!(separate.last_chunk::<3>().unwrap(), &["box", "python3", "main.py"]);
+    }
+    
+    /// A finished run with the given outcome
+    fn run(status: ExecutionState, exit_code: i32, stdout: &str) -> ExecutionResult {
+        let request: ExecutionRequest = serde_json::from_value(serde_json::json!({
+            "id": "job", "language": "python", "source_code": "",
+        })).unwrap();
+        ExecutionResult {
+            status,
+            exit_code: Some(exit_code),
+            stdout: Some(stdout.to_string()),
+            stderr: Some(String::new()),
+            failure_reason: None,
+            time: Some(0.5),
+            memory: Some(1024 * exit_code as u64 + 1024),
+            ..failed_before_start(&request, String::new(), Utc::now())
+        }
+    }
+    
+    fn aggregate(results: Vec<ExecutionResult>) -> ExecutionResult {
+        CodeExecutor::aggregate_results("job", results, Utc::now(), false)
+    }
+    
+    #[test]
+    fn a_failed_run_decides_the_status_wherever_it_falls() {
+        let completed = || run(ExecutionState::Completed, 0, "ok");
+        let failed = || run(ExecutionState::RuntimeError, 3, "boom");
+        
+        let first = aggregate(vec![failed(), completed(), completed()]);
+        assert_eq!((first.status, first.exit_code), (ExecutionState::RuntimeError, Some(3)));
+        assert_eq!(first.failed_run_indices, [0]);
+        
+        let last = aggregate(vec![completed(), completed(), failed()]);
+        assert_eq!((last.status, last.exit_code), (ExecutionState::RuntimeError, Some(3)));
+        assert_eq!(last.failed_run_indices, [2]);
+    }
+    
+    #[test]
+    fn the_most_severe_run_wins_and_ties_go_to_the_earliest() {
+        let runs = vec![
+            run(ExecutionState::RuntimeError, 1, ""),
+            run(ExecutionState::TimeLimitExceeded, 2, ""),
+            run(ExecutionState::WrongAnswer, 0, ""),
+            run(ExecutionState::TimeLimitExceeded, 4, ""),
+        ];
+        let result = aggregate(runs);
+        assert_eq!((result.status, result.exit_code), (ExecutionState::TimeLimitExceeded, Some(2)));
+        assert_eq!(result.failed_run_indices, [0, 1, 2, 3]);
+        
+        let result = aggregate(vec![run(ExecutionState::MemoryLimitExceeded, 9, ""), run(ExecutionState::InternalError, 5, "")]);
+        assert_eq!((result.status, result.exit_code), (ExecutionState::InternalError, Some(5)));
+    }
+    
+    #[test]
+    fn successful_runs_combine_their_output_and_usage() {
+        let result = aggregate(vec![run(ExecutionState::Completed, 0, "a"), run(ExecutionState::Completed, 0, "b")]);
+        assert_eq!((result.status, result.exit_code), (ExecutionState::Completed, Some(0)));
+        assert!(result.failed_run_indices.is_empty());
+        assert_eq!(result.stdout.as_deref(), Some("a\n--- Run 2 ---\nb"));
+        assert_eq!((result.time, result.memory), (Some(1.0), Some(1024)));
+    }
+    
+    #[test]
+    fn a_single_or_missing_run_is_reported_as_is() {
+        let result = aggregate(vec![run(ExecutionState::CompilationError, 1, "")]);
+        assert_eq!(result.status, ExecutionState::CompilationError);
+        assert_eq!(result.failed_run_indices, [0]);
+        
+        assert_eq!(aggregate(Vec::new()).status, ExecutionState::InternalError);
+    }
+    
+    fn executor(config: &EngineConfig) -> (tempfile::TempDir, CodeExecutor) {
+        let scratch = tempfile::tempdir().unwrap();
+        let workdirs = WorkdirStore::new(scratch.path().join("preserved"), chrono::Duration::zero()).unwrap();
+        let executor = CodeExecutor::new(workdirs, config).unwrap();
+        (scratch, executor)
+    }
+    
+    #[test]
+    fn aliases_and_versioned_names_resolve_to_their_key() {
+        let mut config = EngineConfig::default();
+        config.language_aliases.insert("python".to_string(), vec!["Snake".to_string()]);
+        let (_scratch, executor) = executor(&config);
+        
+        for (spelling, key) in [
+            ("python3", "python"), ("py", "python"), ("Python3.11", "python"), (" PYTHON ", "python"),
+            ("node", "javascript"), ("nodejs", "javascript"), ("node18", "javascript"), ("js", "javascript"),
+            ("c++", "cpp"), ("C++17", "cpp"), ("cpp", "cpp"), ("c99", "c"), ("golang", "go"),
+            ("go1.21", "go"), ("java17", "java"), ("rs", "rust"), ("snake", "python"),
+        ] {
+            assert_eq!(executor.resolve_language(spelling), Some(key), "{}", spelling);
+        }
+        assert_eq!(executor.resolve_language("cobol"), None);
+    }
+    
+    #[test]
+    fn unknown_names_suggest_the_closest_keys() {
+        let (_scratch, executor) = executor(&EngineConfig::default());
+        assert_eq!(executor.suggest_languages("pyhton"), ["python"]);
+        assert_eq!(executor.suggest_languages("javscript"), ["javascript"]);
+        assert_eq!(executor.suggest_languages("golnag"), ["go"]);
+        assert!(executor.suggest_languages("cobol").is_empty());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+    
+    #[test]
+    fn aliases_must_name_one_known_language() {
+        let refusal = |key: &str, alias: &str| {
+            let scratch = tempfile::tempdir().unwrap();
+            let workdirs = WorkdirStore::new(scratch.path().join("preserved"), chrono::Duration::zero()).unwrap();
+            let mut config = EngineConfig::default();
+            config.language_aliases.insert(key.to_string(), vec![alias.to_string()]);
+            CodeExecutor::new(workdirs, &config).err().map(|error| error.to_string())
+        };
+        assert_eq!(refusal("go", "gopher"), None);
+        assert!(refusal("go", "py").unwrap().contains("given to both"));
+        assert!(refusal("go", "python").unwrap().contains("another language's key"));
+        assert!(refusal("cobol", "cbl").unwrap().contains("unknown language"));
+    }
+    
+    #[test]
+    fn failed_runs_list_their_files_without_the_binary() {
+        let (_scratch, executor) = executor(&EngineConfig::default());
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("data")).unwrap();
+        fs::write(dir.path().join("data/input.csv"), "1,2\n").unwrap();
+        fs::write(dir.path().join("main.c"), "int main;").unwrap();
+        fs::write(dir.path().join("main"), "\x7fELF").unwrap();
+        let request = ExecutionRequest { list_workdir: Some(true), ..Default::default() };
+        let c = &executor.languages["c"];
+        
+        let listing = list_workdir(&request, c, dir.path(), &ExecutionState::RuntimeError).unwrap();
+        assert_eq!(listing, [
+            WorkdirEntry { path: "data/input.csv".to_string(), size: 4 },
+            WorkdirEntry { path: "main.c".to_string(), size: 9 },
+        ]);
+        assert!(list_workdir(&request, c, dir.path(), &ExecutionState::CompilationError).is_some());
+        
+        // Only failures of requests that ask are listed
+        assert!(list_workdir(&request, c, dir.path(), &ExecutionState::Completed).is_none());
+        assert!(list_workdir(&request, c, dir.path(), &ExecutionState::TimeLimitExceeded).is_none());
+        assert!(list_workdir(&ExecutionRequest::default(), c, dir.path(), &ExecutionState::RuntimeError).is_none());
+    }
+    
+    #[test]
+    fn runs_sharing_a_build_list_its_compiler_once() {
+        let command = |phase, argv: &str| ExecutedCommand {
+            phase,
+            argv: argv.split(' ').map(String::from).collect(),
+            duration_ms: 1,
+            exit_code: 0,
+        };
+        let with_commands = |argv: &str| ExecutionResult {
+            executed_commands: vec![command(CommandPhase::Compile, "gcc -o main main.c"), command(CommandPhase::Run, argv)],
+            ..run(ExecutionState::Completed, 0, "")
+        };
+        
+        let result = aggregate(vec![with_commands("./main 1"), with_commands("./main 2")]);
+        let argv: Vec<String> = result.executed_commands.iter().map(|command| command.argv.join(" ")).collect();
+        assert_eq!(argv, ["gcc -o main main.c", "./main 1", "./main 2"]);
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn isolate_meta_files_decide_the_verdict() {
+        use std::os::unix::process::ExitStatusExt;
+        
+        let verdict = |meta: &str| {
+            let dir = tempfile::tempdir().unwrap();
+            std::fs::write(dir.path().join("meta"), meta).unwrap();
+            let meta = isolate::Meta::read(&dir.path().join("meta")).unwrap();
+            // isolate's own exit status says nothing about the program
+            let output = std::process::Output { status: std::process::ExitStatus::from_raw(1 << 8), stdout: Vec::new(), stderr: Vec::new() };
+            isolate_result(meta, output, Truncated::default(), &ResourceLimits::default(), Utc::now(), 0.5)
+        };
+        
+        let completed = verdict("time:0.100\ntime-wall:0.200\ncg-mem:4096\nexitcode:0\n").unwrap();
+        assert_eq!(run_status(&completed), ExecutionState::Completed);
+        assert_eq!((completed.execution_time, completed.memory_usage), (0.1, 4096 * 1024));
+        assert_eq!(completed.metrics.wall_time, Some(0.2));
+        // isolate does not report these, so they are null rather than zero
+        assert_eq!((completed.metrics.minor_page_faults, completed.metrics.read_bytes), (None, None));
+        
+        let timed_out = verdict("time:1.000\nstatus:TO\nmessage:Time limit exceeded\nkilled:1\nexitsig:9\n").unwrap();
+        assert_eq!(run_status(&timed_out), ExecutionState::TimeLimitExceeded);
+        
+        let out_of_memory = verdict("status:SG\nexitsig:9\ncg-oom-killed:1\n").unwrap();
+        assert_eq!(run_status(&out_of_memory), ExecutionState::MemoryLimitExceeded);
+        assert!(out_of_memory.failure_reason.unwrap().contains("isolate control group"));
+        
+        let oversized = verdict("status:SG\nexitsig:25\n").unwrap();
+        assert_eq!(run_status(&oversized), ExecutionState::OutputLimitExceeded);
+        
+        let crashed = verdict("status:RE\nexitcode:3\nmessage:Exited with error status 3\n").unwrap();
+        assert_eq!(run_status(&crashed), ExecutionState::RuntimeError);
+        assert_eq!(crashed.exit_code, 3);
+        
+        let segfault = verdict("status:SG\nexitsig:11\n").unwrap();
+        assert_eq!((run_status(&segfault), segfault.signal.as_deref()), (ExecutionState::RuntimeError, Some("SIGSEGV")));
+        
+        // XX is isolate failing, which is the engine's problem rather than the program's
+        assert!(verdict("status:XX\nmessage:Cannot create cgroup\n").is_err());
+    }
+}
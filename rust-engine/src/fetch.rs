@@ -0,0 +1,337 @@
+use crate::config::FetchConfig;
+use crate::types::ExecutionRequest;
+use reqwest::{header, redirect, Client, Url};
+use std::error::Error as _;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Content types accepted for `source_url` besides text/*, which S3 often serves uploads as
+const SOURCE_CONTENT_TYPES: &[&str] = &["application/octet-stream"];
+
+/// Content types accepted for `additional_files_url`
+const ARCHIVE_CONTENT_TYPES: &[&str] = &["application/zip", "application/x-zip-compressed", "application/octet-stream"];
+
+/// Every ZIP archive starts with a local file header or, when empty, the end of central directory
+const ZIP_MAGIC: &[&[u8]] = &[b"PK\x03\x04", b"PK\x05\x06"];
+
+/// Why an input could not be downloaded; the job fails with this as its failure reason
+#[derive(Debug, thiserror::Error)]
+#[error("fetch_error: {field}: {reason}")]
+pub struct FetchError {
+    field: &'static str,
+    reason: String,
+}
+
+impl FetchError {
+    fn new(field: &'static str, reason: impl Into<String>) -> Self {
+        Self { field, reason: reason.into() }
+    }
+}
+
+/// One entry of the allowed hosts
+#[derive(Debug, Clone)]
+enum HostPattern {
+    Any,            // *
+    Exact(String),  // files.example.com
+    Suffix(String), // *.example.com, stored as .example.com
+}
+
+impl HostPattern {
+    fn parse(spec: &str) -> Self {
+        let spec = spec.trim().to_ascii_lowercase();
+        if spec == "*" {
+            return Self::Any;
+        }
+        match spec.strip_prefix('*') {
+            Some(suffix) => Self::Suffix(suffix.to_string()),
+            None => Self::Exact(spec),
+        }
+    }
+    
+    fn matches(&self, host: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Exact(allowed) => host == allowed,
+            Self::Suffix(suffix) => host.strip_suffix(suffix.as_str()).is_some_and(|subdomain| !subdomain.is_empty()),
+        }
+    }
+}
+
+/// Whether `url` is http(s) on one of the allowed hosts
+fn is_allowed(hosts: &[HostPattern], url: &Url) -> bool {
+    let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+        return false;
+    };
+    matches!(url.scheme(), "http" | "https") && hosts.iter().any(|pattern| pattern.matches(&host))
+}
+
+/// Downloads the inputs a request references by URL
+#[derive(Clone)]
+pub struct Fetcher {
+    client: Client,
+    hosts: Arc<Vec<HostPattern>>,
+    max_bytes: u64,
+    max_archive_bytes: u64, // additional_files_url is also held to the limit on inline additional_files
+    timeout: Duration,
+}
+
+impl Fetcher {
+    /// Build a client that only follows redirects to allowed hosts, at most `max_redirects` of them
+    pub fn new(config: &FetchConfig, max_archive_bytes: u64) -> anyhow::Result<Self> {
+        let hosts: Arc<Vec<HostPattern>> = Arc::new(config.allowed_hosts
+            .iter()
+            .filter(|spec| !spec.trim().is_empty())
+            .map(|spec| HostPattern::parse(spec))
+            .collect());
+        
+        let max_redirects = config.max_redirects;
+        let redirect_hosts = Arc::clone(&hosts);
+        let policy = redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() > max_redirects {
+                attempt.error(format!("more than {} redirects", max_redirects))
+            } else if !is_allowed(&redirect_hosts, attempt.url()) {
+                let host = attempt.url().host_str().unwrap_or_default().to_string();
+                attempt.error(format!("redirected to {}, which is not an allowed host", host))
+            } else {
+                attempt.follow()
+            }
+        });
+        
+        // The timeout covers connecting, redirects and reading the whole body
+        let timeout = Duration::from_secs(config.timeout_secs);
+        let client = Client::builder()
+            .redirect(policy)
+            .timeout(timeout)
+            .build()?;
+        
+        Ok(Self {
+            client,
+            hosts,
+            max_bytes: config.max_bytes,
+            max_archive_bytes: config.max_bytes.min(max_archive_bytes),
+            timeout,
+        })
+    }
+    
+    /// Whether the request has anything to download
+    pub fn needed(request: &ExecutionRequest) -> bool {
+        request.source_url.is_some() || request.additional_files_url.is_some()
+    }
+    
+    /// Copy of the request with `source_code` and `additional_files` filled in from their URLs
+    /// The source is held to `max_source_bytes`, its language's limit on inline source_code, as well
+    pub async fn resolve(&self, request: &ExecutionRequest, max_source_bytes: usize) -> Result<ExecutionRequest, FetchError> {
+        use base64::{engine::general_purpose, Engine as _};
+        
+        let mut resolved = request.clone();
+        
+        if let Some(url) = &request.source_url {
+            let body = self.download("source_url", url, self.max_bytes.min(max_source_bytes as u64), |content_type| {
+                (content_type.starts_with("text/") && content_type != "text/html")
+                    || SOURCE_CONTENT_TYPES.contains(&content_type)
+            }).await?;
+            resolved.source_code = String::from_utf8(body)
+                .map_err(|_| FetchError::new("source_url", "body is not UTF-8 text"))?;
+        }
+        
+        if let Some(url) = &request.additional_files_url {
+            let body = self.download("additional_files_url", url, self.max_archive_bytes, |content_type| {
+                ARCHIVE_CONTENT_TYPES.contains(&content_type)
+            }).await?;
+            if !ZIP_MAGIC.iter().any(|magic| body.starts_with(magic)) {
+                return Err(FetchError::new("additional_files_url", "body is not a ZIP archive"));
+            }
+            resolved.additional_files = Some(general_purpose::STANDARD.encode(body));
+        }
+        
+        Ok(resolved)
+    }
+    
+    /// Fetch one URL, refusing disallowed hosts, error statuses, unexpected content types and
+    /// bodies over `max_bytes`; a missing Content-Type is accepted and left to the body checks
+    async fn download(
+        &self,
+        field: &'static str,
+        url: &str,
+        max_bytes: u64,
+        accepts: impl Fn(&str) -> bool,
+    ) -> Result<Vec<u8>, FetchError> {
+        let url = Url::parse(url).map_err(|err| FetchError::new(field, format!("invalid URL: {}", err)))?;
+        if self.hosts.is_empty() {
+            return Err(FetchError::new(field, "downloads are disabled on this server"));
+        }
+        if !is_allowed(&self.hosts, &url) {
+            return Err(FetchError::new(
+                field,
+                format!("{} is not an allowed host", url.host_str().unwrap_or_default()),
+            ));
+        }
+        
+        debug!("Downloading {} from {}", field, url.host_str().unwrap_or_default());
+        let mut response = self.client.get(url).send().await
+            .map_err(|err| FetchError::new(field, self.describe(err)))?;
+        
+        let status = response.status();
+        if !status.is_success() {
+            return Err(FetchError::new(field, format!("origin answered {}", status)));
+        }
+        
+        let content_type = response.headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or_default().trim().to_ascii_lowercase());
+        if let Some(content_type) = content_type.filter(|content_type| !accepts(content_type)) {
+            return Err(FetchError::new(field, format!("unexpected content type {}", content_type)));
+        }
+        
+        let too_large = || FetchError::new(field, format!("body is larger than {} bytes", max_bytes));
+        if response.content_length().is_some_and(|length| length > max_bytes) {
+            return Err(too_large());
+        }
+        
+        // Content-Length can be missing or wrong, so the cap is enforced while reading
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|err| FetchError::new(field, self.describe(err)))? {
+            if (body.len() + chunk.len()) as u64 > max_bytes {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        
+        info!("📥 Downloaded {} ({} bytes)", field, body.len());
+        Ok(body)
+    }
+    
+    /// Error text without the URL, whose query string may carry a presigned signature
+    /// The intermediate causes repeat each other, so only the innermost one is kept
+    fn describe(&self, err: reqwest::Error) -> String {
+        if err.is_timeout() {
+            return format!("timed out after {}s", self.timeout.as_secs());
+        }
+        
+        let err = err.without_url();
+        let mut root = err.source();
+        while let Some(cause) = root.and_then(|cause| cause.source()) {
+            root = Some(cause);
+        }
+        let Some(cause) = root else {
+            return err.to_string();
+        };
+        if err.is_connect() {
+            format!("could not connect: {}", cause)
+        } else if err.is_redirect() {
+            cause.to_string()
+        } else {
+            format!("{}: {}", err.to_string().split(':').next().unwrap_or_default(), cause)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::{header::CONTENT_TYPE, StatusCode}, response::Redirect, routing::get, Router};
+    
+    /// Origin on a local port serving a source file, a ZIP and a few misbehaving routes
+    async fn origin() -> String {
+        let app = Router::new()
+            .route("/main.py", get(|| async { ([(CONTENT_TYPE, "text/plain")], "print(1)") }))
+            .route("/files.zip", get(|| async { ([(CONTENT_TYPE, "application/zip")], b"PK\x05\x06\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0".to_vec()) }))
+            .route("/page.html", get(|| async { ([(CONTENT_TYPE, "text/html")], "<html></html>") }))
+            .route("/big.txt", get(|| async { ([(CONTENT_TYPE, "text/plain")], "x".repeat(2048)) }))
+            .route("/missing", get(|| async { StatusCode::NOT_FOUND }))
+            .route("/slow", get(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                "print(1)"
+            }))
+            // Redirects `left` + 1 times before landing on the source
+            .route("/hop/:left", get(|axum::extract::Path(left): axum::extract::Path<u32>| async move {
+                match left {
+                    0 => Redirect::temporary("/main.py"),
+                    left => Redirect::temporary(&format!("/hop/{}", left - 1)),
+                }
+            }))
+            .route("/away", get(|axum::extract::Host(host): axum::extract::Host| async move {
+                // Same server, but under a name the allowlist doesn't have
+                Redirect::temporary(&format!("http://{}/main.py", host.replace("127.0.0.1", "localhost")))
+            }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        base
+    }
+    
+    fn allowing(allowed_hosts: &[&str]) -> Fetcher {
+        let config = FetchConfig {
+            allowed_hosts: allowed_hosts.iter().map(|host| host.to_string()).collect(),
+            max_bytes: 1024,
+            timeout_secs: 1,
+            max_redirects: 2,
+        };
+        Fetcher::new(&config, u64::MAX).unwrap()
+    }
+    
+    fn request(source_url: Option<String>, additional_files_url: Option<String>) -> ExecutionRequest {
+        let mut request: ExecutionRequest = serde_json::from_value(serde_json::json!({"language": "python"})).unwrap();
+        request.source_url = source_url;
+        request.additional_files_url = additional_files_url;
+        request
+    }
+    
+    /// Failure of fetching `path` as the source
+    async fn refusal(fetcher: &Fetcher, url: String) -> String {
+        fetcher.resolve(&request(Some(url), None), usize::MAX).await.unwrap_err().to_string()
+    }
+    
+    #[test]
+    fn host_patterns_match_exact_hosts_and_subdomains() {
+        let hosts: Vec<_> = ["files.example.com", "*.bucket.test"].iter().map(|spec| HostPattern::parse(spec)).collect();
+        let allowed = |url: &str| is_allowed(&hosts, &Url::parse(url).unwrap());
+        assert!(allowed("https://files.example.com/a"));
+        assert!(allowed("https://FILES.example.com/a"));
+        assert!(allowed("http://eu.bucket.test/a"));
+        assert!(!allowed("https://bucket.test/a"));
+        assert!(!allowed("https://example.com/a"));
+        assert!(!allowed("https://files.example.com.evil.test/a"));
+        assert!(!allowed("ftp://files.example.com/a"));
+        assert!(is_allowed(&[HostPattern::parse("*")], &Url::parse("https://anything.test").unwrap()));
+    }
+    
+    #[tokio::test]
+    async fn sources_and_archives_are_filled_in_from_their_urls() {
+        let base = origin().await;
+        let fetcher = allowing(&["127.0.0.1"]);
+        let resolved = fetcher
+            .resolve(&request(Some(format!("{}/main.py", base)), Some(format!("{}/files.zip", base))), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(resolved.source_code, "print(1)");
+        assert_eq!(resolved.additional_files.as_deref(), Some("UEsFBgAAAAAAAAAAAAAAAAAAAAAAAA=="));
+        
+        let redirected = fetcher.resolve(&request(Some(format!("{}/hop/1", base)), None), usize::MAX).await.unwrap();
+        assert_eq!(redirected.source_code, "print(1)");
+    }
+    
+    #[tokio::test]
+    async fn bad_downloads_fail_with_a_fetch_error() {
+        let base = origin().await;
+        let fetcher = allowing(&["127.0.0.1"]);
+        
+        assert!(refusal(&fetcher, format!("{}/missing", base)).await.contains("origin answered 404"));
+        assert!(refusal(&fetcher, format!("{}/page.html", base)).await.contains("unexpected content type text/html"));
+        assert!(refusal(&fetcher, format!("{}/big.txt", base)).await.contains("larger than 1024 bytes"));
+        assert!(refusal(&fetcher, format!("{}/hop/2", base)).await.contains("more than 2 redirects"));
+        assert!(refusal(&fetcher, format!("{}/away", base)).await.contains("localhost, which is not an allowed host"));
+        assert!(refusal(&fetcher, format!("{}/slow", base)).await.contains("timed out after 1s"));
+        assert!(refusal(&fetcher, "http://files.example.com/main.py".to_string()).await.contains("not an allowed host"));
+        assert!(refusal(&allowing(&[]), format!("{}/main.py", base)).await.contains("downloads are disabled"));
+        
+        // The source is also held to the language's own limit
+        let small = fetcher.resolve(&request(Some(format!("{}/main.py", base)), None), 4).await.unwrap_err();
+        assert!(small.to_string().contains("larger than 4 bytes"));
+        
+        let not_zip = fetcher.resolve(&request(None, Some(format!("{}/main.py", base))), usize::MAX).await.unwrap_err();
+        assert!(not_zip.to_string().starts_with("fetch_error: additional_files_url:"), "{}", not_zip);
+    }
+}
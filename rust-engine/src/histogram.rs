@@ -0,0 +1,154 @@
+/// Linear sub-buckets per power of two, keeping bucket error within about 6%
+const SUB_BUCKETS: u64 = 16;
+const SUB_BUCKET_BITS: u32 = SUB_BUCKETS.trailing_zeros();
+
+/// Enough buckets for any millisecond value that fits in a u64
+const BUCKET_COUNT: usize = ((64 - SUB_BUCKET_BITS + 1) * SUB_BUCKETS as u32) as usize;
+
+/// Fixed-size log-linear histogram of durations in milliseconds, HDR style
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    count: u64,
+    sum_ms: u64,
+    max_ms: u64,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            counts: vec![0; BUCKET_COUNT],
+            count: 0,
+            sum_ms: 0,
+            max_ms: 0,
+        }
+    }
+    
+    /// Record one duration
+    pub fn record(&mut self, ms: u64) {
+        self.counts[bucket_index(ms)] += 1;
+        self.count += 1;
+        self.sum_ms = self.sum_ms.saturating_add(ms);
+        self.max_ms = self.max_ms.max(ms);
+    }
+    
+    /// Exact mean in milliseconds
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.sum_ms as f64 / self.count as f64
+    }
+    
+    /// Smallest bucket bound that covers the `quantile` fraction of samples (0.0 to 1.0)
+    pub fn percentile(&self, quantile: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_upper_bound(index).min(self.max_ms);
+            }
+        }
+        self.max_ms
+    }
+    
+    /// Forget every sample
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Small values get a bucket each; larger ones share a bucket per 1/16th of their power of two
+fn bucket_index(ms: u64) -> usize {
+    if ms < SUB_BUCKETS {
+        return ms as usize;
+    }
+    
+    let shift = 63 - ms.leading_zeros() - SUB_BUCKET_BITS;
+    ((shift + 1) as u64 * SUB_BUCKETS + ((ms >> shift) - SUB_BUCKETS)) as usize
+}
+
+/// Largest value that lands in a bucket
+fn bucket_upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    
+    let shift = (index / SUB_BUCKETS - 1) as u32;
+    let mantissa = index % SUB_BUCKETS + SUB_BUCKETS;
+    (mantissa + 1).checked_mul(1 << shift).map_or(u64::MAX, |end| end - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn histogram(samples: impl IntoIterator<Item = u64>) -> LatencyHistogram {
+        let mut histogram = LatencyHistogram::new();
+        for ms in samples {
+            histogram.record(ms);
+        }
+        histogram
+    }
+    
+    #[test]
+    fn small_durations_are_exact() {
+        let histogram = histogram(1..=10);
+        assert_eq!(histogram.mean(), 5.5);
+        assert_eq!(histogram.percentile(0.5), 5);
+        assert_eq!(histogram.percentile(0.95), 10);
+        assert_eq!(histogram.percentile(0.0), 1);
+        assert_eq!(histogram.percentile(1.0), 10);
+    }
+    
+    #[test]
+    fn percentiles_stay_within_the_bucket_error() {
+        let histogram = histogram(1..=10_000);
+        for (quantile, exact) in [(0.5, 5_000.0), (0.95, 9_500.0), (0.99, 9_900.0)] {
+            let reported = histogram.percentile(quantile) as f64;
+            // Buckets report their upper bound, so a percentile is never under the exact one
+            assert!(reported >= exact && reported <= exact * 1.07, "p{} was {}", quantile * 100.0, reported);
+        }
+        assert_eq!(histogram.mean(), 5_000.5);
+        assert_eq!(histogram.percentile(1.0), 10_000);
+    }
+    
+    #[test]
+    fn a_slow_tail_shows_only_in_the_high_percentiles() {
+        let histogram = histogram(std::iter::repeat_n(10, 98).chain([5_000, 5_000]));
+        assert_eq!(histogram.percentile(0.5), 10);
+        assert_eq!(histogram.percentile(0.95), 10);
+        assert_eq!(histogram.percentile(0.99), 5_000);
+    }
+    
+    #[test]
+    fn every_value_lands_in_a_bucket_that_covers_it() {
+        for ms in [0, 15, 16, 17, 31, 32, 1_000, 65_535, 1 << 40, u64::MAX] {
+            let index = bucket_index(ms);
+            assert!(index < BUCKET_COUNT, "{}", ms);
+            assert!(bucket_upper_bound(index) >= ms, "{}", ms);
+            assert!(index == 0 || bucket_upper_bound(index - 1) < ms, "{}", ms);
+        }
+    }
+    
+    #[test]
+    fn empty_and_reset_histograms_report_zero() {
+        let mut histogram = histogram([40, 50]);
+        histogram.reset();
+        assert_eq!((histogram.mean(), histogram.percentile(0.99)), (0.0, 0));
+        assert_eq!(LatencyHistogram::default().percentile(0.5), 0);
+    }
+}
+
@@ -0,0 +1,210 @@
+use crate::types::ExecutionResponse;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+/// Idempotency key reused with a different request body
+#[derive(Debug, thiserror::Error)]
+#[error("Idempotency key was already used with a different request")]
+pub struct IdempotencyConflict;
+
+/// Remembers the response given for each idempotency key within a time window
+#[derive(Clone)]
+pub struct IdempotencyStore {
+    entries: Arc<Mutex<HashMap<String, IdempotencyEntry>>>,
+    ttl: Duration,
+}
+
+struct IdempotencyEntry {
+    fingerprint: String,
+    response: Arc<OnceCell<ExecutionResponse>>,
+    expires_at: DateTime<Utc>,
+}
+
+impl IdempotencyStore {
+    /// Store that remembers keys for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+        }
+    }
+    
+    /// Run `submit` once per key, replaying its response for every later request with the key
+    /// Concurrent requests with the same key wait for the first; a failed submission may be retried
+    pub async fn submit<F, Fut>(&self, key: &str, fingerprint: &str, submit: F) -> Result<ExecutionResponse>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ExecutionResponse>>,
+    {
+        let response = {
+            let mut entries = self.entries.lock().unwrap();
+            let now = Utc::now();
+            
+            let entry = entries.entry(key.to_string())
+                .and_modify(|entry| {
+                    if entry.expires_at <= now {
+                        *entry = IdempotencyEntry::new(fingerprint, now + self.ttl);
+                    }
+                })
+                .or_insert_with(|| IdempotencyEntry::new(fingerprint, now + self.ttl));
+            
+            if entry.fingerprint != fingerprint {
+                return Err(IdempotencyConflict.into());
+            }
+            Arc::clone(&entry.response)
+        };
+        
+        if response.initialized() {
+            debug!("Replaying response for idempotency key {}", key);
+        }
+        response.get_or_try_init(submit).await.cloned()
+    }
+    
+    /// The response a key already got, if its first submission has finished
+    /// A key used with a different request is a conflict here too
+    pub fn replay(&self, key: &str, fingerprint: &str) -> Result<Option<ExecutionResponse>> {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.expires_at > Utc::now() => {
+                if entry.fingerprint != fingerprint {
+                    return Err(IdempotencyConflict.into());
+                }
+                Ok(entry.response.get().cloned())
+            }
+            _ => Ok(None),
+        }
+    }
+    
+    /// Forget keys whose window has passed
+    pub fn sweep(&self) {
+        let now = Utc::now();
+        self.entries.lock().unwrap().retain(|_, entry| entry.expires_at > now);
+    }
+}
+
+impl IdempotencyEntry {
+    fn new(fingerprint: &str, expires_at: DateTime<Utc>) -> Self {
+        Self {
+            fingerprint: fingerprint.to_string(),
+            response: Arc::new(OnceCell::new()),
+            expires_at,
+        }
+    }
+}
+
+/// Spellings of the idempotency key a body may carry
+const KEY_FIELDS: &[&str] = &["idempotency_key", "idempotencyKey"];
+
+/// Spellings of the base64 flag a body may carry
+const BASE64_FIELDS: &[&str] = &["base64_encoded", "base64Encoded"];
+
+/// Hash of a request body and the query options sent with it, ignoring key order and the idempotency key.
+/// `?base64_encoded=true` counts the same as the body field, since both decode the request the same way.
+pub fn fingerprint(body: &serde_json::Value, query: &HashMap<String, String>) -> String {
+    let mut body = body.clone();
+    if let Some(fields) = body.as_object_mut() {
+        for field in KEY_FIELDS {
+            fields.remove(*field);
+        }
+        let base64_encoded = query.get("base64_encoded").is_some_and(|value| value == "true")
+            || BASE64_FIELDS.iter().any(|field| fields.get(*field) == Some(&serde_json::Value::Bool(true)));
+        for field in BASE64_FIELDS {
+            fields.remove(*field);
+        }
+        fields.insert("base64_encoded".to_string(), base64_encoded.into());
+    }
+    let query: BTreeMap<&String, &String> = query.iter().filter(|(name, _)| *name != "base64_encoded").collect();
+    
+    // serde_json maps are sorted, so equal payloads serialize identically
+    let mut hasher = Sha256::new();
+    hasher.update(body.to_string().as_bytes());
+    hasher.update([0]);
+    hasher.update(serde_json::to_string(&query).unwrap_or_default().as_bytes());
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    
+    /// A submission that takes a moment and counts how often it ran
+    async fn create(created: &AtomicUsize) -> Result<ExecutionResponse> {
+        let n = created.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        Ok(ExecutionResponse {
+            id: format!("job-{}", n),
+            status: "queued".to_string(),
+            message: String::new(),
+            parent_id: None,
+        })
+    }
+    
+    #[tokio::test]
+    async fn simultaneous_requests_with_one_key_create_one_job() {
+        let store = IdempotencyStore::new(Duration::minutes(5));
+        let created = AtomicUsize::new(0);
+        let (first, second) = tokio::join!(
+            store.submit("key", "body", || create(&created)),
+            store.submit("key", "body", || create(&created)),
+        );
+        assert_eq!(first.unwrap().id, "job-0");
+        assert_eq!(second.unwrap().id, "job-0");
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+        
+        assert_eq!(store.replay("key", "body").unwrap().unwrap().id, "job-0");
+        assert!(store.replay("other", "body").unwrap().is_none());
+    }
+    
+    #[tokio::test]
+    async fn a_different_request_with_the_same_key_conflicts() {
+        let store = IdempotencyStore::new(Duration::minutes(5));
+        let created = AtomicUsize::new(0);
+        store.submit("key", "body", || create(&created)).await.unwrap();
+        
+        let err = store.submit("key", "other body", || create(&created)).await.unwrap_err();
+        assert!(err.is::<IdempotencyConflict>());
+        assert!(store.replay("key", "other body").unwrap_err().is::<IdempotencyConflict>());
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+    }
+    
+    #[tokio::test]
+    async fn keys_expire_after_their_window() {
+        let store = IdempotencyStore::new(Duration::milliseconds(20));
+        let created = AtomicUsize::new(0);
+        store.submit("key", "body", || create(&created)).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+        
+        store.sweep();
+        assert!(store.entries.lock().unwrap().is_empty());
+        // Expired, the key is free for another request and makes a new job
+        assert_eq!(store.submit("key", "other body", || create(&created)).await.unwrap().id, "job-1");
+    }
+    
+    #[test]
+    fn fingerprints_ignore_the_key_but_not_how_the_body_is_decoded() {
+        let no_query = HashMap::new();
+        let base64_query = HashMap::from([("base64_encoded".to_string(), "true".to_string())]);
+        let body = json!({"language": "python", "source_code": "cHJpbnQoMSk="});
+        let plain = fingerprint(&body, &no_query);
+        
+        assert_eq!(fingerprint(&json!({"source_code": "cHJpbnQoMSk=", "language": "python", "idempotency_key": "a"}), &no_query), plain);
+        assert_eq!(fingerprint(&json!({"language": "python", "source_code": "cHJpbnQoMSk=", "idempotencyKey": "b"}), &no_query), plain);
+        assert_eq!(fingerprint(&json!({"language": "python", "source_code": "cHJpbnQoMSk=", "base64_encoded": false}), &no_query), plain);
+        
+        // Decoded as base64 by either the query or the body, it is a different request
+        let encoded = fingerprint(&body, &base64_query);
+        assert_ne!(encoded, plain);
+        assert_eq!(fingerprint(&json!({"language": "python", "source_code": "cHJpbnQoMSk=", "base64Encoded": true}), &no_query), encoded);
+        
+        let waiting = HashMap::from([("wait".to_string(), "true".to_string())]);
+        assert_ne!(fingerprint(&body, &waiting), plain);
+    }
+}
@@ -0,0 +1,383 @@
+use crate::config::IsolateConfig;
+use crate::types::{ExecutionOptions, ResourceLimits};
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::process::Command;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{info, warn};
+
+/// Directory of a box as the program sees it
+pub const BOX_WORKDIR: &str = "/box";
+
+/// PATH inside boxes, which start with an empty environment
+const BOX_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// Box ids handed out to jobs, each initialised before and cleaned up after a run
+///
+/// An id only returns to the pool once `isolate --cleanup` has finished with it, so a new job
+/// never sees the previous one's files or processes. Ids left behind by a crashed engine are
+/// cleaned when the pool is created.
+#[derive(Clone)]
+pub struct BoxPool {
+    binary: String,
+    cgroups: bool,
+    free: Arc<Mutex<Vec<u32>>>,
+    permits: Arc<Semaphore>, // one per free id
+}
+
+impl BoxPool {
+    /// Check that isolate runs and clean every box id in the configured range
+    pub fn from_config(config: &IsolateConfig) -> Result<Self> {
+        let version = std::process::Command::new(&config.binary)
+            .arg("--version")
+            .output()
+            .with_context(|| format!("cannot run {}", config.binary))?;
+        if !version.status.success() {
+            return Err(anyhow!("{} --version failed: {}", config.binary, String::from_utf8_lossy(&version.stderr).trim()));
+        }
+        let first_line = String::from_utf8_lossy(&version.stdout).lines().next().unwrap_or_default().to_string();
+        info!("📦 Isolate backend enabled ({}, {} boxes from id {})", first_line, config.boxes, config.first_box_id);
+        
+        let ids: Vec<u32> = (config.first_box_id..).take(config.boxes).collect();
+        for id in &ids {
+            let cleanup = std::process::Command::new(&config.binary)
+                .args(box_args(*id, config.cgroups))
+                .arg("--cleanup")
+                .output()?;
+            if !cleanup.status.success() {
+                warn!("Could not clean isolate box {}: {}", id, String::from_utf8_lossy(&cleanup.stderr).trim());
+            }
+        }
+        
+        Ok(Self {
+            binary: config.binary.clone(),
+            cgroups: config.cgroups,
+            permits: Arc::new(Semaphore::new(ids.len())),
+            free: Arc::new(Mutex::new(ids)),
+        })
+    }
+    
+    /// Wait for a free box id and initialise its box
+    pub async fn claim(&self) -> Result<BoxLease> {
+        let permit = self.permits.clone().acquire_owned().await?;
+        let id = self.free.lock().unwrap().pop().ok_or_else(|| anyhow!("isolate box pool is empty"))?;
+        let mut lease = BoxLease {
+            pool: self.clone(),
+            id,
+            dir: PathBuf::new(),
+            permit: Some(permit),
+        };
+        
+        // isolate prints the box's root; the program's directory is its box/ subdirectory
+        let output = self.isolate(id).arg("--init").output().await?;
+        if !output.status.success() {
+            return Err(anyhow!("isolate --init failed for box {}: {}", id, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        lease.dir = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()).join("box");
+        Ok(lease)
+    }
+    
+    fn isolate(&self, id: u32) -> Command {
+        let mut command = Command::new(&self.binary);
+        command.args(box_args(id, self.cgroups));
+        command
+    }
+}
+
+/// Flags selecting a box, which --init, --run and --cleanup must all agree on
+fn box_args(id: u32, cgroups: bool) -> Vec<String> {
+    let mut args = vec![format!("--box-id={}", id)];
+    if cgroups {
+        args.push("--cg".to_string());
+    }
+    args
+}
+
+/// Initialised box that is cleaned up and returned to the pool once the run is over
+pub struct BoxLease {
+    pool: BoxPool,
+    id: u32,
+    dir: PathBuf,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl BoxLease {
+    /// The box's directory on the host, mounted at /box inside it
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    
+    /// `isolate --run` of the command with the job's limits, writing its report to `meta`
+    pub fn command(
+        &self,
+        cmd_args: &[String],
+        limits: &ResourceLimits,
+        env: Option<&HashMap<String, String>>,
+        options: &ExecutionOptions,
+        meta: &Path,
+    ) -> std::process::Command {
+        let mut command = std::process::Command::new(&self.pool.binary);
+        command.args(box_args(self.id, self.pool.cgroups));
+        command.args([
+            "--run",
+            "--silent",
+            &format!("--meta={}", meta.display()),
+            &format!("--time={}", limits.cpu_time),
+            &format!("--extra-time={}", limits.cpu_extra_time),
+            &format!("--wall-time={}", limits.wall_time),
+            &format!("--mem={}", limits.memory / 1024),
+            &format!("--stack={}", limits.stack_limit / 1024),
+            &format!("--fsize={}", limits.file_size / 1024),
+            &format!("--processes={}", limits.processes),
+            &format!("--env=PATH={}", BOX_PATH),
+            &format!("--env=HOME={}", BOX_WORKDIR),
+            "--env=LANG=C.UTF-8",
+        ]);
+        if options.enable_network {
+            command.arg("--share-net");
+        }
+        if options.redirect_stderr_to_stdout {
+            command.arg("--stderr-to-stdout");
+        }
+        for (key, value) in env.into_iter().flatten() {
+            command.arg(format!("--env={}={}", key, value));
+        }
+        command.arg("--").args(cmd_args);
+        command
+    }
+}
+
+impl Drop for BoxLease {
+    fn drop(&mut self) {
+        let pool = self.pool.clone();
+        let id = self.id;
+        let permit = self.permit.take();
+        tokio::spawn(async move {
+            match pool.isolate(id).arg("--cleanup").output().await {
+                Ok(output) if output.status.success() => {}
+                Ok(output) => warn!("Could not clean isolate box {}: {}", id, String::from_utf8_lossy(&output.stderr).trim()),
+                Err(err) => warn!("Could not clean isolate box {}: {}", id, err),
+            }
+            pool.free.lock().unwrap().push(id);
+            drop(permit);
+        });
+    }
+}
+
+/// What isolate's meta file says about a run
+#[derive(Debug, Default)]
+pub struct Meta {
+    /// CPU seconds
+    pub time: f64,
+    /// Wall-clock seconds
+    pub wall_time: Option<f64>,
+    /// Context switches the program asked for
+    pub voluntary_context_switches: Option<u64>,
+    /// Context switches forced on the program
+    pub involuntary_context_switches: Option<u64>,
+    /// Peak memory in bytes: the control group's when metered by cgroup, otherwise the largest process's
+    pub memory: u64,
+    /// Exit code of a program that exited normally
+    pub exit_code: Option<i32>,
+    /// Signal that killed the program
+    pub exit_signal: Option<i32>,
+    /// RE, SG, TO or XX when the run did not succeed
+    pub status: Option<String>,
+    /// The control group ran out of memory
+    pub oom_killed: bool,
+    /// isolate's explanation of a failed run
+    pub message: Option<String>,
+}
+
+impl Meta {
+    /// Read `key:value` lines, ignoring keys this engine does not use
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).context("isolate wrote no meta file")?;
+        let mut meta = Self::default();
+        let (mut max_rss, mut cg_mem) = (0, None);
+        
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            match key {
+                "time" => meta.time = value.parse().unwrap_or_default(),
+                "time-wall" => meta.wall_time = value.parse().ok(),
+                "csw-voluntary" => meta.voluntary_context_switches = value.parse().ok(),
+                "csw-forced" => meta.involuntary_context_switches = value.parse().ok(),
+                "max-rss" => max_rss = value.parse().unwrap_or_default(),
+                "cg-mem" => cg_mem = value.parse().ok(),
+                "exitcode" => meta.exit_code = value.parse().ok(),
+                "exitsig" => meta.exit_signal = value.parse().ok(),
+                "status" => meta.status = Some(value.to_string()),
+                "cg-oom-killed" => meta.oom_killed = true,
+                "message" => meta.message = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        meta.memory = cg_mem.unwrap_or(max_rss) * 1024;
+        Ok(meta)
+    }
+    
+    /// The run exceeded its CPU or wall time
+    pub fn timed_out(&self) -> bool {
+        self.status.as_deref() == Some("TO")
+    }
+}
+
+/// Copy the host working directory into a box
+pub fn copy_in(lease: &BoxLease, working_dir: &Path) -> Result<()> {
+    copy_tree(working_dir, lease.dir())
+}
+
+/// Copy a box's files back to the host working directory
+pub fn copy_out(lease: &BoxLease, working_dir: &Path) -> Result<()> {
+    copy_tree(lease.dir(), working_dir)
+}
+
+/// Copy files and directories, overwriting existing files and skipping anything else such as symlinks
+pub fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            std::fs::create_dir_all(&target)?;
+            copy_tree(&entry.path(), &target)?;
+        } else if file_type.is_file() {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::Duration;
+    
+    /// Stand-in for isolate that logs its arguments and lays out boxes under its own directory
+    fn fake_isolate(dir: &Path) -> IsolateConfig {
+        let script = dir.join("isolate");
+        std::fs::write(&script, format!(
+            "#!/bin/sh\necho \"$@\" >> {root}/calls\nfor arg; do case $arg in --box-id=*) id=${{arg#--box-id=}};; esac; done\ncase \"$*\" in\n  *--version*) echo 'isolate 2.0 (fake)';;\n  *--init*) mkdir -p {root}/$id/box && echo {root}/$id;;\n  *--cleanup*) rm -rf {root}/$id;;\nesac\n",
+            root = dir.display(),
+        )).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        IsolateConfig {
+            binary: script.display().to_string(),
+            boxes: 2,
+            first_box_id: 7,
+            cgroups: true,
+        }
+    }
+    
+    fn calls(dir: &Path) -> Vec<String> {
+        std::fs::read_to_string(dir.join("calls")).unwrap_or_default().lines().map(str::to_string).collect()
+    }
+    
+    #[test]
+    fn meta_files_report_usage_and_verdict() {
+        let dir = tempfile::tempdir().unwrap();
+        let meta = dir.path().join("meta");
+        
+        std::fs::write(&meta, "time:0.120\ntime-wall:0.250\nmax-rss:5000\ncg-mem:8000\ncsw-voluntary:3\ncsw-forced:4\nexitcode:0\n").unwrap();
+        let ok = Meta::read(&meta).unwrap();
+        assert_eq!((ok.time, ok.wall_time, ok.exit_code), (0.12, Some(0.25), Some(0)));
+        // The control group's peak wins over the largest process's
+        assert_eq!(ok.memory, 8000 * 1024);
+        assert_eq!((ok.voluntary_context_switches, ok.involuntary_context_switches), (Some(3), Some(4)));
+        assert!(ok.status.is_none() && !ok.timed_out() && !ok.oom_killed);
+        
+        std::fs::write(&meta, "time:1.010\nmax-rss:5000\nstatus:TO\nmessage:Time limit exceeded\nkilled:1\n").unwrap();
+        let timed_out = Meta::read(&meta).unwrap();
+        assert!(timed_out.timed_out());
+        assert_eq!(timed_out.memory, 5000 * 1024);
+        assert_eq!(timed_out.message.as_deref(), Some("Time limit exceeded"));
+        
+        std::fs::write(&meta, "status:SG\nexitsig:11\ncg-oom-killed:1\n").unwrap();
+        let signalled = Meta::read(&meta).unwrap();
+        assert_eq!((signalled.exit_signal, signalled.exit_code), (Some(11), None));
+        assert!(signalled.oom_killed && !signalled.timed_out());
+        
+        std::fs::write(&meta, "status:RE\nexitcode:3\n").unwrap();
+        assert_eq!(Meta::read(&meta).unwrap().exit_code, Some(3));
+        assert!(Meta::read(&dir.path().join("missing")).is_err());
+    }
+    
+    #[tokio::test]
+    async fn limits_become_isolate_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = BoxPool::from_config(&fake_isolate(dir.path())).unwrap();
+        let lease = pool.claim().await.unwrap();
+        let limits = ResourceLimits {
+            cpu_time: 2.0,
+            wall_time: 5.0,
+            memory: 256 * 1024 * 1024,
+            file_size: 1024 * 1024,
+            processes: 4,
+            ..Default::default()
+        };
+        let options = ExecutionOptions { redirect_stderr_to_stdout: true, ..Default::default() };
+        let env = HashMap::from([("GREETING".to_string(), "hello".to_string())]);
+        let command = lease.command(&["python3".to_string(), "main.py".to_string()], &limits, Some(&env), &options, Path::new("/tmp/meta"));
+        
+        let args: Vec<String> = command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect();
+        for flag in ["--cg", "--run", "--meta=/tmp/meta", "--time=2", "--wall-time=5", "--mem=262144", "--fsize=1024", "--processes=4", "--stderr-to-stdout", "--env=GREETING=hello"] {
+            assert!(args.iter().any(|arg| arg == flag), "{} missing from {:?}", flag, args);
+        }
+        assert!(args.iter().any(|arg| arg.starts_with("--box-id=")));
+        assert!(!args.iter().any(|arg| arg == "--share-net"));
+        assert_eq!(args[args.len() - 3..], ["--", "python3", "main.py"]);
+    }
+    
+    #[tokio::test]
+    async fn stale_boxes_are_cleaned_and_ids_are_never_shared() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = fake_isolate(dir.path());
+        // Left behind by a crashed engine
+        std::fs::create_dir_all(dir.path().join("7/box")).unwrap();
+        std::fs::write(dir.path().join("7/box/leftover"), "x").unwrap();
+        
+        let pool = BoxPool::from_config(&config).unwrap();
+        assert!(!dir.path().join("7").exists());
+        assert!(calls(dir.path()).iter().any(|call| call == "--box-id=8 --cg --cleanup"));
+        
+        let first = pool.claim().await.unwrap();
+        let second = pool.claim().await.unwrap();
+        assert_ne!(first.dir(), second.dir());
+        assert!(first.dir().is_dir() && !first.dir().join("leftover").exists());
+        
+        // A third job waits until a box has been cleaned and returned
+        let waiting = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.claim().await.unwrap().dir().to_path_buf() }
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiting.is_finished());
+        
+        let released = first.dir().to_path_buf();
+        std::fs::write(released.join("old"), "x").unwrap();
+        drop(first);
+        let reused = tokio::time::timeout(Duration::from_secs(5), waiting).await.unwrap().unwrap();
+        assert_eq!(reused, released);
+        assert!(!reused.join("old").exists());
+        drop(second);
+    }
+    
+    #[test]
+    fn copies_skip_symlinks() {
+        let from = tempfile::tempdir().unwrap();
+        let to = tempfile::tempdir().unwrap();
+        std::fs::create_dir(from.path().join("nested")).unwrap();
+        std::fs::write(from.path().join("nested/input.txt"), "1 2").unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", from.path().join("escape")).unwrap();
+        
+        copy_tree(from.path(), to.path()).unwrap();
+        assert_eq!(std::fs::read_to_string(to.path().join("nested/input.txt")).unwrap(), "1 2");
+        assert!(!to.path().join("escape").exists());
+    }
+}
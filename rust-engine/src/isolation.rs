@@ -0,0 +1,330 @@
+use crate::config::{RetentionConfig, SandboxConfig};
+use anyhow::{anyhow, Result};
+use std::ffi::{CStr, CString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::{info, warn};
+
+/// Where every isolated job gets its own tmpfs
+const TMP: &str = "/tmp";
+
+/// Options of the empty tmpfs laid over directories holding other jobs' files
+const COVER_OPTIONS: &CStr = c"size=1m,mode=755";
+
+/// Mount namespace in which a native job can only write its working directory and a private /tmp
+///
+/// The root filesystem is remounted read-only, the directories holding other jobs' working
+/// directories are covered by empty tmpfs mounts with only the job's own directory bound back in,
+/// and /proc is remounted with hidepid so other users' processes are invisible. Without root the
+/// namespace is created inside a user namespace mapping the engine's own uid, so the program keeps
+/// no capabilities to undo any of it.
+#[derive(Debug, Clone)]
+pub(crate) struct MountIsolation {
+    covered: Vec<PathBuf>, // parents of job directories that are not already under /tmp
+    tmp_bytes: u64,
+    hide_processes: bool,
+}
+
+impl MountIsolation {
+    /// Check what this host allows by isolating a trivial command
+    /// Returns None, with a warning, when the engine may not create mount namespaces at all
+    pub fn probe(config: &SandboxConfig, retention: &RetentionConfig) -> Option<Self> {
+        if !config.isolate_filesystem {
+            return None;
+        }
+        
+        // Other jobs' files: working directories, preserved ones and spilled outputs
+        let covered = [&config.temp_base, &retention.preserve_dir, &retention.spill_dir]
+            .into_iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .filter(|dir| !dir.starts_with(TMP))
+            .collect();
+        let mut isolation = Self {
+            covered,
+            tmp_bytes: config.private_tmp_bytes,
+            hide_processes: procfs_per_mount(),
+        };
+        
+        if isolation.hide_processes && isolation.try_run(&config.temp_base).is_err() {
+            isolation.hide_processes = false;
+        }
+        match isolation.try_run(&config.temp_base) {
+            Ok(()) if isolation.hide_processes => {
+                info!("🔒 Native jobs run with a read-only root, a private /tmp and hidden processes");
+                Some(isolation)
+            }
+            Ok(()) => {
+                warn!("Native jobs run with a read-only root and a private /tmp, but other processes stay visible in /proc");
+                Some(isolation)
+            }
+            Err(err) => {
+                warn!("Native jobs run without filesystem isolation, mount namespaces are unavailable: {}", err);
+                None
+            }
+        }
+    }
+    
+    /// Enter the namespace in the child, before the program starts, with `workdir` as its writable directory
+    pub fn apply(&self, command: &mut Command, workdir: &Path) -> Result<()> {
+        use std::os::unix::process::CommandExt;
+        
+        let plan = Plan::new(self, workdir)?;
+        unsafe {
+            command.pre_exec(move || plan.enter());
+        }
+        // Caches that default to $HOME land in the private /tmp instead of failing on the read-only root
+        command.env("XDG_CACHE_HOME", "/tmp/.cache");
+        Ok(())
+    }
+    
+    /// Isolate `true` in a scratch directory
+    fn try_run(&self, temp_base: &Path) -> Result<()> {
+        let scratch = tempfile::TempDir::new_in(temp_base)?;
+        let mut command = Command::new("true");
+        command.current_dir(scratch.path()).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+        self.apply(&mut command, scratch.path())?;
+        
+        let status = command.status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!("isolated probe exited with {}", status))
+        }
+    }
+}
+
+/// Whether mounting /proc creates an instance with its own options (Linux 5.8+)
+/// Older kernels share one instance, so hidepid would also apply to the host's /proc
+fn procfs_per_mount() -> bool {
+    let mut uts: libc::utsname = unsafe { std::mem::zeroed() };
+    if unsafe { libc::uname(&mut uts) } != 0 {
+        return false;
+    }
+    let release = unsafe { CStr::from_ptr(uts.release.as_ptr()) }.to_string_lossy();
+    let mut version = release.split(|c: char| !c.is_ascii_digit()).filter_map(|part| part.parse::<u32>().ok());
+    (version.next().unwrap_or(0), version.next().unwrap_or(0)) >= (5, 8)
+}
+
+/// Everything the child needs, allocated before the fork
+struct Plan {
+    id_maps: Option<(CString, CString)>, // uid_map and gid_map contents when a user namespace is needed
+    workdir: CString,
+    tmp_options: CString,
+    covered: Vec<CString>,
+    parents: Vec<CString>, // directories recreated down to the workdir, outermost first
+    hide_processes: bool,
+}
+
+impl Plan {
+    fn new(isolation: &MountIsolation, workdir: &Path) -> Result<Self> {
+        let workdir = workdir.canonicalize()?;
+        let cover = isolation.covered
+            .iter()
+            .find(|dir| workdir.starts_with(dir))
+            .map_or(Path::new(TMP), PathBuf::as_path);
+        if !workdir.starts_with(cover) {
+            return Err(anyhow!("working directory {} is outside the isolated directories", workdir.display()));
+        }
+        
+        let mut parents: Vec<CString> = workdir.ancestors()
+            .take_while(|dir| *dir != cover)
+            .map(c_path)
+            .collect::<Result<_>>()?;
+        parents.reverse();
+        
+        // Root can create the mount namespace directly; anyone else needs a user namespace first
+        let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+        let id_maps = match uid {
+            0 => None,
+            _ => Some((CString::new(format!("{0} {0} 1", uid))?, CString::new(format!("{0} {0} 1", gid))?)),
+        };
+        
+        Ok(Self {
+            id_maps,
+            workdir: c_path(&workdir)?,
+            tmp_options: CString::new(format!("size={},mode=1777", isolation.tmp_bytes))?,
+            covered: isolation.covered.iter().map(|dir| c_path(dir)).collect::<Result<_>>()?,
+            parents,
+            hide_processes: isolation.hide_processes,
+        })
+    }
+    
+    /// Runs between fork and exec, so it only makes system calls on what `new` prepared
+    fn enter(&self) -> std::io::Result<()> {
+        use libc::{MS_BIND, MS_NODEV, MS_NOEXEC, MS_NOSUID, MS_PRIVATE, MS_RDONLY, MS_REC, MS_REMOUNT};
+        
+        unsafe {
+            match &self.id_maps {
+                Some((uid_map, gid_map)) => {
+                    check(libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNS))?;
+                    write_file(c"/proc/self/setgroups", c"deny")?;
+                    write_file(c"/proc/self/uid_map", uid_map)?;
+                    write_file(c"/proc/self/gid_map", gid_map)?;
+                }
+                None => {
+                    check(libc::unshare(libc::CLONE_NEWNS))?;
+                }
+            }
+            // Nothing done here may propagate back to the host
+            mount(None, c"/", None, MS_REC | MS_PRIVATE, None)?;
+            
+            // Held open so the directory can be bound back once its parent is covered
+            let workdir = check(libc::open(self.workdir.as_ptr(), libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC))?;
+            
+            let read_only = libc::mount_attr {
+                attr_set: libc::MOUNT_ATTR_RDONLY,
+                attr_clr: 0,
+                propagation: 0,
+                userns_fd: 0,
+            };
+            let recursive = libc::syscall(
+                libc::SYS_mount_setattr,
+                libc::AT_FDCWD,
+                c"/".as_ptr(),
+                libc::AT_RECURSIVE,
+                &read_only,
+                std::mem::size_of::<libc::mount_attr>(),
+            );
+            if recursive != 0 {
+                // Before Linux 5.12 only the root mount itself can be made read-only
+                mount(Some(c"/"), c"/", None, MS_BIND | MS_REC, None)?;
+                mount(None, c"/", None, MS_REMOUNT | MS_BIND | MS_RDONLY, None)?;
+            }
+            
+            mount(Some(c"tmpfs"), c"/tmp", Some(c"tmpfs"), MS_NOSUID | MS_NODEV, Some(&self.tmp_options))?;
+            for dir in &self.covered {
+                mount(Some(c"tmpfs"), dir, Some(c"tmpfs"), MS_NOSUID | MS_NODEV, Some(COVER_OPTIONS))?;
+            }
+            for dir in &self.parents {
+                if libc::mkdir(dir.as_ptr(), 0o755) != 0 {
+                    let err = std::io::Error::last_os_error();
+                    if err.raw_os_error() != Some(libc::EEXIST) {
+                        return Err(err);
+                    }
+                }
+            }
+            
+            let mut source = *b"/proc/self/fd/\0\0\0\0\0\0\0\0\0\0\0";
+            format_fd(&mut source[14..], workdir);
+            mount(Some(CStr::from_bytes_until_nul(&source).unwrap_or(c"")), &self.workdir, None, MS_BIND, None)?;
+            // The bind copies the read-only flag of the mount it came from
+            mount(None, &self.workdir, None, MS_REMOUNT | MS_BIND | MS_NOSUID | MS_NODEV, None)?;
+            libc::close(workdir);
+            for dir in &self.covered {
+                mount(None, dir, None, MS_REMOUNT | MS_BIND | MS_RDONLY | MS_NOSUID | MS_NODEV, None)?;
+            }
+            
+            if self.hide_processes {
+                mount(Some(c"proc"), c"/proc", Some(c"proc"), MS_NOSUID | MS_NODEV | MS_NOEXEC, Some(c"hidepid=2"))?;
+            }
+            
+            // The cwd set before this ran still points into the read-only mount
+            check(libc::chdir(self.workdir.as_ptr()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Path as a C string
+fn c_path(path: &Path) -> Result<CString> {
+    Ok(CString::new(path.as_os_str().as_bytes())?)
+}
+
+/// Turn a -1 return into the errno it set
+fn check(result: libc::c_int) -> std::io::Result<libc::c_int> {
+    if result == -1 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+/// mount(2) with optional source, filesystem type and data
+unsafe fn mount(
+    source: Option<&CStr>,
+    target: &CStr,
+    fstype: Option<&CStr>,
+    flags: libc::c_ulong,
+    data: Option<&CStr>,
+) -> std::io::Result<()> {
+    let ptr = |value: Option<&CStr>| value.map_or(std::ptr::null(), |value| value.as_ptr());
+    check(libc::mount(ptr(source), target.as_ptr(), ptr(fstype), flags, ptr(data).cast())).map(drop)
+}
+
+/// Write a whole file without allocating
+unsafe fn write_file(path: &CStr, contents: &CStr) -> std::io::Result<()> {
+    let fd = check(libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC))?;
+    let bytes = contents.to_bytes();
+    let written = libc::write(fd, bytes.as_ptr().cast(), bytes.len());
+    libc::close(fd);
+    if written == bytes.len() as isize {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Decimal digits of a file descriptor into a zeroed buffer, without allocating
+fn format_fd(buffer: &mut [u8], fd: libc::c_int) {
+    let mut digits = [0u8; 10];
+    let mut value = fd.max(0) as u32;
+    let mut count = 0;
+    loop {
+        digits[count] = b'0' + (value % 10) as u8;
+        count += 1;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    for (slot, digit) in buffer.iter_mut().zip(digits[..count].iter().rev()) {
+        *slot = *digit;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn isolation(covered: &Path) -> MountIsolation {
+        MountIsolation {
+            covered: vec![covered.to_path_buf()],
+            tmp_bytes: 1024,
+            hide_processes: false,
+        }
+    }
+    
+    #[test]
+    fn descriptors_print_as_decimal() {
+        for (fd, text) in [(0, "0"), (7, "7"), (42, "42"), (1_000_000, "1000000")] {
+            let mut buffer = [0u8; 11];
+            format_fd(&mut buffer, fd);
+            assert_eq!(CStr::from_bytes_until_nul(&buffer).unwrap().to_str().unwrap(), text);
+        }
+    }
+    
+    #[test]
+    fn plans_recreate_the_parents_below_the_covered_directory() {
+        let base = tempfile::TempDir::new_in(std::env::current_dir().unwrap()).unwrap();
+        let base_path = base.path().canonicalize().unwrap();
+        let workdir = base_path.join("a/b/job");
+        std::fs::create_dir_all(&workdir).unwrap();
+        
+        let plan = Plan::new(&isolation(&base_path), &workdir).unwrap();
+        let parents: Vec<PathBuf> = plan.parents.iter().map(|dir| PathBuf::from(dir.to_str().unwrap())).collect();
+        assert_eq!(parents, [base_path.join("a"), base_path.join("a/b"), workdir.clone()]);
+        assert_eq!(plan.tmp_options.to_str().unwrap(), "size=1024,mode=1777");
+    }
+    
+    #[test]
+    fn plans_refuse_working_directories_outside_the_isolated_ones() {
+        let base = tempfile::TempDir::new_in(std::env::current_dir().unwrap()).unwrap();
+        let elsewhere = tempfile::TempDir::new_in(std::env::current_dir().unwrap()).unwrap();
+        let base_path = base.path().canonicalize().unwrap();
+        
+        assert!(Plan::new(&isolation(&base_path), elsewhere.path()).is_err());
+        // Not under /tmp either
+        assert!(Plan::new(&isolation(&base_path), Path::new("/")).is_err());
+    }
+}
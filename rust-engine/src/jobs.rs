@@ -0,0 +1,100 @@
+use crate::types::ExecutionJob;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use tokio::sync::RwLock;
+
+/// Number of independently locked shards; a power of two keeps the index a mask
+const SHARD_COUNT: usize = 16;
+
+/// Jobs by id, split across shards so status polls and worker updates on different jobs don't contend
+pub struct JobTable {
+    shards: Vec<RwLock<HashMap<String, ExecutionJob>>>,
+    hasher: RandomState,
+}
+
+impl JobTable {
+    pub fn new() -> Self {
+        Self {
+            shards: (0..SHARD_COUNT).map(|_| RwLock::new(HashMap::new())).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+    
+    /// The shard holding `id`; lock it for any read-modify-write of that job
+    pub fn shard(&self, id: &str) -> &RwLock<HashMap<String, ExecutionJob>> {
+        let index = self.hasher.hash_one(id) as usize & (SHARD_COUNT - 1);
+        &self.shards[index]
+    }
+    
+    /// Copy of a job; cheap because results are shared rather than cloned
+    pub async fn get(&self, id: &str) -> Option<ExecutionJob> {
+        self.shard(id).read().await.get(id).cloned()
+    }
+    
+    /// Read part of a job under its shard lock, without copying the rest
+    pub async fn read<T>(&self, id: &str, f: impl FnOnce(&ExecutionJob) -> T) -> Option<T> {
+        self.shard(id).read().await.get(id).map(f)
+    }
+    
+    /// Add or replace a job
+    pub async fn insert(&self, job: ExecutionJob) {
+        self.shard(&job.id).write().await.insert(job.id.clone(), job);
+    }
+    
+    /// Every shard in turn, for the rare operations that touch all jobs
+    pub fn shards(&self) -> impl Iterator<Item = &RwLock<HashMap<String, ExecutionJob>>> {
+        self.shards.iter()
+    }
+}
+
+impl Default for JobTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ExecutionResult;
+    use std::sync::Arc;
+    
+    fn job(id: &str) -> ExecutionJob {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "request": { "id": id, "language": "python", "source_code": "print(1)" },
+            "status": "completed",
+            "created_at": "2030-01-01T00:00:00Z",
+            "started_at": null,
+            "finished_at": null,
+            "result": { "id": id, "status": "completed", "stdout": "x".repeat(1 << 20), "created_at": "2030-01-01T00:00:00Z", "compile_cache_hit": false },
+        })).unwrap()
+    }
+    
+    #[test]
+    fn ids_spread_over_every_shard() {
+        let table = JobTable::new();
+        let mut used = vec![false; SHARD_COUNT];
+        for n in 0..1000 {
+            let shard = table.shard(&format!("job-{}", n));
+            let index = table.shards().position(|candidate| std::ptr::eq(candidate, shard)).unwrap();
+            used[index] = true;
+        }
+        assert!(used.iter().all(|&used| used), "{:?}", used);
+        
+        // The same id always lands on the same shard
+        assert!(std::ptr::eq(table.shard("job-1"), table.shard("job-1")));
+    }
+    
+    #[tokio::test]
+    async fn copies_share_the_stored_result() {
+        let table = JobTable::new();
+        table.insert(job("big")).await;
+        let stored: Arc<ExecutionResult> = table.shard("big").read().await["big"].result.clone().unwrap();
+        
+        let copy = table.get("big").await.unwrap();
+        assert!(Arc::ptr_eq(copy.result.as_ref().unwrap(), &stored), "the megabyte stdout must not be copied");
+        assert!(table.get("missing").await.is_none());
+    }
+}
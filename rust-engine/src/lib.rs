@@ -0,0 +1,80 @@
+//! Sandboxed code execution engine.
+//!
+//! [`ExecutionEngine`] queues submissions and runs them on a pool of workers through the
+//! [`executor::CodeExecutor`], which compiles and runs each program under the [`sandbox`] limits.
+//! The HTTP and gRPC frontends in `src/bin/server` are thin layers over this crate; embedders
+//! can drive the engine directly:
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use labforcode_engine::types::SubmissionContext;
+//! use labforcode_engine::ExecutionEngine;
+//!
+//! let engine = ExecutionEngine::builder().max_concurrent(4).build().await?;
+//! let request = serde_json::from_value(serde_json::json!({
+//!     "language": "python",
+//!     "source_code": "print('hi')",
+//! }))?;
+//! let queued = engine.submit_execution(request, SubmissionContext::default()).await?;
+//! engine.wait_for_completion(&queued.id, std::time::Duration::from_secs(30)).await?;
+//! let result = engine.get_result(&queued.id).await?;
+//! # Ok(())
+//! # }
+//! ```
+#![warn(missing_docs)]
+
+mod archive;
+/// Lifecycle events of each job, kept on the job and optionally written to AUDIT_LOG_PATH
+pub mod audit;
+mod autoscale;
+pub mod callback;
+mod compile_cache;
+pub mod config;
+mod dedupe;
+pub mod diagnostics;
+mod docker;
+mod fetch;
+/// The job lifecycle: admission, queueing, workers, retries and results
+pub mod engine;
+/// Compiling and running one submission under its limits
+pub mod executor;
+mod histogram;
+mod isolate;
+#[cfg(unix)]
+mod isolation;
+/// Replaying responses to retried submissions that carry an idempotency key
+pub mod idempotency;
+mod jobs;
+/// Filtering and capping program output as it is read
+pub mod output;
+/// Deferring new jobs while the host is short of memory
+pub mod pressure;
+pub mod proto;
+/// Queues jobs wait in before a worker takes them
+pub mod queue;
+/// Per-client usage accounting against daily and monthly quotas
+pub mod quota;
+/// Per-client request rate and concurrency limits
+pub mod rate_limit;
+/// Rolling statistics windows and the latest finished executions
+pub mod recent;
+mod redis_queue;
+/// rlimits and process setup applied to every child
+pub mod sandbox;
+/// Outputs too large for a result, served from files instead
+pub mod spill;
+mod store;
+/// Prometheus metrics recorded by the engine
+pub mod telemetry;
+/// Requests, results and the other types shared by the engine and its frontends
+pub mod types;
+/// Checking submissions against the server ceilings
+pub mod validation;
+/// Startup warm-up and the offline smoke check built on it
+pub mod warmup;
+mod watchdog;
+/// Working directories preserved for inspection after a job finishes
+pub mod workdir;
+
+pub use config::EngineConfig;
+pub use engine::{EngineBuilder, ExecutionEngine};
@@ -0,0 +1,329 @@
+use crate::types::{ExecutionOptions, OutputFilter};
+use std::borrow::Cow;
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// Output as the job's options ask for it to be stored; unchanged when no filtering was requested
+pub fn clean<'a>(bytes: &'a [u8], options: &ExecutionOptions) -> Cow<'a, [u8]> {
+    if options.output_filter == OutputFilter::None && !options.normalize_newlines {
+        return Cow::Borrowed(bytes);
+    }
+    let mut cleaner = OutputCleaner::new(options.output_filter, options.normalize_newlines);
+    let mut cleaned = Vec::with_capacity(bytes.len());
+    cleaner.push(bytes, &mut cleaned);
+    cleaner.finish(&mut cleaned);
+    Cow::Owned(cleaned)
+}
+
+/// Where the cleaner is inside an escape sequence
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Text,
+    Escape,       // after ESC
+    Sequence,     // CSI parameters, or the intermediates of a plain escape, up to the final byte
+    String,       // OSC, DCS, SOS, PM or APC payload, up to BEL or ESC \
+    StringEscape, // ESC inside a string, possibly starting its terminator
+}
+
+/// Removes escape sequences and control characters from output fed to it in chunks
+///
+/// The state carries over between calls, so a sequence split across two reads is still removed
+/// whole, and the filter can sit in front of anything that caps or streams output. ECMA-48
+/// sequences are dropped entirely rather than rendered: colors, cursor movement and window
+/// titles all disappear.
+pub struct OutputCleaner {
+    filter: OutputFilter,
+    normalize_newlines: bool,
+    state: State,
+    pending_cr: bool, // a CR that becomes LF if the next byte is LF
+}
+
+impl OutputCleaner {
+    /// Cleaner applying `filter`, and turning CRLF into LF when `normalize_newlines` is set
+    pub fn new(filter: OutputFilter, normalize_newlines: bool) -> Self {
+        Self {
+            filter,
+            normalize_newlines,
+            state: State::Text,
+            pending_cr: false,
+        }
+    }
+    
+    /// Filter the next chunk of output, appending what survives to `out`
+    pub fn push(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        for &byte in input {
+            if self.filter == OutputFilter::None {
+                self.emit(byte, out);
+            } else {
+                self.filter_byte(byte, out);
+            }
+        }
+    }
+    
+    /// Flush a trailing CR; an unterminated escape sequence is dropped
+    pub fn finish(&mut self, out: &mut Vec<u8>) {
+        if std::mem::take(&mut self.pending_cr) {
+            out.push(b'\r');
+        }
+        self.state = State::Text;
+    }
+    
+    fn filter_byte(&mut self, byte: u8, out: &mut Vec<u8>) {
+        match self.state {
+            State::Text if byte == ESC => self.state = State::Escape,
+            State::Text => {
+                if self.filter == OutputFilter::StripControl && is_stripped_control(byte) {
+                    return;
+                }
+                self.emit(byte, out);
+            }
+            State::Escape => match byte {
+                b'[' | 0x20..=0x2f => self.state = State::Sequence,
+                b']' | b'P' | b'X' | b'^' | b'_' => self.state = State::String,
+                0x30..=0x7e => self.state = State::Text,
+                // Not a sequence after all: the ESC goes and the byte is text again
+                _ => {
+                    self.state = State::Text;
+                    self.filter_byte(byte, out);
+                }
+            },
+            State::Sequence => match byte {
+                0x20..=0x3f => {}
+                0x40..=0x7e => self.state = State::Text,
+                _ => {
+                    self.state = State::Text;
+                    self.filter_byte(byte, out);
+                }
+            },
+            State::String => match byte {
+                BEL => self.state = State::Text,
+                ESC => self.state = State::StringEscape,
+                _ => {}
+            },
+            State::StringEscape => {
+                self.state = match byte {
+                    b'\\' => State::Text,
+                    ESC => State::StringEscape,
+                    _ => State::String,
+                };
+            }
+        }
+    }
+    
+    fn emit(&mut self, byte: u8, out: &mut Vec<u8>) {
+        if !self.normalize_newlines {
+            out.push(byte);
+            return;
+        }
+        if std::mem::take(&mut self.pending_cr) && byte != b'\n' {
+            out.push(b'\r');
+        }
+        if byte == b'\r' {
+            self.pending_cr = true;
+        } else {
+            out.push(byte);
+        }
+    }
+}
+
+/// C0 controls and DEL, except the tab, newline and carriage return that text layout needs
+fn is_stripped_control(byte: u8) -> bool {
+    (byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r')) || byte == 0x7f
+}
+
+/// Keeps the first `limit` bytes of output fed to it in chunks, so a runaway program cannot exhaust memory.
+///
+/// Output past the limit is read and dropped, which keeps the program from blocking on a full pipe. The
+/// cut never splits a multi-byte UTF-8 character: the bytes of a character the limit falls inside are
+/// dropped with the rest, so text stays valid UTF-8 and a prefix of what was written. Binary output
+/// loses at most three bytes more than the limit requires.
+///
+/// ```
+/// use labforcode_engine::output::CappedOutput;
+///
+/// let mut capped = CappedOutput::new(5);
+/// capped.push("abcd€".as_bytes()); // the euro sign would end past the limit
+/// assert_eq!(capped.finish(), (b"abcd".to_vec(), true));
+/// ```
+#[derive(Debug)]
+pub struct CappedOutput {
+    limit: usize,
+    kept: Vec<u8>,
+    truncated: bool,
+}
+
+impl CappedOutput {
+    /// Keep at most `limit` bytes
+    pub fn new(limit: usize) -> Self {
+        Self { limit, kept: Vec::new(), truncated: false }
+    }
+    
+    /// Take the next chunk of output; anything past the limit is dropped
+    pub fn push(&mut self, chunk: &[u8]) {
+        if self.truncated {
+            return;
+        }
+        let room = self.limit - self.kept.len();
+        if chunk.len() <= room {
+            self.kept.extend_from_slice(chunk);
+            return;
+        }
+        self.kept.extend_from_slice(&chunk[..room]);
+        self.kept.truncate(char_boundary(&self.kept));
+        self.truncated = true;
+    }
+    
+    /// The bytes kept, and whether anything was dropped
+    pub fn finish(self) -> (Vec<u8>, bool) {
+        (self.kept, self.truncated)
+    }
+}
+
+/// Length of `bytes` without a trailing character that was cut short
+fn char_boundary(bytes: &[u8]) -> usize {
+    // The last lead byte is at most three bytes back in a character cut short
+    for back in 1..=bytes.len().min(4) {
+        let byte = bytes[bytes.len() - back];
+        if byte & 0b1100_0000 == 0b1000_0000 {
+            continue;
+        }
+        let width = match byte {
+            0xf0..=0xf7 => 4,
+            0xe0..=0xef => 3,
+            0xc0..=0xdf => 2,
+            _ => 1,
+        };
+        return if width > back { bytes.len() - back } else { bytes.len() };
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OutputEncoding;
+    
+    /// xorshift, so failures reproduce
+    struct Rng(u64);
+    
+    impl Rng {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+    }
+    
+    /// Feed `bytes` in chunks of one to seven bytes
+    fn capped(bytes: &[u8], limit: usize, rng: &mut Rng) -> (Vec<u8>, bool) {
+        let mut capped = CappedOutput::new(limit);
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let (chunk, tail) = rest.split_at((1 + rng.below(7)).min(rest.len()));
+            capped.push(chunk);
+            rest = tail;
+        }
+        capped.finish()
+    }
+    
+    #[test]
+    fn capped_text_is_a_valid_prefix_of_the_original() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let text: String = (0..rng.below(200)).map(|_| ['a', 'é', '€', '😀', '\n'][rng.below(5)]).collect();
+            let limit = rng.below(text.len() + 8);
+            let (kept, truncated) = capped(text.as_bytes(), limit, &mut rng);
+            
+            let kept = std::str::from_utf8(&kept).expect("always valid UTF-8");
+            assert!(text.starts_with(kept));
+            assert_eq!(truncated, text.len() > limit);
+            assert!(kept.len() <= limit && (truncated || kept.len() == text.len()));
+            // Only the character the limit fell inside is lost
+            assert!(!truncated || kept.len() + 4 > limit, "{} of {} kept", kept.len(), limit);
+        }
+    }
+    
+    #[test]
+    fn binary_output_loses_at_most_three_extra_bytes() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..2000 {
+            let bytes: Vec<u8> = (0..rng.below(100)).map(|_| rng.below(256) as u8).collect();
+            let limit = rng.below(bytes.len() + 1);
+            let (kept, truncated) = capped(&bytes, limit, &mut rng);
+            
+            assert!(bytes.starts_with(&kept));
+            assert_eq!(truncated, bytes.len() > limit);
+            assert!(!truncated || kept.len() + 3 >= limit);
+        }
+    }
+    
+    #[test]
+    fn cuts_only_drop_a_character_cut_short() {
+        assert_eq!(char_boundary("a€".as_bytes()), 4);
+        assert_eq!(char_boundary(&"a€".as_bytes()[..3]), 1);
+        assert_eq!(char_boundary(&"😀".as_bytes()[..1]), 0);
+        assert_eq!(char_boundary(b"\xff\xfe"), 2);
+        assert_eq!(char_boundary(b""), 0);
+    }
+    
+    #[test]
+    fn encodings_are_told_apart() {
+        assert_eq!(OutputEncoding::of("naïve 😀".as_bytes()), OutputEncoding::Utf8);
+        assert_eq!(OutputEncoding::of(&[b'a', 0xff]), OutputEncoding::Binary);
+        assert_eq!(OutputEncoding::of(&"€".as_bytes()[..2]), OutputEncoding::Binary);
+        assert!(OutputEncoding::Binary > OutputEncoding::Utf8);
+    }
+    
+    /// Run `bytes` through a cleaner in chunks of `chunk` bytes
+    fn cleaned(bytes: &[u8], filter: OutputFilter, normalize_newlines: bool, chunk: usize) -> String {
+        let mut cleaner = OutputCleaner::new(filter, normalize_newlines);
+        let mut out = Vec::new();
+        for piece in bytes.chunks(chunk) {
+            cleaner.push(piece, &mut out);
+        }
+        cleaner.finish(&mut out);
+        String::from_utf8(out).unwrap()
+    }
+    
+    #[test]
+    fn colored_gcc_diagnostics_lose_their_escapes() {
+        // gcc -fdiagnostics-color=always, with its bold, colors, resets and URL hyperlinks
+        let gcc = b"\x1b[01m\x1b[Kmain.c:3:5:\x1b[m\x1b[K \x1b[01;31m\x1b[Kerror: \x1b[m\x1b[K'x' undeclared (first use in this function) [\x1b]8;;https://gcc.gnu.org/onlinedocs/gcc/Warning-Options.html\x07\x1b[01;35m-Wimplicit\x1b[m\x1b]8;;\x1b\\]\n    3 |     \x1b[01;31m\x1b[Kx\x1b[m\x1b[K = 1;\n";
+        let expected = "main.c:3:5: error: 'x' undeclared (first use in this function) [-Wimplicit]\n    3 |     x = 1;\n";
+        // Split sequences, down to a byte at a time, are removed whole
+        for chunk in [1, 2, 3, 7, gcc.len()] {
+            assert_eq!(cleaned(gcc, OutputFilter::StripAnsi, false, chunk), expected, "chunks of {}", chunk);
+            assert_eq!(cleaned(gcc, OutputFilter::StripControl, false, chunk), expected, "chunks of {}", chunk);
+        }
+        assert_eq!(cleaned(gcc, OutputFilter::None, false, 4).as_bytes(), gcc);
+    }
+    
+    #[test]
+    fn nul_and_backspace_go_only_with_strip_control() {
+        let text = b"a\x00b\x08c\td\x7f\x1b[2Ke\r\n";
+        assert_eq!(cleaned(text, OutputFilter::StripAnsi, false, 3), "a\x00b\x08c\td\x7fe\r\n");
+        assert_eq!(cleaned(text, OutputFilter::StripControl, false, 3), "abc\tde\r\n");
+        // A lone ESC is not the start of anything and goes with the escapes
+        assert_eq!(cleaned(b"x\x1b\x01y", OutputFilter::StripControl, false, 1), "xy");
+    }
+    
+    #[test]
+    fn crlf_becomes_lf_independently_of_the_filter() {
+        let text = b"one\r\ntwo\rthree\r\n\r";
+        for chunk in [1, 4, text.len()] {
+            assert_eq!(cleaned(text, OutputFilter::None, true, chunk), "one\ntwo\rthree\n\r");
+            assert_eq!(cleaned(text, OutputFilter::StripAnsi, true, chunk), "one\ntwo\rthree\n\r");
+        }
+        assert_eq!(cleaned(text, OutputFilter::None, false, 1), "one\r\ntwo\rthree\r\n\r");
+    }
+    
+    #[test]
+    fn unfiltered_output_is_not_copied() {
+        let options = ExecutionOptions::default();
+        assert!(matches!(clean(b"\x1b[31mred\x1b[0m", &options), Cow::Borrowed(_)));
+        let options = ExecutionOptions { output_filter: OutputFilter::StripAnsi, ..Default::default() };
+        assert_eq!(clean(b"\x1b[31mred\x1b[0m", &options).as_ref(), b"red");
+    }
+}
@@ -0,0 +1,280 @@
+use crate::types::DiskSpace;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often the watchdog samples available memory
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Source of the host's available memory
+pub trait MemoryReader: Send + Sync {
+    /// Bytes that can be allocated without swapping, None when the host does not say
+    fn available_bytes(&self) -> Option<u64>;
+}
+
+/// Available memory as the operating system reports it (MemAvailable on Linux)
+pub struct SystemMemory;
+
+impl MemoryReader for SystemMemory {
+    fn available_bytes(&self) -> Option<u64> {
+        let mut system = sysinfo::System::new();
+        system.refresh_memory();
+        match system.available_memory() {
+            0 => None,
+            available => Some(available),
+        }
+    }
+}
+
+/// Keeps workers from taking new jobs while the host is short of memory
+///
+/// Jobs already running are left alone; only dequeues are deferred until available memory is
+/// back above the floor. A floor of 0 disables the watchdog.
+pub(crate) struct MemoryGuard {
+    floor: u64,
+    reader: Arc<dyn MemoryReader>,
+    under_pressure: AtomicBool,
+    deferred: AtomicU64, // dequeues skipped while jobs were waiting
+}
+
+impl MemoryGuard {
+    pub fn new(floor: u64, reader: Arc<dyn MemoryReader>) -> Self {
+        Self {
+            floor,
+            reader,
+            under_pressure: AtomicBool::new(false),
+            deferred: AtomicU64::new(0),
+        }
+    }
+    
+    pub fn enabled(&self) -> bool {
+        self.floor > 0
+    }
+    
+    /// Read available memory and update the pressure state, logging each change
+    pub fn sample(&self) {
+        // An unreadable value never blocks the queue
+        let Some(available) = self.reader.available_bytes() else {
+            return;
+        };
+        let pressure = available < self.floor;
+        if self.under_pressure.swap(pressure, Ordering::SeqCst) != pressure {
+            if pressure {
+                warn!("🧯 Memory pressure: {} MB available, below the {} MB floor; new jobs wait", available >> 20, self.floor >> 20);
+            } else {
+                info!("Memory pressure over: {} MB available", available >> 20);
+            }
+        }
+    }
+    
+    pub fn under_pressure(&self) -> bool {
+        self.under_pressure.load(Ordering::SeqCst)
+    }
+    
+    /// Count a dequeue skipped because of pressure
+    pub fn defer(&self) {
+        self.deferred.fetch_add(1, Ordering::SeqCst);
+    }
+    
+    pub fn deferred(&self) -> u64 {
+        self.deferred.load(Ordering::SeqCst)
+    }
+}
+
+/// Source of the size and free space of the volume holding a path
+pub trait DiskReader: Send + Sync {
+    /// Total and available bytes of the filesystem `path` is on, None when it cannot be read
+    fn space(&self, path: &Path) -> Option<(u64, u64)>;
+}
+
+/// Filesystem statistics as the operating system reports them (statvfs)
+pub struct SystemDisk;
+
+impl DiskReader for SystemDisk {
+    #[cfg(unix)]
+    fn space(&self, path: &Path) -> Option<(u64, u64)> {
+        use std::os::unix::ffi::OsStrExt;
+        
+        let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+            return None;
+        }
+        let block = stats.f_frsize as u64;
+        // f_bavail rather than f_bfree: blocks reserved for root are no use to jobs
+        Some((stats.f_blocks as u64 * block, stats.f_bavail as u64 * block))
+    }
+    
+    #[cfg(not(unix))]
+    fn space(&self, _path: &Path) -> Option<(u64, u64)> {
+        None
+    }
+}
+
+/// Keeps jobs from starting while the volume under TEMP_BASE is short of space
+///
+/// A job that finds less than the floor free fails at once with an "insufficient disk space"
+/// error instead of running into ENOSPC halfway through. A floor of 0 disables the check.
+pub(crate) struct DiskGuard {
+    path: PathBuf,
+    floor: u64,
+    reader: Arc<dyn DiskReader>,
+    low: AtomicBool,
+}
+
+impl DiskGuard {
+    pub fn new(path: PathBuf, floor: u64, reader: Arc<dyn DiskReader>) -> Self {
+        Self {
+            path,
+            floor,
+            reader,
+            low: AtomicBool::new(false),
+        }
+    }
+    
+    /// The same check against another source of filesystem statistics
+    pub fn with_reader(&self, reader: Arc<dyn DiskReader>) -> Self {
+        Self::new(self.path.clone(), self.floor, reader)
+    }
+    
+    /// Read the volume's free space and update the low-space state, logging each change
+    pub fn sample(&self) -> Option<DiskSpace> {
+        // An unreadable volume never stops jobs
+        let (total, free) = self.reader.space(&self.path)?;
+        let low = self.floor > 0 && free < self.floor;
+        if self.low.swap(low, Ordering::SeqCst) != low {
+            if low {
+                warn!("💾 Low disk space: {} MB free in {}, below the {} MB floor; new jobs fail", free >> 20, self.path.display(), self.floor >> 20);
+            } else {
+                info!("Disk space recovered: {} MB free in {}", free >> 20, self.path.display());
+            }
+        }
+        Some(DiskSpace {
+            path: self.path.display().to_string(),
+            total_bytes: total,
+            free_bytes: free,
+            used_bytes: total.saturating_sub(free),
+        })
+    }
+    
+    /// Sample the volume, explaining why a job cannot start when it is below the floor
+    pub fn check(&self) -> Result<(), String> {
+        match self.sample() {
+            Some(space) if self.low() => Err(format!(
+                "insufficient disk space: {} MB free in TEMP_BASE ({}), below TEMP_MIN_FREE_BYTES of {} MB",
+                space.free_bytes >> 20, space.path, self.floor >> 20
+            )),
+            _ => Ok(()),
+        }
+    }
+    
+    /// The last sample found less free space than the floor
+    pub fn low(&self) -> bool {
+        self.low.load(Ordering::SeqCst)
+    }
+    
+    /// Failure reason for an engine error caused by a full disk, None for any other error
+    pub fn out_of_space(&self, err: &anyhow::Error) -> Option<String> {
+        let full = err.chain().any(|cause| {
+            cause.downcast_ref::<std::io::Error>()
+                .is_some_and(|err| err.kind() == std::io::ErrorKind::StorageFull || err.raw_os_error() == Some(libc::ENOSPC))
+        });
+        full.then(|| format!("insufficient disk space: no space left on device in TEMP_BASE ({})", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const MB: u64 = 1024 * 1024;
+    
+    /// A volume of 1 GB with this much free, or unreadable
+    struct FakeDisk(Option<u64>);
+    
+    impl DiskReader for FakeDisk {
+        fn space(&self, _path: &Path) -> Option<(u64, u64)> {
+            self.0.map(|free| (1024 * MB, free))
+        }
+    }
+    
+    fn guard(floor: u64, free: Option<u64>) -> DiskGuard {
+        DiskGuard::new(PathBuf::from("/scratch"), floor, Arc::new(FakeDisk(free)))
+    }
+    
+    #[test]
+    fn jobs_fail_fast_below_the_floor() {
+        let low = guard(100 * MB, Some(10 * MB));
+        let message = low.check().unwrap_err();
+        assert_eq!(message, "insufficient disk space: 10 MB free in TEMP_BASE (/scratch), below TEMP_MIN_FREE_BYTES of 100 MB");
+        assert!(low.low());
+        assert_eq!(low.sample().unwrap().used_bytes, 1014 * MB);
+        
+        // Recovering is noticed on the next sample
+        let recovered = low.with_reader(Arc::new(FakeDisk(Some(500 * MB))));
+        assert_eq!(recovered.check(), Ok(()));
+        assert!(!recovered.low());
+    }
+    
+    #[test]
+    fn a_zero_floor_or_unreadable_volume_never_stops_jobs() {
+        assert_eq!(guard(0, Some(0)).check(), Ok(()));
+        let unreadable = guard(100 * MB, None);
+        assert_eq!(unreadable.check(), Ok(()));
+        assert!(unreadable.sample().is_none());
+    }
+    
+    #[test]
+    fn only_full_disks_are_named_as_such() {
+        let guard = guard(0, None);
+        let full = anyhow::Error::new(std::io::Error::from_raw_os_error(libc::ENOSPC)).context("writing main.cpp");
+        assert_eq!(
+            guard.out_of_space(&full).as_deref(),
+            Some("insufficient disk space: no space left on device in TEMP_BASE (/scratch)"),
+        );
+        let denied = anyhow::Error::new(std::io::Error::from_raw_os_error(libc::EACCES));
+        assert_eq!(guard.out_of_space(&denied), None);
+    }
+    
+    /// Reports whatever the test last stored, or nothing for u64::MAX
+    struct FakeMemory(AtomicU64);
+    
+    impl MemoryReader for FakeMemory {
+        fn available_bytes(&self) -> Option<u64> {
+            Some(self.0.load(Ordering::SeqCst)).filter(|&available| available != u64::MAX)
+        }
+    }
+    
+    #[test]
+    fn pressure_follows_available_memory_across_the_floor() {
+        let memory = Arc::new(FakeMemory(AtomicU64::new(512 * MB)));
+        let guard = MemoryGuard::new(128 * MB, memory.clone());
+        assert!(guard.enabled());
+        guard.sample();
+        assert!(!guard.under_pressure());
+        
+        memory.0.store(64 * MB, Ordering::SeqCst);
+        guard.sample();
+        assert!(guard.under_pressure());
+        
+        // An unreadable sample keeps the last state
+        memory.0.store(u64::MAX, Ordering::SeqCst);
+        guard.sample();
+        assert!(guard.under_pressure());
+        
+        memory.0.store(128 * MB, Ordering::SeqCst);
+        guard.sample();
+        assert!(!guard.under_pressure());
+    }
+    
+    #[test]
+    fn deferred_dequeues_are_counted() {
+        let guard = MemoryGuard::new(0, Arc::new(FakeMemory(AtomicU64::new(0))));
+        assert!(!guard.enabled());
+        guard.defer();
+        guard.defer();
+        assert_eq!(guard.deferred(), 2);
+    }
+}
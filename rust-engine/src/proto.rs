@@ -0,0 +1,7 @@
+//! gRPC messages and the `ExecutionService` server trait generated from `proto/engine.proto`.
+//! Conversions to and from the JSON types live in [`crate::types`].
+#![allow(missing_docs)]
+// The stream's final result dwarfs its progress events; boxing it would mean editing generated code
+#![allow(clippy::large_enum_variant)]
+
+tonic::include_proto!("labforcode.engine.v1");
@@ -1,46 +1,414 @@
+use crate::config::{QueueBackend, QueueConfig};
+use crate::redis_queue::RedisQueue;
 use crate::types::*;
 use anyhow::Result;
-use std::collections::VecDeque;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{BinaryHeap, VecDeque};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+use tracing::info;
+
+/// Number of priority bands (priorities 0 through 9)
+pub const PRIORITY_BANDS: usize = MAX_PRIORITY as usize + 1;
+
+/// Waiting this long raises a job's effective priority by one band
+pub const AGE_BOOST_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A job waiting in the queue, as listed for operators
+pub struct QueuedEntry {
+    /// The waiting job
+    pub job: ExecutionJob,
+    /// When the job entered the queue
+    pub enqueued_at: DateTime<Utc>,
+    /// Moved ahead of every priority band by an operator
+    pub promoted: bool,
+}
+
+/// Queue of jobs waiting for a worker
+#[async_trait]
+pub trait JobQueue: Send + Sync {
+    /// Add a job to the queue
+    async fn enqueue(&self, job: ExecutionJob) -> Result<()>;
+    
+    /// Take the next job; it must be acknowledged once finished
+    async fn dequeue(&self) -> Result<Option<ExecutionJob>>;
+    
+    /// Mark a dequeued job as done so it is not delivered again
+    async fn ack(&self, id: &str) -> Result<()>;
+    
+    /// Number of jobs that would be dequeued before this one, if it is still queued
+    async fn position(&self, id: &str) -> Result<Option<usize>>;
+    
+    /// Position of a job within its priority band, if it is still queued
+    async fn band_position(&self, id: &str) -> Result<Option<usize>>;
+    
+    /// Get queue size; scheduled jobs count once they are due
+    async fn size(&self) -> Result<usize>;
+    
+    /// Clear the queue
+    async fn clear(&self) -> Result<()>;
+    
+    /// Every queued job, in the order they would be dequeued
+    async fn snapshot(&self) -> Result<Vec<QueuedEntry>>;
+    
+    /// Move a queued job ahead of everything else; false if it is not queued
+    async fn promote(&self, id: &str) -> Result<bool>;
+    
+    /// Take a job out of the queue, or out of the delayed set, without running it; false if it is not waiting
+    async fn remove(&self, id: &str) -> Result<bool>;
+    
+    /// Whether queued jobs survive an engine restart
+    fn is_durable(&self) -> bool {
+        false
+    }
+}
+
+/// Build the configured queue
+pub async fn from_config(config: &QueueConfig) -> Result<Arc<dyn JobQueue>> {
+    match config.backend {
+        QueueBackend::Redis => {
+            let visibility_timeout = Duration::from_secs(config.visibility_timeout_secs);
+            info!("📮 Using Redis queue at {}", config.redis_url);
+            Ok(Arc::new(RedisQueue::new(&config.redis_url, visibility_timeout).await?))
+        }
+        QueueBackend::Memory => Ok(Arc::new(ExecutionQueue::new().await?)),
+    }
+}
+
+/// Priority after ageing, so low bands are never starved indefinitely
+pub fn effective_priority(band: usize, waited: Duration) -> usize {
+    let boost = waited.as_secs() / AGE_BOOST_INTERVAL.as_secs();
+    (band + boost as usize).min(PRIORITY_BANDS - 1)
+}
 
 /// Simple in-memory queue for execution jobs
-/// In production, this would be backed by Redis
+/// Jobs are lost on restart; use the Redis queue to share work between instances
 #[derive(Clone)]
 pub struct ExecutionQueue {
-    queue: Arc<Mutex<VecDeque<ExecutionJob>>>,
+    queue: Arc<Mutex<PriorityBands>>,
+}
+
+/// FIFO queues per priority; higher bands are served first
+/// Promoted jobs wait in `front` and are served before any band, most recent promotion first
+/// Jobs with a future run_at wait in `delayed` and join their band once due
+struct PriorityBands {
+    bands: Vec<VecDeque<QueuedJob>>,
+    front: VecDeque<QueuedJob>,
+    delayed: BinaryHeap<DelayedJob>,
+    next_seq: u64,
+}
+
+/// A job held back until its run_at time
+struct DelayedJob {
+    run_at: DateTime<Utc>,
+    seq: u64,
+    job: ExecutionJob,
+}
+
+impl DelayedJob {
+    fn key(&self) -> (DateTime<Utc>, u64) {
+        (self.run_at, self.seq)
+    }
+}
+
+// Reversed so the heap yields the earliest run_at first
+impl Ord for DelayedJob {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.key().cmp(&self.key())
+    }
+}
+
+impl PartialOrd for DelayedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for DelayedJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for DelayedJob {}
+
+impl PriorityBands {
+    /// Append a job to the back of its priority band
+    fn push(&mut self, job: ExecutionJob) {
+        let band = job.priority() as usize;
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.bands[band].push_back(QueuedJob {
+            job,
+            enqueued_at: Instant::now(),
+            seq,
+        });
+    }
+    
+    /// Move every delayed job whose run_at has passed into its band
+    fn release_due(&mut self, now: DateTime<Utc>) {
+        while self.delayed.peek().is_some_and(|delayed| delayed.run_at <= now) {
+            if let Some(delayed) = self.delayed.pop() {
+                self.push(delayed.job);
+            }
+        }
+    }
+    
+    /// Band and index of a queued job that has not been promoted
+    fn find(&self, id: &str) -> Option<(usize, usize)> {
+        self.bands.iter()
+            .enumerate()
+            .find_map(|(band, jobs)| jobs.iter().position(|queued| queued.job.id == id).map(|index| (band, index)))
+    }
+}
+
+struct QueuedJob {
+    job: ExecutionJob,
+    enqueued_at: Instant,
+    seq: u64,
+}
+
+impl QueuedJob {
+    /// Dequeue order key: effective priority first, then arrival order
+    fn order(&self, band: usize, now: Instant) -> (usize, std::cmp::Reverse<u64>) {
+        (effective_priority(band, now.duration_since(self.enqueued_at)), std::cmp::Reverse(self.seq))
+    }
 }
 
 impl ExecutionQueue {
     /// Create a new execution queue
     pub async fn new() -> Result<Self> {
         Ok(Self {
-            queue: Arc::new(Mutex::new(VecDeque::new())),
+            queue: Arc::new(Mutex::new(PriorityBands {
+                bands: (0..PRIORITY_BANDS).map(|_| VecDeque::new()).collect(),
+                front: VecDeque::new(),
+                delayed: BinaryHeap::new(),
+                next_seq: 0,
+            })),
         })
     }
+}
+
+#[async_trait]
+impl JobQueue for ExecutionQueue {
+    async fn enqueue(&self, job: ExecutionJob) -> Result<()> {
+        let mut queue = self.queue.lock().await;
+        match job.request.run_at.filter(|_| job.is_scheduled(Utc::now())) {
+            Some(run_at) => {
+                let seq = queue.next_seq;
+                queue.next_seq += 1;
+                queue.delayed.push(DelayedJob { run_at, seq, job });
+            }
+            None => queue.push(job),
+        }
+        Ok(())
+    }
     
-    /// Add a job to the queue
-    pub async fn enqueue(&self, job: ExecutionJob) -> Result<()> {
+    async fn dequeue(&self) -> Result<Option<ExecutionJob>> {
         let mut queue = self.queue.lock().await;
-        queue.push_back(job);
+        let now = Instant::now();
+        queue.release_due(Utc::now());
+        
+        if let Some(promoted) = queue.front.pop_front() {
+            return Ok(Some(promoted.job));
+        }
+        
+        // Compare the head of each band; ties go to whoever was enqueued first
+        let next = queue.bands.iter()
+            .enumerate()
+            .filter_map(|(band, jobs)| jobs.front().map(|head| (band, head.order(band, now))))
+            .max_by_key(|(_, order)| *order)
+            .map(|(band, _)| band);
+        
+        Ok(next.and_then(|band| queue.bands[band].pop_front()).map(|queued| queued.job))
+    }
+    
+    async fn ack(&self, _id: &str) -> Result<()> {
+        // Jobs leave the in-memory queue as soon as they are dequeued
         Ok(())
     }
     
-    /// Get the next job from the queue
-    pub async fn dequeue(&self) -> Result<Option<ExecutionJob>> {
+    async fn position(&self, id: &str) -> Result<Option<usize>> {
+        let queue = self.queue.lock().await;
+        let now = Instant::now();
+        
+        if let Some(index) = queue.front.iter().position(|queued| queued.job.id == id) {
+            return Ok(Some(index));
+        }
+        
+        let queued = queue.bands.iter()
+            .enumerate()
+            .flat_map(|(band, jobs)| jobs.iter().map(move |job| (band, job)));
+        let target = match queued.clone().find(|(_, job)| job.job.id == id) {
+            Some((band, job)) => job.order(band, now),
+            None => return Ok(None),
+        };
+        
+        Ok(Some(queue.front.len() + queued.filter(|(band, job)| job.order(*band, now) > target).count()))
+    }
+    
+    async fn band_position(&self, id: &str) -> Result<Option<usize>> {
+        let queue = self.queue.lock().await;
+        Ok(queue.bands.iter()
+            .find_map(|jobs| jobs.iter().position(|queued| queued.job.id == id)))
+    }
+    
+    async fn size(&self) -> Result<usize> {
+        let queue = self.queue.lock().await;
+        Ok(queue.front.len() + queue.bands.iter().map(VecDeque::len).sum::<usize>())
+    }
+    
+    async fn clear(&self) -> Result<()> {
         let mut queue = self.queue.lock().await;
-        Ok(queue.pop_front())
+        queue.bands.iter_mut().for_each(VecDeque::clear);
+        queue.front.clear();
+        queue.delayed.clear();
+        Ok(())
     }
     
-    /// Get queue size
-    pub async fn size(&self) -> usize {
+    async fn snapshot(&self) -> Result<Vec<QueuedEntry>> {
         let queue = self.queue.lock().await;
-        queue.len()
+        let now = Instant::now();
+        let entry = |queued: &QueuedJob, promoted: bool| QueuedEntry {
+            job: queued.job.clone(),
+            enqueued_at: Utc::now() - chrono::Duration::from_std(now.duration_since(queued.enqueued_at)).unwrap_or_default(),
+            promoted,
+        };
+        
+        let mut banded: Vec<(usize, &QueuedJob)> = queue.bands.iter()
+            .enumerate()
+            .flat_map(|(band, jobs)| jobs.iter().map(move |queued| (band, queued)))
+            .collect();
+        banded.sort_by_key(|(band, queued)| std::cmp::Reverse(queued.order(*band, now)));
+        
+        Ok(queue.front.iter()
+            .map(|queued| entry(queued, true))
+            .chain(banded.into_iter().map(|(_, queued)| entry(queued, false)))
+            .collect())
     }
     
-    /// Clear the queue
-    pub async fn clear(&self) {
+    async fn promote(&self, id: &str) -> Result<bool> {
+        let mut queue = self.queue.lock().await;
+        if let Some(index) = queue.front.iter().position(|queued| queued.job.id == id) {
+            let promoted = queue.front.remove(index).expect("index from position");
+            queue.front.push_front(promoted);
+            return Ok(true);
+        }
+        
+        let Some((band, index)) = queue.find(id) else {
+            return Ok(false);
+        };
+        let queued = queue.bands[band].remove(index).expect("index from find");
+        queue.front.push_front(queued);
+        Ok(true)
+    }
+    
+    async fn remove(&self, id: &str) -> Result<bool> {
         let mut queue = self.queue.lock().await;
-        queue.clear();
+        if let Some(index) = queue.front.iter().position(|queued| queued.job.id == id) {
+            queue.front.remove(index);
+            return Ok(true);
+        }
+        
+        if let Some((band, index)) = queue.find(id) {
+            return Ok(queue.bands[band].remove(index).is_some());
+        }
+        
+        let delayed = queue.delayed.len();
+        queue.delayed.retain(|scheduled| scheduled.job.id != id);
+        Ok(queue.delayed.len() < delayed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn job(id: &str, priority: u8) -> ExecutionJob {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "request": { "id": id, "language": "python", "source_code": "print(1)", "priority": priority },
+            "status": "queued",
+            "created_at": "2030-01-01T00:00:00Z",
+            "started_at": null,
+            "finished_at": null,
+            "result": null,
+        })).unwrap()
+    }
+    
+    async fn queue_of(jobs: &[(&str, u8)]) -> ExecutionQueue {
+        let queue = ExecutionQueue::new().await.unwrap();
+        for (id, priority) in jobs {
+            queue.enqueue(job(id, *priority)).await.unwrap();
+        }
+        queue
+    }
+    
+    async fn listed(queue: &ExecutionQueue) -> Vec<(String, bool)> {
+        queue.snapshot().await.unwrap().into_iter().map(|entry| (entry.job.id, entry.promoted)).collect()
+    }
+    
+    async fn drained(queue: &ExecutionQueue) -> Vec<String> {
+        let mut ids = Vec::new();
+        while let Some(job) = queue.dequeue().await.unwrap() {
+            ids.push(job.id);
+        }
+        ids
+    }
+    
+    #[tokio::test]
+    async fn promoting_the_last_job_runs_it_first() {
+        let jobs = [("first", 5), ("second", 5), ("third", 5)];
+        assert_eq!(drained(&queue_of(&jobs).await).await, ["first", "second", "third"]);
+        
+        let queue = queue_of(&jobs).await;
+        assert!(queue.promote("third").await.unwrap());
+        assert_eq!(listed(&queue).await, [
+            ("third".to_string(), true),
+            ("first".to_string(), false),
+            ("second".to_string(), false),
+        ]);
+        assert_eq!(drained(&queue).await, ["third", "first", "second"]);
+    }
+    
+    #[tokio::test]
+    async fn promoted_jobs_pass_higher_priorities_latest_first() {
+        let queue = queue_of(&[("urgent", 9), ("low", 0), ("normal", 5)]).await;
+        assert!(queue.promote("low").await.unwrap());
+        assert!(queue.promote("normal").await.unwrap());
+        assert_eq!(drained(&queue).await, ["normal", "low", "urgent"]);
+        
+        assert!(!queue.promote("normal").await.unwrap(), "no longer queued");
+    }
+    
+    #[tokio::test]
+    async fn removed_jobs_are_never_dequeued() {
+        let queue = queue_of(&[("first", 5), ("second", 5), ("third", 5)]).await;
+        let mut later = job("later", 5);
+        later.request.run_at = Some(Utc::now() + chrono::Duration::hours(1));
+        queue.enqueue(later).await.unwrap();
+        assert!(queue.promote("third").await.unwrap());
+        
+        for id in ["third", "first", "later"] {
+            assert!(queue.remove(id).await.unwrap(), "{}", id);
+        }
+        assert!(!queue.remove("first").await.unwrap());
+        assert!(!queue.remove("unknown").await.unwrap());
+        assert_eq!(drained(&queue).await, ["second"]);
+    }
+    
+    #[tokio::test]
+    async fn scheduled_jobs_are_held_back_until_due() {
+        let queue = ExecutionQueue::new().await.unwrap();
+        let mut scheduled = job("scheduled", 9);
+        scheduled.request.run_at = Some(Utc::now() + chrono::Duration::milliseconds(300));
+        queue.enqueue(scheduled).await.unwrap();
+        queue.enqueue(job("now", 0)).await.unwrap();
+        
+        assert_eq!(drained(&queue).await, ["now"], "a higher priority does not release it early");
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(drained(&queue).await, ["scheduled"]);
     }
 }
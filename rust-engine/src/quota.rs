@@ -0,0 +1,387 @@
+use crate::rate_limit::RateLimiter;
+use crate::store::JobStore;
+use crate::types::{ExecutionResult, PeriodUsage, Quota, QuotaLimits, UsageCounters, UsageReport};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+/// Submission refused because the client used up a quota period
+#[derive(Debug, thiserror::Error)]
+#[error("{period} quota exceeded: {counter} used {used} of {limit}")]
+pub struct QuotaExceeded {
+    /// "daily" or "monthly"
+    pub period: &'static str,
+    /// Which counter ran out
+    pub counter: &'static str,
+    /// Amount consumed this period
+    pub used: f64,
+    /// Cap for this period
+    pub limit: f64,
+    /// When the period rolls over
+    pub resets_at: DateTime<Utc>,
+}
+
+/// Quotas loaded from QUOTA_CONFIG; client ids may be raw API keys or key:/ip: client keys
+#[derive(Debug, Deserialize, Default)]
+struct QuotaConfig {
+    default: Option<Quota>,
+    #[serde(default)]
+    clients: HashMap<String, Quota>,
+}
+
+/// What a client used in the current day and month, as persisted
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct ClientUsage {
+    #[serde(default)]
+    quota: Option<Quota>, // set through the admin API; wins over the config file
+    day: String,
+    daily: UsageCounters,
+    month: String,
+    monthly: UsageCounters,
+}
+
+/// Per-client usage counters checked against daily and monthly quotas
+#[derive(Clone)]
+pub struct UsageTracker {
+    clients: Arc<Mutex<HashMap<String, ClientUsage>>>,
+    configured: Arc<HashMap<String, Quota>>,
+    default_quota: Option<Quota>,
+    store: Option<JobStore>,
+}
+
+impl UsageTracker {
+    /// Load quotas from the JSON file at `path`, if any, and saved usage from the store
+    pub async fn load(path: Option<&Path>, store: Option<JobStore>) -> Result<Self> {
+        let config = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+                let config: QuotaConfig = serde_json::from_str(&text)
+                    .with_context(|| format!("parsing {}", path.display()))?;
+                info!("🎫 Loaded quotas for {} clients from {}", config.clients.len(), path.display());
+                config
+            }
+            None => QuotaConfig::default(),
+        };
+        let configured = config.clients
+            .into_iter()
+            .map(|(client, quota)| (client_id(&client), quota))
+            .collect();
+        
+        let mut clients = HashMap::new();
+        if let Some(store) = &store {
+            for (client, usage) in store.load_usage().await? {
+                match serde_json::from_str(&usage) {
+                    Ok(usage) => {
+                        clients.insert(client, usage);
+                    }
+                    Err(err) => warn!("Ignoring unreadable usage of {}: {}", client, err),
+                }
+            }
+        }
+        
+        Ok(Self {
+            clients: Arc::new(Mutex::new(clients)),
+            configured: Arc::new(configured),
+            default_quota: config.default,
+            store,
+        })
+    }
+    
+    /// Count a submission, or refuse it if any counter has reached its cap for the day or month
+    pub async fn admit(&self, client: &str) -> Result<(), QuotaExceeded> {
+        let now = Utc::now();
+        let usage = {
+            let mut clients = self.clients.lock().unwrap();
+            let usage = clients.entry(client.to_string()).or_default();
+            usage.roll(now);
+            
+            if let Some(quota) = self.quota_for(client, usage) {
+                check("daily", &usage.daily, &quota.daily, next_day(now))?;
+                check("monthly", &usage.monthly, &quota.monthly, next_month(now))?;
+            }
+            usage.daily.submissions += 1;
+            usage.monthly.submissions += 1;
+            usage.clone()
+        };
+        
+        self.persist(client, &usage).await;
+        Ok(())
+    }
+    
+    /// Give back a submission that was admitted but never queued
+    pub async fn refund(&self, client: &str) {
+        let usage = {
+            let mut clients = self.clients.lock().unwrap();
+            let Some(usage) = clients.get_mut(client) else {
+                return;
+            };
+            usage.daily.submissions = usage.daily.submissions.saturating_sub(1);
+            usage.monthly.submissions = usage.monthly.submissions.saturating_sub(1);
+            usage.clone()
+        };
+        
+        self.persist(client, &usage).await;
+    }
+    
+    /// Add the resources a finished execution consumed
+    pub async fn record(&self, client: &str, result: &ExecutionResult) {
+        let cpu_seconds = result.time.unwrap_or(0.0);
+        let memory_mb_seconds = result.memory.unwrap_or(0) as f64 / (1024.0 * 1024.0) * cpu_seconds;
+        
+        let usage = {
+            let mut clients = self.clients.lock().unwrap();
+            let usage = clients.entry(client.to_string()).or_default();
+            usage.roll(Utc::now());
+            for counters in [&mut usage.daily, &mut usage.monthly] {
+                counters.cpu_seconds += cpu_seconds;
+                counters.memory_mb_seconds += memory_mb_seconds;
+            }
+            usage.clone()
+        };
+        
+        self.persist(client, &usage).await;
+    }
+    
+    /// Replace a client's quota
+    pub async fn set_quota(&self, client: &str, quota: Quota) -> UsageReport {
+        let client = &client_id(client);
+        let usage = {
+            let mut clients = self.clients.lock().unwrap();
+            let usage = clients.entry(client.to_string()).or_default();
+            usage.roll(Utc::now());
+            usage.quota = Some(quota);
+            usage.clone()
+        };
+        info!("🎫 Quota of {} updated", client);
+        
+        self.persist(client, &usage).await;
+        self.report(client)
+    }
+    
+    /// Usage and remaining quota of one client
+    pub fn report(&self, client: &str) -> UsageReport {
+        let mut usage = self.clients.lock().unwrap().get(client).cloned().unwrap_or_default();
+        usage.roll(Utc::now());
+        self.report_of(client, &usage)
+    }
+    
+    /// Usage and remaining quota of every client seen so far
+    pub fn reports(&self) -> Vec<UsageReport> {
+        let now = Utc::now();
+        let mut clients = self.clients.lock().unwrap();
+        let mut reports: Vec<UsageReport> = clients.iter_mut()
+            .map(|(client, usage)| {
+                usage.roll(now);
+                self.report_of(client, usage)
+            })
+            .collect();
+        reports.sort_by(|a, b| a.client.cmp(&b.client));
+        reports
+    }
+    
+    fn report_of(&self, client: &str, usage: &ClientUsage) -> UsageReport {
+        let quota = self.quota_for(client, usage).unwrap_or_default();
+        UsageReport {
+            client: client.to_string(),
+            daily: period_usage(&usage.day, &usage.daily, quota.daily),
+            monthly: period_usage(&usage.month, &usage.monthly, quota.monthly),
+        }
+    }
+    
+    fn quota_for(&self, client: &str, usage: &ClientUsage) -> Option<Quota> {
+        usage.quota.clone()
+            .or_else(|| self.configured.get(client).cloned())
+            .or_else(|| self.default_quota.clone())
+    }
+    
+    /// Persistence failures are logged; counters keep working in memory
+    async fn persist(&self, client: &str, usage: &ClientUsage) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let saved = match serde_json::to_string(usage) {
+            Ok(usage) => store.save_usage(client, &usage).await,
+            Err(err) => Err(err.into()),
+        };
+        if let Err(err) = saved {
+            warn!("Failed to persist usage of {}: {}", client, err);
+        }
+    }
+}
+
+impl ClientUsage {
+    /// Start fresh counters when the UTC day or month has changed
+    fn roll(&mut self, now: DateTime<Utc>) {
+        let day = now.format("%Y-%m-%d").to_string();
+        if self.day != day {
+            self.day = day;
+            self.daily = UsageCounters::default();
+        }
+        let month = now.format("%Y-%m").to_string();
+        if self.month != month {
+            self.month = month;
+            self.monthly = UsageCounters::default();
+        }
+    }
+}
+
+/// Raw API keys in the config are hashed the same way the rate limiter identifies clients
+fn client_id(configured: &str) -> String {
+    if configured.starts_with("key:") || configured.starts_with("ip:") {
+        configured.to_string()
+    } else {
+        RateLimiter::api_key_id(configured)
+    }
+}
+
+/// Fail on the first counter that has reached its cap
+fn check(
+    period: &'static str,
+    used: &UsageCounters,
+    limit: &QuotaLimits,
+    resets_at: DateTime<Utc>,
+) -> Result<(), QuotaExceeded> {
+    let counters = [
+        ("submissions", used.submissions as f64, limit.submissions.map(|cap| cap as f64)),
+        ("cpu_seconds", used.cpu_seconds, limit.cpu_seconds),
+        ("memory_mb_seconds", used.memory_mb_seconds, limit.memory_mb_seconds),
+    ];
+    for (counter, used, limit) in counters {
+        if let Some(limit) = limit.filter(|limit| used >= *limit) {
+            return Err(QuotaExceeded { period, counter, used, limit, resets_at });
+        }
+    }
+    Ok(())
+}
+
+fn period_usage(period: &str, used: &UsageCounters, limit: QuotaLimits) -> PeriodUsage {
+    let remaining = QuotaLimits {
+        submissions: limit.submissions.map(|cap| cap.saturating_sub(used.submissions)),
+        cpu_seconds: limit.cpu_seconds.map(|cap| (cap - used.cpu_seconds).max(0.0)),
+        memory_mb_seconds: limit.memory_mb_seconds.map(|cap| (cap - used.memory_mb_seconds).max(0.0)),
+    };
+    PeriodUsage {
+        period: period.to_string(),
+        used: used.clone(),
+        limit,
+        remaining,
+    }
+}
+
+/// Midnight UTC after `now`
+fn next_day(now: DateTime<Utc>) -> DateTime<Utc> {
+    let tomorrow = now.date_naive() + Duration::days(1);
+    Utc.from_utc_datetime(&tomorrow.and_hms_opt(0, 0, 0).unwrap())
+}
+
+/// Start of the UTC month after `now`
+fn next_month(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = match now.month() {
+        12 => (now.year() + 1, 1),
+        month => (now.year(), month + 1),
+    };
+    let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+    Utc.from_utc_datetime(&first.and_hms_opt(0, 0, 0).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn quota(daily: serde_json::Value) -> Quota {
+        serde_json::from_value(serde_json::json!({"daily": daily})).unwrap()
+    }
+    
+    fn ran_for(cpu_seconds: f64) -> ExecutionResult {
+        serde_json::from_value(serde_json::json!({
+            "id": "job", "status": "completed", "time": cpu_seconds, "memory": 64 * 1024 * 1024,
+            "created_at": "2030-01-01T00:00:00Z", "compile_cache_hit": false,
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn exhausted_quotas_refuse_only_their_client() {
+        let tracker = UsageTracker::load(None, None).await.unwrap();
+        tracker.set_quota("key:small", quota(serde_json::json!({"submissions": 2}))).await;
+        tracker.admit("key:small").await.unwrap();
+        tracker.admit("key:small").await.unwrap();
+        
+        let exceeded = tracker.admit("key:small").await.unwrap_err();
+        assert_eq!((exceeded.period, exceeded.counter, exceeded.used, exceeded.limit), ("daily", "submissions", 2.0, 2.0));
+        assert_eq!(exceeded.resets_at, next_day(Utc::now()));
+        for _ in 0..5 {
+            tracker.admit("key:other").await.unwrap();
+        }
+        
+        // A refunded submission frees its place
+        tracker.refund("key:small").await;
+        tracker.admit("key:small").await.unwrap();
+        let report = tracker.report("key:small");
+        assert_eq!((report.daily.used.submissions, report.daily.remaining.submissions), (2, Some(0)));
+    }
+    
+    #[tokio::test]
+    async fn finished_runs_count_against_resource_caps() {
+        let tracker = UsageTracker::load(None, None).await.unwrap();
+        tracker.set_quota("key:busy", quota(serde_json::json!({"cpu_seconds": 1.5}))).await;
+        tracker.admit("key:busy").await.unwrap();
+        tracker.record("key:busy", &ran_for(2.0)).await;
+        
+        let exceeded = tracker.admit("key:busy").await.unwrap_err();
+        assert_eq!(exceeded.counter, "cpu_seconds");
+        let report = tracker.report("key:busy");
+        assert_eq!(report.daily.used.memory_mb_seconds, 128.0);
+        assert_eq!(report.monthly.used.cpu_seconds, 2.0);
+        assert_eq!(report.daily.remaining.cpu_seconds, Some(0.0));
+    }
+    
+    #[tokio::test]
+    async fn configured_raw_keys_are_matched_by_their_client_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("quotas.json");
+        std::fs::write(&path, r#"{"default": {"daily": {"submissions": 10}}, "clients": {"secret-key": {"daily": {"submissions": 1}}}}"#).unwrap();
+        let tracker = UsageTracker::load(Some(&path), None).await.unwrap();
+        
+        let client = RateLimiter::api_key_id("secret-key");
+        assert_eq!(tracker.report(&client).daily.limit.submissions, Some(1));
+        assert_eq!(tracker.report("ip:127.0.0.1").daily.limit.submissions, Some(10));
+    }
+    
+    #[tokio::test]
+    async fn usage_survives_a_restart_with_a_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.db");
+        {
+            let tracker = UsageTracker::load(None, Some(JobStore::open(&path).await.unwrap())).await.unwrap();
+            tracker.set_quota("key:kept", quota(serde_json::json!({"submissions": 3}))).await;
+            tracker.admit("key:kept").await.unwrap();
+            tracker.record("key:kept", &ran_for(0.5)).await;
+        }
+        
+        let tracker = UsageTracker::load(None, Some(JobStore::open(&path).await.unwrap())).await.unwrap();
+        let report = tracker.report("key:kept");
+        assert_eq!((report.daily.used.submissions, report.daily.used.cpu_seconds), (1, 0.5));
+        assert_eq!(report.daily.limit.submissions, Some(3), "admin quotas are saved with the usage");
+    }
+    
+    #[test]
+    fn periods_roll_over_at_utc_midnight_and_month_start() {
+        let at = |text: &str| text.parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(next_day(at("2026-02-28T23:59:59Z")), at("2026-03-01T00:00:00Z"));
+        assert_eq!(next_month(at("2026-12-31T12:00:00Z")), at("2027-01-01T00:00:00Z"));
+        
+        let mut usage = ClientUsage::default();
+        usage.roll(at("2026-10-16T10:00:00Z"));
+        usage.daily.submissions = 4;
+        usage.monthly.submissions = 4;
+        usage.roll(at("2026-10-17T00:00:01Z"));
+        assert_eq!((usage.daily.submissions, usage.monthly.submissions), (0, 4));
+        usage.roll(at("2026-11-01T00:00:01Z"));
+        assert_eq!(usage.monthly.submissions, 0);
+    }
+}
+
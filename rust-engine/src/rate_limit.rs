@@ -0,0 +1,261 @@
+use crate::types::ClientStats;
+use crate::config::RateLimitConfig;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Clients with no activity for this long are forgotten
+const CLIENT_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Suggested wait when a client is at its concurrency cap
+const CONCURRENCY_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Why a submission was turned away
+#[derive(Debug)]
+pub enum Rejection {
+    /// Over the request rate; retry after the given delay
+    RateLimited(Duration),
+    /// Already has this many jobs queued or running
+    TooManyRunning(usize),
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::RateLimited(_) => write!(f, "Rate limit exceeded"),
+            Rejection::TooManyRunning(running) => write!(f, "Too many unfinished executions ({})", running),
+        }
+    }
+}
+
+impl Rejection {
+    /// How long the client should wait before trying again
+    pub fn retry_after(&self) -> Duration {
+        match self {
+            Rejection::RateLimited(wait) => *wait,
+            Rejection::TooManyRunning(_) => CONCURRENCY_RETRY_AFTER,
+        }
+    }
+}
+
+/// Token-bucket rate limiter with a cap on unfinished jobs per client
+#[derive(Clone)]
+pub struct RateLimiter {
+    clients: Arc<Mutex<HashMap<String, ClientState>>>,
+    api_keys: Arc<HashSet<String>>, // keys that get a bucket of their own
+    per_minute: u32,
+    burst: u32,
+    max_running: usize,
+}
+
+struct ClientState {
+    tokens: f64,
+    refilled_at: Instant,
+    last_seen: Instant,
+    jobs: HashSet<String>, // submitted jobs not yet seen finished
+    pending: usize,        // admitted submissions not yet enqueued
+    requests: u64,
+    rate_limited: u64,
+    concurrency_limited: u64,
+}
+
+/// Admission slot held while a submission is enqueued
+pub struct Reservation {
+    limiter: RateLimiter,
+    client: String,
+}
+
+impl RateLimiter {
+    /// Limiter allowing `per_minute` requests with bursts of `burst`, and `max_running` live jobs per client (0 for no cap)
+    pub fn new(per_minute: u32, burst: u32, max_running: usize) -> Self {
+        Self {
+            clients: Arc::new(Mutex::new(HashMap::new())),
+            api_keys: Arc::new(HashSet::new()),
+            per_minute,
+            burst: burst.max(1),
+            max_running,
+        }
+    }
+    
+    /// Limiter for the configured rate and keys; the burst defaults to a minute's worth
+    pub fn from_config(config: &RateLimitConfig) -> Self {
+        Self::new(config.per_minute, config.burst.unwrap_or(config.per_minute), config.max_running_per_client)
+            .with_api_keys(config.api_keys.iter().cloned())
+    }
+    
+    /// Also give these API keys a bucket of their own
+    pub fn with_api_keys(mut self, keys: impl IntoIterator<Item = String>) -> Self {
+        let mut api_keys = HashSet::clone(&self.api_keys);
+        api_keys.extend(keys);
+        self.api_keys = Arc::new(api_keys);
+        self
+    }
+    
+    /// Identify a client by API key when the key is a known one, else by its address
+    ///
+    /// Made-up keys share their address's bucket, so rotating them doesn't buy fresh tokens.
+    pub fn client_key(&self, api_key: Option<&str>, addr: IpAddr) -> String {
+        match api_key {
+            Some(key) if self.api_keys.contains(key) => Self::api_key_id(key),
+            _ => format!("ip:{}", addr),
+        }
+    }
+    
+    /// Client key of an API key; keys are hashed so /admin/clients never displays them
+    pub fn api_key_id(key: &str) -> String {
+        let digest: String = Sha256::digest(key.as_bytes()).iter().take(6).map(|b| format!("{:02x}", b)).collect();
+        format!("key:{}", digest)
+    }
+    
+    /// Jobs the client had running the last time we looked
+    pub fn tracked_jobs(&self, client: &str) -> Vec<String> {
+        let clients = self.clients.lock().unwrap();
+        clients.get(client).map(|state| state.jobs.iter().cloned().collect()).unwrap_or_default()
+    }
+    
+    /// Admit a submission, forgetting `finished` jobs before checking the caps
+    pub fn admit(&self, client: &str, finished: &[String]) -> Result<Reservation, Rejection> {
+        let mut clients = self.clients.lock().unwrap();
+        let now = Instant::now();
+        let state = clients.entry(client.to_string()).or_insert_with(|| ClientState {
+            tokens: self.burst as f64,
+            refilled_at: now,
+            last_seen: now,
+            jobs: HashSet::new(),
+            pending: 0,
+            requests: 0,
+            rate_limited: 0,
+            concurrency_limited: 0,
+        });
+        
+        for id in finished {
+            state.jobs.remove(id);
+        }
+        state.requests += 1;
+        state.last_seen = now;
+        
+        let running = state.jobs.len() + state.pending;
+        if self.max_running > 0 && running >= self.max_running {
+            state.concurrency_limited += 1;
+            debug!("Client {} is at its concurrency cap ({})", client, running);
+            return Err(Rejection::TooManyRunning(running));
+        }
+        
+        if self.per_minute > 0 {
+            let rate = self.per_minute as f64 / 60.0;
+            let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+            state.tokens = (state.tokens + elapsed * rate).min(self.burst as f64);
+            state.refilled_at = now;
+            
+            if state.tokens < 1.0 {
+                state.rate_limited += 1;
+                let wait = Duration::from_secs_f64((1.0 - state.tokens) / rate);
+                debug!("Client {} is rate limited for {:?}", client, wait);
+                return Err(Rejection::RateLimited(wait));
+            }
+            state.tokens -= 1.0;
+        }
+        
+        state.pending += 1;
+        Ok(Reservation {
+            limiter: self.clone(),
+            client: client.to_string(),
+        })
+    }
+    
+    /// Per-client counters for operators
+    pub fn stats(&self) -> Vec<ClientStats> {
+        let clients = self.clients.lock().unwrap();
+        let mut stats: Vec<ClientStats> = clients.iter()
+            .map(|(client, state)| ClientStats {
+                client: client.clone(),
+                requests: state.requests,
+                rate_limited: state.rate_limited,
+                concurrency_limited: state.concurrency_limited,
+                tracked_jobs: state.jobs.len(),
+                idle_seconds: state.last_seen.elapsed().as_secs(),
+            })
+            .collect();
+        stats.sort_by(|a, b| a.client.cmp(&b.client));
+        stats
+    }
+    
+    /// Drop clients that have gone quiet so the table doesn't grow forever
+    pub fn prune(&self) {
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|_, state| state.pending > 0 || state.last_seen.elapsed() < CLIENT_IDLE_TIMEOUT);
+    }
+    
+    /// Prune idle clients once a minute
+    pub fn start_pruning(&self) {
+        let limiter = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                limiter.prune();
+            }
+        });
+    }
+}
+
+impl Reservation {
+    /// Count the enqueued job against the client until it finishes
+    pub fn commit(self, job_id: &str) {
+        let mut clients = self.limiter.clients.lock().unwrap();
+        if let Some(state) = clients.get_mut(&self.client) {
+            state.jobs.insert(job_id.to_string());
+        }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut clients = self.limiter.clients.lock().unwrap();
+        if let Some(state) = clients.get_mut(&self.client) {
+            state.pending = state.pending.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn addr() -> IpAddr {
+        IpAddr::from([203, 0, 113, 7])
+    }
+    
+    #[test]
+    fn only_known_keys_get_their_own_bucket() {
+        let limiter = RateLimiter::new(60, 1, 0).with_api_keys(["team-key".to_string()]);
+        assert_eq!(limiter.client_key(Some("team-key"), addr()), RateLimiter::api_key_id("team-key"));
+        assert_eq!(limiter.client_key(Some("made-up"), addr()), "ip:203.0.113.7");
+        assert_eq!(limiter.client_key(None, addr()), "ip:203.0.113.7");
+    }
+    
+    #[test]
+    fn rotating_unknown_keys_does_not_reset_the_bucket() {
+        let limiter = RateLimiter::new(60, 1, 0);
+        let first = limiter.client_key(Some("key-1"), addr());
+        drop(limiter.admit(&first, &[]).unwrap());
+        
+        let second = limiter.client_key(Some("key-2"), addr());
+        assert!(matches!(limiter.admit(&second, &[]), Err(Rejection::RateLimited(_))));
+    }
+    
+    #[test]
+    fn concurrency_cap_counts_unfinished_jobs() {
+        let limiter = RateLimiter::new(0, 1, 2);
+        let client = limiter.client_key(None, addr());
+        limiter.admit(&client, &[]).unwrap().commit("a");
+        limiter.admit(&client, &[]).unwrap().commit("b");
+        assert!(matches!(limiter.admit(&client, &[]), Err(Rejection::TooManyRunning(2))));
+        
+        // A finished job frees its slot
+        assert!(limiter.admit(&client, &["a".to_string()]).is_ok());
+    }
+}
@@ -0,0 +1,199 @@
+//! Rolling statistics over the last hour and a list of the latest executions, in fixed memory.
+//!
+//! Finished jobs are counted in one bucket per second of a one-hour ring, so the windows stay
+//! exact at any traffic; the per-job entries are kept in a ring of [`RECENT_CAPACITY`].
+use crate::types::{ExecutionState, RecentExecution, StatsWindow, StatsWindows};
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+
+/// Most entries GET /stats/recent can return
+pub const RECENT_CAPACITY: usize = 1000;
+
+/// Longest window, and the length of the bucket ring
+const HORIZON_SECS: i64 = 3600;
+
+/// Jobs that finished within one second
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    second: i64,
+    executions: u64,
+    failed: u64,
+    duration_ms: u64,
+}
+
+/// Latest finished executions and per-second counters of the last hour.
+///
+/// A window of N seconds covers the jobs that finished after `now - N`, up to and including `now`:
+///
+/// ```
+/// use chrono::Utc;
+/// use labforcode_engine::recent::RecentExecutions;
+/// use labforcode_engine::types::{ExecutionState, RecentExecution};
+///
+/// let mut recent = RecentExecutions::new();
+/// recent.record(RecentExecution {
+///     finished_at: Utc::now(),
+///     language: "python".to_string(),
+///     status: ExecutionState::Completed,
+///     duration_ms: 120,
+/// });
+/// assert_eq!(recent.windows(Utc::now()).last_minute.executions, 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecentExecutions {
+    entries: VecDeque<RecentExecution>,
+    buckets: Vec<Bucket>,
+}
+
+impl RecentExecutions {
+    /// Nothing recorded yet
+    pub fn new() -> Self {
+        Self {
+            entries: VecDeque::with_capacity(RECENT_CAPACITY),
+            buckets: vec![Bucket::default(); HORIZON_SECS as usize],
+        }
+    }
+    
+    /// Count a finished job; the oldest entry makes room once the ring is full
+    pub fn record(&mut self, execution: RecentExecution) {
+        let second = execution.finished_at.timestamp();
+        let bucket = &mut self.buckets[second.rem_euclid(HORIZON_SECS) as usize];
+        // A bucket holding a newer second is left alone; one holding an older second is reused
+        if bucket.second < second {
+            *bucket = Bucket { second, ..Bucket::default() };
+        }
+        if bucket.second == second {
+            bucket.executions += 1;
+            bucket.failed += u64::from(execution.status == ExecutionState::InternalError);
+            bucket.duration_ms = bucket.duration_ms.saturating_add(execution.duration_ms);
+        }
+        
+        if self.entries.len() == RECENT_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(execution);
+    }
+    
+    /// Up to `limit` entries, newest first
+    pub fn latest(&self, limit: usize) -> Vec<RecentExecution> {
+        self.entries.iter().rev().take(limit).cloned().collect()
+    }
+    
+    /// The one-minute, five-minute and one-hour windows ending at `now`
+    pub fn windows(&self, now: DateTime<Utc>) -> StatsWindows {
+        StatsWindows {
+            last_minute: self.window(now, 60),
+            last_5_minutes: self.window(now, 300),
+            last_hour: self.window(now, HORIZON_SECS),
+        }
+    }
+    
+    /// Forget every entry and counter
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+    
+    fn window(&self, now: DateTime<Utc>, secs: i64) -> StatsWindow {
+        let end = now.timestamp();
+        let (mut executions, mut failed, mut duration_ms) = (0, 0, 0u64);
+        for bucket in self.buckets.iter().filter(|bucket| bucket.second > end - secs && bucket.second <= end) {
+            executions += bucket.executions;
+            failed += bucket.failed;
+            duration_ms = duration_ms.saturating_add(bucket.duration_ms);
+        }
+        
+        let ratio = |value: f64| if executions == 0 { 0.0 } else { value / executions as f64 };
+        StatsWindow {
+            executions,
+            failed,
+            failure_rate: ratio(failed as f64),
+            average_duration_ms: ratio(duration_ms as f64),
+            per_minute: executions as f64 * 60.0 / secs as f64,
+        }
+    }
+}
+
+impl Default for RecentExecutions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, 1, 12, 0, 0).unwrap()
+    }
+    
+    fn finished(secs_ago: i64, status: ExecutionState, duration_ms: u64) -> RecentExecution {
+        RecentExecution {
+            finished_at: now() - Duration::seconds(secs_ago),
+            language: "python".to_string(),
+            status,
+            duration_ms,
+        }
+    }
+    
+    #[test]
+    fn windows_include_their_last_second_but_not_their_first() {
+        let mut recent = RecentExecutions::new();
+        recent.record(finished(0, ExecutionState::Completed, 100));
+        recent.record(finished(59, ExecutionState::InternalError, 300));
+        recent.record(finished(60, ExecutionState::Completed, 500)); // just outside the minute
+        recent.record(finished(299, ExecutionState::WrongAnswer, 100));
+        recent.record(finished(3599, ExecutionState::Completed, 100));
+        recent.record(finished(3600, ExecutionState::Completed, 100)); // older than the hour
+        
+        let windows = recent.windows(now());
+        assert_eq!((windows.last_minute.executions, windows.last_minute.failed), (2, 1));
+        assert_eq!(windows.last_minute.failure_rate, 0.5);
+        assert_eq!(windows.last_minute.average_duration_ms, 200.0);
+        assert_eq!(windows.last_minute.per_minute, 2.0);
+        assert_eq!(windows.last_5_minutes.executions, 4);
+        assert_eq!(windows.last_5_minutes.per_minute, 0.8);
+        assert_eq!(windows.last_hour.executions, 5);
+    }
+    
+    #[test]
+    fn jobs_leave_the_windows_as_time_passes() {
+        let mut recent = RecentExecutions::new();
+        recent.record(finished(0, ExecutionState::InternalError, 100));
+        
+        let later = recent.windows(now() + Duration::seconds(60));
+        assert_eq!(later.last_minute.executions, 0);
+        assert_eq!(later.last_minute.failure_rate, 0.0);
+        assert_eq!(later.last_minute.average_duration_ms, 0.0);
+        assert_eq!(later.last_hour.failed, 1);
+        
+        // Jobs finishing after `now` are not counted yet
+        assert_eq!(recent.windows(now() - Duration::seconds(1)).last_hour.executions, 0);
+    }
+    
+    #[test]
+    fn a_reused_bucket_forgets_the_hour_before() {
+        let mut recent = RecentExecutions::new();
+        recent.record(finished(3600, ExecutionState::Completed, 100));
+        recent.record(finished(0, ExecutionState::Completed, 100)); // the same slot of the ring
+        // A late report for the older second must not land in the newer one
+        recent.record(finished(3600, ExecutionState::Completed, 100));
+        
+        assert_eq!(recent.windows(now()).last_hour.executions, 1);
+    }
+    
+    #[test]
+    fn the_entry_ring_keeps_the_newest() {
+        let mut recent = RecentExecutions::new();
+        for secs_ago in (0..RECENT_CAPACITY as i64 + 10).rev() {
+            recent.record(finished(secs_ago, ExecutionState::Completed, 1));
+        }
+        
+        assert_eq!(recent.latest(usize::MAX).len(), RECENT_CAPACITY);
+        let latest = recent.latest(2);
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].finished_at, now());
+        assert_eq!(latest[1].finished_at, now() - Duration::seconds(1));
+    }
+}
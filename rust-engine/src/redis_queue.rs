@@ -0,0 +1,351 @@
+use crate::queue::{effective_priority, JobQueue, QueuedEntry, AGE_BOOST_INTERVAL, PRIORITY_BANDS};
+use crate::types::ExecutionJob;
+use anyhow::Result;
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use redis::Script;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Prefix of every key the queue uses
+const KEY_PREFIX: &str = "labforcode:queue";
+
+/// Atomically re-deliver expired jobs and release due scheduled ones,
+/// then pop a promoted job or the best band head into the processing set
+const DEQUEUE_SCRIPT: &str = r#"
+local prefix = ARGV[1]
+local now = tonumber(ARGV[2])
+local boost = tonumber(ARGV[3])
+local visibility = tonumber(ARGV[4])
+local bands = tonumber(ARGV[5])
+
+local due = redis.call('ZRANGEBYSCORE', prefix .. ':delayed', '-inf', now)
+for _, id in ipairs(due) do
+    redis.call('ZREM', prefix .. ':delayed', id)
+    local band = redis.call('HGET', prefix .. ':band', id)
+    if band then
+        redis.call('HSET', prefix .. ':enqueued', id, now)
+        redis.call('RPUSH', prefix .. ':band:' .. band, id)
+    end
+end
+
+local expired = redis.call('ZRANGEBYSCORE', prefix .. ':processing', '-inf', now)
+for _, id in ipairs(expired) do
+    redis.call('ZREM', prefix .. ':processing', id)
+    local band = redis.call('HGET', prefix .. ':band', id)
+    if band then
+        redis.call('LPUSH', prefix .. ':band:' .. band, id)
+    end
+end
+
+local promoted = redis.call('LPOP', prefix .. ':front')
+if promoted then
+    redis.call('ZADD', prefix .. ':processing', now + visibility, promoted)
+    return {promoted, redis.call('HGET', prefix .. ':payload', promoted)}
+end
+
+local best, best_band, best_priority, best_time = nil, nil, -1, 0
+for band = 0, bands - 1 do
+    local id = redis.call('LINDEX', prefix .. ':band:' .. band, 0)
+    if id then
+        local enqueued = tonumber(redis.call('HGET', prefix .. ':enqueued', id))
+        local priority = math.min(band + math.floor((now - enqueued) / boost), bands - 1)
+        if priority > best_priority or (priority == best_priority and enqueued < best_time) then
+            best, best_band, best_priority, best_time = id, band, priority, enqueued
+        end
+    end
+end
+
+if not best then
+    return false
+end
+
+redis.call('LPOP', prefix .. ':band:' .. best_band)
+redis.call('ZADD', prefix .. ':processing', now + visibility, best)
+return {best, redis.call('HGET', prefix .. ':payload', best)}
+"#;
+
+/// Take a waiting job out of the front list and every band; with ARGV[4] = "promote" it goes back at the front,
+/// otherwise it also leaves the delayed set and its payload is dropped. Returns 0 when the job was not waiting
+const UNQUEUE_SCRIPT: &str = r#"
+local prefix = ARGV[1]
+local id = ARGV[2]
+local bands = tonumber(ARGV[3])
+
+local removed = 0
+if ARGV[4] ~= 'promote' then
+    removed = redis.call('ZREM', prefix .. ':delayed', id)
+end
+removed = removed + redis.call('LREM', prefix .. ':front', 0, id)
+for band = 0, bands - 1 do
+    removed = removed + redis.call('LREM', prefix .. ':band:' .. band, 0, id)
+end
+if removed == 0 then
+    return 0
+end
+
+if ARGV[4] == 'promote' then
+    redis.call('LPUSH', prefix .. ':front', id)
+else
+    redis.call('HDEL', prefix .. ':payload', id)
+    redis.call('HDEL', prefix .. ':enqueued', id)
+    redis.call('HDEL', prefix .. ':band', id)
+end
+return 1
+"#;
+
+/// Queue shared by every engine instance pointed at the same Redis
+/// A dequeued job that isn't acknowledged within the visibility timeout is delivered again
+#[derive(Clone)]
+pub struct RedisQueue {
+    connection: ConnectionManager,
+    visibility_timeout: Duration,
+    dequeue_script: Script,
+    unqueue_script: Script,
+}
+
+impl RedisQueue {
+    /// Connect to Redis
+    pub async fn new(url: &str, visibility_timeout: Duration) -> Result<Self> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+        
+        Ok(Self {
+            connection,
+            visibility_timeout,
+            dequeue_script: Script::new(DEQUEUE_SCRIPT),
+            unqueue_script: Script::new(UNQUEUE_SCRIPT),
+        })
+    }
+    
+    /// Ids promoted ahead of the bands, next to be dequeued first
+    async fn promoted(&self) -> Result<Vec<String>> {
+        let mut connection = self.connection.clone();
+        Ok(redis::cmd("LRANGE").arg(key("front")).arg(0).arg(-1).query_async(&mut connection).await?)
+    }
+    
+    /// Remove a waiting job, either to drop it or to push it back at the front
+    async fn unqueue(&self, id: &str, mode: &str) -> Result<bool> {
+        let mut connection = self.connection.clone();
+        let removed: u32 = self.unqueue_script
+            .arg(KEY_PREFIX)
+            .arg(id)
+            .arg(PRIORITY_BANDS)
+            .arg(mode)
+            .invoke_async(&mut connection)
+            .await?;
+        Ok(removed > 0)
+    }
+    
+    /// Every queued id per band, with its enqueue time in milliseconds
+    async fn queued(&self) -> Result<Vec<(usize, String, u64)>> {
+        let mut connection = self.connection.clone();
+        
+        let mut pipe = redis::pipe();
+        for band in 0..PRIORITY_BANDS {
+            pipe.cmd("LRANGE").arg(band_key(band)).arg(0).arg(-1);
+        }
+        let bands: Vec<Vec<String>> = pipe.query_async(&mut connection).await?;
+        
+        let ids: Vec<&String> = bands.iter().flatten().collect();
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let times: Vec<Option<u64>> = redis::cmd("HMGET")
+            .arg(key("enqueued"))
+            .arg(&ids)
+            .query_async(&mut connection)
+            .await?;
+        
+        let mut times = times.into_iter();
+        Ok(bands.iter()
+            .enumerate()
+            .flat_map(|(band, ids)| ids.iter().map(move |id| (band, id.clone())))
+            .map(|(band, id)| (band, id, times.next().flatten().unwrap_or(0)))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl JobQueue for RedisQueue {
+    async fn enqueue(&self, job: ExecutionJob) -> Result<()> {
+        let mut connection = self.connection.clone();
+        let band = job.priority() as usize;
+        let payload = serde_json::to_string(&job)?;
+        
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .hset(key("payload"), &job.id, payload)
+            .hset(key("enqueued"), &job.id, now_millis())
+            .hset(key("band"), &job.id, band);
+        
+        // Scheduled jobs wait in a sorted set until the dequeue script releases them
+        match job.request.run_at.filter(|_| job.is_scheduled(chrono::Utc::now())) {
+            Some(run_at) => pipe.zadd(key("delayed"), &job.id, run_at.timestamp_millis()),
+            None => pipe.rpush(band_key(band), &job.id),
+        };
+        pipe.query_async::<_, ()>(&mut connection).await?;
+        Ok(())
+    }
+    
+    async fn dequeue(&self) -> Result<Option<ExecutionJob>> {
+        let mut connection = self.connection.clone();
+        
+        let next: Option<(String, Option<String>)> = self.dequeue_script
+            .arg(KEY_PREFIX)
+            .arg(now_millis())
+            .arg(AGE_BOOST_INTERVAL.as_millis() as u64)
+            .arg(self.visibility_timeout.as_millis() as u64)
+            .arg(PRIORITY_BANDS)
+            .invoke_async(&mut connection)
+            .await?;
+        
+        match next {
+            Some((_, Some(payload))) => Ok(Some(serde_json::from_str(&payload)?)),
+            Some((id, None)) => {
+                warn!("Dropping queued job {} with no payload", id);
+                self.ack(&id).await?;
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+    
+    async fn ack(&self, id: &str) -> Result<()> {
+        let mut connection = self.connection.clone();
+        
+        redis::pipe()
+            .atomic()
+            .zrem(key("processing"), id)
+            .hdel(key("payload"), id)
+            .hdel(key("enqueued"), id)
+            .hdel(key("band"), id)
+            .query_async::<_, ()>(&mut connection)
+            .await?;
+        Ok(())
+    }
+    
+    async fn position(&self, id: &str) -> Result<Option<usize>> {
+        let promoted = self.promoted().await?;
+        if let Some(index) = promoted.iter().position(|promoted_id| promoted_id == id) {
+            return Ok(Some(index));
+        }
+        
+        let queued = self.queued().await?;
+        let order = dequeue_order(now_millis());
+        let target = match queued.iter().find(|(_, queued_id, _)| queued_id == id) {
+            Some((band, _, enqueued)) => order(*band, *enqueued),
+            None => return Ok(None),
+        };
+        
+        Ok(Some(promoted.len() + queued.iter().filter(|(band, _, enqueued)| order(*band, *enqueued) > target).count()))
+    }
+    
+    async fn band_position(&self, id: &str) -> Result<Option<usize>> {
+        let mut connection = self.connection.clone();
+        
+        let band: Option<usize> = redis::cmd("HGET").arg(key("band")).arg(id).query_async(&mut connection).await?;
+        match band {
+            Some(band) => Ok(redis::cmd("LPOS").arg(band_key(band)).arg(id).query_async(&mut connection).await?),
+            None => Ok(None),
+        }
+    }
+    
+    async fn size(&self) -> Result<usize> {
+        let mut connection = self.connection.clone();
+        
+        let mut pipe = redis::pipe();
+        pipe.llen(key("front"));
+        for band in 0..PRIORITY_BANDS {
+            pipe.llen(band_key(band));
+        }
+        let lengths: Vec<usize> = pipe.query_async(&mut connection).await?;
+        Ok(lengths.iter().sum())
+    }
+    
+    async fn clear(&self) -> Result<()> {
+        let mut connection = self.connection.clone();
+        
+        let mut keys: Vec<String> = (0..PRIORITY_BANDS).map(band_key).collect();
+        keys.extend(["front", "delayed", "payload", "enqueued", "band", "processing"].map(key));
+        redis::cmd("DEL").arg(keys).query_async::<_, ()>(&mut connection).await?;
+        Ok(())
+    }
+    
+    async fn snapshot(&self) -> Result<Vec<QueuedEntry>> {
+        let mut connection = self.connection.clone();
+        
+        let promoted = self.promoted().await?;
+        let promoted_times: Vec<Option<u64>> = if promoted.is_empty() {
+            Vec::new()
+        } else {
+            redis::cmd("HMGET").arg(key("enqueued")).arg(&promoted).query_async(&mut connection).await?
+        };
+        
+        let mut queued = self.queued().await?;
+        let order = dequeue_order(now_millis());
+        queued.sort_by_key(|(band, _, enqueued)| std::cmp::Reverse(order(*band, *enqueued)));
+        
+        let waiting: Vec<(String, u64, bool)> = promoted.into_iter()
+            .zip(promoted_times)
+            .map(|(id, enqueued)| (id, enqueued.unwrap_or(0), true))
+            .chain(queued.into_iter().map(|(_, id, enqueued)| (id, enqueued, false)))
+            .collect();
+        if waiting.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let ids: Vec<&String> = waiting.iter().map(|(id, _, _)| id).collect();
+        let payloads: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(key("payload"))
+            .arg(&ids)
+            .query_async(&mut connection)
+            .await?;
+        
+        // Jobs dequeued between the reads have no payload left and are skipped
+        Ok(waiting.into_iter()
+            .zip(payloads)
+            .filter_map(|((_, enqueued, promoted), payload)| {
+                Some(QueuedEntry {
+                    job: serde_json::from_str(&payload?).ok()?,
+                    enqueued_at: chrono::DateTime::from_timestamp_millis(enqueued as i64).unwrap_or_default(),
+                    promoted,
+                })
+            })
+            .collect())
+    }
+    
+    async fn promote(&self, id: &str) -> Result<bool> {
+        self.unqueue(id, "promote").await
+    }
+    
+    async fn remove(&self, id: &str) -> Result<bool> {
+        self.unqueue(id, "remove").await
+    }
+    
+    fn is_durable(&self) -> bool {
+        true
+    }
+}
+
+/// Same ordering as the dequeue script: effective priority, then enqueue time
+fn dequeue_order(now: u64) -> impl Fn(usize, u64) -> (usize, std::cmp::Reverse<u64>) {
+    move |band, enqueued| {
+        let waited = Duration::from_millis(now.saturating_sub(enqueued));
+        (effective_priority(band, waited), std::cmp::Reverse(enqueued))
+    }
+}
+
+fn key(name: &str) -> String {
+    format!("{}:{}", KEY_PREFIX, name)
+}
+
+fn band_key(band: usize) -> String {
+    key(&format!("band:{}", band))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
@@ -1,6 +1,10 @@
-use crate::types::ResourceLimits;
+use crate::output::CappedOutput;
+use crate::types::{CpuAffinity, ExecutionMetrics, ResourceLimits};
 use anyhow::Result;
-use std::process::Command;
+use std::process::{Child, Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+#[cfg(windows)]
 use tracing::warn;
 
 /// Sandbox for securing code execution
@@ -40,8 +44,8 @@ impl Sandbox {
         
         // Apply resource limits using setrlimit
         let limits = self.limits.clone();
-        command.pre_exec(move || {
-            unsafe {
+        unsafe {
+            command.pre_exec(move || {
                 // CPU time limit (with extra time)
                 let total_cpu_time = limits.cpu_time + limits.cpu_extra_time;
                 let cpu_limit = libc::rlimit {
@@ -84,10 +88,33 @@ impl Sandbox {
                     rlim_max: 0,
                 };
                 libc::setrlimit(libc::RLIMIT_CORE, &core_limit);
-            }
-            
-            Ok(())
-        });
+                
+                // Only ever lowers the priority; validation keeps nice within 0-19
+                if let Some(nice) = limits.nice {
+                    if libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                
+                // The OOM killer should pick the program long before the engine
+                #[cfg(target_os = "linux")]
+                write_oom_score_adj(CHILD_OOM_SCORE_ADJ)?;
+                
+                // Affinity is resolved to concrete cores before the command is built
+                #[cfg(target_os = "linux")]
+                if let Some(CpuAffinity::Cores(cores)) = &limits.cpu_affinity {
+                    let mut set: libc::cpu_set_t = std::mem::zeroed();
+                    for core in cores {
+                        libc::CPU_SET(*core, &mut set);
+                    }
+                    if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                
+                Ok(())
+            });
+        }
         
         Ok(())
     }
@@ -103,3 +130,599 @@ impl Sandbox {
         Ok(())
     }
 }
+
+/// oom_score_adj of every program, so the kernel kills it rather than the engine
+pub const CHILD_OOM_SCORE_ADJ: &std::ffi::CStr = c"900";
+
+/// oom_score_adj the engine gives itself at startup; lowering it needs CAP_SYS_RESOURCE
+const ENGINE_OOM_SCORE_ADJ: &std::ffi::CStr = c"-500";
+
+/// Make the engine one of the last candidates of the OOM killer, if it is allowed to
+#[cfg(target_os = "linux")]
+pub fn protect_engine() {
+    match unsafe { write_oom_score_adj(ENGINE_OOM_SCORE_ADJ) } {
+        Ok(()) => tracing::info!("🛡️ Engine oom_score_adj set to {}", ENGINE_OOM_SCORE_ADJ.to_string_lossy()),
+        Err(err) => tracing::info!("Engine keeps its oom_score_adj, lowering it was refused: {}", err),
+    }
+}
+
+/// Nothing to adjust outside Linux
+#[cfg(not(target_os = "linux"))]
+pub fn protect_engine() {}
+
+/// Write /proc/self/oom_score_adj without allocating, so it is safe between fork and exec
+#[cfg(target_os = "linux")]
+unsafe fn write_oom_score_adj(value: &std::ffi::CStr) -> std::io::Result<()> {
+    let fd = libc::open(c"/proc/self/oom_score_adj".as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+    if fd == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let bytes = value.to_bytes();
+    let written = libc::write(fd, bytes.as_ptr().cast(), bytes.len());
+    let err = std::io::Error::last_os_error();
+    libc::close(fd);
+    if written == bytes.len() as isize {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+/// Cores this process may run on, and so the only ones jobs can be pinned to
+#[cfg(target_os = "linux")]
+pub fn available_cores() -> Vec<usize> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return (0..std::thread::available_parallelism().map_or(1, |n| n.get())).collect();
+        }
+        (0..libc::CPU_SETSIZE as usize).filter(|core| libc::CPU_ISSET(*core, &set)).collect()
+    }
+}
+
+/// Cores this process may run on; pinning is only applied on Linux
+#[cfg(not(target_os = "linux"))]
+pub fn available_cores() -> Vec<usize> {
+    (0..std::thread::available_parallelism().map_or(1, |n| n.get())).collect()
+}
+
+/// Cores handed to jobs pinned with `"auto"`, spreading concurrent jobs over distinct cores
+#[derive(Clone)]
+pub(crate) struct CpuPool {
+    cores: Arc<Vec<usize>>,
+    reserved: bool,
+    jobs: Arc<Mutex<Vec<usize>>>, // jobs pinned to each entry of `cores`
+    next: Arc<AtomicUsize>,       // where the search for the least busy core starts
+}
+
+impl CpuPool {
+    /// Pool over the reserved cores, or every available core when none are reserved
+    pub fn new(reserved: &[usize]) -> Self {
+        let cores = if reserved.is_empty() { available_cores() } else { reserved.to_vec() };
+        Self {
+            jobs: Arc::new(Mutex::new(vec![0; cores.len()])),
+            cores: Arc::new(cores),
+            reserved: !reserved.is_empty(),
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+    
+    /// Cores for a job, held until the lease is dropped
+    /// `"auto"` takes the least busy core, round-robin among ties; explicit cores outside a reserved
+    /// pool are dropped, and a job left with none is placed as if it had asked for `"auto"`
+    pub fn assign(&self, affinity: &CpuAffinity) -> CoreLease {
+        let mut jobs = self.jobs.lock().unwrap();
+        let mut cores = match affinity {
+            CpuAffinity::Cores(cores) if !self.reserved => cores.clone(),
+            CpuAffinity::Cores(cores) => cores.iter().copied().filter(|core| self.cores.contains(core)).collect(),
+            CpuAffinity::Auto(_) => Vec::new(),
+        };
+        if cores.is_empty() {
+            let start = self.next.fetch_add(1, Ordering::Relaxed);
+            let least_busy = (0..self.cores.len())
+                .map(|offset| (start + offset) % self.cores.len())
+                .min_by_key(|index| jobs[*index]);
+            cores.extend(least_busy.map(|index| self.cores[index]));
+        }
+        
+        let indexes: Vec<usize> = (0..self.cores.len()).filter(|index| cores.contains(&self.cores[*index])).collect();
+        for index in &indexes {
+            jobs[*index] += 1;
+        }
+        CoreLease { pool: self.clone(), indexes, cores }
+    }
+}
+
+/// Cores a job is pinned to; they count as busy until this is dropped
+pub(crate) struct CoreLease {
+    pool: CpuPool,
+    indexes: Vec<usize>, // entries of the pool this lease counts against
+    cores: Vec<usize>,
+}
+
+impl CoreLease {
+    pub fn cores(&self) -> &[usize] {
+        &self.cores
+    }
+}
+
+impl Drop for CoreLease {
+    fn drop(&mut self) {
+        let mut jobs = self.pool.jobs.lock().unwrap();
+        for index in &self.indexes {
+            jobs[*index] = jobs[*index].saturating_sub(1);
+        }
+    }
+}
+
+/// Job id reduced to characters that are safe in file and container names
+///
+/// Temp directories and containers carry it so leftovers of a crashed run can be traced back to
+/// their job. Anything but ASCII letters, digits and '-' becomes '_', leaving '.' free to end it.
+pub fn job_tag(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// Forcefully terminate a process started by the executor, along with everything in its process group
+#[cfg(unix)]
+pub fn kill_process(pid: u32) {
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+        libc::kill(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+pub fn kill_process(pid: u32) {
+    warn!("Cannot kill process {} on Windows yet", pid);
+}
+
+/// Give the child's stderr the same pipe as its stdout, so interleaved writes keep their order
+/// stdout must already be set up; stderr then has nothing to read
+#[cfg(unix)]
+pub fn merge_stderr_into_stdout(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    
+    command.stderr(std::process::Stdio::null());
+    unsafe {
+        // Closures run after the stdio redirections, so fd 1 is already the pipe
+        command.pre_exec(|| {
+            if libc::dup2(1, 2) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(windows)]
+pub fn merge_stderr_into_stdout(command: &mut Command) {
+    warn!("Merging stderr into stdout is not supported on Windows yet");
+    command.stderr(std::process::Stdio::piped());
+}
+
+/// Streams that went over the output cap and were cut
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Truncated {
+    /// stdout was cut
+    pub stdout: bool,
+    /// stderr was cut
+    pub stderr: bool,
+}
+
+/// A process that was waited for: its output, the kernel's accounting and how its streams were cut
+#[derive(Debug)]
+pub struct Exited {
+    /// Exit status and the captured streams
+    pub output: Output,
+    /// Kernel accounting, without the wall time
+    pub metrics: ExecutionMetrics,
+    /// Which streams went over the output cap
+    pub truncated: Truncated,
+    /// Where it faulted, when it was traced and received a SIGSEGV from the kernel
+    pub fault: Option<SegvFault>,
+}
+
+/// Address behind a SIGSEGV, and the bounds of the process's stack at the time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegvFault {
+    /// Address whose access faulted
+    pub address: u64,
+    /// Start and end of the main thread's stack mapping
+    pub stack: Option<(u64, u64)>,
+}
+
+/// `Child::wait_with_output`, plus the kernel's accounting of what the child and the children it waited for used
+/// `wall_time` is left to the caller, which knows when the program started. Each stream keeps its first
+/// `max_output` bytes, cut on a character boundary; the rest is read and dropped. A traced child is
+/// stopped by every signal it receives until the engine passes it on, which is how a SIGSEGV's address is seen
+#[cfg(unix)]
+pub fn wait_with_metrics(mut child: Child, max_output: usize, trace: bool) -> std::io::Result<Exited> {
+    use std::os::unix::process::ExitStatusExt;
+    
+    // The thread that seizes the child is its tracer, so this one waits while others read the pipes
+    let pid = child.id() as libc::pid_t;
+    let traced = trace && seize(pid);
+    drop(child.stdin.take());
+    let stdout_pipe = child.stdout.take();
+    let stdout_reader = std::thread::spawn(move || {
+        stdout_pipe.map_or(Ok((Vec::new(), false)), |pipe| read_capped(pipe, max_output))
+    });
+    let stderr_pipe = child.stderr.take();
+    let stderr_reader = std::thread::spawn(move || {
+        stderr_pipe.map_or(Ok((Vec::new(), false)), |pipe| read_capped(pipe, max_output))
+    });
+    let fault = if traced { follow_signals(pid)? } else { None };
+    let (stderr, stderr_truncated) = stderr_reader.join().map_err(|_| std::io::Error::other("stderr reader panicked"))??;
+    let (stdout, stdout_truncated) = stdout_reader.join().map_err(|_| std::io::Error::other("stdout reader panicked"))??;
+    let truncated = Truncated { stdout: stdout_truncated, stderr: stderr_truncated };
+    
+    // Waiting without reaping first keeps /proc/<pid>/io readable for the I/O counters
+    retry_interrupted(|| unsafe {
+        let mut info: libc::siginfo_t = std::mem::zeroed();
+        libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, libc::WEXITED | libc::WNOWAIT)
+    })?;
+    let (read_bytes, written_bytes) = io_counters(pid);
+    
+    let mut status = 0;
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    retry_interrupted(|| unsafe { libc::wait4(pid, &mut status, 0, &mut usage) })?;
+    
+    let seconds = |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1e6;
+    let (user_time, system_time) = (seconds(usage.ru_utime), seconds(usage.ru_stime));
+    // ru_maxrss is in bytes on macOS and in kilobytes everywhere else
+    let rss_unit = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    let metrics = ExecutionMetrics {
+        cpu_time: Some(user_time + system_time),
+        user_time: Some(user_time),
+        system_time: Some(system_time),
+        memory_peak: Some(usage.ru_maxrss.max(0) as u64 * rss_unit),
+        wall_time: None,
+        minor_page_faults: Some(usage.ru_minflt.max(0) as u64),
+        major_page_faults: Some(usage.ru_majflt.max(0) as u64),
+        voluntary_context_switches: Some(usage.ru_nvcsw.max(0) as u64),
+        involuntary_context_switches: Some(usage.ru_nivcsw.max(0) as u64),
+        read_bytes,
+        written_bytes,
+    };
+    
+    let output = Output {
+        status: std::process::ExitStatus::from_raw(status),
+        stdout,
+        stderr,
+    };
+    Ok(Exited { output, metrics, truncated, fault })
+}
+
+#[cfg(windows)]
+pub fn wait_with_metrics(child: Child, max_output: usize, _trace: bool) -> std::io::Result<Exited> {
+    let mut output = child.wait_with_output()?;
+    let mut cap = |bytes: &mut Vec<u8>| {
+        let mut capped = CappedOutput::new(max_output);
+        capped.push(bytes);
+        let (kept, truncated) = capped.finish();
+        *bytes = kept;
+        truncated
+    };
+    let truncated = Truncated { stdout: cap(&mut output.stdout), stderr: cap(&mut output.stderr) };
+    Ok(Exited { output, metrics: ExecutionMetrics::default(), truncated, fault: None })
+}
+
+/// Become the tracer of a running child without stopping it; refused under some seccomp profiles
+#[cfg(target_os = "linux")]
+fn seize(pid: libc::pid_t) -> bool {
+    let seized = unsafe { libc::ptrace(libc::PTRACE_SEIZE, pid, 0, 0) } == 0;
+    if !seized {
+        tracing::debug!("Could not trace process {}: {}", pid, std::io::Error::last_os_error());
+    }
+    seized
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn seize(_pid: libc::pid_t) -> bool {
+    false
+}
+
+/// Pass on every signal a traced child receives until it exits, which is left unreaped
+/// Returns where the last SIGSEGV faulted, unless a process sent that one
+#[cfg(target_os = "linux")]
+fn follow_signals(pid: libc::pid_t) -> std::io::Result<Option<SegvFault>> {
+    let mut fault = None;
+    loop {
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        retry_interrupted(|| unsafe {
+            libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, libc::WEXITED | libc::WSTOPPED | libc::WNOWAIT)
+        })?;
+        if info.si_code != libc::CLD_TRAPPED {
+            return Ok(fault);
+        }
+        
+        let stop = unsafe { info.si_status() };
+        // A group stop carries an event in the upper bits; the child stays stopped until it gets SIGCONT
+        if stop >> 8 != 0 {
+            unsafe { libc::ptrace(libc::PTRACE_LISTEN, pid, 0, 0) };
+            continue;
+        }
+        if stop == libc::SIGSEGV {
+            fault = segv_fault(pid);
+        }
+        // Fails only if the child was killed meanwhile, which the next wait reports
+        unsafe { libc::ptrace(libc::PTRACE_CONT, pid, 0, stop) };
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn follow_signals(_pid: libc::pid_t) -> std::io::Result<Option<SegvFault>> {
+    Ok(None)
+}
+
+/// Where a child stopped for a SIGSEGV faulted, unless the signal was sent by a process
+#[cfg(target_os = "linux")]
+fn segv_fault(pid: libc::pid_t) -> Option<SegvFault> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ptrace(libc::PTRACE_GETSIGINFO, pid, 0, &mut info) } != 0 || info.si_code <= 0 {
+        return None;
+    }
+    let stack = std::fs::read_to_string(format!("/proc/{}/maps", pid)).ok().and_then(|maps| stack_bounds(&maps));
+    Some(SegvFault { address: unsafe { info.si_addr() } as u64, stack })
+}
+
+/// Start and end of the `[stack]` mapping in a /proc/<pid>/maps listing
+#[cfg(target_os = "linux")]
+fn stack_bounds(maps: &str) -> Option<(u64, u64)> {
+    let line = maps.lines().find(|line| line.ends_with("[stack]"))?;
+    let (start, end) = line.split_whitespace().next()?.split_once('-')?;
+    Some((u64::from_str_radix(start, 16).ok()?, u64::from_str_radix(end, 16).ok()?))
+}
+
+/// Read a pipe to its end, keeping the first `limit` bytes
+#[cfg(unix)]
+fn read_capped(mut pipe: impl std::io::Read, limit: usize) -> std::io::Result<(Vec<u8>, bool)> {
+    let mut capped = CappedOutput::new(limit);
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        match pipe.read(&mut buffer) {
+            Ok(0) => return Ok(capped.finish()),
+            Ok(read) => capped.push(&buffer[..read]),
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Repeat a system call that was interrupted by a signal
+#[cfg(unix)]
+fn retry_interrupted(mut call: impl FnMut() -> libc::c_int) -> std::io::Result<()> {
+    loop {
+        if call() != -1 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+/// Bytes an exited but unreaped process passed to read and write calls (rchar and wchar)
+#[cfg(target_os = "linux")]
+fn io_counters(pid: libc::pid_t) -> (Option<u64>, Option<u64>) {
+    let Ok(io) = std::fs::read_to_string(format!("/proc/{}/io", pid)) else {
+        return (None, None);
+    };
+    let counter = |name: &str| io.lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+        .and_then(|value| value.trim().parse().ok());
+    (counter("rchar"), counter("wchar"))
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn io_counters(_pid: libc::pid_t) -> (Option<u64>, Option<u64>) {
+    (None, None)
+}
+
+/// cgroup v2 is mounted and the engine's own group is writable, so it could create groups for jobs
+#[cfg(target_os = "linux")]
+pub fn cgroup_v2_writable() -> bool {
+    // The unified hierarchy is the "0::" line
+    let Some(group) = std::fs::read_to_string("/proc/self/cgroup")
+        .ok()
+        .and_then(|groups| groups.lines().find_map(|line| line.strip_prefix("0::").map(str::to_string)))
+    else {
+        return false;
+    };
+    let dir = std::path::Path::new("/sys/fs/cgroup").join(group.trim_start_matches('/'));
+    let Ok(dir) = std::ffi::CString::new(dir.as_os_str().as_encoded_bytes()) else {
+        return false;
+    };
+    std::path::Path::new("/sys/fs/cgroup/cgroup.controllers").exists() && unsafe { libc::access(dir.as_ptr(), libc::W_OK) } == 0
+}
+
+/// Only Linux has cgroups
+#[cfg(not(target_os = "linux"))]
+pub fn cgroup_v2_writable() -> bool {
+    false
+}
+
+/// Name of the signal that terminated a process, if it did not exit on its own
+#[cfg(unix)]
+pub fn termination_signal(status: &std::process::ExitStatus) -> Option<String> {
+    use std::os::unix::process::ExitStatusExt;
+    
+    status.signal().map(signal_name)
+}
+
+/// Name of a signal number, such as `SIGSEGV`
+#[cfg(unix)]
+pub fn signal_name(signal: i32) -> String {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        libc::SIGSYS => "SIGSYS",
+        other => return format!("signal {}", other),
+    };
+    name.to_string()
+}
+
+/// How a program ended, for people: "exited normally with code 3" or "killed by SIGSEGV (segmentation fault)"
+pub fn exit_description(exit_code: i32, signal: Option<&str>) -> String {
+    match signal {
+        Some(signal) => match signal_meaning(signal) {
+            Some(meaning) => format!("killed by {} ({})", signal, meaning),
+            None => format!("killed by {}", signal),
+        },
+        None => format!("exited normally with code {}", exit_code),
+    }
+}
+
+/// What a fatal signal most likely means for a sandboxed program
+fn signal_meaning(signal: &str) -> Option<&'static str> {
+    let meaning = match signal {
+        "SIGHUP" => "hangup",
+        "SIGINT" => "interrupted",
+        "SIGQUIT" => "quit",
+        "SIGILL" => "illegal instruction",
+        "SIGTRAP" => "trace or breakpoint trap",
+        "SIGABRT" => "aborted, likely a failed assertion or an uncaught exception",
+        "SIGBUS" => "bus error, likely a misaligned or invalid memory access",
+        "SIGFPE" => "floating point exception, likely division by zero",
+        "SIGKILL" => "exceeded time or memory limit",
+        "SIGSEGV" => "segmentation fault",
+        "SIGPIPE" => "broken pipe, wrote to a closed stream",
+        "SIGALRM" => "alarm clock",
+        "SIGTERM" => "terminated",
+        "SIGXCPU" => "exceeded CPU time limit",
+        "SIGXFSZ" => "exceeded file size limit",
+        "SIGSYS" => "bad system call, blocked by the sandbox",
+        _ => return None,
+    };
+    Some(meaning)
+}
+
+#[cfg(windows)]
+pub fn termination_signal(_status: &std::process::ExitStatus) -> Option<String> {
+    None
+}
+
+#[cfg(windows)]
+pub fn signal_name(signal: i32) -> String {
+    format!("signal {}", signal)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::types::AutoAffinity;
+    
+    const AUTO: CpuAffinity = CpuAffinity::Auto(AutoAffinity::Auto);
+    
+    #[test]
+    fn finds_the_stack_mapping() {
+        let maps = "55d0c6a00000-55d0c6a21000 rw-p 00000000 00:00 0                          [heap]\n\
+            7ffc1e9d0000-7ffc1e9f1000 rw-p 00000000 00:00 0                          [stack]\n\
+            7ffc1e9f5000-7ffc1e9f9000 r--p 00000000 00:00 0                          [vvar]\n";
+        assert_eq!(stack_bounds(maps), Some((0x7ffc1e9d0000, 0x7ffc1e9f1000)));
+        assert_eq!(stack_bounds("55d0c6a00000-55d0c6a21000 rw-p 00000000 00:00 0 [heap]\n"), None);
+    }
+    
+    #[test]
+    fn auto_jobs_land_on_distinct_cores_while_any_is_free() {
+        let pool = CpuPool::new(&[2, 3, 5]);
+        let leases: Vec<_> = (0..3).map(|_| pool.assign(&AUTO)).collect();
+        let mut cores: Vec<usize> = leases.iter().flat_map(|lease| lease.cores().to_vec()).collect();
+        cores.sort();
+        assert_eq!(cores, [2, 3, 5]);
+        
+        // A fourth shares a core, and a freed core is the next one handed out
+        assert_eq!(pool.assign(&AUTO).cores().len(), 1);
+        let freed = leases[1].cores()[0];
+        drop(leases);
+        let _busy: Vec<_> = [2, 3, 5].iter().filter(|core| **core != freed).map(|core| pool.assign(&CpuAffinity::Cores(vec![*core]))).collect();
+        assert_eq!(pool.assign(&AUTO).cores(), [freed]);
+    }
+    
+    #[test]
+    fn explicit_cores_stay_inside_a_reserved_pool() {
+        let pool = CpuPool::new(&[2, 3]);
+        assert_eq!(pool.assign(&CpuAffinity::Cores(vec![3, 7])).cores(), [3]);
+        let outside = pool.assign(&CpuAffinity::Cores(vec![7]));
+        assert_eq!(outside.cores().len(), 1);
+        assert!([2, 3].contains(&outside.cores()[0]));
+        
+        // Without a reservation any core asked for is used as is
+        let open = CpuPool::new(&[]);
+        assert_eq!(open.assign(&CpuAffinity::Cores(vec![0, 1])).cores(), [0, 1]);
+    }
+    
+    #[test]
+    fn affinity_reads_and_prints_as_a_cpuset_list() {
+        assert_eq!("0, 2,5".parse::<CpuAffinity>().unwrap(), CpuAffinity::Cores(vec![0, 2, 5]));
+        assert_eq!(CpuAffinity::Cores(vec![0, 2, 5]).to_string(), "0,2,5");
+        assert_eq!("AUTO".parse::<CpuAffinity>().unwrap(), AUTO);
+        assert!("0,x".parse::<CpuAffinity>().is_err());
+    }
+    
+    #[test]
+    fn available_cores_include_the_current_one() {
+        let current = unsafe { libc::sched_getcpu() };
+        assert!(current >= 0);
+        assert!(available_cores().contains(&(current as usize)));
+    }
+    
+    #[test]
+    fn waiting_reports_the_kernels_accounting() {
+        let child = Command::new("sh")
+            .args(["-c", "head -c 100000 /dev/zero; i=0; while [ $i -lt 20000 ]; do i=$((i+1)); done"])
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let Exited { output, metrics, .. } = wait_with_metrics(child, 1 << 20, false).unwrap();
+        
+        assert!(output.status.success());
+        assert_eq!(output.stdout.len(), 100_000);
+        let (user, system) = (metrics.user_time.unwrap(), metrics.system_time.unwrap());
+        assert!((metrics.cpu_time.unwrap() - (user + system)).abs() < 1e-9);
+        assert!(metrics.minor_page_faults.unwrap() > 0);
+        assert!(metrics.memory_peak.unwrap() > 0);
+        // head's own output is counted along with whatever the shell wrote
+        assert!(metrics.written_bytes.is_some_and(|written| written >= 100_000), "{:?}", metrics.written_bytes);
+        assert_eq!(metrics.wall_time, None);
+    }
+    
+    #[test]
+    fn exits_are_described_for_people() {
+        assert_eq!(exit_description(3, None), "exited normally with code 3");
+        assert_eq!(exit_description(-1, Some("SIGSEGV")), "killed by SIGSEGV (segmentation fault)");
+        assert_eq!(exit_description(-1, Some("SIGFPE")), "killed by SIGFPE (floating point exception, likely division by zero)");
+        assert_eq!(exit_description(-1, Some("SIGKILL")), "killed by SIGKILL (exceeded time or memory limit)");
+        assert_eq!(exit_description(-1, Some("signal 40")), "killed by signal 40");
+    }
+    
+    #[test]
+    fn every_named_signal_has_a_meaning() {
+        for signal in 1..=31 {
+            let name = signal_name(signal);
+            if !name.starts_with("signal ") {
+                assert!(signal_meaning(&name).is_some(), "{} has no explanation", name);
+            }
+        }
+        assert_eq!(signal_name(libc::SIGSYS), "SIGSYS");
+        assert_eq!(signal_name(64), "signal 64");
+    }
+}
@@ -0,0 +1,200 @@
+//! Outputs too large to embed in a result, kept as files and served as downloads.
+//!
+//! A spilled stream is null in the result, which carries its size and download URL instead.
+//! The files live as long as their job: they go when it is deleted or evicted, and the janitor
+//! removes any whose job is gone, such as after a restart without a job store.
+use crate::types::ExecutionResult;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use std::borrow::Cow;
+use std::fs;
+use std::path::PathBuf;
+
+/// Output stream of a spill file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    /// Standard output
+    Stdout,
+    /// Standard error
+    Stderr,
+}
+
+impl OutputStream {
+    /// Name used in file names and URLs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutputStream::Stdout => "stdout",
+            OutputStream::Stderr => "stderr",
+        }
+    }
+}
+
+/// Spill files of outputs over a size threshold, one per job and stream.
+///
+/// ```
+/// use labforcode_engine::spill::{OutputSpill, OutputStream};
+///
+/// let root = tempfile::tempdir().unwrap();
+/// let spill = OutputSpill::new(root.path().join("spill"), 8 * 1024 * 1024).unwrap();
+/// assert!(spill.file("job-1", OutputStream::Stdout).is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutputSpill {
+    root: PathBuf,
+    threshold: usize,
+}
+
+impl OutputSpill {
+    /// Keep files under `root`, spilling outputs over `threshold` bytes; 0 never spills
+    pub fn new(root: PathBuf, threshold: usize) -> Result<Self> {
+        if threshold > 0 {
+            fs::create_dir_all(&root)?;
+        }
+        Ok(Self { root, threshold })
+    }
+    
+    /// Move stdout and stderr over the threshold out of the result into files
+    ///
+    /// Base64 outputs are written decoded, so the download is the program's actual bytes.
+    pub fn spill(&self, result: &mut ExecutionResult, base64_encoded: bool) -> Result<()> {
+        if self.threshold == 0 {
+            return Ok(());
+        }
+        let id = result.id.clone();
+        for stream in [OutputStream::Stdout, OutputStream::Stderr] {
+            let (output, bytes, url) = match stream {
+                OutputStream::Stdout => (&mut result.stdout, &mut result.stdout_bytes, &mut result.stdout_url),
+                OutputStream::Stderr => (&mut result.stderr, &mut result.stderr_bytes, &mut result.stderr_url),
+            };
+            let Some(text) = output.as_deref().filter(|text| text.len() > self.threshold) else {
+                continue;
+            };
+            let content = match base64_encoded {
+                true => general_purpose::STANDARD.decode(text).map_or(Cow::Borrowed(text.as_bytes()), Cow::Owned),
+                false => Cow::Borrowed(text.as_bytes()),
+            };
+            let path = self.path_for(&id, stream)
+                .ok_or_else(|| anyhow!("Execution id '{}' cannot be used as a file name", id))?;
+            fs::write(&path, &content)?;
+            
+            *bytes = Some(content.len() as u64);
+            *url = Some(format!("/v1/result/{}/{}", id, stream.as_str()));
+            *output = None;
+        }
+        Ok(())
+    }
+    
+    /// Spill file of a job's stream, when there is one
+    pub fn file(&self, id: &str, stream: OutputStream) -> Option<PathBuf> {
+        self.path_for(id, stream).filter(|path| path.is_file())
+    }
+    
+    /// Delete a job's spill files
+    pub fn remove(&self, id: &str) -> Result<()> {
+        for stream in [OutputStream::Stdout, OutputStream::Stderr] {
+            if let Some(path) = self.file(id, stream) {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Ids of the jobs that have spill files
+    pub fn ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = fs::read_dir(&self.root).into_iter().flatten().flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let (id, stream) = name.rsplit_once('.')?;
+                matches!(stream, "stdout" | "stderr").then(|| id.to_string())
+            })
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+    
+    /// File for a job's stream, or None if the id isn't safe in a file name
+    fn path_for(&self, id: &str, stream: OutputStream) -> Option<PathBuf> {
+        let safe = !id.is_empty()
+            && !id.starts_with('.')
+            && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        
+        safe.then(|| self.root.join(format!("{}.{}", id, stream.as_str())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn result(id: &str, stdout: &str, stderr: &str) -> ExecutionResult {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "status": "completed", "stdout": stdout, "stderr": stderr,
+            "created_at": "2030-01-01T00:00:00Z", "compile_cache_hit": false,
+        })).unwrap()
+    }
+    
+    #[test]
+    fn only_outputs_over_the_threshold_are_spilled() {
+        let root = tempfile::tempdir().unwrap();
+        let spill = OutputSpill::new(root.path().join("spill"), 1024).unwrap();
+        let mut result = result("job-1", &"x".repeat(4096), &"y".repeat(1024));
+        spill.spill(&mut result, false).unwrap();
+        
+        assert_eq!(result.stdout, None);
+        assert_eq!(result.stdout_bytes, Some(4096));
+        assert_eq!(result.stdout_url.as_deref(), Some("/v1/result/job-1/stdout"));
+        assert_eq!((result.stderr.as_deref().map(str::len), result.stderr_bytes), (Some(1024), None));
+        
+        let file = spill.file("job-1", OutputStream::Stdout).unwrap();
+        assert_eq!(fs::read(file).unwrap().len(), 4096);
+        assert!(spill.file("job-1", OutputStream::Stderr).is_none());
+    }
+    
+    #[test]
+    fn base64_outputs_are_written_decoded() {
+        let root = tempfile::tempdir().unwrap();
+        let spill = OutputSpill::new(root.path().to_path_buf(), 16).unwrap();
+        let bytes: Vec<u8> = (0..=255).collect();
+        let mut result = result("job-2", &general_purpose::STANDARD.encode(&bytes), "");
+        spill.spill(&mut result, true).unwrap();
+        
+        assert_eq!(result.stdout_bytes, Some(256));
+        assert_eq!(fs::read(spill.file("job-2", OutputStream::Stdout).unwrap()).unwrap(), bytes);
+    }
+    
+    #[test]
+    fn ids_that_are_not_file_names_are_refused() {
+        let root = tempfile::tempdir().unwrap();
+        let spill = OutputSpill::new(root.path().to_path_buf(), 1).unwrap();
+        for id in ["../escape", ".hidden", "a/b", ""] {
+            assert!(spill.spill(&mut result(id, "long enough", ""), false).is_err(), "{:?}", id);
+            assert!(spill.file(id, OutputStream::Stdout).is_none());
+        }
+    }
+    
+    #[test]
+    fn files_are_listed_by_job_and_removed_together() {
+        let root = tempfile::tempdir().unwrap();
+        let spill = OutputSpill::new(root.path().to_path_buf(), 1).unwrap();
+        spill.spill(&mut result("job-b", "out", "err"), false).unwrap();
+        spill.spill(&mut result("job-a", "out", ""), false).unwrap();
+        fs::write(root.path().join("notes.txt"), "unrelated").unwrap();
+        assert_eq!(spill.ids(), ["job-a", "job-b"]);
+        
+        spill.remove("job-b").unwrap();
+        assert_eq!(spill.ids(), ["job-a"]);
+        assert!(spill.file("job-b", OutputStream::Stderr).is_none());
+    }
+    
+    #[test]
+    fn a_zero_threshold_never_spills() {
+        let root = tempfile::tempdir().unwrap();
+        let spill = OutputSpill::new(root.path().join("unused"), 0).unwrap();
+        let mut result = result("job-3", &"x".repeat(1 << 20), "");
+        spill.spill(&mut result, false).unwrap();
+        
+        assert!(result.stdout.is_some());
+        assert!(!root.path().join("unused").exists());
+    }
+}
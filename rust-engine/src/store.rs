@@ -0,0 +1,128 @@
+use crate::types::ExecutionJob;
+use anyhow::Result;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::path::Path;
+use tracing::info;
+
+/// Durable copy of every job, so restarts don't lose queued work or finished results
+#[derive(Clone)]
+pub struct JobStore {
+    pool: SqlitePool,
+}
+
+impl JobStore {
+    /// Open (or create) the database at the given path
+    pub async fn open(path: &Path) -> Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await?;
+        
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS jobs (
+                id TEXT PRIMARY KEY,
+                status TEXT NOT NULL,
+                job TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS usage (
+                client TEXT PRIMARY KEY,
+                usage TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        
+        info!("💾 Persisting jobs to {}", path.display());
+        Ok(Self { pool })
+    }
+    
+    /// Insert or replace a job with its current state and result
+    pub async fn save(&self, job: &ExecutionJob) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO jobs (id, status, job, updated_at) VALUES (?, ?, ?, ?)")
+            .bind(&job.id)
+            .bind(job.status.as_str())
+            .bind(serde_json::to_string(job)?)
+            .bind(chrono::Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+    
+    /// Look up a job by id
+    pub async fn load(&self, id: &str) -> Result<Option<ExecutionJob>> {
+        let row = sqlx::query("SELECT job FROM jobs WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        
+        match row {
+            Some(row) => Ok(Some(serde_json::from_str(row.get::<&str, _>("job"))?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Jobs that were queued or running when the engine last stopped
+    pub async fn unfinished(&self) -> Result<Vec<ExecutionJob>> {
+        let rows = sqlx::query("SELECT job FROM jobs WHERE status IN ('scheduled', 'queued', 'processing', 'running') ORDER BY updated_at")
+            .fetch_all(&self.pool)
+            .await?;
+        
+        rows.iter()
+            .map(|row| Ok(serde_json::from_str(row.get::<&str, _>("job"))?))
+            .collect()
+    }
+    
+    /// Insert or replace a client's quota usage, serialized by the caller
+    pub async fn save_usage(&self, client: &str, usage: &str) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO usage (client, usage, updated_at) VALUES (?, ?, ?)")
+            .bind(client)
+            .bind(usage)
+            .bind(chrono::Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+    
+    /// Every client's saved quota usage
+    pub async fn load_usage(&self) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query("SELECT client, usage FROM usage")
+            .fetch_all(&self.pool)
+            .await?;
+        
+        Ok(rows.iter()
+            .map(|row| (row.get("client"), row.get("usage")))
+            .collect())
+    }
+    
+    /// Remove archived jobs that finished before the cutoff, returning how many there were
+    pub async fn delete_archived(&self, cutoff: chrono::DateTime<chrono::Utc>) -> Result<u64> {
+        let deleted = sqlx::query(
+            "DELETE FROM jobs WHERE json_extract(job, '$.archived_at') IS NOT NULL
+                AND julianday(json_extract(job, '$.finished_at')) < julianday(?)",
+        )
+        .bind(cutoff.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(deleted.rows_affected())
+    }
+    
+    /// Remove a job
+    pub async fn delete(&self, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM jobs WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
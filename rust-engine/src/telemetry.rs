@@ -0,0 +1,71 @@
+use anyhow::Result;
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+/// Bucket bounds for execution durations, in seconds
+const DURATION_BUCKETS: &[f64] = &[0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Install the Prometheus recorder; the returned handle renders /metrics
+pub fn install() -> Result<PrometheusHandle> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Full("execution_duration_seconds".to_string()), DURATION_BUCKETS)?
+        .install_recorder()?;
+    
+    describe_counter!("executions_total", "Finished executions by language and final status");
+    describe_histogram!("execution_duration_seconds", Unit::Seconds, "Time a job occupied a worker");
+    describe_gauge!("queue_depth", "Jobs waiting for a worker");
+    describe_gauge!("active_executions", "Jobs currently running");
+    describe_counter!("callback_failures_total", "Result callbacks that could not be delivered");
+    describe_gauge!("process_resident_memory_bytes", Unit::Bytes, "Resident memory of the engine process");
+    
+    // Export zeros before the first event so dashboards don't show gaps
+    counter!("callback_failures_total").absolute(0);
+    gauge!("queue_depth").set(0.0);
+    gauge!("active_executions").set(0.0);
+    
+    Ok(handle)
+}
+
+/// Count a finished execution and how long it ran
+pub fn record_execution(language: &str, status: &str, seconds: f64) {
+    counter!("executions_total", "language" => language.to_string(), "status" => status.to_string()).increment(1);
+    histogram!("execution_duration_seconds", "language" => language.to_string()).record(seconds);
+}
+
+/// Number of jobs waiting in the queue
+pub fn set_queue_depth(depth: usize) {
+    gauge!("queue_depth").set(depth as f64);
+}
+
+/// Number of jobs that can run at once
+pub fn set_workers(workers: usize) {
+    gauge!("workers").set(workers as f64);
+}
+
+/// A worker picked up a job
+pub fn execution_started() {
+    gauge!("active_executions").increment(1.0);
+}
+
+/// A worker finished a job, successfully or not
+pub fn execution_finished() {
+    gauge!("active_executions").decrement(1.0);
+}
+
+/// A result callback gave up after its last attempt
+pub fn callback_failed() {
+    counter!("callback_failures_total").increment(1);
+}
+
+/// Render every metric in the Prometheus text format
+pub fn render(handle: &PrometheusHandle) -> String {
+    let mut system = sysinfo::System::new();
+    if let Ok(pid) = sysinfo::get_current_pid() {
+        system.refresh_process(pid);
+        if let Some(process) = system.process(pid) {
+            gauge!("process_resident_memory_bytes").set(process.memory() as f64);
+        }
+    }
+    
+    handle.render()
+}
@@ -1,235 +1,3987 @@
+use crate::config::{QueueBackend, RuntimeSettings};
+use crate::executor::ExecutionBackend;
+use crate::proto;
+use crate::histogram::LatencyHistogram;
+use crate::recent::RecentExecutions;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Highest job priority; larger numbers are dequeued first
+pub const MAX_PRIORITY: u8 = 9;
+
+/// Priority of jobs that don't ask for one
+pub const DEFAULT_PRIORITY: u8 = 5;
 
 /// Execution request from the TypeScript API
-#[derive(Debug, Deserialize, Clone)]
+///
+/// Every field serializes, so a job's request is stored and queued as it was submitted.
+/// A field that must not leave the engine would need `#[serde(skip_serializing)]`.
+/// Unknown fields are ignored unless [`RequestValidator::reject_unknown_fields`](crate::validation::RequestValidator::reject_unknown_fields) is set:
+///
+/// ```
+/// use labforcode_engine::types::ExecutionRequest;
+/// use labforcode_engine::validation::unknown_fields;
+///
+/// let body = serde_json::json!({ "language": "cpp", "source_code": "int main() {}", "memroy_limit": 1024 });
+/// let request: ExecutionRequest = serde_json::from_value(body.clone()).unwrap();
+/// assert_eq!(unknown_fields(&body)[0].field, "memroy_limit");
+/// ```
+///
+/// Every multi-word field also accepts its camelCase spelling, for JavaScript clients:
+///
+/// ```
+/// use labforcode_engine::types::ExecutionRequest;
+///
+/// let body = serde_json::json!({ "language": "python", "sourceCode": "print(1)", "cpuTimeLimit": 2.0 });
+/// let request: ExecutionRequest = serde_json::from_value(body).unwrap();
+/// assert_eq!(request.cpu_time_limit, Some(2.0));
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ToSchema)]
 pub struct ExecutionRequest {
+    #[serde(default)]
+    /// Assigned by the engine when omitted
     pub id: String,
+    /// Language key, e.g. "python"
     pub language: String,
+    #[serde(alias = "languageId")]
+    /// Judge0 language id, used when `language` is empty
     pub language_id: Option<u32>,
+    /// Program text; may be left out when `source_url` is given
+    #[serde(default, alias = "sourceCode")]
     pub source_code: String,
+    /// Standard input
     pub stdin: Option<String>,
+    #[serde(default, alias = "stdinBase64")]
+    /// Standard input as base64, for binary data; the program reads the decoded bytes. Conflicts with stdin
+    pub stdin_base64: Option<String>,
+    #[serde(alias = "expectedOutput")]
+    /// Compared with stdout by the Judge0 compatibility API
+    pub expected_output: Option<String>,
+    #[serde(alias = "compilerOptions")]
+    /// Extra flags for the compiler
     pub compiler_options: Option<String>,
+    #[serde(alias = "commandLineArguments")]
+    /// Arguments passed to the program
     pub command_line_arguments: Option<String>,
+    #[serde(alias = "sourceFileName")]
+    /// Overrides the language's default entry file
+    pub source_file_name: Option<String>,
+    /// Environment for the run phase only
+    pub env: Option<HashMap<String, String>>,
+    #[serde(alias = "artifactPatterns")]
+    /// Globs of output files to return
+    pub artifact_patterns: Option<Vec<String>>,
+    #[serde(alias = "preserveWorkdir")]
+    /// Keep the working directory for inspection (admin only)
+    pub preserve_workdir: Option<bool>,
+    #[serde(alias = "listWorkdir")]
+    /// On a runtime or compilation error, list the working directory's files in `workdir_listing`
+    pub list_workdir: Option<bool>,
+    /// 0-9, capped per API key
+    pub priority: Option<u8>,
+    #[serde(alias = "maxRetries")]
+    /// Retries after internal (non-user) errors, default 0
+    pub max_retries: Option<u32>,
+    #[serde(alias = "runAt")]
+    /// Hold the job until this time
+    pub run_at: Option<DateTime<Utc>>,
+    #[serde(alias = "base64Encoded")]
+    /// Stdin stays encoded until written; outputs are returned encoded
+    pub base64_encoded: Option<bool>,
+    /// Reuse an identical queued, running or recently finished job of the same client
+    pub dedupe: Option<bool>,
+    /// Server-defined profile (GET /profiles) filling in the limits and options left out here
+    pub profile: Option<String>,
+    /// Caller's labels, such as submission or user ids; echoed in statuses, results and callbacks, never interpreted
+    pub metadata: Option<HashMap<String, String>>,
     
     // Resource limits
+    #[serde(alias = "cpuTimeLimit")]
+    /// Seconds of CPU time
     pub cpu_time_limit: Option<f64>,
+    #[serde(alias = "cpuExtraTime")]
+    /// Seconds of CPU time allowed beyond the limit before the program is killed
     pub cpu_extra_time: Option<f64>,
+    #[serde(alias = "memoryLimit")]
+    /// Bytes (the Judge0 API converts its kilobytes)
     pub memory_limit: Option<u64>,
+    #[serde(alias = "memoryLimitKb")]
+    /// Same limit in kilobytes, folded into memory_limit
+    pub memory_limit_kb: Option<u64>,
+    #[serde(alias = "wallTimeLimit")]
+    /// Seconds of wall-clock time
     pub wall_time_limit: Option<f64>,
+    #[serde(alias = "stackLimit")]
+    /// Bytes
     pub stack_limit: Option<u64>,
+    #[serde(alias = "stackLimitKb")]
+    /// Same limit in kilobytes, folded into stack_limit
+    pub stack_limit_kb: Option<u64>,
+    #[serde(alias = "maxProcessesAndOrThreads")]
+    /// Processes and threads the program may create
     pub max_processes_and_or_threads: Option<u32>,
+    #[serde(alias = "enablePerProcessAndThreadTimeLimit")]
+    /// Apply the CPU limit to each process instead of the whole program
     pub enable_per_process_and_thread_time_limit: Option<bool>,
+    #[serde(alias = "enablePerProcessAndThreadMemoryLimit")]
+    /// Apply the memory limit to each process instead of the whole program
     pub enable_per_process_and_thread_memory_limit: Option<bool>,
+    #[serde(alias = "maxFileSize")]
+    /// Bytes
     pub max_file_size: Option<u64>,
+    #[serde(alias = "maxFileSizeKb")]
+    /// Same limit in kilobytes, folded into max_file_size
+    pub max_file_size_kb: Option<u64>,
+    #[serde(alias = "workdirQuota")]
+    /// Bytes
+    pub workdir_quota: Option<u64>,
+    #[serde(alias = "cpuAffinity")]
+    /// Core ids the program is pinned to, or "auto" for a free core of the reserved pool
+    pub cpu_affinity: Option<CpuAffinity>,
+    /// Scheduling niceness, 0 (normal) to 19 (lowest)
+    pub nice: Option<i32>,
     
     // Execution options
+    #[serde(alias = "redirectStderrToStdout")]
+    /// Merge stderr into stdout
     pub redirect_stderr_to_stdout: Option<bool>,
+    #[serde(alias = "enableNetwork")]
+    /// Allow network access
     pub enable_network: Option<bool>,
+    #[serde(alias = "numberOfRuns")]
+    /// Run the program this many times
     pub number_of_runs: Option<u32>,
+    #[serde(alias = "freshWorkdirPerRun")]
+    /// Give each run its own copy of the built working directory (default true); false lets runs see each other's files
+    pub fresh_workdir_per_run: Option<bool>,
+    /// "strong" runs the job under the docker backend's gVisor runtime
+    pub isolation: Option<IsolationLevel>,
+    #[serde(alias = "outputFilter")]
+    /// Escape sequences or control characters removed from text outputs
+    pub output_filter: Option<OutputFilter>,
+    #[serde(alias = "normalizeNewlines")]
+    /// Turn CRLF into LF in text outputs
+    pub normalize_newlines: Option<bool>,
     
     // Callback and files
+    #[serde(alias = "callbackUrl")]
+    /// URL the result is POSTed to when the job finishes
     pub callback_url: Option<String>,
-    pub additional_files: Option<String>, // Base64 encoded ZIP
+    #[serde(alias = "idempotencyKey")]
+    /// Alternative to the Idempotency-Key header
+    pub idempotency_key: Option<String>,
+    #[serde(alias = "additionalFiles")]
+    /// Base64 encoded ZIP
+    pub additional_files: Option<String>,
+    #[serde(alias = "sourceUrl")]
+    /// Downloaded into `source_code` before the job runs, instead of sending it inline
+    pub source_url: Option<String>,
+    #[serde(alias = "additionalFilesUrl")]
+    /// ZIP downloaded like `additional_files` before the job runs
+    pub additional_files_url: Option<String>,
+    
+    // Interactive problems
+    /// Judge program run alongside this one, each one's stdout feeding the other's stdin
+    pub interactor: Option<Interactor>,
+    
+    // Test groups
+    #[serde(alias = "testCases")]
+    /// Cases run against a single build of the program, each in a fresh copy of the working directory
+    pub test_cases: Option<Vec<TestCase>>,
+}
+
+impl ExecutionRequest {
+    /// Bytes the program reads on stdin: `stdin_base64` decoded, else `stdin`, decoded for base64_encoded submissions
+    ///
+    /// ```
+    /// use labforcode_engine::types::ExecutionRequest;
+    ///
+    /// let binary = ExecutionRequest { stdin_base64: Some("AP8K".to_string()), ..Default::default() };
+    /// assert_eq!(binary.stdin_bytes(), Some(vec![0x00, 0xff, b'\n']));
+    /// let text = ExecutionRequest { stdin: Some("1 2".to_string()), ..Default::default() };
+    /// assert_eq!(text.stdin_bytes(), Some(b"1 2".to_vec()));
+    /// ```
+    pub fn stdin_bytes(&self) -> Option<Vec<u8>> {
+        use base64::{engine::general_purpose, Engine as _};
+        
+        match (&self.stdin_base64, &self.stdin) {
+            (Some(encoded), _) => Some(general_purpose::STANDARD.decode(encoded).unwrap_or_default()),
+            (None, Some(stdin)) => Some(decode_output(stdin, self.base64_encoded.unwrap_or(false))),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Fields of ExecutionRequest that POST /executions/:id/rerun may override
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct RerunOverrides {
+    /// Same encoding as the original submission; replaces the original stdin or stdin_base64
+    pub stdin: Option<String>,
+    #[serde(default, alias = "stdinBase64")]
+    /// Replaces the original stdin or stdin_base64 with binary input
+    pub stdin_base64: Option<String>,
+    #[serde(alias = "cpuTimeLimit")]
+    /// Replaces the original cpu_time_limit
+    pub cpu_time_limit: Option<f64>,
+    #[serde(alias = "cpuExtraTime")]
+    /// Replaces the original cpu_extra_time
+    pub cpu_extra_time: Option<f64>,
+    #[serde(alias = "memoryLimit")]
+    /// Replaces the original memory_limit
+    pub memory_limit: Option<u64>,
+    #[serde(alias = "memoryLimitKb")]
+    /// Replaces the original memory_limit in kilobytes
+    pub memory_limit_kb: Option<u64>,
+    #[serde(alias = "wallTimeLimit")]
+    /// Replaces the original wall_time_limit
+    pub wall_time_limit: Option<f64>,
+    #[serde(alias = "stackLimit")]
+    /// Replaces the original stack_limit
+    pub stack_limit: Option<u64>,
+    #[serde(alias = "stackLimitKb")]
+    /// Replaces the original stack_limit in kilobytes
+    pub stack_limit_kb: Option<u64>,
+    #[serde(alias = "maxProcessesAndOrThreads")]
+    /// Replaces the original max_processes_and_or_threads
+    pub max_processes_and_or_threads: Option<u32>,
+    #[serde(alias = "maxFileSize")]
+    /// Replaces the original max_file_size
+    pub max_file_size: Option<u64>,
+    #[serde(alias = "maxFileSizeKb")]
+    /// Replaces the original max_file_size in kilobytes
+    pub max_file_size_kb: Option<u64>,
+    #[serde(alias = "workdirQuota")]
+    /// Replaces the original workdir_quota
+    pub workdir_quota: Option<u64>,
+    #[serde(alias = "numberOfRuns")]
+    /// Replaces the original number_of_runs
+    pub number_of_runs: Option<u32>,
+}
+
+impl RerunOverrides {
+    /// Copy every given field onto the request
+    pub fn apply(self, request: &mut ExecutionRequest) {
+        fn set<T>(target: &mut Option<T>, value: Option<T>) {
+            if value.is_some() {
+                *target = value;
+            }
+        }
+        
+        // A kilobyte override replaces the original byte value instead of conflicting with it
+        fn set_size(bytes: &mut Option<u64>, kilobytes: &mut Option<u64>, new_bytes: Option<u64>, new_kilobytes: Option<u64>) {
+            if new_kilobytes.is_some() {
+                *bytes = None;
+            }
+            set(bytes, new_bytes);
+            set(kilobytes, new_kilobytes);
+        }
+        
+        if self.stdin.is_some() || self.stdin_base64.is_some() {
+            request.stdin = self.stdin;
+            request.stdin_base64 = self.stdin_base64;
+        }
+        set(&mut request.cpu_time_limit, self.cpu_time_limit);
+        set(&mut request.cpu_extra_time, self.cpu_extra_time);
+        set_size(&mut request.memory_limit, &mut request.memory_limit_kb, self.memory_limit, self.memory_limit_kb);
+        set(&mut request.wall_time_limit, self.wall_time_limit);
+        set_size(&mut request.stack_limit, &mut request.stack_limit_kb, self.stack_limit, self.stack_limit_kb);
+        set(&mut request.max_processes_and_or_threads, self.max_processes_and_or_threads);
+        set_size(&mut request.max_file_size, &mut request.max_file_size_kb, self.max_file_size, self.max_file_size_kb);
+        set(&mut request.workdir_quota, self.workdir_quota);
+        set(&mut request.number_of_runs, self.number_of_runs);
+    }
+}
+
+/// Where a submission came from, recorded on its job
+#[derive(Debug, Clone, Default)]
+pub struct SubmissionContext {
+    /// X-Request-Id of the submitting HTTP request
+    pub request_id: Option<String>,
+    /// Rate limiter key of the submitter
+    pub client: Option<String>,
+    /// Execution this one re-runs
+    pub parent_id: Option<String>,
 }
 
 /// Response when submitting execution
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone, ToSchema)]
 pub struct ExecutionResponse {
+    /// Execution id to poll
     pub id: String,
+    /// Initial state, usually "queued"
     pub status: String,
+    /// Human readable summary
     pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Execution this one re-runs
+    pub parent_id: Option<String>,
+}
+
+/// JSON error envelope returned by every failing endpoint
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// What went wrong
+    pub error: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    /// Every field that failed validation
+    pub fields: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    /// Extra context, such as quota counters
+    pub details: Option<serde_json::Value>,
+}
+
+/// A single request field that failed validation
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldError {
+    /// Request field name
+    pub field: String,
+    /// Rule the value broke
+    pub constraint: String,
+}
+
+impl FieldError {
+    /// Violation of `constraint` by `field`
+    pub fn new(field: &str, constraint: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            constraint: constraint.into(),
+        }
+    }
+}
+
+/// Per-client admission counters shown to operators
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ClientStats {
+    /// Rate limiter key (hashed API key or IP)
+    pub client: String,
+    /// Requests admitted
+    pub requests: u64,
+    /// Requests refused for exceeding the rate
+    pub rate_limited: u64,
+    /// Requests refused for too many live jobs
+    pub concurrency_limited: u64,
+    /// Jobs currently queued or running
+    pub tracked_jobs: usize,
+    /// Seconds since the last request
+    pub idle_seconds: u64,
+}
+
+/// Resources a client consumed in one quota period
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct UsageCounters {
+    /// Executions submitted
+    pub submissions: u64,
+    /// CPU seconds used
+    pub cpu_seconds: f64,
+    /// Peak memory in megabytes times run time
+    pub memory_mb_seconds: f64,
+}
+
+/// Caps for one quota period; a missing cap is unlimited
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct QuotaLimits {
+    /// Executions per period
+    pub submissions: Option<u64>,
+    /// CPU seconds per period
+    pub cpu_seconds: Option<f64>,
+    /// Memory megabyte-seconds per period
+    pub memory_mb_seconds: Option<f64>,
+}
+
+/// Daily and monthly caps for a client, by UTC calendar day and month
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct Quota {
+    #[serde(default)]
+    /// Caps per UTC day
+    pub daily: QuotaLimits,
+    #[serde(default)]
+    /// Caps per UTC month
+    pub monthly: QuotaLimits,
+}
+
+/// Usage and what is left of the quota for one period
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PeriodUsage {
+    /// "2026-10-16" or "2026-10"
+    pub period: String,
+    /// Consumed so far
+    pub used: UsageCounters,
+    /// Caps for the period
+    pub limit: QuotaLimits,
+    /// Caps minus usage; missing when unlimited
+    pub remaining: QuotaLimits,
+}
+
+/// Quota accounting for one client
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UsageReport {
+    /// Client the report is for
+    pub client: String,
+    /// Today
+    pub daily: PeriodUsage,
+    /// This month
+    pub monthly: PeriodUsage,
 }
 
 /// Current execution status
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ExecutionStatus {
+    /// Execution id
     pub id: String,
+    #[serde(flatten, with = "status_with_code")]
+    #[schema(value_type = StatusWithCode)]
+    /// Current state
     pub status: ExecutionState,
+    /// When the job was submitted
     pub created_at: DateTime<Utc>,
+    /// When a worker started the job
     pub started_at: Option<DateTime<Utc>>,
+    /// When the job reached a terminal state
     pub finished_at: Option<DateTime<Utc>>,
-    pub progress: Option<String>,
+    /// When a scheduled job becomes eligible to run
+    pub run_at: Option<DateTime<Utc>>,
+    /// Execution this one re-runs
+    pub parent_id: Option<String>,
+    /// While a worker has the job
+    pub progress: Option<ExecutionProgress>,
+    /// Effective priority, 0-9
+    pub priority: u8,
+    /// 0 until a worker first picks the job up
+    pub attempt: u32,
+    /// Jobs ahead of this one at the same priority, while queued
+    pub band_position: Option<usize>,
+    /// Jobs ahead of this one overall, while queued
+    pub queue_position: Option<usize>,
+    /// Rough time until a worker picks the job up, while queued
+    pub estimated_wait_seconds: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The request's metadata, as submitted
+    pub metadata: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Instance and worker that took the job, once one has
+    pub runner: Option<Runner>,
+}
+
+/// Engine instance and worker that ran a job.
+///
+/// ```
+/// use labforcode_engine::executor::ExecutionBackend;
+/// use labforcode_engine::types::Runner;
+///
+/// let runner = Runner::new("engine-eu-1", 3, ExecutionBackend::Docker);
+/// assert_eq!(runner.worker_id, "engine-eu-1-3");
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Runner {
+    /// Instance name and worker slot, e.g. "engine-eu-1-3"
+    pub worker_id: String,
+    /// INSTANCE_NAME, or the host name
+    pub instance: String,
+    /// Worker slot on the instance, from 1
+    pub worker: usize,
+    /// Version of the engine that ran the job
+    pub engine_version: String,
+    /// Sandbox the program ran in; docker jobs of languages without an image run as processes
+    pub backend: ExecutionBackend,
+}
+
+impl Runner {
+    /// Worker `worker` of `instance`, running this build of the engine
+    pub fn new(instance: &str, worker: usize, backend: ExecutionBackend) -> Self {
+        Self {
+            worker_id: format!("{}-{}", instance, worker),
+            instance: instance.to_string(),
+            worker,
+            engine_version: env!("CARGO_PKG_VERSION").to_string(),
+            backend,
+        }
+    }
 }
 
 /// Execution result with output
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ExecutionResult {
+    /// Execution id
     pub id: String,
+    #[serde(flatten, with = "status_with_code")]
+    #[schema(value_type = StatusWithCode)]
+    /// Verdict
     pub status: ExecutionState,
+    /// Standard output of the last run
     pub stdout: Option<String>,
+    /// Standard error of the last run
     pub stderr: Option<String>,
+    /// Compiler output
     pub compile_output: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// Errors and warnings found in compile_output, best effort
+    pub diagnostics: Vec<Diagnostic>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Size of a stdout too large to embed, which is then null here
+    pub stdout_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Where to download that stdout
+    pub stdout_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Size of a stderr too large to embed, which is then null here
+    pub stderr_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Where to download that stderr
+    pub stderr_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Whether stdout is text or needs base64_encoded to come back intact
+    pub stdout_encoding: Option<OutputEncoding>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Whether stderr is text or needs base64_encoded to come back intact
+    pub stderr_encoding: Option<OutputEncoding>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    /// stdout went over OUTPUT_MAX_BYTES; only its start was kept
+    pub stdout_truncated: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    /// stderr went over OUTPUT_MAX_BYTES; only its start was kept
+    pub stderr_truncated: bool,
+    /// Exit code of the last run
     pub exit_code: Option<i32>,
+    /// Signal that killed the program
     pub signal: Option<String>,
+    #[serde(default)]
+    /// How the program ended, such as "killed by SIGSEGV (segmentation fault)"
+    pub exit_description: Option<String>,
+    /// CPU seconds
     pub time: Option<f64>,
+    /// Peak memory in bytes
     pub memory: Option<u64>,
+    /// Resource usage of the run, summed over runs
+    pub metrics: Option<ExecutionMetrics>,
+    /// Files matched by artifact_patterns
+    pub artifacts: Option<Vec<Artifact>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Files of the working directory when the program or compiler failed, for `list_workdir` requests
+    pub workdir_listing: Option<Vec<WorkdirEntry>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// Compiler and program invocations in the order they ran, with their arguments as executed
+    pub executed_commands: Vec<ExecutedCommand>,
+    /// Why an internal error happened
+    pub failure_reason: Option<String>,
+    /// Compilation was skipped thanks to the compile cache
+    pub compile_cache_hit: bool,
+    #[serde(default)]
+    /// Zero-based runs that did not complete
+    pub failed_run_indices: Vec<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// Likely mistakes in the submission, such as limits in the wrong unit
+    pub warnings: Vec<String>,
+    #[serde(default)]
+    /// Includes automatic retries after internal errors
+    pub attempts: u32,
+    /// Limits actually enforced
+    pub limits: Option<ResourceLimits>,
+    /// Options actually applied
+    pub options: Option<ExecutionOptions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The request's metadata, as submitted
+    pub metadata: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Instance and worker that ran the job
+    pub runner: Option<Runner>,
+    /// When the first process of the result was spawned
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the job was submitted
     pub created_at: DateTime<Utc>,
+    /// When the job reached a terminal state
     pub finished_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    /// Stages the job went through, oldest first
+    pub timeline: Vec<TimelineEntry>,
+    #[serde(default)]
+    /// Milliseconds between being queued and a worker taking the job
+    pub queue_wait_ms: Option<f64>,
+    #[serde(default)]
+    /// Milliseconds on a worker spent outside the compiler and the program
+    pub overhead_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Where the time on the worker went: setup, compile, run and teardown
+    pub phases: Option<PhaseTimings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Bytes written into the working directory before anything ran: the source and extracted additional_files
+    pub setup_bytes: Option<u64>,
+    #[serde(default)]
+    /// How the interactor ended, for interactive runs
+    pub interactor: Option<InteractorResult>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// Outcome of each test case, in submission order, for test groups
+    pub test_cases: Vec<TestCaseResult>,
+    #[serde(default)]
+    /// Counts and first failing case, for test groups
+    pub test_summary: Option<TestGroupSummary>,
+}
+
+/// Top-level fields of a serialized ExecutionResult, the names `?fields=` can select
+/// Keep in step with ExecutionResult
+pub const RESULT_FIELDS: &[&str] = &[
+    "id", "status", "status_code", "stdout", "stderr", "compile_output", "diagnostics", "stdout_bytes", "stdout_url",
+    "stderr_bytes", "stderr_url", "stdout_encoding", "stderr_encoding", "stdout_truncated", "stderr_truncated", "exit_code", "signal", "exit_description", "time", "memory", "metrics", "artifacts", "workdir_listing", "executed_commands", "failure_reason", "compile_cache_hit",
+    "failed_run_indices", "warnings", "attempts", "limits", "options", "metadata", "runner", "started_at", "created_at", "finished_at",
+    "timeline", "queue_wait_ms", "overhead_ms", "phases", "setup_bytes", "interactor", "test_cases", "test_summary",
+];
+
+/// Fields that hold program output, left out by `include_output=false`
+const OUTPUT_FIELDS: &[&str] = &["stdout", "stderr", "compile_output"];
+
+/// Query parameters of GET /result that shape the response
+///
+/// `include_output` takes true/false, 1/0 or yes/no in any case; `fields` is a comma-separated
+/// list of [`RESULT_FIELDS`], in snake_case or camelCase:
+///
+/// ```
+/// use labforcode_engine::types::ResultQuery;
+/// use std::collections::HashMap;
+///
+/// let params = HashMap::from([("fields".to_string(), "status,exitCode".to_string())]);
+/// assert_eq!(ResultQuery::parse(&params).unwrap().fields.unwrap(), ["status", "exit_code"]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultQuery {
+    /// Serialize stdout, stderr and compile_output
+    pub include_output: bool,
+    /// Only these top-level fields, when given
+    pub fields: Option<Vec<String>>,
+}
+
+impl ResultQuery {
+    /// Read `include_output` and `fields`, listing every value that can't be used
+    pub fn parse(params: &HashMap<String, String>) -> Result<Self, Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let include_output = match params.get("include_output").map(|value| value.trim().to_ascii_lowercase()) {
+            None => true,
+            Some(value) => match value.as_str() {
+                "true" | "1" | "yes" => true,
+                "false" | "0" | "no" => false,
+                _ => {
+                    errors.push(FieldError::new("include_output", "must be true or false (or 1/0, yes/no)"));
+                    true
+                }
+            },
+        };
+        
+        let fields = params.get("fields").map(|list| {
+            let names: Vec<String> = list.split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(crate::validation::snake_case)
+                .collect();
+            if names.is_empty() {
+                errors.push(FieldError::new("fields", "must name at least one field"));
+            }
+            for name in names.iter().filter(|name| !RESULT_FIELDS.contains(&name.as_str())) {
+                errors.push(FieldError::new(
+                    "fields",
+                    format!("unknown field `{}`; valid fields: {}", name, RESULT_FIELDS.join(", ")),
+                ));
+            }
+            names
+        });
+        
+        if errors.is_empty() {
+            Ok(Self { include_output, fields })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// A stored result as served by GET /result, optionally without its output streams or projected to some fields
+#[derive(Debug, Clone)]
+pub struct ResultView {
+    /// The stored result
+    pub result: Arc<ExecutionResult>,
+    /// Serialize stdout, stderr and compile_output
+    pub include_output: bool,
+    /// Only these top-level fields, when given
+    pub fields: Option<Vec<String>>,
+}
+
+impl Serialize for ResultView {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Some(fields) = &self.fields {
+            // Outputs nobody asked for are never copied into the intermediate value
+            let full = ResultView {
+                result: self.result.clone(),
+                include_output: self.include_output && fields.iter().any(|field| OUTPUT_FIELDS.contains(&field.as_str())),
+                fields: None,
+            };
+            let mut value = serde_json::to_value(&full).map_err(serde::ser::Error::custom)?;
+            if let Some(object) = value.as_object_mut() {
+                object.retain(|key, _| fields.contains(key));
+            }
+            return value.serialize(serializer);
+        }
+        if self.include_output {
+            return self.result.serialize(serializer);
+        }
+        
+        let result = &*self.result;
+        ResultWithoutOutput {
+            id: &result.id,
+            status: result.status.clone(),
+            stdout: None,
+            stderr: None,
+            compile_output: None,
+            diagnostics: &result.diagnostics,
+            stdout_bytes: result.stdout_bytes,
+            stdout_url: result.stdout_url.as_deref(),
+            stderr_bytes: result.stderr_bytes,
+            stderr_url: result.stderr_url.as_deref(),
+            stdout_encoding: result.stdout_encoding,
+            stderr_encoding: result.stderr_encoding,
+            stdout_truncated: result.stdout_truncated,
+            stderr_truncated: result.stderr_truncated,
+            exit_code: result.exit_code,
+            signal: result.signal.as_deref(),
+            exit_description: result.exit_description.as_deref(),
+            time: result.time,
+            memory: result.memory,
+            metrics: result.metrics.as_ref(),
+            artifacts: result.artifacts.as_deref(),
+            workdir_listing: result.workdir_listing.as_deref(),
+            executed_commands: &result.executed_commands,
+            failure_reason: result.failure_reason.as_deref(),
+            compile_cache_hit: result.compile_cache_hit,
+            failed_run_indices: &result.failed_run_indices,
+            warnings: &result.warnings,
+            attempts: result.attempts,
+            limits: result.limits.as_ref(),
+            options: result.options.as_ref(),
+            metadata: result.metadata.as_ref(),
+            runner: result.runner.as_ref(),
+            started_at: result.started_at,
+            created_at: result.created_at,
+            finished_at: result.finished_at,
+            timeline: &result.timeline,
+            queue_wait_ms: result.queue_wait_ms,
+            overhead_ms: result.overhead_ms,
+            phases: result.phases.as_ref(),
+            setup_bytes: result.setup_bytes,
+            interactor: result.interactor.as_ref(),
+            test_cases: &result.test_cases,
+            test_summary: result.test_summary.as_ref(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Borrowed copy of ExecutionResult's fields with the outputs left null, so the large strings are never cloned
+/// Keep in step with ExecutionResult
+#[derive(Serialize)]
+struct ResultWithoutOutput<'a> {
+    id: &'a str,
+    #[serde(flatten, serialize_with = "status_with_code::serialize")]
+    status: ExecutionState,
+    stdout: Option<&'a str>,
+    stderr: Option<&'a str>,
+    compile_output: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[Diagnostic]>::is_empty")]
+    diagnostics: &'a [Diagnostic],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stdout_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stdout_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stderr_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stderr_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stdout_encoding: Option<OutputEncoding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stderr_encoding: Option<OutputEncoding>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stdout_truncated: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stderr_truncated: bool,
+    exit_code: Option<i32>,
+    signal: Option<&'a str>,
+    exit_description: Option<&'a str>,
+    time: Option<f64>,
+    memory: Option<u64>,
+    metrics: Option<&'a ExecutionMetrics>,
+    artifacts: Option<&'a [Artifact]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    workdir_listing: Option<&'a [WorkdirEntry]>,
+    #[serde(skip_serializing_if = "<[ExecutedCommand]>::is_empty")]
+    executed_commands: &'a [ExecutedCommand],
+    failure_reason: Option<&'a str>,
+    compile_cache_hit: bool,
+    failed_run_indices: &'a [u32],
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    warnings: &'a [String],
+    attempts: u32,
+    limits: Option<&'a ResourceLimits>,
+    options: Option<&'a ExecutionOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<&'a HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    runner: Option<&'a Runner>,
+    started_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+    timeline: &'a [TimelineEntry],
+    queue_wait_ms: Option<f64>,
+    overhead_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phases: Option<&'a PhaseTimings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    setup_bytes: Option<u64>,
+    interactor: Option<&'a InteractorResult>,
+    #[serde(skip_serializing_if = "<[TestCaseResult]>::is_empty")]
+    test_cases: &'a [TestCaseResult],
+    test_summary: Option<&'a TestGroupSummary>,
+}
+
+/// Judge program of an interactive problem; the request's stdin is its `input.txt`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct Interactor {
+    /// Language key, e.g. "cpp"
+    pub language: String,
+    #[serde(alias = "sourceCode")]
+    /// Program text, base64 encoded like source_code when `base64_encoded`
+    pub source_code: String,
+}
+
+/// How the interactor of an interactive run ended
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct InteractorResult {
+    /// 0 accepts the submission, anything else makes it a wrong answer
+    pub exit_code: i32,
+    /// Signal that killed the interactor
+    pub signal: Option<String>,
+    /// How the interactor ended, as in the result's own `exit_description`
+    pub exit_description: String,
+    /// Standard error, for debugging the exchange
+    pub stderr: String,
+    /// CPU seconds
+    pub time: f64,
+}
+
+/// One input of a test group, with the output it should produce
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ToSchema)]
+pub struct TestCase {
+    /// Standard input, base64 encoded when `base64_encoded`
+    pub stdin: Option<String>,
+    #[serde(alias = "expectedOutput")]
+    /// Compared with stdout ignoring trailing whitespace; a mismatch is a wrong answer
+    pub expected_output: Option<String>,
+    #[serde(alias = "cpuTimeLimit")]
+    /// Seconds of CPU time, instead of the request's
+    pub cpu_time_limit: Option<f64>,
+    #[serde(alias = "wallTimeLimit")]
+    /// Seconds of wall-clock time, instead of the request's
+    pub wall_time_limit: Option<f64>,
+    #[serde(alias = "memoryLimit")]
+    /// Bytes, instead of the request's
+    pub memory_limit: Option<u64>,
+}
+
+/// How one test case of a test group went
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct TestCaseResult {
+    /// Verdict of this case; skipped cases are not listed
+    pub status: ExecutionState,
+    /// Standard output
+    pub stdout: Option<String>,
+    /// Standard error
+    pub stderr: Option<String>,
+    /// Exit code
+    pub exit_code: Option<i32>,
+    /// Signal that killed the program
+    pub signal: Option<String>,
+    /// How the program ended
+    pub exit_description: Option<String>,
+    /// CPU seconds
+    pub time: Option<f64>,
+    /// Peak memory in bytes
+    pub memory: Option<u64>,
+    /// Which limit stopped the program, or why it crashed
+    pub failure_reason: Option<String>,
+}
+
+/// Overall outcome of a test group
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct TestGroupSummary {
+    /// Cases that completed with the expected output
+    pub passed: u32,
+    /// Cases with any other verdict
+    pub failed: u32,
+    /// Cases never run because the job was cancelled
+    pub skipped: u32,
+    /// Zero-based index of the first failing case
+    pub first_failure: Option<u32>,
+}
+
+/// A compiler error, warning or note pointing at a line of the sources
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct Diagnostic {
+    /// Path relative to the working directory, e.g. "main.cpp"
+    pub file: String,
+    /// 1-based
+    pub line: u32,
+    /// 1-based, when the compiler reports one
+    pub column: Option<u32>,
+    /// How serious the compiler considers it
+    pub severity: DiagnosticSeverity,
+    /// First line of the compiler's message, not base64 encoded
+    pub message: String,
+}
+
+/// Kind of a compiler diagnostic; "fatal error" counts as an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    /// The build fails
+    Error,
+    /// The build goes on
+    Warning,
+    /// Context for the diagnostic before it
+    Note,
+}
+
+/// Output file collected from the working directory after a run
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Artifact {
+    /// Path relative to the working directory
+    pub name: String,
+    /// Bytes on disk
+    pub size: u64,
+    /// File content, absent when over the artifact size budget
+    pub content_base64: Option<String>,
+    /// Content was cut to fit the budget
+    pub truncated: bool,
+}
+
+/// Phase of a job a worker is running
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionPhase {
+    /// Building the program
+    Compiling,
+    /// Running the program
+    Running,
+}
+
+/// What a running job is doing right now; run counters only appear for multi-run jobs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ExecutionProgress {
+    /// Current phase
+    pub phase: ExecutionPhase,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// One-based run being executed
+    pub run: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Runs requested
+    pub total_runs: Option<u32>,
+}
+
+impl ExecutionProgress {
+    /// Progress of a job that is compiling
+    pub fn compiling() -> Self {
+        Self { phase: ExecutionPhase::Compiling, run: None, total_runs: None }
+    }
+    
+    /// Progress of a job executing run `run` of `total_runs`
+    pub fn running(run: u32, total_runs: u32) -> Self {
+        let multi_run = total_runs > 1;
+        Self {
+            phase: ExecutionPhase::Running,
+            run: multi_run.then_some(run),
+            total_runs: multi_run.then_some(total_runs),
+        }
+    }
+}
+
+/// Point in a job's life that its timeline records
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineStage {
+    /// Entered the queue, or became due if it was scheduled
+    Enqueued,
+    /// Taken by a worker
+    Dequeued,
+    /// Working directory, container or box ready for a run
+    SandboxReady,
+    /// Compiler started
+    CompileStarted,
+    /// Compiler exited
+    CompileFinished,
+    /// Program started
+    RunStarted,
+    /// Program exited
+    RunFinished,
+    /// Result stored
+    Finalized,
+}
+
+/// When a job reached a stage
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct TimelineEntry {
+    /// Stage reached
+    pub stage: TimelineStage,
+    /// When it was reached
+    pub at: DateTime<Utc>,
+}
+
+/// Milliseconds the latest attempt spent in each phase on its worker; they add up to its time there.
+///
+/// Setup is everything before the compiler or program starts, such as the working directory, the
+/// sources, additional_files and the container or box; with several runs, the time between them counts
+/// too. Teardown runs from the last exit until the result is stored. Setup and teardown together are
+/// the attempt's `overhead_ms`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct PhaseTimings {
+    /// Until the compiler or program started
+    pub setup_ms: f64,
+    /// Compiler, zero for interpreted languages and compile cache hits
+    pub compile_ms: f64,
+    /// Program, summed over runs and test cases
+    pub run_ms: f64,
+    /// From the last exit until the result was stored: artifacts, cleanup
+    pub teardown_ms: f64,
+}
+
+/// Stages a job went through, oldest first; retries and multi-run jobs repeat stages
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Timeline(Vec<TimelineEntry>);
+
+impl Timeline {
+    /// Record that the job reached `stage` now
+    pub fn stamp(&mut self, stage: TimelineStage) {
+        self.stamp_at(stage, Utc::now());
+    }
+    
+    /// Record that the job reached `stage` at `at`
+    pub fn stamp_at(&mut self, stage: TimelineStage, at: DateTime<Utc>) {
+        self.0.push(TimelineEntry { stage, at });
+    }
+    
+    /// Recorded stages, oldest first
+    pub fn entries(&self) -> &[TimelineEntry] {
+        &self.0
+    }
+    
+    /// Milliseconds the latest attempt waited between being queued and a worker taking it
+    pub fn queue_wait_ms(&self) -> Option<f64> {
+        let dequeued = self.0.iter().rposition(|entry| entry.stage == TimelineStage::Dequeued)?;
+        let enqueued = self.0[..dequeued].iter().rev().find(|entry| entry.stage == TimelineStage::Enqueued)?;
+        Some(millis_between(enqueued.at, self.0[dequeued].at))
+    }
+    
+    /// Milliseconds the latest attempt spent on a worker outside the compiler and the program
+    pub fn overhead_ms(&self) -> Option<f64> {
+        let dequeued = self.0.iter().rposition(|entry| entry.stage == TimelineStage::Dequeued)?;
+        let attempt = &self.0[dequeued..];
+        let finalized = attempt.iter().rev().find(|entry| entry.stage == TimelineStage::Finalized)?;
+        
+        let mut busy = 0.0;
+        let mut started = None;
+        for entry in attempt {
+            match entry.stage {
+                TimelineStage::CompileStarted | TimelineStage::RunStarted => started = Some(entry.at),
+                TimelineStage::CompileFinished | TimelineStage::RunFinished => {
+                    if let Some(started) = started.take() {
+                        busy += millis_between(started, entry.at);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some((millis_between(attempt[0].at, finalized.at) - busy).max(0.0))
+    }
+    
+    /// Where the latest attempt's time on a worker went, once it is finalized
+    pub fn phases(&self) -> Option<PhaseTimings> {
+        let dequeued = self.0.iter().rposition(|entry| entry.stage == TimelineStage::Dequeued)?;
+        let attempt = &self.0[dequeued..];
+        let finalized = attempt.iter().rposition(|entry| entry.stage == TimelineStage::Finalized)?;
+        let attempt = &attempt[..=finalized];
+        let last_exit = attempt.iter()
+            .rposition(|entry| matches!(entry.stage, TimelineStage::CompileFinished | TimelineStage::RunFinished));
+        
+        let mut phases = PhaseTimings::default();
+        for (index, pair) in attempt.windows(2).enumerate() {
+            let phase = match (pair[0].stage, pair[1].stage) {
+                (TimelineStage::CompileStarted, TimelineStage::CompileFinished) => &mut phases.compile_ms,
+                (TimelineStage::RunStarted, TimelineStage::RunFinished) => &mut phases.run_ms,
+                _ if last_exit.is_some_and(|exit| index >= exit) => &mut phases.teardown_ms,
+                _ => &mut phases.setup_ms,
+            };
+            *phase += millis_between(pair[0].at, pair[1].at);
+        }
+        Some(phases)
+    }
+}
+
+/// Milliseconds from `start` to `end`, never negative
+fn millis_between(start: DateTime<Utc>, end: DateTime<Utc>) -> f64 {
+    (end - start).num_microseconds().unwrap_or(0).max(0) as f64 / 1000.0
+}
+
+/// Process output as returned in a result: base64 of the raw bytes, or lossy UTF-8 text
+pub fn encode_output(bytes: &[u8], base64_encoded: bool) -> String {
+    use base64::{engine::general_purpose, Engine as _};
+    
+    if base64_encoded {
+        general_purpose::STANDARD.encode(bytes)
+    } else {
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+}
+
+/// Raw bytes of an output produced by `encode_output`
+pub fn decode_output(text: &str, base64_encoded: bool) -> Vec<u8> {
+    use base64::{engine::general_purpose, Engine as _};
+    
+    if base64_encoded {
+        general_purpose::STANDARD.decode(text).unwrap_or_default()
+    } else {
+        text.as_bytes().to_vec()
+    }
+}
+
+/// A state as statuses and results serialize it: its name plus its numeric code
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatusWithCode {
+    /// State name
+    pub status: ExecutionState,
+    /// Stable numeric code of the state; codes are never renumbered or reused
+    pub status_code: u32,
+}
+
+/// Writes a state as `status` and `status_code`; reads either, so results stored before the code existed still load
+mod status_with_code {
+    use super::{ExecutionState, StatusWithCode};
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    
+    pub fn serialize<S: Serializer>(status: &ExecutionState, serializer: S) -> Result<S::Ok, S::Error> {
+        StatusWithCode { status: status.clone(), status_code: status.code() }.serialize(serializer)
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ExecutionState, D::Error> {
+        #[derive(Deserialize)]
+        struct Either {
+            status: Option<ExecutionState>,
+            status_code: Option<u32>,
+        }
+        
+        match Either::deserialize(deserializer)? {
+            Either { status: Some(status), .. } => Ok(status),
+            Either { status_code: Some(code), .. } => {
+                ExecutionState::try_from(code).map_err(|err| D::Error::custom(err.constraint))
+            }
+            _ => Err(D::Error::missing_field("status")),
+        }
+    }
 }
 
 /// Execution states
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
 pub enum ExecutionState {
+    #[serde(rename = "scheduled")]
+    /// Waiting for its run_at time
+    Scheduled,
     #[serde(rename = "queued")]
+    /// Waiting for a worker
     Queued,
     #[serde(rename = "processing")]
+    /// Taken by a worker, not yet started
     Processing,
     #[serde(rename = "running")]
+    /// Program is running
     Running,
     #[serde(rename = "completed")]
+    /// Finished normally
     Completed,
     #[serde(rename = "compilation_error")]
+    /// Did not compile
     CompilationError,
     #[serde(rename = "runtime_error")]
+    /// Exited nonzero or was killed by a signal
     RuntimeError,
+    #[serde(rename = "wrong_answer")]
+    /// Rejected by the interactor of an interactive run
+    WrongAnswer,
     #[serde(rename = "time_limit_exceeded")]
+    /// Exceeded its CPU or wall-clock limit
     TimeLimitExceeded,
     #[serde(rename = "memory_limit_exceeded")]
+    /// Exceeded its memory limit
     MemoryLimitExceeded,
+    #[serde(rename = "output_limit_exceeded")]
+    /// Wrote more than its file size limit
+    OutputLimitExceeded,
     #[serde(rename = "cancelled")]
+    /// Cancelled before finishing
     Cancelled,
     #[serde(rename = "internal_error")]
+    /// The engine failed, not the program
     InternalError,
 }
 
+impl ExecutionState {
+    /// Whether the job has finished and will not change state again
+    pub fn is_terminal(&self) -> bool {
+        !matches!(
+            self,
+            ExecutionState::Scheduled | ExecutionState::Queued | ExecutionState::Processing | ExecutionState::Running
+        )
+    }
+    
+    /// How bad a finished run was, used to pick the overall status of a multi-run job:
+    /// internal error > cancelled > memory limit > time limit > output limit > runtime error >
+    /// wrong answer > compilation error > completed
+    pub fn severity(&self) -> u8 {
+        match self {
+            ExecutionState::InternalError => 8,
+            ExecutionState::Cancelled => 7,
+            ExecutionState::MemoryLimitExceeded => 6,
+            ExecutionState::TimeLimitExceeded => 5,
+            ExecutionState::OutputLimitExceeded => 4,
+            ExecutionState::RuntimeError => 3,
+            ExecutionState::WrongAnswer => 2,
+            ExecutionState::CompilationError => 1,
+            // Listed one by one, so a new state has to be ranked here before it compiles
+            ExecutionState::Completed
+            | ExecutionState::Scheduled
+            | ExecutionState::Queued
+            | ExecutionState::Processing
+            | ExecutionState::Running => 0,
+        }
+    }
+    
+    /// Stable numeric code of the state, served as `status_code`
+    /// These are the gRPC enum's values, which are never renumbered or reused
+    pub fn code(&self) -> u32 {
+        proto::ExecutionState::from(self.clone()) as u32
+    }
+    
+    /// Wire name of the state, as serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionState::Scheduled => "scheduled",
+            ExecutionState::Queued => "queued",
+            ExecutionState::Processing => "processing",
+            ExecutionState::Running => "running",
+            ExecutionState::Completed => "completed",
+            ExecutionState::CompilationError => "compilation_error",
+            ExecutionState::RuntimeError => "runtime_error",
+            ExecutionState::WrongAnswer => "wrong_answer",
+            ExecutionState::TimeLimitExceeded => "time_limit_exceeded",
+            ExecutionState::MemoryLimitExceeded => "memory_limit_exceeded",
+            ExecutionState::OutputLimitExceeded => "output_limit_exceeded",
+            ExecutionState::Cancelled => "cancelled",
+            ExecutionState::InternalError => "internal_error",
+        }
+    }
+}
+
+/// File left in a preserved working directory, or in that of a failed run.
+///
+/// A `list_workdir` request that fails at runtime shows what its program could have opened.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct WorkdirEntry {
+    /// Path relative to the working directory
+    pub path: String,
+    /// Bytes
+    pub size: u64,
+}
+
+/// Compiler or program run for a job, as it was actually invoked.
+///
+/// `argv` has the request's `compiler_options` and `command_line_arguments` in place. The
+/// sandbox wrapper (docker exec, isolate) and the environment are left out, so variable values
+/// never show up in a result.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct ExecutedCommand {
+    /// Step of the job the command belongs to
+    pub phase: CommandPhase,
+    /// Program and arguments
+    pub argv: Vec<String>,
+    /// Wall time from start to exit or kill
+    pub duration_ms: u64,
+    /// -1 when killed
+    pub exit_code: i32,
+}
+
+/// Step of a job that runs a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandPhase {
+    /// Building the submission
+    Compile,
+    /// The submission itself, once per run or test case
+    Run,
+    /// Building the interactor of an interactive run
+    InteractorCompile,
+    /// The interactor, alongside the submission
+    Interactor,
+}
+
 /// Language information
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct LanguageInfo {
+    /// Judge0 language id
     pub id: u32,
+    /// Name to send as `language`
+    pub key: String,
+    /// Other names accepted as `language`, such as "py" or "c++"
+    pub aliases: Vec<String>,
+    /// Display name
     pub name: String,
+    /// Toolchain version
     pub version: String,
+    /// Command that builds the program
     pub compile_cmd: Option<String>,
+    /// Command that runs the program
     pub run_cmd: String,
+    /// Limits of requests that do not set their own
+    pub default_limits: Option<ResourceLimits>,
+    /// Most a request may ask for
+    pub max_limits: Option<LimitCeilings>,
+    /// Largest source_code, in bytes
+    pub max_source_bytes: Option<usize>,
 }
 
 /// Health check response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct HealthResponse {
+    /// "healthy", "draining", "warming up", "runtime unavailable", "memory pressure" or "low disk space"
     pub status: String,
+    /// Engine name
     pub engine: String,
+    /// Crate version
     pub version: String,
+    /// Workers are not starting new jobs
+    pub paused: bool,
+    /// Docker runtimes the backend is configured with and whether they were available at startup
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub runtimes: BTreeMap<String, bool>,
+    /// Prefixes the engine API is served under, oldest first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub api_versions: Vec<String>,
+    /// Git commit the engine was built from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// When the engine was built
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub built_at: Option<DateTime<Utc>>,
+    /// Jobs that can run at once right now
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workers: Option<usize>,
+    /// Where waiting jobs are kept
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue: Option<QueueBackend>,
+    /// How jobs are isolated on this host
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxFeatures>,
+    /// Startup warm-up of each language, when enabled (WARMUP)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub warmup: BTreeMap<String, LanguageWarmup>,
+    /// Size and free space of the volume holding the working directories
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temp_disk: Option<DiskSpace>,
+    /// Time of the check
     pub timestamp: DateTime<Utc>,
 }
 
+/// Size and free space of a volume
+#[derive(Debug, Serialize, Clone, PartialEq, ToSchema)]
+pub struct DiskSpace {
+    /// Directory the figures were read for (TEMP_BASE)
+    pub path: String,
+    /// Size of the filesystem in bytes
+    pub total_bytes: u64,
+    /// Bytes available to the engine
+    pub free_bytes: u64,
+    /// Bytes not available, including any reserved for root
+    pub used_bytes: u64,
+}
+
+/// How a language's startup warm-up went
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct LanguageWarmup {
+    /// The image is present and the hello-world ran; false takes the language out of service
+    pub ready: bool,
+    /// Milliseconds spent pulling and building
+    pub duration_ms: u64,
+    /// Why the language was taken out of service
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Backend and isolation features, as found at startup
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct SandboxFeatures {
+    /// Where programs run (EXECUTION_BACKEND)
+    pub backend: ExecutionBackend,
+    /// Languages with an image run in containers under an available default runtime
+    pub docker: bool,
+    /// Jobs run in isolate boxes
+    pub isolate: bool,
+    /// Native jobs get a read-only root and a private /tmp
+    pub filesystem_isolation: bool,
+    /// The engine may create control groups under its own cgroup v2 group
+    pub cgroups_v2_writable: bool,
+    /// System calls are filtered with seccomp; this build has no filter
+    pub seccomp: bool,
+}
+
 /// Engine statistics
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone, ToSchema)]
 pub struct EngineStats {
+    /// Instance these figures are for, to tell replicas apart when aggregating
+    pub instance: String,
+    /// Executions submitted since start
     pub total_executions: u64,
+    /// Jobs on a worker
     pub active_executions: u64,
+    /// Jobs that can run at once right now, after any autoscaling
+    pub workers: usize,
+    /// Jobs waiting in the queue
     pub queued_executions: u64,
+    /// Jobs that reached a verdict
     pub completed_executions: u64,
+    /// Jobs that ended in an internal error
     pub failed_executions: u64,
+    /// Seconds per execution
     pub average_execution_time: f64,
+    /// Median seconds per execution
+    pub p50_execution_time: f64,
+    /// 95th percentile seconds per execution
+    pub p95_execution_time: f64,
+    /// 99th percentile seconds per execution
+    pub p99_execution_time: f64,
+    #[serde(skip)]
+    /// Samples behind the percentiles
+    pub execution_times: LatencyHistogram,
+    /// Seconds between submission and a worker picking the job up
+    pub average_queue_wait_time: f64,
+    #[serde(skip)]
+    /// Jobs behind average_queue_wait_time
+    pub queue_wait_samples: u64,
+    /// One-minute load average
     pub system_load: f64,
+    /// Memory in use on the host, in bytes
     pub memory_usage: u64,
+    /// Seconds since start
     pub uptime_seconds: u64,
+    /// Leaked temp directories removed
+    pub reclaimed_temp_dirs: u64,
+    /// Compilations served from the cache
+    pub compile_cache_hits: u64,
+    /// Compilations that missed the cache
+    pub compile_cache_misses: u64,
+    /// Hits over lookups
+    pub compile_cache_hit_rate: f64,
+    /// Milliseconds between submission and the first process, the compiler when there is one, being spawned
+    pub average_start_latency_ms: f64,
+    #[serde(skip)]
+    /// Jobs behind average_start_latency_ms
+    pub start_latency_samples: u64,
+    /// Milliseconds a job spends on a worker outside the compiler and the program
+    pub average_overhead_ms: f64,
+    #[serde(skip)]
+    /// Jobs behind average_overhead_ms
+    pub overhead_samples: u64,
+    /// Median milliseconds of overhead per job
+    pub overhead_ms_p50: f64,
+    /// 95th percentile milliseconds of overhead per job
+    pub overhead_ms_p95: f64,
+    #[serde(skip)]
+    /// Samples behind the overhead percentiles
+    pub overhead_times: LatencyHistogram,
+    /// Refused because the queue was full
+    pub rejected_executions: u64,
+    /// Results accepted by their callback_url receiver
+    pub callbacks_delivered: u64,
+    /// Callbacks that failed every attempt and were dead-lettered
+    pub callbacks_dead_lettered: u64,
+    /// Results copied to the archive bucket
+    pub results_archived: u64,
+    /// Archive uploads that failed; each is retried by the janitor
+    pub archive_failures: u64,
+    /// Results waiting for an archive upload retry
+    pub archive_pending: usize,
+    /// Archived jobs dropped from memory and the job store
+    pub evicted_executions: u64,
+    /// Workers are not starting new jobs
+    pub paused: bool,
+    /// Workers are deferring new jobs because available memory is below the floor
+    pub memory_pressure: bool,
+    /// Dequeues skipped under memory pressure while jobs were waiting
+    pub deferred_dequeues: u64,
+    /// Jobs fail fast because the volume under TEMP_BASE has less free space than the floor
+    pub low_disk_space: bool,
+    /// Size and free space of the volume under TEMP_BASE
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temp_disk: Option<DiskSpace>,
+    /// Throughput, failures and durations of the jobs that finished lately
+    pub windows: StatsWindows,
+    #[serde(skip)]
+    /// Jobs behind the windows and GET /stats/recent
+    pub recent: RecentExecutions,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    /// Counters per language
+    pub by_language: HashMap<String, LanguageStats>,
+}
+
+/// Rolling figures over the jobs that finished in the last minute, five minutes and hour
+#[derive(Debug, Serialize, Clone, Default, ToSchema)]
+pub struct StatsWindows {
+    /// Last 60 seconds
+    pub last_minute: StatsWindow,
+    /// Last 300 seconds
+    pub last_5_minutes: StatsWindow,
+    /// Last 3600 seconds
+    pub last_hour: StatsWindow,
+}
+
+/// Jobs that finished within one window
+#[derive(Debug, Serialize, Clone, Default, ToSchema)]
+pub struct StatsWindow {
+    /// Jobs that finished, whatever their verdict
+    pub executions: u64,
+    /// Jobs that ended in an internal error
+    pub failed: u64,
+    /// failed over executions, 0 without executions
+    pub failure_rate: f64,
+    /// Milliseconds on a worker per job
+    pub average_duration_ms: f64,
+    /// Jobs finished per minute, averaged over the window
+    pub per_minute: f64,
+}
+
+/// A job that finished lately, as listed by GET /stats/recent
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct RecentExecution {
+    /// When the job reached its verdict
+    pub finished_at: DateTime<Utc>,
+    /// Language key
+    pub language: String,
+    /// Verdict
+    pub status: ExecutionState,
+    /// Milliseconds on a worker
+    pub duration_ms: u64,
+}
+
+/// Execution counters for one language
+#[derive(Debug, Serialize, Clone, Default, ToSchema)]
+pub struct LanguageStats {
+    /// Executions submitted
+    pub submissions: u64,
+    /// Executions that reached a verdict
+    pub completed: u64,
+    /// Executions that ended in an internal error
+    pub failed: u64,
+    /// Seconds of reported run time
+    pub average_execution_time: f64,
+    /// Bytes
+    pub average_memory: f64,
+    /// Bytes of inline source_code per submission
+    pub average_source_bytes: f64,
+}
+
+/// A waiting job as listed by /admin/queue
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QueuedJobInfo {
+    /// Execution id
+    pub id: String,
+    /// Jobs that will be dequeued before this one
+    pub position: usize,
+    /// Language key
+    pub language: String,
+    /// Submitting client
+    pub client: Option<String>,
+    /// Effective priority, 0-9
+    pub priority: u8,
+    /// Moved ahead of every band by an operator
+    pub promoted: bool,
+    /// When the job entered the queue
+    pub enqueued_at: DateTime<Utc>,
+}
+
+/// A callback that failed every attempt, as listed by /admin/callbacks/failed
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DeadLetter {
+    /// Execution whose result was not delivered
+    pub job_id: String,
+    /// Where the result was posted
+    pub url: String,
+    /// Attempts made so far, manual retries included
+    pub attempts: u32,
+    /// HTTP status of the last answer; absent when the receiver could not be reached
+    pub last_status: Option<u16>,
+    /// Why the last attempt failed
+    pub last_error: String,
+    /// SHA-256 of the last body sent, to match against what the receiver logged
+    pub payload_sha256: String,
+    /// When the last attempt failed
+    pub failed_at: DateTime<Utc>,
+}
+
+/// One page of failed callbacks, newest first
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeadLetterPage {
+    /// Entries currently kept
+    pub total: usize,
+    /// Entries skipped before this page
+    pub offset: usize,
+    /// This page
+    pub items: Vec<DeadLetter>,
+}
+
+/// Runtime-adjustable capacity limits
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CapacitySettings {
+    /// Waiting jobs allowed before submissions are refused, 0 for unbounded
+    pub max_queue_depth: usize,
+    /// Jobs run at once
+    pub max_concurrent_executions: usize,
+    /// Whether the worker count follows the queue
+    pub autoscale: bool,
+    /// Fewest workers autoscaling keeps
+    pub min_workers: usize,
+    /// Most workers autoscaling starts
+    pub max_workers: usize,
+}
+
+/// Partial update of the capacity limits
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CapacityUpdate {
+    /// New queue depth limit
+    pub max_queue_depth: Option<usize>,
+    /// New worker count, kept within the bounds while autoscaling
+    pub max_concurrent_executions: Option<usize>,
+    /// New lower autoscaling bound
+    pub min_workers: Option<usize>,
+    /// New upper autoscaling bound
+    pub max_workers: Option<usize>,
 }
 
 /// Cancel response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct CancelResponse {
+    /// Whether the job was stopped
     pub cancelled: bool,
+    /// Human readable summary
     pub message: String,
 }
 
-/// Internal execution job
-#[derive(Debug, Clone)]
-pub struct ExecutionJob {
-    pub id: String,
-    pub request: ExecutionRequest,
-    pub status: ExecutionState,
-    pub created_at: DateTime<Utc>,
-    pub started_at: Option<DateTime<Utc>>,
-    pub finished_at: Option<DateTime<Utc>>,
-    pub result: Option<ExecutionResult>,
+/// Which jobs POST /admin/cancel stops; every field given must match, and an empty filter matches every unfinished job
+#[derive(Debug, Deserialize, Default, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CancelFilter {
+    /// Submitting client, as in /admin/clients
+    pub client: Option<String>,
+    /// Language key
+    pub language: Option<String>,
+    /// Waiting jobs, running jobs or both (the default)
+    pub status: Option<Vec<CancelStatus>>,
+    /// Only jobs submitted before this time
+    pub created_before: Option<DateTime<Utc>>,
+    /// Recorded as each cancelled result's failure_reason
+    pub reason: Option<String>,
 }
 
-/// Resource limits for execution
-#[derive(Debug, Clone)]
-pub struct ResourceLimits {
-    pub cpu_time: f64,          // seconds
-    pub cpu_extra_time: f64,    // extra time for cleanup
-    pub memory: u64,            // bytes
-    pub wall_time: f64,         // seconds
-    pub stack_limit: u64,       // bytes
-    pub file_size: u64,         // bytes
-    pub processes: u32,         // max processes/threads
-    pub enable_per_process_time_limit: bool,
-    pub enable_per_process_memory_limit: bool,
+impl CancelFilter {
+    /// Whether the filter selects a job; finished jobs never match
+    pub fn matches(&self, job: &ExecutionJob) -> bool {
+        let status = match job.status {
+            ExecutionState::Scheduled | ExecutionState::Queued => CancelStatus::Queued,
+            ExecutionState::Processing | ExecutionState::Running => CancelStatus::Running,
+            _ => return false,
+        };
+        self.status.as_ref().is_none_or(|statuses| statuses.contains(&status))
+            && self.client.as_ref().is_none_or(|client| job.client.as_ref() == Some(client))
+            && self.language.as_ref().is_none_or(|language| job.request.language.eq_ignore_ascii_case(language))
+            && self.created_before.is_none_or(|before| job.created_at < before)
+    }
 }
 
-impl Default for ResourceLimits {
+/// Most jobs one GET /executions response lists
+pub const MAX_LIST_LIMIT: usize = 1000;
+
+/// Which jobs GET /executions lists, newest first; every filter given must match
+///
+/// Metadata filters are `metadata.<key>=<value>` parameters, compared as plain strings:
+///
+/// ```
+/// use labforcode_engine::types::ExecutionListQuery;
+/// use std::collections::HashMap;
+///
+/// let params = HashMap::from([("metadata.user_id".to_string(), "42".to_string())]);
+/// assert_eq!(ExecutionListQuery::parse(&params).unwrap().metadata["user_id"], "42");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionListQuery {
+    /// Only the jobs this client submitted
+    pub client: Option<String>,
+    /// Only jobs in this state
+    pub status: Option<ExecutionState>,
+    /// Only jobs in this language
+    pub language: Option<String>,
+    /// Only jobs whose metadata has each of these values
+    pub metadata: BTreeMap<String, String>,
+    /// Most jobs listed
+    pub limit: usize,
+}
+
+impl Default for ExecutionListQuery {
     fn default() -> Self {
         Self {
-            cpu_time: 5.0,
-            cpu_extra_time: 0.5,
-            memory: 256 * 1024 * 1024, // 256MB
-            wall_time: 10.0,
-            stack_limit: 64 * 1024 * 1024, // 64MB
-            file_size: 1024 * 1024,     // 1MB
-            processes: 1,
-            enable_per_process_time_limit: false,
-            enable_per_process_memory_limit: true,
+            client: None,
+            status: None,
+            language: None,
+            metadata: BTreeMap::new(),
+            limit: 100,
         }
     }
 }
 
-impl ResourceLimits {
-    /// Create resource limits from execution request
-    pub fn from_request(req: &ExecutionRequest) -> Self {
-        Self {
-            cpu_time: req.cpu_time_limit.unwrap_or(5.0),
-            cpu_extra_time: req.cpu_extra_time.unwrap_or(0.5),
-            memory: req.memory_limit.unwrap_or(256 * 1024 * 1024),
-            wall_time: req.wall_time_limit.unwrap_or(10.0),
-            stack_limit: req.stack_limit.unwrap_or(64 * 1024 * 1024),
-            file_size: req.max_file_size.unwrap_or(1024 * 1024),
-            processes: req.max_processes_and_or_threads.unwrap_or(1),
-            enable_per_process_time_limit: req.enable_per_process_and_thread_time_limit.unwrap_or(false),
-            enable_per_process_memory_limit: req.enable_per_process_and_thread_memory_limit.unwrap_or(true),
+impl ExecutionListQuery {
+    /// Read `status`, `language`, `limit` and `metadata.*`, listing every value that can't be used
+    pub fn parse(params: &HashMap<String, String>) -> Result<Self, Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let mut query = Self::default();
+        
+        if let Some(status) = params.get("status") {
+            match status.parse() {
+                Ok(status) => query.status = Some(status),
+                Err(error) => errors.push(error),
+            }
         }
+        query.language = params.get("language").cloned();
+        if let Some(limit) = params.get("limit") {
+            match limit.parse::<usize>() {
+                Ok(limit) if (1..=MAX_LIST_LIMIT).contains(&limit) => query.limit = limit,
+                _ => errors.push(FieldError::new("limit", format!("must be between 1 and {}", MAX_LIST_LIMIT))),
+            }
+        }
+        
+        let mut filters: Vec<_> = params.iter()
+            .filter_map(|(name, value)| Some((name, name.strip_prefix("metadata.")?, value)))
+            .collect();
+        filters.sort();
+        for (name, key, value) in filters {
+            if key.is_empty() {
+                errors.push(FieldError::new(name, "must name a metadata key, as in metadata.user_id"));
+            } else {
+                query.metadata.insert(key.to_string(), value.clone());
+            }
+        }
+        
+        if errors.is_empty() {
+            Ok(query)
+        } else {
+            Err(errors)
+        }
+    }
+    
+    /// Whether the query selects a job
+    pub fn matches(&self, job: &ExecutionJob) -> bool {
+        let metadata = job.request.metadata.as_ref();
+        self.client.as_ref().is_none_or(|client| job.client.as_ref() == Some(client))
+            && self.status.as_ref().is_none_or(|status| &job.status == status)
+            && self.language.as_ref().is_none_or(|language| job.request.language.eq_ignore_ascii_case(language))
+            && self.metadata.iter().all(|(key, value)| metadata.and_then(|metadata| metadata.get(key)) == Some(value))
     }
 }
 
-/// Execution options
-#[derive(Debug, Clone)]
-pub struct ExecutionOptions {
-    pub redirect_stderr_to_stdout: bool,
-    pub enable_network: bool,
-    pub number_of_runs: u32,
-    pub stop_on_first_failure: bool,
+/// Job states a bulk cancel can select
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CancelStatus {
+    /// Queued or scheduled
+    Queued,
+    /// Taken by a worker, compiling or running
+    Running,
 }
 
-impl Default for ExecutionOptions {
-    fn default() -> Self {
+/// Outcome of POST /admin/cancel
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkCancelResponse {
+    /// Jobs cancelled
+    pub cancelled: u64,
+    /// Jobs cancelled by the state they were in, e.g. {"queued": 40, "running": 2}
+    pub by_status: BTreeMap<String, u64>,
+}
+
+/// Lifecycle transition recorded in the audit log
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventKind {
+    /// Accepted into the queue
+    Submitted,
+    /// Taken by a worker
+    Started,
+    /// Compilation finished
+    Compiled,
+    /// Finished with a verdict, whatever it was
+    Completed,
+    /// Gave up after an engine error
+    Failed,
+    /// Cancelled by a client or operator
+    Cancelled,
+    /// The result was accepted by the callback_url receiver
+    CallbackDelivered,
+    /// The callback_url receiver could not be reached after every attempt
+    CallbackFailed,
+    /// Runtime settings were changed through PUT /admin/config; not tied to a job
+    ConfigChanged,
+}
+
+/// Change of runtime settings, as written to the audit log
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ConfigChangeEvent {
+    /// Always `config_changed`
+    pub event: AuditEventKind,
+    /// When the change took effect
+    pub at: DateTime<Utc>,
+    /// Client key of the admin who made it, e.g. "key:3f2a9c01b2d4"
+    pub admin: String,
+    /// X-Request-Id of the change request
+    pub request_id: Option<String>,
+    /// Settings the request changed, with API keys replaced by their client keys
+    pub changes: RuntimeSettings,
+}
+
+/// Outcome of a finished execution, without its output
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResultSummary {
+    /// Verdict
+    pub status: ExecutionState,
+    /// Exit code of the last run
+    pub exit_code: Option<i32>,
+    /// CPU seconds
+    pub time: Option<f64>,
+    /// Peak memory in bytes
+    pub memory: Option<u64>,
+}
+
+/// Who did what to a job and when
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEvent {
+    /// What happened
+    pub event: AuditEventKind,
+    /// Job it happened to
+    pub job_id: String,
+    /// When it happened
+    pub at: DateTime<Utc>,
+    /// Submitting client
+    pub client: Option<String>,
+    /// Language key
+    pub language: String,
+    /// X-Request-Id of the submitting HTTP request
+    pub request_id: Option<String>,
+    /// Worker attempt the event belongs to
+    pub attempt: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Instance and worker that had the job, from `started` on
+    pub runner: Option<Runner>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Outcome, on completion
+    pub result: Option<ResultSummary>,
+}
+
+/// Internal execution job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionJob {
+    /// Execution id
+    pub id: String,
+    /// Submission as admitted
+    pub request: ExecutionRequest,
+    /// Current state
+    pub status: ExecutionState,
+    /// When the job was submitted
+    pub created_at: DateTime<Utc>,
+    /// When a worker started the job
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the job reached a terminal state
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Shared so copying a job doesn't copy its output
+    pub result: Option<Arc<ExecutionResult>>,
+    #[serde(default)]
+    /// X-Request-Id of the submitting HTTP request
+    pub request_id: Option<String>,
+    #[serde(default)]
+    /// Rate limiter key of the submitter (hashed API key or IP)
+    pub client: Option<String>,
+    #[serde(default)]
+    /// Times a worker has started this job
+    pub attempts: u32,
+    #[serde(default)]
+    /// Execution this one re-runs
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    /// While a worker has the job
+    pub progress: Option<ExecutionProgress>,
+    #[serde(default)]
+    /// Audit trail, oldest first
+    pub events: Vec<AuditEvent>,
+    #[serde(default)]
+    /// When the job reached each stage of its execution
+    pub timeline: Timeline,
+    #[serde(default)]
+    /// When the result was copied to the archive bucket
+    pub archived_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    /// Bytes of source_code as submitted; 0 for a source_url
+    pub source_size_bytes: u64,
+    #[serde(default)]
+    /// Instance and worker running the job, or that ran its last attempt
+    pub runner: Option<Runner>,
+}
+
+impl ExecutionJob {
+    /// Requested priority, defaulting to the middle band
+    pub fn priority(&self) -> u8 {
+        self.request.priority.unwrap_or(DEFAULT_PRIORITY).min(MAX_PRIORITY)
+    }
+    
+    /// Whether the job must still wait for its run_at time
+    pub fn is_scheduled(&self, now: DateTime<Utc>) -> bool {
+        self.request.run_at.is_some_and(|run_at| run_at > now)
+    }
+    
+    /// Stamp the job's entry into the queue; a scheduled job only starts waiting at its run_at time
+    pub fn mark_enqueued(&mut self, now: DateTime<Utc>) {
+        let at = self.request.run_at.filter(|&run_at| run_at > now).unwrap_or(now);
+        self.timeline.stamp_at(TimelineStage::Enqueued, at);
+    }
+}
+
+/// Memory limits below this are almost always megabytes or kilobytes sent as bytes
+const IMPLAUSIBLE_MEMORY_BYTES: u64 = 1024 * 1024;
+
+/// Stack limits below this are too small for any runtime to start
+const IMPLAUSIBLE_STACK_BYTES: u64 = 64 * 1024;
+
+/// Resource limits for execution.
+///
+/// The wall time covers everything the program started. A grandchild that moves to a session of
+/// its own and keeps the output open is killed along with it, and the run ends at the limit.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct ResourceLimits {
+    /// Seconds
+    pub cpu_time: f64,
+    /// Extra time for cleanup
+    pub cpu_extra_time: f64,
+    /// Bytes
+    pub memory: u64,
+    /// Seconds
+    pub wall_time: f64,
+    /// Bytes
+    pub stack_limit: u64,
+    /// Bytes
+    pub file_size: u64,
+    /// Bytes on disk for the whole working directory
+    pub workdir_quota: u64,
+    /// Max processes/threads
+    pub processes: u32,
+    /// Apply cpu_time to each process
+    pub enable_per_process_time_limit: bool,
+    /// Apply memory to each process
+    pub enable_per_process_memory_limit: bool,
+    /// Cores the program is pinned to; results list the cores actually used
+    pub cpu_affinity: Option<CpuAffinity>,
+    /// Scheduling niceness, 0 (normal) to 19 (lowest)
+    pub nice: Option<i32>,
+}
+
+/// Cores a job's processes are pinned to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum CpuAffinity {
+    /// `"auto"`: the least busy core of the reserved pool
+    Auto(AutoAffinity),
+    /// These core ids
+    Cores(Vec<usize>),
+}
+
+/// Spelling of [`CpuAffinity::Auto`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoAffinity {
+    /// Let the engine pick
+    Auto,
+}
+
+impl FromStr for CpuAffinity {
+    type Err = String;
+    
+    /// `auto` or comma separated core ids, as in DEFAULT_CPU_AFFINITY and the gRPC API
+    fn from_str(value: &str) -> Result<Self, String> {
+        if value.trim().eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto(AutoAffinity::Auto));
+        }
+        value.split(',')
+            .map(|core| core.trim().parse().map_err(|_| format!("'{}' is not a core id", core.trim())))
+            .collect::<Result<_, _>>()
+            .map(Self::Cores)
+    }
+}
+
+impl fmt::Display for CpuAffinity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto(_) => f.write_str("auto"),
+            Self::Cores(cores) => {
+                let cores: Vec<String> = cores.iter().map(usize::to_string).collect();
+                f.write_str(&cores.join(","))
+            }
+        }
+    }
+}
+
+/// How strong a kernel boundary a job runs behind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum IsolationLevel {
+    /// The backend's usual sandbox
+    #[default]
+    Standard,
+    /// A container under DOCKER_STRONG_RUNTIME (gVisor), refused where that is unavailable
+    Strong,
+}
+
+impl FromStr for IsolationLevel {
+    type Err = String;
+    
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "standard" => Ok(Self::Standard),
+            "strong" => Ok(Self::Strong),
+            other => Err(format!("'{}' is not standard or strong", other)),
+        }
+    }
+}
+
+impl fmt::Display for IsolationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Standard => "standard",
+            Self::Strong => "strong",
+        })
+    }
+}
+
+/// Whether an output is text, as the program wrote it; binary outranks text when runs are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub enum OutputEncoding {
+    /// Valid UTF-8, returned exactly
+    #[serde(rename = "utf-8")]
+    Utf8,
+    /// Not UTF-8: as text it has replacement characters, so only base64_encoded returns the exact bytes
+    #[serde(rename = "binary")]
+    Binary,
+}
+
+impl OutputEncoding {
+    /// Encoding of the bytes a program wrote
+    pub fn of(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(_) => Self::Utf8,
+            Err(_) => Self::Binary,
+        }
+    }
+    
+    /// Name used in results
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Binary => "binary",
+        }
+    }
+}
+
+impl FromStr for OutputEncoding {
+    type Err = String;
+    
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "utf-8" => Ok(Self::Utf8),
+            "binary" => Ok(Self::Binary),
+            other => Err(format!("'{}' is not utf-8 or binary", other)),
+        }
+    }
+}
+
+/// What is removed from stdout, stderr and compile_output before a result is stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFilter {
+    /// Output is stored as produced
+    #[default]
+    None,
+    /// ANSI escape sequences such as colors and cursor movement
+    StripAnsi,
+    /// Escape sequences and every control character but tab, newline and carriage return
+    StripControl,
+}
+
+impl FromStr for OutputFilter {
+    type Err = String;
+    
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "strip_ansi" => Ok(Self::StripAnsi),
+            "strip_control" => Ok(Self::StripControl),
+            other => Err(format!("'{}' is not none, strip_ansi or strip_control", other)),
+        }
+    }
+}
+
+impl fmt::Display for OutputFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::StripAnsi => "strip_ansi",
+            Self::StripControl => "strip_control",
+        })
+    }
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            cpu_time: 5.0,
+            cpu_extra_time: 0.5,
+            memory: 256 * 1024 * 1024, // 256MB
+            wall_time: 10.0,
+            stack_limit: 64 * 1024 * 1024, // 64MB
+            file_size: 1024 * 1024,     // 1MB
+            workdir_quota: 64 * 1024 * 1024, // 64MB
+            processes: 1,
+            enable_per_process_time_limit: false,
+            enable_per_process_memory_limit: true,
+            cpu_affinity: None,
+            nice: None,
+        }
+    }
+}
+
+impl ResourceLimits {
+    /// Create resource limits from execution request, taking what it leaves out from `defaults`
+    pub fn from_request(req: &ExecutionRequest, defaults: &ResourceLimits) -> Self {
+        Self {
+            cpu_time: req.cpu_time_limit.unwrap_or(defaults.cpu_time),
+            cpu_extra_time: req.cpu_extra_time.unwrap_or(defaults.cpu_extra_time),
+            memory: req.memory_limit.unwrap_or(defaults.memory),
+            wall_time: req.wall_time_limit.unwrap_or(defaults.wall_time),
+            stack_limit: req.stack_limit.unwrap_or(defaults.stack_limit),
+            file_size: req.max_file_size.unwrap_or(defaults.file_size),
+            workdir_quota: req.workdir_quota.unwrap_or(defaults.workdir_quota),
+            processes: req.max_processes_and_or_threads.unwrap_or(defaults.processes),
+            enable_per_process_time_limit: req.enable_per_process_and_thread_time_limit
+                .unwrap_or(defaults.enable_per_process_time_limit),
+            enable_per_process_memory_limit: req.enable_per_process_and_thread_memory_limit
+                .unwrap_or(defaults.enable_per_process_memory_limit),
+            cpu_affinity: req.cpu_affinity.clone().or_else(|| defaults.cpu_affinity.clone()),
+            nice: req.nice.or(defaults.nice),
+        }
+    }
+    
+    /// Explain limits that look like a unit mix-up rather than an intended value
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.memory < IMPLAUSIBLE_MEMORY_BYTES {
+            warnings.push(format!(
+                "memory limit of {} bytes is implausibly small; memory_limit is in bytes, use memory_limit_kb for kilobytes",
+                self.memory
+            ));
+        }
+        if self.stack_limit < IMPLAUSIBLE_STACK_BYTES {
+            warnings.push(format!(
+                "stack limit of {} bytes is implausibly small; stack_limit is in bytes, use stack_limit_kb for kilobytes",
+                self.stack_limit
+            ));
+        }
+        warnings
+    }
+}
+
+/// Limits that replace inherited ones where set, such as a language's own defaults or maxima
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Seconds
+    pub cpu_time: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Seconds
+    pub wall_time: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Bytes
+    pub memory: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Bytes
+    pub stack_limit: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Bytes
+    pub file_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Bytes on disk for the whole working directory
+    pub workdir_quota: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Max processes/threads
+    pub processes: Option<u32>,
+}
+
+impl LimitOverrides {
+    /// These values where set, `inherited` ones elsewhere
+    pub fn or(&self, inherited: &Self) -> Self {
+        Self {
+            cpu_time: self.cpu_time.or(inherited.cpu_time),
+            wall_time: self.wall_time.or(inherited.wall_time),
+            memory: self.memory.or(inherited.memory),
+            stack_limit: self.stack_limit.or(inherited.stack_limit),
+            file_size: self.file_size.or(inherited.file_size),
+            workdir_quota: self.workdir_quota.or(inherited.workdir_quota),
+            processes: self.processes.or(inherited.processes),
+        }
+    }
+    
+    /// Replace the limits that are set here
+    pub fn apply(&self, limits: &mut ResourceLimits) {
+        limits.cpu_time = self.cpu_time.unwrap_or(limits.cpu_time);
+        limits.wall_time = self.wall_time.unwrap_or(limits.wall_time);
+        limits.memory = self.memory.unwrap_or(limits.memory);
+        limits.stack_limit = self.stack_limit.unwrap_or(limits.stack_limit);
+        limits.file_size = self.file_size.unwrap_or(limits.file_size);
+        limits.workdir_quota = self.workdir_quota.unwrap_or(limits.workdir_quota);
+        limits.processes = self.processes.unwrap_or(limits.processes);
+    }
+}
+
+/// Largest limits a request may ask for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct LimitCeilings {
+    /// Seconds
+    pub cpu_time: f64,
+    /// Seconds
+    pub wall_time: f64,
+    /// Bytes
+    pub memory: u64,
+    /// Bytes
+    pub stack_limit: u64,
+    /// Bytes
+    pub file_size: u64,
+    /// Bytes on disk for the whole working directory
+    pub workdir_quota: u64,
+    /// Max processes/threads
+    pub processes: u32,
+}
+
+impl LimitCeilings {
+    /// Lower any limit above its ceiling
+    pub fn clamp(&self, limits: &mut ResourceLimits) {
+        limits.cpu_time = limits.cpu_time.min(self.cpu_time);
+        limits.wall_time = limits.wall_time.min(self.wall_time);
+        limits.memory = limits.memory.min(self.memory);
+        limits.stack_limit = limits.stack_limit.min(self.stack_limit);
+        limits.file_size = limits.file_size.min(self.file_size);
+        limits.workdir_quota = limits.workdir_quota.min(self.workdir_quota);
+        limits.processes = limits.processes.min(self.processes);
+    }
+}
+
+/// Execution options
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExecutionOptions {
+    /// Merge stderr into stdout
+    pub redirect_stderr_to_stdout: bool,
+    /// Allow network access
+    pub enable_network: bool,
+    /// Times the program is run
+    pub number_of_runs: u32,
+    /// Each run started from its own copy of the built working directory
+    #[serde(default = "fresh_by_default")]
+    pub fresh_workdir_per_run: bool,
+    /// Skip the remaining runs after one fails
+    pub stop_on_first_failure: bool,
+    /// Sandbox boundary the job ran behind
+    #[serde(default)]
+    pub isolation: IsolationLevel,
+    /// What was removed from the text outputs
+    #[serde(default)]
+    pub output_filter: OutputFilter,
+    /// CRLF was turned into LF in the text outputs
+    #[serde(default)]
+    pub normalize_newlines: bool,
+}
+
+/// Results stored before `fresh_workdir_per_run` existed rebuilt the program for every run
+fn fresh_by_default() -> bool {
+    true
+}
+
+impl Default for ExecutionOptions {
+    fn default() -> Self {
         Self {
             redirect_stderr_to_stdout: false,
             enable_network: false,
             number_of_runs: 1,
+            fresh_workdir_per_run: true,
             stop_on_first_failure: true,
+            isolation: IsolationLevel::Standard,
+            output_filter: OutputFilter::None,
+            normalize_newlines: false,
         }
     }
 }
 
 impl ExecutionOptions {
     /// Create execution options from request
+    ///
+    /// Several runs share one build, and each starts from its own copy of the built working
+    /// directory unless `fresh_workdir_per_run` is false.
     pub fn from_request(req: &ExecutionRequest) -> Self {
         Self {
             redirect_stderr_to_stdout: req.redirect_stderr_to_stdout.unwrap_or(false),
             enable_network: req.enable_network.unwrap_or(false),
             number_of_runs: req.number_of_runs.unwrap_or(1),
+            fresh_workdir_per_run: req.fresh_workdir_per_run.unwrap_or(true),
             stop_on_first_failure: true, // Default behavior
+            isolation: req.isolation.unwrap_or_default(),
+            // base64 outputs are always the raw bytes
+            output_filter: match req.base64_encoded {
+                Some(true) => OutputFilter::None,
+                _ => req.output_filter.unwrap_or_default(),
+            },
+            normalize_newlines: req.base64_encoded != Some(true) && req.normalize_newlines.unwrap_or(false),
         }
     }
 }
 
-/// Execution metrics
-#[derive(Debug, Default)]
+/// How a run used the machine, from the kernel's accounting; null where the backend or platform cannot tell
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ToSchema)]
 pub struct ExecutionMetrics {
-    pub cpu_time: f64,
-    pub memory_peak: u64,
-    pub wall_time: f64,
-    pub exit_code: i32,
-    pub signal: Option<String>,
+    /// CPU seconds, user plus system
+    pub cpu_time: Option<f64>,
+    /// CPU seconds spent in the program itself
+    pub user_time: Option<f64>,
+    /// CPU seconds spent in the kernel on the program's behalf
+    pub system_time: Option<f64>,
+    /// Peak resident memory in bytes
+    pub memory_peak: Option<u64>,
+    /// Wall-clock seconds
+    pub wall_time: Option<f64>,
+    /// Page faults served without I/O
+    pub minor_page_faults: Option<u64>,
+    /// Page faults that had to read from disk
+    pub major_page_faults: Option<u64>,
+    /// Times the program gave up the CPU, usually to wait for I/O
+    pub voluntary_context_switches: Option<u64>,
+    /// Times the program was preempted
+    pub involuntary_context_switches: Option<u64>,
+    /// Bytes passed to read calls, including stdin
+    pub read_bytes: Option<u64>,
+    /// Bytes passed to write calls, including stdout and stderr
+    pub written_bytes: Option<u64>,
+}
+
+impl ExecutionMetrics {
+    /// Totals over several runs; memory_peak is the largest, and a value missing from either is missing from both
+    pub fn combine(&self, other: &Self) -> Self {
+        fn sum<T: std::ops::Add<Output = T> + Copy>(a: Option<T>, b: Option<T>) -> Option<T> {
+            a.zip(b).map(|(a, b)| a + b)
+        }
+        Self {
+            cpu_time: sum(self.cpu_time, other.cpu_time),
+            user_time: sum(self.user_time, other.user_time),
+            system_time: sum(self.system_time, other.system_time),
+            memory_peak: self.memory_peak.zip(other.memory_peak).map(|(a, b)| a.max(b)),
+            wall_time: sum(self.wall_time, other.wall_time),
+            minor_page_faults: sum(self.minor_page_faults, other.minor_page_faults),
+            major_page_faults: sum(self.major_page_faults, other.major_page_faults),
+            voluntary_context_switches: sum(self.voluntary_context_switches, other.voluntary_context_switches),
+            involuntary_context_switches: sum(self.involuntary_context_switches, other.involuntary_context_switches),
+            read_bytes: sum(self.read_bytes, other.read_bytes),
+            written_bytes: sum(self.written_bytes, other.written_bytes),
+        }
+    }
+}
+
+// Conversions between the JSON types above and the gRPC messages in proto/engine.proto.
+// Every struct is destructured in full, so a field added on either side fails to compile here.
+
+impl From<ExecutionState> for proto::ExecutionState {
+    fn from(state: ExecutionState) -> Self {
+        match state {
+            ExecutionState::Scheduled => proto::ExecutionState::Scheduled,
+            ExecutionState::Queued => proto::ExecutionState::Queued,
+            ExecutionState::Processing => proto::ExecutionState::Processing,
+            ExecutionState::Running => proto::ExecutionState::Running,
+            ExecutionState::Completed => proto::ExecutionState::Completed,
+            ExecutionState::CompilationError => proto::ExecutionState::CompilationError,
+            ExecutionState::RuntimeError => proto::ExecutionState::RuntimeError,
+            ExecutionState::WrongAnswer => proto::ExecutionState::WrongAnswer,
+            ExecutionState::TimeLimitExceeded => proto::ExecutionState::TimeLimitExceeded,
+            ExecutionState::MemoryLimitExceeded => proto::ExecutionState::MemoryLimitExceeded,
+            ExecutionState::OutputLimitExceeded => proto::ExecutionState::OutputLimitExceeded,
+            ExecutionState::Cancelled => proto::ExecutionState::Cancelled,
+            ExecutionState::InternalError => proto::ExecutionState::InternalError,
+        }
+    }
+}
+
+impl TryFrom<i32> for ExecutionState {
+    type Error = FieldError;
+    
+    fn try_from(value: i32) -> Result<Self, FieldError> {
+        match proto::ExecutionState::try_from(value) {
+            Ok(proto::ExecutionState::Scheduled) => Ok(ExecutionState::Scheduled),
+            Ok(proto::ExecutionState::Queued) => Ok(ExecutionState::Queued),
+            Ok(proto::ExecutionState::Processing) => Ok(ExecutionState::Processing),
+            Ok(proto::ExecutionState::Running) => Ok(ExecutionState::Running),
+            Ok(proto::ExecutionState::Completed) => Ok(ExecutionState::Completed),
+            Ok(proto::ExecutionState::CompilationError) => Ok(ExecutionState::CompilationError),
+            Ok(proto::ExecutionState::RuntimeError) => Ok(ExecutionState::RuntimeError),
+            Ok(proto::ExecutionState::WrongAnswer) => Ok(ExecutionState::WrongAnswer),
+            Ok(proto::ExecutionState::TimeLimitExceeded) => Ok(ExecutionState::TimeLimitExceeded),
+            Ok(proto::ExecutionState::MemoryLimitExceeded) => Ok(ExecutionState::MemoryLimitExceeded),
+            Ok(proto::ExecutionState::OutputLimitExceeded) => Ok(ExecutionState::OutputLimitExceeded),
+            Ok(proto::ExecutionState::Cancelled) => Ok(ExecutionState::Cancelled),
+            Ok(proto::ExecutionState::InternalError) => Ok(ExecutionState::InternalError),
+            Ok(proto::ExecutionState::Unspecified) | Err(_) => Err(FieldError::new("status", format!("unknown execution state {}", value))),
+        }
+    }
+}
+
+impl From<ExecutionState> for u32 {
+    fn from(state: ExecutionState) -> Self {
+        state.code()
+    }
+}
+
+impl TryFrom<u32> for ExecutionState {
+    type Error = FieldError;
+    
+    fn try_from(code: u32) -> Result<Self, FieldError> {
+        i32::try_from(code)
+            .map_err(|_| FieldError::new("status", format!("unknown execution state {}", code)))
+            .and_then(ExecutionState::try_from)
+    }
+}
+
+impl FromStr for ExecutionState {
+    type Err = FieldError;
+    
+    /// A state name such as "time_limit_exceeded", or its numeric code such as "8"
+    fn from_str(value: &str) -> Result<Self, FieldError> {
+        if let Ok(code) = value.parse::<u32>() {
+            return ExecutionState::try_from(code);
+        }
+        serde_json::from_value(serde_json::Value::from(value))
+            .map_err(|_| FieldError::new("status", format!("unknown execution state '{}'", value)))
+    }
+}
+
+impl From<ExecutionRequest> for proto::ExecutionRequest {
+    fn from(request: ExecutionRequest) -> Self {
+        let ExecutionRequest {
+            id,
+            language,
+            language_id,
+            source_code,
+            stdin,
+            stdin_base64,
+            expected_output,
+            compiler_options,
+            command_line_arguments,
+            source_file_name,
+            env,
+            artifact_patterns,
+            preserve_workdir,
+            list_workdir,
+            priority,
+            max_retries,
+            run_at,
+            base64_encoded,
+            dedupe,
+            profile,
+            metadata,
+            cpu_time_limit,
+            cpu_extra_time,
+            memory_limit,
+            memory_limit_kb,
+            wall_time_limit,
+            stack_limit,
+            stack_limit_kb,
+            max_processes_and_or_threads,
+            enable_per_process_and_thread_time_limit,
+            enable_per_process_and_thread_memory_limit,
+            max_file_size,
+            max_file_size_kb,
+            workdir_quota,
+            cpu_affinity,
+            nice,
+            redirect_stderr_to_stdout,
+            enable_network,
+            number_of_runs,
+            fresh_workdir_per_run,
+            isolation,
+            output_filter,
+            normalize_newlines,
+            callback_url,
+            idempotency_key,
+            additional_files,
+            source_url,
+            additional_files_url,
+            interactor,
+            test_cases,
+        } = request;
+        
+        Self {
+            id,
+            language,
+            language_id,
+            source_code,
+            stdin,
+            stdin_base64,
+            expected_output,
+            compiler_options,
+            command_line_arguments,
+            source_file_name,
+            env: env.unwrap_or_default(),
+            artifact_patterns: artifact_patterns.unwrap_or_default(),
+            preserve_workdir,
+            list_workdir,
+            priority: priority.map(u32::from),
+            max_retries,
+            run_at: run_at.map(|run_at| run_at.to_rfc3339()),
+            base64_encoded,
+            dedupe,
+            profile,
+            metadata: metadata.unwrap_or_default(),
+            cpu_time_limit,
+            cpu_extra_time,
+            memory_limit,
+            memory_limit_kb,
+            wall_time_limit,
+            stack_limit,
+            stack_limit_kb,
+            max_processes_and_or_threads,
+            enable_per_process_and_thread_time_limit,
+            enable_per_process_and_thread_memory_limit,
+            max_file_size,
+            max_file_size_kb,
+            workdir_quota,
+            cpu_affinity: cpu_affinity.map(|affinity| affinity.to_string()),
+            nice,
+            redirect_stderr_to_stdout,
+            enable_network,
+            number_of_runs,
+            fresh_workdir_per_run,
+            isolation: isolation.map(|isolation| isolation.to_string()),
+            output_filter: output_filter.map(|filter| filter.to_string()),
+            normalize_newlines,
+            callback_url,
+            idempotency_key,
+            additional_files,
+            source_url,
+            additional_files_url,
+            interactor: interactor.map(Into::into),
+            test_cases: test_cases.unwrap_or_default().into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::ExecutionRequest> for ExecutionRequest {
+    type Error = FieldError;
+    
+    /// Empty `env`, `metadata` and `artifact_patterns` become None, as if the JSON fields were left out
+    fn try_from(request: proto::ExecutionRequest) -> Result<Self, FieldError> {
+        let proto::ExecutionRequest {
+            id,
+            language,
+            language_id,
+            source_code,
+            stdin,
+            stdin_base64,
+            expected_output,
+            compiler_options,
+            command_line_arguments,
+            source_file_name,
+            env,
+            artifact_patterns,
+            preserve_workdir,
+            list_workdir,
+            priority,
+            max_retries,
+            run_at,
+            base64_encoded,
+            dedupe,
+            profile,
+            metadata,
+            cpu_time_limit,
+            cpu_extra_time,
+            memory_limit,
+            memory_limit_kb,
+            wall_time_limit,
+            stack_limit,
+            stack_limit_kb,
+            max_processes_and_or_threads,
+            enable_per_process_and_thread_time_limit,
+            enable_per_process_and_thread_memory_limit,
+            max_file_size,
+            max_file_size_kb,
+            workdir_quota,
+            cpu_affinity,
+            nice,
+            redirect_stderr_to_stdout,
+            enable_network,
+            number_of_runs,
+            fresh_workdir_per_run,
+            isolation,
+            output_filter,
+            normalize_newlines,
+            callback_url,
+            idempotency_key,
+            additional_files,
+            source_url,
+            additional_files_url,
+            interactor,
+            test_cases,
+        } = request;
+        
+        let priority = priority
+            .map(|priority| u8::try_from(priority).map_err(|_| FieldError::new("priority", format!("must be at most {}", MAX_PRIORITY))))
+            .transpose()?;
+        
+        Ok(Self {
+            id,
+            language,
+            language_id,
+            source_code,
+            stdin,
+            stdin_base64,
+            expected_output,
+            compiler_options,
+            command_line_arguments,
+            source_file_name,
+            env: (!env.is_empty()).then_some(env),
+            artifact_patterns: (!artifact_patterns.is_empty()).then_some(artifact_patterns),
+            preserve_workdir,
+            list_workdir,
+            priority,
+            max_retries,
+            run_at: run_at.as_deref().map(|run_at| parse_timestamp("run_at", run_at)).transpose()?,
+            base64_encoded,
+            dedupe,
+            profile,
+            metadata: (!metadata.is_empty()).then_some(metadata),
+            cpu_time_limit,
+            cpu_extra_time,
+            memory_limit,
+            memory_limit_kb,
+            wall_time_limit,
+            stack_limit,
+            stack_limit_kb,
+            max_processes_and_or_threads,
+            enable_per_process_and_thread_time_limit,
+            enable_per_process_and_thread_memory_limit,
+            max_file_size,
+            max_file_size_kb,
+            workdir_quota,
+            cpu_affinity: cpu_affinity
+                .map(|affinity| affinity.parse().map_err(|err: String| FieldError::new("cpu_affinity", err)))
+                .transpose()?,
+            nice,
+            redirect_stderr_to_stdout,
+            enable_network,
+            number_of_runs,
+            fresh_workdir_per_run,
+            isolation: isolation
+                .map(|isolation| isolation.parse().map_err(|err: String| FieldError::new("isolation", err)))
+                .transpose()?,
+            output_filter: output_filter
+                .map(|filter| filter.parse().map_err(|err: String| FieldError::new("output_filter", err)))
+                .transpose()?,
+            normalize_newlines,
+            callback_url,
+            idempotency_key,
+            additional_files,
+            source_url,
+            additional_files_url,
+            interactor: interactor.map(Into::into),
+            test_cases: (!test_cases.is_empty()).then(|| test_cases.into_iter().map(Into::into).collect()),
+        })
+    }
+}
+
+impl From<ExecutionResponse> for proto::SubmitExecutionResponse {
+    fn from(response: ExecutionResponse) -> Self {
+        let ExecutionResponse { id, status, message, parent_id } = response;
+        Self { id, status, message, parent_id }
+    }
+}
+
+impl From<ExecutionProgress> for proto::ExecutionProgress {
+    fn from(progress: ExecutionProgress) -> Self {
+        let ExecutionProgress { phase, run, total_runs } = progress;
+        let phase = match phase {
+            ExecutionPhase::Compiling => proto::ExecutionPhase::Compiling,
+            ExecutionPhase::Running => proto::ExecutionPhase::Running,
+        };
+        Self { phase: phase.into(), run, total_runs }
+    }
+}
+
+impl From<ExecutionStatus> for proto::ExecutionStatus {
+    fn from(status: ExecutionStatus) -> Self {
+        let ExecutionStatus {
+            id,
+            status,
+            created_at,
+            started_at,
+            finished_at,
+            run_at,
+            parent_id,
+            progress,
+            priority,
+            attempt,
+            band_position,
+            queue_position,
+            estimated_wait_seconds,
+            metadata,
+            runner,
+        } = status;
+        
+        Self {
+            id,
+            status: proto::ExecutionState::from(status).into(),
+            created_at: created_at.to_rfc3339(),
+            started_at: started_at.map(|at| at.to_rfc3339()),
+            finished_at: finished_at.map(|at| at.to_rfc3339()),
+            run_at: run_at.map(|at| at.to_rfc3339()),
+            parent_id,
+            progress: progress.map(Into::into),
+            priority: priority.into(),
+            attempt,
+            band_position: band_position.map(|position| position as u64),
+            queue_position: queue_position.map(|position| position as u64),
+            estimated_wait_seconds,
+            metadata: metadata.unwrap_or_default(),
+            runner: runner.map(Into::into),
+        }
+    }
+}
+
+impl From<Runner> for proto::Runner {
+    fn from(runner: Runner) -> Self {
+        let Runner { worker_id, instance, worker, engine_version, backend } = runner;
+        Self { worker_id, instance, worker: worker as u32, engine_version, backend: backend.to_string() }
+    }
+}
+
+impl From<proto::Runner> for Runner {
+    fn from(runner: proto::Runner) -> Self {
+        let proto::Runner { worker_id, instance, worker, engine_version, backend } = runner;
+        Self { worker_id, instance, worker: worker as usize, engine_version, backend: backend.parse().unwrap_or_default() }
+    }
+}
+
+impl From<Artifact> for proto::Artifact {
+    fn from(artifact: Artifact) -> Self {
+        let Artifact { name, size, content_base64, truncated } = artifact;
+        Self { name, size, content_base64, truncated }
+    }
+}
+
+impl From<proto::Artifact> for Artifact {
+    fn from(artifact: proto::Artifact) -> Self {
+        let proto::Artifact { name, size, content_base64, truncated } = artifact;
+        Self { name, size, content_base64, truncated }
+    }
+}
+
+impl From<WorkdirEntry> for proto::WorkdirEntry {
+    fn from(entry: WorkdirEntry) -> Self {
+        let WorkdirEntry { path, size } = entry;
+        Self { path, size }
+    }
+}
+
+impl From<proto::WorkdirEntry> for WorkdirEntry {
+    fn from(entry: proto::WorkdirEntry) -> Self {
+        let proto::WorkdirEntry { path, size } = entry;
+        Self { path, size }
+    }
+}
+
+impl From<ExecutedCommand> for proto::ExecutedCommand {
+    fn from(command: ExecutedCommand) -> Self {
+        let ExecutedCommand { phase, argv, duration_ms, exit_code } = command;
+        let phase = match phase {
+            CommandPhase::Compile => proto::CommandPhase::Compile,
+            CommandPhase::Run => proto::CommandPhase::Run,
+            CommandPhase::InteractorCompile => proto::CommandPhase::InteractorCompile,
+            CommandPhase::Interactor => proto::CommandPhase::Interactor,
+        };
+        Self { phase: phase.into(), argv, duration_ms, exit_code }
+    }
+}
+
+impl TryFrom<proto::ExecutedCommand> for ExecutedCommand {
+    type Error = FieldError;
+    
+    fn try_from(command: proto::ExecutedCommand) -> Result<Self, FieldError> {
+        let proto::ExecutedCommand { phase, argv, duration_ms, exit_code } = command;
+        let phase = match proto::CommandPhase::try_from(phase) {
+            Ok(proto::CommandPhase::Compile) => CommandPhase::Compile,
+            Ok(proto::CommandPhase::Run) => CommandPhase::Run,
+            Ok(proto::CommandPhase::InteractorCompile) => CommandPhase::InteractorCompile,
+            Ok(proto::CommandPhase::Interactor) => CommandPhase::Interactor,
+            Ok(proto::CommandPhase::Unspecified) | Err(_) => {
+                return Err(FieldError::new("executed_commands", format!("unknown command phase {}", phase)));
+            }
+        };
+        Ok(Self { phase, argv, duration_ms, exit_code })
+    }
+}
+
+impl From<Interactor> for proto::Interactor {
+    fn from(interactor: Interactor) -> Self {
+        let Interactor { language, source_code } = interactor;
+        Self { language, source_code }
+    }
+}
+
+impl From<proto::Interactor> for Interactor {
+    fn from(interactor: proto::Interactor) -> Self {
+        let proto::Interactor { language, source_code } = interactor;
+        Self { language, source_code }
+    }
+}
+
+impl From<InteractorResult> for proto::InteractorResult {
+    fn from(result: InteractorResult) -> Self {
+        let InteractorResult { exit_code, signal, exit_description, stderr, time } = result;
+        Self { exit_code, signal, exit_description, stderr, time }
+    }
+}
+
+impl From<proto::InteractorResult> for InteractorResult {
+    fn from(result: proto::InteractorResult) -> Self {
+        let proto::InteractorResult { exit_code, signal, exit_description, stderr, time } = result;
+        Self { exit_code, signal, exit_description, stderr, time }
+    }
+}
+
+impl From<TestCase> for proto::TestCase {
+    fn from(case: TestCase) -> Self {
+        let TestCase { stdin, expected_output, cpu_time_limit, wall_time_limit, memory_limit } = case;
+        Self { stdin, expected_output, cpu_time_limit, wall_time_limit, memory_limit }
+    }
+}
+
+impl From<proto::TestCase> for TestCase {
+    fn from(case: proto::TestCase) -> Self {
+        let proto::TestCase { stdin, expected_output, cpu_time_limit, wall_time_limit, memory_limit } = case;
+        Self { stdin, expected_output, cpu_time_limit, wall_time_limit, memory_limit }
+    }
+}
+
+impl From<TestCaseResult> for proto::TestCaseResult {
+    fn from(result: TestCaseResult) -> Self {
+        let TestCaseResult { status, stdout, stderr, exit_code, signal, exit_description, time, memory, failure_reason } = result;
+        Self {
+            status: proto::ExecutionState::from(status).into(),
+            stdout,
+            stderr,
+            exit_code,
+            signal,
+            exit_description,
+            time,
+            memory,
+            failure_reason,
+        }
+    }
+}
+
+impl TryFrom<proto::TestCaseResult> for TestCaseResult {
+    type Error = FieldError;
+    
+    fn try_from(result: proto::TestCaseResult) -> Result<Self, FieldError> {
+        let proto::TestCaseResult { status, stdout, stderr, exit_code, signal, exit_description, time, memory, failure_reason } = result;
+        Ok(Self {
+            status: ExecutionState::try_from(status)?,
+            stdout,
+            stderr,
+            exit_code,
+            signal,
+            exit_description,
+            time,
+            memory,
+            failure_reason,
+        })
+    }
+}
+
+impl From<TestGroupSummary> for proto::TestGroupSummary {
+    fn from(summary: TestGroupSummary) -> Self {
+        let TestGroupSummary { passed, failed, skipped, first_failure } = summary;
+        Self { passed, failed, skipped, first_failure }
+    }
+}
+
+impl From<proto::TestGroupSummary> for TestGroupSummary {
+    fn from(summary: proto::TestGroupSummary) -> Self {
+        let proto::TestGroupSummary { passed, failed, skipped, first_failure } = summary;
+        Self { passed, failed, skipped, first_failure }
+    }
+}
+
+impl From<PhaseTimings> for proto::PhaseTimings {
+    fn from(phases: PhaseTimings) -> Self {
+        let PhaseTimings { setup_ms, compile_ms, run_ms, teardown_ms } = phases;
+        Self { setup_ms, compile_ms, run_ms, teardown_ms }
+    }
+}
+
+impl From<proto::PhaseTimings> for PhaseTimings {
+    fn from(phases: proto::PhaseTimings) -> Self {
+        let proto::PhaseTimings { setup_ms, compile_ms, run_ms, teardown_ms } = phases;
+        Self { setup_ms, compile_ms, run_ms, teardown_ms }
+    }
+}
+
+impl From<ExecutionMetrics> for proto::ExecutionMetrics {
+    fn from(metrics: ExecutionMetrics) -> Self {
+        let ExecutionMetrics {
+            cpu_time,
+            user_time,
+            system_time,
+            memory_peak,
+            wall_time,
+            minor_page_faults,
+            major_page_faults,
+            voluntary_context_switches,
+            involuntary_context_switches,
+            read_bytes,
+            written_bytes,
+        } = metrics;
+        
+        Self {
+            cpu_time,
+            user_time,
+            system_time,
+            memory_peak,
+            wall_time,
+            minor_page_faults,
+            major_page_faults,
+            voluntary_context_switches,
+            involuntary_context_switches,
+            read_bytes,
+            written_bytes,
+        }
+    }
+}
+
+impl From<proto::ExecutionMetrics> for ExecutionMetrics {
+    fn from(metrics: proto::ExecutionMetrics) -> Self {
+        let proto::ExecutionMetrics {
+            cpu_time,
+            user_time,
+            system_time,
+            memory_peak,
+            wall_time,
+            minor_page_faults,
+            major_page_faults,
+            voluntary_context_switches,
+            involuntary_context_switches,
+            read_bytes,
+            written_bytes,
+        } = metrics;
+        
+        Self {
+            cpu_time,
+            user_time,
+            system_time,
+            memory_peak,
+            wall_time,
+            minor_page_faults,
+            major_page_faults,
+            voluntary_context_switches,
+            involuntary_context_switches,
+            read_bytes,
+            written_bytes,
+        }
+    }
+}
+
+impl From<ResourceLimits> for proto::ResourceLimits {
+    fn from(limits: ResourceLimits) -> Self {
+        let ResourceLimits {
+            cpu_time,
+            cpu_extra_time,
+            memory,
+            wall_time,
+            stack_limit,
+            file_size,
+            workdir_quota,
+            processes,
+            enable_per_process_time_limit,
+            enable_per_process_memory_limit,
+            cpu_affinity,
+            nice,
+        } = limits;
+        Self {
+            cpu_time,
+            cpu_extra_time,
+            memory,
+            wall_time,
+            stack_limit,
+            file_size,
+            workdir_quota,
+            processes,
+            enable_per_process_time_limit,
+            enable_per_process_memory_limit,
+            cpu_affinity: cpu_affinity.map(|affinity| affinity.to_string()),
+            nice,
+        }
+    }
+}
+
+impl From<proto::ResourceLimits> for ResourceLimits {
+    fn from(limits: proto::ResourceLimits) -> Self {
+        let proto::ResourceLimits {
+            cpu_time,
+            cpu_extra_time,
+            memory,
+            wall_time,
+            stack_limit,
+            file_size,
+            workdir_quota,
+            processes,
+            enable_per_process_time_limit,
+            enable_per_process_memory_limit,
+            cpu_affinity,
+            nice,
+        } = limits;
+        Self {
+            cpu_time,
+            cpu_extra_time,
+            memory,
+            wall_time,
+            stack_limit,
+            file_size,
+            workdir_quota,
+            processes,
+            enable_per_process_time_limit,
+            enable_per_process_memory_limit,
+            cpu_affinity: cpu_affinity.and_then(|affinity| affinity.parse().ok()),
+            nice,
+        }
+    }
+}
+
+impl From<ExecutionOptions> for proto::ExecutionOptions {
+    fn from(options: ExecutionOptions) -> Self {
+        let ExecutionOptions {
+            redirect_stderr_to_stdout,
+            enable_network,
+            number_of_runs,
+            fresh_workdir_per_run,
+            stop_on_first_failure,
+            isolation,
+            output_filter,
+            normalize_newlines,
+        } = options;
+        Self {
+            redirect_stderr_to_stdout,
+            enable_network,
+            number_of_runs,
+            fresh_workdir_per_run,
+            stop_on_first_failure,
+            isolation: isolation.to_string(),
+            output_filter: output_filter.to_string(),
+            normalize_newlines,
+        }
+    }
+}
+
+impl From<proto::ExecutionOptions> for ExecutionOptions {
+    fn from(options: proto::ExecutionOptions) -> Self {
+        let proto::ExecutionOptions {
+            redirect_stderr_to_stdout,
+            enable_network,
+            number_of_runs,
+            fresh_workdir_per_run,
+            stop_on_first_failure,
+            isolation,
+            output_filter,
+            normalize_newlines,
+        } = options;
+        Self {
+            redirect_stderr_to_stdout,
+            enable_network,
+            number_of_runs,
+            fresh_workdir_per_run,
+            stop_on_first_failure,
+            isolation: isolation.parse().unwrap_or_default(),
+            output_filter: output_filter.parse().unwrap_or_default(),
+            normalize_newlines,
+        }
+    }
+}
+
+impl From<ExecutionResult> for proto::ExecutionResult {
+    fn from(result: ExecutionResult) -> Self {
+        let ExecutionResult {
+            id,
+            status,
+            stdout,
+            stderr,
+            compile_output,
+            diagnostics,
+            stdout_bytes,
+            stdout_url,
+            stderr_bytes,
+            stderr_url,
+            stdout_encoding,
+            stderr_encoding,
+            stdout_truncated,
+            stderr_truncated,
+            exit_code,
+            signal,
+            exit_description,
+            interactor,
+            test_cases,
+            test_summary,
+            time,
+            memory,
+            metrics,
+            artifacts,
+            workdir_listing,
+            executed_commands,
+            failure_reason,
+            compile_cache_hit,
+            failed_run_indices,
+            warnings,
+            attempts,
+            limits,
+            options,
+            metadata,
+            runner,
+            started_at,
+            created_at,
+            finished_at,
+            timeline,
+            queue_wait_ms,
+            overhead_ms,
+            phases,
+            setup_bytes,
+        } = result;
+        
+        Self {
+            id,
+            status: proto::ExecutionState::from(status).into(),
+            stdout,
+            stderr,
+            compile_output,
+            diagnostics: diagnostics.into_iter().map(Into::into).collect(),
+            stdout_bytes,
+            stdout_url,
+            stderr_bytes,
+            stderr_url,
+            stdout_encoding: stdout_encoding.map(|encoding| encoding.as_str().to_string()),
+            stderr_encoding: stderr_encoding.map(|encoding| encoding.as_str().to_string()),
+            stdout_truncated,
+            stderr_truncated,
+            exit_code,
+            signal,
+            exit_description,
+            interactor: interactor.map(Into::into),
+            test_cases: test_cases.into_iter().map(Into::into).collect(),
+            test_summary: test_summary.map(Into::into),
+            time,
+            memory,
+            metrics: metrics.map(Into::into),
+            artifacts: artifacts.unwrap_or_default().into_iter().map(Into::into).collect(),
+            workdir_listing: workdir_listing.unwrap_or_default().into_iter().map(Into::into).collect(),
+            executed_commands: executed_commands.into_iter().map(Into::into).collect(),
+            failure_reason,
+            compile_cache_hit,
+            failed_run_indices,
+            warnings,
+            attempts,
+            limits: limits.map(Into::into),
+            options: options.map(Into::into),
+            metadata: metadata.unwrap_or_default(),
+            runner: runner.map(Into::into),
+            started_at: started_at.map(|at| at.to_rfc3339()),
+            created_at: created_at.to_rfc3339(),
+            finished_at: finished_at.map(|at| at.to_rfc3339()),
+            timeline: timeline.into_iter().map(Into::into).collect(),
+            queue_wait_ms,
+            overhead_ms,
+            phases: phases.map(Into::into),
+            setup_bytes,
+        }
+    }
+}
+
+impl TryFrom<proto::ExecutionResult> for ExecutionResult {
+    type Error = FieldError;
+    
+    /// An empty artifact list becomes None
+    fn try_from(result: proto::ExecutionResult) -> Result<Self, FieldError> {
+        let proto::ExecutionResult {
+            id,
+            status,
+            stdout,
+            stderr,
+            compile_output,
+            diagnostics,
+            stdout_bytes,
+            stdout_url,
+            stderr_bytes,
+            stderr_url,
+            stdout_encoding,
+            stderr_encoding,
+            stdout_truncated,
+            stderr_truncated,
+            exit_code,
+            signal,
+            exit_description,
+            interactor,
+            test_cases,
+            test_summary,
+            time,
+            memory,
+            metrics,
+            artifacts,
+            workdir_listing,
+            executed_commands,
+            failure_reason,
+            compile_cache_hit,
+            failed_run_indices,
+            warnings,
+            attempts,
+            limits,
+            options,
+            metadata,
+            runner,
+            started_at,
+            created_at,
+            finished_at,
+            timeline,
+            queue_wait_ms,
+            overhead_ms,
+            phases,
+            setup_bytes,
+        } = result;
+        
+        Ok(Self {
+            id,
+            status: ExecutionState::try_from(status)?,
+            stdout,
+            stderr,
+            compile_output,
+            diagnostics: diagnostics.into_iter().map(TryInto::try_into).collect::<Result<_, _>>()?,
+            stdout_bytes,
+            stdout_url,
+            stderr_bytes,
+            stderr_url,
+            stdout_encoding: stdout_encoding.and_then(|encoding| encoding.parse().ok()),
+            stderr_encoding: stderr_encoding.and_then(|encoding| encoding.parse().ok()),
+            stdout_truncated,
+            stderr_truncated,
+            exit_code,
+            signal,
+            exit_description,
+            interactor: interactor.map(Into::into),
+            test_cases: test_cases.into_iter().map(TryInto::try_into).collect::<Result<_, _>>()?,
+            test_summary: test_summary.map(Into::into),
+            time,
+            memory,
+            metrics: metrics.map(Into::into),
+            artifacts: (!artifacts.is_empty()).then(|| artifacts.into_iter().map(Into::into).collect()),
+            workdir_listing: (!workdir_listing.is_empty()).then(|| workdir_listing.into_iter().map(Into::into).collect()),
+            executed_commands: executed_commands.into_iter().map(TryInto::try_into).collect::<Result<_, _>>()?,
+            failure_reason,
+            compile_cache_hit,
+            failed_run_indices,
+            warnings,
+            attempts,
+            limits: limits.map(Into::into),
+            options: options.map(Into::into),
+            metadata: (!metadata.is_empty()).then_some(metadata),
+            runner: runner.map(Into::into),
+            started_at: started_at.as_deref().map(|at| parse_timestamp("started_at", at)).transpose()?,
+            created_at: parse_timestamp("created_at", &created_at)?,
+            finished_at: finished_at.as_deref().map(|at| parse_timestamp("finished_at", at)).transpose()?,
+            timeline: timeline.into_iter().map(TryInto::try_into).collect::<Result<_, _>>()?,
+            queue_wait_ms,
+            overhead_ms,
+            phases: phases.map(Into::into),
+            setup_bytes,
+        })
+    }
+}
+
+impl From<Diagnostic> for proto::Diagnostic {
+    fn from(diagnostic: Diagnostic) -> Self {
+        let Diagnostic { file, line, column, severity, message } = diagnostic;
+        let severity = match severity {
+            DiagnosticSeverity::Error => proto::DiagnosticSeverity::Error,
+            DiagnosticSeverity::Warning => proto::DiagnosticSeverity::Warning,
+            DiagnosticSeverity::Note => proto::DiagnosticSeverity::Note,
+        };
+        Self { file, line, column, severity: severity.into(), message }
+    }
+}
+
+impl TryFrom<proto::Diagnostic> for Diagnostic {
+    type Error = FieldError;
+    
+    fn try_from(diagnostic: proto::Diagnostic) -> Result<Self, FieldError> {
+        let proto::Diagnostic { file, line, column, severity, message } = diagnostic;
+        let severity = match proto::DiagnosticSeverity::try_from(severity) {
+            Ok(proto::DiagnosticSeverity::Error) => DiagnosticSeverity::Error,
+            Ok(proto::DiagnosticSeverity::Warning) => DiagnosticSeverity::Warning,
+            Ok(proto::DiagnosticSeverity::Note) => DiagnosticSeverity::Note,
+            Ok(proto::DiagnosticSeverity::Unspecified) | Err(_) => {
+                return Err(FieldError::new("diagnostics", format!("unknown diagnostic severity {}", severity)));
+            }
+        };
+        Ok(Self { file, line, column, severity, message })
+    }
+}
+
+impl From<TimelineEntry> for proto::TimelineEntry {
+    fn from(entry: TimelineEntry) -> Self {
+        let TimelineEntry { stage, at } = entry;
+        let stage = match stage {
+            TimelineStage::Enqueued => proto::TimelineStage::Enqueued,
+            TimelineStage::Dequeued => proto::TimelineStage::Dequeued,
+            TimelineStage::SandboxReady => proto::TimelineStage::SandboxReady,
+            TimelineStage::CompileStarted => proto::TimelineStage::CompileStarted,
+            TimelineStage::CompileFinished => proto::TimelineStage::CompileFinished,
+            TimelineStage::RunStarted => proto::TimelineStage::RunStarted,
+            TimelineStage::RunFinished => proto::TimelineStage::RunFinished,
+            TimelineStage::Finalized => proto::TimelineStage::Finalized,
+        };
+        Self { stage: stage.into(), at: at.to_rfc3339() }
+    }
+}
+
+impl TryFrom<proto::TimelineEntry> for TimelineEntry {
+    type Error = FieldError;
+    
+    fn try_from(entry: proto::TimelineEntry) -> Result<Self, FieldError> {
+        let proto::TimelineEntry { stage, at } = entry;
+        let stage = match proto::TimelineStage::try_from(stage) {
+            Ok(proto::TimelineStage::Enqueued) => TimelineStage::Enqueued,
+            Ok(proto::TimelineStage::Dequeued) => TimelineStage::Dequeued,
+            Ok(proto::TimelineStage::SandboxReady) => TimelineStage::SandboxReady,
+            Ok(proto::TimelineStage::CompileStarted) => TimelineStage::CompileStarted,
+            Ok(proto::TimelineStage::CompileFinished) => TimelineStage::CompileFinished,
+            Ok(proto::TimelineStage::RunStarted) => TimelineStage::RunStarted,
+            Ok(proto::TimelineStage::RunFinished) => TimelineStage::RunFinished,
+            Ok(proto::TimelineStage::Finalized) => TimelineStage::Finalized,
+            Ok(proto::TimelineStage::Unspecified) | Err(_) => {
+                return Err(FieldError::new("timeline", format!("unknown timeline stage {}", stage)));
+            }
+        };
+        Ok(Self { stage, at: parse_timestamp("timeline", &at)? })
+    }
+}
+
+impl From<CancelResponse> for proto::CancelExecutionResponse {
+    fn from(response: CancelResponse) -> Self {
+        let CancelResponse { cancelled, message } = response;
+        Self { cancelled, message }
+    }
+}
+
+/// RFC 3339 timestamp from a gRPC message
+fn parse_timestamp(field: &str, value: &str) -> Result<DateTime<Utc>, FieldError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|err| FieldError::new(field, format!("must be an RFC 3339 timestamp: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    
+    #[test]
+    fn severity_follows_the_documented_order() {
+        let worst_first = [
+            ExecutionState::InternalError,
+            ExecutionState::Cancelled,
+            ExecutionState::MemoryLimitExceeded,
+            ExecutionState::TimeLimitExceeded,
+            ExecutionState::OutputLimitExceeded,
+            ExecutionState::RuntimeError,
+            ExecutionState::WrongAnswer,
+            ExecutionState::CompilationError,
+            ExecutionState::Completed,
+        ];
+        for pair in worst_first.windows(2) {
+            assert!(pair[0].severity() > pair[1].severity(), "{:?} should outrank {:?}", pair[0], pair[1]);
+        }
+    }
+    
+    #[test]
+    fn unfinished_states_have_no_severity() {
+        for state in [ExecutionState::Scheduled, ExecutionState::Queued, ExecutionState::Processing, ExecutionState::Running] {
+            assert!(!state.is_terminal());
+            assert_eq!(state.severity(), ExecutionState::Completed.severity());
+        }
+    }
+    
+    #[test]
+    fn implausibly_small_limits_warn_about_units() {
+        let request: ExecutionRequest = serde_json::from_value(serde_json::json!({
+            "language": "c", "source_code": "", "memory_limit": 256, "stack_limit": 8192,
+        })).unwrap();
+        let warnings = ResourceLimits::from_request(&request, &ResourceLimits::default()).warnings();
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].starts_with("memory limit of 256 bytes is implausibly small"), "{}", warnings[0]);
+        assert!(warnings[1].contains("use stack_limit_kb for kilobytes"), "{}", warnings[1]);
+        
+        // The defaults, and sizes given in kilobytes once resolved, are fine
+        assert!(ResourceLimits::default().warnings().is_empty());
+        let request = ExecutionRequest { memory_limit: Some(65536 * 1024), stack_limit: Some(8192 * 1024), ..request };
+        assert!(ResourceLimits::from_request(&request, &ResourceLimits::default()).warnings().is_empty());
+    }
+    
+    /// Requests stored and read back compare equal to what was submitted
+    fn round_trip(body: serde_json::Value) -> ExecutionRequest {
+        let request: ExecutionRequest = serde_json::from_value(body).unwrap();
+        let stored = serde_json::to_value(&request).unwrap();
+        assert_eq!(serde_json::from_value::<ExecutionRequest>(stored).unwrap(), request);
+        request
+    }
+    
+    #[test]
+    fn minimal_requests_round_trip() {
+        let request = round_trip(serde_json::json!({"language": "python", "source_code": "print(1)"}));
+        assert_eq!(request, ExecutionRequest {
+            language: "python".to_string(),
+            source_code: "print(1)".to_string(),
+            ..Default::default()
+        });
+    }
+    
+    /// A request with every field set
+    fn maximal() -> serde_json::Value {
+        serde_json::from_str(r#"{
+            "id": "job-1",
+            "language": "cpp",
+            "language_id": 54,
+            "source_code": "int main() {}",
+            "stdin": "1 2",
+            "stdin_base64": "MSAy",
+            "expected_output": "3",
+            "compiler_options": "-O2",
+            "command_line_arguments": "--fast",
+            "source_file_name": "main.cpp",
+            "env": {"GREETING": "hello"},
+            "artifact_patterns": ["*.txt"],
+            "preserve_workdir": true,
+            "list_workdir": true,
+            "priority": 7,
+            "max_retries": 2,
+            "run_at": "2026-01-01T00:00:00Z",
+            "base64_encoded": false,
+            "dedupe": true,
+            "profile": "contest",
+            "metadata": {"course": "cs101"},
+            "cpu_time_limit": 2.5,
+            "cpu_extra_time": 0.5,
+            "memory_limit": 268435456,
+            "memory_limit_kb": 262144,
+            "wall_time_limit": 5.0,
+            "stack_limit": 8388608,
+            "stack_limit_kb": 8192,
+            "max_processes_and_or_threads": 4,
+            "enable_per_process_and_thread_time_limit": true,
+            "enable_per_process_and_thread_memory_limit": false,
+            "max_file_size": 1048576,
+            "max_file_size_kb": 1024,
+            "workdir_quota": 16777216,
+            "cpu_affinity": [0, 1],
+            "nice": 5,
+            "redirect_stderr_to_stdout": true,
+            "enable_network": false,
+            "number_of_runs": 3,
+            "fresh_workdir_per_run": true,
+            "isolation": "strong",
+            "output_filter": "strip_ansi",
+            "normalize_newlines": true,
+            "callback_url": "https://example.com/hook",
+            "idempotency_key": "key-1",
+            "additional_files": "UEsFBgAAAAAAAAAAAAAAAAAAAAAAAA==",
+            "source_url": "https://example.com/main.cpp",
+            "additional_files_url": "https://example.com/files.zip",
+            "interactor": {"language": "python", "source_code": "print(1)"},
+            "test_cases": [{"stdin": "3", "expected_output": "3", "cpu_time_limit": 1.0, "wall_time_limit": 2.0, "memory_limit": 1048576}]
+        }"#).unwrap()
+    }
+    
+    #[test]
+    fn every_field_keeps_its_name_and_shape() {
+        let body = maximal();
+        let stored = serde_json::to_value(round_trip(body.clone())).unwrap();
+        let missing: Vec<_> = stored.as_object().unwrap().iter().filter(|(_, value)| value.is_null()).map(|(key, _)| key).collect();
+        assert!(missing.is_empty(), "fixture leaves out {:?}", missing);
+        assert_eq!(stored, body);
+    }
+    
+    #[test]
+    fn judge0_style_requests_use_the_camel_case_aliases() {
+        let request = round_trip(serde_json::json!({
+            "language": "python",
+            "languageId": 71,
+            "sourceCode": "print(input())",
+            "stdin": "hi",
+            "expectedOutput": "hi",
+            "cpuTimeLimit": 2.0,
+            "memoryLimitKb": 128000,
+            "redirectStderrToStdout": true,
+            "testCases": [{"stdin": "a", "expectedOutput": "a"}],
+        }));
+        assert_eq!(request.language_id, Some(71));
+        assert_eq!(request.source_code, "print(input())");
+        assert_eq!(request.expected_output.as_deref(), Some("hi"));
+        assert_eq!(request.cpu_time_limit, Some(2.0));
+        assert_eq!(request.memory_limit_kb, Some(128000));
+        assert_eq!(request.redirect_stderr_to_stdout, Some(true));
+        assert_eq!(request.test_cases.unwrap()[0].expected_output.as_deref(), Some("a"));
+    }
+    
+    #[test]
+    fn every_field_accepts_its_camel_case_spelling() {
+        fn camel(object: &mut serde_json::Value) {
+            let fields = std::mem::take(object.as_object_mut().unwrap());
+            for (key, value) in fields {
+                let mut words = key.split('_');
+                let mut renamed = words.next().unwrap().to_string();
+                for word in words {
+                    renamed.push(word.as_bytes()[0].to_ascii_uppercase() as char);
+                    renamed.push_str(&word[1..]);
+                }
+                object[renamed] = value;
+            }
+        }
+        
+        let mut body = maximal();
+        camel(&mut body["interactor"]);
+        camel(&mut body["test_cases"][0]);
+        camel(&mut body);
+        assert!(body.get("sourceCode").is_some() && body["interactor"].get("sourceCode").is_some());
+        
+        let request: ExecutionRequest = serde_json::from_value(body).unwrap();
+        assert_eq!(request, serde_json::from_value(maximal()).unwrap());
+    }
+    
+    fn result_query(pairs: &[(&str, &str)]) -> Result<ResultQuery, Vec<FieldError>> {
+        ResultQuery::parse(&pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect())
+    }
+    
+    #[test]
+    fn include_output_is_read_leniently() {
+        assert!(result_query(&[]).unwrap().include_output);
+        for (value, expected) in [("False", false), ("YES", true), ("0", false), (" no ", false), ("1", true)] {
+            assert_eq!(result_query(&[("include_output", value)]).unwrap().include_output, expected, "{}", value);
+        }
+    }
+    
+    #[test]
+    fn result_fields_accept_both_cases_and_report_every_mistake() {
+        let fields = result_query(&[("fields", "status, time,exitCode")]).unwrap().fields.unwrap();
+        assert_eq!(fields, ["status", "time", "exit_code"]);
+        
+        let errors = result_query(&[("include_output", "ture"), ("fields", "status,stdot")]).unwrap_err();
+        assert_eq!(errors.iter().map(|error| error.field.as_str()).collect::<Vec<_>>(), ["include_output", "fields"]);
+        assert!(errors[1].constraint.starts_with("unknown field `stdot`; valid fields: id, status,"), "{}", errors[1].constraint);
+        assert_eq!(result_query(&[("fields", " , ")]).unwrap_err()[0].constraint, "must name at least one field");
+    }
+    
+    #[test]
+    fn result_views_project_and_drop_output() {
+        let result: ExecutionResult = serde_json::from_value(serde_json::json!({
+            "id": "job-1", "status": "completed", "stdout": "x".repeat(1 << 20), "time": 0.25,
+            "created_at": "2030-01-01T00:00:00Z", "compile_cache_hit": false, "warnings": ["deprecated option"],
+        })).unwrap();
+        let view = |include_output: bool, fields: Option<&[&str]>| {
+            let fields = fields.map(|fields| fields.iter().map(|field| field.to_string()).collect());
+            serde_json::to_value(ResultView { result: Arc::new(result.clone()), include_output, fields }).unwrap()
+        };
+        
+        assert_eq!(view(true, Some(&["status", "time"])), serde_json::json!({"status": "completed", "time": 0.25}));
+        assert_eq!(view(false, Some(&["stdout", "time"])), serde_json::json!({"stdout": null, "time": 0.25}));
+        assert!(view(false, None)["stdout"].is_null());
+        
+        // Every field a full result has can be selected
+        let full = view(true, None);
+        assert_eq!(full["stdout"].as_str().unwrap().len(), 1 << 20);
+        assert!(full.as_object().unwrap().keys().all(|key| RESULT_FIELDS.contains(&key.as_str())));
+    }
+    
+    /// Timeline with each stage stamped this many milliseconds after a fixed start
+    fn timeline(stages: &[(TimelineStage, i64)]) -> Timeline {
+        let start = Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap();
+        let mut timeline = Timeline::default();
+        for (stage, ms) in stages {
+            timeline.stamp_at(*stage, start + chrono::Duration::milliseconds(*ms));
+        }
+        timeline
+    }
+    
+    #[test]
+    fn phases_split_the_time_on_the_worker() {
+        let timeline = timeline(&[
+            (TimelineStage::Enqueued, 0),
+            (TimelineStage::Dequeued, 50),
+            (TimelineStage::SandboxReady, 170),
+            (TimelineStage::CompileStarted, 180),
+            (TimelineStage::CompileFinished, 680),
+            (TimelineStage::RunStarted, 700),
+            (TimelineStage::RunFinished, 900),
+            (TimelineStage::Finalized, 940),
+        ]);
+        let phases = timeline.phases().unwrap();
+        assert_eq!((phases.setup_ms, phases.compile_ms, phases.run_ms, phases.teardown_ms), (150.0, 500.0, 200.0, 40.0));
+        assert_eq!(timeline.overhead_ms(), Some(phases.setup_ms + phases.teardown_ms));
+    }
+    
+    #[test]
+    fn phases_cover_only_the_latest_finished_attempt() {
+        let retried = timeline(&[
+            (TimelineStage::Enqueued, 0),
+            (TimelineStage::Dequeued, 10),
+            (TimelineStage::RunStarted, 20),
+            (TimelineStage::Enqueued, 1000),
+            (TimelineStage::Dequeued, 1100),
+            (TimelineStage::RunStarted, 1130),
+            (TimelineStage::RunFinished, 1230),
+            (TimelineStage::RunStarted, 1250),
+            (TimelineStage::RunFinished, 1350),
+            (TimelineStage::Finalized, 1360),
+        ]);
+        let phases = retried.phases().unwrap();
+        // The gap between the two runs is setup for the second
+        assert_eq!((phases.setup_ms, phases.compile_ms, phases.run_ms, phases.teardown_ms), (50.0, 0.0, 200.0, 10.0));
+        
+        let unfinished = timeline(&[(TimelineStage::Enqueued, 0), (TimelineStage::Dequeued, 10), (TimelineStage::RunStarted, 20)]);
+        assert_eq!(unfinished.phases(), None);
+    }
+    
+    fn list_query(pairs: &[(&str, &str)]) -> Result<ExecutionListQuery, Vec<FieldError>> {
+        ExecutionListQuery::parse(&pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect())
+    }
+    
+    #[test]
+    fn list_queries_collect_every_metadata_filter() {
+        let parsed = list_query(&[("metadata.user_id", "42"), ("metadata.assignment", "hw-3"), ("status", "completed")]).unwrap();
+        assert_eq!(parsed.metadata["user_id"], "42");
+        assert_eq!(parsed.metadata.len(), 2);
+        assert_eq!(parsed.status, Some(ExecutionState::Completed));
+        assert_eq!(parsed.limit, 100);
+        
+        let errors = list_query(&[("limit", "0"), ("status", "done"), ("metadata.", "1")]).unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["status", "limit", "metadata."]);
+    }
+    
+    #[test]
+    fn runners_name_the_instance_worker_and_backend() {
+        let runner = Runner::new("engine-eu-1", 3, ExecutionBackend::Docker);
+        assert_eq!(serde_json::to_value(&runner).unwrap(), serde_json::json!({
+            "worker_id": "engine-eu-1-3",
+            "instance": "engine-eu-1",
+            "worker": 3,
+            "engine_version": env!("CARGO_PKG_VERSION"),
+            "backend": "docker",
+        }));
+        assert_eq!(Runner::from(proto::Runner::from(runner.clone())), runner);
+        
+        for backend in [ExecutionBackend::Process, ExecutionBackend::Docker, ExecutionBackend::Isolate] {
+            assert_eq!(backend.to_string().parse::<ExecutionBackend>(), Ok(backend));
+        }
+        assert_eq!(" Native ".parse::<ExecutionBackend>(), Ok(ExecutionBackend::Process));
+        assert!("firecracker".parse::<ExecutionBackend>().is_err());
+    }
+    
+    #[test]
+    fn runs_get_a_fresh_workdir_unless_asked_otherwise() {
+        assert!(ExecutionOptions::from_request(&ExecutionRequest::default()).fresh_workdir_per_run);
+        let shared = ExecutionRequest { fresh_workdir_per_run: Some(false), ..Default::default() };
+        assert!(!ExecutionOptions::from_request(&shared).fresh_workdir_per_run);
+        
+        // Options stored before the field existed came from a fresh build per run
+        let mut stored = serde_json::to_value(ExecutionOptions { fresh_workdir_per_run: false, ..Default::default() }).unwrap();
+        stored.as_object_mut().unwrap().remove("fresh_workdir_per_run");
+        assert!(serde_json::from_value::<ExecutionOptions>(stored).unwrap().fresh_workdir_per_run);
+    }
+    
+    #[test]
+    fn executed_commands_survive_the_grpc_conversion() {
+        for phase in [CommandPhase::Compile, CommandPhase::Run, CommandPhase::InteractorCompile, CommandPhase::Interactor] {
+            let command = ExecutedCommand { phase, argv: vec!["./main".to_string(), "alpha".to_string()], duration_ms: 12, exit_code: -1 };
+            assert_eq!(ExecutedCommand::try_from(proto::ExecutedCommand::from(command.clone())).unwrap(), command);
+        }
+        
+        let unspecified = proto::ExecutedCommand { phase: proto::CommandPhase::Unspecified.into(), ..Default::default() };
+        assert_eq!(ExecutedCommand::try_from(unspecified).unwrap_err().field, "executed_commands");
+        assert_eq!(serde_json::to_value(CommandPhase::InteractorCompile).unwrap(), "interactor_compile");
+    }
+    
+    #[test]
+    fn rerun_overrides_replace_only_what_they_name() {
+        let mut request: ExecutionRequest = serde_json::from_value(serde_json::json!({
+            "language": "python",
+            "source_code": "print(input())",
+            "stdin_base64": "AP8K",
+            "memory_limit": 1048576,
+            "wall_time_limit": 3.0,
+        })).unwrap();
+        let overrides: RerunOverrides = serde_json::from_value(serde_json::json!({
+            "stdin": "new input",
+            "memory_limit_kb": 2048,
+            "number_of_runs": 3,
+        })).unwrap();
+        overrides.apply(&mut request);
+        
+        assert_eq!((request.stdin.as_deref(), request.stdin_base64.as_deref()), (Some("new input"), None));
+        assert_eq!((request.memory_limit, request.memory_limit_kb), (None, Some(2048)));
+        assert_eq!((request.wall_time_limit, request.number_of_runs), (Some(3.0), Some(3)));
+        assert_eq!(request.source_code, "print(input())");
+        
+        RerunOverrides::default().apply(&mut request);
+        assert_eq!(request.stdin.as_deref(), Some("new input"));
+    }
+    
+    #[test]
+    fn results_serialize_the_limits_and_options_applied() {
+        let request: ExecutionRequest = serde_json::from_value(serde_json::json!({
+            "language": "python", "source_code": "print(1)", "redirect_stderr_to_stdout": true, "number_of_runs": 3, "wall_time_limit": 2.5,
+        })).unwrap();
+        let mut result: ExecutionResult = serde_json::from_value(serde_json::json!({
+            "id": "job-1", "status": "completed", "created_at": "2030-01-01T00:00:00Z", "compile_cache_hit": false,
+        })).unwrap();
+        assert!(result.limits.is_none() && result.options.is_none(), "results stored before the fields existed still load");
+        result.limits = Some(ResourceLimits::from_request(&request, &ResourceLimits::default()));
+        result.options = Some(ExecutionOptions::from_request(&request));
+        
+        let json = serde_json::to_value(&result).unwrap();
+        let limits = &json["limits"];
+        for field in ["cpu_time", "wall_time", "memory", "stack_limit", "file_size", "workdir_quota", "processes"] {
+            assert!(limits.get(field).is_some(), "limits.{} missing from {}", field, limits);
+        }
+        assert_eq!(limits["wall_time"], 2.5);
+        let options = &json["options"];
+        assert_eq!(
+            (&options["redirect_stderr_to_stdout"], &options["enable_network"], &options["number_of_runs"], &options["stop_on_first_failure"]),
+            (&true.into(), &false.into(), &3.into(), &true.into()),
+        );
+        
+        let stored: ExecutionResult = serde_json::from_value(json).unwrap();
+        assert_eq!(stored.limits.unwrap().wall_time, 2.5);
+        assert_eq!(stored.options.unwrap().number_of_runs, 3);
+    }
+    
+    #[test]
+    fn requests_survive_the_grpc_conversion() {
+        let body = maximal();
+        let request: ExecutionRequest = serde_json::from_value(body.clone()).unwrap();
+        let back = ExecutionRequest::try_from(proto::ExecutionRequest::from(request)).unwrap();
+        assert_eq!(serde_json::to_value(back).unwrap(), body);
+        
+        let minimal = serde_json::json!({"language": "python", "source_code": "print(1)"});
+        let request: ExecutionRequest = serde_json::from_value(minimal).unwrap();
+        let back = ExecutionRequest::try_from(proto::ExecutionRequest::from(request.clone())).unwrap();
+        assert_eq!(serde_json::to_value(back).unwrap(), serde_json::to_value(request).unwrap());
+    }
+    
+    #[test]
+    fn every_state_survives_the_grpc_conversion() {
+        let states: Vec<_> = (1..).map_while(|code: u32| ExecutionState::try_from(code).ok()).collect();
+        assert_eq!(states.len(), 13);
+        for state in states {
+            let wire = proto::ExecutionState::from(state.clone()) as i32;
+            assert_eq!(ExecutionState::try_from(wire).unwrap(), state);
+        }
+        assert!(ExecutionState::try_from(proto::ExecutionState::Unspecified as i32).is_err());
+        assert!(ExecutionState::try_from(999).is_err());
+    }
+    
+    #[test]
+    fn results_survive_the_grpc_conversion() {
+        let body: serde_json::Value = serde_json::from_str(r#"{
+            "id": "job-1",
+            "status": "wrong_answer",
+            "status_code": 13,
+            "stdout": "4\n",
+            "stderr": "warning\n",
+            "compile_output": "main.c:1:1: warning: unused",
+            "diagnostics": [{"file": "main.c", "line": 1, "column": 1, "severity": "warning", "message": "unused"}],
+            "stdout_bytes": 2,
+            "stdout_url": "/v1/result/job-1/stdout",
+            "stderr_bytes": 8,
+            "stderr_url": "/v1/result/job-1/stderr",
+            "stdout_encoding": "utf-8",
+            "stderr_encoding": "binary",
+            "stdout_truncated": true,
+            "stderr_truncated": true,
+            "exit_code": 0,
+            "signal": null,
+            "exit_description": "exited with code 0",
+            "time": 0.25,
+            "memory": 1048576,
+            "metrics": {
+                "cpu_time": 0.25, "user_time": 0.2, "system_time": 0.05, "memory_peak": 1048576, "wall_time": 0.5,
+                "minor_page_faults": 10, "major_page_faults": 1, "voluntary_context_switches": 3,
+                "involuntary_context_switches": 2, "read_bytes": 4096, "written_bytes": 512
+            },
+            "artifacts": [{"name": "out.txt", "size": 3, "content_base64": "YWJj", "truncated": false}],
+            "workdir_listing": [{"path": "main.c", "size": 40}],
+            "executed_commands": [{"phase": "compile", "argv": ["gcc", "main.c"], "duration_ms": 120, "exit_code": 0}],
+            "failure_reason": "expected 5",
+            "compile_cache_hit": true,
+            "failed_run_indices": [1],
+            "warnings": ["memory_limit looks like kilobytes"],
+            "attempts": 2,
+            "limits": {
+                "cpu_time": 2.0, "cpu_extra_time": 0.5, "memory": 268435456, "wall_time": 5.0, "stack_limit": 8388608,
+                "file_size": 1048576, "workdir_quota": 16777216, "processes": 4, "enable_per_process_time_limit": true,
+                "enable_per_process_memory_limit": false, "cpu_affinity": [0, 1], "nice": 5
+            },
+            "options": {
+                "redirect_stderr_to_stdout": true, "enable_network": false, "number_of_runs": 2, "fresh_workdir_per_run": true,
+                "stop_on_first_failure": false, "isolation": "strong", "output_filter": "strip_ansi", "normalize_newlines": true
+            },
+            "metadata": {"course": "cs101"},
+            "runner": {"worker_id": "host-a/3", "instance": "host-a", "worker": 3, "engine_version": "1.0.0", "backend": "docker"},
+            "started_at": "2026-01-01T00:00:01Z",
+            "created_at": "2026-01-01T00:00:00Z",
+            "finished_at": "2026-01-01T00:00:02Z",
+            "timeline": [{"stage": "enqueued", "at": "2026-01-01T00:00:00Z"}, {"stage": "finalized", "at": "2026-01-01T00:00:02Z"}],
+            "queue_wait_ms": 12.5,
+            "overhead_ms": 3.0,
+            "phases": {"setup_ms": 1.0, "compile_ms": 120.0, "run_ms": 250.0, "teardown_ms": 2.0},
+            "setup_bytes": 40,
+            "interactor": {"exit_code": 0, "signal": null, "exit_description": "exited with code 0", "stderr": "", "time": 0.1},
+            "test_cases": [{
+                "status": "wrong_answer", "stdout": "4", "stderr": "", "exit_code": 0, "signal": null,
+                "exit_description": "exited with code 0", "time": 0.1, "memory": 1024, "failure_reason": "expected 5"
+            }],
+            "test_summary": {"passed": 0, "failed": 1, "skipped": 0, "first_failure": 0}
+        }"#).unwrap();
+        let result: ExecutionResult = serde_json::from_value(body.clone()).unwrap();
+        let back = ExecutionResult::try_from(proto::ExecutionResult::from(result)).unwrap();
+        assert_eq!(serde_json::to_value(back).unwrap(), body);
+    }
+    
+    #[test]
+    fn metrics_add_up_over_runs_and_stay_null_where_unknown() {
+        let first = ExecutionMetrics {
+            cpu_time: Some(0.5),
+            user_time: Some(0.4),
+            system_time: Some(0.1),
+            memory_peak: Some(1000),
+            minor_page_faults: Some(10),
+            read_bytes: None,
+            ..Default::default()
+        };
+        let second = ExecutionMetrics {
+            cpu_time: Some(0.25),
+            user_time: Some(0.2),
+            system_time: Some(0.05),
+            memory_peak: Some(3000),
+            minor_page_faults: Some(5),
+            read_bytes: Some(64),
+            ..Default::default()
+        };
+        let total = first.combine(&second);
+        assert_eq!(total.cpu_time, Some(0.75));
+        assert_eq!(total.memory_peak, Some(3000));
+        assert_eq!(total.minor_page_faults, Some(15));
+        // Known for one run only is not known for the job
+        assert_eq!(total.read_bytes, None);
+        
+        let json = serde_json::to_value(&total).unwrap();
+        assert!(json["read_bytes"].is_null() && json.get("read_bytes").is_some(), "{}", json);
+        assert_eq!(json["major_page_faults"], serde_json::Value::Null);
+    }
+    
+    /// Every state with its documented code; a new variant fails to compile here until it is given one
+    fn documented_code(state: &ExecutionState) -> u32 {
+        match state {
+            ExecutionState::Scheduled => 1,
+            ExecutionState::Queued => 2,
+            ExecutionState::Processing => 3,
+            ExecutionState::Running => 4,
+            ExecutionState::Completed => 5,
+            ExecutionState::CompilationError => 6,
+            ExecutionState::RuntimeError => 7,
+            ExecutionState::TimeLimitExceeded => 8,
+            ExecutionState::MemoryLimitExceeded => 9,
+            ExecutionState::OutputLimitExceeded => 10,
+            ExecutionState::Cancelled => 11,
+            ExecutionState::InternalError => 12,
+            ExecutionState::WrongAnswer => 13,
+        }
+    }
+    
+    #[test]
+    fn status_codes_are_stable_and_round_trip() {
+        let states: Vec<ExecutionState> = (0..=20).filter_map(|code: u32| ExecutionState::try_from(code).ok()).collect();
+        assert_eq!(states.len(), 13);
+        for state in states {
+            let code = state.code();
+            assert_eq!(code, documented_code(&state), "{:?}", state);
+            assert_eq!(u32::from(state.clone()), code);
+            assert_eq!(ExecutionState::try_from(code).unwrap(), state);
+            assert_eq!(code.to_string().parse::<ExecutionState>().unwrap(), state);
+            assert_eq!(state.as_str().parse::<ExecutionState>().unwrap(), state);
+        }
+        
+        assert_eq!(ExecutionState::try_from(0u32).unwrap_err().constraint, "unknown execution state 0");
+        assert_eq!(ExecutionState::try_from(99u32).unwrap_err().constraint, "unknown execution state 99");
+        assert_eq!("tle".parse::<ExecutionState>().unwrap_err().constraint, "unknown execution state 'tle'");
+    }
+    
+    #[test]
+    fn results_serialize_their_code_and_load_from_either_form() {
+        let result = |fields: serde_json::Value| {
+            let mut json = serde_json::json!({"id": "job", "created_at": "2030-01-01T00:00:00Z", "compile_cache_hit": false});
+            json.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+            serde_json::from_value::<ExecutionResult>(json)
+        };
+        
+        let tle = result(serde_json::json!({"status": "time_limit_exceeded", "status_code": 8})).unwrap();
+        let json = serde_json::to_value(&tle).unwrap();
+        assert_eq!((&json["status"], &json["status_code"]), (&serde_json::json!("time_limit_exceeded"), &serde_json::json!(8)));
+        let view = serde_json::to_value(ResultView { result: Arc::new(tle), include_output: false, fields: None }).unwrap();
+        assert_eq!(view["status_code"], 8);
+        
+        // Stored before codes existed, or stored only as a code
+        assert_eq!(result(serde_json::json!({"status": "wrong_answer"})).unwrap().status, ExecutionState::WrongAnswer);
+        assert_eq!(result(serde_json::json!({"status_code": 13})).unwrap().status, ExecutionState::WrongAnswer);
+        assert!(result(serde_json::json!({"status_code": 99})).unwrap_err().to_string().contains("unknown execution state 99"));
+        assert!(result(serde_json::json!({})).is_err());
+    }
 }
@@ -0,0 +1,1078 @@
+use crate::config::Profile;
+use crate::sandbox::available_cores;
+use crate::types::{CpuAffinity, ExecutionRequest, FieldError, Interactor, LimitCeilings, LimitOverrides, TestCase, DEFAULT_PRIORITY, MAX_PRIORITY};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use utoipa::ToSchema;
+
+/// Niceness a job may ask for; negative values would put it ahead of the engine itself
+pub const NICE_RANGE: RangeInclusive<i32> = 0..=19;
+
+/// Most entries a request's metadata may have
+pub const MAX_METADATA_KEYS: usize = 32;
+
+/// Longest metadata key, in bytes
+pub const MAX_METADATA_KEY_BYTES: usize = 64;
+
+/// Longest metadata value, in bytes
+pub const MAX_METADATA_VALUE_BYTES: usize = 512;
+
+/// Most bytes of metadata, keys and values together
+pub const MAX_METADATA_BYTES: usize = 4096;
+
+/// Most variables a request's env may set
+pub const MAX_ENV_VARS: usize = 64;
+
+/// Longest env value, in bytes
+pub const MAX_ENV_VALUE_BYTES: usize = 4096;
+
+/// Variables requests may not set, since they control how the toolchain is found and loaded
+pub const RESERVED_ENV_VARS: &[&str] = &["PATH", "LD_PRELOAD", "LD_LIBRARY_PATH"];
+
+/// How far in the past a run_at may be before it is treated as a client bug rather than clock skew
+const RUN_AT_SKEW_TOLERANCE_SECS: i64 = 5;
+
+/// What to do with requested limits above the server ceilings
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitsMode {
+    /// Fail the request with 422
+    #[default]
+    Reject,
+    /// Lower the value to the ceiling
+    Clamp,
+}
+
+/// What the executor can do for a request, checked alongside the request's own fields
+#[derive(Debug, Clone, Copy)]
+pub struct Support {
+    /// The language is known
+    pub language: bool,
+    /// The language was not taken out of service by a failed warm-up
+    pub in_service: bool,
+    /// The language was not disabled by an operator
+    pub enabled: bool,
+    /// The requested isolation level is available for that language
+    pub isolation: bool,
+    /// The language's own maxima, which lower the server ceilings
+    pub max_limits: LimitOverrides,
+    /// The language's own source_code ceiling, which replaces the server's
+    pub max_source_bytes: Option<usize>,
+    /// The interactor's language is known, or there is no interactor
+    pub interactor_language: bool,
+    /// Both programs of an interactive run would be native processes, or there is no interactor
+    pub interactive: bool,
+}
+
+/// Server-side limits that submissions are checked against
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RequestValidator {
+    /// What to do with limits above a ceiling
+    pub mode: LimitsMode,
+    /// Refuse submissions with fields the API doesn't know, such as a misspelt limit
+    pub reject_unknown_fields: bool,
+    /// Largest source_code, unless its language sets its own
+    pub max_source_bytes: usize,
+    /// Largest additional_files ZIP, decoded
+    pub max_additional_files_bytes: u64,
+    /// Largest cpu_time_limit
+    pub max_cpu_time: f64,
+    /// Largest wall_time_limit
+    pub max_wall_time: f64,
+    /// Largest memory_limit
+    pub max_memory: u64,
+    /// Largest stack_limit
+    pub max_stack: u64,
+    /// Largest max_processes_and_or_threads
+    pub max_processes: u32,
+    /// Largest max_file_size
+    pub max_file_size: u64,
+    /// Largest workdir_quota
+    pub max_workdir_quota: u64,
+    /// Largest number_of_runs
+    pub max_runs: u32,
+    /// Most test_cases in one request
+    pub max_test_cases: u32,
+    /// Largest max_retries
+    pub max_retries: u32,
+    /// Highest priority callers get without an override
+    pub max_client_priority: u8,
+    /// Per API key overrides
+    pub priority_caps: HashMap<String, u8>,
+}
+
+impl Default for RequestValidator {
+    fn default() -> Self {
+        Self {
+            mode: LimitsMode::Reject,
+            reject_unknown_fields: false,
+            max_source_bytes: 256 * 1024, // 256KB
+            max_additional_files_bytes: 16 * 1024 * 1024, // 16MB
+            max_cpu_time: 15.0,
+            max_wall_time: 20.0,
+            max_memory: 512 * 1024 * 1024, // 512MB
+            max_stack: 128 * 1024 * 1024, // 128MB
+            max_processes: 64,
+            max_file_size: 16 * 1024 * 1024, // 16MB
+            max_workdir_quota: 256 * 1024 * 1024, // 256MB
+            max_runs: 20,
+            max_test_cases: 100,
+            max_retries: 3,
+            max_client_priority: DEFAULT_PRIORITY,
+            priority_caps: HashMap::new(),
+        }
+    }
+}
+
+impl RequestValidator {
+    /// The server ceilings, lowered to a language's own maxima
+    pub fn ceilings(&self, language: &LimitOverrides) -> LimitCeilings {
+        fn lower<T: PartialOrd>(ceiling: T, language: Option<T>) -> T {
+            match language {
+                Some(max) if max < ceiling => max,
+                _ => ceiling,
+            }
+        }
+        LimitCeilings {
+            cpu_time: lower(self.max_cpu_time, language.cpu_time),
+            wall_time: lower(self.max_wall_time, language.wall_time),
+            memory: lower(self.max_memory, language.memory),
+            stack_limit: lower(self.max_stack, language.stack_limit),
+            file_size: lower(self.max_file_size, language.file_size),
+            workdir_quota: lower(self.max_workdir_quota, language.workdir_quota),
+            processes: lower(self.max_processes, language.processes),
+        }
+    }
+    
+    /// Lower any requested limit above its ceiling when running in clamp mode
+    pub fn apply_ceilings(&self, request: &mut ExecutionRequest, support: Support) {
+        if self.mode != LimitsMode::Clamp {
+            return;
+        }
+        
+        let ceilings = self.ceilings(&support.max_limits);
+        clamp(&mut request.cpu_time_limit, ceilings.cpu_time);
+        clamp(&mut request.wall_time_limit, ceilings.wall_time);
+        clamp(&mut request.memory_limit, ceilings.memory);
+        clamp(&mut request.stack_limit, ceilings.stack_limit);
+        clamp(&mut request.max_file_size, ceilings.file_size);
+        clamp(&mut request.workdir_quota, ceilings.workdir_quota);
+        clamp(&mut request.max_processes_and_or_threads, ceilings.processes);
+        clamp(&mut request.number_of_runs, self.max_runs);
+        clamp(&mut request.max_retries, self.max_retries);
+        for case in request.test_cases.iter_mut().flatten() {
+            clamp(&mut case.cpu_time_limit, ceilings.cpu_time);
+            clamp(&mut case.wall_time_limit, ceilings.wall_time);
+            clamp(&mut case.memory_limit, ceilings.memory);
+        }
+    }
+    
+    /// Lower the requested priority to what the caller is allowed; admins may use any band
+    pub fn cap_priority(&self, request: &mut ExecutionRequest, api_key: Option<&str>, admin: bool) {
+        let cap = if admin {
+            MAX_PRIORITY
+        } else {
+            api_key.and_then(|key| self.priority_caps.get(key).copied())
+                .unwrap_or(self.max_client_priority)
+        };
+        
+        if let Some(priority) = &mut request.priority {
+            if *priority <= MAX_PRIORITY {
+                *priority = (*priority).min(cap);
+            }
+        }
+    }
+    
+    /// Check a submission, returning every violation found
+    pub fn validate(&self, request: &ExecutionRequest, support: Support) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        
+        // An omitted id is generated by the engine, but a blank one is a client bug
+        if !request.id.is_empty() && request.id.trim().is_empty() {
+            errors.push(FieldError::new("id", "must not be blank"));
+        }
+        
+        let max_source_bytes = support.max_source_bytes.unwrap_or(self.max_source_bytes);
+        if request.source_url.is_some() {
+            if !request.source_code.is_empty() {
+                errors.push(FieldError::new("source_url", "conflicts with source_code"));
+            }
+        } else if request.source_code.trim().is_empty() {
+            errors.push(FieldError::new("source_code", "must not be empty"));
+        } else if request.source_code.len() > max_source_bytes {
+            errors.push(FieldError::new(
+                "source_code",
+                format!("must be at most {} bytes", max_source_bytes),
+            ));
+        }
+        // Base64 grows the ZIP by a third; this is its decoded size
+        if let Some(zip) = &request.additional_files {
+            let size = (zip.trim_end_matches('=').len() as u64) * 3 / 4;
+            if size > self.max_additional_files_bytes {
+                errors.push(FieldError::new(
+                    "additional_files",
+                    format!("must be at most {} bytes once decoded", self.max_additional_files_bytes),
+                ));
+            }
+        }
+        
+        if let Some(stdin) = &request.stdin_base64 {
+            use base64::{engine::general_purpose, Engine as _};
+            
+            if request.stdin.is_some() {
+                errors.push(FieldError::new("stdin_base64", "conflicts with stdin"));
+            } else if general_purpose::STANDARD.decode(stdin).is_err() {
+                errors.push(FieldError::new("stdin_base64", "must be valid base64"));
+            }
+        }
+        
+        if !support.language {
+            errors.push(FieldError::new(
+                "language",
+                format!("unsupported language '{}'", request.language),
+            ));
+        } else if !support.enabled {
+            errors.push(FieldError::new(
+                "language",
+                format!("language '{}' is disabled on this engine", request.language),
+            ));
+        } else if !support.in_service {
+            errors.push(FieldError::new(
+                "language",
+                format!("language '{}' is out of service: its warm-up failed", request.language),
+            ));
+        } else if !support.isolation {
+            errors.push(FieldError::new(
+                "isolation",
+                "strong isolation is not available for this language on this engine",
+            ));
+        }
+        
+        if let Some(interactor) = &request.interactor {
+            if interactor.source_code.trim().is_empty() {
+                errors.push(FieldError::new("interactor.source_code", "must not be empty"));
+            } else if interactor.source_code.len() > self.max_source_bytes {
+                errors.push(FieldError::new(
+                    "interactor.source_code",
+                    format!("must be at most {} bytes", self.max_source_bytes),
+                ));
+            }
+            if !support.interactor_language {
+                errors.push(FieldError::new(
+                    "interactor.language",
+                    format!("unsupported language '{}'", interactor.language),
+                ));
+            } else if !support.interactive {
+                errors.push(FieldError::new("interactor", "interactive runs need both programs on the native backend"));
+            }
+            // The program's stdout is the interactor's input
+            if request.redirect_stderr_to_stdout.unwrap_or(false) {
+                errors.push(FieldError::new("redirect_stderr_to_stdout", "conflicts with interactor"));
+            }
+        }
+        
+        let ceilings = self.ceilings(&support.max_limits);
+        check_time(&mut errors, "cpu_time_limit", request.cpu_time_limit, ceilings.cpu_time);
+        check_time(&mut errors, "wall_time_limit", request.wall_time_limit, ceilings.wall_time);
+        check_size(&mut errors, "memory_limit", request.memory_limit, ceilings.memory);
+        check_size(&mut errors, "stack_limit", request.stack_limit, ceilings.stack_limit);
+        check_size(&mut errors, "max_file_size", request.max_file_size, ceilings.file_size);
+        check_size(&mut errors, "workdir_quota", request.workdir_quota, ceilings.workdir_quota);
+        check_size(
+            &mut errors,
+            "max_processes_and_or_threads",
+            request.max_processes_and_or_threads.map(u64::from),
+            u64::from(ceilings.processes),
+        );
+        check_size(
+            &mut errors,
+            "number_of_runs",
+            request.number_of_runs.map(u64::from),
+            u64::from(self.max_runs),
+        );
+        
+        if let Some(cases) = &request.test_cases {
+            if cases.is_empty() {
+                errors.push(FieldError::new("test_cases", "must list at least one case"));
+            } else if cases.len() > self.max_test_cases as usize {
+                errors.push(FieldError::new("test_cases", format!("must list at most {} cases", self.max_test_cases)));
+            }
+            for (index, case) in cases.iter().enumerate() {
+                check_time(&mut errors, &format!("test_cases[{}].cpu_time_limit", index), case.cpu_time_limit, ceilings.cpu_time);
+                check_time(&mut errors, &format!("test_cases[{}].wall_time_limit", index), case.wall_time_limit, ceilings.wall_time);
+                check_size(&mut errors, &format!("test_cases[{}].memory_limit", index), case.memory_limit, ceilings.memory);
+            }
+            // Each case brings its own input and answer, and is run once
+            for (field, set) in [
+                ("stdin", request.stdin.is_some()),
+                ("stdin_base64", request.stdin_base64.is_some()),
+                ("expected_output", request.expected_output.is_some()),
+                ("interactor", request.interactor.is_some()),
+                ("number_of_runs", request.number_of_runs.is_some_and(|runs| runs > 1)),
+            ] {
+                if set {
+                    errors.push(FieldError::new(field, "conflicts with test_cases"));
+                }
+            }
+        }
+        
+        // Zero is allowed here: it just disables retries
+        if request.max_retries.is_some_and(|retries| retries > self.max_retries) {
+            errors.push(FieldError::new("max_retries", format!("must be at most {}", self.max_retries)));
+        }
+        
+        // Slightly stale times run immediately, so client clocks a little ahead don't fail submissions
+        let earliest = chrono::Utc::now() - chrono::Duration::seconds(RUN_AT_SKEW_TOLERANCE_SECS);
+        if request.run_at.is_some_and(|run_at| run_at < earliest) {
+            errors.push(FieldError::new("run_at", "must not be in the past"));
+        }
+        
+        if request.nice.is_some_and(|nice| !NICE_RANGE.contains(&nice)) {
+            errors.push(FieldError::new(
+                "nice",
+                format!("must be between {} and {}", NICE_RANGE.start(), NICE_RANGE.end()),
+            ));
+        }
+        if let Some(CpuAffinity::Cores(cores)) = &request.cpu_affinity {
+            let available = available_cores();
+            if cores.is_empty() {
+                errors.push(FieldError::new("cpu_affinity", "must list at least one core, or be \"auto\""));
+            } else if let Some(core) = cores.iter().find(|core| !available.contains(core)) {
+                errors.push(FieldError::new("cpu_affinity", format!("core {} is not available", core)));
+            }
+        }
+        
+        if request.priority.is_some_and(|priority| priority > MAX_PRIORITY) {
+            errors.push(FieldError::new("priority", format!("must be between 0 and {}", MAX_PRIORITY)));
+        }
+        errors.extend(check_metadata(request));
+        errors.extend(check_env(request));
+        for (index, pattern) in request.artifact_patterns.iter().flatten().enumerate() {
+            if let Err(err) = glob::Pattern::new(pattern) {
+                errors.push(FieldError::new(&format!("artifact_patterns[{}]", index), format!("is not a valid glob: {}", err.msg)));
+            }
+        }
+        
+        if request.additional_files_url.is_some() && request.additional_files.is_some() {
+            errors.push(FieldError::new("additional_files_url", "conflicts with additional_files"));
+        }
+        
+        for (field, url) in [
+            ("callback_url", &request.callback_url),
+            ("source_url", &request.source_url),
+            ("additional_files_url", &request.additional_files_url),
+        ] {
+            if url.as_ref().is_some_and(|url| !(url.starts_with("http://") || url.starts_with("https://"))) {
+                errors.push(FieldError::new(field, "must be an http or https URL"));
+            }
+        }
+        
+        errors
+    }
+}
+
+/// Keys of a submission body, its interactor and its test cases that no request field is named after
+pub fn unknown_fields(body: &Value) -> Vec<FieldError> {
+    fn check<T: Serialize>(errors: &mut Vec<FieldError>, prefix: &str, value: Option<&Value>, known: T) {
+        let (Some(Value::Object(fields)), Ok(Value::Object(known))) = (value, serde_json::to_value(known)) else {
+            return;
+        };
+        for key in fields.keys().filter(|key| !known.contains_key(&snake_case(key))) {
+            errors.push(FieldError::new(&format!("{}{}", prefix, key), "unknown field"));
+        }
+    }
+    
+    // Every field serializes, even when None, so a default value lists them all
+    let mut errors = Vec::new();
+    check(&mut errors, "", Some(body), ExecutionRequest::default());
+    let interactor = Interactor { language: String::new(), source_code: String::new() };
+    check(&mut errors, "interactor.", body.get("interactor"), interactor);
+    if let Some(Value::Array(cases)) = body.get("test_cases").or_else(|| body.get("testCases")) {
+        for (index, case) in cases.iter().enumerate() {
+            check(&mut errors, &format!("test_cases[{}].", index), Some(case), TestCase::default());
+        }
+    }
+    errors
+}
+
+/// `sourceCode` as `source_code`, so the camelCase aliases count as known fields
+pub(crate) fn snake_case(key: &str) -> String {
+    let mut snake = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// Fold the kilobyte spellings of the size limits into the byte fields the engine enforces
+pub fn resolve_limit_units(request: &mut ExecutionRequest) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let sizes = [
+        ("memory_limit_kb", "memory_limit", &mut request.memory_limit, request.memory_limit_kb.take()),
+        ("stack_limit_kb", "stack_limit", &mut request.stack_limit, request.stack_limit_kb.take()),
+        ("max_file_size_kb", "max_file_size", &mut request.max_file_size, request.max_file_size_kb.take()),
+    ];
+    
+    for (field, bytes_field, bytes, kilobytes) in sizes {
+        let Some(kilobytes) = kilobytes else {
+            continue;
+        };
+        match kilobytes.checked_mul(1024) {
+            None => errors.push(FieldError::new(field, "is too large")),
+            Some(value) if bytes.is_some_and(|bytes| bytes != value) => {
+                errors.push(FieldError::new(field, format!("conflicts with {}", bytes_field)));
+            }
+            Some(value) => *bytes = Some(value),
+        }
+    }
+    
+    errors
+}
+
+/// Fill in the limits and options of the request's `profile`, which must be one of `profiles`
+pub fn apply_profile(request: &mut ExecutionRequest, profiles: &HashMap<String, Profile>) -> Option<FieldError> {
+    let name = request.profile.as_deref()?;
+    match profiles.get(name) {
+        Some(profile) => {
+            profile.apply(request);
+            None
+        }
+        None => Some(FieldError::new("profile", format!("unknown profile '{}'", name))),
+    }
+}
+
+/// Decode the text fields of a base64_encoded submission in place
+/// stdin is only checked here; it stays encoded so the executor can write the raw bytes
+pub fn decode_base64_fields(request: &mut ExecutionRequest) -> Vec<FieldError> {
+    use base64::{engine::general_purpose, Engine as _};
+    
+    let mut errors = Vec::new();
+    if !request.base64_encoded.unwrap_or(false) {
+        return errors;
+    }
+    
+    let decode_text = |field: &str, value: &str, errors: &mut Vec<FieldError>| {
+        match general_purpose::STANDARD.decode(value).map(String::from_utf8) {
+            Ok(Ok(text)) => Some(text),
+            Ok(Err(_)) => {
+                errors.push(FieldError::new(field, "must decode to UTF-8 text"));
+                None
+            }
+            Err(_) => {
+                errors.push(FieldError::new(field, "must be valid base64"));
+                None
+            }
+        }
+    };
+    
+    if let Some(source) = decode_text("source_code", &request.source_code, &mut errors) {
+        request.source_code = source;
+    }
+    if let Some(interactor) = &mut request.interactor {
+        if let Some(source) = decode_text("interactor.source_code", &interactor.source_code, &mut errors) {
+            interactor.source_code = source;
+        }
+    }
+    if let Some(expected) = request.expected_output.take() {
+        request.expected_output = Some(decode_text("expected_output", &expected, &mut errors).unwrap_or(expected));
+    }
+    for (index, case) in request.test_cases.iter_mut().flatten().enumerate() {
+        if let Some(expected) = case.expected_output.take() {
+            let field = format!("test_cases[{}].expected_output", index);
+            case.expected_output = Some(decode_text(&field, &expected, &mut errors).unwrap_or(expected));
+        }
+    }
+    errors.extend(check_encoded_stdin(request));
+    
+    errors
+}
+
+/// stdin of a base64_encoded submission, and of each of its test cases, must decode; it is only decoded when written for the run
+pub fn check_encoded_stdin(request: &ExecutionRequest) -> Vec<FieldError> {
+    use base64::{engine::general_purpose, Engine as _};
+    
+    if !request.base64_encoded.unwrap_or(false) {
+        return Vec::new();
+    }
+    let cases = request.test_cases.iter().flatten().enumerate()
+        .map(|(index, case)| (format!("test_cases[{}].stdin", index), case.stdin.as_ref()));
+    std::iter::once(("stdin".to_string(), request.stdin.as_ref()))
+        .chain(cases)
+        .filter_map(|(field, stdin)| Some((field, stdin?)))
+        .filter(|(_, stdin)| general_purpose::STANDARD.decode(stdin).is_err())
+        .map(|(field, _)| FieldError::new(&field, "must be valid base64"))
+        .collect()
+}
+
+/// metadata stays small: it is stored with the job and copied into every status, result and callback.
+/// Only sizes are checked; keys and values are otherwise taken as they are.
+pub fn check_metadata(request: &ExecutionRequest) -> Vec<FieldError> {
+    let Some(metadata) = &request.metadata else {
+        return Vec::new();
+    };
+    let mut errors = Vec::new();
+    if metadata.len() > MAX_METADATA_KEYS {
+        errors.push(FieldError::new("metadata", format!("must have at most {} entries", MAX_METADATA_KEYS)));
+    }
+    
+    // Sorted, so the errors come out in the same order every time
+    let mut entries: Vec<_> = metadata.iter().collect();
+    entries.sort();
+    for (key, value) in &entries {
+        if key.is_empty() || key.len() > MAX_METADATA_KEY_BYTES {
+            errors.push(FieldError::new(
+                "metadata",
+                format!("keys must be 1 to {} bytes, got {:?}", MAX_METADATA_KEY_BYTES, key),
+            ));
+        } else if value.len() > MAX_METADATA_VALUE_BYTES {
+            errors.push(FieldError::new(
+                &format!("metadata.{}", key),
+                format!("must be at most {} bytes", MAX_METADATA_VALUE_BYTES),
+            ));
+        }
+    }
+    
+    let total: usize = entries.iter().map(|(key, value)| key.len() + value.len()).sum();
+    if total > MAX_METADATA_BYTES {
+        errors.push(FieldError::new("metadata", format!("must be at most {} bytes in total", MAX_METADATA_BYTES)));
+    }
+    errors
+}
+
+/// Violations in a request's env: too many variables, a name that is not `[A-Z_][A-Z0-9_]*`,
+/// a reserved name or a value that is too long
+fn check_env(request: &ExecutionRequest) -> Vec<FieldError> {
+    let Some(env) = &request.env else {
+        return Vec::new();
+    };
+    let mut errors = Vec::new();
+    if env.len() > MAX_ENV_VARS {
+        errors.push(FieldError::new("env", format!("must have at most {} entries", MAX_ENV_VARS)));
+    }
+    
+    // Sorted, so the errors come out in the same order every time
+    let mut entries: Vec<_> = env.iter().collect();
+    entries.sort();
+    for (key, value) in entries {
+        let mut chars = key.chars();
+        let valid_key = chars.next().is_some_and(|c| c.is_ascii_uppercase() || c == '_')
+            && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        
+        if !valid_key {
+            errors.push(FieldError::new("env", format!("names must match [A-Z_][A-Z0-9_]*, got {:?}", key)));
+        } else if RESERVED_ENV_VARS.contains(&key.as_str()) {
+            errors.push(FieldError::new(&format!("env.{}", key), "cannot be overridden"));
+        } else if value.len() > MAX_ENV_VALUE_BYTES {
+            errors.push(FieldError::new(&format!("env.{}", key), format!("must be at most {} bytes", MAX_ENV_VALUE_BYTES)));
+        }
+    }
+    errors
+}
+
+/// Record a violation unless the duration is positive and within the ceiling
+fn check_time(errors: &mut Vec<FieldError>, field: &str, value: Option<f64>, max: f64) {
+    match value {
+        Some(value) if !value.is_finite() || value <= 0.0 => {
+            errors.push(FieldError::new(field, "must be a positive number"));
+        }
+        Some(value) if value > max => {
+            errors.push(FieldError::new(field, format!("must be at most {}", max)));
+        }
+        _ => {}
+    }
+}
+
+/// Record a violation unless the quantity is non-zero and within the ceiling
+fn check_size(errors: &mut Vec<FieldError>, field: &str, value: Option<u64>, max: u64) {
+    match value {
+        Some(0) => errors.push(FieldError::new(field, "must be greater than 0")),
+        Some(value) if value > max => {
+            errors.push(FieldError::new(field, format!("must be at most {}", max)));
+        }
+        _ => {}
+    }
+}
+
+/// Cap a requested value at the ceiling
+fn clamp<T: PartialOrd + Copy>(value: &mut Option<T>, max: T) {
+    if let Some(requested) = value {
+        if *requested > max {
+            *requested = max;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose, Engine as _};
+    
+    fn with_env(entries: &[(&str, &str)]) -> ExecutionRequest {
+        ExecutionRequest {
+            env: Some(entries.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()),
+            ..Default::default()
+        }
+    }
+    
+    /// Support for a known, enabled language with no limits of its own
+    fn supported() -> Support {
+        Support {
+            language: true,
+            in_service: true,
+            enabled: true,
+            isolation: true,
+            max_limits: LimitOverrides::default(),
+            max_source_bytes: None,
+            interactor_language: true,
+            interactive: true,
+        }
+    }
+    
+    fn fields(errors: &[FieldError]) -> Vec<&str> {
+        errors.iter().map(|error| error.field.as_str()).collect()
+    }
+    
+    /// A request asking for far more than the default ceilings allow
+    fn greedy() -> ExecutionRequest {
+        ExecutionRequest {
+            language: "python".to_string(),
+            source_code: "print(1)".to_string(),
+            cpu_time_limit: Some(3600.0),
+            wall_time_limit: Some(86400.0),
+            memory_limit: Some(16 * 1024 * 1024 * 1024),
+            max_processes_and_or_threads: Some(4096),
+            number_of_runs: Some(1000),
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn unknown_fields_are_found_at_every_level() {
+        let body = serde_json::json!({
+            "language": "python",
+            "sourceCode": "print(1)",
+            "memroy_limit": 1024,
+            "interactor": {"language": "python", "source_code": "", "timeout": 1},
+            "testCases": [{"stdin": "1", "expectedOutput": "1"}, {"stdout": "2"}],
+        });
+        assert_eq!(fields(&unknown_fields(&body)), ["memroy_limit", "interactor.timeout", "test_cases[1].stdout"]);
+        
+        // Every field a request serializes is known, so stored requests are accepted back
+        let stored = serde_json::to_value(greedy()).unwrap();
+        assert!(unknown_fields(&stored).is_empty());
+    }
+    
+    #[test]
+    fn binary_stdin_must_be_base64_and_alone() {
+        let validator = RequestValidator::default();
+        let request = ExecutionRequest {
+            language: "python".to_string(),
+            source_code: "print(1)".to_string(),
+            stdin_base64: Some("AP8K".to_string()),
+            ..Default::default()
+        };
+        assert!(validator.validate(&request, supported()).is_empty());
+        
+        let garbled = ExecutionRequest { stdin_base64: Some("not base64!".to_string()), ..request.clone() };
+        assert_eq!(validator.validate(&garbled, supported())[0].constraint, "must be valid base64");
+        let both = ExecutionRequest { stdin: Some("text".to_string()), ..request };
+        assert_eq!(validator.validate(&both, supported())[0].constraint, "conflicts with stdin");
+    }
+    
+    fn with_metadata(entries: Vec<(String, String)>) -> ExecutionRequest {
+        ExecutionRequest { metadata: Some(entries.into_iter().collect()), ..Default::default() }
+    }
+    
+    fn numbered(count: usize, value: &str) -> Vec<(String, String)> {
+        (0..count).map(|i| (format!("key{}", i), value.to_string())).collect()
+    }
+    
+    #[test]
+    fn metadata_within_its_limits_is_taken_as_it_is() {
+        assert!(check_metadata(&with_metadata(numbered(MAX_METADATA_KEYS, "42"))).is_empty());
+        // Anything goes in a value, such as JSON the engine must not parse
+        assert!(check_metadata(&with_metadata(vec![("raw".into(), "{\"not\": json".into())])).is_empty());
+        assert!(check_metadata(&ExecutionRequest::default()).is_empty());
+    }
+    
+    #[test]
+    fn metadata_over_its_limits_is_refused() {
+        let errors = check_metadata(&with_metadata(numbered(MAX_METADATA_KEYS + 1, "42")));
+        assert_eq!((errors[0].field.as_str(), errors[0].constraint.as_str()), ("metadata", "must have at most 32 entries"));
+        
+        let errors = check_metadata(&with_metadata(vec![("user_id".into(), "x".repeat(MAX_METADATA_VALUE_BYTES + 1))]));
+        assert_eq!(errors[0].field, "metadata.user_id");
+        assert_eq!(errors[0].constraint, "must be at most 512 bytes");
+        
+        let errors = check_metadata(&with_metadata(vec![("".into(), "1".into()), ("k".repeat(MAX_METADATA_KEY_BYTES + 1), "1".into())]));
+        assert_eq!(errors.len(), 2);
+        
+        // Every value within its own limit, but too much altogether
+        let errors = check_metadata(&with_metadata(numbered(10, &"x".repeat(MAX_METADATA_BYTES / 10))));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].constraint, format!("must be at most {} bytes in total", MAX_METADATA_BYTES));
+    }
+    
+    #[test]
+    fn reject_mode_lists_every_limit_over_its_ceiling() {
+        let validator = RequestValidator::default();
+        let mut request = greedy();
+        validator.apply_ceilings(&mut request, supported());
+        assert_eq!(request.wall_time_limit, Some(86400.0), "reject mode must leave the request alone");
+        
+        let errors = validator.validate(&request, supported());
+        assert_eq!(
+            fields(&errors),
+            ["cpu_time_limit", "wall_time_limit", "memory_limit", "max_processes_and_or_threads", "number_of_runs"],
+        );
+        assert_eq!(errors[1].constraint, "must be at most 20");
+    }
+    
+    #[test]
+    fn clamp_mode_lowers_limits_to_the_ceilings() {
+        let validator = RequestValidator { mode: LimitsMode::Clamp, ..Default::default() };
+        let mut request = greedy();
+        request.stack_limit = Some(1024 * 1024); // under its ceiling, so kept
+        validator.apply_ceilings(&mut request, supported());
+        
+        assert_eq!(request.cpu_time_limit, Some(validator.max_cpu_time));
+        assert_eq!(request.wall_time_limit, Some(validator.max_wall_time));
+        assert_eq!(request.memory_limit, Some(validator.max_memory));
+        assert_eq!(request.max_processes_and_or_threads, Some(validator.max_processes));
+        assert_eq!(request.number_of_runs, Some(validator.max_runs));
+        assert_eq!(request.stack_limit, Some(1024 * 1024));
+        assert!(validator.validate(&request, supported()).is_empty());
+    }
+    
+    #[test]
+    fn language_maxima_lower_the_ceilings_in_both_modes() {
+        let support = Support {
+            max_limits: LimitOverrides { cpu_time: Some(2.0), ..Default::default() },
+            ..supported()
+        };
+        let request = ExecutionRequest { cpu_time_limit: Some(5.0), ..greedy() };
+        
+        let errors = RequestValidator::default().validate(&request, support);
+        let cpu = errors.iter().find(|error| error.field == "cpu_time_limit").unwrap();
+        assert_eq!(cpu.constraint, "must be at most 2");
+        
+        let mut clamped = request.clone();
+        RequestValidator { mode: LimitsMode::Clamp, ..Default::default() }.apply_ceilings(&mut clamped, support);
+        assert_eq!(clamped.cpu_time_limit, Some(2.0));
+    }
+    
+    #[test]
+    fn accepts_ordinary_variables() {
+        assert!(check_env(&with_env(&[("API_BASE", "http://localhost"), ("_DEBUG", "1"), ("LOCALE2", "")])).is_empty());
+        assert!(check_env(&ExecutionRequest::default()).is_empty());
+    }
+    
+    #[test]
+    fn rejects_bad_names_reserved_names_and_long_values() {
+        let long = "x".repeat(MAX_ENV_VALUE_BYTES + 1);
+        let errors = check_env(&with_env(&[("lower", "1"), ("1ST", "1"), ("LD_PRELOAD", "/tmp/evil.so"), ("BIG", &long)]));
+        // In name order: 1ST, BIG, LD_PRELOAD, lower
+        assert_eq!(fields(&errors), ["env", "env.BIG", "env.LD_PRELOAD", "env"]);
+        assert_eq!(errors[2].constraint, "cannot be overridden");
+    }
+    
+    #[test]
+    fn rejects_invalid_artifact_globs() {
+        let request = ExecutionRequest {
+            language: "python".to_string(),
+            source_code: "print(1)".to_string(),
+            artifact_patterns: Some(vec!["out/*.txt".to_string(), "[".to_string(), "**/*.png".to_string(), "a**b".to_string()]),
+            ..Default::default()
+        };
+        let errors = RequestValidator::default().validate(&request, supported());
+        assert_eq!(fields(&errors), ["artifact_patterns[1]", "artifact_patterns[3]"]);
+        assert!(errors[0].constraint.starts_with("is not a valid glob: "), "{}", errors[0].constraint);
+    }
+    
+    #[test]
+    fn rejects_too_many_variables() {
+        let names: Vec<String> = (0..=MAX_ENV_VARS).map(|i| format!("VAR{}", i)).collect();
+        let entries: Vec<(&str, &str)> = names.iter().map(|name| (name.as_str(), "1")).collect();
+        let errors = check_env(&with_env(&entries));
+        assert_eq!(fields(&errors), ["env"]);
+        assert_eq!(errors[0].constraint, format!("must have at most {} entries", MAX_ENV_VARS));
+    }
+    
+    /// A request as the API would deserialize it, with the size units resolved
+    fn resolved(limits: serde_json::Value) -> (ExecutionRequest, Vec<FieldError>) {
+        let mut body = serde_json::json!({"language": "python", "source_code": "print(1)"});
+        body.as_object_mut().unwrap().extend(limits.as_object().unwrap().clone());
+        let mut request: ExecutionRequest = serde_json::from_value(body).unwrap();
+        let errors = resolve_limit_units(&mut request);
+        (request, errors)
+    }
+    
+    #[test]
+    fn kilobyte_limits_become_bytes() {
+        let (request, errors) = resolved(serde_json::json!({"memory_limit_kb": 65536, "stackLimitKb": 8192, "max_file_size_kb": 1024}));
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(request.memory_limit, Some(64 * 1024 * 1024));
+        assert_eq!(request.stack_limit, Some(8 * 1024 * 1024));
+        assert_eq!(request.max_file_size, Some(1024 * 1024));
+        assert_eq!((request.memory_limit_kb, request.stack_limit_kb, request.max_file_size_kb), (None, None, None));
+    }
+    
+    #[test]
+    fn byte_limits_are_kept_and_may_agree_with_kilobytes() {
+        let (request, errors) = resolved(serde_json::json!({"memoryLimit": 268435456, "stack_limit": 8388608, "stack_limit_kb": 8192}));
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(request.memory_limit, Some(256 * 1024 * 1024));
+        assert_eq!(request.stack_limit, Some(8 * 1024 * 1024));
+        assert_eq!(request.max_file_size, None);
+    }
+    
+    #[test]
+    fn conflicting_or_overflowing_kilobytes_are_errors() {
+        let (request, errors) = resolved(serde_json::json!({"memory_limit": 256, "memory_limit_kb": 256, "max_file_size_kb": u64::MAX}));
+        assert_eq!(fields(&errors), ["memory_limit_kb", "max_file_size_kb"]);
+        assert_eq!(errors[0].constraint, "conflicts with memory_limit");
+        assert_eq!(errors[1].constraint, "is too large");
+        assert_eq!(request.memory_limit, Some(256), "a conflict leaves the byte field alone");
+    }
+    
+    fn encoded(fields: serde_json::Value) -> (ExecutionRequest, Vec<FieldError>) {
+        let mut body = serde_json::json!({"language": "python", "base64_encoded": true});
+        body.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        let mut request: ExecutionRequest = serde_json::from_value(body).unwrap();
+        let errors = decode_base64_fields(&mut request);
+        (request, errors)
+    }
+    
+    #[test]
+    fn base64_text_fields_are_decoded_and_stdin_is_left_encoded() {
+        let (request, errors) = encoded(serde_json::json!({"source_code": "cHJpbnQoMSk=", "expected_output": "MQ==", "stdin": "AP8K"}));
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!((request.source_code.as_str(), request.expected_output.as_deref()), ("print(1)", Some("1")));
+        assert_eq!(request.stdin.as_deref(), Some("AP8K"));
+        assert_eq!(request.stdin_bytes(), Some(vec![0x00, 0xff, b'\n']));
+    }
+    
+    #[test]
+    fn invalid_base64_fields_are_refused() {
+        // "/w==" is the single byte 0xFF, which is not UTF-8 source
+        let (_, errors) = encoded(serde_json::json!({"source_code": "/w==", "expected_output": "not base64!", "stdin": "%%"}));
+        let found: Vec<(&str, &str)> = errors.iter().map(|error| (error.field.as_str(), error.constraint.as_str())).collect();
+        assert_eq!(found, [
+            ("source_code", "must decode to UTF-8 text"),
+            ("expected_output", "must be valid base64"),
+            ("stdin", "must be valid base64"),
+        ]);
+    }
+    
+    #[test]
+    fn plain_submissions_are_not_decoded() {
+        let mut request = ExecutionRequest { source_code: "cHJpbnQoMSk=".to_string(), ..Default::default() };
+        assert!(decode_base64_fields(&mut request).is_empty());
+        assert_eq!(request.source_code, "cHJpbnQoMSk=");
+    }
+    
+    #[test]
+    fn run_at_tolerates_a_few_seconds_of_clock_skew() {
+        let validator = RequestValidator::default();
+        let at = |offset: i64| ExecutionRequest {
+            language: "python".to_string(),
+            source_code: "print(1)".to_string(),
+            run_at: Some(chrono::Utc::now() + chrono::Duration::seconds(offset)),
+            ..Default::default()
+        };
+        for offset in [3600, -2] {
+            assert!(fields(&validator.validate(&at(offset), supported())).is_empty(), "{}s", offset);
+        }
+        assert_eq!(fields(&validator.validate(&at(-60), supported())), ["run_at"]);
+    }
+    
+    #[test]
+    fn urls_stand_in_for_inline_inputs_but_not_beside_them() {
+        let validator = RequestValidator::default();
+        let by_url = ExecutionRequest {
+            language: "python".to_string(),
+            source_url: Some("https://files.example.com/main.py".to_string()),
+            additional_files_url: Some("https://files.example.com/files.zip".to_string()),
+            ..Default::default()
+        };
+        assert!(validator.validate(&by_url, supported()).is_empty());
+        
+        let both = ExecutionRequest {
+            source_code: "print(1)".to_string(),
+            additional_files: Some("UEsFBgAAAAAAAAAAAAAAAAAAAAAAAA==".to_string()),
+            ..by_url.clone()
+        };
+        assert_eq!(fields(&validator.validate(&both, supported())), ["source_url", "additional_files_url"]);
+        
+        let not_http = ExecutionRequest { source_url: Some("file:///etc/passwd".to_string()), ..by_url };
+        assert_eq!(fields(&validator.validate(&not_http, supported())), ["source_url"]);
+    }
+    
+    fn profiles() -> HashMap<String, Profile> {
+        let contest: Profile = serde_json::from_value(serde_json::json!({
+            "limits": {"cpu_time": 1.0, "memory": 268435456},
+            "options": {"number_of_runs": 3, "redirect_stderr_to_stdout": true},
+        })).unwrap();
+        HashMap::from([("contest".to_string(), contest)])
+    }
+    
+    #[test]
+    fn profiles_fill_in_only_what_the_request_leaves_out() {
+        let mut request = ExecutionRequest {
+            profile: Some("contest".to_string()),
+            cpu_time_limit: Some(2.0),
+            redirect_stderr_to_stdout: Some(false),
+            ..Default::default()
+        };
+        assert!(apply_profile(&mut request, &profiles()).is_none());
+        
+        assert_eq!(request.cpu_time_limit, Some(2.0));
+        assert_eq!(request.redirect_stderr_to_stdout, Some(false));
+        assert_eq!(request.memory_limit, Some(268435456));
+        assert_eq!(request.number_of_runs, Some(3));
+        assert_eq!(request.wall_time_limit, None);
+        
+        let mut plain = ExecutionRequest::default();
+        assert!(apply_profile(&mut plain, &profiles()).is_none());
+        assert_eq!(plain.memory_limit, None);
+    }
+    
+    #[test]
+    fn unknown_profiles_are_refused() {
+        let mut request = ExecutionRequest { profile: Some("benchmark".to_string()), ..Default::default() };
+        let error = apply_profile(&mut request, &profiles()).unwrap();
+        assert_eq!((error.field.as_str(), error.constraint.as_str()), ("profile", "unknown profile 'benchmark'"));
+        assert_eq!(request.cpu_time_limit, None);
+    }
+    
+    #[test]
+    fn interactors_are_checked_like_submissions() {
+        let validator = RequestValidator::default();
+        let interactive = |source: &str| ExecutionRequest {
+            language: "python".to_string(),
+            source_code: "print(1)".to_string(),
+            interactor: Some(Interactor { language: "cobol".to_string(), source_code: source.to_string() }),
+            redirect_stderr_to_stdout: Some(true),
+            ..Default::default()
+        };
+        let fields = |errors: Vec<FieldError>| errors.into_iter().map(|error| (error.field, error.constraint)).collect::<Vec<_>>();
+        
+        let errors = validator.validate(&interactive(" "), Support { interactor_language: false, ..supported() });
+        assert_eq!(fields(errors), [
+            ("interactor.source_code".to_string(), "must not be empty".to_string()),
+            ("interactor.language".to_string(), "unsupported language 'cobol'".to_string()),
+            ("redirect_stderr_to_stdout".to_string(), "conflicts with interactor".to_string()),
+        ]);
+        
+        let request = ExecutionRequest { redirect_stderr_to_stdout: None, ..interactive("print(2)") };
+        let errors = validator.validate(&request, Support { interactive: false, ..supported() });
+        assert_eq!(fields(errors), [("interactor".to_string(), "interactive runs need both programs on the native backend".to_string())]);
+        assert!(validator.validate(&request, supported()).is_empty());
+    }
+    
+    #[test]
+    fn test_groups_bring_their_own_inputs_within_the_ceilings() {
+        let validator = RequestValidator { max_test_cases: 2, ..Default::default() };
+        let case = |cpu: f64| TestCase { stdin: Some("1".to_string()), cpu_time_limit: Some(cpu), ..Default::default() };
+        let group = |cases: Vec<TestCase>| ExecutionRequest {
+            language: "python".to_string(),
+            source_code: "print(input())".to_string(),
+            test_cases: Some(cases),
+            ..Default::default()
+        };
+        let fields = |errors: Vec<FieldError>| errors.into_iter().map(|error| format!("{}: {}", error.field, error.constraint)).collect::<Vec<_>>();
+        
+        assert!(validator.validate(&group(vec![case(1.0), case(2.0)]), supported()).is_empty());
+        assert_eq!(fields(validator.validate(&group(vec![]), supported())), ["test_cases: must list at least one case"]);
+        assert_eq!(fields(validator.validate(&group(vec![case(1.0); 3]), supported())), ["test_cases: must list at most 2 cases"]);
+        assert_eq!(fields(validator.validate(&group(vec![case(1.0), case(600.0)]), supported())), ["test_cases[1].cpu_time_limit: must be at most 15"]);
+        
+        let request = ExecutionRequest {
+            stdin: Some("2".to_string()),
+            expected_output: Some("2".to_string()),
+            number_of_runs: Some(3),
+            ..group(vec![case(1.0)])
+        };
+        assert_eq!(fields(validator.validate(&request, supported())), [
+            "stdin: conflicts with test_cases",
+            "expected_output: conflicts with test_cases",
+            "number_of_runs: conflicts with test_cases",
+        ]);
+        
+        // Clamping lowers each case to the ceilings instead
+        let validator = RequestValidator { mode: LimitsMode::Clamp, ..validator };
+        let mut request = group(vec![case(600.0)]);
+        validator.apply_ceilings(&mut request, supported());
+        assert_eq!(request.test_cases.unwrap()[0].cpu_time_limit, Some(15.0));
+    }
+    
+    #[test]
+    fn encoded_test_case_inputs_must_decode() {
+        let request = ExecutionRequest {
+            base64_encoded: Some(true),
+            stdin: None,
+            test_cases: Some(vec![
+                TestCase { stdin: Some("aGk=".to_string()), ..Default::default() },
+                TestCase { stdin: Some("not base64!".to_string()), ..Default::default() },
+            ]),
+            ..Default::default()
+        };
+        let errors = check_encoded_stdin(&request);
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].field.as_str(), errors[0].constraint.as_str()), ("test_cases[1].stdin", "must be valid base64"));
+        assert!(check_encoded_stdin(&ExecutionRequest { base64_encoded: Some(false), ..request }).is_empty());
+    }
+    
+    #[test]
+    fn sources_are_held_to_their_languages_ceiling() {
+        let validator = RequestValidator { max_source_bytes: 100, ..Default::default() };
+        let sized = |bytes: usize| ExecutionRequest {
+            language: "python".to_string(),
+            source_code: "#".repeat(bytes),
+            ..Default::default()
+        };
+        let source_errors = |request: &ExecutionRequest, support: Support| validator.validate(request, support)
+            .into_iter()
+            .filter(|error| error.field == "source_code")
+            .map(|error| error.constraint)
+            .collect::<Vec<_>>();
+        
+        assert!(source_errors(&sized(99), supported()).is_empty());
+        assert!(source_errors(&sized(100), supported()).is_empty());
+        assert_eq!(source_errors(&sized(101), supported()), ["must be at most 100 bytes"]);
+        
+        // A language's own ceiling replaces the server's, upwards or downwards
+        let notebooks = Support { max_source_bytes: Some(1000), ..supported() };
+        assert!(source_errors(&sized(1000), notebooks).is_empty());
+        assert_eq!(source_errors(&sized(1001), notebooks), ["must be at most 1000 bytes"]);
+        let tiny = Support { max_source_bytes: Some(10), ..supported() };
+        assert_eq!(source_errors(&sized(11), tiny), ["must be at most 10 bytes"]);
+    }
+    
+    #[test]
+    fn additional_files_have_their_own_ceiling() {
+        let validator = RequestValidator { max_source_bytes: 10, max_additional_files_bytes: 30, ..Default::default() };
+        let with_files = |decoded: usize| ExecutionRequest {
+            language: "python".to_string(),
+            source_code: "print(1)".to_string(),
+            additional_files: Some(general_purpose::STANDARD.encode(vec![0u8; decoded])),
+            ..Default::default()
+        };
+        let file_errors = |request: &ExecutionRequest| validator.validate(request, supported())
+            .into_iter()
+            .filter(|error| error.field == "additional_files")
+            .map(|error| error.constraint)
+            .collect::<Vec<_>>();
+        
+        // Well past the source ceiling, but measured against its own
+        assert!(file_errors(&with_files(30)).is_empty());
+        assert!(file_errors(&with_files(29)).is_empty());
+        assert_eq!(file_errors(&with_files(31)), ["must be at most 30 bytes once decoded"]);
+    }
+}
@@ -0,0 +1,143 @@
+//! Startup warm-up: pull every language's image and build a hello-world per compiled language,
+//! so the first real submission doesn't pay for a cold image, toolchain or page cache.
+//!
+//! A language whose warm-up fails is taken out of service rather than failing startup.
+//! [`check`] runs the same steps once per language for `labforcode-engine check`, with a
+//! hello-world for the interpreted languages too.
+use crate::executor::CodeExecutor;
+use crate::types::LanguageWarmup;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Program built and run per compiled language; interpreted languages only need their image
+fn hello_world(language: &str) -> Option<&'static str> {
+    match language {
+        "c" => Some("#include <stdio.h>\nint main(void) { puts(\"hello\"); return 0; }\n"),
+        "cpp" => Some("#include <iostream>\nint main() { std::cout << \"hello\" << std::endl; }\n"),
+        "java" => Some("public class Main { public static void main(String[] args) { System.out.println(\"hello\"); } }\n"),
+        "go" => Some("package main\n\nimport \"fmt\"\n\nfunc main() { fmt.Println(\"hello\") }\n"),
+        "rust" => Some("fn main() { println!(\"hello\"); }\n"),
+        _ => None,
+    }
+}
+
+/// Program run by [`check`]: the warm-up one, or a hello-world for an interpreted language
+fn smoke_program(language: &str) -> Option<&'static str> {
+    hello_world(language).or(match language {
+        "python" => Some("print(\"hello\")\n"),
+        "javascript" => Some("console.log(\"hello\");\n"),
+        _ => None,
+    })
+}
+
+/// Pull the image of each language and build and run a hello-world in it, in the order given.
+///
+/// Nothing is taken out of service; the outcomes are only reported.
+pub async fn check(executor: &CodeExecutor, languages: &[String]) -> Vec<(String, LanguageWarmup)> {
+    let mut outcomes = Vec::new();
+    for language in languages {
+        outcomes.push((language.clone(), attempt(executor, language, smoke_program(language)).await));
+    }
+    outcomes
+}
+
+/// Make sure the language's image is present and `program` runs, timing the whole
+async fn attempt(executor: &CodeExecutor, language: &str, program: Option<&str>) -> LanguageWarmup {
+    let started = Instant::now();
+    let error = executor.warm_up(language, program).await.err().map(|err| err.to_string());
+    LanguageWarmup {
+        ready: error.is_none(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        error,
+    }
+}
+
+/// Progress of the warm-up, shared with the health check
+#[derive(Default)]
+pub(crate) struct Warmup {
+    finished: AtomicBool,
+    languages: Mutex<BTreeMap<String, LanguageWarmup>>,
+}
+
+impl Warmup {
+    /// State of an engine that skips warm-up
+    pub fn skipped() -> Self {
+        Self {
+            finished: AtomicBool::new(true),
+            languages: Mutex::default(),
+        }
+    }
+    
+    /// Whether warm-up finished or gave up
+    pub fn finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst)
+    }
+    
+    /// How each language's warm-up went so far
+    pub fn languages(&self) -> BTreeMap<String, LanguageWarmup> {
+        self.languages.lock().unwrap().clone()
+    }
+    
+    /// Warm each language in turn, giving up after `timeout`; languages not reached stay in service, cold
+    pub async fn run(&self, executor: &CodeExecutor, timeout: Duration) {
+        let started = Instant::now();
+        let all = async {
+            for language in executor.language_keys() {
+                self.warm(executor, &language).await;
+            }
+        };
+        if tokio::time::timeout(timeout, all).await.is_err() {
+            warn!("Warm-up gave up after {}s; languages not reached yet stay in service", timeout.as_secs());
+        } else {
+            info!("🔥 Warm-up finished in {:.1}s", started.elapsed().as_secs_f64());
+        }
+        self.finished.store(true, Ordering::SeqCst);
+    }
+    
+    async fn warm(&self, executor: &CodeExecutor, language: &str) {
+        let outcome = attempt(executor, language, hello_world(language)).await;
+        match &outcome.error {
+            None => info!("🔥 Warmed up {} in {}ms", language, outcome.duration_ms),
+            Some(err) => {
+                warn!("Warm-up of {} failed after {}ms, taking it out of service: {}", language, outcome.duration_ms, err);
+                executor.take_out_of_service(language);
+            }
+        }
+        self.languages.lock().unwrap().insert(language.to_string(), outcome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EngineConfig;
+    use crate::workdir::WorkdirStore;
+    
+    #[test]
+    fn every_built_in_language_has_a_smoke_program() {
+        for language in ["c", "cpp", "java", "go", "rust", "python", "javascript"] {
+            assert!(smoke_program(language).is_some(), "{}", language);
+        }
+        // Warm-up only builds the compiled ones
+        assert!(hello_world("python").is_none());
+        assert!(smoke_program("cobol").is_none());
+    }
+    
+    #[tokio::test]
+    async fn check_reports_each_language_in_order() {
+        let scratch = tempfile::tempdir().unwrap();
+        let workdirs = WorkdirStore::new(scratch.path().to_path_buf(), chrono::Duration::zero()).unwrap();
+        let executor = CodeExecutor::new(workdirs, &EngineConfig::default()).unwrap();
+        
+        let outcomes = check(&executor, &["cobol".to_string(), "fortran".to_string()]).await;
+        let languages: Vec<&str> = outcomes.iter().map(|(language, _)| language.as_str()).collect();
+        assert_eq!(languages, ["cobol", "fortran"]);
+        assert!(!outcomes[0].1.ready);
+        assert_eq!(outcomes[0].1.error.as_deref(), Some("Unsupported language: cobol"));
+        // A failed check takes nothing out of service
+        assert!(executor.language_keys().contains(&"python".to_string()));
+    }
+}
@@ -0,0 +1,223 @@
+//! Last-resort enforcement of the wall-clock limit, independent of the tasks waiting on programs.
+//!
+//! Every spawned program is registered with its deadline. A reaper thread looks every 250ms and
+//! kills the process group of any program past its deadline plus a grace period, along with every
+//! process still holding one of its output pipes, such as a grandchild that moved to a session of
+//! its own. The wait on the program can then finish, and the run ends as time_limit_exceeded.
+use crate::sandbox::kill_process;
+use std::collections::HashMap;
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How often the reaper looks for programs past their deadline
+const SCAN_INTERVAL: Duration = Duration::from_millis(250);
+
+type Entries = Mutex<HashMap<u64, Entry>>;
+
+/// Programs being waited on, and the thread that kills those past their deadline
+#[derive(Clone)]
+pub(crate) struct Watchdog {
+    entries: Arc<Entries>,
+    next_id: Arc<AtomicU64>,
+}
+
+struct Entry {
+    target: Target,
+    deadline: Instant,
+    fired: Arc<AtomicBool>,
+}
+
+/// A program's process group and the pipes it writes its output to
+#[derive(Clone)]
+struct Target {
+    pid: u32,
+    pipes: Vec<u64>, // inode numbers
+}
+
+impl Target {
+    /// Kill the process group, then anything else still holding one of the pipes
+    fn kill(&self) {
+        kill_process(self.pid);
+        for holder in pipe_holders(&self.pipes) {
+            kill_process(holder);
+        }
+    }
+}
+
+impl Watchdog {
+    /// Start the reaper, which kills programs `grace` after their deadline; it stops once every clone is dropped
+    pub fn start(grace: Duration) -> std::io::Result<Self> {
+        let watchdog = Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+        };
+        let entries = Arc::downgrade(&watchdog.entries);
+        std::thread::Builder::new()
+            .name("watchdog".to_string())
+            .spawn(move || reap(entries, grace))?;
+        Ok(watchdog)
+    }
+    
+    /// Watch a just spawned child until the returned guard is dropped
+    pub fn watch(&self, child: &Child, deadline: Instant) -> Watch {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let target = Target { pid: child.id(), pipes: pipe_ids(child) };
+        let fired = Arc::new(AtomicBool::new(false));
+        self.entries.lock().unwrap().insert(id, Entry { target: target.clone(), deadline, fired: Arc::clone(&fired) });
+        Watch { entries: Arc::clone(&self.entries), id, target, fired }
+    }
+}
+
+/// Registration of a program with the watchdog, removed on drop
+pub(crate) struct Watch {
+    entries: Arc<Entries>,
+    id: u64,
+    target: Target,
+    fired: Arc<AtomicBool>,
+}
+
+impl Watch {
+    /// The reaper had to kill the program
+    pub fn fired(&self) -> bool {
+        self.fired.load(Ordering::SeqCst)
+    }
+    
+    /// Kill the program as the reaper would, escaped grandchildren included
+    pub fn kill(&self) {
+        self.target.kill();
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        self.entries.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Kill whatever is past its deadline, every scan until its waiter lets go
+fn reap(entries: Weak<Entries>, grace: Duration) {
+    loop {
+        std::thread::sleep(SCAN_INTERVAL);
+        let Some(entries) = entries.upgrade() else {
+            return;
+        };
+        let now = Instant::now();
+        let overdue: Vec<Target> = entries.lock().unwrap().values()
+            .filter(|entry| now > entry.deadline + grace)
+            .map(|entry| {
+                if !entry.fired.swap(true, Ordering::SeqCst) {
+                    warn!("⏱️ Process {} outlived its deadline by {:?}, killing it from the watchdog", entry.target.pid, grace);
+                }
+                entry.target.clone()
+            })
+            .collect();
+        for target in overdue {
+            target.kill();
+        }
+    }
+}
+
+/// Inode numbers of the child's stdout and stderr pipes
+#[cfg(unix)]
+fn pipe_ids(child: &Child) -> Vec<u64> {
+    use std::os::unix::io::{AsRawFd, RawFd};
+    
+    let inode = |fd: RawFd| {
+        let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+        (unsafe { libc::fstat(fd, &mut stat) } == 0).then_some(stat.st_ino as u64)
+    };
+    let stdout = child.stdout.as_ref().map(AsRawFd::as_raw_fd);
+    let stderr = child.stderr.as_ref().map(AsRawFd::as_raw_fd);
+    [stdout, stderr].into_iter().flatten().filter_map(inode).collect()
+}
+
+#[cfg(windows)]
+fn pipe_ids(_child: &Child) -> Vec<u64> {
+    Vec::new()
+}
+
+/// Other processes with one of the pipes open, found through /proc/<pid>/fd
+#[cfg(target_os = "linux")]
+fn pipe_holders(pipes: &[u64]) -> Vec<u32> {
+    if pipes.is_empty() {
+        return Vec::new();
+    }
+    let names: Vec<String> = pipes.iter().map(|inode| format!("pipe:[{}]", inode)).collect();
+    let engine = std::process::id();
+    std::fs::read_dir("/proc").into_iter().flatten().flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| *pid != engine)
+        .filter(|pid| {
+            std::fs::read_dir(format!("/proc/{}/fd", pid)).into_iter().flatten().flatten()
+                .filter_map(|fd| std::fs::read_link(fd.path()).ok())
+                .any(|target| names.iter().any(|name| target.as_os_str() == name.as_str()))
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pipe_holders(_pipes: &[u64]) -> Vec<u32> {
+    Vec::new()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+    
+    /// Shell script in a process group of its own, with its stdout piped back
+    fn spawn(script: &str) -> Child {
+        Command::new("sh")
+            .args(["-c", script])
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap()
+    }
+    
+    #[test]
+    fn programs_past_their_deadline_are_killed() {
+        let watchdog = Watchdog::start(Duration::ZERO).unwrap();
+        let mut child = spawn("sleep 30");
+        let watch = watchdog.watch(&child, Instant::now());
+        
+        let started = Instant::now();
+        child.wait().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(watch.fired());
+    }
+    
+    #[test]
+    fn released_programs_are_left_alone() {
+        let watchdog = Watchdog::start(Duration::ZERO).unwrap();
+        let mut child = spawn("sleep 1");
+        drop(watchdog.watch(&child, Instant::now()));
+        
+        std::thread::sleep(SCAN_INTERVAL * 2);
+        assert!(child.try_wait().unwrap().is_none(), "the program was killed after its watch ended");
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+    
+    #[test]
+    fn grandchildren_holding_the_output_are_killed_too() {
+        let watchdog = Watchdog::start(Duration::from_secs(60)).unwrap();
+        // The shell exits at once, leaving a grandchild in a session of its own with the pipe open
+        let mut child = spawn("setsid sleep 30 & echo started");
+        let watch = watchdog.watch(&child, Instant::now());
+        child.wait().unwrap();
+        
+        let started = Instant::now();
+        watch.kill();
+        let mut output = String::new();
+        child.stdout.take().unwrap().read_to_string(&mut output).unwrap();
+        assert_eq!(output, "started\n");
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!watch.fired());
+    }
+}
@@ -0,0 +1,272 @@
+use crate::isolate::copy_tree;
+use crate::types::WorkdirEntry;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Subdirectory of PRESERVE_DIR the store owns
+const STORE_DIR: &str = "labforcode-workdirs";
+
+/// Directory under the store holding one empty file per directory the store wrote
+const MARKER_DIR: &str = ".markers";
+
+/// Retained working directories kept around for debugging
+#[derive(Clone)]
+pub struct WorkdirStore {
+    root: PathBuf,
+    ttl: Duration,
+    expiries: Arc<Mutex<HashMap<String, DateTime<Utc>>>>,
+}
+
+impl WorkdirStore {
+    /// Create the store in its own subdirectory of `preserve_dir`
+    ///
+    /// Directories a previous process preserved are discarded; anything else found there is left alone.
+    pub fn new(preserve_dir: PathBuf, ttl: Duration) -> Result<Self> {
+        let root = preserve_dir.join(STORE_DIR);
+        let markers = root.join(MARKER_DIR);
+        fs::create_dir_all(&markers)?;
+        
+        for entry in fs::read_dir(&markers)? {
+            let marker = entry?.path();
+            if let Some(id) = marker.file_name() {
+                let dir = root.join(id);
+                if dir.is_dir() {
+                    fs::remove_dir_all(&dir)?;
+                }
+            }
+            fs::remove_file(&marker)?;
+        }
+        
+        Ok(Self {
+            root,
+            ttl,
+            expiries: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+    
+    /// Move a finished working directory into the retained area
+    ///
+    /// Falls back to copying when the directory is on another filesystem.
+    pub async fn preserve(&self, id: &str, dir: &Path) -> Result<()> {
+        let target = self.path_for(id)
+            .ok_or_else(|| anyhow!("Execution id '{}' cannot be used as a directory name", id))?;
+        let marker = self.root.join(MARKER_DIR).join(id);
+        let dir = dir.to_path_buf();
+        
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            // Marked first, so a crash halfway through still gets cleaned up on the next start
+            fs::write(&marker, b"")?;
+            if target.exists() {
+                fs::remove_dir_all(&target)?;
+            }
+            if fs::rename(&dir, &target).is_err() {
+                fs::create_dir_all(&target)?;
+                copy_tree(&dir, &target)?;
+                fs::remove_dir_all(&dir)?;
+            }
+            Ok(())
+        }).await??;
+        
+        let mut expiries = self.expiries.lock().await;
+        expiries.insert(id.to_string(), Utc::now() + self.ttl);
+        
+        info!("📁 Preserved working directory for {}", id);
+        Ok(())
+    }
+    
+    /// List every file in a preserved directory
+    pub async fn list(&self, id: &str) -> Result<Option<Vec<WorkdirEntry>>> {
+        let dir = match self.existing_dir(id).await {
+            Some(dir) => dir,
+            None => return Ok(None),
+        };
+        
+        let entries = tokio::task::spawn_blocking(move || -> Result<Vec<WorkdirEntry>> {
+            let mut entries = Vec::new();
+            list_tree(&dir, &dir, &mut entries)?;
+            entries.sort_by(|a, b| a.path.cmp(&b.path));
+            Ok(entries)
+        }).await??;
+        Ok(Some(entries))
+    }
+    
+    /// Resolve a file inside a preserved directory, refusing anything that escapes it
+    pub async fn resolve(&self, id: &str, relative: &str) -> Result<Option<PathBuf>> {
+        let dir = match self.existing_dir(id).await {
+            Some(dir) => dir,
+            None => return Ok(None),
+        };
+        
+        let relative = PathBuf::from(relative);
+        if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(anyhow!("Invalid path: {}", relative.display()));
+        }
+        
+        tokio::task::spawn_blocking(move || {
+            let candidate = dir.join(&relative);
+            if !candidate.is_file() {
+                return Ok(None);
+            }
+            
+            // Symlinks created by the program must not lead outside the directory
+            let canonical = candidate.canonicalize()?;
+            if !canonical.starts_with(dir.canonicalize()?) {
+                return Err(anyhow!("Invalid path: {}", relative.display()));
+            }
+            
+            Ok(Some(canonical))
+        }).await?
+    }
+    
+    /// Delete a preserved directory
+    pub async fn remove(&self, id: &str) -> Result<bool> {
+        let removed = self.expiries.lock().await.remove(id).is_some();
+        
+        if let Some(dir) = self.path_for(id) {
+            let marker = self.root.join(MARKER_DIR).join(id);
+            tokio::task::spawn_blocking(move || -> Result<()> {
+                if dir.exists() {
+                    fs::remove_dir_all(&dir)?;
+                }
+                if marker.exists() {
+                    fs::remove_file(&marker)?;
+                }
+                Ok(())
+            }).await??;
+        }
+        
+        Ok(removed)
+    }
+    
+    /// Delete every directory past its TTL, returning how many were removed
+    pub async fn sweep(&self) -> usize {
+        let now = Utc::now();
+        let expired: Vec<String> = {
+            let expiries = self.expiries.lock().await;
+            expiries.iter()
+                .filter(|(_, expires_at)| **expires_at <= now)
+                .map(|(id, _)| id.clone())
+                .collect()
+        };
+        
+        let mut removed = 0;
+        for id in expired {
+            match self.remove(&id).await {
+                Ok(_) => removed += 1,
+                Err(err) => warn!("Failed to remove preserved directory for {}: {}", id, err),
+            }
+        }
+        
+        if removed > 0 {
+            debug!("Removed {} expired preserved directories", removed);
+        }
+        removed
+    }
+    
+    /// Directory of a tracked, unexpired execution
+    async fn existing_dir(&self, id: &str) -> Option<PathBuf> {
+        let expiries = self.expiries.lock().await;
+        match expiries.get(id) {
+            Some(expires_at) if *expires_at > Utc::now() => self.path_for(id),
+            _ => None,
+        }
+    }
+    
+    /// Directory for an id, or None if the id isn't a safe single path component
+    fn path_for(&self, id: &str) -> Option<PathBuf> {
+        // A leading dot would reach the marker directory
+        let safe = !id.is_empty()
+            && !id.starts_with('.')
+            && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        
+        safe.then(|| self.root.join(id))
+    }
+}
+
+/// Recursively list regular files under `dir` relative to `root`
+pub(crate) fn list_tree(root: &Path, dir: &Path, entries: &mut Vec<WorkdirEntry>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        
+        if file_type.is_dir() {
+            list_tree(root, &path, entries)?;
+        } else if file_type.is_file() {
+            if let Ok(relative) = path.strip_prefix(root) {
+                entries.push(WorkdirEntry {
+                    path: relative.to_string_lossy().to_string(),
+                    size: entry.metadata()?.len(),
+                });
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn startup_leaves_files_it_did_not_write() {
+        let preserve_dir = tempfile::tempdir().unwrap();
+        fs::write(preserve_dir.path().join("operator.txt"), "keep").unwrap();
+        let foreign = preserve_dir.path().join(STORE_DIR).join("foreign");
+        fs::create_dir_all(&foreign).unwrap();
+        
+        WorkdirStore::new(preserve_dir.path().to_path_buf(), Duration::hours(1)).unwrap();
+        assert!(preserve_dir.path().join("operator.txt").exists());
+        assert!(foreign.exists());
+    }
+    
+    #[tokio::test]
+    async fn startup_discards_directories_a_previous_store_preserved() {
+        let preserve_dir = tempfile::tempdir().unwrap();
+        let store = WorkdirStore::new(preserve_dir.path().to_path_buf(), Duration::hours(1)).unwrap();
+        let work = tempfile::tempdir().unwrap();
+        fs::write(work.path().join("out.txt"), "42").unwrap();
+        store.preserve("job-1", work.path()).await.unwrap();
+        let kept = preserve_dir.path().join(STORE_DIR).join("job-1");
+        assert!(kept.join("out.txt").exists());
+        
+        WorkdirStore::new(preserve_dir.path().to_path_buf(), Duration::hours(1)).unwrap();
+        assert!(!kept.exists());
+    }
+    
+    #[tokio::test]
+    async fn preserved_directories_can_be_listed_read_and_removed() {
+        let preserve_dir = tempfile::tempdir().unwrap();
+        let store = WorkdirStore::new(preserve_dir.path().to_path_buf(), Duration::hours(1)).unwrap();
+        let work = tempfile::tempdir().unwrap();
+        fs::create_dir(work.path().join("sub")).unwrap();
+        fs::write(work.path().join("sub/out.txt"), "42").unwrap();
+        store.preserve("job-1", work.path()).await.unwrap();
+        
+        let entries = store.list("job-1").await.unwrap().unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.path.as_str()).collect::<Vec<_>>(), ["sub/out.txt"]);
+        let file = store.resolve("job-1", "sub/out.txt").await.unwrap().unwrap();
+        assert_eq!(fs::read_to_string(file).unwrap(), "42");
+        assert!(store.resolve("job-1", "../job-1/sub/out.txt").await.is_err());
+        
+        assert!(store.remove("job-1").await.unwrap());
+        assert!(store.list("job-1").await.unwrap().is_none());
+        assert!(!preserve_dir.path().join(STORE_DIR).join("job-1").exists());
+    }
+    
+    #[tokio::test]
+    async fn ids_cannot_reach_the_marker_directory() {
+        let preserve_dir = tempfile::tempdir().unwrap();
+        let store = WorkdirStore::new(preserve_dir.path().to_path_buf(), Duration::hours(1)).unwrap();
+        let work = tempfile::tempdir().unwrap();
+        assert!(store.preserve(MARKER_DIR, work.path()).await.is_err());
+        assert!(store.preserve("..", work.path()).await.is_err());
+    }
+}
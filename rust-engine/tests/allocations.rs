@@ -0,0 +1,74 @@
+//! Memory allocated when serving stored results.
+//!
+//! A counting global allocator is installed for this test binary only.
+mod common;
+
+use common::{engine, has_toolchain, run};
+use labforcode_engine::types::ResultView;
+use serde_json::json;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Passes every request to the system allocator, adding up the bytes asked for
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+    
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+    
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const POLLS: usize = 10_000;
+const STDOUT_BYTES: usize = 1 << 20;
+
+#[tokio::test]
+async fn polling_a_large_result_does_not_copy_its_output() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let id = run(&engine, json!({"language": "python", "source_code": format!("print('x' * {})", STDOUT_BYTES - 1)})).await.id.clone();
+    
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    for _ in 0..POLLS {
+        let result = engine.get_result(&id).await.unwrap().unwrap();
+        assert_eq!(result.stdout.as_ref().map(String::len), Some(STDOUT_BYTES));
+        let view = ResultView { result, include_output: false, fields: None };
+        serde_json::to_writer(std::io::sink(), &view).unwrap();
+    }
+    let per_poll = (ALLOCATED.load(Ordering::Relaxed) - before) / POLLS;
+    
+    // A single copy of the output would be a megabyte a poll
+    assert!(per_poll < 16 * 1024, "{} bytes allocated per poll", per_poll);
+}
+
+#[tokio::test]
+async fn results_without_output_keep_every_other_field() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({"language": "python", "source_code": "import sys\nprint('out')\nprint('err', file=sys.stderr)"})).await;
+    
+    let mut expected = serde_json::to_value(&*result).unwrap();
+    for field in ["stdout", "stderr", "compile_output"] {
+        expected[field] = serde_json::Value::Null;
+    }
+    let view = ResultView { result, include_output: false, fields: None };
+    assert_eq!(serde_json::to_value(&view).unwrap(), expected);
+}
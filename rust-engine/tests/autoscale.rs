@@ -0,0 +1,67 @@
+//! The worker pool following a burst of sleeping jobs up and back down.
+mod common;
+
+use common::has_toolchain;
+use labforcode_engine::config::AutoscaleConfig;
+use labforcode_engine::types::{CapacityUpdate, SubmissionContext};
+use labforcode_engine::{EngineConfig, ExecutionEngine};
+use std::time::{Duration, Instant};
+
+/// Worker count once `done` holds for it, polling the stats every 100ms for up to `within`
+async fn workers_when(engine: &ExecutionEngine, within: Duration, done: impl Fn(usize) -> bool) -> usize {
+    let deadline = Instant::now() + within;
+    loop {
+        let workers = engine.get_stats().await.unwrap().workers;
+        if done(workers) || Instant::now() > deadline {
+            return workers;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[tokio::test]
+async fn bursts_scale_the_pool_up_and_idle_time_scales_it_back_down() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let config = EngineConfig {
+        autoscale: AutoscaleConfig {
+            enabled: true,
+            min_workers: 1,
+            max_workers: 3,
+            scale_up_queue_depth: 2,
+            scale_up_wait_secs: 0.0,
+            scale_up_after_secs: 0,
+            scale_down_after_secs: 1,
+            step: 1,
+        },
+        ..Default::default()
+    };
+    let engine = ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap();
+    assert_eq!(engine.get_stats().await.unwrap().workers, 1);
+    
+    let mut ids = Vec::new();
+    for _ in 0..8 {
+        let request = serde_json::from_value(serde_json::json!({
+            "language": "python",
+            "source_code": "import time\ntime.sleep(1)",
+        })).unwrap();
+        ids.push(engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id);
+    }
+    
+    assert_eq!(workers_when(&engine, Duration::from_secs(20), |workers| workers == 3).await, 3);
+    let stats = engine.get_stats().await.unwrap();
+    assert!(stats.active_executions as usize <= stats.workers);
+    
+    for id in &ids {
+        engine.wait_for_completion(id, Duration::from_secs(60)).await.unwrap();
+    }
+    assert_eq!(workers_when(&engine, Duration::from_secs(20), |workers| workers == 1).await, 1);
+    
+    // New bounds from the admin API apply without waiting for the next burst
+    let update = CapacityUpdate { max_queue_depth: None, max_concurrent_executions: None, min_workers: Some(2), max_workers: None };
+    assert_eq!(engine.update_capacity(update).unwrap().max_concurrent_executions, 2);
+    assert_eq!(engine.get_stats().await.unwrap().workers, 2);
+    let inverted = CapacityUpdate { max_queue_depth: None, max_concurrent_executions: None, min_workers: Some(4), max_workers: Some(3) };
+    assert!(engine.update_capacity(inverted).is_err());
+}
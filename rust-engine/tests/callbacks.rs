@@ -0,0 +1,167 @@
+//! Signed result callbacks, checked by a receiver on a local port.
+mod common;
+
+use axum::body::Bytes;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use common::has_toolchain;
+use labforcode_engine::callback::{verify, SignatureError, DEFAULT_TOLERANCE, REQUEST_ID_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use labforcode_engine::types::SubmissionContext;
+use labforcode_engine::{EngineConfig, ExecutionEngine};
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// A callback as the receiver saw it, and whether its signature held up
+struct Delivery {
+    body: Bytes,
+    timestamp: String,
+    signature: String,
+    request_id: String,
+    checked: Result<(), SignatureError>,
+}
+
+/// Receiver that checks signatures against `secret` and refuses bad ones with 401
+async fn receiver(secret: &'static str) -> (String, mpsc::UnboundedReceiver<Delivery>) {
+    let (sender, received) = mpsc::unbounded_channel();
+    let app = Router::new().route("/hook", post(move |headers: HeaderMap, body: Bytes| async move {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).unwrap_or_default().to_string();
+        let (timestamp, signature) = (header(TIMESTAMP_HEADER), header(SIGNATURE_HEADER));
+        let checked = verify(secret.as_bytes(), &timestamp, &signature, &body, DEFAULT_TOLERANCE);
+        let status = if checked.is_ok() { StatusCode::OK } else { StatusCode::UNAUTHORIZED };
+        let request_id = header(REQUEST_ID_HEADER);
+        let _ = sender.send(Delivery { body, timestamp, signature, request_id, checked });
+        status
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (url, received)
+}
+
+/// Engine signing with `secret` that gives up on a callback after one attempt
+async fn engine(secret: &str) -> ExecutionEngine {
+    let mut config = EngineConfig::default();
+    config.callbacks.secret = Some(secret.to_string());
+    config.callbacks.max_attempts = 1;
+    ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap()
+}
+
+async fn submit(engine: &ExecutionEngine, url: &str) -> String {
+    let request = serde_json::from_value(json!({
+        "language": "python",
+        "source_code": "print(1)",
+        "callback_url": url,
+        "metadata": {"submission_id": "s-1"},
+    })).unwrap();
+    let context = SubmissionContext { request_id: Some("req-42".to_string()), ..Default::default() };
+    engine.submit_execution(request, context).await.unwrap().id
+}
+
+#[tokio::test]
+async fn receivers_accept_signed_callbacks_and_reject_modified_bodies() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let (url, mut received) = receiver("shared-secret").await;
+    let engine = engine("shared-secret").await;
+    let id = submit(&engine, &url).await;
+    
+    let delivery = tokio::time::timeout(Duration::from_secs(60), received.recv()).await.unwrap().unwrap();
+    assert_eq!(delivery.checked, Ok(()));
+    let result: serde_json::Value = serde_json::from_slice(&delivery.body).unwrap();
+    assert_eq!(result["id"], id);
+    assert_eq!(result["metadata"], json!({"submission_id": "s-1"}));
+    assert_eq!(delivery.request_id, "req-42");
+    
+    // The same signature over a body with one byte changed is refused
+    let mut tampered = delivery.body.to_vec();
+    let last = tampered.len() - 2;
+    tampered[last] ^= 1;
+    let checked = verify(b"shared-secret", &delivery.timestamp, &delivery.signature, &tampered, DEFAULT_TOLERANCE);
+    assert_eq!(checked, Err(SignatureError::Mismatch));
+}
+
+#[tokio::test]
+async fn callbacks_signed_with_another_secret_are_dead_lettered() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let (url, mut received) = receiver("shared-secret").await;
+    let engine = engine("stale-secret").await;
+    let id = submit(&engine, &url).await;
+    
+    let delivery = tokio::time::timeout(Duration::from_secs(60), received.recv()).await.unwrap().unwrap();
+    assert_eq!(delivery.checked, Err(SignatureError::Mismatch));
+    
+    // Dead-lettering follows the receiver's answer
+    for _ in 0..50 {
+        let page = engine.failed_callbacks(0, 10);
+        if let Some(entry) = page.items.first() {
+            assert_eq!(entry.job_id, id);
+            assert_eq!(entry.last_status, Some(401));
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("the refused callback was not dead-lettered");
+}
+
+#[tokio::test]
+async fn callbacks_refused_five_times_are_dead_lettered_and_can_be_retried() {
+    use sha2::{Digest, Sha256};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    // 500 for the first five posts, 200 afterwards
+    let posts = Arc::new(AtomicU32::new(0));
+    let (sender, mut received) = mpsc::unbounded_channel();
+    let counter = Arc::clone(&posts);
+    let app = Router::new().route("/hook", post(move |body: Bytes| async move {
+        let _ = sender.send(body);
+        match counter.fetch_add(1, Ordering::SeqCst) {
+            0..=4 => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::OK,
+        }
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    let id = submit(&engine, &url).await;
+    
+    // Five attempts back off 1, 2, 4 and 8 seconds
+    let mut entry = None;
+    for _ in 0..300 {
+        entry = engine.failed_callbacks(0, 10).items.into_iter().next();
+        if entry.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let entry = entry.expect("the refused callback was not dead-lettered");
+    assert_eq!(entry.job_id, id);
+    assert_eq!(entry.url, url);
+    assert_eq!(entry.attempts, 5);
+    assert_eq!(entry.last_status, Some(500));
+    let body = received.recv().await.unwrap();
+    let digest: String = Sha256::digest(&body).iter().map(|b| format!("{:02x}", b)).collect();
+    assert_eq!(entry.payload_sha256, digest);
+    let stats = engine.get_stats().await.unwrap();
+    assert_eq!((stats.callbacks_delivered, stats.callbacks_dead_lettered), (0, 1));
+    
+    assert!(engine.retry_callback(&id).await.unwrap().is_none());
+    assert_eq!(posts.load(Ordering::SeqCst), 6);
+    assert_eq!(engine.failed_callbacks(0, 10).total, 0);
+    let stats = engine.get_stats().await.unwrap();
+    assert_eq!((stats.callbacks_delivered, stats.callbacks_dead_lettered), (1, 1));
+    
+    // Nothing is left to retry
+    assert!(engine.retry_callback(&id).await.is_err());
+}
+
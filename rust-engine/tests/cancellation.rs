@@ -0,0 +1,133 @@
+//! Cancelling jobs while their programs run.
+mod common;
+
+use common::{has_toolchain, stdout};
+use labforcode_engine::types::{CancelFilter, CancelStatus, ExecutionState, SubmissionContext};
+use labforcode_engine::ExecutionEngine;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn bulk_cancel_kills_running_jobs_of_the_client() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    let submit = |client: &str, code: &str| {
+        let request = serde_json::from_value(json!({"language": "python", "source_code": code})).unwrap();
+        let context = SubmissionContext { client: Some(client.to_string()), ..Default::default() };
+        engine.submit_execution(request, context)
+    };
+    let flood = submit("flood", "import time\ntime.sleep(30)").await.unwrap().id;
+    let alice = submit("alice", "print('kept')").await.unwrap().id;
+    
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while engine.get_status(&flood).await.unwrap().unwrap().status != ExecutionState::Running {
+        assert!(Instant::now() < deadline, "the job never started");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let filter = CancelFilter {
+        client: Some("flood".to_string()),
+        status: Some(vec![CancelStatus::Running]),
+        ..Default::default()
+    };
+    let started = Instant::now();
+    let response = engine.cancel_matching(&filter).await.unwrap();
+    assert_eq!(response.by_status["running"], 1);
+    
+    // The killed program frees the worker long before its sleep ends
+    engine.wait_for_completion(&alice, Duration::from_secs(20)).await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(20));
+    assert_eq!(engine.get_status(&flood).await.unwrap().unwrap().status, ExecutionState::Cancelled);
+    let kept = engine.get_result(&alice).await.unwrap().unwrap();
+    assert_eq!(stdout(&kept), "kept");
+}
+
+#[tokio::test]
+async fn jobs_running_at_shutdown_end_in_a_terminal_state() {
+    if !has_toolchain(&["python3", "sleep"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(2).build().await.unwrap();
+    let submit = |code: &str| {
+        let request = serde_json::from_value(json!({"language": "python", "source_code": code})).unwrap();
+        engine.submit_execution(request, SubmissionContext::default())
+    };
+    let straggler = submit("import os\nos.execvp('sleep', ['sleep', '27.1828'])").await.unwrap().id;
+    let quick = submit("import time\ntime.sleep(0.3)\nprint('finished')").await.unwrap().id;
+    
+    let deadline = Instant::now() + Duration::from_secs(30);
+    for id in [&straggler, &quick] {
+        while engine.get_status(id).await.unwrap().unwrap().status != ExecutionState::Running {
+            assert!(Instant::now() < deadline, "the job never started");
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+    let started = Instant::now();
+    engine.shutdown(Duration::from_secs(2)).await;
+    assert!(started.elapsed() < Duration::from_secs(5));
+    
+    // The quick job finished within the drain; the straggler was killed and cancelled
+    let finished = engine.get_result(&quick).await.unwrap().unwrap();
+    assert_eq!((finished.status.clone(), stdout(&finished)), (ExecutionState::Completed, "finished"));
+    assert_eq!(engine.get_status(&straggler).await.unwrap().unwrap().status, ExecutionState::Cancelled);
+    let sleeping = || std::fs::read_dir("/proc").unwrap().flatten()
+        .filter_map(|entry| std::fs::read(entry.path().join("cmdline")).ok())
+        .any(|cmdline| cmdline == b"sleep\x0027.1828\x00");
+    // SIGKILL takes a moment to land
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while sleeping() && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(!sleeping(), "the straggler's program outlived the shutdown");
+}
+
+
+#[tokio::test]
+async fn cancelled_jobs_stop_between_runs_and_keep_the_runs_that_finished() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    let request = serde_json::from_value(json!({
+        "language": "python",
+        "source_code": "import time\ntime.sleep(0.5)\nprint('ran')",
+        "number_of_runs": 10,
+    })).unwrap();
+    let id = engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id;
+    
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while engine.get_status(&id).await.unwrap().unwrap().status != ExecutionState::Running {
+        assert!(Instant::now() < deadline, "the job never started");
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    assert!(engine.cancel_execution(&id).await.unwrap());
+    
+    // The run in progress finishes, then the partial result is stored
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let result = loop {
+        if engine.lookup(&id).await.unwrap().unwrap().result.is_some() {
+            break engine.get_result(&id).await.unwrap().unwrap();
+        }
+        assert!(Instant::now() < deadline, "the cancelled job kept no result");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    assert_eq!(result.status, ExecutionState::Cancelled);
+    assert_eq!(engine.get_status(&id).await.unwrap().unwrap().status, ExecutionState::Cancelled);
+    let runs = stdout(&result).matches("ran").count();
+    assert!((2..=3).contains(&runs), "{} runs finished:\n{}", runs, stdout(&result));
+}
+
+#[tokio::test]
+async fn embedders_are_held_to_the_run_ceiling() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    let request = serde_json::from_value(json!({"language": "python", "source_code": "print('ran')", "number_of_runs": 1000})).unwrap();
+    let id = engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id;
+    engine.wait_for_completion(&id, Duration::from_secs(60)).await.unwrap();
+    let result = engine.get_result(&id).await.unwrap().unwrap();
+    assert_eq!(stdout(&result).matches("ran").count(), 20);
+}
@@ -0,0 +1,37 @@
+//! Subcommands of the server binary that exit without serving.
+mod common;
+
+use common::has_toolchain;
+use std::process::{Command, Output};
+
+/// Run the server binary with these arguments and the default configuration
+fn engine(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_labforcode-engine"))
+        .args(args)
+        .env_remove("ENGINE_CONFIG")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn check_passes_when_every_language_runs() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let output = engine(&["check", "--languages", "python"]);
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", report);
+    assert!(report.contains("1 of 1 passed"), "{}", report);
+}
+
+#[test]
+fn check_fails_when_a_language_does_not_run() {
+    let output = engine(&["check", "--languages", "cobol"]);
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", report);
+    assert!(report.contains("cobol        FAIL"), "{}", report);
+    assert!(report.contains("0 of 1 passed"), "{}", report);
+    
+    // Languages must be named unless every one is checked
+    assert_eq!(engine(&["check"]).status.code(), Some(2));
+}
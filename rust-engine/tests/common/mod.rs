@@ -0,0 +1,72 @@
+//! Helpers shared by the integration tests.
+//!
+//! These tests run real programs through the engine. Each one names the toolchains it needs and
+//! returns early, with a note on stderr, on hosts that lack them.
+#![allow(dead_code)]
+
+use labforcode_engine::types::{ExecutionResult, SubmissionContext};
+use labforcode_engine::ExecutionEngine;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// JVM sizes that fit a 512MB address space limit, for javac's `-J` options and JAVA_TOOL_OPTIONS
+pub const JVM_FLAGS: &[&str] = &["-Xmx64m", "-XX:ReservedCodeCacheSize=32m", "-XX:CompressedClassSpaceSize=64m", "-XX:+UseSerialGC"];
+
+/// A Java request with the JVM sized to fit under its memory limit
+pub fn java(mut request: serde_json::Value) -> serde_json::Value {
+    let compiler_options: Vec<String> = JVM_FLAGS.iter().map(|flag| format!("-J{}", flag)).collect();
+    request["compiler_options"] = compiler_options.join(" ").into();
+    request["env"] = serde_json::json!({"JAVA_TOOL_OPTIONS": JVM_FLAGS.join(" ")});
+    request["memory_limit"] = (512 * 1024 * 1024).into();
+    request
+}
+
+/// Whether every tool is on the PATH, printing which test is skipped when one is not
+pub fn has_toolchain(tools: &[&str]) -> bool {
+    let missing: Vec<&str> = tools.iter()
+        .copied()
+        .filter(|tool| {
+            !Command::new("sh")
+                .args(["-c", &format!("command -v {}", tool)])
+                .stdout(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+        })
+        .collect();
+    if !missing.is_empty() {
+        eprintln!("skipped: needs {}", missing.join(", "));
+    }
+    missing.is_empty()
+}
+
+/// Engine with a single worker and the default configuration
+pub async fn engine() -> ExecutionEngine {
+    ExecutionEngine::builder().max_concurrent(1).build().await.unwrap()
+}
+
+/// Submit a JSON request and wait for its result
+pub async fn run(engine: &ExecutionEngine, request: serde_json::Value) -> Arc<ExecutionResult> {
+    let request = serde_json::from_value(request).unwrap();
+    let id = engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id;
+    engine.wait_for_completion(&id, Duration::from_secs(60)).await.unwrap();
+    engine.get_result(&id).await.unwrap().unwrap()
+}
+
+/// Stdout of a result, with the trailing newline dropped
+pub fn stdout(result: &ExecutionResult) -> &str {
+    result.stdout.as_deref().unwrap_or_default().trim_end()
+}
+
+/// Base64 zip of `files`, as sent in additional_files
+pub fn zip(files: &[(&str, &str)]) -> String {
+    use base64::{engine::general_purpose, Engine as _};
+    use std::io::Write;
+    
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, content) in files {
+        zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+    }
+    general_purpose::STANDARD.encode(zip.finish().unwrap().into_inner())
+}
@@ -0,0 +1,153 @@
+//! Driving the engine as a library, without the HTTP server.
+mod common;
+
+use common::{has_toolchain, run, stdout};
+use labforcode_engine::config::RuntimeSettings;
+use labforcode_engine::executor::ExecutionBackend;
+use labforcode_engine::queue::ExecutionQueue;
+use labforcode_engine::types::{ExecutionState, SubmissionContext};
+use labforcode_engine::validation::RequestValidator;
+use labforcode_engine::{EngineConfig, ExecutionEngine};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn builder_options_override_the_configuration() {
+    let limits = RequestValidator { max_cpu_time: 10.0, ..RequestValidator::default() };
+    let engine = ExecutionEngine::builder()
+        .max_concurrent(3)
+        .max_queue_depth(7)
+        .queue(Arc::new(ExecutionQueue::new().await.unwrap()))
+        .limits(limits)
+        .build()
+        .await
+        .unwrap();
+    
+    let capacity = engine.capacity();
+    assert_eq!(capacity.max_concurrent_executions, 3);
+    assert_eq!(capacity.max_queue_depth, 7);
+    assert_eq!(engine.validator().max_cpu_time, 10.0);
+    
+    // Submissions are checked against the ceilings given to the builder
+    let request = serde_json::from_value(json!({"language": "python", "source_code": "", "cpu_time_limit": 12.0})).unwrap();
+    let errors = engine.validator().validate(&request, engine.support(&request));
+    assert!(errors.iter().any(|error| error.field == "cpu_time_limit"), "{:?}", errors);
+}
+
+#[tokio::test]
+async fn submissions_run_without_the_server() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    let result = run(&engine, json!({"language": "python", "source_code": "print(6 * 7)"})).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed);
+    assert_eq!(stdout(&result), "42");
+}
+
+#[tokio::test]
+async fn jobs_name_the_instance_and_worker_that_ran_them() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let config = EngineConfig { instance_name: Some("engine-eu-1".to_string()), ..EngineConfig::default() };
+    let engine = ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap();
+    let result = run(&engine, json!({"language": "python", "source_code": "print(1)"})).await;
+    
+    let runner = result.runner.clone().unwrap();
+    assert_eq!((runner.worker_id.as_str(), runner.worker), ("engine-eu-1-1", 1));
+    assert_eq!(runner.backend, ExecutionBackend::Process);
+    let status = engine.get_status(&result.id).await.unwrap().unwrap();
+    assert_eq!(status.runner, Some(runner.clone()));
+    let events = engine.events(&result.id).await.unwrap().unwrap();
+    assert_eq!(events.last().unwrap().runner, Some(runner));
+    assert_eq!(engine.get_stats().await.unwrap().instance, "engine-eu-1");
+}
+
+#[tokio::test]
+async fn running_jobs_keep_the_settings_they_started_with() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    let request = serde_json::from_value(json!({"language": "python", "source_code": "import time\ntime.sleep(0.3)\nprint('done')"})).unwrap();
+    let id = engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id;
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while engine.get_status(&id).await.unwrap().unwrap().status != ExecutionState::Running {
+        assert!(Instant::now() < deadline, "the job never started");
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    
+    let changes: RuntimeSettings = serde_json::from_value(json!({"disabled_languages": ["python"]})).unwrap();
+    engine.update_settings(changes, "key:admin", None).await.unwrap();
+    engine.wait_for_completion(&id, Duration::from_secs(30)).await.unwrap();
+    let result = engine.get_result(&id).await.unwrap().unwrap();
+    assert_eq!(result.status, ExecutionState::Completed);
+    assert_eq!(stdout(&result), "done");
+}
+
+#[tokio::test]
+async fn paused_jobs_wait_and_run_as_soon_as_resumed() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    engine.pause();
+    let request = serde_json::from_value(json!({"language": "python", "source_code": "print('resumed')"})).unwrap();
+    let id = engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id;
+    
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let job = engine.lookup(&id).await.unwrap().unwrap();
+    assert_eq!((job.status, job.started_at), (ExecutionState::Queued, None));
+    
+    // Workers are woken rather than left to their next poll
+    let resumed = chrono::Utc::now();
+    engine.resume();
+    engine.wait_for_completion(&id, Duration::from_secs(30)).await.unwrap();
+    let job = engine.lookup(&id).await.unwrap().unwrap();
+    assert_eq!(stdout(job.result.as_ref().unwrap()), "resumed");
+    let started = job.started_at.unwrap() - resumed;
+    assert!(started < chrono::Duration::seconds(1), "started {} after resuming", started);
+}
+
+#[tokio::test]
+async fn finished_jobs_answer_identical_dedupe_submissions() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    let submission = json!({"language": "python", "source_code": "import random\nprint(random.random())", "dedupe": true});
+    let first = run(&engine, submission.clone()).await;
+    
+    let request = serde_json::from_value(submission).unwrap();
+    let again = engine.submit_execution(request, SubmissionContext::default()).await.unwrap();
+    assert_eq!((again.id.as_str(), again.status.as_str()), (first.id.as_str(), "deduplicated"));
+    assert!(again.message.contains("already finished"), "{}", again.message);
+    let result = engine.get_result(&again.id).await.unwrap().unwrap();
+    assert_eq!(stdout(&result), stdout(&first), "the earlier output stands in for a new run");
+}
+
+#[tokio::test]
+async fn finished_jobs_record_each_step_they_went_through() {
+    if !has_toolchain(&["gcc", "python3"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    let cases = [
+        (json!({"language": "c", "source_code": "int main(void) { return 0; }"}), vec!["submitted", "started", "compiled", "completed"]),
+        (json!({"language": "python", "source_code": "print(1)"}), vec!["submitted", "started", "completed"]),
+    ];
+    for (submission, expected) in cases {
+        let id = run(&engine, submission).await.id.clone();
+        let events = engine.events(&id).await.unwrap().unwrap();
+        let kinds: Vec<String> = events.iter()
+            .map(|event| serde_json::to_value(event.event).unwrap().as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(kinds, expected);
+        assert!(events.windows(2).all(|pair| pair[0].at <= pair[1].at));
+        let summary = events.last().unwrap().result.as_ref().expect("the final event carries the result");
+        assert_eq!((&summary.status, summary.exit_code), (&ExecutionState::Completed, Some(0)));
+    }
+}
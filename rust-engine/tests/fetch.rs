@@ -0,0 +1,92 @@
+//! Inputs given by URL, downloaded from an origin on a local port.
+mod common;
+
+use axum::http::header;
+use axum::routing::get;
+use axum::Router;
+use common::{has_toolchain, stdout, zip};
+use labforcode_engine::types::{ExecutionResult, ExecutionState, SubmissionContext};
+use labforcode_engine::{EngineConfig, ExecutionEngine};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Origin serving a Python program, a ZIP of its input, and the same program after a delay
+async fn origin() -> String {
+    use base64::{engine::general_purpose, Engine as _};
+    
+    let archive = general_purpose::STANDARD.decode(zip(&[("data/input.txt", "from the archive")])).unwrap();
+    let source = "print(open('data/input.txt').read())";
+    let app = Router::new()
+        .route("/main.py", get(move || async move { ([(header::CONTENT_TYPE, "text/plain")], source) }))
+        .route("/files.zip", get(move || async move { ([(header::CONTENT_TYPE, "application/zip")], archive) }))
+        .route("/slow.py", get(|| async {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            ([(header::CONTENT_TYPE, "text/plain")], "print('slow')")
+        }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    base
+}
+
+/// Engine that may download from the local origin only
+async fn engine() -> ExecutionEngine {
+    let mut config = EngineConfig::default();
+    config.fetch.allowed_hosts = vec!["127.0.0.1".to_string()];
+    ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap()
+}
+
+async fn run(engine: &ExecutionEngine, request: serde_json::Value) -> Arc<ExecutionResult> {
+    let request = serde_json::from_value(request).unwrap();
+    let id = engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id;
+    engine.wait_for_completion(&id, Duration::from_secs(60)).await.unwrap();
+    engine.get_result(&id).await.unwrap().unwrap()
+}
+
+#[tokio::test]
+async fn downloaded_sources_and_files_run_like_inline_ones() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let base = origin().await;
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_url": format!("{}/main.py", base),
+        "additional_files_url": format!("{}/files.zip", base),
+    })).await;
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.stderr);
+    assert_eq!(stdout(&result), "from the archive");
+}
+
+#[tokio::test]
+async fn downloads_do_not_count_against_the_wall_time_limit() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let base = origin().await;
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_url": format!("{}/slow.py", base),
+        "wall_time_limit": 1.0,
+    })).await;
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.stderr);
+    assert_eq!(stdout(&result), "slow");
+}
+
+#[tokio::test]
+async fn failed_downloads_fail_the_job_with_a_fetch_error() {
+    let base = origin().await;
+    let engine = engine().await;
+    
+    let missing = run(&engine, json!({"language": "python", "source_url": format!("{}/missing.py", base)})).await;
+    assert_eq!(missing.status, ExecutionState::InternalError);
+    assert_eq!(missing.failure_reason.as_deref(), Some("fetch_error: source_url: origin answered 404 Not Found"));
+    
+    let elsewhere = run(&engine, json!({"language": "python", "source_url": "http://files.example.com/main.py"})).await;
+    assert_eq!(elsewhere.status, ExecutionState::InternalError);
+    assert!(elsewhere.failure_reason.as_deref().unwrap().starts_with("fetch_error: source_url:"));
+    assert!(elsewhere.started_at.is_none());
+}
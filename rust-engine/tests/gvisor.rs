@@ -0,0 +1,63 @@
+//! Strongly isolated jobs under gVisor; set LABFORCODE_TEST_RUNSC=1 on hosts with docker and runsc to run these.
+mod common;
+
+use common::{run, stdout};
+use labforcode_engine::executor::ExecutionBackend;
+use labforcode_engine::types::{ExecutionState, IsolationLevel};
+use labforcode_engine::{EngineConfig, ExecutionEngine};
+use serde_json::json;
+
+/// Docker-backed engine, or None unless LABFORCODE_TEST_RUNSC is set and docker offers runsc
+async fn gvisor_engine() -> Option<ExecutionEngine> {
+    if std::env::var_os("LABFORCODE_TEST_RUNSC").is_none() {
+        eprintln!("skipped: needs LABFORCODE_TEST_RUNSC");
+        return None;
+    }
+    let mut config = EngineConfig::default();
+    config.sandbox.backend = ExecutionBackend::Docker;
+    let engine = ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap();
+    assert_eq!(engine.docker_runtimes().get("runsc"), Some(&true), "LABFORCODE_TEST_RUNSC is set but docker has no runsc");
+    Some(engine)
+}
+
+#[tokio::test]
+async fn strong_jobs_run_under_gvisor_with_their_usage_measured() {
+    let Some(engine) = gvisor_engine().await else {
+        return;
+    };
+    // gVisor reports its own kernel in place of the host's
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": "import platform\nprint(sum(range(10**6)))\nprint(platform.release())",
+        "isolation": "strong",
+    })).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.failure_reason);
+    let lines: Vec<&str> = stdout(&result).lines().collect();
+    assert_eq!(lines[0], "499999500000");
+    assert_eq!(result.options.as_ref().unwrap().isolation, IsolationLevel::Strong);
+    assert!(result.time.is_some_and(|time| time > 0.0), "{:?}", result.time);
+    assert!(result.memory.is_some_and(|memory| memory > 0), "{:?}", result.memory);
+}
+
+#[tokio::test]
+async fn strong_jobs_still_hit_their_limits() {
+    let Some(engine) = gvisor_engine().await else {
+        return;
+    };
+    let hog = run(&engine, json!({
+        "language": "python",
+        "source_code": "blocks = []\nwhile True:\n    blocks.append(bytearray(16 * 1024 * 1024))",
+        "memory_limit": 128 * 1024 * 1024,
+        "isolation": "strong",
+    })).await;
+    assert_eq!(hog.status, ExecutionState::MemoryLimitExceeded, "{:?}", hog.failure_reason);
+    
+    let spin = run(&engine, json!({
+        "language": "python",
+        "source_code": "while True:\n    pass",
+        "cpu_time_limit": 1.0,
+        "isolation": "strong",
+    })).await;
+    assert_eq!(spin.status, ExecutionState::TimeLimitExceeded, "{:?}", spin.failure_reason);
+}
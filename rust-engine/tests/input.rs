@@ -0,0 +1,46 @@
+//! How programs receive their input.
+mod common;
+
+use base64::{engine::general_purpose, Engine as _};
+use common::{engine, has_toolchain, run, stdout, zip};
+use labforcode_engine::types::ExecutionState;
+use serde_json::json;
+
+/// Prints every byte of stdin as two hex digits
+const HEXDUMP: &str = "#include <stdio.h>\nint main(void) {\n    int c;\n    while ((c = getchar()) != EOF) printf(\"%02x\", c);\n    return 0;\n}\n";
+
+#[tokio::test]
+async fn binary_stdin_reaches_the_program_unchanged() {
+    if !has_toolchain(&["gcc"]) {
+        return;
+    }
+    let engine = engine().await;
+    let blob: Vec<u8> = (0..65536u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+    let result = run(&engine, json!({
+        "language": "c",
+        "source_code": HEXDUMP,
+        "stdin_base64": general_purpose::STANDARD.encode(&blob),
+    })).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.compile_output);
+    let hex: String = blob.iter().map(|byte| format!("{:02x}", byte)).collect();
+    assert!(stdout(&result) == hex, "stdout differs from the blob");
+}
+
+#[tokio::test]
+async fn failed_runs_list_the_files_the_program_could_open() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": "print(open('input.csv').read())",
+        "additional_files": zip(&[("data/input.csv", "1,2\n")]),
+        "list_workdir": true,
+    })).await;
+    
+    assert_eq!(result.status, ExecutionState::RuntimeError);
+    let paths: Vec<&str> = result.workdir_listing.iter().flatten().map(|entry| entry.path.as_str()).collect();
+    assert_eq!(paths, ["data/input.csv", "main.py"]);
+}
@@ -0,0 +1,159 @@
+//! Interactive runs, where the submission converses with a judge-provided interactor.
+mod common;
+
+use common::{engine, has_toolchain, run};
+use labforcode_engine::types::{CommandPhase, ExecutionState};
+use serde_json::json;
+use std::time::Instant;
+
+/// Answers guesses of the number in input.txt with "<", ">" or "=", accepting within 10 guesses
+const INTERACTOR: &str = r#"
+import sys
+secret = int(open("input.txt").read())
+for guesses in range(1, 11):
+    line = sys.stdin.readline()
+    if not line:
+        print("no guess", file=sys.stderr)
+        sys.exit(1)
+    guess = int(line)
+    if guess == secret:
+        print("=", flush=True)
+        print(f"found in {guesses}", file=sys.stderr)
+        sys.exit(0)
+    print("<" if secret < guess else ">", flush=True)
+print("too many guesses", file=sys.stderr)
+sys.exit(1)
+"#;
+
+/// Binary search over 1..=1000
+const SEARCH: &str = r#"
+import sys
+low, high = 1, 1000
+while True:
+    guess = (low + high) // 2
+    print(guess, flush=True)
+    answer = input()
+    if answer == "=":
+        break
+    if answer == "<":
+        high = guess - 1
+    else:
+        low = guess + 1
+print("done", file=sys.stderr)
+"#;
+
+fn interactive(source: &str, interactor: &str, secret: &str) -> serde_json::Value {
+    json!({
+        "language": "python",
+        "source_code": source,
+        "stdin": secret,
+        "interactor": {"language": "python", "source_code": interactor},
+    })
+}
+
+#[tokio::test]
+async fn binary_search_is_accepted_by_the_interactor() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, interactive(SEARCH, INTERACTOR, "737")).await;
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result);
+    
+    // Each side's stderr is kept apart
+    let interactor = result.interactor.as_ref().unwrap();
+    assert_eq!((interactor.exit_code, interactor.stderr.trim()), (0, "found in 10"));
+    assert_eq!(interactor.exit_description, "exited normally with code 0");
+    assert_eq!(result.stderr.as_deref().map(str::trim), Some("done"));
+}
+
+#[tokio::test]
+async fn the_interactors_exit_code_is_the_verdict() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, interactive("print(1, flush=True)\ninput()", INTERACTOR, "737")).await;
+    assert_eq!(result.status, ExecutionState::WrongAnswer, "{:?}", result);
+    let interactor = result.interactor.as_ref().unwrap();
+    assert_eq!((interactor.exit_code, interactor.stderr.trim()), (1, "no guess"));
+    
+    // A submission that crashes is a runtime error whatever the interactor says
+    let result = run(&engine, interactive("raise SystemExit(4)", INTERACTOR, "737")).await;
+    assert_eq!((result.status.clone(), result.exit_code), (ExecutionState::RuntimeError, Some(4)));
+    
+    // An interactor that crashes is the judge's fault
+    let result = run(&engine, interactive("pass", "import os, signal\nos.kill(os.getpid(), signal.SIGSEGV)", "737")).await;
+    assert_eq!(result.status, ExecutionState::InternalError, "{:?}", result);
+    assert_eq!(result.failure_reason.as_deref(), Some("interactor killed by SIGSEGV"));
+}
+
+#[tokio::test]
+async fn deadlocks_end_at_the_wall_time_limit() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let mut request = interactive("input()", "input()", "1");
+    request["wall_time_limit"] = 1.0.into();
+    let started = Instant::now();
+    let result = run(&engine, request).await;
+    assert_eq!(result.status, ExecutionState::TimeLimitExceeded, "{:?}", result);
+    assert!(started.elapsed().as_secs_f64() < 10.0, "took {:?}", started.elapsed());
+    
+    // Both sides were killed rather than left blocked on each other
+    assert_eq!(result.signal.as_deref(), Some("SIGKILL"));
+    assert_eq!(result.interactor.as_ref().unwrap().signal.as_deref(), Some("SIGKILL"));
+}
+
+/// The INTERACTOR protocol in C, so it has a compile step
+const C_INTERACTOR: &str = r#"
+#include <stdio.h>
+int main(void) {
+    int secret, guess;
+    FILE *input = fopen("input.txt", "r");
+    fscanf(input, "%d", &secret);
+    for (int guesses = 1; guesses <= 10; guesses++) {
+        if (scanf("%d", &guess) != 1) return 1;
+        if (guess == secret) {
+            printf("=\n");
+            fflush(stdout);
+            return 0;
+        }
+        printf(secret < guess ? "<\n" : ">\n");
+        fflush(stdout);
+    }
+    return 1;
+}
+"#;
+
+#[tokio::test]
+async fn the_interactor_is_compiled_once_for_every_run() {
+    if !has_toolchain(&["gcc", "python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let mut request = interactive(SEARCH, C_INTERACTOR, "42");
+    request["interactor"]["language"] = "c".into();
+    request["number_of_runs"] = 3.into();
+    let result = run(&engine, request).await;
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result);
+    
+    let phases: Vec<CommandPhase> = result.executed_commands.iter().map(|command| command.phase).collect();
+    assert_eq!(phases, [
+        CommandPhase::InteractorCompile,
+        CommandPhase::Run, CommandPhase::Interactor,
+        CommandPhase::Run, CommandPhase::Interactor,
+        CommandPhase::Run, CommandPhase::Interactor,
+    ]);
+    
+    // A broken judge fails the job before the submission ever runs
+    let mut request = interactive(SEARCH, "int main(void) { return }", "42");
+    request["interactor"]["language"] = "c".into();
+    request["number_of_runs"] = 3.into();
+    let result = run(&engine, request).await;
+    assert_eq!(result.status, ExecutionState::InternalError);
+    assert_eq!(result.failure_reason.as_deref(), Some("interactor did not compile"));
+    let phases: Vec<CommandPhase> = result.executed_commands.iter().map(|command| command.phase).collect();
+    assert_eq!(phases, [CommandPhase::InteractorCompile]);
+}
@@ -0,0 +1,72 @@
+//! The isolate backend against a real isolate install; skipped where isolate is not on the PATH.
+mod common;
+
+use common::{has_toolchain, run, stdout};
+use labforcode_engine::executor::ExecutionBackend;
+use labforcode_engine::types::ExecutionState;
+use labforcode_engine::{EngineConfig, ExecutionEngine};
+use serde_json::json;
+use std::time::Duration;
+
+/// Engine on the isolate backend with two boxes, from a range other isolate users are unlikely to hold
+async fn isolate_engine() -> Option<ExecutionEngine> {
+    if !has_toolchain(&["isolate", "python3"]) {
+        return None;
+    }
+    let mut config = EngineConfig::default();
+    config.sandbox.backend = ExecutionBackend::Isolate;
+    config.sandbox.isolate.boxes = 2;
+    config.sandbox.isolate.first_box_id = 900;
+    Some(ExecutionEngine::builder().config(config).max_concurrent(4).build().await.unwrap())
+}
+
+#[tokio::test]
+async fn programs_run_in_boxes_with_metered_usage() {
+    let Some(engine) = isolate_engine().await else {
+        return;
+    };
+    let result = run(&engine, json!({"language": "python", "source_code": "import os\nprint(os.getcwd(), input())", "stdin": "hi"})).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.failure_reason);
+    assert_eq!(stdout(&result), "/box hi");
+    assert!(result.time.is_some_and(|time| time > 0.0));
+    assert!(result.memory.is_some_and(|memory| memory > 0));
+}
+
+#[tokio::test]
+async fn isolate_verdicts_map_to_states() {
+    let Some(engine) = isolate_engine().await else {
+        return;
+    };
+    let cases = [
+        (json!({"source_code": "while True:\n    pass", "cpu_time_limit": 0.5}), ExecutionState::TimeLimitExceeded),
+        (json!({"source_code": "x = bytearray(512 * 1024 * 1024)", "memory_limit": 64 * 1024 * 1024}), ExecutionState::MemoryLimitExceeded),
+        (json!({"source_code": "import sys\nsys.exit(3)"}), ExecutionState::RuntimeError),
+        (json!({"source_code": "import os, signal\nos.kill(os.getpid(), signal.SIGSEGV)"}), ExecutionState::RuntimeError),
+    ];
+    for (mut request, expected) in cases {
+        request["language"] = "python".into();
+        let result = run(&engine, request.clone()).await;
+        assert_eq!(result.status, expected, "{} gave {:?}", request, result.failure_reason);
+    }
+}
+
+#[tokio::test]
+async fn more_jobs_than_boxes_wait_their_turn() {
+    let Some(engine) = isolate_engine().await else {
+        return;
+    };
+    // Four workers share two boxes, and every job still finds its box empty
+    let mut ids = Vec::new();
+    for n in 0..6 {
+        let source = format!("import os\nprint(sorted(os.listdir('.')))\nopen('left-{}', 'w').write('x')", n);
+        let request = serde_json::from_value(json!({"language": "python", "source_code": source})).unwrap();
+        ids.push(engine.submit_execution(request, Default::default()).await.unwrap().id);
+    }
+    for id in &ids {
+        engine.wait_for_completion(id, Duration::from_secs(60)).await.unwrap();
+        let result = engine.get_result(id).await.unwrap().unwrap();
+        assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.failure_reason);
+        assert!(!stdout(&result).contains("left-"), "{}", stdout(&result));
+    }
+}
@@ -0,0 +1,147 @@
+//! What one job can see of the engine and of other jobs.
+mod common;
+
+use common::{engine, has_toolchain, run, stdout};
+use labforcode_engine::types::{ExecutionState, SubmissionContext};
+use labforcode_engine::ExecutionEngine;
+use serde_json::json;
+use std::time::Duration;
+
+#[tokio::test]
+async fn programs_do_not_see_the_engine_environment() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    // Stands in for REDIS_URL, API keys and the like; only this test in this binary reads it
+    std::env::set_var("LABFORCODE_TEST_SECRET", "hunter2");
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": "import os\nfor key, value in sorted(os.environ.items()):\n    print(f'{key}={value}')\n",
+        "env": {"GREETING": "hello"},
+    })).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.stderr);
+    let environment = stdout(&result);
+    assert!(!environment.contains("hunter2"), "{}", environment);
+    // cargo gives the test process a handful of its own variables, none of which may leak either
+    for (key, _) in std::env::vars().filter(|(key, _)| key.starts_with("CARGO")) {
+        assert!(!environment.contains(&format!("{}=", key)), "{} leaked into {}", key, environment);
+    }
+    let home = std::env::var("HOME").unwrap_or_default();
+    assert!(!environment.lines().any(|line| line == format!("HOME={}", home)), "{}", environment);
+    assert!(environment.contains("GREETING=hello"), "{}", environment);
+    assert!(environment.contains("LANG=C.UTF-8"), "{}", environment);
+}
+
+#[tokio::test]
+async fn concurrent_jobs_get_their_own_tmp() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(2).build().await.unwrap();
+    if !engine.sandbox_features().filesystem_isolation {
+        eprintln!("skipped: needs mount namespaces for a private /tmp");
+        return;
+    }
+    
+    // Each job finds /tmp empty, writes its name there, and reads it back once both have written
+    let program = |name: &str| json!({
+        "language": "python",
+        "source_code": format!(
+            "import os, time\nseen = os.path.exists('/tmp/shared.txt')\nopen('/tmp/shared.txt', 'w').write('{}')\ntime.sleep(1)\nprint(seen, open('/tmp/shared.txt').read())\n",
+            name
+        ),
+    });
+    let mut ids = Vec::new();
+    for name in ["first", "second"] {
+        let request = serde_json::from_value(program(name)).unwrap();
+        ids.push(engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id);
+    }
+    
+    for (id, name) in ids.iter().zip(["first", "second"]) {
+        engine.wait_for_completion(id, Duration::from_secs(60)).await.unwrap();
+        let result = engine.get_result(id).await.unwrap().unwrap();
+        assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.stderr);
+        assert_eq!(stdout(&result), format!("False {}", name));
+    }
+    assert!(!std::path::Path::new("/tmp/shared.txt").exists(), "the host's /tmp was written");
+}
+
+#[tokio::test]
+async fn runs_of_a_job_start_from_a_fresh_copy() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    // Each run appends to a file in the working directory and prints how many runs it holds
+    let counts = |fresh: bool| {
+        let engine = &engine;
+        async move {
+            let result = run(engine, json!({
+                "language": "python",
+                "source_code": "open('runs.txt', 'a').write('run\\n')\nprint(len(open('runs.txt').readlines()))",
+                "number_of_runs": 3,
+                "fresh_workdir_per_run": fresh,
+            })).await;
+            assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.stderr);
+            stdout(&result).lines()
+                .filter(|line| !line.is_empty() && !line.starts_with("---"))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        }
+    };
+    
+    assert_eq!(counts(true).await, ["1", "1", "1"]);
+    assert_eq!(counts(false).await, ["1", "2", "3"]);
+}
+
+#[tokio::test]
+async fn jobs_see_neither_the_host_tmp_nor_other_jobs() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    if !engine.sandbox_features().filesystem_isolation {
+        eprintln!("skipped: mount namespaces are unavailable");
+        return;
+    }
+    
+    // Stands in for the working directory of another job
+    let sibling = tempfile::TempDir::new_in(&engine.config().sandbox.temp_base).unwrap();
+    std::fs::write(sibling.path().join("secret.txt"), "hunter2").unwrap();
+    let marker = format!("/tmp/labforcode-isolation-{}", std::process::id());
+    
+    let source = format!(
+        r#"
+import os
+def attempt(action):
+    try:
+        action()
+        return "ok"
+    except OSError as err:
+        return type(err).__name__
+def write(path):
+    with open(path, "w") as f:
+        f.write("x")
+print("tmp", attempt(lambda: write("{marker}")))
+print("root", attempt(lambda: write("/x")))
+print("etc", attempt(lambda: write("/etc/labforcode")))
+print("sibling", attempt(lambda: open("{secret}").read()))
+print("workdir", attempt(lambda: write("out.txt")))
+"#,
+        marker = marker,
+        secret = sibling.path().join("secret.txt").display(),
+    );
+    let result = run(&engine, json!({"language": "python", "source_code": source})).await;
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.stderr);
+    
+    let lines: Vec<&str> = stdout(&result).lines().collect();
+    // The private /tmp takes the write, which never reaches the host's
+    assert_eq!(lines[0], "tmp ok");
+    assert!(!std::path::Path::new(&marker).exists());
+    assert_eq!(lines[1], "root OSError");
+    assert_eq!(lines[2], "etc OSError");
+    assert_eq!(lines[3], "sibling FileNotFoundError");
+    assert_eq!(lines[4], "workdir ok");
+}
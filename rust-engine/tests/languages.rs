@@ -0,0 +1,145 @@
+//! Compiling and running submissions in each language, end to end.
+mod common;
+
+use common::{engine, has_toolchain, java, run, stdout, zip};
+use labforcode_engine::types::{CommandPhase, DiagnosticSeverity, ExecutionState};
+use serde_json::json;
+
+#[tokio::test]
+async fn cpp_links_every_translation_unit() {
+    if !has_toolchain(&["g++"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "cpp",
+        "source_code": "#include <iostream>\n#include \"util.h\"\nint main() { std::cout << twice(21) << std::endl; }\n",
+        "additional_files": zip(&[
+            ("util.h", "int twice(int x);\n"),
+            ("util.cpp", "#include \"util.h\"\nint twice(int x) { return 2 * x; }\n"),
+        ]),
+    })).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.compile_output);
+    assert_eq!(stdout(&result), "42");
+}
+
+#[tokio::test]
+async fn java_compiles_every_class() {
+    if !has_toolchain(&["javac", "java"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, java(json!({
+        "language": "java",
+        "source_code": "public class Main { public static void main(String[] args) { System.out.println(Helper.greet(\"java\")); } }\n",
+        "additional_files": zip(&[
+            ("Helper.java", "public class Helper { static String greet(String name) { return \"hello \" + name; } }\n"),
+        ]),
+    }))).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.compile_output);
+    assert_eq!(stdout(&result), "hello java");
+}
+
+#[tokio::test]
+async fn source_file_name_overrides_the_entry_file() {
+    if !has_toolchain(&["python3", "javac", "java"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": "import os\nprint(os.path.basename(__file__))",
+        "source_file_name": "solution.py",
+    })).await;
+    assert_eq!(stdout(&result), "solution.py");
+    
+    // The public class of a Java entry file must match the name given
+    let result = run(&engine, java(json!({
+        "language": "java",
+        "source_code": "public class Solution { public static void main(String[] args) { System.out.println(\"named\"); } }",
+        "source_file_name": "Solution.java",
+    }))).await;
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.compile_output);
+    assert_eq!(stdout(&result), "named");
+}
+
+#[tokio::test]
+async fn java_entry_file_follows_the_public_class() {
+    if !has_toolchain(&["javac", "java"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, java(json!({
+        "language": "java",
+        "source_code": "public class Solution { public static void main(String[] args) { System.out.println(\"detected\"); } }",
+    }))).await;
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.compile_output);
+    assert_eq!(stdout(&result), "detected");
+    
+    // A public nested class leaves the file named Main
+    let result = run(&engine, java(json!({
+        "language": "java",
+        "source_code": "class Main {\n    public static class Inner {}\n    public static void main(String[] args) { System.out.println(\"nested\"); }\n}",
+    }))).await;
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.compile_output);
+    assert_eq!(stdout(&result), "nested");
+}
+
+#[tokio::test]
+async fn java_class_files_are_not_artifacts() {
+    if !has_toolchain(&["javac", "java"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, java(json!({
+        "language": "java",
+        "source_code": "import java.nio.file.*;\npublic class Main {\n    static class Helper {}\n    public static void main(String[] args) throws Exception { Files.writeString(Path.of(\"out.txt\"), \"done\"); }\n}\n",
+        "artifact_patterns": ["*"],
+    }))).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.compile_output);
+    let names: Vec<&str> = result.artifacts.iter().flatten().map(|artifact| artifact.name.as_str()).collect();
+    assert_eq!(names, ["out.txt"]);
+}
+
+#[tokio::test]
+async fn compile_errors_point_at_the_submitted_file() {
+    if !has_toolchain(&["gcc"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "c",
+        "source_code": "int main(void) {\n    return x;\n}\n",
+    })).await;
+    
+    assert_eq!(result.status, ExecutionState::CompilationError);
+    let error = result.diagnostics.iter().find(|diagnostic| diagnostic.severity == DiagnosticSeverity::Error).unwrap();
+    assert_eq!((error.file.as_str(), error.line), ("main.c", 2), "{:?}", result.diagnostics);
+}
+
+#[tokio::test]
+async fn executed_commands_show_the_arguments_but_not_the_environment() {
+    if !has_toolchain(&["gcc"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "c",
+        "source_code": "int main(int argc, char **argv) { return argc - 3; }",
+        "compiler_options": "-O2 -Wall",
+        "command_line_arguments": "alpha beta",
+        "env": {"API_TOKEN": "hunter2"},
+        "number_of_runs": 2,
+    })).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.compile_output);
+    let phases: Vec<CommandPhase> = result.executed_commands.iter().map(|command| command.phase).collect();
+    assert_eq!(phases, [CommandPhase::Compile, CommandPhase::Run, CommandPhase::Run]);
+    assert_eq!(result.executed_commands[0].argv, ["gcc", "-O2", "-Wall", "-o", "main", "main.c", "-std=c17", "-lm"]);
+    assert_eq!(result.executed_commands[1].argv, ["./main", "alpha", "beta"]);
+    assert!(result.executed_commands.iter().all(|command| command.exit_code == 0));
+    assert!(!serde_json::to_string(&*result).unwrap().contains("hunter2"));
+}
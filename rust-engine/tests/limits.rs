@@ -0,0 +1,185 @@
+//! Resource limits enforced on running programs.
+mod common;
+
+use common::{engine, has_toolchain, run};
+use labforcode_engine::types::ExecutionState;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn many_small_files_fill_the_workdir_quota() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": "import time\nfor i in range(200):\n    with open(f'f{i}', 'wb') as f:\n        f.write(b'x' * 1024 * 1024)\ntime.sleep(5)\n",
+        "workdir_quota": 64 * 1024 * 1024,
+        "max_file_size": 2 * 1024 * 1024,
+        "wall_time_limit": 10.0,
+    })).await;
+    
+    assert_eq!(result.status, ExecutionState::OutputLimitExceeded);
+    let reason = result.failure_reason.as_deref().unwrap_or_default();
+    assert!(reason.contains("working directory quota of 67108864 bytes"), "{}", reason);
+    assert!(reason.contains("disk usage sampling"), "{}", reason);
+}
+
+#[tokio::test]
+async fn compiler_output_does_not_count_against_the_workdir_quota() {
+    if !has_toolchain(&["g++"]) {
+        return;
+    }
+    let engine = engine().await;
+    // The binary alone is larger than the quota
+    let result = run(&engine, json!({
+        "language": "cpp",
+        "source_code": "#include <chrono>\n#include <thread>\nint main() { std::this_thread::sleep_for(std::chrono::seconds(1)); }\n",
+        "compiler_options": "-g",
+        "workdir_quota": 4096,
+    })).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.failure_reason);
+}
+
+#[tokio::test]
+async fn deep_recursion_in_c_is_a_stack_overflow() {
+    if !has_toolchain(&["gcc"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "c",
+        "source_code": "#include <stdio.h>\nint depth(int n) { volatile char frame[256]; frame[0] = n; return n ? depth(n - 1) + frame[0] : 0; }\nint main() { printf(\"%d\\n\", depth(100000000)); }\n",
+        "stack_limit": 8 * 1024 * 1024,
+    })).await;
+    
+    assert_eq!(result.status, ExecutionState::RuntimeError);
+    assert_eq!(result.signal.as_deref(), Some("SIGSEGV"));
+    let reason = result.failure_reason.as_deref().unwrap_or_default();
+    assert!(reason.starts_with("stack overflow"), "{}", reason);
+}
+
+#[tokio::test]
+async fn runtimes_are_matched_on_their_own_overflow_reports() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": "def depth(n):\n    return depth(n + 1)\ndepth(0)\n",
+    })).await;
+    assert_eq!(result.status, ExecutionState::RuntimeError);
+    let reason = result.failure_reason.as_deref().unwrap_or_default();
+    assert!(reason.starts_with("stack overflow"), "{}", reason);
+    
+    // V8's message means nothing coming from CPython
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": "import sys\nsys.exit('RangeError: Maximum call stack size exceeded')\n",
+    })).await;
+    assert_eq!(result.status, ExecutionState::RuntimeError);
+    assert_eq!(result.failure_reason, None);
+}
+
+#[tokio::test]
+async fn other_segfaults_and_lookalike_messages_are_plain_runtime_errors() {
+    if !has_toolchain(&["gcc"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "c",
+        "source_code": "int main() { volatile int *p = 0; return *p; }\n",
+    })).await;
+    assert_eq!(result.status, ExecutionState::RuntimeError);
+    assert_eq!(result.signal.as_deref(), Some("SIGSEGV"));
+    assert_eq!(result.failure_reason, None);
+    
+    // Only the runtime's own reports count, and C's runtime dies of SIGXFSZ rather than printing
+    let result = run(&engine, json!({
+        "language": "c",
+        "source_code": "#include <stdio.h>\nint main() { fputs(\"stack overflow\\nFile too large\\n\", stderr); return 1; }\n",
+    })).await;
+    assert_eq!(result.status, ExecutionState::RuntimeError);
+    assert_eq!(result.failure_reason, None);
+}
+
+#[tokio::test]
+async fn endless_writes_hit_a_tiny_file_limit() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    // CPython ignores SIGXFSZ, so the write fails with EFBIG instead
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": "with open('out.txt', 'w') as out:\n    while True:\n        out.write('y\\n')\n",
+        "max_file_size": 4096,
+    })).await;
+    assert_eq!(result.status, ExecutionState::OutputLimitExceeded);
+    let reason = result.failure_reason.as_deref().unwrap_or_default();
+    assert!(reason.contains("file size limit of 4096 bytes"), "{}", reason);
+    
+    // With the default action restored, the kernel kills it
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": "import signal\nsignal.signal(signal.SIGXFSZ, signal.SIG_DFL)\nwith open('out.txt', 'w') as out:\n    while True:\n        out.write('y\\n')\n",
+        "max_file_size": 4096,
+    })).await;
+    assert_eq!(result.status, ExecutionState::OutputLimitExceeded);
+    assert_eq!(result.signal.as_deref(), Some("SIGXFSZ"));
+}
+
+#[tokio::test]
+async fn escaped_grandchildren_end_with_the_wall_time() {
+    if !has_toolchain(&["python3", "sleep"]) {
+        return;
+    }
+    let engine = engine().await;
+    let started = Instant::now();
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": "import os\nif os.fork() == 0:\n    os.setsid()\n    os.execvp('sleep', ['sleep', '31.4159'])\nprint('parent done')",
+        "wall_time_limit": 1.0,
+    })).await;
+    assert!(started.elapsed() < Duration::from_secs(3));
+    assert_eq!(result.status, ExecutionState::TimeLimitExceeded);
+    
+    let sleeping = || std::fs::read_dir("/proc").unwrap().flatten()
+        .filter_map(|entry| std::fs::read(entry.path().join("cmdline")).ok())
+        .any(|cmdline| cmdline == b"sleep\x0031.4159\x00");
+    // SIGKILL takes a moment to land
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while sleeping() && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(!sleeping(), "the grandchild outlived its job");
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn programs_run_on_their_pinned_cores_at_their_nice_level() {
+    use labforcode_engine::types::CpuAffinity;
+    
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let source = "import os\nprint(sorted(os.sched_getaffinity(0)), os.nice(0))";
+    
+    // "auto" is resolved to a concrete core, which the result records
+    let auto = run(&engine, json!({"language": "python", "source_code": source, "cpu_affinity": "auto", "nice": 5})).await;
+    assert_eq!(auto.status, ExecutionState::Completed, "{:?}", auto.stderr);
+    let Some(CpuAffinity::Cores(cores)) = auto.limits.as_ref().unwrap().cpu_affinity.clone() else {
+        panic!("no cores recorded: {:?}", auto.limits);
+    };
+    assert_eq!(cores.len(), 1);
+    assert_eq!(auto.stdout.as_deref().unwrap().trim(), format!("[{}] 5", cores[0]));
+    assert_eq!(auto.limits.as_ref().unwrap().nice, Some(5));
+    
+    let pinned = run(&engine, json!({"language": "python", "source_code": source, "cpu_affinity": [cores[0]]})).await;
+    assert_eq!(pinned.stdout.as_deref().unwrap().trim(), format!("[{}] 0", cores[0]));
+}
@@ -0,0 +1,76 @@
+//! The engine under sustained load.
+mod common;
+
+use common::has_toolchain;
+use labforcode_engine::types::SubmissionContext;
+use labforcode_engine::ExecutionEngine;
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 99th percentile of the latencies, which it sorts
+fn p99(latencies: &mut [Duration]) -> Duration {
+    latencies.sort();
+    latencies[latencies.len() * 99 / 100]
+}
+
+/// Poll the status of `ids` round robin for `duration`, returning each poll's latency
+async fn poll(engine: &ExecutionEngine, ids: &[String], duration: Duration) -> Vec<Duration> {
+    let mut latencies = Vec::new();
+    let deadline = Instant::now() + duration;
+    for id in ids.iter().cycle() {
+        if Instant::now() >= deadline {
+            break;
+        }
+        let started = Instant::now();
+        engine.get_status(id).await.unwrap().unwrap();
+        latencies.push(started.elapsed());
+        tokio::task::yield_now().await;
+    }
+    latencies
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn status_polls_stay_fast_while_jobs_run() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = Arc::new(ExecutionEngine::builder().max_concurrent(4).build().await.unwrap());
+    let submit = |engine: Arc<ExecutionEngine>| async move {
+        // Each job leaves a sizable result in the job table
+        let request = serde_json::from_value(json!({"language": "python", "source_code": "print('x' * 100000)"})).unwrap();
+        engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id
+    };
+    let mut ids = Vec::new();
+    for _ in 0..20 {
+        ids.push(submit(Arc::clone(&engine)).await);
+    }
+    for id in &ids {
+        engine.wait_for_completion(id, Duration::from_secs(60)).await.unwrap();
+    }
+    let idle = p99(&mut poll(&engine, &ids, Duration::from_secs(1)).await);
+    
+    // Submit at more than 1000 jobs a minute while polling
+    let busy = Arc::new(AtomicBool::new(true));
+    let load = {
+        let (engine, busy) = (Arc::clone(&engine), Arc::clone(&busy));
+        tokio::spawn(async move {
+            let mut submitted = Vec::new();
+            while busy.load(Ordering::SeqCst) {
+                submitted.push(submit(Arc::clone(&engine)).await);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            submitted
+        })
+    };
+    let loaded = p99(&mut poll(&engine, &ids, Duration::from_secs(5)).await);
+    busy.store(false, Ordering::SeqCst);
+    let submitted = load.await.unwrap();
+    
+    let finished = engine.get_stats().await.unwrap().completed_executions as usize;
+    assert!(submitted.len() >= 80, "only {} jobs submitted", submitted.len());
+    assert!(finished > ids.len(), "no job finished under load");
+    eprintln!("status p99: {:?} idle, {:?} while {} jobs were submitted", idle, loaded, submitted.len());
+    assert!(loaded < idle * 10 + Duration::from_millis(5), "status p99 went from {:?} to {:?} under load", idle, loaded);
+}
@@ -0,0 +1,34 @@
+//! The kernel's accounting of a run, reported under `metrics`.
+mod common;
+
+use common::{engine, has_toolchain, run};
+use labforcode_engine::types::ExecutionState;
+use serde_json::json;
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn memory_churn_shows_up_as_page_faults() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    // Fresh 64MB buffers, each touched page by page, then a short sleep and some output
+    let source = "import sys, time\nfor _ in range(4):\n    b = bytearray(64 * 1024 * 1024)\n    for i in range(0, len(b), 4096):\n        b[i] = 1\n    del b\ntime.sleep(0.05)\nsys.stdout.write(sys.stdin.read() * 1000)\n";
+    let result = run(&engine, json!({"language": "python", "source_code": source, "stdin": "0123456789"})).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.stderr);
+    let metrics = result.metrics.as_ref().unwrap();
+    // One fault per 4KB page of 256MB, give or take what the allocator reuses
+    assert!(metrics.minor_page_faults.unwrap() > 10_000, "{:?}", metrics);
+    assert!(metrics.major_page_faults.is_some());
+    assert!(metrics.voluntary_context_switches.unwrap() > 0, "{:?}", metrics);
+    assert!(metrics.involuntary_context_switches.is_some());
+    assert!(metrics.user_time.unwrap() > 0.0 && metrics.system_time.is_some());
+    assert!(metrics.read_bytes.unwrap() >= 10, "{:?}", metrics);
+    assert!(metrics.written_bytes.unwrap() >= 10_000, "{:?}", metrics);
+    assert!(metrics.wall_time.unwrap() >= metrics.cpu_time.unwrap() * 0.5);
+    
+    // The same object in the JSON the API serves
+    let body = serde_json::to_value(&*result).unwrap();
+    assert!(body["metrics"]["minor_page_faults"].as_u64().unwrap() > 10_000);
+}
@@ -0,0 +1,56 @@
+//! Jobs kept in the SQLite store across engine restarts.
+mod common;
+
+use common::{has_toolchain, run, stdout};
+use labforcode_engine::config::EngineConfig;
+use labforcode_engine::types::{ExecutionState, SubmissionContext};
+use labforcode_engine::ExecutionEngine;
+use serde_json::json;
+use std::path::Path;
+use std::time::Duration;
+
+/// Single-worker engine persisting to the given SQLite file
+async fn engine_on(path: &Path) -> ExecutionEngine {
+    let mut config = EngineConfig::default();
+    config.retention.job_store_path = Some(path.to_path_buf());
+    ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap()
+}
+
+#[tokio::test]
+async fn results_are_served_after_a_restart() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("jobs.db");
+    
+    let engine = engine_on(&path).await;
+    let id = run(&engine, json!({"language": "python", "source_code": "print('persisted')"})).await.id.clone();
+    drop(engine);
+    
+    let engine = engine_on(&path).await;
+    let result = engine.get_result(&id).await.unwrap().expect("result loaded from the store");
+    assert_eq!(result.status, ExecutionState::Completed);
+    assert_eq!(stdout(&result), "persisted");
+}
+
+#[tokio::test]
+async fn queued_jobs_run_after_a_restart() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("jobs.db");
+    
+    // Stopped before any worker took the job
+    let engine = engine_on(&path).await;
+    engine.pause();
+    let request = serde_json::from_value(json!({"language": "python", "source_code": "print('recovered')"})).unwrap();
+    let id = engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id;
+    drop(engine);
+    
+    let engine = engine_on(&path).await;
+    engine.wait_for_completion(&id, Duration::from_secs(60)).await.unwrap();
+    let result = engine.get_result(&id).await.unwrap().unwrap();
+    assert_eq!(stdout(&result), "recovered");
+}
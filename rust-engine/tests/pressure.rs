@@ -0,0 +1,63 @@
+//! How jobs and the engine fare when the host runs short of memory.
+mod common;
+
+use common::{has_toolchain, run, stdout};
+use labforcode_engine::pressure::MemoryReader;
+use labforcode_engine::types::ExecutionState;
+use labforcode_engine::{EngineConfig, ExecutionEngine};
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const MB: u64 = 1024 * 1024;
+
+/// Available memory as the test sets it
+struct FakeMemory(AtomicU64);
+
+impl MemoryReader for FakeMemory {
+    fn available_bytes(&self) -> Option<u64> {
+        Some(self.0.load(Ordering::SeqCst))
+    }
+}
+
+#[tokio::test]
+async fn jobs_wait_out_memory_pressure() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let memory = Arc::new(FakeMemory(AtomicU64::new(16 * MB)));
+    let config = EngineConfig { memory_floor_bytes: 128 * MB, ..EngineConfig::default() };
+    let engine = ExecutionEngine::builder().config(config).memory_reader(memory.clone()).max_concurrent(1).build().await.unwrap();
+    
+    let request = serde_json::from_value(json!({"language": "python", "source_code": "print('ran')"})).unwrap();
+    let id = engine.submit_execution(request, Default::default()).await.unwrap().id;
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    
+    assert_eq!(engine.get_status(&id).await.unwrap().unwrap().status, ExecutionState::Queued);
+    let stats = engine.get_stats().await.unwrap();
+    assert!(stats.memory_pressure);
+    assert!(stats.deferred_dequeues >= 1, "{}", stats.deferred_dequeues);
+    
+    // Memory coming back lets the job through on a later sample
+    memory.0.store(1024 * MB, Ordering::SeqCst);
+    engine.wait_for_completion(&id, Duration::from_secs(30)).await.unwrap();
+    let result = engine.get_result(&id).await.unwrap().unwrap();
+    assert_eq!(stdout(&result), "ran");
+    assert!(!engine.get_stats().await.unwrap().memory_pressure);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn programs_are_the_oom_killers_first_choice() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = common::engine().await;
+    let result = run(&engine, json!({"language": "python", "source_code": "print(open('/proc/self/oom_score_adj').read().strip())"})).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.stderr);
+    assert_eq!(stdout(&result), "900");
+    let engine_adj: i32 = std::fs::read_to_string("/proc/self/oom_score_adj").unwrap().trim().parse().unwrap();
+    assert!(engine_adj < 900);
+}
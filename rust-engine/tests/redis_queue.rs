@@ -0,0 +1,99 @@
+//! The Redis queue against a real server; set REDIS_URL to run these.
+//!
+//! They clear the queue's keys, so point REDIS_URL at a scratch database.
+use labforcode_engine::config::{QueueBackend, QueueConfig};
+use labforcode_engine::queue::{self, JobQueue};
+use labforcode_engine::types::ExecutionJob;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Queue with a one second visibility timeout, or None without REDIS_URL
+async fn redis_queue() -> Option<Arc<dyn JobQueue>> {
+    let Ok(redis_url) = std::env::var("REDIS_URL") else {
+        eprintln!("skipped: needs REDIS_URL");
+        return None;
+    };
+    let config = QueueConfig { backend: QueueBackend::Redis, redis_url, visibility_timeout_secs: 1 };
+    let queue = queue::from_config(&config).await.unwrap();
+    queue.clear().await.unwrap();
+    Some(queue)
+}
+
+fn job(id: &str) -> ExecutionJob {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "request": { "id": id, "language": "python", "source_code": "print(1)" },
+        "status": "queued",
+        "created_at": "2030-01-01T00:00:00Z",
+        "started_at": null,
+        "finished_at": null,
+        "result": null,
+    })).unwrap()
+}
+
+#[tokio::test]
+async fn unacknowledged_jobs_are_delivered_again() {
+    let Some(queue) = redis_queue().await else {
+        return;
+    };
+    queue.enqueue(job("crashed")).await.unwrap();
+    
+    // The worker takes the job and dies without acknowledging it
+    assert_eq!(queue.dequeue().await.unwrap().unwrap().id, "crashed");
+    assert!(queue.dequeue().await.unwrap().is_none(), "still within its visibility timeout");
+    
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let redelivered = queue.dequeue().await.unwrap().expect("re-delivered after the timeout");
+    assert_eq!(redelivered.id, "crashed");
+    assert_eq!(redelivered.request.source_code, "print(1)");
+    
+    // Acknowledged, it is gone for good
+    queue.ack("crashed").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert!(queue.dequeue().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn jobs_come_out_in_priority_order() {
+    let Some(queue) = redis_queue().await else {
+        return;
+    };
+    let mut urgent = job("urgent");
+    urgent.request.priority = Some(9);
+    queue.enqueue(job("normal")).await.unwrap();
+    queue.enqueue(urgent).await.unwrap();
+    assert_eq!(queue.size().await.unwrap(), 2);
+    
+    for expected in ["urgent", "normal"] {
+        let taken = queue.dequeue().await.unwrap().unwrap();
+        assert_eq!(taken.id, expected);
+        queue.ack(&taken.id).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn promoted_jobs_jump_the_queue_and_removed_ones_leave_it() {
+    let Some(queue) = redis_queue().await else {
+        return;
+    };
+    for id in ["first", "second", "third"] {
+        queue.enqueue(job(id)).await.unwrap();
+    }
+    assert!(queue.promote("third").await.unwrap());
+    assert!(queue.remove("first").await.unwrap());
+    assert!(!queue.remove("first").await.unwrap());
+    
+    let listed: Vec<(String, bool)> = queue.snapshot().await.unwrap()
+        .into_iter()
+        .map(|entry| (entry.job.id, entry.promoted))
+        .collect();
+    assert_eq!(listed, [("third".to_string(), true), ("second".to_string(), false)]);
+    
+    for expected in ["third", "second"] {
+        let taken = queue.dequeue().await.unwrap().unwrap();
+        assert_eq!(taken.id, expected);
+        queue.ack(&taken.id).await.unwrap();
+    }
+    assert!(queue.dequeue().await.unwrap().is_none());
+}
+
@@ -0,0 +1,30 @@
+//! Engine statistics gathered from jobs that really ran.
+mod common;
+
+use common::{engine, has_toolchain, run};
+use labforcode_engine::types::ExecutionState;
+use serde_json::json;
+
+#[tokio::test]
+async fn finished_jobs_are_counted_under_their_language() {
+    if !has_toolchain(&["python3", "gcc"]) {
+        return;
+    }
+    let engine = engine().await;
+    for request in [
+        json!({"language": "python", "source_code": "print(1)"}),
+        json!({"language": "python", "source_code": "x = bytearray(8 * 1024 * 1024)\nprint(len(x))"}),
+        json!({"language": "c", "source_code": "int main(void) { return 0; }"}),
+    ] {
+        assert_eq!(run(&engine, request).await.status, ExecutionState::Completed);
+    }
+    
+    let stats = engine.get_stats().await.unwrap();
+    let python = &stats.by_language["python"];
+    assert_eq!((python.submissions, python.completed, python.failed), (2, 2, 0));
+    assert!(python.average_execution_time > 0.0);
+    assert!(python.average_memory > 0.0);
+    let c = &stats.by_language["c"];
+    assert_eq!((c.submissions, c.completed, c.failed), (1, 1, 0));
+    assert_eq!(stats.by_language.len(), 2);
+}
@@ -0,0 +1,128 @@
+//! How program output is captured.
+mod common;
+
+use common::{engine, has_toolchain, run, stdout};
+use labforcode_engine::executor::ExecutionBackend;
+use labforcode_engine::types::{ExecutionState, OutputEncoding};
+use labforcode_engine::{EngineConfig, ExecutionEngine};
+use serde_json::json;
+use std::process::{Command, Stdio};
+
+/// Writes to stdout and stderr in turn, flushing after each
+const INTERLEAVED: &str = "import sys\nfor stream, text in [(sys.stdout, 'A'), (sys.stderr, 'B'), (sys.stdout, 'C'), (sys.stderr, 'D')]:\n    stream.write(text + '\\n')\n    stream.flush()\n";
+
+fn interleaved() -> serde_json::Value {
+    json!({"language": "python", "source_code": INTERLEAVED, "redirect_stderr_to_stdout": true})
+}
+
+#[tokio::test]
+async fn merged_streams_keep_their_order() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, interleaved()).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed);
+    assert_eq!(stdout(&result), "A\nB\nC\nD");
+    assert_eq!(result.stderr, None);
+}
+
+#[tokio::test]
+async fn merged_streams_keep_their_order_in_a_container() {
+    let daemon = Command::new("docker").arg("info").stdout(Stdio::null()).stderr(Stdio::null()).status();
+    if !daemon.is_ok_and(|status| status.success()) {
+        eprintln!("skipped: needs a running docker daemon");
+        return;
+    }
+    let mut config = EngineConfig::default();
+    config.sandbox.backend = ExecutionBackend::Docker;
+    let engine = ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap();
+    let result = run(&engine, interleaved()).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.failure_reason);
+    assert_eq!(stdout(&result), "A\nB\nC\nD");
+    assert_eq!(result.stderr, None);
+}
+
+#[tokio::test]
+async fn capped_output_stays_valid_text() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let mut config = EngineConfig::default();
+    config.sandbox.output_max_bytes = 10;
+    let engine = ExecutionEngine::builder().config(config).max_concurrent(1).build().await.unwrap();
+    // Ten bytes end inside the fourth euro sign
+    let result = run(&engine, json!({"language": "python", "source_code": "print('€' * 1000)"})).await;
+    
+    assert_eq!(result.stdout.as_deref(), Some("€€€"));
+    assert!(result.stdout_truncated);
+    assert_eq!(result.stdout_encoding, Some(OutputEncoding::Utf8));
+}
+
+#[tokio::test]
+async fn binary_output_is_flagged() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({"language": "python", "source_code": "import sys\nsys.stdout.buffer.write(b'\\xff\\xfe')"})).await;
+    
+    assert_eq!(result.stdout_encoding, Some(OutputEncoding::Binary));
+    assert!(!result.stdout_truncated);
+}
+
+#[tokio::test]
+async fn base64_encoded_output_keeps_every_byte() {
+    use base64::{engine::general_purpose, Engine as _};
+    
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    // Echoes its binary stdin, then a 0xFF byte and UTF-16 text, which are not UTF-8
+    let source = "import sys\nout = sys.stdout.buffer\nout.write(sys.stdin.buffer.read())\nout.write(b'\\xff' + 'h\\u00e9 \\u2603'.encode('utf-16'))\n";
+    let engine = engine().await;
+    // Text fields are decoded by the API that received them; the engine decodes stdin itself
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": source,
+        "stdin": general_purpose::STANDARD.encode([0x00, 0xff, 0xfe]),
+        "base64_encoded": true,
+    })).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.stderr);
+    let mut expected = vec![0x00, 0xff, 0xfe, 0xff];
+    expected.extend([0xff, 0xfe, b'h', 0, 0xe9, 0, b' ', 0, 0x03, 0x26]);
+    assert_eq!(general_purpose::STANDARD.decode(result.stdout.as_deref().unwrap()).unwrap(), expected);
+    
+    // Without base64_encoded the same bytes come back lossily as text
+    let result = run(&engine, json!({"language": "python", "source_code": source})).await;
+    assert!(result.stdout.as_deref().unwrap().contains('\u{fffd}'));
+}
+
+#[tokio::test]
+async fn filtered_output_drops_escapes_unless_base64_encoded() {
+    use base64::{engine::general_purpose, Engine as _};
+    
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let source = "import sys\nprint('\\x1b[1;32mok\\x1b[0m\\x00\\r')\nsys.stderr.write('\\x1b[31mfail\\x1b[0m\\n')\n";
+    let request = |extra: serde_json::Value| {
+        let mut request = json!({"language": "python", "source_code": source, "output_filter": "strip_control", "normalize_newlines": true});
+        request.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        request
+    };
+    
+    let filtered = run(&engine, request(json!({}))).await;
+    assert_eq!(filtered.stdout.as_deref(), Some("ok\n"));
+    assert_eq!(filtered.stderr.as_deref(), Some("fail\n"));
+    
+    // base64 is for the raw bytes, so neither option applies to it
+    let raw = run(&engine, request(json!({"base64_encoded": true}))).await;
+    let stdout = general_purpose::STANDARD.decode(raw.stdout.as_deref().unwrap()).unwrap();
+    assert_eq!(stdout, b"\x1b[1;32mok\x1b[0m\x00\r\n");
+}
+
@@ -0,0 +1,109 @@
+//! Test groups: one build of a submission run against many cases.
+mod common;
+
+use common::{engine, has_toolchain, run};
+use labforcode_engine::types::{CommandPhase, ExecutionState, SubmissionContext};
+use labforcode_engine::ExecutionEngine;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+/// Doubles its input, and complains when a previous case left a file behind
+const DOUBLER: &str = r#"
+#include <stdio.h>
+int main(void) {
+    FILE *marker = fopen("marker", "r");
+    if (marker) {
+        puts("dirty");
+        return 0;
+    }
+    fclose(fopen("marker", "w"));
+    int n;
+    scanf("%d", &n);
+    printf("%d\n", 2 * n);
+    return 0;
+}
+"#;
+
+#[tokio::test]
+async fn groups_compile_once_and_run_each_case_in_a_clean_copy() {
+    if !has_toolchain(&["gcc"]) {
+        return;
+    }
+    let engine = engine().await;
+    let cases: Vec<_> = (1..=5).map(|n| json!({"stdin": n.to_string(), "expected_output": format!("{}\n", 2 * n)})).collect();
+    let result = run(&engine, json!({"language": "c", "source_code": DOUBLER, "test_cases": cases})).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result);
+    let stdouts: Vec<_> = result.test_cases.iter().map(|case| case.stdout.as_deref().unwrap_or_default().trim()).collect();
+    assert_eq!(stdouts, ["2", "4", "6", "8", "10"]);
+    let compiles = result.executed_commands.iter().filter(|command| command.phase == CommandPhase::Compile).count();
+    let runs = result.executed_commands.iter().filter(|command| command.phase == CommandPhase::Run).count();
+    assert_eq!((compiles, runs), (1, 5));
+    
+    let summary = result.test_summary.as_ref().unwrap();
+    assert_eq!((summary.passed, summary.failed, summary.skipped, summary.first_failure), (5, 0, 0, None));
+}
+
+#[tokio::test]
+async fn the_first_failing_case_decides_the_verdict() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "python",
+        "source_code": "n = int(input())\nassert n != 4\nprint(n + 1 if n == 2 else 2 * n)",
+        "test_cases": [
+            {"stdin": "1", "expected_output": "2"},
+            {"stdin": "2", "expected_output": "4"},
+            {"stdin": "3", "expected_output": "6"},
+            {"stdin": "4", "expected_output": "8"},
+        ],
+    })).await;
+    
+    let statuses: Vec<_> = result.test_cases.iter().map(|case| case.status.clone()).collect();
+    assert_eq!(statuses, [ExecutionState::Completed, ExecutionState::WrongAnswer, ExecutionState::Completed, ExecutionState::RuntimeError]);
+    assert_eq!((result.status.clone(), result.stdout.as_deref()), (ExecutionState::WrongAnswer, Some("3\n")));
+    let summary = result.test_summary.as_ref().unwrap();
+    assert_eq!((summary.passed, summary.failed, summary.skipped, summary.first_failure), (2, 2, 0, Some(1)));
+    
+    // Time adds up over the cases and memory is the peak
+    let time: f64 = result.test_cases.iter().filter_map(|case| case.time).sum();
+    assert!((result.time.unwrap() - time).abs() < 1e-6);
+    assert_eq!(result.memory, result.test_cases.iter().filter_map(|case| case.memory).max());
+}
+
+#[tokio::test]
+async fn cancelling_a_group_skips_the_remaining_cases() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = ExecutionEngine::builder().max_concurrent(1).build().await.unwrap();
+    let cases = vec![json!({"stdin": "", "expected_output": "ok"}); 10];
+    let request = serde_json::from_value(json!({
+        "language": "python",
+        "source_code": "import time\ntime.sleep(0.5)\nprint('ok')",
+        "test_cases": cases,
+    })).unwrap();
+    let id = engine.submit_execution(request, SubmissionContext::default()).await.unwrap().id;
+    
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while engine.get_status(&id).await.unwrap().unwrap().status != ExecutionState::Running {
+        assert!(Instant::now() < deadline, "the job never started");
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    assert!(engine.cancel_execution(&id).await.unwrap());
+    
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while engine.lookup(&id).await.unwrap().unwrap().result.is_none() {
+        assert!(Instant::now() < deadline, "the cancelled group kept no result");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let result = engine.get_result(&id).await.unwrap().unwrap();
+    assert_eq!(result.status, ExecutionState::Cancelled);
+    let summary = result.test_summary.as_ref().unwrap();
+    assert!((2..=3).contains(&summary.passed), "{:?}", summary);
+    assert_eq!(summary.passed + summary.skipped, 10);
+    assert_eq!(result.test_cases.len() as u32, summary.passed);
+}
@@ -0,0 +1,61 @@
+//! When jobs start and where their time goes, as reported in results.
+mod common;
+
+use common::{engine, has_toolchain, run};
+use labforcode_engine::types::{CommandPhase, ExecutionState};
+use serde_json::json;
+
+#[tokio::test]
+async fn started_at_is_when_the_compiler_was_spawned() {
+    if !has_toolchain(&["g++"]) {
+        return;
+    }
+    let engine = engine().await;
+    let result = run(&engine, json!({
+        "language": "cpp",
+        "source_code": "#include <chrono>\n#include <thread>\nint main() { std::this_thread::sleep_for(std::chrono::milliseconds(200)); }\n",
+    })).await;
+    assert_eq!(result.status, ExecutionState::Completed, "{:?}", result.compile_output);
+    
+    let durations: u64 = result.executed_commands.iter().map(|command| command.duration_ms).sum();
+    assert_eq!(result.executed_commands[0].phase, CommandPhase::Compile);
+    let elapsed = (result.finished_at.unwrap() - result.started_at.unwrap()).num_milliseconds() as u64;
+    // A few milliseconds of slack for the rounding of each duration
+    assert!(elapsed + 10 >= durations, "{}ms from start to finish, commands took {}ms", elapsed, durations);
+}
+
+/// Most milliseconds of overhead a trivial job may cost on the native backend
+const OVERHEAD_BUDGET_MS: f64 = 500.0;
+
+#[tokio::test]
+async fn trivial_jobs_stay_within_the_overhead_budget() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    for _ in 0..5 {
+        let result = run(&engine, json!({"language": "python", "source_code": "print(1)"})).await;
+        let phases = result.phases.as_ref().unwrap();
+        assert!(phases.run_ms > 0.0 && phases.compile_ms == 0.0, "{:?}", phases);
+        assert_eq!(result.setup_bytes, Some(8));
+        assert!(phases.setup_ms + phases.teardown_ms < OVERHEAD_BUDGET_MS, "{:?}", phases);
+    }
+    
+    let stats = engine.get_stats().await.unwrap();
+    assert!(stats.overhead_ms_p50 <= stats.overhead_ms_p95, "{} > {}", stats.overhead_ms_p50, stats.overhead_ms_p95);
+    assert!(stats.overhead_ms_p95 < OVERHEAD_BUDGET_MS);
+}
+
+#[tokio::test]
+async fn scheduled_jobs_start_no_earlier_than_run_at() {
+    if !has_toolchain(&["python3"]) {
+        return;
+    }
+    let engine = engine().await;
+    let run_at = chrono::Utc::now() + chrono::Duration::seconds(2);
+    let result = run(&engine, json!({"language": "python", "source_code": "print(1)", "run_at": run_at.to_rfc3339()})).await;
+    
+    assert_eq!(result.status, ExecutionState::Completed);
+    let started_at = result.started_at.expect("started");
+    assert!(started_at >= run_at, "started at {} before {}", started_at, run_at);
+}